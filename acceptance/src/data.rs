@@ -1,8 +1,9 @@
-use std::{path::Path, sync::Arc};
+use std::{collections::HashMap, path::Path, sync::Arc};
 
 use arrow_array::RecordBatch;
+use arrow_cast::display::{ArrayFormatter, FormatOptions};
 use arrow_ord::sort::{lexsort_to_indices, SortColumn};
-use arrow_schema::{DataType, Schema};
+use arrow_schema::{DataType, Field, Schema};
 use arrow_select::{concat::concat_batches, filter::filter_record_batch, take::take};
 
 use delta_kernel::{
@@ -12,7 +13,7 @@ use futures::{stream::TryStreamExt, StreamExt};
 use object_store::{local::LocalFileSystem, ObjectStore};
 use parquet::arrow::async_reader::{ParquetObjectReader, ParquetRecordBatchStreamBuilder};
 
-use crate::{TestCaseInfo, TestResult};
+use crate::{AssertionError, TestCaseInfo, TestResult};
 
 pub async fn read_golden(path: &Path, _version: Option<&str>) -> DeltaResult<Option<RecordBatch>> {
     let expected_root = path.join("expected").join("latest").join("table_content");
@@ -62,9 +63,38 @@ pub fn sort_record_batch(batch: RecordBatch) -> DeltaResult<RecordBatch> {
     Ok(RecordBatch::try_new(batch.schema(), columns)?)
 }
 
-static SKIPPED_TESTS: &[&str; 2] = &[
-    // iceberg compat requires column mapping
-    "iceberg_compat_v1",
+/// Whether `batch` has struct, list or map columns, which [`sort_record_batch`] can't sort by
+fn has_nested_columns(batch: &RecordBatch) -> bool {
+    batch.schema().fields().iter().any(|field| {
+        matches!(
+            field.data_type(),
+            DataType::Struct(_) | DataType::List(_) | DataType::Map(_, _)
+        )
+    })
+}
+
+/// Count how often each row occurs in `batch`, where a row is the display strings of its values
+/// (including the values of nested columns). This compares batches as multisets of rows, so their
+/// rows can be in any order.
+fn row_counts(batch: &RecordBatch) -> DeltaResult<HashMap<Vec<String>, usize>> {
+    let options = FormatOptions::default().with_null("NULL");
+    let formatters: Vec<_> = batch
+        .columns()
+        .iter()
+        .map(|column| ArrayFormatter::try_new(column.as_ref(), &options))
+        .collect::<Result<_, _>>()?;
+    let mut counts = HashMap::new();
+    for row in 0..batch.num_rows() {
+        let values = formatters
+            .iter()
+            .map(|formatter| formatter.value(row).to_string())
+            .collect();
+        *counts.entry(values).or_insert(0) += 1;
+    }
+    Ok(counts)
+}
+
+static SKIPPED_TESTS: &[&str; 1] = &[
     // For multi_partitioned_2: The golden table stores the timestamp as an INT96 (which is
     // nanosecond precision), while the spec says we should read partition columns as
     // microseconds. This means the read and golden data don't line up. When this is released in
@@ -72,29 +102,44 @@ static SKIPPED_TESTS: &[&str; 2] = &[
     "multi_partitioned_2",
 ];
 
-// Ensure that two schema have the same field names, data types, and dict_id/ordering.
-// We ignore:
+// Ensure that two schema have the same number of fields, and that the fields have the same
+// names, data types, and dict_id/ordering. On a mismatch the error names the first field that
+// differs. We ignore:
 //  - nullability: parquet marks many things as nullable that we don't in our schema
 //  - metadata: because that diverges from the real data to the golden tabled data
-fn assert_schema_fields_match(schema: &Schema, golden: &Schema) {
-    for (schema_field, golden_field) in schema.fields.iter().zip(golden.fields.iter()) {
-        assert!(
-            schema_field.name() == golden_field.name(),
-            "Field names don't match"
-        );
-        assert!(
-            schema_field.data_type() == golden_field.data_type(),
-            "Field data types don't match"
-        );
-        assert!(
-            schema_field.dict_id() == golden_field.dict_id(),
-            "Field dict_id doesn't match"
-        );
-        assert!(
-            schema_field.dict_is_ordered() == golden_field.dict_is_ordered(),
-            "Field dict_is_ordered doesn't match"
-        );
+fn assert_schema_fields_match(schema: &Schema, golden: &Schema) -> TestResult<()> {
+    if schema.fields.len() != golden.fields.len() {
+        return Err(AssertionError::FieldCountMismatch {
+            expected: golden.fields.len(),
+            actual: schema.fields.len(),
+        });
     }
+    let describe = |field: &Field| {
+        format!(
+            "{:?} of type {} (dict_id {:?}, dict_is_ordered {:?})",
+            field.name(),
+            field.data_type(),
+            field.dict_id(),
+            field.dict_is_ordered()
+        )
+    };
+    for (index, (schema_field, golden_field)) in
+        schema.fields.iter().zip(golden.fields.iter()).enumerate()
+    {
+        let matches = schema_field.name() == golden_field.name()
+            && schema_field.data_type() == golden_field.data_type()
+            && schema_field.dict_id() == golden_field.dict_id()
+            && schema_field.dict_is_ordered() == golden_field.dict_is_ordered();
+        if !matches {
+            return Err(AssertionError::FieldMismatch {
+                index,
+                name: golden_field.name().clone(),
+                expected: describe(golden_field),
+                actual: describe(schema_field),
+            });
+        }
+    }
+    Ok(())
 }
 
 pub async fn assert_scan_data(engine: Arc<dyn Engine>, test_case: &TestCaseInfo) -> TestResult<()> {
@@ -132,17 +177,26 @@ pub async fn assert_scan_data(engine: Arc<dyn Engine>, test_case: &TestCaseInfo)
         })
         .collect();
     let all_data = concat_batches(&schema.unwrap(), batches.iter()).map_err(Error::from)?;
-    let all_data = sort_record_batch(all_data)?;
     let golden = read_golden(test_case.root_dir(), None)
         .await?
         .expect("Didn't find golden data");
-    let golden = sort_record_batch(golden)?;
 
-    assert!(
-        all_data.columns() == golden.columns(),
-        "Read data does not equal golden data"
-    );
-    assert_schema_fields_match(all_data.schema().as_ref(), golden.schema().as_ref());
+    if has_nested_columns(&all_data) || has_nested_columns(&golden) {
+        // sorting skips nested columns, so rows that only differ in those could end up in a
+        // different order. Compare the rows regardless of their order instead.
+        assert!(
+            row_counts(&all_data)? == row_counts(&golden)?,
+            "Read data does not equal golden data"
+        );
+    } else {
+        let all_data = sort_record_batch(all_data.clone())?;
+        let golden = sort_record_batch(golden.clone())?;
+        assert!(
+            all_data.columns() == golden.columns(),
+            "Read data does not equal golden data"
+        );
+    }
+    assert_schema_fields_match(all_data.schema().as_ref(), golden.schema().as_ref())?;
     assert!(
         all_data.num_rows() == golden.num_rows(),
         "Didn't have same number of rows"
@@ -150,3 +204,68 @@ pub async fn assert_scan_data(engine: Arc<dyn Engine>, test_case: &TestCaseInfo)
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_row_counts() {
+        use arrow_array::{ArrayRef, Int64Array, StructArray};
+
+        let batch = |ids: Vec<i64>, values: Vec<i64>| {
+            let ids = Arc::new(Int64Array::from(ids)) as ArrayRef;
+            let values = Arc::new(Int64Array::from(values)) as ArrayRef;
+            let nested = StructArray::from(vec![(
+                Arc::new(Field::new("value", DataType::Int64, true)),
+                values,
+            )]);
+            RecordBatch::try_from_iter([("id", ids), ("nested", Arc::new(nested) as ArrayRef)])
+                .unwrap()
+        };
+        let read = batch(vec![1, 1, 2], vec![10, 20, 30]);
+        assert!(has_nested_columns(&read));
+        // sorting by the id alone can't order the rows with the same id
+        let golden = batch(vec![1, 1, 2], vec![20, 10, 30]);
+        assert_eq!(row_counts(&read).unwrap(), row_counts(&golden).unwrap());
+        let different = batch(vec![1, 1, 2], vec![10, 10, 30]);
+        assert_ne!(row_counts(&read).unwrap(), row_counts(&different).unwrap());
+    }
+
+    #[test]
+    fn test_assert_schema_fields_match() {
+        let schema = |fields: Vec<Field>| Schema::new(fields);
+        let id = Field::new("id", DataType::Int64, true);
+        let name = Field::new("name", DataType::Utf8, true);
+        let golden = schema(vec![id.clone(), name.clone()]);
+
+        // nullability is ignored
+        let not_null = schema(vec![id.clone().with_nullable(false), name.clone()]);
+        assert!(assert_schema_fields_match(&not_null, &golden).is_ok());
+
+        let int_id = schema(vec![Field::new("id", DataType::Int32, true), name.clone()]);
+        match assert_schema_fields_match(&int_id, &golden) {
+            Err(AssertionError::FieldMismatch {
+                index,
+                name,
+                expected,
+                actual,
+            }) => {
+                assert_eq!((index, name.as_str()), (0, "id"));
+                assert!(expected.contains("Int64"), "{expected}");
+                assert!(actual.contains("Int32"), "{actual}");
+            }
+            result => panic!("Unexpected result {result:?}"),
+        }
+
+        // a missing field is reported, rather than only comparing the fields both schemas have
+        let missing = schema(vec![id]);
+        assert!(matches!(
+            assert_schema_fields_match(&missing, &golden),
+            Err(AssertionError::FieldCountMismatch {
+                expected: 2,
+                actual: 1
+            })
+        ));
+    }
+}
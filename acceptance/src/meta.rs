@@ -18,6 +18,17 @@ pub enum AssertionError {
 
     #[error("Kernel error: {0}")]
     KernelError(#[from] Error),
+
+    #[error("Schemas have different numbers of fields: expected {expected}, found {actual}")]
+    FieldCountMismatch { expected: usize, actual: usize },
+
+    #[error("Field {index} ({name}) doesn't match: expected {expected}, found {actual}")]
+    FieldMismatch {
+        index: usize,
+        name: String,
+        expected: String,
+        actual: String,
+    },
 }
 
 pub type TestResult<T, E = AssertionError> = std::result::Result<T, E>;
@@ -3,7 +3,17 @@
 /// Since each new `.rs` file in this directory results in increased build and link time, it is
 /// important to only add new files if absolutely necessary for code readability or test
 /// performance.
+use std::sync::Arc;
+
+use arrow_array::{cast::AsArray, types::Int64Type, Int64Array, RecordBatch};
+use delta_kernel::engine::arrow_data::ArrowEngineData;
+use delta_kernel::engine::default::executor::tokio::TokioBackgroundExecutor;
+use delta_kernel::engine::default::DefaultEngine;
+use delta_kernel::scan::ScanBuilder;
 use delta_kernel::snapshot::CheckpointMetadata;
+use delta_kernel::Table;
+use parquet::arrow::ArrowWriter;
+use url::Url;
 
 #[test]
 fn test_checkpoint_serde() {
@@ -15,6 +25,108 @@ fn test_checkpoint_serde() {
     assert_eq!(cp.version, 2)
 }
 
+/// Create a shallow clone of the table at `parent` in `clone`: its first commit clones the parent's
+/// only file by absolute path, and its second commit adds a file of the clone itself (with values
+/// 10..13) by relative path.
+fn create_shallow_clone(parent: &Url, clone: &std::path::Path) {
+    let parent_file = "part-00000-517f5d32-9c95-48e8-82b4-0229cc194867-c000.snappy.parquet";
+    let schema_string = serde_json::json!({
+        "type": "struct",
+        "fields": [{"name": "value", "type": "long", "nullable": true, "metadata": {}}]
+    })
+    .to_string();
+    let clone_commit = [
+        serde_json::json!({"commitInfo": {
+            "timestamp": 1678020190000i64,
+            "operation": "CLONE",
+            "operationParameters": {"source": parent.as_str(), "sourceVersion": 0, "isShallow": true}
+        }}),
+        serde_json::json!({"protocol": {"minReaderVersion": 1, "minWriterVersion": 2}}),
+        serde_json::json!({"metaData": {
+            "id": "9f43a4c8-a7f3-4a9b-9d5c-8b0b1c5e1c1e",
+            "format": {"provider": "parquet", "options": {}},
+            "schemaString": schema_string,
+            "partitionColumns": [],
+            "configuration": {},
+            "createdTime": 1678020190000i64
+        }}),
+        serde_json::json!({"add": {
+            "path": parent.join(parent_file).unwrap().as_str(),
+            "partitionValues": {},
+            "size": 548,
+            "modificationTime": 1678020185157i64,
+            "dataChange": true
+        }}),
+    ];
+
+    // the clone's own data file
+    let batch =
+        RecordBatch::try_from_iter([("value", Arc::new(Int64Array::from(vec![10, 11, 12])) as _)])
+            .unwrap();
+    let mut data = vec![];
+    let mut writer = ArrowWriter::try_new(&mut data, batch.schema(), None).unwrap();
+    writer.write(&batch).unwrap();
+    writer.close().unwrap();
+    std::fs::write(clone.join("part-00000-clone.parquet"), &data).unwrap();
+    let append_commit = [serde_json::json!({"add": {
+        "path": "part-00000-clone.parquet",
+        "partitionValues": {},
+        "size": data.len(),
+        "modificationTime": 1678020195000i64,
+        "dataChange": true
+    }})];
+
+    let log_dir = clone.join("_delta_log");
+    std::fs::create_dir(&log_dir).unwrap();
+    for (version, commit) in [&clone_commit[..], &append_commit[..]].iter().enumerate() {
+        let commit = commit
+            .iter()
+            .map(|action| action.to_string())
+            .collect::<Vec<_>>();
+        std::fs::write(
+            log_dir.join(format!("{version:020}.json")),
+            commit.join("\n"),
+        )
+        .unwrap();
+    }
+}
+
+#[test]
+fn test_read_shallow_clone() {
+    let parent = std::fs::canonicalize("./tests/data/shallow_clone_parent/").unwrap();
+    let parent = Url::from_directory_path(parent).unwrap();
+    let clone = tempfile::tempdir().unwrap();
+    create_shallow_clone(&parent, clone.path());
+
+    let url = Url::from_directory_path(clone.path()).unwrap();
+    let engine = DefaultEngine::try_new(
+        &url,
+        std::iter::empty::<(&str, &str)>(),
+        Arc::new(TokioBackgroundExecutor::new()),
+    )
+    .unwrap();
+    let snapshot = Table::new(url).snapshot(&engine, None).unwrap();
+    assert_eq!(snapshot.version(), 1);
+    let scan = ScanBuilder::new(snapshot).build().unwrap();
+
+    // the inherited file is read from the parent and the clone's own file from the clone
+    let mut values: Vec<i64> = scan
+        .execute(&engine)
+        .unwrap()
+        .into_iter()
+        .flat_map(|result| {
+            let batch: RecordBatch =
+                ArrowEngineData::try_from_engine_data(result.raw_data.unwrap())
+                    .unwrap()
+                    .into();
+            let values = batch.column(0).as_primitive::<Int64Type>();
+            values.values().to_vec()
+        })
+        .collect();
+    values.sort();
+    assert_eq!(values, (0..13).collect::<Vec<_>>());
+}
+
 /*
 #[tokio::test]
 async fn test_read_last_checkpoint() {
@@ -178,6 +178,7 @@ DEFINE_VISIT_SIMPLE_TYPE(binary);
 DEFINE_VISIT_SIMPLE_TYPE(date);
 DEFINE_VISIT_SIMPLE_TYPE(timestamp);
 DEFINE_VISIT_SIMPLE_TYPE(timestamp_ntz);
+DEFINE_VISIT_SIMPLE_TYPE(void);
 
 // free all the data in the builder (but not the builder itself, it's stack allocated)
 void free_builder(SchemaBuilder builder) {
@@ -221,7 +222,8 @@ void print_schema(const SnapshotHandle *snapshot) {
     .visit_binary = visit_binary,
     .visit_date = visit_date,
     .visit_timestamp = visit_timestamp,
-    .visit_timestamp_ntz = visit_timestamp_ntz
+    .visit_timestamp_ntz = visit_timestamp_ntz,
+    .visit_void = visit_void
   };
   uintptr_t schema_list_id = visit_schema(snapshot, &visitor);
 #ifdef PRINT_VISITS
@@ -71,10 +71,10 @@ mod private {
     /// An accompanying [`HandleDescriptor`] trait defines the behavior of each handle type:
     ///
     /// * The true underlying ("target") type the handle represents. For safety reasons, target type
-    /// must always be [`Send`].
+    ///   must always be [`Send`].
     ///
     /// * Mutable (`Box`-like) vs. shared (`Arc`-like). For safety reasons, the target type of a
-    /// shared handle must always be [`Send`]+[`Sync`].
+    ///   shared handle must always be [`Send`]+[`Sync`].
     ///
     /// * Sized vs. unsized. Sized types allow handle operations to be implemented more efficiently.
     ///
@@ -177,7 +177,6 @@ mod private {
     {
         /// Obtains a mutable reference to the handle's underlying object. Unsafe equivalent to
         /// [`AsMut::as_mut`].
-
         ///
         /// # Safety
         ///
@@ -496,6 +495,7 @@ mod tests {
     use delta_kernel_ffi_macros::handle_descriptor;
 
     #[derive(Debug)]
+    #[allow(dead_code)]
     pub struct Foo {
         pub x: usize,
         pub y: String,
@@ -505,6 +505,7 @@ mod tests {
     pub struct MutableFoo;
 
     #[derive(Debug)]
+    #[allow(dead_code)]
     pub struct Bar {
         pub x: usize,
         pub y: String,
@@ -530,6 +531,7 @@ mod tests {
     pub struct SharedBaz;
 
     #[derive(Debug)]
+    #[allow(dead_code)]
     pub struct NotSync {
         pub ptr: *mut u32,
     }
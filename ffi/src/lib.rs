@@ -226,6 +226,7 @@ pub enum KernelError {
     #[cfg(feature = "default-engine")]
     Reqwest,
     FileNotFoundError,
+    StaleReadError,
     MissingColumnError,
     UnexpectedColumnTypeError,
     MissingDataError,
@@ -244,6 +245,18 @@ pub enum KernelError {
     InvalidTableLocation,
     InvalidDecimalError,
     InvalidStructData,
+    ScanValidationError,
+    InvalidPartitionPathError,
+    InvalidParquetFileError,
+    UnsupportedCompressionCodecError,
+    UnsupportedProtocolError,
+    SchemaMismatchError,
+    DuplicateAddError,
+    InsufficientPruningError,
+    TimestampBeforeEarliestCommitError,
+    InvalidCheckpointError,
+    InvalidCommitError,
+    ChangeDataFeedNotEnabledError,
 }
 
 impl From<Error> for KernelError {
@@ -259,6 +272,13 @@ impl From<Error> for KernelError {
             Error::IOError(_) => KernelError::IOErrorError,
             #[cfg(any(feature = "default-engine", feature = "sync-engine"))]
             Error::Parquet(_) => KernelError::ParquetError,
+            #[cfg(any(feature = "default-engine", feature = "sync-engine"))]
+            Error::InvalidParquetFile { .. } => KernelError::InvalidParquetFileError,
+            Error::UnsupportedCompressionCodec { .. } => {
+                KernelError::UnsupportedCompressionCodecError
+            }
+            Error::UnsupportedProtocol { .. } => KernelError::UnsupportedProtocolError,
+            Error::SchemaMismatch { .. } => KernelError::SchemaMismatchError,
             #[cfg(feature = "default-engine")]
             Error::ObjectStore(_) => KernelError::ObjectStoreError,
             #[cfg(feature = "default-engine")]
@@ -266,6 +286,7 @@ impl From<Error> for KernelError {
             #[cfg(feature = "default-engine")]
             Error::Reqwest(_) => KernelError::Reqwest,
             Error::FileNotFound(_) => KernelError::FileNotFoundError,
+            Error::StaleRead(_) => KernelError::StaleReadError,
             Error::MissingColumn(_) => KernelError::MissingColumnError,
             Error::UnexpectedColumnType(_) => KernelError::UnexpectedColumnTypeError,
             Error::MissingData(_) => KernelError::MissingDataError,
@@ -284,6 +305,16 @@ impl From<Error> for KernelError {
             Error::InvalidTableLocation(_) => KernelError::InvalidTableLocation,
             Error::InvalidDecimal(_) => KernelError::InvalidDecimalError,
             Error::InvalidStructData(_) => KernelError::InvalidStructData,
+            Error::ScanValidation(_) => KernelError::ScanValidationError,
+            Error::InvalidPartitionPath(_) => KernelError::InvalidPartitionPathError,
+            Error::DuplicateAdd(_) => KernelError::DuplicateAddError,
+            Error::InsufficientPruning { .. } => KernelError::InsufficientPruningError,
+            Error::TimestampBeforeEarliestCommit { .. } => {
+                KernelError::TimestampBeforeEarliestCommitError
+            }
+            Error::InvalidCheckpoint(_) => KernelError::InvalidCheckpointError,
+            Error::InvalidCommit(_) => KernelError::InvalidCommitError,
+            Error::ChangeDataFeedNotEnabled { .. } => KernelError::ChangeDataFeedNotEnabledError,
             Error::Backtraced {
                 source,
                 backtrace: _,
@@ -768,6 +799,10 @@ pub struct EngineSchemaVisitor {
     /// Visit a `timestamp` with no timezone belonging to the list identified by `sibling_list_id`.
     pub visit_timestamp_ntz:
         extern "C" fn(data: *mut c_void, sibling_list_id: usize, name: KernelStringSlice),
+
+    /// Visit a `void` (always null) column belonging to the list identified by `sibling_list_id`.
+    pub visit_void:
+        extern "C" fn(data: *mut c_void, sibling_list_id: usize, name: KernelStringSlice),
 }
 
 /// Visit the schema of the passed `SnapshotHandle`, using the provided `visitor`. See the
@@ -845,6 +880,7 @@ pub unsafe extern "C" fn visit_schema(
             &DataType::DATE => call!(visit_date),
             &DataType::TIMESTAMP => call!(visit_timestamp),
             &DataType::TIMESTAMP_NTZ => call!(visit_timestamp_ntz),
+            &DataType::VOID => call!(visit_void),
         }
     }
 
@@ -1044,7 +1080,7 @@ fn visit_expression_column_impl(
     state: &mut KernelExpressionVisitorState,
     name: DeltaResult<String>,
 ) -> DeltaResult<usize> {
-    Ok(wrap_expression(state, Expression::Column(name?)))
+    Ok(wrap_expression(state, Expression::column(name?)))
 }
 
 #[no_mangle]
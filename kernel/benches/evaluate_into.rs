@@ -0,0 +1,123 @@
+//! Compare evaluating a predicate on many small batches with `ExpressionEvaluator::evaluate`,
+//! which materializes a new batch for each result, to `ExpressionEvaluator::evaluate_into`, which
+//! writes each result into one reused buffer. This is the pattern of data skipping, which
+//! evaluates its predicate on the stats of every batch of actions in the log.
+//!
+//! Run with `cargo bench --bench evaluate_into`. Besides the time per batch, this reports the
+//! number of allocations per batch, counted by a global allocator.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::hint::black_box;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Instant;
+
+use arrow::array::{AsArray, Int64Array, RecordBatch};
+use arrow::datatypes::{DataType as ArrowDataType, Field, Schema as ArrowSchema};
+use delta_kernel::engine::arrow_data::ArrowEngineData;
+use delta_kernel::engine::sync::SyncEngine;
+use delta_kernel::expressions::Expression;
+use delta_kernel::schema::{DataType, StructField, StructType};
+use delta_kernel::Engine;
+
+/// The number of rows of each batch, about the number of files in a commit
+const BATCH_SIZE: usize = 64;
+const NUM_BATCHES: usize = 100_000;
+
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+/// Run `f` on each batch, printing the time and number of allocations per batch
+fn bench(name: &str, batches: &[ArrowEngineData], mut f: impl FnMut(&ArrowEngineData)) {
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed);
+    let start = Instant::now();
+    for batch in batches {
+        f(batch);
+    }
+    let elapsed = start.elapsed();
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed) - allocations;
+    println!(
+        "{name:>14}: {:>8.0} ns/batch, {:>6.1} allocations/batch",
+        elapsed.as_nanos() as f64 / batches.len() as f64,
+        allocations as f64 / batches.len() as f64,
+    );
+}
+
+fn main() {
+    let schema = Arc::new(StructType::new(vec![
+        StructField::new("min", DataType::LONG, true),
+        StructField::new("max", DataType::LONG, true),
+    ]));
+    // like the skipping predicate of `x = 500`, which is null for files without stats
+    let predicate = Expression::and(
+        Expression::column("min").lt_eq(Expression::literal(500i64)),
+        Expression::column("max").gt_eq(Expression::literal(500i64)),
+    )
+    .distinct(Expression::literal(false));
+    let evaluator = SyncEngine::new()
+        .get_expression_handler()
+        .get_evaluator(schema, predicate, DataType::BOOLEAN)
+        .unwrap();
+
+    let arrow_schema = Arc::new(ArrowSchema::new(vec![
+        Field::new("min", ArrowDataType::Int64, true),
+        Field::new("max", ArrowDataType::Int64, true),
+    ]));
+    let batches: Vec<_> = (0..NUM_BATCHES as i64)
+        .map(|index| {
+            let min = (0..BATCH_SIZE as i64).map(|row| Some(index + row * 10));
+            let max = (0..BATCH_SIZE as i64).map(|row| Some(index + row * 10 + 5));
+            let batch = RecordBatch::try_new(
+                arrow_schema.clone(),
+                vec![
+                    Arc::new(min.collect::<Int64Array>()),
+                    Arc::new(max.collect::<Int64Array>()),
+                ],
+            )
+            .unwrap();
+            ArrowEngineData::new(batch)
+        })
+        .collect();
+
+    bench("evaluate", &batches, |batch| {
+        let result = evaluator.evaluate(batch).unwrap();
+        let result = result
+            .as_any()
+            .downcast_ref::<ArrowEngineData>()
+            .unwrap()
+            .record_batch();
+        let selection: Vec<bool> = result
+            .column(0)
+            .as_boolean()
+            .iter()
+            .map(|v| v == Some(true))
+            .collect();
+        black_box(selection);
+    });
+
+    let mut selection = Vec::with_capacity(BATCH_SIZE);
+    bench("evaluate_into", &batches, |batch| {
+        evaluator.evaluate_into(batch, &mut selection).unwrap();
+        black_box(&selection);
+    });
+}
@@ -243,6 +243,8 @@ fn do_work(
             last_modified: 0,
             size: scan_file.size as usize,
             location,
+            e_tag: None,
+            version: None,
         };
 
         // this example uses the parquet_handler from the engine, but an engine could
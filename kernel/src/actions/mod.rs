@@ -7,11 +7,12 @@ pub(crate) mod visitors;
 
 use delta_kernel_derive::Schema;
 use lazy_static::lazy_static;
-use visitors::{AddVisitor, MetadataVisitor, ProtocolVisitor};
+use visitors::{AddVisitor, CommitInfoVisitor, MetadataVisitor, ProtocolVisitor};
 
 use self::deletion_vector::DeletionVectorDescriptor;
 use crate::actions::schemas::GetStructField;
-use crate::{schema::StructType, DeltaResult, EngineData};
+use crate::schema::{DataType, PrimitiveType, SchemaRef, StructType};
+use crate::{DeltaResult, EngineData, Error};
 
 use std::collections::HashMap;
 
@@ -20,6 +21,25 @@ pub(crate) const REMOVE_NAME: &str = "remove";
 pub(crate) const METADATA_NAME: &str = "metaData";
 pub(crate) const PROTOCOL_NAME: &str = "protocol";
 pub(crate) const TRANSACTION_NAME: &str = "txn";
+pub(crate) const COMMIT_INFO_NAME: &str = "commitInfo";
+pub(crate) const CDC_NAME: &str = "cdc";
+pub(crate) const SIDECAR_NAME: &str = "sidecar";
+
+/// The highest reader version the kernel implements. Version 3 is the table features protocol,
+/// where the `readerFeatures` of the protocol name what a reader must implement.
+const MAX_READER_VERSION: i32 = 3;
+/// The highest writer version the kernel knows of. Version 7 is the table features protocol.
+const MAX_WRITER_VERSION: i32 = 7;
+/// The reader feature required to read columns of type `timestamp_ntz`
+const TIMESTAMP_NTZ_FEATURE: &str = "timestampNtz";
+/// The reader features the kernel implements
+const SUPPORTED_READER_FEATURES: &[&str] = &[
+    "columnMapping",
+    "deletionVectors",
+    TIMESTAMP_NTZ_FEATURE,
+    "v2Checkpoint",
+    "vacuumProtocolCheck",
+];
 
 lazy_static! {
     static ref LOG_SCHEMA: StructType = StructType::new(
@@ -29,9 +49,10 @@ lazy_static! {
             Option::<Metadata>::get_struct_field(METADATA_NAME),
             Option::<Protocol>::get_struct_field(PROTOCOL_NAME),
             Option::<Transaction>::get_struct_field(TRANSACTION_NAME),
+            Option::<CommitInfo>::get_struct_field(COMMIT_INFO_NAME),
+            Option::<Cdc>::get_struct_field(CDC_NAME),
+            Option::<Sidecar>::get_struct_field(SIDECAR_NAME),
             // We don't support the following actions yet
-            //Option<Cdc>::get_field(CDC_NAME),
-            //Option<CommitInfo>::get_field(COMMIT_INFO_NAME),
             //Option<DomainMetadata>::get_field(DOMAIN_METADATA_NAME),
         ]
     );
@@ -41,6 +62,46 @@ pub(crate) fn get_log_schema() -> &'static StructType {
     &LOG_SCHEMA
 }
 
+/// The types of action that can be read from the log. An operation that only needs some actions
+/// (e.g. a scan only needs [`ActionType::Add`] and [`ActionType::Remove`]) can use
+/// `get_log_schema_for` to avoid parsing the others.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ActionType {
+    Add,
+    Remove,
+    Metadata,
+    Protocol,
+    Transaction,
+    CommitInfo,
+    Cdc,
+    Sidecar,
+}
+
+impl ActionType {
+    /// The name of the field in the log that holds this type of action
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Add => ADD_NAME,
+            Self::Remove => REMOVE_NAME,
+            Self::Metadata => METADATA_NAME,
+            Self::Protocol => PROTOCOL_NAME,
+            Self::Transaction => TRANSACTION_NAME,
+            Self::CommitInfo => COMMIT_INFO_NAME,
+            Self::Cdc => CDC_NAME,
+            Self::Sidecar => SIDECAR_NAME,
+        }
+    }
+}
+
+/// Get the schema to read the log with such that only the given `actions` are parsed. The fields of
+/// the returned schema are in the same order as `actions`.
+#[cfg_attr(feature = "developer-visibility", visibility::make(pub))]
+#[cfg_attr(not(feature = "developer-visibility"), visibility::make(pub(crate)))]
+fn get_log_schema_for(actions: &[ActionType]) -> DeltaResult<SchemaRef> {
+    let names: Vec<_> = actions.iter().map(ActionType::name).collect();
+    get_log_schema().project(&names)
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Schema)]
 pub struct Format {
     /// Name of the encoding for files in this table
@@ -112,6 +173,62 @@ impl Protocol {
         data.extract(get_log_schema().project(&[PROTOCOL_NAME])?, &mut visitor)?;
         Ok(visitor.protocol)
     }
+
+    /// Check that the kernel can read a table with this protocol, returning an
+    /// [`Error::UnsupportedProtocol`] if not. Reader versions 1 and 2 predate table features and
+    /// are always supported (version 2 is column mapping). With reader version 3, every one of the
+    /// `readerFeatures` must be supported. Writer features are only checked by writers, but a
+    /// writer version newer than the table features protocol is rejected as unknown.
+    pub fn ensure_read_supported(&self) -> DeltaResult<()> {
+        let supported = match self.min_reader_version {
+            1 | 2 => true,
+            MAX_READER_VERSION => self
+                .reader_features
+                .iter()
+                .flatten()
+                .all(|feature| SUPPORTED_READER_FEATURES.contains(&feature.as_str())),
+            _ => false,
+        };
+        if supported && self.min_writer_version <= MAX_WRITER_VERSION {
+            Ok(())
+        } else {
+            Err(Error::unsupported_protocol(self))
+        }
+    }
+
+    /// Check that `schema`, the schema of a table with this protocol, only uses types whose
+    /// reader feature the protocol declares, returning an [`Error::UnsupportedProtocol`] if not.
+    /// Columns of type `timestamp_ntz` require the `timestampNtz` reader feature, as readers
+    /// without it would interpret them as UTC timestamps.
+    pub fn ensure_schema_supported(&self, schema: &StructType) -> DeltaResult<()> {
+        let uses_timestamp_ntz = schema
+            .fields()
+            .any(|field| uses_timestamp_ntz(field.data_type()));
+        if uses_timestamp_ntz && !self.has_reader_feature(TIMESTAMP_NTZ_FEATURE) {
+            return Err(Error::unsupported_protocol(self));
+        }
+        Ok(())
+    }
+
+    /// Whether the protocol names `feature` in its `readerFeatures`
+    fn has_reader_feature(&self, feature: &str) -> bool {
+        self.min_reader_version == MAX_READER_VERSION
+            && self.reader_features.iter().flatten().any(|f| f == feature)
+    }
+}
+
+/// Whether `data_type` is, or (for a nested type) contains, a `timestamp_ntz`
+fn uses_timestamp_ntz(data_type: &DataType) -> bool {
+    match data_type {
+        DataType::Primitive(primitive) => primitive == &PrimitiveType::TimestampNtz,
+        DataType::Array(array) => uses_timestamp_ntz(array.element_type()),
+        DataType::Map(map) => {
+            uses_timestamp_ntz(&map.key_type) || uses_timestamp_ntz(&map.value_type)
+        }
+        DataType::Struct(fields) => fields
+            .fields()
+            .any(|field| uses_timestamp_ntz(field.data_type())),
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Schema)]
@@ -170,54 +287,105 @@ impl Add {
     pub fn dv_unique_id(&self) -> Option<String> {
         self.deletion_vector.as_ref().map(|dv| dv.unique_id())
     }
+
+    /// The number of records in this file, if it is recorded in the [`Add::stats`]
+    pub fn num_records(&self) -> Option<u64> {
+        let stats: serde_json::Value = serde_json::from_str(self.stats.as_ref()?).ok()?;
+        stats.get("numRecords")?.as_u64()
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Schema)]
-pub(crate) struct Remove {
+pub struct Remove {
     /// A relative path to a data file from the root of the table or an absolute path to a file
     /// that should be added to the table. The path is a URI as specified by
     /// [RFC 2396 URI Generic Syntax], which needs to be decoded to get the data file path.
     ///
     /// [RFC 2396 URI Generic Syntax]: https://www.ietf.org/rfc/rfc2396.txt
-    pub(crate) path: String,
+    pub path: String,
 
-    /// The time this logical file was created, as milliseconds since the epoch.
-    pub(crate) deletion_timestamp: Option<i64>,
+    /// The time this logical file was removed from the table, as milliseconds since the epoch.
+    pub deletion_timestamp: Option<i64>,
 
     /// When `false` the logical file must already be present in the table or the records
     /// in the added file must be contained in one or more remove actions in the same version.
-    pub(crate) data_change: bool,
+    pub data_change: bool,
 
     /// When true the fields `partition_values`, `size`, and `tags` are present
-    pub(crate) extended_file_metadata: Option<bool>,
+    pub extended_file_metadata: Option<bool>,
 
     /// A map from partition column to value for this logical file.
-    pub(crate) partition_values: Option<HashMap<String, String>>,
+    pub partition_values: Option<HashMap<String, String>>,
 
     /// The size of this data file in bytes
-    pub(crate) size: Option<i64>,
+    pub size: Option<i64>,
 
     /// Map containing metadata about this logical file.
-    pub(crate) tags: Option<HashMap<String, String>>,
+    pub tags: Option<HashMap<String, String>>,
 
     /// Information about deletion vector (DV) associated with this add action
-    pub(crate) deletion_vector: Option<DeletionVectorDescriptor>,
+    pub deletion_vector: Option<DeletionVectorDescriptor>,
 
     /// Default generated Row ID of the first row in the file. The default generated Row IDs
     /// of the other rows in the file can be reconstructed by adding the physical index of the
     /// row within the file to the base Row ID
-    pub(crate) base_row_id: Option<i64>,
+    pub base_row_id: Option<i64>,
 
     /// First commit version in which an add action with the same path was committed to the table.
-    pub(crate) default_row_commit_version: Option<i64>,
+    pub default_row_commit_version: Option<i64>,
 }
 
 impl Remove {
-    pub(crate) fn dv_unique_id(&self) -> Option<String> {
+    pub fn dv_unique_id(&self) -> Option<String> {
         self.deletion_vector.as_ref().map(|dv| dv.unique_id())
     }
 }
 
+/// A file of the change data feed, holding the changed rows of a commit
+#[derive(Debug, Clone, PartialEq, Eq, Schema)]
+pub struct Cdc {
+    /// A relative path to a change data file from the root of the table or an absolute path to a
+    /// change data file that should be added to the table. The path is a URI as specified by
+    /// [RFC 2396 URI Generic Syntax], which needs to be decoded to get the file path.
+    ///
+    /// [RFC 2396 URI Generic Syntax]: https://www.ietf.org/rfc/rfc2396.txt
+    pub path: String,
+
+    /// A map from partition column to value for this file.
+    pub partition_values: HashMap<String, String>,
+
+    /// The size of this file in bytes
+    pub size: i64,
+
+    /// Should always be set to `false` for `cdc` actions because they *do not* change the
+    /// underlying data of the table
+    pub data_change: bool,
+
+    /// Map containing metadata about this file.
+    pub tags: Option<HashMap<String, String>>,
+}
+
+/// A file of a V2 checkpoint that holds some of the checkpoint's file actions. The sidecar actions
+/// are in the top-level file of the checkpoint, and the sidecar files themselves hold only `add`
+/// and `remove` actions.
+#[derive(Debug, Clone, PartialEq, Eq, Schema)]
+pub struct Sidecar {
+    /// The path of the sidecar file, relative to the `_delta_log/_sidecars` directory or an
+    /// absolute URL. The path is a URI as specified by [RFC 2396 URI Generic Syntax].
+    ///
+    /// [RFC 2396 URI Generic Syntax]: https://www.ietf.org/rfc/rfc2396.txt
+    pub path: String,
+
+    /// The size of the sidecar file in bytes
+    pub size_in_bytes: i64,
+
+    /// The time the sidecar file was created, in milliseconds since the Unix epoch
+    pub modification_time: i64,
+
+    /// Map containing metadata about the sidecar file
+    pub tags: Option<HashMap<String, String>>,
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Schema)]
 pub struct Transaction {
     /// A unique identifier for the application performing the transaction.
@@ -230,12 +398,142 @@ pub struct Transaction {
     pub last_updated: Option<i64>,
 }
 
+/// The subset of the (free-form) `commitInfo` action of a commit that the kernel understands.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Schema)]
+pub struct CommitInfo {
+    /// The time of the commit in milliseconds since the Unix epoch, as recorded by the writer.
+    pub timestamp: Option<i64>,
+
+    /// The monotonically increasing timestamp of the commit in milliseconds since the Unix epoch.
+    /// Only present for tables with the [in-commit timestamps] feature enabled, in which case it
+    /// is the authoritative timestamp of the commit.
+    ///
+    /// [in-commit timestamps]: https://github.com/delta-io/delta/blob/master/PROTOCOL.md#in-commit-timestamps
+    pub in_commit_timestamp: Option<i64>,
+
+    /// The name of the operation that produced the commit, e.g. `WRITE`
+    pub operation: Option<String>,
+
+    /// The parameters of the operation, e.g. the `mode` of a `WRITE`. Parameters with a null value
+    /// are left out.
+    pub operation_parameters: Option<HashMap<String, String>>,
+
+    /// Arbitrary metadata the user attached to the commit
+    pub user_metadata: Option<String>,
+}
+
+impl CommitInfo {
+    pub fn try_new_from_data(data: &dyn EngineData) -> DeltaResult<Option<CommitInfo>> {
+        let mut visitor = CommitInfoVisitor::default();
+        data.extract(get_log_schema().project(&[COMMIT_INFO_NAME])?, &mut visitor)?;
+        Ok(visitor.commit_info)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::sync::Arc;
 
     use super::*;
-    use crate::schema::{ArrayType, DataType, MapType, StructField};
+    use crate::schema::{ArrayType, MapType, StructField};
+
+    #[test]
+    fn test_log_schema_for() {
+        let schema = get_log_schema_for(&[ActionType::Remove, ActionType::Add]).unwrap();
+        let names: Vec<_> = schema.fields().map(StructField::name).collect();
+        assert_eq!(names, [REMOVE_NAME, ADD_NAME]);
+        assert_eq!(schema.field(ADD_NAME), get_log_schema().field(ADD_NAME),);
+
+        let schema = get_log_schema_for(&[]).unwrap();
+        assert_eq!(schema.fields().count(), 0);
+    }
+
+    #[test]
+    fn test_ensure_read_supported() {
+        let protocol = |reader, writer, reader_features: Option<&[&str]>| Protocol {
+            min_reader_version: reader,
+            min_writer_version: writer,
+            reader_features: reader_features
+                .map(|features| features.iter().map(ToString::to_string).collect()),
+            writer_features: reader_features
+                .map(|features| features.iter().map(ToString::to_string).collect()),
+        };
+        let supported = [
+            protocol(1, 2, None),
+            protocol(2, 5, None),
+            protocol(3, 7, Some(&[])),
+            protocol(3, 7, Some(&["deletionVectors", "columnMapping"])),
+        ];
+        for protocol in supported {
+            assert!(protocol.ensure_read_supported().is_ok(), "{protocol:?}");
+        }
+
+        let unsupported = [
+            protocol(4, 7, None),
+            protocol(1, 8, None),
+            protocol(3, 7, Some(&["deletionVectors", "someFutureFeature"])),
+        ];
+        for protocol in unsupported {
+            match protocol.ensure_read_supported() {
+                Err(Error::UnsupportedProtocol {
+                    min_reader_version,
+                    min_writer_version,
+                    required_reader_features,
+                    required_writer_features,
+                }) => {
+                    assert_eq!(min_reader_version, protocol.min_reader_version);
+                    assert_eq!(min_writer_version, protocol.min_writer_version);
+                    let expected = protocol.reader_features.clone().unwrap_or_default();
+                    assert_eq!(required_reader_features, expected);
+                    assert_eq!(required_writer_features, expected);
+                }
+                result => panic!("Expected an unsupported protocol error, got {result:?}"),
+            }
+        }
+    }
+
+    #[test]
+    fn test_ensure_schema_supported() {
+        let protocol = |reader, reader_features: Option<&[&str]>| Protocol {
+            min_reader_version: reader,
+            min_writer_version: 7,
+            reader_features: reader_features
+                .map(|features| features.iter().map(ToString::to_string).collect()),
+            writer_features: None,
+        };
+        let plain = StructType::new(vec![
+            StructField::new("ts", DataType::TIMESTAMP, true),
+            StructField::new("date", DataType::DATE, true),
+        ]);
+        let ntz = StructType::new(vec![StructField::new("ts", DataType::TIMESTAMP_NTZ, true)]);
+        let nested_ntz = StructType::new(vec![StructField::new(
+            "values",
+            MapType::new(
+                DataType::STRING,
+                ArrayType::new(DataType::TIMESTAMP_NTZ, true).into(),
+                true,
+            ),
+            true,
+        )]);
+
+        let with_feature = protocol(3, Some(&["timestampNtz"]));
+        for schema in [&plain, &ntz, &nested_ntz] {
+            assert!(with_feature.ensure_schema_supported(schema).is_ok());
+        }
+        for without_feature in [
+            protocol(1, None),
+            protocol(3, Some(&[])),
+            protocol(3, Some(&["deletionVectors"])),
+        ] {
+            assert!(without_feature.ensure_schema_supported(&plain).is_ok());
+            for schema in [&ntz, &nested_ntz] {
+                assert!(matches!(
+                    without_feature.ensure_schema_supported(schema),
+                    Err(Error::UnsupportedProtocol { .. })
+                ));
+            }
+        }
+    }
 
     #[test]
     fn test_metadata_schema() {
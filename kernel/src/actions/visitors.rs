@@ -9,7 +9,8 @@ use crate::{
 };
 
 use super::{
-    deletion_vector::DeletionVectorDescriptor, Add, Format, Metadata, Protocol, Remove, Transaction,
+    deletion_vector::DeletionVectorDescriptor, Add, Cdc, CommitInfo, Format, Metadata, Protocol,
+    Remove, Sidecar, Transaction,
 };
 
 #[derive(Default)]
@@ -72,8 +73,8 @@ pub(crate) struct SelectionVectorVisitor {
 impl DataVisitor for SelectionVectorVisitor {
     fn visit<'a>(&mut self, row_count: usize, getters: &[&'a dyn GetData<'a>]) -> DeltaResult<()> {
         for i in 0..row_count {
-            self.selection_vector
-                .push(getters[0].get(i, "selectionvector.output")?)
+            let selected: Option<bool> = getters[0].get_opt(i, "selectionvector.output")?;
+            self.selection_vector.push(selected.unwrap_or(false))
         }
         Ok(())
     }
@@ -190,11 +191,10 @@ impl RemoveVisitor {
         let extended_file_metadata: Option<bool> =
             getters[3].get_opt(row_index, "remove.extendedFileMetadata")?;
 
-        // TODO(nick) handle partition values in getters[4]
-
+        let partition_values: Option<HashMap<_, _>> =
+            getters[4].get_opt(row_index, "remove.partitionValues")?;
         let size: Option<i64> = getters[5].get_opt(row_index, "remove.size")?;
-
-        // TODO(nick) tags are skipped in getters[6]
+        let tags: Option<HashMap<_, _>> = getters[6].get_opt(row_index, "remove.tags")?;
 
         let deletion_vector = visit_deletion_vector_at(row_index, &getters[7..])?;
 
@@ -207,9 +207,9 @@ impl RemoveVisitor {
             data_change,
             deletion_timestamp,
             extended_file_metadata,
-            partition_values: None,
+            partition_values,
             size,
-            tags: None,
+            tags,
             deletion_vector,
             base_row_id,
             default_row_commit_version,
@@ -223,7 +223,6 @@ impl DataVisitor for RemoveVisitor {
             // Since path column is required, use it to detect presence of an Remove action
             if let Some(path) = getters[0].get_opt(i, "remove.path")? {
                 self.removes.push(Self::visit_remove(i, path, getters)?);
-                break;
             }
         }
         Ok(())
@@ -276,10 +275,10 @@ impl DataVisitor for TransactionVisitor {
         for i in 0..row_count {
             if let Some(app_id) = getters[0].get_opt(i, "txn.appId")? {
                 // if caller requested a specific id then only visit matches
-                if !self
+                if self
                     .application_id
                     .as_ref()
-                    .is_some_and(|requested| !requested.eq(&app_id))
+                    .is_none_or(|requested| requested.eq(&app_id))
                 {
                     let txn = TransactionVisitor::visit_txn(i, app_id, getters)?;
                     if !self.transactions.contains_key(&txn.app_id) {
@@ -292,6 +291,76 @@ impl DataVisitor for TransactionVisitor {
     }
 }
 
+#[derive(Default)]
+pub(crate) struct CdcVisitor {
+    pub(crate) cdcs: Vec<Cdc>,
+}
+
+impl DataVisitor for CdcVisitor {
+    fn visit<'a>(&mut self, row_count: usize, getters: &[&'a dyn GetData<'a>]) -> DeltaResult<()> {
+        for i in 0..row_count {
+            // Since path column is required, use it to detect presence of a cdc action
+            if let Some(path) = getters[0].get_opt(i, "cdc.path")? {
+                self.cdcs.push(Cdc {
+                    path,
+                    partition_values: getters[1].get(i, "cdc.partitionValues")?,
+                    size: getters[2].get(i, "cdc.size")?,
+                    data_change: getters[3].get(i, "cdc.dataChange")?,
+                    tags: getters[4].get_opt(i, "cdc.tags")?,
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+pub(crate) struct SidecarVisitor {
+    pub(crate) sidecars: Vec<Sidecar>,
+}
+
+impl DataVisitor for SidecarVisitor {
+    fn visit<'a>(&mut self, row_count: usize, getters: &[&'a dyn GetData<'a>]) -> DeltaResult<()> {
+        for i in 0..row_count {
+            // Since path column is required, use it to detect presence of a sidecar action
+            if let Some(path) = getters[0].get_opt(i, "sidecar.path")? {
+                self.sidecars.push(Sidecar {
+                    path,
+                    size_in_bytes: getters[1].get(i, "sidecar.sizeInBytes")?,
+                    modification_time: getters[2].get(i, "sidecar.modificationTime")?,
+                    tags: getters[3].get_opt(i, "sidecar.tags")?,
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+pub(crate) struct CommitInfoVisitor {
+    pub(crate) commit_info: Option<CommitInfo>,
+}
+
+impl DataVisitor for CommitInfoVisitor {
+    fn visit<'a>(&mut self, row_count: usize, getters: &[&'a dyn GetData<'a>]) -> DeltaResult<()> {
+        for i in 0..row_count {
+            let commit_info = CommitInfo {
+                timestamp: getters[0].get_opt(i, "commitInfo.timestamp")?,
+                in_commit_timestamp: getters[1].get_opt(i, "commitInfo.inCommitTimestamp")?,
+                operation: getters[2].get_opt(i, "commitInfo.operation")?,
+                operation_parameters: getters[3].get_opt(i, "commitInfo.operationParameters")?,
+                user_metadata: getters[4].get_opt(i, "commitInfo.userMetadata")?,
+            };
+            // commitInfo has no required fields, so treat a row without any of them as absent
+            if commit_info != CommitInfo::default() {
+                self.commit_info = Some(commit_info);
+                break;
+            }
+        }
+        Ok(())
+    }
+}
+
 /// Get a DV out of some engine data. The caller is responsible for slicing the `getters` slice such
 /// that the first element contains the `storageType` element of the deletion vector.
 pub(crate) fn visit_deletion_vector_at<'a>(
@@ -327,7 +396,7 @@ mod tests {
 
     use super::*;
     use crate::{
-        actions::{get_log_schema, ADD_NAME, TRANSACTION_NAME},
+        actions::{get_log_schema, ADD_NAME, REMOVE_NAME, TRANSACTION_NAME},
         engine::arrow_data::ArrowEngineData,
         engine::sync::{json::SyncJsonHandler, SyncEngine},
         Engine, EngineData, JsonHandler,
@@ -401,6 +470,55 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_parse_remove() {
+        let engine = SyncEngine::new();
+        let json_handler = engine.get_json_handler();
+        let json_strings: StringArray = vec![
+            r#"{"remove":{"path":"c1=4/part-00003.snappy.parquet","deletionTimestamp":1670892998177,"dataChange":true,"extendedFileMetadata":true,"partitionValues":{"c1":"4"},"size":452,"tags":{"INSERTION_TIME":"1670892998135000"}}}"#,
+            r#"{"protocol":{"minReaderVersion":1,"minWriterVersion":2}}"#,
+            r#"{"remove":{"path":"part-00004.snappy.parquet","dataChange":false}}"#,
+        ]
+        .into();
+        let output_schema = Arc::new(get_log_schema().clone());
+        let batch = json_handler
+            .parse_json(string_array_to_engine_data(json_strings), output_schema)
+            .unwrap();
+        let remove_schema = get_log_schema()
+            .project(&[REMOVE_NAME])
+            .expect("Can't get remove schema");
+        let mut remove_visitor = RemoveVisitor::default();
+        batch.extract(remove_schema, &mut remove_visitor).unwrap();
+        let remove1 = Remove {
+            path: "c1=4/part-00003.snappy.parquet".into(),
+            deletion_timestamp: Some(1670892998177),
+            data_change: true,
+            extended_file_metadata: Some(true),
+            partition_values: Some(HashMap::from([("c1".to_string(), "4".to_string())])),
+            size: Some(452),
+            tags: Some(HashMap::from([(
+                "INSERTION_TIME".to_string(),
+                "1670892998135000".to_string(),
+            )])),
+            deletion_vector: None,
+            base_row_id: None,
+            default_row_commit_version: None,
+        };
+        let remove2 = Remove {
+            path: "part-00004.snappy.parquet".into(),
+            deletion_timestamp: None,
+            data_change: false,
+            extended_file_metadata: None,
+            partition_values: None,
+            size: None,
+            tags: None,
+            deletion_vector: None,
+            base_row_id: None,
+            default_row_commit_version: None,
+        };
+        assert_eq!(remove_visitor.removes, [remove1, remove2]);
+    }
+
     #[test]
     fn test_parse_add_partitioned() {
         let engine = SyncEngine::new();
@@ -460,11 +578,37 @@ mod tests {
             ..add1.clone()
         };
         let expected = vec![add1, add2, add3];
-        for (add, expected) in add_visitor.adds.into_iter().zip(expected.into_iter()) {
+        for (add, expected) in add_visitor.adds.into_iter().zip(expected) {
             assert_eq!(add, expected);
         }
     }
 
+    #[test]
+    fn test_parse_add_clustering_provider() {
+        let engine = SyncEngine::new();
+        let json_handler = engine.get_json_handler();
+        let json_strings: StringArray = vec![
+            r#"{"add":{"path":"part-00000-8ea8e6b1-ee18-4bc9-b1e8-c8bb3b1e3d61.c000.snappy.parquet","partitionValues":{},"size":452,"modificationTime":1670892998135,"dataChange":true,"clusteringProvider":"liquid"}}"#,
+            r#"{"add":{"path":"part-00001-2b0d2ef6-5e2e-4c1c-9c41-18f40e1f6e3a.c000.snappy.parquet","partitionValues":{},"size":452,"modificationTime":1670892998136,"dataChange":true}}"#,
+        ]
+        .into();
+        let output_schema = Arc::new(get_log_schema().clone());
+        let batch = json_handler
+            .parse_json(string_array_to_engine_data(json_strings), output_schema)
+            .unwrap();
+        let add_schema = get_log_schema()
+            .project(&[ADD_NAME])
+            .expect("Can't get add schema");
+        let mut add_visitor = AddVisitor::default();
+        batch.extract(add_schema, &mut add_visitor).unwrap();
+        let clustering_providers: Vec<_> = add_visitor
+            .adds
+            .iter()
+            .map(|add| add.clustering_provider.as_deref())
+            .collect();
+        assert_eq!(clustering_providers, [Some("liquid"), None]);
+    }
+
     #[test]
     fn test_parse_txn() {
         let engine = SyncEngine::new();
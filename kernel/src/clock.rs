@@ -0,0 +1,138 @@
+//! The source of the timestamps of commits that don't have an in-commit timestamp, which an
+//! [`Engine`] can provide with [`Engine::get_clock`].
+//!
+//! Such commits are timestamped with the modification time of their file, which makes code
+//! resolving timestamps (like [`Snapshot::timestamp`] or timestamp ranges of table changes) hard to
+//! test deterministically. A [`FixedClock`] lets tests choose the timestamps instead, as well as
+//! the current time that retention periods (like that of [`Snapshot::vacuum_candidates`]) are
+//! measured from.
+//!
+//! [`Engine`]: crate::Engine
+//! [`Engine::get_clock`]: crate::Engine::get_clock
+//! [`Snapshot::timestamp`]: crate::snapshot::Snapshot::timestamp
+//! [`Snapshot::vacuum_candidates`]: crate::snapshot::Snapshot::vacuum_candidates
+use std::collections::HashMap;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::{FileMeta, Version};
+
+/// Resolves the timestamp of commits that don't have an in-commit timestamp.
+pub trait Clock: Send + Sync {
+    /// The timestamp, in milliseconds since the Unix epoch, of the commit of `version`, whose
+    /// file is `commit_file`.
+    fn commit_timestamp(&self, version: Version, commit_file: &FileMeta) -> i64;
+
+    /// The current time, in milliseconds since the Unix epoch. Defaults to the system time.
+    fn now(&self) -> i64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |duration| duration.as_millis() as i64)
+    }
+}
+
+/// The [`Clock`] used by default, which timestamps commits with the modification time of their
+/// file, as reported by the [`crate::FileSystemClient`] that listed it.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct FileModificationClock;
+
+impl Clock for FileModificationClock {
+    fn commit_timestamp(&self, _version: Version, commit_file: &FileMeta) -> i64 {
+        commit_file.last_modified
+    }
+}
+
+/// A [`Clock`] with fixed timestamps for some commits, e.g. for tests. Other commits are
+/// timestamped with the modification time of their file like with a [`FileModificationClock`].
+/// The current time is the system time unless it is fixed too.
+#[derive(Debug, Default, Clone)]
+pub struct FixedClock {
+    commit_timestamps: HashMap<Version, i64>,
+    now: Option<i64>,
+}
+
+impl FixedClock {
+    /// Create a clock without any fixed timestamps
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Timestamp the commit of `version` with `timestamp` (in milliseconds since the Unix epoch)
+    pub fn with_commit_timestamp(mut self, version: Version, timestamp: i64) -> Self {
+        self.commit_timestamps.insert(version, timestamp);
+        self
+    }
+
+    /// Fix the current time to `timestamp` (in milliseconds since the Unix epoch)
+    pub fn with_now(mut self, timestamp: i64) -> Self {
+        self.now = Some(timestamp);
+        self
+    }
+}
+
+impl Clock for FixedClock {
+    fn commit_timestamp(&self, version: Version, commit_file: &FileMeta) -> i64 {
+        self.commit_timestamps
+            .get(&version)
+            .copied()
+            .unwrap_or(commit_file.last_modified)
+    }
+
+    fn now(&self) -> i64 {
+        self.now.unwrap_or_else(|| FileModificationClock.now())
+    }
+}
+
+#[cfg(all(test, feature = "sync-engine"))]
+mod tests {
+    use std::path::PathBuf;
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::engine::sync::SyncEngine;
+    use crate::{Engine, ExpressionHandler, FileSystemClient, JsonHandler, ParquetHandler, Table};
+
+    /// A [`SyncEngine`] with a [`FixedClock`]
+    struct FixedClockEngine {
+        inner: SyncEngine,
+        clock: Arc<FixedClock>,
+    }
+
+    impl Engine for FixedClockEngine {
+        fn get_expression_handler(&self) -> Arc<dyn ExpressionHandler> {
+            self.inner.get_expression_handler()
+        }
+        fn get_file_system_client(&self) -> Arc<dyn FileSystemClient> {
+            self.inner.get_file_system_client()
+        }
+        fn get_json_handler(&self) -> Arc<dyn JsonHandler> {
+            self.inner.get_json_handler()
+        }
+        fn get_parquet_handler(&self) -> Arc<dyn ParquetHandler> {
+            self.inner.get_parquet_handler()
+        }
+        fn get_clock(&self) -> Arc<dyn Clock> {
+            self.clock.clone()
+        }
+    }
+
+    #[test]
+    fn test_fixed_clock() {
+        let path =
+            std::fs::canonicalize(PathBuf::from("./tests/data/table-with-dv-small/")).unwrap();
+        let table = Table::new(url::Url::from_directory_path(path).unwrap());
+        let engine = FixedClockEngine {
+            inner: SyncEngine::new(),
+            clock: Arc::new(FixedClock::new().with_commit_timestamp(1, 1000)),
+        };
+        let snapshot = table.snapshot(&engine, Some(1)).unwrap();
+        assert_eq!(snapshot.timestamp(&engine).unwrap(), 1000);
+
+        // commits without a fixed timestamp use the modification time of their file
+        let snapshot = table.snapshot(&engine, Some(0)).unwrap();
+        let default_engine = SyncEngine::new();
+        assert_eq!(
+            snapshot.timestamp(&engine).unwrap(),
+            snapshot.timestamp(&default_engine).unwrap()
+        );
+    }
+}
@@ -1,15 +1,18 @@
 //! Code to handle column mapping, including modes and schema transforms
+use std::collections::HashMap;
 use std::str::FromStr;
 
 use serde::{Deserialize, Serialize};
 
+use crate::schema::{ColumnMetadataKey, DataType, MetadataValue, StructField, StructType};
 use crate::{DeltaResult, Error};
 
 /// Modes of column mapping a table can be in
-#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq)]
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq, Default)]
 #[serde(rename_all = "camelCase")]
 pub enum ColumnMappingMode {
     /// No column mapping is applied
+    #[default]
     None,
     /// Columns are mapped by their field_id in parquet
     Id,
@@ -41,12 +44,6 @@ impl FromStr for ColumnMappingMode {
     }
 }
 
-impl Default for ColumnMappingMode {
-    fn default() -> Self {
-        Self::None
-    }
-}
-
 impl AsRef<str> for ColumnMappingMode {
     fn as_ref(&self) -> &str {
         match self {
@@ -56,3 +53,273 @@ impl AsRef<str> for ColumnMappingMode {
         }
     }
 }
+
+/// The table properties that enable iceberg compatibility, each of which requires column mapping
+const ICEBERG_COMPAT_KEYS: [&str; 2] =
+    ["delta.enableIcebergCompatV1", "delta.enableIcebergCompatV2"];
+
+/// Whether the columns of the parquet files of a table are matched by their field ids rather than
+/// their physical names. This is the case in [`ColumnMappingMode::Id`], and for column-mapped tables
+/// with iceberg compatibility enabled, since parquet field ids are the source of truth for iceberg.
+pub(crate) fn matches_parquet_field_ids(
+    mode: ColumnMappingMode,
+    configuration: &HashMap<String, String>,
+) -> bool {
+    match mode {
+        ColumnMappingMode::None => false,
+        ColumnMappingMode::Id => true,
+        ColumnMappingMode::Name => ICEBERG_COMPAT_KEYS.iter().any(|key| {
+            configuration
+                .get(*key)
+                .is_some_and(|value| value.eq_ignore_ascii_case("true"))
+        }),
+    }
+}
+
+/// Set the [`ColumnMetadataKey::ParquetFieldId`] of a (physical, top-level) field to its column
+/// mapping id, so that the parquet handler matches it by field id. Fields without an id are left
+/// to be matched by name.
+pub(crate) fn with_parquet_field_id(mut field: StructField) -> StructField {
+    if let Some(MetadataValue::Number(id)) =
+        field.get_config_value(&ColumnMetadataKey::ColumnMappingId)
+    {
+        let id = MetadataValue::Number(*id);
+        field
+            .metadata
+            .insert(ColumnMetadataKey::ParquetFieldId.as_ref().to_string(), id);
+    }
+    field
+}
+
+/// How a logical column of a column-mapped table is found in its parquet files
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PhysicalColumn {
+    /// The column has this (physical) name in the parquet files. Used in
+    /// [`ColumnMappingMode::Name`].
+    Name(String),
+    /// The column has this field id in the parquet files. Used in [`ColumnMappingMode::Id`].
+    Id(i32),
+}
+
+/// The mapping from the logical columns of a column-mapped table to the physical columns in its
+/// parquet files. Connectors that read data files with their own reader (rather than the kernel's
+/// [`crate::ParquetHandler`]) need this to build their read request. Get one from
+/// [`crate::snapshot::Snapshot::column_mapping`].
+///
+/// Each column is identified by its path of logical field names from the root of the table schema.
+/// Fields nested in arrays and maps are included under the path of the containing column, e.g. the
+/// field `b` in a column `a: array<struct<b: int>>` has the path `["a", "b"]`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColumnMapping {
+    mode: ColumnMappingMode,
+    columns: Vec<(Vec<String>, PhysicalColumn)>,
+}
+
+impl ColumnMapping {
+    /// Build the mapping for `schema` in `mode`. Returns `None` if the mode is
+    /// [`ColumnMappingMode::None`], and an error if any field lacks the metadata `mode` requires.
+    pub(crate) fn try_new(
+        schema: &StructType,
+        mode: ColumnMappingMode,
+    ) -> DeltaResult<Option<Self>> {
+        if mode == ColumnMappingMode::None {
+            return Ok(None);
+        }
+        let mut columns = vec![];
+        collect_columns(schema, mode, &mut vec![], &mut columns)?;
+        Ok(Some(Self { mode, columns }))
+    }
+
+    /// The column mapping mode of the table
+    pub fn mode(&self) -> ColumnMappingMode {
+        self.mode
+    }
+
+    /// Get the physical column for the logical column at `path`, if there is such a column
+    pub fn physical_column(&self, path: &[impl AsRef<str>]) -> Option<&PhysicalColumn> {
+        self.columns
+            .iter()
+            .find(|(logical_path, _)| {
+                logical_path.len() == path.len()
+                    && logical_path
+                        .iter()
+                        .zip(path)
+                        .all(|(logical, requested)| logical == requested.as_ref())
+            })
+            .map(|(_, physical)| physical)
+    }
+
+    /// Iterate over the logical path and physical column of every (possibly nested) column of the
+    /// table, in schema order. Parents come before their children.
+    pub fn columns(&self) -> impl Iterator<Item = (&[String], &PhysicalColumn)> {
+        self.columns
+            .iter()
+            .map(|(path, physical)| (path.as_slice(), physical))
+    }
+}
+
+fn collect_columns(
+    schema: &StructType,
+    mode: ColumnMappingMode,
+    path: &mut Vec<String>,
+    columns: &mut Vec<(Vec<String>, PhysicalColumn)>,
+) -> DeltaResult<()> {
+    for field in schema.fields() {
+        path.push(field.name().clone());
+        let physical = match mode {
+            ColumnMappingMode::Id => {
+                match field.get_config_value(&ColumnMetadataKey::ColumnMappingId) {
+                    Some(MetadataValue::Number(id)) => PhysicalColumn::Id(*id),
+                    invalid => {
+                        return Err(Error::generic(format!(
+                            "Missing or invalid {} for column {}: {invalid:?}",
+                            ColumnMetadataKey::ColumnMappingId.as_ref(),
+                            path.join("."),
+                        )))
+                    }
+                }
+            }
+            _ => match field.physical_name(mode) {
+                Ok(name) => PhysicalColumn::Name(name.to_string()),
+                Err(_) => {
+                    return Err(Error::generic(format!(
+                        "Missing or invalid {} for column {} in column mapping mode {}",
+                        ColumnMetadataKey::ColumnMappingPhysicalName.as_ref(),
+                        path.join("."),
+                        mode.as_ref(),
+                    )))
+                }
+            },
+        };
+        columns.push((path.clone(), physical));
+        collect_nested_columns(field.data_type(), mode, path, columns)?;
+        path.pop();
+    }
+    Ok(())
+}
+
+// structs nested in arrays and maps are mapped too, so look through those
+fn collect_nested_columns(
+    data_type: &DataType,
+    mode: ColumnMappingMode,
+    path: &mut Vec<String>,
+    columns: &mut Vec<(Vec<String>, PhysicalColumn)>,
+) -> DeltaResult<()> {
+    match data_type {
+        DataType::Struct(schema) => collect_columns(schema, mode, path, columns),
+        DataType::Array(array) => collect_nested_columns(array.element_type(), mode, path, columns),
+        DataType::Map(map) => {
+            collect_nested_columns(map.key_type(), mode, path, columns)?;
+            collect_nested_columns(map.value_type(), mode, path, columns)
+        }
+        DataType::Primitive(_) => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mapped_schema() -> StructType {
+        serde_json::from_str(
+            r#"{
+                "type": "struct",
+                "fields": [
+                    {
+                        "name": "a",
+                        "type": "integer",
+                        "nullable": true,
+                        "metadata": {
+                            "delta.columnMapping.id": 1,
+                            "delta.columnMapping.physicalName": "col-a"
+                        }
+                    },
+                    {
+                        "name": "b",
+                        "type": {
+                            "type": "array",
+                            "elementType": {
+                                "type": "struct",
+                                "fields": [{
+                                    "name": "c",
+                                    "type": "string",
+                                    "nullable": true,
+                                    "metadata": {
+                                        "delta.columnMapping.id": 3,
+                                        "delta.columnMapping.physicalName": "col-c"
+                                    }
+                                }]
+                            },
+                            "containsNull": true
+                        },
+                        "nullable": true,
+                        "metadata": {
+                            "delta.columnMapping.id": 2,
+                            "delta.columnMapping.physicalName": "col-b"
+                        }
+                    }
+                ]
+            }"#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_column_mapping() {
+        let schema = mapped_schema();
+        assert_eq!(
+            ColumnMapping::try_new(&schema, ColumnMappingMode::None).unwrap(),
+            None
+        );
+
+        let mapping = ColumnMapping::try_new(&schema, ColumnMappingMode::Name)
+            .unwrap()
+            .unwrap();
+        assert_eq!(mapping.mode(), ColumnMappingMode::Name);
+        let columns: Vec<_> = mapping.columns().collect();
+        assert_eq!(
+            columns,
+            [
+                (
+                    &["a".to_string()][..],
+                    &PhysicalColumn::Name("col-a".into())
+                ),
+                (
+                    &["b".to_string()][..],
+                    &PhysicalColumn::Name("col-b".into())
+                ),
+                (
+                    &["b".to_string(), "c".to_string()][..],
+                    &PhysicalColumn::Name("col-c".into())
+                ),
+            ]
+        );
+
+        let mapping = ColumnMapping::try_new(&schema, ColumnMappingMode::Id)
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            mapping.physical_column(&["b", "c"]),
+            Some(&PhysicalColumn::Id(3))
+        );
+        assert_eq!(
+            mapping.physical_column(&["a"]),
+            Some(&PhysicalColumn::Id(1))
+        );
+        assert_eq!(mapping.physical_column(&["c"]), None);
+    }
+
+    #[test]
+    fn test_column_mapping_missing_metadata() {
+        let schema = StructType::new(vec![crate::schema::StructField::new(
+            "a",
+            DataType::INTEGER,
+            true,
+        )]);
+        for mode in [ColumnMappingMode::Name, ColumnMappingMode::Id] {
+            let err = ColumnMapping::try_new(&schema, mode).unwrap_err();
+            // the error names the column that lacks the metadata
+            assert!(err.to_string().contains("for column a"), "{err}");
+        }
+    }
+}
@@ -112,6 +112,7 @@ impl TryFrom<&DataType> for ArrowDataType {
                     PrimitiveType::TimestampNtz => {
                         Ok(ArrowDataType::Timestamp(TimeUnit::Microsecond, None))
                     }
+                    PrimitiveType::Void => Ok(ArrowDataType::Null),
                 }
             }
             DataType::Struct(s) => Ok(ArrowDataType::Struct(
@@ -190,6 +191,7 @@ impl TryFrom<&ArrowDataType> for DataType {
                 DataType::decimal(*p, *s as u8)
                     .map_err(|e| ArrowError::from_external_error(e.into()))
             }
+            ArrowDataType::Null => Ok(DataType::Primitive(PrimitiveType::Void)),
             ArrowDataType::Date32 => Ok(DataType::Primitive(PrimitiveType::Date)),
             ArrowDataType::Date64 => Ok(DataType::Primitive(PrimitiveType::Date)),
             ArrowDataType::Timestamp(TimeUnit::Microsecond, None) => {
@@ -160,8 +160,8 @@ impl ArrowEngineData {
     /// # Arguments
     ///
     /// * `out_col_array` - the vec that leaf values will be pushed onto. it is passed as an arg to
-    /// make the recursion below easier. if we returned a [`Vec`] we would have to `extend` it each
-    /// time we encountered a struct and made the recursive call.
+    ///   make the recursion below easier. if we returned a [`Vec`] we would have to `extend` it
+    ///   each time we encountered a struct and made the recursive call.
     /// * `schema` - the schema to extract getters for
     pub fn extract_columns<'a>(
         &'a self,
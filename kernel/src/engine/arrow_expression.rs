@@ -1,25 +1,38 @@
 //! Expression handling based on arrow-rs compute kernels.
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 
-use arrow_arith::boolean::{and_kleene, is_null, not, or_kleene};
+use arrow_arith::boolean::{and_kleene, is_not_null, is_null, not, or_kleene};
 use arrow_arith::numeric::{add, div, mul, sub};
 use arrow_array::cast::AsArray;
+use arrow_array::types::IntervalMonthDayNanoType;
 use arrow_array::{
-    Array, ArrayRef, BinaryArray, BooleanArray, Date32Array, Datum, Decimal128Array, Float32Array,
-    Float64Array, Int16Array, Int32Array, Int64Array, Int8Array, ListArray, RecordBatch,
-    StringArray, StructArray, TimestampMicrosecondArray,
+    new_null_array, Array, ArrayRef, BinaryArray, BooleanArray, Date32Array, Datum,
+    Decimal128Array, Float32Array, Float64Array, Int16Array, Int32Array, Int64Array, Int8Array,
+    IntervalMonthDayNanoArray, ListArray, MapArray, NullArray, RecordBatch, StringArray,
+    StructArray, TimestampMicrosecondArray, UInt32Array, UInt64Array,
 };
+use arrow_cast::{cast_with_options, CastOptions};
 use arrow_ord::cmp::{distinct, eq, gt, gt_eq, lt, lt_eq, neq};
+use arrow_row::{RowConverter, SortField};
 use arrow_schema::{
     ArrowError, DataType as ArrowDataType, Field as ArrowField, Fields, Schema as ArrowSchema,
+    TimeUnit,
 };
+use arrow_select::filter::prep_null_mask_filter;
+use arrow_select::nullif::nullif;
+use arrow_select::take::take;
+use arrow_select::zip::zip;
 use itertools::Itertools;
+use regex::Regex;
 
 use super::arrow_conversion::LIST_ARRAY_ROOT;
 use crate::engine::arrow_data::ArrowEngineData;
 use crate::error::{DeltaResult, Error};
-use crate::expressions::{BinaryOperator, Expression, Scalar, UnaryOperator, VariadicOperator};
-use crate::schema::{DataType, PrimitiveType, SchemaRef};
+use crate::expressions::{
+    BinaryOperator, ColumnName, Expression, Scalar, UnaryOperator, VariadicOperator,
+};
+use crate::schema::{DataType, PrimitiveType, SchemaRef, StructField, StructType};
 use crate::utils::require;
 use crate::{EngineData, ExpressionEvaluator, ExpressionHandler};
 
@@ -31,6 +44,427 @@ fn downcast_to_bool(arr: &dyn Array) -> DeltaResult<&BooleanArray> {
         .ok_or(Error::generic("expected boolean array"))
 }
 
+/// A column that is null in every row may come back with arrow's `Null` type (e.g. when its type
+/// couldn't be inferred), which the arrow compute kernels reject. Replace such an array with an
+/// all-null array of `data_type`, so it behaves like any other null input.
+fn coerce_null_array(arr: ArrayRef, data_type: &ArrowDataType) -> ArrayRef {
+    if arr.data_type() == &ArrowDataType::Null && data_type != &ArrowDataType::Null {
+        new_null_array(data_type, arr.len())
+    } else {
+        arr
+    }
+}
+
+/// Coerce `arr` and `literal_arr`, the array of the non-null `literal` that `arr` is an operand
+/// with, to a common type: their common supertype (see [`common_supertype`]), so that neither side
+/// is narrowed, e.g. a double column compared with an integer literal is compared as doubles. A
+/// string column, which has no common supertype with other types, is parsed as the type of the
+/// literal instead, e.g. to compare it with a timestamp. The cast fails for values that can't be
+/// represented in the common type, rather than turning them into nulls. A dictionary-encoded `arr`
+/// whose values already have the literal's type is left encoded (see [`keeps_dictionary`]).
+fn coerce_with_literal(
+    arr: ArrayRef,
+    literal_arr: ArrayRef,
+    literal: &Scalar,
+) -> DeltaResult<(ArrayRef, ArrayRef)> {
+    let literal_type = literal_arr.data_type();
+    match arr.data_type() {
+        ArrowDataType::Null => return Ok((arr, literal_arr)),
+        ArrowDataType::Dictionary(_, value_type) if value_type.as_ref() == literal_type => {
+            return Ok((arr, literal_arr))
+        }
+        data_type if data_type == literal_type => return Ok((arr, literal_arr)),
+        _ => {}
+    }
+    let cannot_coerce = |err: &dyn std::fmt::Display| {
+        Error::generic(format!(
+            "Cannot coerce {} to the type of literal {literal}: {err}",
+            arr.data_type()
+        ))
+    };
+    match common_supertype(arr.data_type(), literal_type) {
+        Some(data_type) => Ok((
+            cast_to_supertype(&arr, &data_type).map_err(|err| cannot_coerce(&err))?,
+            cast_to_supertype(&literal_arr, &data_type).map_err(|err| cannot_coerce(&err))?,
+        )),
+        None if matches!(
+            arr.data_type(),
+            ArrowDataType::Utf8 | ArrowDataType::LargeUtf8
+        ) =>
+        {
+            let options = CastOptions {
+                safe: false,
+                ..Default::default()
+            };
+            let arr = cast_with_options(&arr, literal_type, &options)
+                .map_err(|err| cannot_coerce(&err))?;
+            Ok((arr, literal_arr))
+        }
+        None => Err(cannot_coerce(&"the types have no common supertype")),
+    }
+}
+
+/// Cast `arr` to `data_type`, a supertype of its type (see [`common_supertype`])
+fn cast_to_supertype(arr: &ArrayRef, data_type: &ArrowDataType) -> Result<ArrayRef, ArrowError> {
+    let options = CastOptions {
+        safe: false,
+        ..Default::default()
+    };
+    match arr.data_type() {
+        arr_type if arr_type == data_type => Ok(arr.clone()),
+        // arrow can only cast dates to timestamps without a timezone, which are UTC here
+        ArrowDataType::Date32 if matches!(data_type, ArrowDataType::Timestamp(..)) => {
+            let ntz_type = ArrowDataType::Timestamp(TimeUnit::Microsecond, None);
+            cast_with_options(arr, &ntz_type, &options)
+                .and_then(|arr| cast_with_options(&arr, data_type, &options))
+        }
+        _ => cast_with_options(arr, data_type, &options),
+    }
+}
+
+/// Whether comparing values of `left` and `right` compares decimals of different types, i.e. two
+/// decimals that differ in precision or scale, or a decimal and an integer
+fn is_decimal_comparison(left: &ArrowDataType, right: &ArrowDataType) -> bool {
+    use ArrowDataType::*;
+    let is_integer = |data_type: &ArrowDataType| matches!(data_type, Int8 | Int16 | Int32 | Int64);
+    left != right
+        && match (left, right) {
+            (Decimal128(..), Decimal128(..)) => true,
+            (Decimal128(..), other) | (other, Decimal128(..)) => is_integer(other),
+            _ => false,
+        }
+}
+
+/// Coerce the decimal (or integer) arrays `left` and `right` to their common decimal supertype,
+/// so their values can be compared exactly. Fails if a value doesn't fit in it, which can only
+/// happen when the supertype would need more than the maximum precision of 38.
+fn coerce_to_common_decimal(left: ArrayRef, right: ArrayRef) -> DeltaResult<(ArrayRef, ArrayRef)> {
+    let data_type = common_supertype(left.data_type(), right.data_type()).ok_or_else(|| {
+        Error::generic(format!(
+            "Cannot compare {} and {}",
+            left.data_type(),
+            right.data_type()
+        ))
+    })?;
+    let options = CastOptions {
+        safe: false,
+        ..Default::default()
+    };
+    let cast = |arr: ArrayRef| -> DeltaResult<ArrayRef> {
+        if arr.data_type() == &data_type {
+            return Ok(arr);
+        }
+        cast_with_options(&arr, &data_type, &options).map_err(|err| {
+            Error::generic(format!(
+                "Cannot coerce {} to {data_type} to compare it: {err}",
+                arr.data_type()
+            ))
+        })
+    };
+    Ok((cast(left)?, cast(right)?))
+}
+
+/// The common supertype of `left` and `right`, which the operands of GREATEST and LEAST are
+/// compared as: the wider of two numeric types (where a decimal and an integer or another decimal
+/// widen to a decimal wide enough for both, and a decimal and a float to a double), a timestamp for
+/// a date and a timestamp, or the type of an operand with the other operand's type or [`Null`].
+/// Returns `None` if the types have no common supertype.
+///
+/// [`Null`]: ArrowDataType::Null
+fn common_supertype(left: &ArrowDataType, right: &ArrowDataType) -> Option<ArrowDataType> {
+    use ArrowDataType::*;
+    fn numeric_rank(data_type: &ArrowDataType) -> Option<u8> {
+        match data_type {
+            Int8 => Some(1),
+            Int16 => Some(2),
+            Int32 => Some(3),
+            Int64 => Some(4),
+            Float32 => Some(5),
+            Float64 => Some(6),
+            _ => None,
+        }
+    }
+    // the precision and scale of a decimal that can hold every value of an integer or decimal type
+    fn decimal_bounds(data_type: &ArrowDataType) -> Option<(i16, i16)> {
+        match data_type {
+            Int8 => Some((3, 0)),
+            Int16 => Some((5, 0)),
+            Int32 => Some((10, 0)),
+            Int64 => Some((20, 0)),
+            Decimal128(precision, scale) => Some((*precision as i16, *scale as i16)),
+            _ => None,
+        }
+    }
+    match (left, right) {
+        _ if left == right => Some(left.clone()),
+        (Null, other) | (other, Null) => Some(other.clone()),
+        (Timestamp(TimeUnit::Microsecond, Some(_)), Timestamp(TimeUnit::Microsecond, Some(_))) => {
+            // both are UTC timestamps, which arrow may spell differently
+            Some(left.clone())
+        }
+        (Date32, timestamp @ Timestamp(TimeUnit::Microsecond, _))
+        | (timestamp @ Timestamp(TimeUnit::Microsecond, _), Date32) => Some(timestamp.clone()),
+        (Decimal128(..), Float32 | Float64) | (Float32 | Float64, Decimal128(..)) => Some(Float64),
+        (Decimal128(..), _) | (_, Decimal128(..)) => {
+            let (left_precision, left_scale) = decimal_bounds(left)?;
+            let (right_precision, right_scale) = decimal_bounds(right)?;
+            let scale = left_scale.max(right_scale);
+            let integral_digits = (left_precision - left_scale).max(right_precision - right_scale);
+            let precision = (integral_digits + scale).min(38);
+            Some(Decimal128(precision as u8, scale as i8))
+        }
+        _ => {
+            let (left_rank, right_rank) = (numeric_rank(left)?, numeric_rank(right)?);
+            Some(match left_rank >= right_rank {
+                true => left.clone(),
+                false => right.clone(),
+            })
+        }
+    }
+}
+
+/// Convert `value`, from the list of an IN expression, to a single value array of `data_type`, the
+/// type of the expression the value is looked up for. Fails if the value isn't representable in
+/// that type.
+fn in_list_value_array(value: &Scalar, data_type: &ArrowDataType) -> DeltaResult<ArrayRef> {
+    let arr = value.to_array(1)?;
+    if arr.data_type() == data_type {
+        return Ok(arr);
+    }
+    let options = CastOptions {
+        safe: false,
+        ..Default::default()
+    };
+    cast_with_options(&arr, data_type, &options).map_err(|err| {
+        Error::generic(format!(
+            "Cannot coerce {value} in an IN list to {data_type}: {err}"
+        ))
+    })
+}
+
+/// Whether each value of `target` is in `list`, with the SQL semantics for nulls: a null target
+/// is null (unless the list is empty), and so is one that isn't in a list containing a null. The
+/// values are looked up in a set of the (row-encoded) list values, so this takes linear time in
+/// the lengths of both.
+fn in_list(target: &ArrayRef, list: &[Scalar]) -> DeltaResult<BooleanArray> {
+    if list.is_empty() {
+        return Ok(BooleanArray::from(vec![false; target.len()]));
+    }
+    let values: Vec<_> = list
+        .iter()
+        .map(|value| in_list_value_array(value, target.data_type()))
+        .try_collect()?;
+    let converter = RowConverter::new(vec![SortField::new(target.data_type().clone())])?;
+    let mut value_rows = converter.empty_rows(values.len(), 0);
+    let mut list_has_null = false;
+    for value in &values {
+        list_has_null |= value.is_null(0);
+        if value.is_valid(0) {
+            converter.append(&mut value_rows, std::slice::from_ref(value))?;
+        }
+    }
+    let value_rows: HashSet<_> = value_rows.iter().collect();
+    let target_rows = converter.convert_columns(std::slice::from_ref(target))?;
+    Ok(target_rows
+        .iter()
+        .enumerate()
+        .map(|(i, row)| {
+            if target.is_null(i) {
+                None
+            } else if value_rows.contains(&row) {
+                Some(true)
+            } else {
+                (!list_has_null).then_some(false)
+            }
+        })
+        .collect())
+}
+
+/// The field of the (possibly nested) column `name` of `schema`, if there is one
+fn column_field<'a>(name: &ColumnName, schema: &'a StructType) -> Option<&'a StructField> {
+    let mut path = name.path().iter();
+    let mut field = path.next().and_then(|name| schema.field(name));
+    for name in path {
+        field = match field.map(StructField::data_type) {
+            Some(DataType::Struct(inner)) => inner.field(name),
+            _ => None,
+        };
+    }
+    field
+}
+
+/// Check that every column that `expression` references is in `schema`, and that the path of
+/// every nested one (e.g. `a.b`) resolves in it, i.e. that each step of it is a field of the
+/// struct the path leads to. This way an evaluator of an expression referencing a missing column
+/// can't be created, instead of failing when it is used.
+fn validate_columns(expression: &Expression, schema: &StructType) -> DeltaResult<()> {
+    for name in expression.references().into_iter().sorted() {
+        let mut path = name.path().iter();
+        let Some(mut field) = path.next().and_then(|step| schema.field(step)) else {
+            return Err(Error::missing_column(format!("Column {name} not found")));
+        };
+        for step in path {
+            let DataType::Struct(inner) = field.data_type() else {
+                return Err(Error::missing_column(format!(
+                    "Column {name} not found: {} is not a struct",
+                    field.name()
+                )));
+            };
+            field = inner.field(step).ok_or_else(|| {
+                Error::missing_column(format!(
+                    "Column {name} not found: {} has no field {step}",
+                    field.name()
+                ))
+            })?;
+        }
+    }
+    Ok(())
+}
+
+/// Check that the literals of `expression` can be used with the columns of `schema` they are
+/// operands with, so that an invalid expression is reported before evaluation:
+/// - the values of an IN expression on a column must be coercible to the type of the column
+/// - a column that is an operand of a binary operation with a literal must have a common
+///   supertype with the literal (see [`common_supertype`]), or be a string column, which is
+///   parsed as the type of the literal. E.g. an integer column can be compared with a double
+///   literal and a string column with a timestamp literal, but a boolean column can't be
+///   compared with a date literal, nor a double column with a string one
+fn validate_literals(expression: &Expression, schema: &StructType) -> DeltaResult<()> {
+    for expr in expression.walk() {
+        match expr {
+            Expression::In { target, list } => {
+                let Expression::Column(name) = target.as_ref() else {
+                    continue;
+                };
+                // a missing column was reported by validate_columns
+                if let Some(field) = column_field(name, schema) {
+                    let data_type = ArrowDataType::try_from(field.data_type())?;
+                    for value in list {
+                        in_list_value_array(value, &data_type)?;
+                    }
+                }
+            }
+            Expression::BinaryOperation { left, right, .. } => {
+                let (name, literal) = match (left.as_ref(), right.as_ref()) {
+                    (Expression::Column(name), Expression::Literal(literal))
+                    | (Expression::Literal(literal), Expression::Column(name)) => (name, literal),
+                    _ => continue,
+                };
+                let Some(field) = column_field(name, schema) else {
+                    continue;
+                };
+                if literal.is_null() {
+                    continue;
+                }
+                let column_type = ArrowDataType::try_from(field.data_type())?;
+                let literal_type = ArrowDataType::try_from(&literal.data_type())?;
+                // like `coerce_with_literal`, which only widens the column, or parses a string one
+                require!(
+                    common_supertype(&column_type, &literal_type).is_some()
+                        || matches!(column_type, ArrowDataType::Utf8 | ArrowDataType::LargeUtf8),
+                    Error::generic(format!(
+                        "Column {name} of type {} can't be used with literal {literal} of type {}",
+                        field.data_type(),
+                        literal.data_type()
+                    ))
+                );
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// Check that the column operands of every NULLIF and IFNULL in `expression` have a common
+/// supertype (see [`common_supertype`]), which the values of both are cast to, since the result
+/// takes values from both. Literal operands are checked by [`validate_literals`].
+fn validate_null_if_operands(expression: &Expression, schema: &StructType) -> DeltaResult<()> {
+    for expr in expression.walk() {
+        let Expression::BinaryOperation {
+            op: op @ (BinaryOperator::NullIf | BinaryOperator::IfNull),
+            left,
+            right,
+        } = expr
+        else {
+            continue;
+        };
+        let (Expression::Column(left), Expression::Column(right)) = (left.as_ref(), right.as_ref())
+        else {
+            continue;
+        };
+        // a missing column was reported by validate_columns
+        let (Some(left_field), Some(right_field)) =
+            (column_field(left, schema), column_field(right, schema))
+        else {
+            continue;
+        };
+        let left_type = ArrowDataType::try_from(left_field.data_type())?;
+        let right_type = ArrowDataType::try_from(right_field.data_type())?;
+        require!(
+            common_supertype(&left_type, &right_type).is_some(),
+            Error::generic(format!(
+                "Cannot evaluate {op} on column {left} of type {} and column {right} of type {}",
+                left_field.data_type(),
+                right_field.data_type()
+            ))
+        );
+    }
+    Ok(())
+}
+
+/// The compiled regular expressions of the RLIKE expressions of an expression, by pattern
+type Patterns = HashMap<String, Regex>;
+
+/// Compile the pattern of every RLIKE expression in `expression`, so that they are compiled once
+/// per evaluator rather than once per evaluation. Fails if a pattern isn't a valid regex.
+fn compile_patterns(expression: &Expression) -> DeltaResult<Patterns> {
+    let mut patterns = Patterns::new();
+    for expr in expression.walk() {
+        if let Expression::RLike { pattern, .. } = expr {
+            if !patterns.contains_key(pattern) {
+                let regex = Regex::new(pattern).map_err(|err| {
+                    Error::generic(format!("Invalid regex '{pattern}' in RLIKE: {err}"))
+                })?;
+                patterns.insert(pattern.clone(), regex);
+            }
+        }
+    }
+    Ok(patterns)
+}
+
+/// Look up the key in each row of `keys` among the entries of the same row of `map`, returning
+/// the matching values. The result is null where the map is null or doesn't contain the key.
+fn lookup_map_values(map: &MapArray, keys: &dyn Array) -> DeltaResult<ArrayRef> {
+    let offsets = map.value_offsets();
+    // the map may be a slice, so only compare the entries of its rows
+    let first_entry = offsets[0] as usize;
+    let num_entries = offsets[map.len()] as usize - first_entry;
+    let entry_rows: UInt32Array = (0..map.len())
+        .flat_map(|row| {
+            let row_entries = (offsets[row + 1] - offsets[row]) as usize;
+            std::iter::repeat_n(row as u32, row_entries)
+        })
+        .collect();
+    // compare the key of every entry with the key being looked up in its row
+    let entry_keys = map.keys().slice(first_entry, num_entries);
+    let matches = eq(&entry_keys, &take(keys, &entry_rows, None)?)?;
+    let value_indices: UInt32Array = (0..map.len())
+        .map(|row| {
+            if map.is_null(row) {
+                return None;
+            }
+            let (start, end) = (offsets[row] as usize, offsets[row + 1] as usize);
+            (start..end)
+                .find(|&entry| {
+                    let entry = entry - first_entry;
+                    matches.is_valid(entry) && matches.value(entry)
+                })
+                .map(|entry| entry as u32)
+        })
+        .collect();
+    Ok(take(map.values(), &value_indices, None)?)
+}
+
 impl Scalar {
     /// Convert scalar to arrow array.
     pub fn to_array(&self, num_rows: usize) -> DeltaResult<ArrayRef> {
@@ -85,6 +519,7 @@ impl Scalar {
                     }
                     PrimitiveType::Date => Arc::new(Date32Array::new_null(num_rows)),
                     PrimitiveType::Binary => Arc::new(BinaryArray::new_null(num_rows)),
+                    PrimitiveType::Void => Arc::new(NullArray::new(num_rows)),
                     PrimitiveType::Decimal(precision, scale) => Arc::new(
                         Decimal128Array::new_null(num_rows)
                             .with_precision_and_scale(*precision, *scale as i8)?,
@@ -99,7 +534,7 @@ impl Scalar {
                         ArrowField::new(LIST_ARRAY_ROOT, t.element_type().try_into()?, true);
                     Arc::new(ListArray::new_null(Arc::new(field), num_rows))
                 }
-                DataType::Map { .. } => unimplemented!(),
+                DataType::Map(_) => new_null_array(&data_type.try_into()?, num_rows),
             },
         };
         Ok(arr)
@@ -110,55 +545,28 @@ fn wrap_comparison_result(arr: BooleanArray) -> ArrayRef {
     Arc::new(arr) as Arc<dyn Array>
 }
 
-trait ProvidesColumnByName {
-    fn column_by_name(&self, name: &str) -> Option<&Arc<dyn Array>>;
-}
-
-impl ProvidesColumnByName for RecordBatch {
-    fn column_by_name(&self, name: &str) -> Option<&Arc<dyn Array>> {
-        self.column_by_name(name)
-    }
-}
-
-impl ProvidesColumnByName for StructArray {
-    fn column_by_name(&self, name: &str) -> Option<&Arc<dyn Array>> {
-        self.column_by_name(name)
-    }
-}
-
-fn extract_column<'array, 'path>(
-    array: &'array dyn ProvidesColumnByName,
-    path_step: &str,
-    remaining_path_steps: &mut impl Iterator<Item = &'path str>,
-) -> Result<&'array Arc<dyn Array>, ArrowError> {
-    let child = array
-        .column_by_name(path_step)
-        .ok_or(ArrowError::SchemaError(format!(
-            "No such field: {}",
-            path_step,
-        )))?;
-    if let Some(next_path_step) = remaining_path_steps.next() {
-        // This is not the last path step. Drill deeper.
-        extract_column(
-            column_as_struct(path_step, &Some(child))?,
-            next_path_step,
-            remaining_path_steps,
-        )
-    } else {
-        // Last path step. Return it.
-        Ok(child)
+/// Get the (possibly nested) column `name` of `batch`. A nested column is null in the rows where
+/// a struct it is nested in is null, whatever value the struct has for it.
+fn extract_column(batch: &RecordBatch, name: &ColumnName) -> DeltaResult<ArrayRef> {
+    let mut path = name.path().iter();
+    let mut column = path
+        .next()
+        .and_then(|step| batch.column_by_name(step))
+        .ok_or_else(|| Error::missing_column(name.to_string()))?
+        .clone();
+    for step in path {
+        let struct_array = column.as_struct_opt().ok_or_else(|| {
+            Error::missing_column(format!("{name}: the parent of {step} is not a struct"))
+        })?;
+        let child = struct_array
+            .column_by_name(step)
+            .ok_or_else(|| Error::missing_column(name.to_string()))?;
+        column = match struct_array.null_count() {
+            0 => child.clone(),
+            _ => nullif(child, &is_null(struct_array)?)?,
+        };
     }
-}
-
-fn column_as_struct<'a>(
-    name: &str,
-    column: &Option<&'a Arc<dyn Array>>,
-) -> Result<&'a StructArray, ArrowError> {
-    column
-        .ok_or(ArrowError::SchemaError(format!("No such column: {}", name)))?
-        .as_any()
-        .downcast_ref::<StructArray>()
-        .ok_or(ArrowError::SchemaError(format!("{} is not a struct", name)))
+    Ok(column)
 }
 
 fn make_arrow_error(s: String) -> Error {
@@ -174,8 +582,9 @@ fn ensure_data_types(kernel_type: &DataType, arrow_type: &ArrowDataType) -> Delt
         (DataType::Primitive(_), _) if arrow_type.is_primitive() => Ok(()),
         (DataType::Primitive(PrimitiveType::Boolean), ArrowDataType::Boolean)
         | (DataType::Primitive(PrimitiveType::String), ArrowDataType::Utf8)
-        | (DataType::Primitive(PrimitiveType::Binary), ArrowDataType::Binary) => {
-            // strings, bools, and binary  aren't primitive in arrow
+        | (DataType::Primitive(PrimitiveType::Binary), ArrowDataType::Binary)
+        | (DataType::Primitive(PrimitiveType::Void), ArrowDataType::Null) => {
+            // strings, bools, binary, and nulls aren't primitive in arrow
             Ok(())
         }
         (
@@ -235,46 +644,87 @@ fn ensure_data_types(kernel_type: &DataType, arrow_type: &ArrowDataType) -> Delt
     }
 }
 
+/// Decode `arr` to its value type if it is dictionary-encoded. This only decodes the values the
+/// keys refer to, so it also works for each batch having its own dictionary.
+fn decode_dictionary(arr: ArrayRef) -> DeltaResult<ArrayRef> {
+    match arr.data_type() {
+        ArrowDataType::Dictionary(_, value_type) => Ok(cast_with_options(
+            &arr,
+            value_type,
+            &CastOptions::default(),
+        )?),
+        _ => Ok(arr),
+    }
+}
+
+/// Whether `arr`, an operand of `op`, can stay dictionary-encoded. This is the case if it is
+/// compared with `other`, a non-null literal of the dictionary's value type: arrow's comparison
+/// kernels compare with the dictionary's values by looking them up through the keys, so the
+/// values need not be decoded for every batch (each of which may have its own dictionary).
+fn keeps_dictionary(arr: &dyn Array, op: &BinaryOperator, other: &Expression) -> bool {
+    use BinaryOperator::*;
+    let is_comparison = matches!(
+        op,
+        LessThan | LessThanOrEqual | GreaterThan | GreaterThanOrEqual | Equal | NotEqual
+    );
+    match (arr.data_type(), other) {
+        (ArrowDataType::Dictionary(_, value_type), Expression::Literal(literal))
+            if is_comparison && !literal.is_null() =>
+        {
+            ArrowDataType::try_from(&literal.data_type()).is_ok_and(|t| t == **value_type)
+        }
+        _ => false,
+    }
+}
+
+/// Evaluate `expression` on `batch`. Dictionary-encoded columns are supported anywhere, but the
+/// result is never dictionary-encoded, as kernel data types have no dictionary type.
 fn evaluate_expression(
     expression: &Expression,
     batch: &RecordBatch,
     result_type: Option<&DataType>,
+    patterns: &Patterns,
+) -> DeltaResult<ArrayRef> {
+    decode_dictionary(evaluate_encoded(expression, batch, result_type, patterns)?)
+}
+
+/// Evaluate `expression` on `batch` like [`evaluate_expression`], except that the result of a
+/// column (or a comparison operand) may still be dictionary-encoded
+fn evaluate_encoded(
+    expression: &Expression,
+    batch: &RecordBatch,
+    result_type: Option<&DataType>,
+    patterns: &Patterns,
 ) -> DeltaResult<ArrayRef> {
     use BinaryOperator::*;
     use Expression::*;
 
     match (expression, result_type) {
         (Literal(scalar), _) => Ok(scalar.to_array(batch.num_rows())?),
-        (Column(name), _) => {
-            // TODO properly handle nested columns
-            // https://github.com/delta-incubator/delta-kernel-rs/issues/86
-            if name.contains('.') {
-                let mut path = name.split('.');
-                // Safety: we know that the first path step exists, because we checked for '.'
-                Ok(extract_column(batch, path.next().unwrap(), &mut path).cloned()?)
-            } else {
-                batch
-                    .column_by_name(name)
-                    .ok_or(Error::missing_column(name))
-                    .cloned()
-            }
-        }
+        (Column(name), _) => extract_column(batch, name),
         (Struct(fields), Some(DataType::Struct(schema))) => {
-            let columns = fields
-                .iter()
-                .zip(schema.fields())
-                .map(|(expr, field)| evaluate_expression(expr, batch, Some(field.data_type())));
+            let columns =
+                fields
+                    .iter()
+                    .zip(schema.fields())
+                    .map(|(expr, field)| -> DeltaResult<_> {
+                        let arr =
+                            evaluate_expression(expr, batch, Some(field.data_type()), patterns)?;
+                        Ok(coerce_null_array(arr, &field.data_type().try_into()?))
+                    });
             let output_cols: Vec<Arc<dyn Array>> = columns.try_collect()?;
             let output_fields: Vec<ArrowField> = output_cols
                 .iter()
                 .zip(schema.fields())
                 .map(|(array, input_field)| -> DeltaResult<_> {
                     ensure_data_types(input_field.data_type(), array.data_type())?;
+                    let metadata = ArrowField::try_from(input_field)?.metadata().clone();
                     Ok(ArrowField::new(
                         input_field.name(),
                         array.data_type().clone(),
                         array.is_nullable(),
-                    ))
+                    )
+                    .with_metadata(metadata))
                 })
                 .try_collect()?;
             let result = StructArray::try_new(output_fields.into(), output_cols, None)?;
@@ -284,15 +734,92 @@ fn evaluate_expression(
             "Data type is required to evaluate struct expressions",
         )),
         (UnaryOperation { op, expr }, _) => {
-            let arr = evaluate_expression(expr.as_ref(), batch, None)?;
+            let arr = evaluate_expression(expr.as_ref(), batch, None, patterns)?;
             Ok(match op {
-                UnaryOperator::Not => Arc::new(not(downcast_to_bool(&arr)?)?),
+                UnaryOperator::Not => {
+                    let arr = coerce_null_array(arr, &ArrowDataType::Boolean);
+                    Arc::new(not(downcast_to_bool(&arr)?)?)
+                }
                 UnaryOperator::IsNull => Arc::new(is_null(&arr)?),
             })
         }
         (BinaryOperation { op, left, right }, _) => {
-            let left_arr = evaluate_expression(left.as_ref(), batch, None)?;
-            let right_arr = evaluate_expression(right.as_ref(), batch, None)?;
+            let left_arr = evaluate_encoded(left.as_ref(), batch, None, patterns)?;
+            let right_arr = evaluate_encoded(right.as_ref(), batch, None, patterns)?;
+            let left_arr = match keeps_dictionary(&left_arr, op, right) {
+                true => left_arr,
+                false => decode_dictionary(left_arr)?,
+            };
+            let right_arr = match keeps_dictionary(&right_arr, op, left) {
+                true => right_arr,
+                false => decode_dictionary(right_arr)?,
+            };
+            // literals carry their type, so the other side is coerced to a type that can hold
+            // both
+            let (left_arr, right_arr) = match (left.as_ref(), right.as_ref()) {
+                // decimals of different scales are compared as a decimal that holds both exactly,
+                // since coercing one to the scale of the other would round (or overflow) it
+                _ if matches!(
+                    op,
+                    LessThan
+                        | LessThanOrEqual
+                        | GreaterThan
+                        | GreaterThanOrEqual
+                        | Equal
+                        | NotEqual
+                        | Distinct
+                ) && is_decimal_comparison(left_arr.data_type(), right_arr.data_type()) =>
+                {
+                    coerce_to_common_decimal(left_arr, right_arr)?
+                }
+                (Literal(_), Literal(_)) => (left_arr, right_arr),
+                (Literal(literal), _) if !literal.is_null() => {
+                    let (right_arr, left_arr) = coerce_with_literal(right_arr, left_arr, literal)?;
+                    (left_arr, right_arr)
+                }
+                (_, Literal(literal)) if !literal.is_null() => {
+                    coerce_with_literal(left_arr, right_arr, literal)?
+                }
+                _ => (left_arr, right_arr),
+            };
+            // an untyped all-null side takes the type of the other side
+            let left_arr = coerce_null_array(left_arr, right_arr.data_type());
+            let right_arr = coerce_null_array(right_arr, left_arr.data_type());
+            if left_arr.data_type() == &ArrowDataType::Null {
+                // both sides are untyped and all-null, so the result is too
+                return Ok(match op {
+                    Plus | Minus | Multiply | Divide | NullIf | IfNull => left_arr,
+                    Distinct => Arc::new(BooleanArray::from(vec![false; left_arr.len()])),
+                    _ => Arc::new(BooleanArray::new_null(left_arr.len())),
+                });
+            }
+
+            if matches!(op, NullIf | IfNull) {
+                // the result takes values from both sides, so they are cast to their common
+                // supertype, e.g. IFNULL of an integer and a long column is a long. A right side
+                // that is all null (e.g. a typed null literal) never contributes a value, so it
+                // can take the type of the left side.
+                let right_arr = if right_arr.null_count() == right_arr.len() {
+                    new_null_array(left_arr.data_type(), right_arr.len())
+                } else {
+                    right_arr
+                };
+                let data_type = common_supertype(left_arr.data_type(), right_arr.data_type())
+                    .ok_or_else(|| {
+                        Error::generic(format!(
+                            "Cannot evaluate {op} on {} and {}",
+                            left_arr.data_type(),
+                            right_arr.data_type()
+                        ))
+                    })?;
+                let left_arr = cast_to_supertype(&left_arr, &data_type)?;
+                let right_arr = cast_to_supertype(&right_arr, &data_type)?;
+                let result = match op {
+                    NullIf => nullif(&left_arr, &eq(&left_arr, &right_arr)?),
+                    _ => zip(&is_not_null(&left_arr)?, &left_arr, &right_arr),
+                };
+                return result.map_err(Error::generic_err);
+            }
 
             type Operation = fn(&dyn Datum, &dyn Datum) -> Result<Arc<dyn Array>, ArrowError>;
             let eval: Operation = match op {
@@ -307,6 +834,7 @@ fn evaluate_expression(
                 Equal => |l, r| eq(l, r).map(wrap_comparison_result),
                 NotEqual => |l, r| neq(l, r).map(wrap_comparison_result),
                 Distinct => |l, r| distinct(l, r).map(wrap_comparison_result),
+                NullIf | IfNull => unreachable!("handled above"),
             };
 
             eval(&left_arr, &right_arr).map_err(Error::generic_err)
@@ -319,13 +847,16 @@ fn evaluate_expression(
             };
             exprs
                 .iter()
-                .map(|expr| evaluate_expression(expr, batch, result_type))
+                .map(|expr| {
+                    let arr = evaluate_expression(expr, batch, result_type, patterns)?;
+                    Ok(coerce_null_array(arr, &ArrowDataType::Boolean))
+                })
                 .reduce(|l, r| {
                     Ok(reducer(downcast_to_bool(&l?)?, downcast_to_bool(&r?)?)
                         .map(wrap_comparison_result)?)
                 })
                 .unwrap_or_else(|| {
-                    evaluate_expression(&Expression::literal(default), batch, result_type)
+                    evaluate_expression(&Expression::literal(default), batch, result_type, patterns)
                 })
         }
         (VariadicOperation { .. }, _) => {
@@ -334,6 +865,119 @@ fn evaluate_expression(
                 "Variadic {expression:?} is expected to return boolean results, got {result_type:?}"
             )))
         }
+        (AddInterval { expr, interval }, _) => {
+            let arr = evaluate_expression(expr.as_ref(), batch, None, patterns)?;
+            match arr.data_type() {
+                ArrowDataType::Date32 => require!(
+                    interval.microseconds == 0,
+                    Error::generic(format!("Cannot add {interval} to a date"))
+                ),
+                ArrowDataType::Timestamp(TimeUnit::Microsecond, _) => {}
+                data_type => {
+                    return Err(Error::generic(format!(
+                        "Cannot add {interval} to {data_type}"
+                    )))
+                }
+            }
+            let nanoseconds = interval
+                .microseconds
+                .checked_mul(1000)
+                .ok_or_else(|| Error::generic(format!("{interval} is out of range")))?;
+            let interval = IntervalMonthDayNanoArray::new_scalar(
+                IntervalMonthDayNanoType::make_value(interval.months, interval.days, nanoseconds),
+            );
+            add(&arr, &interval).map_err(Error::generic_err)
+        }
+        (Greatest(exprs) | Least(exprs), _) => {
+            let arrays: Vec<_> = exprs
+                .iter()
+                .map(|expr| evaluate_expression(expr, batch, None, patterns))
+                .try_collect()?;
+            let data_type = arrays
+                .iter()
+                .try_fold(ArrowDataType::Null, |data_type, arr| {
+                    common_supertype(&data_type, arr.data_type()).ok_or_else(|| {
+                        Error::generic(format!(
+                            "Cannot evaluate {expression} on {data_type} and {}",
+                            arr.data_type()
+                        ))
+                    })
+                })?;
+            let is_greatest = matches!(expression, Greatest(_));
+            let mut arrays = arrays
+                .into_iter()
+                .map(|arr| cast_to_supertype(&arr, &data_type).map_err(Error::generic_err));
+            let first = arrays.next().ok_or_else(|| {
+                Error::generic(format!("{expression} needs at least one operand"))
+            })??;
+            if data_type == ArrowDataType::Null {
+                // every operand is untyped and all-null, so the result is too
+                return Ok(first);
+            }
+            arrays.try_fold(first, |result, arr| {
+                let arr = arr?;
+                let is_extremum = match is_greatest {
+                    true => gt_eq(&result, &arr)?,
+                    false => lt_eq(&result, &arr)?,
+                };
+                // keep the result where it is the extremum or the operand is null, so that nulls
+                // are ignored (a null result compares as null, and so takes the operand)
+                let keep_result = or_kleene(&is_null(&arr)?, &is_extremum)?;
+                Ok(zip(&prep_null_mask_filter(&keep_result), &result, &arr)?)
+            })
+        }
+        (In { target, list }, _) => {
+            let target_arr = evaluate_encoded(target.as_ref(), batch, None, patterns)?;
+            if target_arr.data_type() == &ArrowDataType::Null {
+                // an untyped all-null target is null in every row, or never in an empty list
+                return Ok(match list.is_empty() {
+                    true => Arc::new(BooleanArray::from(vec![false; target_arr.len()])),
+                    false => Arc::new(BooleanArray::new_null(target_arr.len())),
+                });
+            }
+            // a dictionary-encoded target is compared with values of the dictionary's value type,
+            // and only its distinct values are looked up in the list
+            if let Some(dictionary) = target_arr.as_any_dictionary_opt() {
+                let values = in_list(dictionary.values(), list)?;
+                let keys = UInt64Array::from_iter_values(
+                    dictionary
+                        .normalized_keys()
+                        .into_iter()
+                        .map(|key| key as u64),
+                );
+                let result = take(&values, &keys, None)?;
+                // null keys are null targets
+                return Ok(Arc::new(nullif(&result, &is_null(dictionary.keys())?)?));
+            }
+            Ok(Arc::new(in_list(&target_arr, list)?))
+        }
+        (MapLookup { map_expr, key }, _) => {
+            let map_arr = evaluate_expression(map_expr.as_ref(), batch, None, patterns)?;
+            let map = map_arr.as_map_opt().ok_or_else(|| {
+                Error::generic(format!(
+                    "Map lookup expects a map, got {}",
+                    map_arr.data_type()
+                ))
+            })?;
+            let key_arr = evaluate_expression(key.as_ref(), batch, None, patterns)?;
+            let key_arr = coerce_null_array(key_arr, map.key_type());
+            lookup_map_values(map, &key_arr)
+        }
+        (RLike { expr, pattern }, _) => {
+            let arr = evaluate_expression(expr.as_ref(), batch, None, patterns)?;
+            let arr = coerce_null_array(arr, &ArrowDataType::Utf8);
+            let strings = arr.as_string_opt::<i32>().ok_or_else(|| {
+                Error::generic(format!("RLIKE expects a string, got {}", arr.data_type()))
+            })?;
+            let regex = patterns.get(pattern).ok_or_else(|| {
+                Error::generic(format!("RLIKE pattern '{pattern}' isn't compiled"))
+            })?;
+            let result: BooleanArray = strings
+                .iter()
+                .map(|value| value.map(|value| regex.is_match(value)))
+                .collect();
+            Ok(Arc::new(result))
+        }
     }
 }
 
@@ -346,12 +990,17 @@ impl ExpressionHandler for ArrowExpressionHandler {
         schema: SchemaRef,
         expression: Expression,
         output_type: DataType,
-    ) -> Arc<dyn ExpressionEvaluator> {
-        Arc::new(DefaultExpressionEvaluator {
+    ) -> DeltaResult<Arc<dyn ExpressionEvaluator>> {
+        validate_columns(&expression, &schema)?;
+        validate_literals(&expression, &schema)?;
+        validate_null_if_operands(&expression, &schema)?;
+        let patterns = compile_patterns(&expression)?;
+        Ok(Arc::new(DefaultExpressionEvaluator {
             input_schema: schema,
             expression: Box::new(expression),
             output_type,
-        })
+            patterns,
+        }))
     }
 }
 
@@ -360,6 +1009,9 @@ pub struct DefaultExpressionEvaluator {
     input_schema: SchemaRef,
     expression: Box<Expression>,
     output_type: DataType,
+    /// The regexes of the RLIKE expressions in the expression, compiled when the evaluator is
+    /// created
+    patterns: Patterns,
 }
 
 impl ExpressionEvaluator for DefaultExpressionEvaluator {
@@ -378,7 +1030,12 @@ impl ExpressionEvaluator for DefaultExpressionEvaluator {
         //         batch.schema()
         //     )));
         // };
-        let array_ref = evaluate_expression(&self.expression, batch, Some(&self.output_type))?;
+        let array_ref = evaluate_expression(
+            &self.expression,
+            batch,
+            Some(&self.output_type),
+            &self.patterns,
+        )?;
         let arrow_type: ArrowDataType = ArrowDataType::try_from(&self.output_type)?;
         let batch: RecordBatch = if let DataType::Struct(_) = self.output_type {
             array_ref
@@ -386,21 +1043,67 @@ impl ExpressionEvaluator for DefaultExpressionEvaluator {
                 .ok_or(Error::unexpected_column_type("Expected a struct array"))?
                 .into()
         } else {
+            let array_ref = coerce_null_array(array_ref, &arrow_type);
             let schema = ArrowSchema::new(vec![ArrowField::new("output", arrow_type, true)]);
             RecordBatch::try_new(Arc::new(schema), vec![array_ref])?
         };
         Ok(Box::new(ArrowEngineData::new(batch)))
     }
+
+    fn evaluate_into(&self, batch: &dyn EngineData, out: &mut Vec<bool>) -> DeltaResult<()> {
+        let batch = batch
+            .as_any()
+            .downcast_ref::<ArrowEngineData>()
+            .ok_or(Error::engine_data_type("ArrowEngineData"))?
+            .record_batch();
+        let array_ref = evaluate_expression(
+            &self.expression,
+            batch,
+            Some(&self.output_type),
+            &self.patterns,
+        )?;
+        out.clear();
+        if array_ref.data_type() == &ArrowDataType::Null {
+            out.resize(array_ref.len(), false);
+            return Ok(());
+        }
+        let array = array_ref
+            .as_boolean_opt()
+            .ok_or(Error::unexpected_column_type("Expected a boolean array"))?;
+        // copy the values straight out of the array, instead of wrapping it in a batch and
+        // visiting that
+        match array.nulls() {
+            Some(nulls) => out.extend(
+                array
+                    .values()
+                    .iter()
+                    .zip(nulls.iter())
+                    .map(|(value, valid)| value && valid),
+            ),
+            None => out.extend(array.values().iter()),
+        }
+        Ok(())
+    }
 }
 
 #[cfg(test)]
 mod tests {
 
     use super::*;
+    use crate::expressions::Interval;
     use arrow_array::Int32Array;
     use arrow_schema::{DataType, Field, Fields, Schema};
     use std::ops::{Add, Div, Mul, Sub};
 
+    fn evaluate_expression(
+        expression: &Expression,
+        batch: &RecordBatch,
+        result_type: Option<&crate::schema::DataType>,
+    ) -> DeltaResult<ArrayRef> {
+        let patterns = compile_patterns(expression)?;
+        super::evaluate_expression(expression, batch, result_type, &patterns)
+    }
+
     #[test]
     fn test_extract_column() {
         let schema = Schema::new(vec![Field::new("a", DataType::Int32, false)]);
@@ -433,6 +1136,82 @@ mod tests {
         assert_eq!(results.as_ref(), &values);
     }
 
+    #[test]
+    fn test_nested_column() {
+        let leaf = Field::new("zip", DataType::Int32, true);
+        let address = StructArray::new(
+            vec![leaf.clone()].into(),
+            vec![Arc::new(Int32Array::from(vec![Some(1), None, Some(3)]))],
+            // the struct of the third row is null, which hides its zip
+            Some(vec![true, true, false].into()),
+        );
+        let address_field = Field::new("address", address.data_type().clone(), true);
+        let schema = Schema::new(vec![
+            address_field,
+            Field::new("id", DataType::Int32, false),
+        ]);
+        let batch = RecordBatch::try_new(
+            Arc::new(schema.clone()),
+            vec![Arc::new(address), Arc::new(Int32Array::from(vec![1, 2, 3]))],
+        )
+        .unwrap();
+        let zip = Expression::nested_column(["address", "zip"]);
+        assert_eq!(zip, Expression::column("address.zip"));
+        let results = evaluate_expression(&zip, &batch, None).unwrap();
+        assert_eq!(
+            results.as_ref(),
+            &Int32Array::from(vec![Some(1), None, None])
+        );
+        let results = evaluate_expression(&zip.clone().gt(Expression::literal(0)), &batch, None);
+        assert_eq!(
+            results.unwrap().as_ref(),
+            &BooleanArray::from(vec![Some(true), None, None])
+        );
+
+        // the path is checked against the schema when the evaluator is created
+        let schema: SchemaRef = Arc::new((&schema).try_into().unwrap());
+        for (column, message) in [
+            ("address.zip", None),
+            ("address.city", Some("address has no field city")),
+            ("id.zip", Some("id is not a struct")),
+            ("address.zip.code", Some("zip is not a struct")),
+            ("missing", Some("Column missing not found")),
+        ] {
+            let evaluator = ArrowExpressionHandler.get_evaluator(
+                schema.clone(),
+                Expression::column(column),
+                crate::schema::DataType::INTEGER,
+            );
+            match message {
+                None => {
+                    let result = evaluator
+                        .unwrap()
+                        .evaluate(&ArrowEngineData::new(batch.clone()));
+                    assert!(result.is_ok(), "{column}");
+                }
+                Some(message) => {
+                    let err = evaluator.map(|_| ()).unwrap_err().to_string();
+                    assert!(err.contains(message), "{column}: {err}");
+                }
+            }
+        }
+
+        // a name containing a `.` can only be referenced by its path
+        let schema = Schema::new(vec![Field::new("a.b", DataType::Int32, false)]);
+        let values = Int32Array::from(vec![1, 2, 3]);
+        let batch = RecordBatch::try_new(Arc::new(schema.clone()), vec![Arc::new(values)]).unwrap();
+        let column = Expression::nested_column(["a.b"]);
+        let results = evaluate_expression(&column, &batch, None).unwrap();
+        assert_eq!(results.as_ref(), &Int32Array::from(vec![1, 2, 3]));
+        let schema: SchemaRef = Arc::new((&schema).try_into().unwrap());
+        let evaluator = ArrowExpressionHandler.get_evaluator(
+            schema,
+            Expression::column("a.b"),
+            crate::schema::DataType::INTEGER,
+        );
+        assert!(matches!(evaluator, Err(Error::MissingColumn(_))));
+    }
+
     #[test]
     fn test_binary_op_scalar() {
         let schema = Schema::new(vec![Field::new("a", DataType::Int32, false)]);
@@ -493,6 +1272,286 @@ mod tests {
         assert_eq!(results.as_ref(), expected.as_ref());
     }
 
+    #[test]
+    fn test_binary_op_coerces_to_literal_type() {
+        let schema = Schema::new(vec![
+            Field::new("ts", DataType::Utf8, false),
+            Field::new("int", DataType::Int32, false),
+        ]);
+        let batch = RecordBatch::try_new(
+            Arc::new(schema),
+            vec![
+                Arc::new(StringArray::from(vec![
+                    "2023-12-31T00:00:00Z",
+                    "2024-01-02T00:00:00Z",
+                ])),
+                Arc::new(Int32Array::from(vec![1, 3])),
+            ],
+        )
+        .unwrap();
+
+        // compared as timestamps rather than strings
+        let timestamp = Scalar::Timestamp(1704067200000000); // 2024-01-01T00:00:00Z
+        let expression = Expression::column("ts").gt(Expression::literal(timestamp.clone()));
+        let results = evaluate_expression(&expression, &batch, None).unwrap();
+        let expected = Arc::new(BooleanArray::from(vec![false, true]));
+        assert_eq!(results.as_ref(), expected.as_ref());
+        let expression = Expression::literal(timestamp).lt(Expression::column("ts"));
+        let results = evaluate_expression(&expression, &batch, None).unwrap();
+        assert_eq!(results.as_ref(), expected.as_ref());
+
+        let expression = Expression::column("int").add(Expression::literal(2i64));
+        let results = evaluate_expression(&expression, &batch, None).unwrap();
+        let expected = Arc::new(Int64Array::from(vec![3, 5]));
+        assert_eq!(results.as_ref(), expected.as_ref());
+
+        // values that can't be coerced fail the evaluation
+        let expression = Expression::column("ts").eq(Expression::literal(1));
+        assert!(evaluate_expression(&expression, &batch, None).is_err());
+    }
+
+    #[test]
+    fn test_binary_op_never_narrows_column() {
+        let schema = Schema::new(vec![
+            Field::new("double", DataType::Float64, false),
+            Field::new("long", DataType::Int64, false),
+        ]);
+        let batch = RecordBatch::try_new(
+            Arc::new(schema),
+            vec![
+                Arc::new(Float64Array::from(vec![0.5, 1.5])),
+                Arc::new(Int64Array::from(vec![1, i64::MAX])),
+            ],
+        )
+        .unwrap();
+
+        // the doubles aren't truncated to integers, so 1.5 > 1
+        let expression = Expression::column("double").gt(Expression::literal(1));
+        let results = evaluate_expression(&expression, &batch, None).unwrap();
+        let expected = Arc::new(BooleanArray::from(vec![false, true]));
+        assert_eq!(results.as_ref(), expected.as_ref());
+        let expression = Expression::literal(1).lt(Expression::column("double"));
+        let results = evaluate_expression(&expression, &batch, None).unwrap();
+        assert_eq!(results.as_ref(), expected.as_ref());
+
+        // the longs don't overflow an integer
+        let expression = Expression::column("long").gt(Expression::literal(1));
+        let results = evaluate_expression(&expression, &batch, None).unwrap();
+        assert_eq!(results.as_ref(), expected.as_ref());
+    }
+
+    #[test]
+    fn test_evaluator_checks_literal_types() {
+        let kernel_schema = Arc::new(crate::schema::StructType::new(vec![
+            crate::schema::StructField::new("flag", crate::schema::DataType::BOOLEAN, true),
+            crate::schema::StructField::new("ts", crate::schema::DataType::STRING, true),
+            crate::schema::StructField::new("price", crate::schema::DataType::DOUBLE, true),
+        ]));
+        // the types are checked when the evaluator is created, without any data
+        let create = |predicate| {
+            ArrowExpressionHandler
+                .get_evaluator(
+                    kernel_schema.clone(),
+                    predicate,
+                    crate::schema::DataType::BOOLEAN,
+                )
+                .map(|_| ())
+        };
+
+        // the column and the literal have a common supertype, or the column is parsed
+        let timestamp = Expression::literal(Scalar::Timestamp(0));
+        assert!(create(Expression::column("ts").gt(timestamp)).is_ok());
+        assert!(create(Expression::column("flag").eq(Expression::literal(true))).is_ok());
+        assert!(create(Expression::column("price").gt(Expression::literal(1))).is_ok());
+
+        let date = Expression::literal(Scalar::Date(0));
+        let err = create(Expression::column("flag").eq(date.clone())).unwrap_err();
+        assert!(
+            err.to_string().contains("Column flag of type boolean"),
+            "{err}"
+        );
+        let predicate = Expression::and(
+            !Expression::column("ts").is_null(),
+            date.lt(Expression::column("flag")),
+        );
+        assert!(create(predicate).is_err());
+        // arrow can cast a double to a string, but that would narrow the column
+        let predicate = Expression::column("price").eq(Expression::literal("1.0"));
+        assert!(create(predicate).is_err());
+    }
+
+    #[test]
+    fn test_rlike() {
+        let schema = Schema::new(vec![Field::new("path", DataType::Utf8, true)]);
+        let paths = StringArray::from(vec![
+            Some("/prod/app.log"),
+            Some("/prod/app.txt"),
+            Some("/dev/app.log"),
+            None,
+        ]);
+        let batch = RecordBatch::try_new(Arc::new(schema), vec![Arc::new(paths)]).unwrap();
+
+        let expression = Expression::column("path").rlike(r"^/prod/.*\.log$");
+        let results = evaluate_expression(&expression, &batch, None).unwrap();
+        let expected = BooleanArray::from(vec![Some(true), Some(false), Some(false), None]);
+        assert_eq!(results.as_ref(), &expected);
+
+        // an unanchored pattern matches anywhere in the value
+        let expression = Expression::column("path").rlike("app");
+        let results = evaluate_expression(&expression, &batch, None).unwrap();
+        let expected = BooleanArray::from(vec![Some(true), Some(true), Some(true), None]);
+        assert_eq!(results.as_ref(), &expected);
+
+        // the patterns are compiled when the evaluator is created, and reused for every batch
+        let kernel_schema = Arc::new(crate::schema::StructType::new(vec![
+            crate::schema::StructField::new("path", crate::schema::DataType::STRING, true),
+        ]));
+        let get_evaluator = |predicate| {
+            ArrowExpressionHandler.get_evaluator(
+                kernel_schema.clone(),
+                predicate,
+                crate::schema::DataType::BOOLEAN,
+            )
+        };
+        let evaluator = get_evaluator(Expression::column("path").rlike("log$")).unwrap();
+        for _ in 0..2 {
+            let mut selected = vec![];
+            let batch = ArrowEngineData::new(batch.clone());
+            evaluator.evaluate_into(&batch, &mut selected).unwrap();
+            assert_eq!(selected, [true, false, true, false]);
+        }
+
+        // an invalid pattern, anywhere in the expression, fails creating the evaluator
+        for predicate in [
+            Expression::column("path").rlike("(unclosed"),
+            Expression::or(
+                Expression::column("path").is_null(),
+                Expression::column("path").rlike("(unclosed"),
+            ),
+        ] {
+            let err = get_evaluator(predicate).map(|_| ()).unwrap_err();
+            assert!(
+                err.to_string().contains("Invalid regex '(unclosed'"),
+                "{err}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_add_interval() {
+        let day = 24 * 60 * 60 * 1_000_000i64;
+        let schema = Schema::new(vec![
+            Field::new("date", DataType::Date32, true),
+            Field::new(
+                "ts",
+                DataType::Timestamp(TimeUnit::Microsecond, Some("+00:00".into())),
+                true,
+            ),
+        ]);
+        // 2024-01-31, 2024-02-29 and null
+        let dates = Date32Array::from(vec![Some(19753), Some(19782), None]);
+        let timestamps = TimestampMicrosecondArray::from(vec![Some(19753 * day), Some(1), None])
+            .with_timezone_utc();
+        let batch = RecordBatch::try_new(
+            Arc::new(schema),
+            vec![Arc::new(dates), Arc::new(timestamps)],
+        )
+        .unwrap();
+        let evaluate = |column: &str, interval| {
+            let expression = Expression::column(column).add_interval(interval);
+            evaluate_expression(&expression, &batch, None)
+        };
+
+        let results = evaluate("date", Interval::days(7)).unwrap();
+        let expected = Date32Array::from(vec![Some(19760), Some(19789), None]);
+        assert_eq!(results.as_ref(), &expected);
+        // months clamp to the end of shorter months
+        let results = evaluate("date", Interval::months(1)).unwrap();
+        let expected = Date32Array::from(vec![Some(19782), Some(19811), None]);
+        assert_eq!(results.as_ref(), &expected);
+        let results = evaluate("date", Interval::months(-1)).unwrap();
+        let expected = Date32Array::from(vec![Some(19722), Some(19751), None]);
+        assert_eq!(results.as_ref(), &expected);
+
+        let results = evaluate("ts", Interval::new(1, 1, 1)).unwrap();
+        let expected = TimestampMicrosecondArray::from(vec![
+            Some((19782 + 1) * day + 1),
+            Some((31 + 1) * day + 2),
+            None,
+        ])
+        .with_timezone_utc();
+        assert_eq!(results.as_ref(), &expected);
+
+        // dates only have whole days, and other types can't have intervals added
+        assert!(evaluate("date", Interval::microseconds(1)).is_err());
+        let expression = Expression::literal(1).add_interval(Interval::days(1));
+        assert!(evaluate_expression(&expression, &batch, None).is_err());
+    }
+
+    #[test]
+    fn test_greatest_and_least() {
+        let schema = Schema::new(vec![
+            Field::new("a", DataType::Int32, true),
+            Field::new("b", DataType::Int64, true),
+            Field::new("c", DataType::Float64, true),
+            Field::new("d", DataType::Date32, true),
+            Field::new("s", DataType::Utf8, true),
+        ]);
+        let a = Int32Array::from(vec![Some(1), Some(5), None, None]);
+        let b = Int64Array::from(vec![Some(3), None, Some(2), None]);
+        let c = Float64Array::from(vec![Some(2.5), Some(-1.0), None, None]);
+        let d = Date32Array::from(vec![Some(1), Some(2), None, None]);
+        let s = StringArray::from(vec![Some("x"), None, None, None]);
+        let batch = RecordBatch::try_new(
+            Arc::new(schema),
+            vec![
+                Arc::new(a),
+                Arc::new(b),
+                Arc::new(c),
+                Arc::new(d),
+                Arc::new(s),
+            ],
+        )
+        .unwrap();
+        let columns = |names: &[&str]| {
+            names
+                .iter()
+                .map(|name| Expression::column(*name))
+                .collect_vec()
+        };
+
+        // the integers are compared as longs, and nulls are ignored unless all values are null
+        let results =
+            evaluate_expression(&Expression::greatest(columns(&["a", "b"])), &batch, None).unwrap();
+        let expected = Int64Array::from(vec![Some(3), Some(5), Some(2), None]);
+        assert_eq!(results.as_ref(), &expected);
+        let results =
+            evaluate_expression(&Expression::least(columns(&["a", "b", "c"])), &batch, None)
+                .unwrap();
+        let expected = Float64Array::from(vec![Some(1.0), Some(-1.0), Some(2.0), None]);
+        assert_eq!(results.as_ref(), &expected);
+
+        // a date and a timestamp are compared as timestamps
+        let timestamp = Expression::literal(Scalar::Timestamp(24 * 60 * 60 * 1_000_000 + 1));
+        let expression = Expression::greatest([Expression::column("d"), timestamp]);
+        let results = evaluate_expression(&expression, &batch, None).unwrap();
+        let day = 24 * 60 * 60 * 1_000_000;
+        let expected = TimestampMicrosecondArray::from(vec![day + 1, 2 * day, day + 1, day + 1])
+            .with_timezone_utc();
+        assert_eq!(results.as_ref(), &expected);
+
+        // which can be compared like any other expression
+        let expression = Expression::greatest(columns(&["a", "b"])).gt(Expression::literal(2i64));
+        let results = evaluate_expression(&expression, &batch, None).unwrap();
+        let expected = BooleanArray::from(vec![Some(true), Some(true), Some(false), None]);
+        assert_eq!(results.as_ref(), &expected);
+
+        // types without a common supertype can't be compared
+        let expression = Expression::greatest(columns(&["a", "s"]));
+        assert!(evaluate_expression(&expression, &batch, None).is_err());
+        assert!(evaluate_expression(&Expression::least([]), &batch, None).is_err());
+    }
+
     #[test]
     fn test_binary_cmp() {
         let schema = Schema::new(vec![Field::new("a", DataType::Int32, false)]);
@@ -532,6 +1591,537 @@ mod tests {
         assert_eq!(results.as_ref(), expected.as_ref());
     }
 
+    #[test]
+    fn test_null_if_and_if_null() {
+        let schema = Schema::new(vec![Field::new("a", DataType::Int32, true)]);
+        let values = Int32Array::from(vec![Some(1), None, Some(3)]);
+        let batch = RecordBatch::try_new(Arc::new(schema), vec![Arc::new(values)]).unwrap();
+        let column = Expression::column("a");
+
+        let expression = column.clone().null_if(Expression::literal(3));
+        let results = evaluate_expression(&expression, &batch, None).unwrap();
+        let expected = Arc::new(Int32Array::from(vec![Some(1), None, None]));
+        assert_eq!(results.as_ref(), expected.as_ref());
+
+        let expression = column.clone().if_null(Expression::literal(0));
+        let results = evaluate_expression(&expression, &batch, None).unwrap();
+        let expected = Arc::new(Int32Array::from(vec![1, 0, 3]));
+        assert_eq!(results.as_ref(), expected.as_ref());
+
+        // a null default takes the type of the value
+        for data_type in [
+            crate::schema::DataType::INTEGER,
+            crate::schema::DataType::LONG,
+        ] {
+            let default = Expression::literal(Scalar::Null(data_type));
+            for expression in [
+                column.clone().null_if(default.clone()),
+                column.clone().if_null(default),
+            ] {
+                let results = evaluate_expression(&expression, &batch, None).unwrap();
+                let expected = Arc::new(Int32Array::from(vec![Some(1), None, Some(3)]));
+                assert_eq!(results.as_ref(), expected.as_ref(), "{expression}");
+            }
+        }
+
+        // the value is coerced to the type of a literal default
+        let expression = column.clone().if_null(Expression::literal(0i64));
+        let results = evaluate_expression(&expression, &batch, None).unwrap();
+        let expected = Arc::new(Int64Array::from(vec![1, 0, 3]));
+        assert_eq!(results.as_ref(), expected.as_ref());
+
+        // and to the common supertype of the value and any other default
+        let expression = column.if_null(Expression::literal(0i64).add(Expression::literal(0i64)));
+        let results = evaluate_expression(&expression, &batch, None).unwrap();
+        assert_eq!(results.as_ref(), expected.as_ref());
+    }
+
+    #[test]
+    fn test_null_if_and_if_null_columns() {
+        use crate::schema::{DataType as KernelDataType, StructField, StructType};
+
+        let kernel_schema = Arc::new(StructType::new(vec![
+            StructField::new("int", KernelDataType::INTEGER, true),
+            StructField::new("long", KernelDataType::LONG, true),
+            StructField::new("string", KernelDataType::STRING, true),
+        ]));
+        let schema = Schema::new(vec![
+            Field::new("int", DataType::Int32, true),
+            Field::new("long", DataType::Int64, true),
+            Field::new("string", DataType::Utf8, true),
+        ]);
+        let batch = RecordBatch::try_new(
+            Arc::new(schema),
+            vec![
+                Arc::new(Int32Array::from(vec![Some(1), None, Some(3), None])),
+                Arc::new(Int64Array::from(vec![Some(1), Some(20), Some(30), None])),
+                Arc::new(StringArray::from(vec!["a", "b", "c", "d"])),
+            ],
+        )
+        .unwrap();
+        let evaluate = |expression: Expression| {
+            let evaluator = ArrowExpressionHandler.get_evaluator(
+                kernel_schema.clone(),
+                expression,
+                KernelDataType::LONG,
+            )?;
+            let result = evaluator.evaluate(&ArrowEngineData::new(batch.clone()))?;
+            let batch: RecordBatch = ArrowEngineData::try_from_engine_data(result)?.into();
+            Ok::<_, Error>(batch.column(0).clone())
+        };
+
+        // columns of different types are unified to their common supertype
+        let int = || Expression::column("int");
+        let long = || Expression::column("long");
+        let cases = [
+            (
+                int().if_null(long()),
+                vec![Some(1), Some(20), Some(3), None],
+            ),
+            (
+                long().if_null(int()),
+                vec![Some(1), Some(20), Some(30), None],
+            ),
+            (int().null_if(long()), vec![None, None, Some(3), None]),
+            (long().null_if(int()), vec![None, Some(20), Some(30), None]),
+        ];
+        for (expression, expected) in cases {
+            let results = evaluate(expression.clone()).unwrap();
+            let expected = Int64Array::from(expected);
+            assert_eq!(results.as_ref(), &expected as &dyn Array, "{expression}");
+        }
+
+        // columns without a common supertype are rejected when the evaluator is created
+        for expression in [
+            int().if_null(Expression::column("string")),
+            Expression::column("string").null_if(long()),
+        ] {
+            let res = ArrowExpressionHandler.get_evaluator(
+                kernel_schema.clone(),
+                expression.clone(),
+                KernelDataType::LONG,
+            );
+            assert!(res.is_err(), "{expression}");
+        }
+    }
+
+    #[test]
+    fn test_empty_batch() {
+        let schema = Schema::new(vec![
+            Field::new("a", DataType::Int32, true),
+            Field::new("b", DataType::Boolean, true),
+        ]);
+        let batch = RecordBatch::new_empty(Arc::new(schema));
+        let column_a = Expression::column("a");
+        let column_b = Expression::column("b");
+
+        let cases = [
+            (
+                column_a.clone().lt(Expression::literal(2)),
+                DataType::Boolean,
+            ),
+            (column_a.clone().eq(column_a.clone()), DataType::Boolean),
+            (
+                column_a.clone().add(Expression::literal(1)),
+                DataType::Int32,
+            ),
+            (
+                column_a.clone().div(Expression::literal(0)),
+                DataType::Int32,
+            ),
+            (
+                column_b.clone().and(Expression::literal(true)),
+                DataType::Boolean,
+            ),
+            (column_b.clone().or(column_b.clone()), DataType::Boolean),
+            (!column_b.clone(), DataType::Boolean),
+            (column_a.is_null(), DataType::Boolean),
+            (Expression::and_from(vec![]), DataType::Boolean),
+        ];
+        for (expression, expected_type) in cases {
+            let results = evaluate_expression(&expression, &batch, None).unwrap();
+            assert_eq!(results.len(), 0, "{expression}");
+            assert_eq!(results.data_type(), &expected_type, "{expression}");
+        }
+
+        let schema = crate::schema::StructType::new(vec![crate::schema::StructField::new(
+            "b",
+            crate::schema::DataType::BOOLEAN,
+            true,
+        )]);
+        let evaluator = ArrowExpressionHandler
+            .get_evaluator(
+                Arc::new(schema.clone()),
+                Expression::struct_expr([column_b]),
+                schema.into(),
+            )
+            .unwrap();
+        let results = evaluator.evaluate(&ArrowEngineData::new(batch)).unwrap();
+        assert_eq!(results.length(), 0);
+    }
+
+    #[test]
+    fn test_all_null_input() {
+        let schema = Schema::new(vec![
+            Field::new("a", DataType::Int32, true),
+            Field::new("n", DataType::Null, true),
+        ]);
+        let batch = RecordBatch::try_new(
+            Arc::new(schema),
+            vec![
+                Arc::new(Int32Array::new_null(3)),
+                Arc::new(arrow_array::NullArray::new(3)),
+            ],
+        )
+        .unwrap();
+        let lit = Expression::literal(2);
+        let nulls = Arc::new(BooleanArray::new_null(3));
+
+        // comparisons against all-null inputs are all null, whether or not the input is typed
+        for column in [Expression::column("a"), Expression::column("n")] {
+            for expression in [
+                column.clone().lt(lit.clone()),
+                column.clone().gt_eq(lit.clone()),
+                column.clone().eq(lit.clone()),
+                column.clone().ne(lit.clone()),
+                lit.clone().lt(column.clone()),
+            ] {
+                let results = evaluate_expression(&expression, &batch, None).unwrap();
+                assert_eq!(results.as_ref(), nulls.as_ref(), "{expression}");
+            }
+
+            let expression = column.clone().add(lit.clone());
+            let results = evaluate_expression(&expression, &batch, None).unwrap();
+            let expected = Arc::new(Int32Array::new_null(3));
+            assert_eq!(results.as_ref(), expected.as_ref(), "{expression}");
+
+            let expression = column.clone().distinct(lit.clone());
+            let results = evaluate_expression(&expression, &batch, None).unwrap();
+            let expected = Arc::new(BooleanArray::from(vec![true; 3]));
+            assert_eq!(results.as_ref(), expected.as_ref(), "{expression}");
+
+            let expression = column.is_null();
+            let results = evaluate_expression(&expression, &batch, None).unwrap();
+            let expected = Arc::new(BooleanArray::from(vec![true; 3]));
+            assert_eq!(results.as_ref(), expected.as_ref(), "{expression}");
+        }
+
+        let column = Expression::column("n");
+        let results =
+            evaluate_expression(&column.clone().eq(column.clone()), &batch, None).unwrap();
+        assert_eq!(results.as_ref(), nulls.as_ref());
+
+        // logical operators treat untyped nulls as null booleans
+        let expression = column.clone().and(Expression::literal(true));
+        let results =
+            evaluate_expression(&expression, &batch, Some(&crate::schema::DataType::BOOLEAN))
+                .unwrap();
+        assert_eq!(results.as_ref(), nulls.as_ref());
+
+        let expression = column.clone().and(Expression::literal(false));
+        let results =
+            evaluate_expression(&expression, &batch, Some(&crate::schema::DataType::BOOLEAN))
+                .unwrap();
+        let expected = Arc::new(BooleanArray::from(vec![false; 3]));
+        assert_eq!(results.as_ref(), expected.as_ref());
+
+        let expression = column.clone().or(Expression::literal(true));
+        let results =
+            evaluate_expression(&expression, &batch, Some(&crate::schema::DataType::BOOLEAN))
+                .unwrap();
+        let expected = Arc::new(BooleanArray::from(vec![true; 3]));
+        assert_eq!(results.as_ref(), expected.as_ref());
+
+        let results = evaluate_expression(&!column.clone(), &batch, None).unwrap();
+        assert_eq!(results.as_ref(), nulls.as_ref());
+
+        // untyped nulls in the output get the output type
+        let schema = crate::schema::StructType::new(vec![crate::schema::StructField::new(
+            "n",
+            crate::schema::DataType::LONG,
+            true,
+        )]);
+        let evaluator = ArrowExpressionHandler
+            .get_evaluator(Arc::new(schema), column, crate::schema::DataType::LONG)
+            .unwrap();
+        let results = evaluator.evaluate(&ArrowEngineData::new(batch)).unwrap();
+        let results = ArrowEngineData::try_from_engine_data(results).unwrap();
+        let expected = Arc::new(Int64Array::new_null(3));
+        assert_eq!(results.record_batch().column(0).as_ref(), expected.as_ref());
+    }
+
+    #[test]
+    fn test_null_map_literal() {
+        let map_type = crate::schema::MapType::new(
+            crate::schema::DataType::STRING,
+            crate::schema::DataType::STRING,
+            true,
+        );
+        let results = Scalar::Null(map_type.into()).to_array(2).unwrap();
+        assert_eq!(results.len(), 2);
+        assert_eq!(results.null_count(), 2);
+    }
+
+    #[test]
+    fn test_map_lookup() {
+        use arrow_array::builder::{MapBuilder, StringBuilder};
+
+        let mut builder = MapBuilder::new(None, StringBuilder::new(), StringBuilder::new());
+        let rows: [Option<&[(&str, &str)]>; 5] = [
+            Some(&[("a", "b"), ("env", "prod")]),
+            Some(&[("env", "dev")]),
+            None,
+            Some(&[]),
+            Some(&[("a", "c")]),
+        ];
+        for row in rows {
+            if let Some(entries) = row {
+                for (key, value) in entries {
+                    builder.keys().append_value(key);
+                    builder.values().append_value(value);
+                }
+            }
+            builder.append(row.is_some()).unwrap();
+        }
+        let map = Arc::new(builder.finish());
+        let keys = Arc::new(StringArray::from(vec!["env", "env", "a", "a", "a"]));
+        let batch = RecordBatch::try_from_iter([("m", map as ArrayRef), ("k", keys as _)]).unwrap();
+
+        let expression = Expression::map_lookup(Expression::column("m"), "env");
+        let results = evaluate_expression(&expression, &batch, None).unwrap();
+        let expected = StringArray::from(vec![Some("prod"), Some("dev"), None, None, None]);
+        assert_eq!(results.as_ref(), &expected);
+
+        let expression = Expression::map_lookup(Expression::column("m"), "env").eq("prod".into());
+        let results = evaluate_expression(&expression, &batch, None).unwrap();
+        let expected = BooleanArray::from(vec![Some(true), Some(false), None, None, None]);
+        assert_eq!(results.as_boolean(), &expected);
+
+        // the key can differ by row, and be looked up in a slice of the map
+        let expression = Expression::map_lookup(Expression::column("m"), Expression::column("k"));
+        let results = evaluate_expression(&expression, &batch.slice(1, 4), None).unwrap();
+        let expected = StringArray::from(vec![Some("dev"), None, None, Some("c")]);
+        assert_eq!(results.as_ref(), &expected);
+
+        let expression = Expression::map_lookup(Expression::column("k"), "env");
+        assert!(evaluate_expression(&expression, &batch, None).is_err());
+    }
+
+    #[test]
+    fn test_evaluate_into() {
+        /// An evaluator that only implements `evaluate`, to check the provided `evaluate_into`
+        struct EvaluateOnly(Arc<dyn ExpressionEvaluator>);
+        impl ExpressionEvaluator for EvaluateOnly {
+            fn evaluate(&self, batch: &dyn EngineData) -> DeltaResult<Box<dyn EngineData>> {
+                self.0.evaluate(batch)
+            }
+        }
+
+        let schema = Schema::new(vec![Field::new("a", DataType::Int32, true)]);
+        let values = Int32Array::from(vec![Some(1), None, Some(3), Some(5)]);
+        let batch = RecordBatch::try_new(Arc::new(schema), vec![Arc::new(values)]).unwrap();
+        let batch = ArrowEngineData::new(batch);
+        let kernel_schema = Arc::new(crate::schema::StructType::new(vec![
+            crate::schema::StructField::new("a", crate::schema::DataType::INTEGER, true),
+        ]));
+        let predicate = Expression::column("a").gt(Expression::literal(2));
+        let evaluator = ArrowExpressionHandler
+            .get_evaluator(
+                kernel_schema.clone(),
+                predicate,
+                crate::schema::DataType::BOOLEAN,
+            )
+            .unwrap();
+
+        // the buffer is cleared and reused
+        let mut out = vec![true; 16];
+        let capacity = out.capacity();
+        evaluator.evaluate_into(&batch, &mut out).unwrap();
+        assert_eq!(out, [false, false, true, true]);
+        assert_eq!(out.capacity(), capacity);
+
+        let mut default_out = vec![];
+        EvaluateOnly(evaluator)
+            .evaluate_into(&batch, &mut default_out)
+            .unwrap();
+        assert_eq!(default_out, out);
+
+        // a null literal is never true
+        let evaluator = ArrowExpressionHandler
+            .get_evaluator(
+                kernel_schema,
+                Expression::literal(Scalar::Null(crate::schema::DataType::BOOLEAN)),
+                crate::schema::DataType::BOOLEAN,
+            )
+            .unwrap();
+        evaluator.evaluate_into(&batch, &mut out).unwrap();
+        assert_eq!(out, [false; 4]);
+    }
+
+    #[test]
+    fn test_dictionary_column() {
+        use arrow_array::types::Int32Type;
+        use arrow_array::{DictionaryArray, StringArray};
+
+        let kernel_schema = Arc::new(crate::schema::StructType::new(vec![
+            crate::schema::StructField::new("s", crate::schema::DataType::STRING, true),
+        ]));
+        let x = || Expression::column("s");
+        let predicates = [
+            x().eq(Expression::literal("b")),
+            Expression::literal("b").ne(x()),
+            x().lt(Expression::literal("b")),
+            x().distinct(Expression::literal("b")),
+            x().is_in(["a", "c"]),
+            x().rlike("^[ab]$"),
+            Expression::and(x().gt_eq(Expression::literal("b")), x().eq(x())),
+        ];
+        let evaluators: Vec<_> = predicates
+            .iter()
+            .map(|predicate| {
+                ArrowExpressionHandler
+                    .get_evaluator(
+                        kernel_schema.clone(),
+                        predicate.clone(),
+                        crate::schema::DataType::BOOLEAN,
+                    )
+                    .unwrap()
+            })
+            .collect();
+        let evaluate = |evaluator: &Arc<dyn ExpressionEvaluator>, column: ArrayRef| {
+            let schema = Schema::new(vec![Field::new("s", column.data_type().clone(), true)]);
+            let batch = RecordBatch::try_new(Arc::new(schema), vec![column]).unwrap();
+            let result = evaluator.evaluate(&ArrowEngineData::new(batch)).unwrap();
+            let batch: RecordBatch = ArrowEngineData::try_from_engine_data(result)
+                .unwrap()
+                .into();
+            batch.column(0).as_boolean().clone()
+        };
+
+        // the same values, with two different dictionaries, in batches evaluated one after the
+        // other with the same evaluators
+        let values = [Some("a"), Some("b"), None, Some("c"), Some("b")];
+        let plain: ArrayRef = Arc::new(StringArray::from(values.to_vec()));
+        let first: ArrayRef = Arc::new(DictionaryArray::<Int32Type>::new(
+            [Some(0), Some(1), None, Some(2), Some(1)]
+                .into_iter()
+                .collect(),
+            Arc::new(StringArray::from(vec!["a", "b", "c"])),
+        ));
+        let second: ArrayRef = Arc::new(DictionaryArray::<Int32Type>::new(
+            [Some(2), Some(0), Some(1), Some(3), Some(0)]
+                .into_iter()
+                .collect(),
+            Arc::new(StringArray::from(vec![
+                Some("b"),
+                None,
+                Some("a"),
+                Some("c"),
+            ])),
+        ));
+        for (predicate, evaluator) in predicates.iter().zip(&evaluators) {
+            let expected = evaluate(evaluator, plain.clone());
+            for column in [&first, &second] {
+                assert_eq!(evaluate(evaluator, column.clone()), expected, "{predicate}");
+            }
+        }
+        let expected =
+            BooleanArray::from(vec![Some(false), Some(true), None, Some(false), Some(true)]);
+        assert_eq!(evaluate(&evaluators[0], second), expected);
+
+        // a dictionary-encoded column is output decoded
+        let evaluator = ArrowExpressionHandler
+            .get_evaluator(
+                kernel_schema.clone(),
+                Expression::struct_expr([x()]),
+                crate::schema::DataType::Struct(Box::new(kernel_schema.as_ref().clone())),
+            )
+            .unwrap();
+        let schema = Schema::new(vec![Field::new("s", first.data_type().clone(), true)]);
+        let batch = RecordBatch::try_new(Arc::new(schema), vec![first]).unwrap();
+        let result = evaluator.evaluate(&ArrowEngineData::new(batch)).unwrap();
+        let batch: RecordBatch = ArrowEngineData::try_from_engine_data(result)
+            .unwrap()
+            .into();
+        assert_eq!(batch.column(0), &plain);
+    }
+
+    #[test]
+    fn test_in_list() {
+        let schema = Schema::new(vec![Field::new("a", DataType::Int32, true)]);
+        let values = Int32Array::from(vec![Some(1), Some(2), None, Some(4)]);
+        let batch = RecordBatch::try_new(Arc::new(schema), vec![Arc::new(values)]).unwrap();
+        let batch = ArrowEngineData::new(batch);
+        let kernel_schema = Arc::new(crate::schema::StructType::new(vec![
+            crate::schema::StructField::new("a", crate::schema::DataType::INTEGER, true),
+        ]));
+        let evaluate = |predicate| -> DeltaResult<BooleanArray> {
+            let evaluator = ArrowExpressionHandler.get_evaluator(
+                kernel_schema.clone(),
+                predicate,
+                crate::schema::DataType::BOOLEAN,
+            )?;
+            let result = evaluator.evaluate(&batch)?;
+            let batch: RecordBatch = ArrowEngineData::try_from_engine_data(result)?.into();
+            Ok(batch.column(0).as_boolean().clone())
+        };
+
+        // the values are coerced to the type of the column
+        let result = evaluate(Expression::column("a").is_in([1i64, 4])).unwrap();
+        let expected = BooleanArray::from(vec![Some(true), Some(false), None, Some(true)]);
+        assert_eq!(result, expected);
+        // a value that isn't in a list with a null may be the null
+        let list = [
+            Scalar::Integer(2),
+            Scalar::Null(crate::schema::DataType::INTEGER),
+        ];
+        let result = evaluate(Expression::column("a").is_in(list)).unwrap();
+        let expected = BooleanArray::from(vec![None, Some(true), None, None]);
+        assert_eq!(result, expected);
+        let result = evaluate(Expression::column("a").is_in([0; 0])).unwrap();
+        assert_eq!(result, BooleanArray::from(vec![false; 4]));
+
+        // values that can't be coerced make the evaluator fail
+        assert!(evaluate(Expression::column("a").is_in(["x"])).is_err());
+        assert!(evaluate(Expression::column("a").is_in([i64::MAX])).is_err());
+    }
+
+    #[test]
+    fn test_boolean_column_predicate() {
+        let schema = Schema::new(vec![Field::new("is_active", DataType::Boolean, true)]);
+        let values = BooleanArray::from(vec![Some(true), None, Some(false), Some(true)]);
+        let batch = RecordBatch::try_new(Arc::new(schema), vec![Arc::new(values)]).unwrap();
+        let batch = ArrowEngineData::new(batch);
+        let kernel_schema = Arc::new(crate::schema::StructType::new(vec![
+            crate::schema::StructField::new("is_active", crate::schema::DataType::BOOLEAN, true),
+        ]));
+        let evaluate = |predicate| -> DeltaResult<Vec<bool>> {
+            let evaluator = ArrowExpressionHandler.get_evaluator(
+                kernel_schema.clone(),
+                predicate,
+                crate::schema::DataType::BOOLEAN,
+            )?;
+            let mut out = vec![];
+            evaluator.evaluate_into(&batch, &mut out).map(|_| out)
+        };
+
+        // the column is the selection, where nulls aren't selected
+        let column = Expression::column("is_active");
+        assert_eq!(
+            evaluate(column.clone()).unwrap(),
+            [true, false, false, true]
+        );
+        assert_eq!(
+            evaluate(!column.clone()).unwrap(),
+            [false, false, true, false]
+        );
+        let predicate = column.or(Expression::literal(false));
+        assert_eq!(evaluate(predicate).unwrap(), [true, false, false, true]);
+
+        // a predicate must be boolean
+        assert!(evaluate(Expression::literal(1)).is_err());
+    }
+
     #[test]
     fn test_logical() {
         let schema = Schema::new(vec![
@@ -1,58 +1,535 @@
 //! Some utilities for working with arrow data types
 
+use std::io::Cursor;
 use std::sync::Arc;
+#[cfg(any(
+    feature = "default-engine",
+    feature = "test-utils",
+    feature = "tar-bundle"
+))]
+use std::task::{ready, Poll};
 
-use crate::{schema::SchemaRef, utils::require, DeltaResult, Error};
+use crate::engine::arrow_data::ArrowEngineData;
+use crate::schema::{
+    ColumnMetadataKey, DataType, MetadataValue, PrimitiveType, SchemaRef, StructField, StructType,
+};
+use crate::{utils::require, DeltaResult, EngineData, Error, RowGroupMeta};
 
-use arrow_array::RecordBatch;
-use arrow_schema::{Schema as ArrowSchema, SchemaRef as ArrowSchemaRef};
+use arrow_array::cast::AsArray;
+use arrow_array::{new_null_array, Array, ArrayRef, ListArray, MapArray, RecordBatch, StructArray};
+use arrow_cast::cast;
+use arrow_json::ReaderBuilder;
+use arrow_schema::{
+    DataType as ArrowDataType, Field as ArrowField, Fields, Schema as ArrowSchema,
+    SchemaRef as ArrowSchemaRef,
+};
+use arrow_select::concat::concat_batches;
+#[cfg(any(
+    feature = "default-engine",
+    feature = "test-utils",
+    feature = "tar-bundle"
+))]
+use bytes::{Buf, Bytes};
+use chrono::{Days, NaiveDate};
+use itertools::Itertools;
+use parquet::arrow::PARQUET_FIELD_ID_META_KEY;
+use parquet::basic::Compression;
+use parquet::file::metadata::{ParquetMetaData, RowGroupMetaData};
+use parquet::file::statistics::Statistics;
 use parquet::{arrow::ProjectionMask, schema::types::SchemaDescriptor};
+use serde_json::{json, Map, Value};
+use url::Url;
 
-/// Get the indicies in `parquet_schema` of the specified columns in `requested_schema`. This
-/// returns a tuples of (mask_indicies: Vec<parquet_schema_index>, reorder_indicies:
-/// Vec<requested_index>). `mask_indicies` is used for generating the mask for reading from the
-/// parquet file, and simply contains an entry for each index we wish to select from the parquet
-/// file set to the index of the requested column in the parquet. `reorder_indicies` is used for
-/// re-ordering and will be the same size as `requested_schema`. Each index in `reorder_indicies`
-/// represents a column that will be in the read parquet data at that index. The value stored in
-/// `reorder_indicies` is the position that the column should appear in the final output. For
-/// example, if `reorder_indicies` is `[2,0,1]`, then the re-ordering code should take the third
-/// column in the raw-read parquet data, and move it to the first column in the final output, the
-/// first column to the second, and the second to the third.
+/// Decodes newline-delimited JSON that is read in chunks into batches. The decoder keeps the
+/// partial record at the end of a chunk until the next chunk completes it. This is shared by
+/// [`parse_json_stream`] and the JSON handler of the sync engine's client handlers, which feed it
+/// chunks from a stream and an iterator.
+#[cfg(any(
+    feature = "default-engine",
+    feature = "test-utils",
+    feature = "tar-bundle"
+))]
+pub(crate) struct JsonChunkDecoder {
+    decoder: arrow_json::reader::Decoder,
+    /// The rest of the current chunk, which is not decoded yet
+    chunk: Bytes,
+    done: bool,
+}
+
+#[cfg(any(
+    feature = "default-engine",
+    feature = "test-utils",
+    feature = "tar-bundle"
+))]
+impl JsonChunkDecoder {
+    pub(crate) fn try_new(schema: ArrowSchemaRef, batch_size: usize) -> DeltaResult<Self> {
+        require!(
+            batch_size > 0,
+            Error::generic("The batch size to parse JSON with must be positive")
+        );
+        let decoder = ReaderBuilder::new(schema)
+            .with_batch_size(batch_size)
+            .build_decoder()?;
+        Ok(Self {
+            decoder,
+            chunk: Bytes::new(),
+            done: false,
+        })
+    }
+
+    /// Decode the next batch, with `next_chunk` polled for each chunk that is needed for it.
+    /// Returns `None` once all the input is decoded, or after an error, and is pending while the
+    /// next chunk is.
+    pub(crate) fn poll_next_batch<C>(
+        &mut self,
+        mut next_chunk: C,
+    ) -> Poll<Option<DeltaResult<RecordBatch>>>
+    where
+        C: FnMut() -> Poll<Option<DeltaResult<Bytes>>>,
+    {
+        Poll::Ready(loop {
+            if self.done {
+                break None;
+            }
+            if self.chunk.is_empty() {
+                match ready!(next_chunk()) {
+                    Some(Ok(chunk)) => self.chunk = chunk,
+                    Some(Err(err)) => {
+                        self.done = true;
+                        break Some(Err(err));
+                    }
+                    None => {
+                        // this fails if the last record is truncated
+                        self.done = true;
+                        break self.flush();
+                    }
+                }
+                continue;
+            }
+            match self.decoder.decode(&self.chunk) {
+                Ok(decoded) => {
+                    self.chunk.advance(decoded);
+                    // the decoder stops before the end of the chunk once it has a full batch
+                    if !self.chunk.is_empty() {
+                        break self.flush();
+                    }
+                }
+                Err(err) => {
+                    self.done = true;
+                    break Some(Err(err.into()));
+                }
+            }
+        })
+    }
+
+    /// Flush the rows decoded so far into a batch, if there are any
+    fn flush(&mut self) -> Option<DeltaResult<RecordBatch>> {
+        match self.decoder.flush() {
+            Ok(batch) => batch.map(Ok),
+            Err(err) => {
+                self.done = true;
+                Some(Err(err.into()))
+            }
+        }
+    }
+}
+
+/// Parse the newline-delimited JSON of the chunks of `json` as they arrive into batches of at
+/// most `batch_size` rows with `schema`, see [`crate::JsonHandler::parse_json_stream`]. Each
+/// batch is yielded as soon as it is full, and the partial record at the end of a chunk is kept
+/// until the next chunk completes it. This is shared by the JSON handlers of the default and sync
+/// engines.
+#[cfg(feature = "default-engine")]
+pub(crate) fn parse_json_stream<'a>(
+    mut json: futures::stream::BoxStream<'a, DeltaResult<Bytes>>,
+    schema: ArrowSchemaRef,
+    batch_size: usize,
+) -> DeltaResult<futures::stream::BoxStream<'a, DeltaResult<RecordBatch>>> {
+    use futures::StreamExt;
+
+    let mut decoder = JsonChunkDecoder::try_new(schema, batch_size)?;
+    Ok(
+        futures::stream::poll_fn(move |cx| decoder.poll_next_batch(|| json.poll_next_unpin(cx)))
+            .boxed(),
+    )
+}
+
+/// Parse the single string column of `json_strings` as JSON with `output_schema`, with one output
+/// row per string. A null string parses as a row of nulls. This is shared by the JSON handlers of
+/// the default and sync engines.
+pub(crate) fn parse_json(
+    json_strings: Box<dyn EngineData>,
+    output_schema: SchemaRef,
+) -> DeltaResult<Box<dyn EngineData>> {
+    let json_strings: RecordBatch = ArrowEngineData::try_from_engine_data(json_strings)?.into();
+    require!(
+        json_strings.num_columns() == 1,
+        Error::missing_column("Expected single column")
+    );
+    let json_strings =
+        json_strings
+            .column(0)
+            .as_string_opt::<i32>()
+            .ok_or(Error::unexpected_column_type(
+                "Expected column to be String",
+            ))?;
+    let output_schema: ArrowSchemaRef = Arc::new(output_schema.as_ref().try_into()?);
+    if json_strings.is_empty() {
+        return Ok(Box::new(ArrowEngineData::new(RecordBatch::new_empty(
+            output_schema,
+        ))));
+    }
+    let output: Vec<_> = json_strings
+        .iter()
+        .map(|json_string| hack_parse(&output_schema, json_string))
+        .try_collect()?;
+    Ok(Box::new(ArrowEngineData::new(concat_batches(
+        &output_schema,
+        output.iter(),
+    )?)))
+}
+
+fn hack_parse(
+    stats_schema: &ArrowSchemaRef,
+    json_string: Option<&str>,
+) -> DeltaResult<RecordBatch> {
+    match json_string {
+        Some(s) => Ok(ReaderBuilder::new(stats_schema.clone())
+            .build(Cursor::new(s.as_bytes()))?
+            .next()
+            .transpose()?
+            .ok_or(Error::missing_data("Expected data"))?),
+        None => Ok(RecordBatch::try_new(
+            stats_schema.clone(),
+            stats_schema
+                .fields
+                .iter()
+                .map(|field| new_null_array(field.data_type(), 1))
+                .collect(),
+        )?),
+    }
+}
+
+/// Whether the parquet reader can decompress data compressed with `codec`. Each codec is behind a
+/// feature of the parquet crate, and the kernel enables the features of all of them explicitly
+/// (see its `Cargo.toml`), so this is every codec except `LZO`, which the parquet crate doesn't
+/// implement. `LZ4_RAW` shares the `lz4` feature with `LZ4`.
+fn is_supported_codec(codec: Compression) -> bool {
+    match codec {
+        Compression::UNCOMPRESSED
+        | Compression::SNAPPY
+        | Compression::GZIP(_)
+        | Compression::BROTLI(_)
+        | Compression::LZ4
+        | Compression::ZSTD(_)
+        | Compression::LZ4_RAW => true,
+        Compression::LZO => false,
+    }
+}
+
+/// Check that every column chunk of a parquet file is compressed with a codec that the parquet
+/// reader supports (see [`is_supported_codec`]). Checking this up front gives a clear error naming
+/// the codec and the file, instead of a decoding failure later on.
+pub(crate) fn ensure_supported_codecs(
+    metadata: &ParquetMetaData,
+    location: &Url,
+) -> DeltaResult<()> {
+    let unsupported = metadata
+        .row_groups()
+        .iter()
+        .flat_map(|row_group| row_group.columns())
+        .map(|column| column.compression())
+        .find(|codec| !is_supported_codec(*codec));
+    match unsupported {
+        Some(codec) => Err(Error::unsupported_compression_codec(location, codec)),
+        None => Ok(()),
+    }
+}
+
+/// The layout of the row groups of the parquet file with the footer `metadata`
+pub(crate) fn row_group_layout(metadata: &ParquetMetaData) -> Vec<RowGroupMeta> {
+    metadata
+        .row_groups()
+        .iter()
+        .map(|row_group| {
+            let ranges = row_group.columns().iter().map(|column| {
+                let (start, length) = column.byte_range();
+                start as usize..(start + length) as usize
+            });
+            let byte_range = ranges
+                .reduce(|a, b| a.start.min(b.start)..a.end.max(b.end))
+                .unwrap_or_default();
+            RowGroupMeta {
+                num_rows: row_group.num_rows() as usize,
+                byte_range,
+            }
+        })
+        .collect()
+}
+
+/// Check that the parquet file at `location` with the footer `metadata` has a row group for each
+/// of the `row_group_indices`
+pub(crate) fn ensure_row_groups_exist(
+    metadata: &ParquetMetaData,
+    row_group_indices: &[usize],
+    location: &Url,
+) -> DeltaResult<()> {
+    let num_row_groups = metadata.num_row_groups();
+    match row_group_indices
+        .iter()
+        .find(|index| **index >= num_row_groups)
+    {
+        Some(index) => Err(Error::generic(format!(
+            "Row group {index} is out of range for {location}, which has {num_row_groups} row groups"
+        ))),
+        None => Ok(()),
+    }
+}
+
+/// Find the index of the column of `fields` for the `requested` field. A requested field with a
+/// [`ColumnMetadataKey::ParquetFieldId`] is matched to the column with that parquet field id, if
+/// there is one. Otherwise names must match exactly, unless `trim_names` is set, in which case
+/// leading and trailing whitespace is ignored if there's no exact match.
+fn find_column(fields: &Fields, requested: &StructField, trim_names: bool) -> Option<usize> {
+    let name = requested.name();
+    let field_id = match requested.get_config_value(&ColumnMetadataKey::ParquetFieldId) {
+        Some(MetadataValue::Number(id)) => Some(id.to_string()),
+        _ => None,
+    };
+    field_id
+        .and_then(|field_id| {
+            fields.iter().position(|field| {
+                field.metadata().get(PARQUET_FIELD_ID_META_KEY) == Some(&field_id)
+            })
+        })
+        .or_else(|| fields.iter().position(|field| field.name() == name))
+        .or_else(|| {
+            trim_names
+                .then(|| {
+                    fields
+                        .iter()
+                        .position(|field| field.name().trim() == name.trim())
+                })
+                .flatten()
+        })
+}
+
+/// Get the indicies in `parquet_schema` of the specified columns in `requested_schema`, in the
+/// order they appear in the parquet file at `location`. These are used for generating the mask for
+/// reading from the parquet file. Requested columns that don't exist in the parquet file are left
+/// out, and will be filled with nulls by [`reorder_record_batch`]. This is only allowed for
+/// nullable columns, since e.g. a column added to the table after a file was written must read as
+/// null for that file.
+///
+/// Column names are matched exactly (or ignoring surrounding whitespace if `trim_names` is set).
+/// Every requested column that can't be matched, including the fields of struct columns, must be
+/// nullable, or this fails with an [`Error::SchemaMismatch`] naming the file's columns. So does a
+/// requested column that is missing but only differs from a column of the file by case or
+/// surrounding whitespace, even if it is nullable: it is most likely a physical name mangled by a
+/// writer rather than a column that was added later, so it shouldn't read as null.
 pub(crate) fn get_requested_indices(
     requested_schema: &SchemaRef,
     parquet_schema: &ArrowSchemaRef,
-) -> DeltaResult<(Vec<usize>, Vec<usize>)> {
-    let (mask_indicies, reorder_indicies): (Vec<usize>, Vec<usize>) = parquet_schema
+    location: &Url,
+    trim_names: bool,
+) -> DeltaResult<Vec<usize>> {
+    let parquet_fields = parquet_schema.fields();
+    ensure_unmatched_fields_nullable(requested_schema, parquet_fields, "", location, trim_names)?;
+    let mut mask_indicies: Vec<_> = requested_schema
         .fields()
-        .iter()
-        .enumerate()
-        .filter_map(|(parquet_index, field)| {
-            requested_schema
-                .index_of(field.name())
-                .map(|index| (parquet_index, index))
-        })
-        .unzip();
+        .filter_map(|field| find_column(parquet_fields, field, trim_names))
+        .collect();
+    mask_indicies.sort_unstable();
+    mask_indicies.dedup();
+    Ok(mask_indicies)
+}
+
+/// Check that the fields of `requested` that don't match any of `fields` (the columns of the
+/// parquet file at `location`, or the fields of one of its struct columns, whose name starts with
+/// `prefix`) can read as null, and aren't near misses of one of `fields`. See
+/// [`get_requested_indices`].
+fn ensure_unmatched_fields_nullable(
+    requested: &StructType,
+    fields: &Fields,
+    prefix: &str,
+    location: &Url,
+    trim_names: bool,
+) -> DeltaResult<()> {
+    let normalize = |name: &str| name.trim().to_lowercase();
+    for field in requested.fields() {
+        let column = format!("{prefix}{}", field.name());
+        if let Some(index) = find_column(fields, field, trim_names) {
+            if let (DataType::Struct(requested), ArrowDataType::Struct(fields)) =
+                (field.data_type(), fields[index].data_type())
+            {
+                let prefix = format!("{column}.");
+                ensure_unmatched_fields_nullable(requested, fields, &prefix, location, trim_names)?;
+            }
+            continue;
+        }
+        let near_miss = fields
+            .iter()
+            .any(|parquet_field| normalize(parquet_field.name()) == normalize(field.name()));
+        require!(
+            field.is_nullable() && !near_miss,
+            Error::schema_mismatch(
+                location,
+                &column,
+                format!("a column named {:?}", field.name()),
+                format!(
+                    "{} {}",
+                    if prefix.is_empty() {
+                        "columns"
+                    } else {
+                        "fields"
+                    },
+                    fields.iter().map(|f| format!("{:?}", f.name())).join(", ")
+                ),
+            )
+        );
+    }
+    Ok(())
+}
+
+/// Check that the columns of `requested_schema` that exist in the parquet file at `location` with
+/// the schema `parquet_schema` have types compatible with the requested ones, so that a file
+/// whose schema drifted from the table's fails to read with an [`Error::SchemaMismatch`] naming
+/// the (possibly nested) column, instead of reading wrong data. Columns are matched like in
+/// [`get_requested_indices`], which checks the columns that are missing (missing nested fields
+/// read as null, see [`reorder_record_batch`]).
+///
+/// Types are compatible if they have the same physical representation: integers and floating
+/// point numbers must have the same width, decimals the same scale (and at most the requested
+/// precision), and timestamps may have any unit and timezone (but `timestamp_ntz` columns none),
+/// since writers differ in how they annotate them. They are cast to microseconds in UTC when read,
+/// see [`reorder_record_batch`].
+#[cfg(feature = "default-engine")]
+pub(crate) fn ensure_compatible_types(
+    requested_schema: &StructType,
+    parquet_schema: &ArrowSchemaRef,
+    location: &Url,
+    trim_names: bool,
+) -> DeltaResult<()> {
+    ensure_compatible_fields(
+        requested_schema,
+        parquet_schema.fields(),
+        "",
+        location,
+        trim_names,
+    )
+}
+
+#[cfg(feature = "default-engine")]
+fn ensure_compatible_fields(
+    requested: &StructType,
+    fields: &Fields,
+    prefix: &str,
+    location: &Url,
+    trim_names: bool,
+) -> DeltaResult<()> {
+    for requested_field in requested.fields() {
+        let Some(index) = find_column(fields, requested_field, trim_names) else {
+            continue;
+        };
+        let column = format!("{prefix}{}", requested_field.name());
+        ensure_compatible_type(
+            requested_field.data_type(),
+            fields[index].data_type(),
+            &column,
+            location,
+            trim_names,
+        )?;
+    }
+    Ok(())
+}
+
+#[cfg(feature = "default-engine")]
+fn ensure_compatible_type(
+    requested: &DataType,
+    actual: &ArrowDataType,
+    column: &str,
+    location: &Url,
+    trim_names: bool,
+) -> DeltaResult<()> {
+    let compatible = match (requested, actual) {
+        (_, ArrowDataType::Dictionary(_, value_type)) => {
+            return ensure_compatible_type(requested, value_type, column, location, trim_names)
+        }
+        (DataType::Primitive(primitive), actual) => match (primitive, actual) {
+            (PrimitiveType::Void, _) => true,
+            (PrimitiveType::Decimal(precision, scale), ArrowDataType::Decimal128(p, s)) => {
+                p <= precision && *s == *scale as i8
+            }
+            (PrimitiveType::Timestamp, actual) => matches!(actual, ArrowDataType::Timestamp(..)),
+            (PrimitiveType::TimestampNtz, actual) => {
+                matches!(actual, ArrowDataType::Timestamp(_, None))
+            }
+            (PrimitiveType::String, actual) => {
+                matches!(actual, ArrowDataType::Utf8 | ArrowDataType::LargeUtf8)
+            }
+            (PrimitiveType::Binary, actual) => matches!(
+                actual,
+                ArrowDataType::Binary
+                    | ArrowDataType::LargeBinary
+                    | ArrowDataType::FixedSizeBinary(_)
+            ),
+            (primitive, actual) => ArrowDataType::try_from(&DataType::Primitive(primitive.clone()))
+                .is_ok_and(|expected| expected == *actual),
+        },
+        (DataType::Array(array), ArrowDataType::List(field) | ArrowDataType::LargeList(field)) => {
+            let element = format!("{column}.element");
+            return ensure_compatible_type(
+                array.element_type(),
+                field.data_type(),
+                &element,
+                location,
+                trim_names,
+            );
+        }
+        (DataType::Map(map), ArrowDataType::Map(entries, _)) => match entries.data_type() {
+            ArrowDataType::Struct(fields) if fields.len() == 2 => {
+                let key = format!("{column}.key");
+                ensure_compatible_type(
+                    &map.key_type,
+                    fields[0].data_type(),
+                    &key,
+                    location,
+                    trim_names,
+                )?;
+                let value = format!("{column}.value");
+                return ensure_compatible_type(
+                    &map.value_type,
+                    fields[1].data_type(),
+                    &value,
+                    location,
+                    trim_names,
+                );
+            }
+            _ => false,
+        },
+        (DataType::Struct(requested), ArrowDataType::Struct(fields)) => {
+            let prefix = format!("{column}.");
+            return ensure_compatible_fields(requested, fields, &prefix, location, trim_names);
+        }
+        _ => false,
+    };
     require!(
-        mask_indicies.len() == requested_schema.fields.len(),
-        Error::generic("Didn't find all requested columns in parquet schema")
+        compatible,
+        Error::schema_mismatch(location, column, requested, actual)
     );
-    Ok((mask_indicies, reorder_indicies))
+    Ok(())
 }
 
 /// Create a mask that will only select the specified indicies from the parquet. Currently we only
 /// handle "root" level columns, and hence use `ProjectionMask::roots`, but will support leaf
 /// selection in the future. See issues #86 and #96 as well.
 pub(crate) fn generate_mask(
-    requested_schema: &SchemaRef,
     parquet_schema: &ArrowSchemaRef,
     parquet_physical_schema: &SchemaDescriptor,
     indicies: &[usize],
 ) -> Option<ProjectionMask> {
-    if parquet_schema.fields.size() == requested_schema.fields.len() {
-        // we assume that in get_requested_indicies we will have caught any column name mismatches,
-        // so here we can just say that if we request the same # of columns as the parquet file
-        // actually has, we don't need to mask anything out
+    if parquet_schema.fields.size() == indicies.len() {
+        // we select every column in the parquet file, so we don't need to mask anything out
         None
     } else {
         Some(ProjectionMask::roots(
@@ -62,28 +539,413 @@ pub(crate) fn generate_mask(
     }
 }
 
-/// Reorder a RecordBatch to match `requested_ordering`. For each non-zero value in
-/// `requested_ordering`, the column at that index will be added in order to returned batch
+/// Reorder the columns of a RecordBatch read from a parquet file to match `requested_schema`.
+/// Requested columns that weren't read (because they don't exist in the file) are filled with
+/// nulls, and so are requested fields of struct columns that don't exist in the file, e.g. the
+/// `stats` of the `add` actions of a checkpoint that was written without stats. Only nullable
+/// columns and fields are filled: this fails if a non-nullable one is missing. Timestamp columns
+/// (and fields) are cast to the unit and timezone of the requested type. Columns are matched
+/// by name like in [`get_requested_indices`], and take the requested name.
 pub(crate) fn reorder_record_batch(
     input_data: RecordBatch,
-    requested_ordering: &[usize],
+    requested_schema: &SchemaRef,
+    trim_names: bool,
 ) -> DeltaResult<RecordBatch> {
-    if requested_ordering.windows(2).all(|is| is[0] < is[1]) {
-        // indicies is already sorted, meaning we requested in the order that the columns were
-        // stored in the parquet
-        Ok(input_data)
-    } else {
-        // requested an order different from the parquet, reorder
-        let input_schema = input_data.schema();
-        let mut fields = Vec::with_capacity(requested_ordering.len());
-        let reordered_columns = requested_ordering
+    let input_schema = input_data.schema();
+    let in_order = input_schema.fields().len() == requested_schema.fields.len()
+        && input_schema
+            .fields()
             .iter()
-            .map(|index| {
-                fields.push(input_schema.field(*index).clone());
-                input_data.column(*index).clone() // cheap Arc clone
-            })
-            .collect();
-        let schema = Arc::new(ArrowSchema::new(fields));
-        Ok(RecordBatch::try_new(schema, reordered_columns)?)
+            .zip(requested_schema.fields())
+            .enumerate()
+            .all(|(index, (input_field, requested_field))| {
+                input_field.name() == requested_field.name()
+                    && find_column(input_schema.fields(), requested_field, trim_names)
+                        == Some(index)
+            });
+    let complete =
+        input_data
+            .columns()
+            .iter()
+            .zip(input_schema.fields())
+            .all(|(column, input_field)| {
+                requested_schema
+                    .field(input_field.name())
+                    .is_none_or(|field| !needs_projection(column, field.data_type()))
+            });
+    if in_order && complete {
+        // we requested the columns in the order they are stored in the parquet
+        return Ok(input_data);
+    }
+    let mut fields = Vec::with_capacity(requested_schema.fields.len());
+    let mut columns = Vec::with_capacity(requested_schema.fields.len());
+    for requested_field in requested_schema.fields() {
+        match find_column(input_schema.fields(), requested_field, trim_names) {
+            Some(index) => {
+                let column = project_column(input_data.column(index), requested_field, trim_names)?;
+                let field = input_schema
+                    .field(index)
+                    .clone()
+                    .with_name(requested_field.name())
+                    .with_data_type(column.data_type().clone());
+                fields.push(field);
+                columns.push(column);
+            }
+            None => {
+                require!(
+                    requested_field.is_nullable(),
+                    Error::generic(format!(
+                        "Didn't find non-nullable requested column {} in parquet schema",
+                        requested_field.name()
+                    ))
+                );
+                let field = ArrowField::try_from(requested_field)?;
+                columns.push(new_null_array(field.data_type(), input_data.num_rows()));
+                fields.push(field);
+            }
+        }
+    }
+    let schema = Arc::new(ArrowSchema::new(fields));
+    Ok(RecordBatch::try_new(schema, columns)?)
+}
+
+/// Whether `column` has to be converted to match `requested_type`: it is a timestamp column with
+/// another unit than microseconds (e.g. an `INT96` column, which reads as nanoseconds) or another
+/// timezone than that of the requested type (UTC, or none for `timestamp_ntz`), or a
+/// struct column whose (possibly nested) fields differ from the fields of `requested_type`, i.e. it
+/// lacks some of them, has fields that weren't requested (e.g. because only some fields of a
+/// struct column are read), has them in another order, or has such timestamp fields
+fn needs_projection(column: &ArrayRef, requested_type: &DataType) -> bool {
+    match (column.as_struct_opt(), requested_type) {
+        (Some(column), DataType::Struct(requested)) => {
+            column.num_columns() != requested.fields.len()
+                || requested.fields().enumerate().any(|(index, field)| {
+                    column.column_names().get(index) != Some(&field.name().as_str())
+                        || needs_projection(column.column(index), field.data_type())
+                })
+        }
+        (None, &DataType::TIMESTAMP | &DataType::TIMESTAMP_NTZ) => {
+            matches!(column.data_type(), ArrowDataType::Timestamp(..))
+                && ArrowDataType::try_from(requested_type)
+                    .is_ok_and(|expected| *column.data_type() != expected)
+        }
+        _ => false,
+    }
+}
+
+/// Convert `column` to match `requested_field`: timestamps are cast to microseconds, the unit of
+/// Delta timestamps, in UTC (or without a timezone for `timestamp_ntz`), and struct columns are
+/// projected to the requested fields, adding null children for the fields they lack and dropping
+/// the ones that weren't requested. Fields are matched like columns in [`get_requested_indices`].
+/// The children of the result are in the order of the requested fields, and take their names.
+///
+/// A timestamp without a timezone (e.g. from an `INT96` column) requested as a `timestamp` is
+/// taken to be in UTC, like Delta writers store them. A timestamp with a timezone can't be read as
+/// a `timestamp_ntz`, since its values are instants rather than wall clock times.
+fn project_column(
+    column: &ArrayRef,
+    requested_field: &StructField,
+    trim_names: bool,
+) -> DeltaResult<ArrayRef> {
+    if !needs_projection(column, requested_field.data_type()) {
+        return Ok(column.clone()); // cheap Arc clone
+    }
+    if let ArrowDataType::Timestamp(_, tz) = column.data_type() {
+        if let (Some(tz), &DataType::TIMESTAMP_NTZ) = (tz, requested_field.data_type()) {
+            return Err(Error::unexpected_column_type(format!(
+                "Column {} is a timestamp_ntz, but its data has the timezone {tz}",
+                requested_field.name()
+            )));
+        }
+        let expected = ArrowDataType::try_from(requested_field.data_type())?;
+        return Ok(cast(column, &expected)?);
+    }
+    let (Some(struct_array), DataType::Struct(requested)) =
+        (column.as_struct_opt(), requested_field.data_type())
+    else {
+        return Ok(column.clone());
+    };
+    let (input_fields, _, nulls) = struct_array.clone().into_parts();
+    let mut fields = Vec::with_capacity(requested.fields.len());
+    let mut children = Vec::with_capacity(requested.fields.len());
+    for field in requested.fields() {
+        match find_column(&input_fields, field, trim_names) {
+            Some(index) => {
+                let child = project_column(struct_array.column(index), field, trim_names)?;
+                fields.push(
+                    input_fields[index]
+                        .as_ref()
+                        .clone()
+                        .with_name(field.name())
+                        .with_data_type(child.data_type().clone()),
+                );
+                children.push(child);
+            }
+            None => {
+                require!(
+                    field.is_nullable(),
+                    Error::generic(format!(
+                        "Didn't find non-nullable requested field {} of column {} in parquet schema",
+                        field.name(),
+                        requested_field.name()
+                    ))
+                );
+                let field = ArrowField::try_from(field)?;
+                children.push(new_null_array(field.data_type(), column.len()));
+                fields.push(field);
+            }
+        }
+    }
+    Ok(Arc::new(StructArray::try_new(
+        fields.into(),
+        children,
+        nulls,
+    )?))
+}
+
+/// Give the (possibly nested) fields of `batch` the metadata of the corresponding fields of
+/// `schema`, e.g. the arrow schema of the output schema of a scan. Fields are matched by position,
+/// and nested fields that have no counterpart in `schema` keep their metadata. Only the fields
+/// change, the data isn't copied.
+pub(crate) fn with_field_metadata(
+    batch: &RecordBatch,
+    schema: &ArrowSchema,
+) -> DeltaResult<RecordBatch> {
+    if batch.schema().fields() == schema.fields() {
+        return Ok(batch.clone());
+    }
+    let (fields, columns): (Vec<_>, Vec<_>) = batch
+        .schema()
+        .fields()
+        .iter()
+        .zip(batch.columns())
+        .enumerate()
+        .map(|(index, (field, column))| -> DeltaResult<_> {
+            let Some(target) = schema.fields().get(index) else {
+                return Ok((field.as_ref().clone(), column.clone()));
+            };
+            let column = with_nested_metadata(column, target.data_type())?;
+            let field = field
+                .as_ref()
+                .clone()
+                .with_data_type(column.data_type().clone())
+                .with_metadata(target.metadata().clone());
+            Ok((field, column))
+        })
+        .process_results(|iter| iter.unzip())?;
+    let schema = ArrowSchema::new_with_metadata(fields, batch.schema().metadata().clone());
+    Ok(RecordBatch::try_new(Arc::new(schema), columns)?)
+}
+
+/// Give the nested fields of `column` (the fields of a struct, the element of a list, or the
+/// entries of a map) the metadata of the corresponding fields of `target`
+fn with_nested_metadata(column: &ArrayRef, target: &ArrowDataType) -> DeltaResult<ArrayRef> {
+    if column.data_type() == target {
+        return Ok(column.clone());
+    }
+    let with_metadata_of = |field: &ArrowField, target: &ArrowField, child: &ArrayRef| {
+        Arc::new(
+            field
+                .clone()
+                .with_data_type(child.data_type().clone())
+                .with_metadata(target.metadata().clone()),
+        )
+    };
+    match (column.data_type(), target) {
+        (ArrowDataType::Struct(fields), ArrowDataType::Struct(targets))
+            if fields.len() == targets.len() =>
+        {
+            let (_, children, nulls) = column.as_struct().clone().into_parts();
+            let children: Vec<_> = children
+                .iter()
+                .zip(targets.iter())
+                .map(|(child, target)| with_nested_metadata(child, target.data_type()))
+                .try_collect()?;
+            let fields: Fields = fields
+                .iter()
+                .zip(targets.iter())
+                .zip(&children)
+                .map(|((field, target), child)| with_metadata_of(field, target, child))
+                .collect();
+            Ok(Arc::new(StructArray::try_new(fields, children, nulls)?))
+        }
+        (ArrowDataType::List(field), ArrowDataType::List(target)) => {
+            let (_, offsets, values, nulls) = column.as_list::<i32>().clone().into_parts();
+            let values = with_nested_metadata(&values, target.data_type())?;
+            let field = with_metadata_of(field, target, &values);
+            Ok(Arc::new(ListArray::try_new(field, offsets, values, nulls)?))
+        }
+        (ArrowDataType::Map(field, _), ArrowDataType::Map(target, _)) => {
+            let (_, offsets, entries, nulls, ordered) = column.as_map().clone().into_parts();
+            let entries: ArrayRef = Arc::new(entries);
+            let entries = with_nested_metadata(&entries, target.data_type())?;
+            let field = with_metadata_of(field, target, &entries);
+            let entries = entries.as_struct().clone();
+            Ok(Arc::new(MapArray::try_new(
+                field, offsets, entries, nulls, ordered,
+            )?))
+        }
+        _ => Ok(column.clone()),
+    }
+}
+
+/// Build statistics for parquet files from their footers, with one row per footer in `footers`.
+/// The statistics follow the Delta [per-file statistics] format, and have the schema
+/// `stats_schema`. Only `numRecords`, `minValues`, and `maxValues` are filled in, everything else
+/// is null. In particular, null counts are left out since parquet can't tell us if they're missing.
+///
+/// [per-file statistics]: https://github.com/delta-io/delta/blob/master/PROTOCOL.md#Per-file-Statistics
+pub(crate) fn stats_from_footers(
+    footers: &[Arc<ParquetMetaData>],
+    stats_schema: &StructType,
+) -> DeltaResult<RecordBatch> {
+    let stats = footers.iter().map(|footer| {
+        let num_rows = footer.file_metadata().num_rows();
+        (num_rows, footer.as_ref(), footer.row_groups())
+    });
+    parse_stats(stats, stats_schema)
+}
+
+/// Build statistics for the row groups of a parquet file from its footer, with one row per row
+/// group, in the same format as [`stats_from_footers`]. These allow skipping the row groups of a
+/// file that can't match a predicate, just like data skipping skips whole files.
+#[cfg(feature = "default-engine")]
+pub(crate) fn stats_from_row_groups(
+    footer: &ParquetMetaData,
+    stats_schema: &StructType,
+) -> DeltaResult<RecordBatch> {
+    let row_groups = footer.row_groups();
+    let stats = (0..row_groups.len()).map(|index| {
+        let row_group = &row_groups[index..=index];
+        (row_group[0].num_rows(), footer, row_group)
+    });
+    parse_stats(stats, stats_schema)
+}
+
+/// Parse the statistics of each `(num_rows, footer, row_groups)` in `stats` into one row of a
+/// batch with the schema `stats_schema`.
+fn parse_stats<'a>(
+    stats: impl Iterator<Item = (i64, &'a ParquetMetaData, &'a [RowGroupMetaData])>,
+    stats_schema: &StructType,
+) -> DeltaResult<RecordBatch> {
+    let stats_columns: Vec<&StructField> =
+        match stats_schema.field("minValues").map(StructField::data_type) {
+            Some(DataType::Struct(columns)) => columns.fields().collect(),
+            _ => vec![],
+        };
+    let mut json = vec![];
+    for (num_rows, footer, row_groups) in stats {
+        let mut min_values = Map::new();
+        let mut max_values = Map::new();
+        for column in stats_columns.iter() {
+            if let Some((min, max)) = row_groups_min_max(footer, row_groups, column) {
+                min_values.insert(column.name().clone(), min);
+                max_values.insert(column.name().clone(), max);
+            }
+        }
+        let stats = json!({
+            "numRecords": num_rows,
+            "minValues": min_values,
+            "maxValues": max_values,
+        });
+        json.extend(stats.to_string().into_bytes());
+        json.push(b'\n');
+    }
+    let schema: ArrowSchemaRef = Arc::new(stats_schema.try_into()?);
+    let batches: Vec<_> = ReaderBuilder::new(schema.clone())
+        .build(Cursor::new(json))?
+        .try_collect()?;
+    Ok(concat_batches(&schema, &batches)?)
+}
+
+/// Get the min and max values of a (top-level) column over `row_groups` of a parquet file with
+/// the footer `footer`, as json values. Returns `None` if any row group is missing statistics for
+/// the column, or if the column's type isn't supported.
+fn row_groups_min_max(
+    footer: &ParquetMetaData,
+    row_groups: &[RowGroupMetaData],
+    column: &StructField,
+) -> Option<(Value, Value)> {
+    use PrimitiveType::*;
+
+    let column_index = footer
+        .file_metadata()
+        .schema_descr()
+        .columns()
+        .iter()
+        .position(|descr| descr.path().parts() == [column.name().as_str()])?;
+    let stats: Vec<&Statistics> = row_groups
+        .iter()
+        .map(|row_group| {
+            row_group
+                .column(column_index)
+                .statistics()
+                .filter(|stats| stats.has_min_max_set())
+        })
+        .collect::<Option<_>>()?;
+
+    // fold the min/max of each row group into the min/max of all of them
+    fn fold<'a, T: PartialOrd + Clone + 'a>(
+        stats: impl Iterator<Item = Option<(&'a T, &'a T)>>,
+    ) -> Option<(T, T)> {
+        stats.fold(None, |acc, stats| {
+            let (min, max) = stats?;
+            match acc {
+                None => Some((min.clone(), max.clone())),
+                Some((acc_min, acc_max)) => Some((
+                    if *min < acc_min { min.clone() } else { acc_min },
+                    if *max > acc_max { max.clone() } else { acc_max },
+                )),
+            }
+        })
+    }
+    macro_rules! fold_stats {
+        ($variant: ident) => {
+            fold(stats.iter().map(|stats| match stats {
+                Statistics::$variant(stats) => Some((stats.min(), stats.max())),
+                _ => None,
+            }))
+        };
+    }
+
+    let DataType::Primitive(data_type) = column.data_type() else {
+        return None;
+    };
+    match data_type {
+        Byte | Short | Integer => fold_stats!(Int32).map(|(min, max)| (json!(min), json!(max))),
+        Long => match stats.first()? {
+            Statistics::Int32(_) => fold_stats!(Int32).map(|(min, max)| (json!(min), json!(max))),
+            _ => fold_stats!(Int64).map(|(min, max)| (json!(min), json!(max))),
+        },
+        Float => fold_stats!(Float)
+            .filter(|(min, max)| !min.is_nan() && !max.is_nan())
+            .map(|(min, max)| (json!(min), json!(max))),
+        Double => fold_stats!(Double)
+            .filter(|(min, max)| !min.is_nan() && !max.is_nan())
+            .map(|(min, max)| (json!(min), json!(max))),
+        Date => {
+            let to_date = |days: i32| {
+                let epoch = NaiveDate::from_ymd_opt(1970, 1, 1)?;
+                let date = match u64::try_from(days) {
+                    Ok(days) => epoch.checked_add_days(Days::new(days)),
+                    Err(_) => epoch.checked_sub_days(Days::new(days.unsigned_abs() as u64)),
+                }?;
+                Some(json!(date.format("%Y-%m-%d").to_string()))
+            };
+            let (min, max) = fold_stats!(Int32)?;
+            Some((to_date(min)?, to_date(max)?))
+        }
+        String => {
+            // the deprecated min/max fields were written with a signed (i.e. not utf-8) ordering
+            if stats.iter().any(|stats| stats.is_min_max_deprecated()) {
+                return None;
+            }
+            let (min, max) = fold_stats!(ByteArray)?;
+            Some((
+                json!(min.as_utf8().ok()?.to_string()),
+                json!(max.as_utf8().ok()?.to_string()),
+            ))
+        }
+        _ => None,
     }
 }
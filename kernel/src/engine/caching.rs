@@ -0,0 +1,391 @@
+//! A [`FileSystemClient`] decorator that caches the bytes read from another client on local disk,
+//! so that repeated reads of the same (e.g. remote) table are served from the cache.
+//!
+//! Listings are never cached, since the log is listed to find new commits. They are used to
+//! validate the cache instead: a cached read of a file is only served if its `last_modified` is
+//! the same as the last time the file was listed. Files that were never listed (like data files and
+//! deletion vectors) are looked up with a listing of their own before a cached read of them is
+//! served.
+use std::collections::{BTreeMap, HashMap};
+use std::ops::Range;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+use bytes::Bytes;
+use url::Url;
+
+use crate::utils::list_file;
+use crate::{DeltaResult, Error, FileMeta, FileSlice, FileSystemClient};
+
+/// The name of the only file in the log which is overwritten in place, and so is never cached
+const LAST_CHECKPOINT_FILE_NAME: &str = "_last_checkpoint";
+
+type CacheKey = (Url, Option<Range<usize>>);
+
+/// A cached read of a file, stored on disk at `path`
+struct CacheEntry {
+    path: PathBuf,
+    size: usize,
+    /// The `last_modified` of the file when it was read
+    last_modified: i64,
+    /// When the entry was last used, its key in [`CacheState::lru`]
+    last_used: u64,
+}
+
+#[derive(Default)]
+struct CacheState {
+    entries: HashMap<CacheKey, CacheEntry>,
+    /// The keys of the entries by when they were last used, from least to most recently used
+    lru: BTreeMap<u64, CacheKey>,
+    /// The `last_modified` of each file as of the last time it was listed
+    last_modified: HashMap<Url, i64>,
+    size: usize,
+    clock: u64,
+    next_id: u64,
+}
+
+impl CacheState {
+    fn tick(&mut self) -> u64 {
+        self.clock += 1;
+        self.clock
+    }
+
+    fn remove(&mut self, key: &CacheKey) {
+        if let Some(entry) = self.entries.remove(key) {
+            self.lru.remove(&entry.last_used);
+            self.size -= entry.size;
+            // the cache is only an optimization, so failing to delete the file isn't an error
+            let _ = std::fs::remove_file(entry.path);
+        }
+    }
+
+    /// Get the path of the cached read of `key`, if there is one of the file as of
+    /// `last_modified`, marking it as the most recently used. A stale read is removed.
+    fn get(&mut self, key: &CacheKey, last_modified: i64) -> Option<PathBuf> {
+        let entry = self.entries.get(key)?;
+        if entry.last_modified != last_modified {
+            self.remove(key);
+            return None;
+        }
+        let (previous_use, path) = (entry.last_used, entry.path.clone());
+        let last_used = self.tick();
+        self.lru.remove(&previous_use);
+        self.lru.insert(last_used, key.clone());
+        if let Some(entry) = self.entries.get_mut(key) {
+            entry.last_used = last_used;
+        }
+        Some(path)
+    }
+
+    /// Evict least recently used entries until `size` more bytes fit in `max_size`
+    fn make_room(&mut self, size: usize, max_size: usize) {
+        while self.size + size > max_size {
+            let Some((_, key)) = self.lru.pop_first() else {
+                break;
+            };
+            self.remove(&key);
+        }
+    }
+}
+
+/// A [`FileSystemClient`] that caches the results of [`FileSystemClient::read_files`] of an inner
+/// client in a local directory, keyed by the location, byte range and `last_modified` of each
+/// read. The total size of the cached reads is kept within a budget by evicting the least recently
+/// used ones.
+///
+/// The index of the cache is kept in memory, so the cache lives as long as the client does. Files
+/// are assumed not to change unless a listing reports a new `last_modified` for them, except for
+/// `_last_checkpoint`, which is never cached. Files that were never listed are listed (on their
+/// own) whenever they are read, to check that a cached read of them is still valid.
+pub struct CachingFileSystemClient {
+    inner: Arc<dyn FileSystemClient>,
+    cache_dir: PathBuf,
+    max_size: usize,
+    state: Arc<Mutex<CacheState>>,
+}
+
+impl std::fmt::Debug for CachingFileSystemClient {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CachingFileSystemClient")
+            .field("cache_dir", &self.cache_dir)
+            .field("max_size", &self.max_size)
+            .finish_non_exhaustive()
+    }
+}
+
+impl CachingFileSystemClient {
+    /// Create a client caching the reads of `inner` in `cache_dir` (which is created if it doesn't
+    /// exist), using up to `max_size` bytes of disk space.
+    pub fn try_new(
+        inner: Arc<dyn FileSystemClient>,
+        cache_dir: impl Into<PathBuf>,
+        max_size: usize,
+    ) -> DeltaResult<Self> {
+        let cache_dir = cache_dir.into();
+        std::fs::create_dir_all(&cache_dir)?;
+        Ok(Self {
+            inner,
+            cache_dir,
+            max_size,
+            state: Default::default(),
+        })
+    }
+
+    /// The total size in bytes of the reads that are currently cached
+    pub fn cached_size(&self) -> usize {
+        self.state.lock().unwrap().size
+    }
+
+    /// The current `last_modified` of the file at `location`: as of the last time it was listed,
+    /// or as listed now if it never was. `None` if it isn't cacheable or can't be listed.
+    fn current_last_modified(&self, location: &Url) -> Option<i64> {
+        if !is_cacheable(location) {
+            return None;
+        }
+        let listed = self
+            .state
+            .lock()
+            .unwrap()
+            .last_modified
+            .get(location)
+            .copied();
+        listed.or_else(|| self.list_one(location))
+    }
+
+    /// List the file at `location` on its own, returning its `last_modified` if it exists
+    fn list_one(&self, location: &Url) -> Option<i64> {
+        let file = list_file(self.inner.as_ref(), location).ok()??;
+        Some(file.last_modified)
+    }
+}
+
+/// Whether reads of the file at `location` can be cached
+fn is_cacheable(location: &Url) -> bool {
+    location
+        .path_segments()
+        .and_then(|mut segments| segments.next_back())
+        != Some(LAST_CHECKPOINT_FILE_NAME)
+}
+
+/// Read a single file slice with `client`
+fn read_one(client: &dyn FileSystemClient, file: FileSlice) -> DeltaResult<Bytes> {
+    let location = file.0.clone();
+    client
+        .read_files(vec![file])?
+        .next()
+        .unwrap_or_else(|| Err(Error::file_not_found(location)))
+}
+
+/// Cache the `data` read for `key` from the file as of `last_modified` on disk, if it fits in the
+/// budget
+fn insert(
+    state: &Mutex<CacheState>,
+    cache_dir: &std::path::Path,
+    max_size: usize,
+    key: CacheKey,
+    last_modified: i64,
+    data: &Bytes,
+) {
+    if data.len() > max_size {
+        return;
+    }
+    let mut state = state.lock().unwrap();
+    state.remove(&key);
+    state.make_room(data.len(), max_size);
+    let path = cache_dir.join(format!("{}.bin", state.next_id));
+    state.next_id += 1;
+    if std::fs::write(&path, data).is_err() {
+        return;
+    }
+    let entry = CacheEntry {
+        path,
+        size: data.len(),
+        last_modified,
+        last_used: state.tick(),
+    };
+    state.size += entry.size;
+    state.lru.insert(entry.last_used, key.clone());
+    state.entries.insert(key, entry);
+}
+
+impl CachingFileSystemClient {
+    /// Record the `last_modified` of every listed file, to validate the cache with
+    fn record_listing(
+        &self,
+        files: Box<dyn Iterator<Item = DeltaResult<FileMeta>>>,
+    ) -> Box<dyn Iterator<Item = DeltaResult<FileMeta>>> {
+        let state = self.state.clone();
+        let files = files.inspect(move |file| {
+            if let Ok(file) = file {
+                let mut state = state.lock().unwrap();
+                state
+                    .last_modified
+                    .insert(file.location.clone(), file.last_modified);
+            }
+        });
+        Box::new(files)
+    }
+}
+
+impl FileSystemClient for CachingFileSystemClient {
+    fn list_from(
+        &self,
+        path: &Url,
+    ) -> DeltaResult<Box<dyn Iterator<Item = DeltaResult<FileMeta>>>> {
+        Ok(self.record_listing(self.inner.list_from(path)?))
+    }
+
+    fn list_from_recursive(
+        &self,
+        path: &Url,
+    ) -> DeltaResult<Box<dyn Iterator<Item = DeltaResult<FileMeta>>>> {
+        Ok(self.record_listing(self.inner.list_from_recursive(path)?))
+    }
+
+    fn read_files(
+        &self,
+        files: Vec<FileSlice>,
+    ) -> DeltaResult<Box<dyn Iterator<Item = DeltaResult<Bytes>>>> {
+        // look up every read up front, so the misses can be read from the inner client together
+        let lookups: Vec<_> = files
+            .iter()
+            .map(|file| {
+                let last_modified = self.current_last_modified(&file.0)?;
+                let cached = self.state.lock().unwrap().get(file, last_modified);
+                Some((last_modified, cached))
+            })
+            .collect();
+        let misses: Vec<_> = files
+            .iter()
+            .zip(&lookups)
+            .filter(|(_, lookup)| !matches!(lookup, Some((_, Some(_)))))
+            .map(|(file, _)| file.clone())
+            .collect();
+        let mut inner_reads = match misses.is_empty() {
+            true => Box::new(std::iter::empty()),
+            false => self.inner.read_files(misses)?,
+        };
+
+        let inner = self.inner.clone();
+        let state = self.state.clone();
+        let cache_dir = self.cache_dir.clone();
+        let max_size = self.max_size;
+        let reads = files.into_iter().zip(lookups).map(move |(file, lookup)| {
+            let (last_modified, cached) = lookup.unzip();
+            let cached = cached.flatten();
+            if let Some(data) = cached.as_ref().and_then(|path| std::fs::read(path).ok()) {
+                return Ok(Bytes::from(data));
+            }
+            let data = match cached {
+                // the cached read was evicted since it was looked up, so read it again
+                Some(_) => read_one(inner.as_ref(), file.clone())?,
+                None => inner_reads
+                    .next()
+                    .unwrap_or_else(|| read_one(inner.as_ref(), file.clone()))?,
+            };
+            if let Some(last_modified) = last_modified {
+                insert(&state, &cache_dir, max_size, file, last_modified, &data);
+            }
+            Ok(data)
+        });
+        Ok(Box::new(reads))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::in_memory::InMemoryEngine;
+    use crate::Engine;
+
+    fn read(client: &dyn FileSystemClient, files: &[FileSlice]) -> Vec<Bytes> {
+        client
+            .read_files(files.to_vec())
+            .unwrap()
+            .collect::<DeltaResult<_>>()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_caching_file_system_client() {
+        let engine = InMemoryEngine::new();
+        let cache_dir = tempfile::tempdir().unwrap();
+        let url = |name: &str| Url::parse("memory:///table/").unwrap().join(name).unwrap();
+        engine.put(url("a"), "0123456789");
+        engine.put(url("b"), "abcdefghij");
+        engine.put(url(LAST_CHECKPOINT_FILE_NAME), "{}");
+        let inner = engine.get_file_system_client();
+        let client = CachingFileSystemClient::try_new(inner, cache_dir.path(), 15).unwrap();
+        // the number of slices read with the inner client so far
+        let mut total_reads = 0;
+        let mut reads = || {
+            total_reads += engine.take_reads().len();
+            total_reads
+        };
+
+        let files = [(url("a"), Some(2..5)), (url("b"), None)];
+        let expected = [Bytes::from("234"), Bytes::from("abcdefghij")];
+        assert_eq!(read(&client, &files), expected);
+        assert_eq!(reads(), 2);
+        assert_eq!(client.cached_size(), 13);
+
+        // cached reads are served from disk, and other ranges of the same file are not
+        assert_eq!(read(&client, &files), expected);
+        assert_eq!(reads(), 2);
+        let files = [(url("a"), Some(2..5)), (url("a"), Some(0..3))];
+        assert_eq!(
+            read(&client, &files),
+            [Bytes::from("234"), Bytes::from("012")]
+        );
+        assert_eq!(reads(), 3);
+        // which evicted the least recently used read of b to stay within the budget
+        assert_eq!(client.cached_size(), 6);
+        read(&client, &[(url("b"), None)]);
+        assert_eq!(reads(), 4);
+
+        // a file that was never listed is listed on its own to validate its cached reads, so
+        // rewriting it invalidates them
+        let rewrite = |name: &str, data: &'static str| engine.put(url(name), data);
+        rewrite("b", "ABCDEFGHIJ");
+        assert_eq!(
+            read(&client, &[(url("b"), None)]),
+            [Bytes::from("ABCDEFGHIJ")]
+        );
+        assert_eq!(reads(), 5);
+        read(&client, &[(url("b"), None)]);
+        assert_eq!(reads(), 5);
+
+        // a listed file is validated by its last listing, so its cached reads are served until a
+        // listing reports a different last_modified
+        let listed = |client: &CachingFileSystemClient| {
+            client
+                .list_from(&url("a"))
+                .unwrap()
+                .collect::<DeltaResult<Vec<_>>>()
+                .unwrap()
+                .len()
+        };
+        assert_eq!(listed(&client), 2);
+        // (the reads of a were evicted to make room for b)
+        read(&client, &[(url("a"), Some(2..5))]);
+        assert_eq!(reads(), 6);
+        rewrite("a", "9876543210");
+        assert_eq!(
+            read(&client, &[(url("a"), Some(2..5))]),
+            [Bytes::from("234")]
+        );
+        assert_eq!(reads(), 6);
+        assert_eq!(listed(&client), 2);
+        assert_eq!(
+            read(&client, &[(url("a"), Some(2..5))]),
+            [Bytes::from("765")]
+        );
+        assert_eq!(reads(), 7);
+
+        // _last_checkpoint is overwritten in place, so it's never cached
+        let files = [(url(LAST_CHECKPOINT_FILE_NAME), None)];
+        read(&client, &files);
+        read(&client, &files);
+        assert_eq!(reads(), 9);
+    }
+}
@@ -0,0 +1,192 @@
+//! Adapters to feed the results of a [`Scan`] into [DataFusion]. This is enabled by the
+//! `datafusion` feature flag.
+//!
+//! [`ScanStream`] reads the data of a scan as a DataFusion [`RecordBatchStream`], with the
+//! deletion vector masks of the scan already applied and a stable schema, so that it can be
+//! returned from the `execute` method of the `ExecutionPlan` of a DataFusion `TableProvider`.
+//!
+//! [DataFusion]: https://datafusion.apache.org/
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use arrow_array::RecordBatch;
+use arrow_schema::{Schema as ArrowSchema, SchemaRef as ArrowSchemaRef};
+use arrow_select::filter::filter_record_batch;
+use datafusion_common::DataFusionError;
+use datafusion_execution::{RecordBatchStream, SendableRecordBatchStream};
+use futures::{FutureExt, Stream};
+use tokio::task::JoinHandle;
+
+use crate::engine::arrow_data::ArrowEngineData;
+use crate::scan::{execute_lazily, Scan, ScanResult};
+use crate::schema::DataType;
+use crate::{DeltaResult, Engine, Error};
+
+/// The arrow schema of the batches of a [`ScanStream`] of `scan`, i.e. its logical schema
+pub fn arrow_schema(scan: &Scan) -> DeltaResult<ArrowSchemaRef> {
+    match scan.output_type() {
+        DataType::Struct(schema) => Ok(Arc::new(ArrowSchema::try_from(schema.as_ref())?)),
+        data_type => Err(Error::unexpected_column_type(format!(
+            "Expected the scan output to be a struct, got {data_type}"
+        ))),
+    }
+}
+
+/// A DataFusion [`RecordBatchStream`] of the data of a [`Scan`]. Rows removed by deletion vectors
+/// are filtered out, and every batch has the schema returned by [`arrow_schema`].
+///
+/// The files of the scan are opened as the stream is polled, up to the read-ahead depth set with
+/// [`crate::scan::ScanBuilder::with_read_ahead`] beyond the one being consumed. Reading uses the
+/// (blocking) handlers of the engine, so each batch is read with [`tokio::task::spawn_blocking`]
+/// and polling the stream never blocks. The stream must therefore be polled within a tokio
+/// runtime, like DataFusion polls the streams of its plans.
+pub struct ScanStream {
+    schema: ArrowSchemaRef,
+    state: ReadState,
+}
+
+/// The results of a scan, boxed as an iterator
+type ScanResults = Box<dyn Iterator<Item = DeltaResult<ScanResult>> + Send>;
+
+/// The state of the reads of a [`ScanStream`]
+enum ReadState {
+    /// No read is in flight
+    Idle(ScanResults),
+    /// The next result is being read on a blocking thread, which hands back the results with it
+    Reading(JoinHandle<(ScanResults, Option<DeltaResult<ScanResult>>)>),
+    /// All the results were read, or reading them failed
+    Done,
+}
+
+impl ScanStream {
+    /// Create a stream of the data of `scan`, read with `engine`. This performs the log replay of
+    /// the scan lazily, like [`Scan::execute_iter`].
+    pub fn try_new(scan: Arc<Scan>, engine: Arc<dyn Engine>) -> DeltaResult<Self> {
+        let schema = arrow_schema(&scan)?;
+        let results = execute_lazily(scan, engine)?;
+        Ok(Self {
+            schema,
+            state: ReadState::Idle(Box::new(results)),
+        })
+    }
+
+    /// Box this stream as a [`SendableRecordBatchStream`]
+    pub fn into_sendable(self) -> SendableRecordBatchStream {
+        Box::pin(self)
+    }
+
+    fn to_record_batch(&self, result: ScanResult) -> DeltaResult<RecordBatch> {
+        let batch: RecordBatch = ArrowEngineData::try_from_engine_data(result.raw_data?)?.into();
+        let batch = match result.mask {
+            Some(mut mask) => {
+                // rows past the end of the mask are valid
+                mask.resize(batch.num_rows(), true);
+                filter_record_batch(&batch, &mask.into())?
+            }
+            None => batch,
+        };
+        // use the scan's schema for every batch, regardless of the metadata of the file it was
+        // read from
+        Ok(RecordBatch::try_new(
+            self.schema.clone(),
+            batch.columns().to_vec(),
+        )?)
+    }
+}
+
+impl Stream for ScanStream {
+    type Item = datafusion_common::Result<RecordBatch>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        loop {
+            match std::mem::replace(&mut this.state, ReadState::Done) {
+                ReadState::Idle(mut results) => {
+                    let read = tokio::task::spawn_blocking(move || {
+                        let result = results.next();
+                        (results, result)
+                    });
+                    this.state = ReadState::Reading(read);
+                }
+                ReadState::Reading(mut read) => {
+                    let (results, result) = match read.poll_unpin(cx) {
+                        Poll::Pending => {
+                            this.state = ReadState::Reading(read);
+                            return Poll::Pending;
+                        }
+                        Poll::Ready(Ok(read)) => read,
+                        Poll::Ready(Err(err)) => {
+                            let err = Error::join_failure(err);
+                            return Poll::Ready(Some(Err(DataFusionError::External(Box::new(
+                                err,
+                            )))));
+                        }
+                    };
+                    let Some(result) = result else {
+                        return Poll::Ready(None);
+                    };
+                    this.state = ReadState::Idle(results);
+                    let batch = result
+                        .and_then(|result| this.to_record_batch(result))
+                        .map_err(|err| DataFusionError::External(Box::new(err)));
+                    return Poll::Ready(Some(batch));
+                }
+                ReadState::Done => return Poll::Ready(None),
+            }
+        }
+    }
+}
+
+impl RecordBatchStream for ScanStream {
+    fn schema(&self) -> ArrowSchemaRef {
+        self.schema.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use arrow_array::cast::AsArray;
+    use arrow_array::types::Int32Type;
+    use futures::StreamExt;
+
+    use super::*;
+    use crate::engine::sync::SyncEngine;
+    use crate::scan::ScanBuilder;
+    use crate::Table;
+
+    #[tokio::test]
+    async fn test_scan_stream() {
+        let path =
+            std::fs::canonicalize(PathBuf::from("./tests/data/table-with-dv-small/")).unwrap();
+        let url = url::Url::from_directory_path(path).unwrap();
+        let engine = Arc::new(SyncEngine::new());
+        let snapshot = Table::new(url).snapshot(engine.as_ref(), None).unwrap();
+        let scan = Arc::new(ScanBuilder::new(snapshot).build().unwrap());
+
+        let stream = ScanStream::try_new(scan.clone(), engine)
+            .unwrap()
+            .into_sendable();
+        assert_eq!(stream.schema(), arrow_schema(&scan).unwrap());
+        let batches: Vec<_> = stream
+            .collect::<Vec<_>>()
+            .await
+            .into_iter()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        let values: Vec<_> = batches
+            .iter()
+            .flat_map(|batch| {
+                batch
+                    .column(0)
+                    .as_primitive::<Int32Type>()
+                    .values()
+                    .to_vec()
+            })
+            .collect();
+        // the deletion vector removes the rows with values 0 and 9
+        assert_eq!(values, [1, 2, 3, 4, 5, 6, 7, 8]);
+    }
+}
@@ -4,10 +4,12 @@
 //!
 //! A generic trait [TaskExecutor] can be implemented with your preferred async
 //! runtime. Behind the `tokio` feature flag, we provide a both a single-threaded
-//! and multi-threaded executor based on Tokio.
-use futures::{future::BoxFuture, Future};
+//! and multi-threaded executor based on Tokio. To run the engine's IO on an existing
+//! executor without implementing the trait, wrap its spawn function in a [SpawnFnExecutor].
+use futures::channel::oneshot;
+use futures::{future::BoxFuture, Future, FutureExt};
 
-use crate::DeltaResult;
+use crate::{DeltaResult, Error};
 
 /// An executor that can be used to run async tasks. This is used by IO functions
 /// within the `DefaultEngine`.
@@ -16,11 +18,12 @@ use crate::DeltaResult;
 /// on another thread. This could be a multi-threaded runtime, like Tokio's or
 /// could be a single-threaded runtime on a background thread.
 pub trait TaskExecutor: Send + Sync + 'static {
-    /// Block on the given future, returning its output.
+    /// Block on the given future, returning its output, or an error if the
+    /// executor dropped the future before it completed.
     ///
     /// This should NOT panic if called within an async context. Thus it can't
     /// be implemented by `tokio::runtime::Runtime::block_on`.
-    fn block_on<T>(&self, task: T) -> T::Output
+    fn block_on<T>(&self, task: T) -> DeltaResult<T::Output>
     where
         T: Future + Send + 'static,
         T::Output: Send + 'static;
@@ -36,6 +39,90 @@ pub trait TaskExecutor: Send + Sync + 'static {
         R: Send + 'static;
 }
 
+/// Spawns a task of a [`SpawnFnExecutor`]
+type SpawnFn = Box<dyn Fn(BoxFuture<'static, ()>) + Send + Sync>;
+/// Runs a blocking task of a [`SpawnFnExecutor`]
+type SpawnBlockingFn = Box<dyn Fn(Box<dyn FnOnce() + Send>) + Send + Sync>;
+
+/// A [`TaskExecutor`] that runs every task of the engine with a spawn function, e.g. one that
+/// spawns onto an executor of the embedding application, so that the engine doesn't start
+/// threads or runtimes of its own for its async IO (parallel reads and prefetching).
+///
+/// The spawn function must run the future it is given to completion in the background, and not
+/// on the calling thread: the engine calls it from synchronous kernel APIs and then blocks the
+/// calling thread until the future completes, so running it inline (or on an executor driven by
+/// the calling thread) deadlocks. The futures may perform IO with the engine's object stores, so
+/// they must run wherever those can (e.g. within a tokio runtime for the object stores of the
+/// `object_store` crate). They may be spawned from within an async context.
+///
+/// Blocking work (like handing results back to a waiting kernel thread) runs with a separate
+/// spawn function, which must run it where blocking doesn't stall the futures of the executor.
+pub struct SpawnFnExecutor {
+    spawn: SpawnFn,
+    spawn_blocking: SpawnBlockingFn,
+}
+
+impl std::fmt::Debug for SpawnFnExecutor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SpawnFnExecutor").finish_non_exhaustive()
+    }
+}
+
+impl SpawnFnExecutor {
+    /// Create an executor that runs every future with `spawn` (see [`SpawnFnExecutor`] for its
+    /// contract) and every blocking task with `spawn_blocking`. The latter must run the task in
+    /// the background, on a thread where blocking doesn't stall the futures of the executor, e.g.
+    /// with `tokio::runtime::Handle::spawn_blocking` or on a dedicated thread pool.
+    pub fn new(
+        spawn: impl Fn(BoxFuture<'static, ()>) + Send + Sync + 'static,
+        spawn_blocking: impl Fn(Box<dyn FnOnce() + Send>) + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            spawn: Box::new(spawn),
+            spawn_blocking: Box::new(spawn_blocking),
+        }
+    }
+}
+
+impl TaskExecutor for SpawnFnExecutor {
+    fn block_on<T>(&self, task: T) -> DeltaResult<T::Output>
+    where
+        T: Future + Send + 'static,
+        T::Output: Send + 'static,
+    {
+        let (sender, receiver) = std::sync::mpsc::channel::<T::Output>();
+        (self.spawn)(Box::pin(async move {
+            sender.send(task.await).ok();
+        }));
+        receiver
+            .recv()
+            .map_err(|_| Error::join_failure("SpawnFnExecutor dropped a task before it completed"))
+    }
+
+    fn spawn<F>(&self, task: F)
+    where
+        F: Future<Output = ()> + Send + 'static,
+    {
+        (self.spawn)(Box::pin(task));
+    }
+
+    fn spawn_blocking<T, R>(&self, task: T) -> BoxFuture<'_, DeltaResult<R>>
+    where
+        T: FnOnce() -> R + Send + 'static,
+        R: Send + 'static,
+    {
+        let (sender, receiver) = oneshot::channel();
+        (self.spawn_blocking)(Box::new(move || {
+            sender.send(task()).ok();
+        }));
+        receiver
+            .map(|result| {
+                result.map_err(|_| Error::join_failure("SpawnFnExecutor dropped a blocking task"))
+            })
+            .boxed()
+    }
+}
+
 #[cfg(any(feature = "tokio", test))]
 pub mod tokio {
     use super::TaskExecutor;
@@ -102,7 +189,7 @@ pub mod tokio {
     }
 
     impl TaskExecutor for TokioBackgroundExecutor {
-        fn block_on<T>(&self, task: T) -> T::Output
+        fn block_on<T>(&self, task: T) -> DeltaResult<T::Output>
         where
             T: Future + Send + 'static,
             T::Output: Send + 'static,
@@ -125,7 +212,7 @@ pub mod tokio {
 
             receiver
                 .recv()
-                .expect("TokioBackgroundExecutor has crashed")
+                .map_err(|_| crate::Error::join_failure("TokioBackgroundExecutor has crashed"))
         }
 
         fn spawn<F>(&self, task: F)
@@ -164,7 +251,7 @@ pub mod tokio {
     }
 
     impl TaskExecutor for TokioMultiThreadExecutor {
-        fn block_on<T>(&self, task: T) -> T::Output
+        fn block_on<T>(&self, task: T) -> DeltaResult<T::Output>
         where
             T: Future + Send + 'static,
             T::Output: Send + 'static,
@@ -188,7 +275,7 @@ pub mod tokio {
 
             receiver
                 .recv()
-                .expect("TokioMultiThreadExecutor has crashed")
+                .map_err(|_| crate::Error::join_failure("TokioMultiThreadExecutor has crashed"))
         }
 
         fn spawn<F>(&self, task: F)
@@ -217,7 +304,7 @@ pub mod tokio {
                 tokio::time::sleep(std::time::Duration::from_millis(10)).await;
                 2 + 2
             };
-            let result = executor.block_on(task);
+            let result = executor.block_on(task).unwrap();
             assert_eq!(result, 4);
 
             // Can spawn a task
@@ -243,3 +330,75 @@ pub mod tokio {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::engine::default::DefaultEngine;
+    use crate::Table;
+
+    #[test]
+    fn test_spawn_fn_executor() {
+        let runtime = ::tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(2)
+            .enable_all()
+            .build()
+            .unwrap();
+        let handle = runtime.handle().clone();
+        let spawned = Arc::new(AtomicUsize::new(0));
+        let spawned_blocking = Arc::new(AtomicUsize::new(0));
+        let executor = {
+            let spawned = spawned.clone();
+            let spawned_blocking = spawned_blocking.clone();
+            let blocking_handle = handle.clone();
+            SpawnFnExecutor::new(
+                move |task| {
+                    spawned.fetch_add(1, Ordering::SeqCst);
+                    handle.spawn(task);
+                },
+                move |task| {
+                    spawned_blocking.fetch_add(1, Ordering::SeqCst);
+                    blocking_handle.spawn_blocking(task);
+                },
+            )
+        };
+
+        assert_eq!(executor.block_on(async { 2 + 2 }).unwrap(), 4);
+        let result = futures::executor::block_on(executor.spawn_blocking(|| 2 + 2));
+        assert_eq!(result.unwrap(), 4);
+        assert_eq!(spawned_blocking.swap(0, Ordering::SeqCst), 1);
+
+        // all the IO of a scan runs on the injected executor
+        spawned.store(0, Ordering::SeqCst);
+        let path =
+            std::fs::canonicalize(PathBuf::from("./tests/data/table-with-dv-small/")).unwrap();
+        let url = url::Url::from_directory_path(path).unwrap();
+        let engine =
+            DefaultEngine::try_new(&url, std::iter::empty::<(&str, &str)>(), Arc::new(executor))
+                .unwrap();
+        let snapshot = Table::new(url).snapshot(&engine, None).unwrap();
+        let scan = crate::scan::ScanBuilder::new(snapshot).build().unwrap();
+        let rows: usize = scan
+            .execute(&engine)
+            .unwrap()
+            .into_iter()
+            .map(|result| result.raw_data.unwrap().length())
+            .sum();
+        assert_eq!(rows, 10);
+        assert!(spawned.load(Ordering::SeqCst) > 0);
+    }
+    #[test]
+    fn test_spawn_fn_executor_dropped_task() {
+        // an executor that drops its tasks (e.g. because it's shutting down) fails the blocked
+        // call instead of panicking
+        let executor = SpawnFnExecutor::new(drop, drop);
+        let result = executor.block_on(async { 2 + 2 });
+        assert!(matches!(result, Err(Error::JoinFailure(_))), "{result:?}");
+        let result = futures::executor::block_on(executor.spawn_blocking(|| 2 + 2));
+        assert!(matches!(result, Err(Error::JoinFailure(_))), "{result:?}");
+    }
+}
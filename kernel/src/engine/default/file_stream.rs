@@ -32,19 +32,15 @@ pub trait FileOpener: Send + Unpin {
 
 /// Describes the behavior of the `FileStream` if file opening or scanning fails
 #[allow(missing_debug_implementations)]
+#[derive(Default)]
 pub enum OnError {
     /// Fail the entire stream and return the underlying error
+    #[default]
     Fail,
     /// Continue scanning, ignoring the failed file
     Skip,
 }
 
-impl Default for OnError {
-    fn default() -> Self {
-        Self::Fail
-    }
-}
-
 /// Represents the state of the next `FileOpenFuture`. Since we need to poll
 /// this future while scanning the current file, we need to store the result if it
 /// is ready
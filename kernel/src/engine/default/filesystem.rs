@@ -1,29 +1,53 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::ops::Range;
 use std::sync::Arc;
 
 use bytes::Bytes;
+use futures::future::FutureExt;
 use futures::stream::StreamExt;
 use object_store::path::Path;
-use object_store::DynObjectStore;
+use object_store::{DynObjectStore, GetOptions};
 use url::Url;
 
 use crate::engine::default::executor::TaskExecutor;
+use crate::engine::default::storage::ObjectStoreRegistry;
 use crate::{DeltaResult, Error, FileMeta, FileSlice, FileSystemClient};
 
+/// A [`FileSystemClient`] for the files in the [`object_store`] stores of an
+/// [`ObjectStoreRegistry`], i.e. for the `file`, `memory`, `s3`, `gs`, `az`/`abfss` and
+/// `http`/`https` URL schemes (the cloud schemes require the `cloud` feature). The files of other
+/// schemes can be accessed with handlers registered with [`Self::with_scheme_handler`].
 #[derive(Debug)]
 pub struct ObjectStoreFileSystemClient<E: TaskExecutor> {
-    inner: Arc<DynObjectStore>,
+    stores: Arc<ObjectStoreRegistry>,
+    scheme_handlers: SchemeHandlers,
+    // listing uses the parent directory of the requested path, so this is currently unused
+    #[allow(unused)]
     table_root: Path,
     task_executor: Arc<E>,
     readahead: usize,
+    range_coalescing: Option<RangeCoalescing>,
 }
 
 impl<E: TaskExecutor> ObjectStoreFileSystemClient<E> {
     pub fn new(store: Arc<DynObjectStore>, table_root: Path, task_executor: Arc<E>) -> Self {
+        let stores = Arc::new(ObjectStoreRegistry::new(store));
+        Self::new_with_registry(stores, table_root, task_executor)
+    }
+
+    /// Create a client that accesses each file in its store in `stores`
+    pub fn new_with_registry(
+        stores: Arc<ObjectStoreRegistry>,
+        table_root: Path,
+        task_executor: Arc<E>,
+    ) -> Self {
         Self {
-            inner: store,
+            stores,
+            scheme_handlers: SchemeHandlers::default(),
             table_root,
             task_executor,
             readahead: 10,
+            range_coalescing: None,
         }
     }
 
@@ -32,6 +56,157 @@ impl<E: TaskExecutor> ObjectStoreFileSystemClient<E> {
         self.readahead = readahead;
         self
     }
+
+    /// Read byte ranges of the same file that are at most `coalesce_gap` bytes apart (or that
+    /// overlap) in a single request, as long as that reads at most `max_coalesced_size` bytes,
+    /// and slice the requested ranges out of it. This trades reading the bytes in the gaps for
+    /// fewer requests, which pays off for stores with a high cost or latency per request, e.g.
+    /// when reading many small adjacent row groups of a checkpoint.
+    ///
+    /// By default every range is read with a request of its own.
+    pub fn with_range_coalescing(mut self, coalesce_gap: usize, max_coalesced_size: usize) -> Self {
+        self.range_coalescing = Some(RangeCoalescing {
+            gap: coalesce_gap,
+            max_size: max_coalesced_size,
+        });
+        self
+    }
+
+    /// Access the files whose URLs have the scheme `scheme` (e.g. `myfs` for `myfs://...` URLs)
+    /// with `handler`: [`FileSystemClient::list_from`], [`FileSystemClient::list_from_recursive`]
+    /// and [`FileSystemClient::read_files`] are passed on to it for those URLs, instead of being
+    /// served from an object store. This also takes precedence over the built-in schemes.
+    ///
+    /// Only the files accessed through this client go to `handler`. The default parquet and JSON
+    /// handlers need it too to read the files of a table with the scheme, which
+    /// [`super::DefaultEngineBuilder::with_scheme_handler`] takes care of.
+    pub fn with_scheme_handler(
+        mut self,
+        scheme: impl Into<String>,
+        handler: Arc<dyn FileSystemClient>,
+    ) -> Self {
+        self.scheme_handlers.0.insert(scheme.into(), handler);
+        self
+    }
+
+    /// The handler registered for the scheme of `url`, if any
+    fn scheme_handler(&self, url: &Url) -> Option<&Arc<dyn FileSystemClient>> {
+        self.scheme_handlers.get(url)
+    }
+
+    /// Read the `files`, which are all in object stores, each only if it still has the e-tag it
+    /// comes with, if any. See [`FileSystemClient::read_files_if_unchanged`].
+    fn read_store_files(
+        &self,
+        files: Vec<(FileSlice, Option<String>)>,
+    ) -> DeltaResult<Box<dyn Iterator<Item = DeltaResult<Bytes>>>> {
+        let stores = self.stores.clone();
+        let reads = coalesce_reads(files, self.range_coalescing);
+
+        // This channel will become the output iterator.
+        // Because there will already be buffering in the stream, we set the
+        // buffer size to 0.
+        let (sender, receiver) = std::sync::mpsc::sync_channel(0);
+
+        // the reads are ordered by the first slice they serve, and a read can serve slices after
+        // those of later reads, so slices are sent once all slices before them have been
+        let mut pending = BTreeMap::new();
+        let mut next_slice = 0;
+        self.task_executor.spawn(
+            futures::stream::iter(reads)
+                .map(move |read| {
+                    let url = read.url;
+                    // Wasn't checking the scheme before calling to_file_path causing the url path to
+                    // be eaten in a strange way. Now, if not a file scheme, just blindly convert to a path.
+                    // https://docs.rs/url/latest/url/struct.Url.html#method.to_file_path has more
+                    // details about why this check is necessary
+                    let path = if url.scheme() == "file" {
+                        let file_path = url.to_file_path().expect("Not a valid file path");
+                        Path::from_absolute_path(file_path).expect("Not able to be made into Path")
+                    } else {
+                        Path::from(url.path())
+                    };
+                    let store = stores.get_store(&url);
+                    let range = read.range.clone();
+                    let e_tag = read.e_tag;
+                    let data = async move {
+                        match url.scheme() {
+                            "http" | "https" => {
+                                // have to annotate type here or rustc can't figure it out
+                                Ok::<bytes::Bytes, Error>(reqwest::get(url).await?.bytes().await?)
+                            }
+                            _ => {
+                                let store = store?;
+                                match (range, e_tag) {
+                                    (Some(rng), None) => Ok(store.get_range(&path, rng).await?),
+                                    (None, None) => Ok(store.get(&path).await?.bytes().await?),
+                                    (range, if_match) => {
+                                        let options = GetOptions {
+                                            if_match,
+                                            range: range.map(Into::into),
+                                            ..Default::default()
+                                        };
+                                        let result = store.get_opts(&path, options).await?;
+                                        Ok(result.bytes().await?)
+                                    }
+                                }
+                            }
+                        }
+                    };
+                    data.map(move |data| (read.range, read.slices, data))
+                })
+                // We allow executing up to `readahead` futures concurrently and
+                // buffer the results. This allows us to achieve async concurrency
+                // within a synchronous method.
+                .buffered(self.readahead)
+                .for_each(move |(range, slices, data)| {
+                    pending.extend(split_read(range, slices, data));
+                    while let Some(res) = pending.remove(&next_slice) {
+                        sender.send(res).ok();
+                        next_slice += 1;
+                    }
+                    futures::future::ready(())
+                }),
+        );
+
+        Ok(Box::new(receiver.into_iter()))
+    }
+}
+
+/// The [`FileSystemClient`] registered for each URL scheme, see
+/// [`ObjectStoreFileSystemClient::with_scheme_handler`]
+#[derive(Clone, Default)]
+pub(super) struct SchemeHandlers(pub(super) HashMap<String, Arc<dyn FileSystemClient>>);
+
+impl SchemeHandlers {
+    /// The handler registered for the scheme of `url`, if any
+    pub(super) fn get(&self, url: &Url) -> Option<&Arc<dyn FileSystemClient>> {
+        self.0.get(url.scheme())
+    }
+}
+
+/// Read the whole file at `location` with the scheme handler `handler`
+pub(super) fn read_with_handler(
+    handler: &dyn FileSystemClient,
+    location: &Url,
+) -> DeltaResult<Bytes> {
+    handler
+        .read_files(vec![(location.clone(), None)])?
+        .next()
+        .unwrap_or_else(|| Err(Error::file_not_found(location)))
+}
+
+/// Whether `location` is a file directly in the directory `dir`, and not in a subdirectory of it
+fn is_in_dir(location: &Path, dir: &Path) -> bool {
+    location
+        .prefix_match(dir)
+        .is_some_and(|mut parts| parts.next().is_some() && parts.next().is_none())
+}
+
+impl std::fmt::Debug for SchemeHandlers {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_set().entries(self.0.keys()).finish()
+    }
 }
 
 impl<E: TaskExecutor> FileSystemClient for ObjectStoreFileSystemClient<E> {
@@ -39,12 +214,21 @@ impl<E: TaskExecutor> FileSystemClient for ObjectStoreFileSystemClient<E> {
         &self,
         path: &Url,
     ) -> DeltaResult<Box<dyn Iterator<Item = DeltaResult<FileMeta>>>> {
+        if let Some(handler) = self.scheme_handler(path) {
+            return handler.list_from(path);
+        }
         let url = path.clone();
         let offset = Path::from(path.path());
-        // TODO properly handle table prefix
-        let prefix = self.table_root.child("_delta_log");
+        // list the directory that contains `path`. If `path` is a directory itself (ends with a
+        // `/`), then the whole directory is listed. The prefix matches whole path segments, so
+        // sibling directories that merely start with the same name (like `table2/` next to
+        // `table/`) are never listed, and only the files directly in the directory are listed
+        let prefix = match path.path().rsplit_once('/') {
+            Some((parent, _)) => Path::from(parent),
+            None => Path::default(),
+        };
 
-        let store = self.inner.clone();
+        let store = self.stores.get_store(path)?;
 
         // This channel will become the iterator
         let (sender, receiver) = std::sync::mpsc::sync_channel(4_000);
@@ -53,23 +237,96 @@ impl<E: TaskExecutor> FileSystemClient for ObjectStoreFileSystemClient<E> {
             let mut stream = store.list_with_offset(Some(&prefix), &offset);
 
             while let Some(meta) = stream.next().await {
-                match meta {
+                let sent = match meta {
+                    // object stores list recursively, and some match the prefix as a plain string
+                    // prefix. Make sure that e.g. listing `table/_delta_log/` returns neither the
+                    // files of `table/_delta_log/_commits/` nor those of a sibling
+                    // `table/_delta_log_old/`
+                    Ok(meta) if !is_in_dir(&meta.location, &prefix) => continue,
                     Ok(meta) => {
                         let mut location = url.clone();
                         location.set_path(&format!("/{}", meta.location.as_ref()));
-                        sender
-                            .send(Ok(FileMeta {
-                                location,
-                                last_modified: meta.last_modified.timestamp(),
-                                size: meta.size,
-                            }))
-                            .ok();
+                        sender.send(Ok(FileMeta {
+                            location,
+                            last_modified: meta.last_modified.timestamp_millis(),
+                            size: meta.size,
+                            e_tag: meta.e_tag,
+                            version: meta.version,
+                        }))
                     }
+                    Err(e) => sender.send(Err(e.into())),
+                };
+                // stop listing once the listing is dropped
+                if sent.is_err() {
+                    break;
+                }
+            }
+        });
+
+        Ok(Box::new(receiver.into_iter()))
+    }
+
+    /// Lists with [`object_store::ObjectStore::list_with_offset`], which lists recursively. Stores
+    /// don't all list in order, so the listing is collected and sorted before it is returned. A
+    /// listed object that is also the parent directory of other listed objects is a directory
+    /// placeholder and is left out.
+    fn list_from_recursive(
+        &self,
+        path: &Url,
+    ) -> DeltaResult<Box<dyn Iterator<Item = DeltaResult<FileMeta>>>> {
+        if let Some(handler) = self.scheme_handler(path) {
+            return handler.list_from_recursive(path);
+        }
+        let url = path.clone();
+        let offset = Path::from(path.path());
+        // see list_from
+        let prefix = match path.path().rsplit_once('/') {
+            Some((parent, _)) => Path::from(parent),
+            None => Path::default(),
+        };
+        let store = self.stores.get_store(path)?;
+
+        let (sender, receiver) = std::sync::mpsc::sync_channel(4_000);
+
+        self.task_executor.spawn(async move {
+            let mut stream = store.list_with_offset(Some(&prefix), &offset);
+            let mut metas = Vec::new();
+            while let Some(meta) = stream.next().await {
+                match meta {
+                    Ok(meta) if !meta.location.prefix_matches(&prefix) => {}
+                    Ok(meta) => metas.push(meta),
                     Err(e) => {
                         sender.send(Err(e.into())).ok();
+                        return;
                     }
                 }
             }
+            metas.sort_by(|a, b| a.location.as_ref().cmp(b.location.as_ref()));
+            let dirs: HashSet<_> = metas
+                .iter()
+                .flat_map(|meta| {
+                    let location = meta.location.as_ref();
+                    location.match_indices('/').map(|(i, _)| &location[..i])
+                })
+                .collect();
+            for meta in &metas {
+                if dirs.contains(meta.location.as_ref()) {
+                    continue;
+                }
+                let mut location = url.clone();
+                location.set_path(&format!("/{}", meta.location.as_ref()));
+                let file = FileMeta {
+                    location,
+                    last_modified: meta.last_modified.timestamp_millis(),
+                    size: meta.size,
+                    e_tag: meta.e_tag.clone(),
+                    version: meta.version.clone(),
+                };
+                if sender.send(Ok(file)).is_err() {
+                    // the receiver was dropped
+                    return;
+                }
+            }
         });
 
         Ok(Box::new(receiver.into_iter()))
@@ -80,61 +337,168 @@ impl<E: TaskExecutor> FileSystemClient for ObjectStoreFileSystemClient<E> {
     /// This will return the data in the same order as the provided file slices.
     ///
     /// Multiple reads may occur in parallel, depending on the configured readahead.
-    /// See [`Self::with_readahead`].
+    /// See [`Self::with_readahead`]. Slices of the same file may be read together, see
+    /// [`Self::with_range_coalescing`]. The slices of files with a registered scheme handler are
+    /// read by the handler, see [`Self::with_scheme_handler`].
     fn read_files(
         &self,
         files: Vec<FileSlice>,
     ) -> DeltaResult<Box<dyn Iterator<Item = DeltaResult<Bytes>>>> {
-        let store = self.inner.clone();
+        self.read_files_if_unchanged(files.into_iter().map(|file| (file, None)).collect())
+    }
 
-        // This channel will become the output iterator.
-        // Because there will already be buffering in the stream, we set the
-        // buffer size to 0.
-        let (sender, receiver) = std::sync::mpsc::sync_channel(0);
+    /// Like [`Self::read_files`], with the e-tags checked by making the requests to the object
+    /// stores conditional on them (`If-Match`). Presigned `http`/`https` URLs are read without
+    /// checking their e-tags.
+    fn read_files_if_unchanged(
+        &self,
+        files: Vec<(FileSlice, Option<String>)>,
+    ) -> DeltaResult<Box<dyn Iterator<Item = DeltaResult<Bytes>>>> {
+        if self.scheme_handlers.0.is_empty() {
+            return self.read_store_files(files);
+        }
+        // read each run of consecutive slices of the same handler (or of the object stores)
+        // together, so the results stay in the order of the slices
+        let mut runs: Vec<(_, Vec<_>)> = Vec::new();
+        for file in files {
+            let handler = self.scheme_handler(&file.0 .0);
+            match runs.last_mut() {
+                Some((run_handler, run)) if same_handler(*run_handler, handler) => run.push(file),
+                _ => runs.push((handler, vec![file])),
+            }
+        }
+        let results = runs
+            .into_iter()
+            .map(|(handler, files)| match handler {
+                Some(handler) => handler.read_files_if_unchanged(files),
+                None => self.read_store_files(files),
+            })
+            .collect::<DeltaResult<Vec<_>>>()?;
+        Ok(Box::new(results.into_iter().flatten()))
+    }
+}
 
-        self.task_executor.spawn(
-            futures::stream::iter(files)
-                .map(move |(url, range)| {
-                    // Wasn't checking the scheme before calling to_file_path causing the url path to
-                    // be eaten in a strange way. Now, if not a file scheme, just blindly convert to a path.
-                    // https://docs.rs/url/latest/url/struct.Url.html#method.to_file_path has more
-                    // details about why this check is necessary
-                    let path = if url.scheme() == "file" {
-                        let file_path = url.to_file_path().expect("Not a valid file path");
-                        Path::from_absolute_path(file_path).expect("Not able to be made into Path")
-                    } else {
-                        Path::from(url.path())
-                    };
-                    let store = store.clone();
-                    async move {
-                        match url.scheme() {
-                            "http" | "https" => {
-                                // have to annotate type here or rustc can't figure it out
-                                Ok::<bytes::Bytes, Error>(reqwest::get(url).await?.bytes().await?)
-                            }
-                            _ => {
-                                if let Some(rng) = range {
-                                    Ok(store.get_range(&path, rng).await?)
-                                } else {
-                                    let result = store.get(&path).await?;
-                                    Ok(result.bytes().await?)
-                                }
-                            }
-                        }
-                    }
-                })
-                // We allow executing up to `readahead` futures concurrently and
-                // buffer the results. This allows us to achieve async concurrency
-                // within a synchronous method.
-                .buffered(self.readahead)
-                .for_each(move |res| {
-                    sender.send(res).ok();
-                    futures::future::ready(())
-                }),
-        );
+/// Whether two files are read by the same scheme handler, or both from the object stores
+fn same_handler(
+    a: Option<&Arc<dyn FileSystemClient>>,
+    b: Option<&Arc<dyn FileSystemClient>>,
+) -> bool {
+    match (a, b) {
+        (Some(a), Some(b)) => Arc::ptr_eq(a, b),
+        (a, b) => a.is_none() && b.is_none(),
+    }
+}
 
-        Ok(Box::new(receiver.into_iter()))
+/// How [`ObjectStoreFileSystemClient::read_files`] merges the byte ranges of a file into fewer
+/// reads. See [`ObjectStoreFileSystemClient::with_range_coalescing`].
+#[derive(Debug, Clone, Copy)]
+struct RangeCoalescing {
+    gap: usize,
+    max_size: usize,
+}
+
+/// A read of `range` of the file at `url` (or of the whole file), which serves the `slices`: the
+/// index of each in the requested files and, if it's only part of the read, its range in the file.
+/// The read fails if the file no longer has the `e_tag`, if any.
+struct CoalescedRead {
+    url: Url,
+    e_tag: Option<String>,
+    range: Option<Range<usize>>,
+    slices: Vec<(usize, Option<Range<usize>>)>,
+}
+
+/// Plan the reads of `files`, merging the ranges of each file whose gaps are at most
+/// `coalescing.gap` bytes, as long as the merged range is at most `coalescing.max_size` bytes.
+/// Ranges can overlap, and only ranges to read with the same e-tag are merged. The reads are
+/// ordered by the first of the `files` they serve.
+fn coalesce_reads(
+    files: Vec<(FileSlice, Option<String>)>,
+    coalescing: Option<RangeCoalescing>,
+) -> Vec<CoalescedRead> {
+    let mut reads = Vec::new();
+    let mut ranges: HashMap<_, Vec<(usize, Range<usize>)>> = HashMap::new();
+    for (index, ((url, range), e_tag)) in files.into_iter().enumerate() {
+        match (range, coalescing) {
+            // presigned urls are read whole, see read_files
+            (Some(range), Some(_)) if !matches!(url.scheme(), "http" | "https") => {
+                ranges.entry((url, e_tag)).or_default().push((index, range))
+            }
+            (range, _) => reads.push(CoalescedRead {
+                url,
+                e_tag,
+                range,
+                slices: vec![(index, None)],
+            }),
+        }
+    }
+    if let Some(coalescing) = coalescing {
+        for ((url, e_tag), mut ranges) in ranges {
+            ranges.sort_by_key(|(_, range)| (range.start, range.end));
+            let mut merged: Vec<(Range<usize>, Vec<_>)> = Vec::new();
+            for (index, range) in ranges {
+                match merged.last_mut() {
+                    Some((read_range, slices))
+                        if range.start <= read_range.end.saturating_add(coalescing.gap)
+                            && read_range.end.max(range.end) - read_range.start
+                                <= coalescing.max_size =>
+                    {
+                        read_range.end = read_range.end.max(range.end);
+                        slices.push((index, Some(range)));
+                    }
+                    _ => merged.push((range.clone(), vec![(index, Some(range))])),
+                }
+            }
+            reads.extend(merged.into_iter().map(|(range, slices)| CoalescedRead {
+                url: url.clone(),
+                e_tag: e_tag.clone(),
+                range: Some(range),
+                slices,
+            }));
+        }
     }
+    reads.sort_by_key(|read| read.slices.iter().map(|(index, _)| *index).min());
+    reads
+}
+
+/// Split the `data` of a read of `range` into its `slices`, by their index in the requested
+/// files. If the read failed, every slice fails.
+fn split_read(
+    range: Option<Range<usize>>,
+    slices: Vec<(usize, Option<Range<usize>>)>,
+    data: DeltaResult<Bytes>,
+) -> Vec<(usize, DeltaResult<Bytes>)> {
+    let data = match data {
+        Ok(data) => data,
+        Err(err) => {
+            // errors can't be cloned, so only the first slice gets the original one
+            let msg = err.to_string();
+            let mut err = Some(err);
+            return slices
+                .into_iter()
+                .map(|(index, _)| {
+                    let err = err.take().unwrap_or_else(|| Error::generic(&msg));
+                    (index, Err(err))
+                })
+                .collect();
+        }
+    };
+    let start = range.map_or(0, |range| range.start);
+    slices
+        .into_iter()
+        .map(|(index, slice)| {
+            let res = match slice {
+                None => Ok(data.clone()),
+                Some(slice) if slice.end - start <= data.len() => {
+                    Ok(data.slice(slice.start - start..slice.end - start))
+                }
+                Some(slice) => Err(Error::generic(format!(
+                    "Read of {slice:?} returned only {} bytes from {start}",
+                    data.len()
+                ))),
+            };
+            (index, res)
+        })
+        .collect()
 }
 
 #[cfg(test)]
@@ -144,6 +508,8 @@ mod tests {
     use object_store::{local::LocalFileSystem, ObjectStore};
 
     use crate::engine::default::executor::tokio::TokioBackgroundExecutor;
+    use crate::engine::in_memory::InMemoryEngine;
+    use crate::Engine;
 
     use itertools::Itertools;
 
@@ -162,10 +528,9 @@ mod tests {
         let mut url = Url::from_directory_path(tmp.path()).unwrap();
 
         let store = Arc::new(LocalFileSystem::new());
-        let prefix = Path::from(url.path());
         let client = ObjectStoreFileSystemClient::new(
             store,
-            prefix,
+            Path::from(""),
             Arc::new(TokioBackgroundExecutor::new()),
         );
 
@@ -186,4 +551,397 @@ mod tests {
         assert_eq!(data[1], Bytes::from("data"));
         assert_eq!(data[2], Bytes::from("el-da"));
     }
+
+    #[tokio::test]
+    async fn test_list_from_non_log_dir() {
+        let tmp = tempfile::tempdir().unwrap();
+        let tmp_store = LocalFileSystem::new_with_prefix(tmp.path()).unwrap();
+
+        let data = Bytes::from("kernel-data");
+        for name in ["a", "b", "c", "_delta_log/00000000000000000000.json"] {
+            tmp_store
+                .put(&Path::from(name), data.clone())
+                .await
+                .unwrap();
+        }
+
+        let url = Url::from_directory_path(tmp.path()).unwrap();
+        let store = Arc::new(LocalFileSystem::new());
+        let client = ObjectStoreFileSystemClient::new(
+            store,
+            Path::from(""),
+            Arc::new(TokioBackgroundExecutor::new()),
+        );
+
+        let mut files: Vec<FileMeta> = client.list_from(&url).unwrap().try_collect().unwrap();
+        files.sort_by(|a, b| a.location.cmp(&b.location));
+        // the files of subdirectories are not listed
+        let expected = ["a", "b", "c"];
+        assert_eq!(files.len(), expected.len());
+        for (file, name) in files.iter().zip(expected) {
+            assert_eq!(file.location, url.join(name).unwrap());
+            assert_eq!(file.size, data.len());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_list_from_ignores_sibling_tables() {
+        let tmp = tempfile::tempdir().unwrap();
+        let tmp_store = LocalFileSystem::new_with_prefix(tmp.path()).unwrap();
+
+        let data = Bytes::from("kernel-data");
+        let commit = "00000000000000000000.json";
+        for name in [
+            format!("warehouse/db/table/_delta_log/{commit}"),
+            "warehouse/db/table/_delta_log/00000000000000000001.json".to_string(),
+            // decoys next to the table, and next to its log
+            format!("warehouse/db/table2/_delta_log/{commit}"),
+            format!("warehouse/db/table_backup/_delta_log/{commit}"),
+            format!("warehouse/db/table/_delta_log_old/{commit}"),
+            format!("warehouse/db/table/_delta_log2/{commit}"),
+        ] {
+            tmp_store
+                .put(&Path::from(name.as_str()), data.clone())
+                .await
+                .unwrap();
+        }
+
+        let table_root = Url::from_directory_path(tmp.path())
+            .unwrap()
+            .join("warehouse/db/table/")
+            .unwrap();
+        let log_root = table_root.join("_delta_log/").unwrap();
+        let client = ObjectStoreFileSystemClient::new(
+            Arc::new(LocalFileSystem::new()),
+            Path::from(table_root.path()),
+            Arc::new(TokioBackgroundExecutor::new()),
+        );
+        let expected = [
+            log_root.join(commit).unwrap(),
+            log_root.join("00000000000000000001.json").unwrap(),
+        ];
+
+        for start in [
+            log_root.clone(),
+            log_root.join("00000000000000000000").unwrap(),
+        ] {
+            let mut files: Vec<_> = client
+                .list_from(&start)
+                .unwrap()
+                .map_ok(|file| file.location)
+                .try_collect()
+                .unwrap();
+            files.sort();
+            assert_eq!(files, expected);
+        }
+        let files: Vec<_> = client
+            .list_from(&log_root.join("00000000000000000001").unwrap())
+            .unwrap()
+            .map_ok(|file| file.location)
+            .try_collect()
+            .unwrap();
+        assert_eq!(files, expected[1..]);
+    }
+
+    /// An in-memory store that, like some object stores, matches the prefix of a listing as a
+    /// plain string prefix rather than by whole path segments
+    #[derive(Debug, Default)]
+    struct StringPrefixStore(object_store::memory::InMemory);
+
+    impl std::fmt::Display for StringPrefixStore {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            write!(f, "StringPrefixStore")
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl ObjectStore for StringPrefixStore {
+        async fn put_opts(
+            &self,
+            location: &Path,
+            bytes: Bytes,
+            opts: object_store::PutOptions,
+        ) -> object_store::Result<object_store::PutResult> {
+            self.0.put_opts(location, bytes, opts).await
+        }
+
+        async fn put_multipart(
+            &self,
+            location: &Path,
+        ) -> object_store::Result<(
+            object_store::MultipartId,
+            Box<dyn tokio::io::AsyncWrite + Unpin + Send>,
+        )> {
+            self.0.put_multipart(location).await
+        }
+
+        async fn abort_multipart(
+            &self,
+            location: &Path,
+            multipart_id: &object_store::MultipartId,
+        ) -> object_store::Result<()> {
+            self.0.abort_multipart(location, multipart_id).await
+        }
+
+        async fn get_opts(
+            &self,
+            location: &Path,
+            options: GetOptions,
+        ) -> object_store::Result<object_store::GetResult> {
+            self.0.get_opts(location, options).await
+        }
+
+        async fn delete(&self, location: &Path) -> object_store::Result<()> {
+            self.0.delete(location).await
+        }
+
+        fn list(
+            &self,
+            prefix: Option<&Path>,
+        ) -> futures::stream::BoxStream<'_, object_store::Result<object_store::ObjectMeta>>
+        {
+            let prefix = prefix.map(|prefix| prefix.to_string()).unwrap_or_default();
+            self.0
+                .list(None)
+                .filter(move |meta| {
+                    let keep = match meta {
+                        Ok(meta) => meta.location.as_ref().starts_with(&prefix),
+                        Err(_) => true,
+                    };
+                    futures::future::ready(keep)
+                })
+                .boxed()
+        }
+
+        async fn list_with_delimiter(
+            &self,
+            prefix: Option<&Path>,
+        ) -> object_store::Result<object_store::ListResult> {
+            self.0.list_with_delimiter(prefix).await
+        }
+
+        async fn copy(&self, from: &Path, to: &Path) -> object_store::Result<()> {
+            self.0.copy(from, to).await
+        }
+
+        async fn copy_if_not_exists(&self, from: &Path, to: &Path) -> object_store::Result<()> {
+            self.0.copy_if_not_exists(from, to).await
+        }
+    }
+
+    #[tokio::test]
+    async fn test_list_from_string_prefix_store() {
+        let store = Arc::new(StringPrefixStore::default());
+        let data = Bytes::from("kernel-data");
+        let commit = "00000000000000000000.json";
+        for name in [
+            format!("table/_delta_log/{commit}"),
+            // decoys that the store lists for the prefix `table/_delta_log`
+            format!("table/_delta_log_old/{commit}"),
+            format!("table/_delta_log2/{commit}"),
+        ] {
+            store
+                .put(&Path::from(name.as_str()), data.clone())
+                .await
+                .unwrap();
+        }
+
+        let client = ObjectStoreFileSystemClient::new(
+            store,
+            Path::from(""),
+            Arc::new(TokioBackgroundExecutor::new()),
+        );
+        let log_root = Url::parse("memory:///table/_delta_log/").unwrap();
+        for start in [log_root.clone(), log_root.join("0").unwrap()] {
+            let files: Vec<_> = client
+                .list_from(&start)
+                .unwrap()
+                .map_ok(|file| file.location)
+                .try_collect()
+                .unwrap();
+            assert_eq!(files, [log_root.join(commit).unwrap()]);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_list_from_recursive() {
+        let store = Arc::new(object_store::memory::InMemory::new());
+        let data = Bytes::from("kernel-data");
+        for name in [
+            "table/_delta_log/_sidecars/b.parquet",
+            "table/_delta_log/00000000000000000000.json",
+            "table/_delta_log/_sidecars/a.parquet",
+            "table/_delta_log/00000000000000000001.json",
+            "table/part-00000.parquet",
+        ] {
+            store.put(&Path::from(name), data.clone()).await.unwrap();
+        }
+        // a placeholder for the directory of the sidecars
+        store
+            .put(&Path::from("table/_delta_log/_sidecars"), Bytes::new())
+            .await
+            .unwrap();
+
+        let client = ObjectStoreFileSystemClient::new(
+            store,
+            Path::from(""),
+            Arc::new(TokioBackgroundExecutor::new()),
+        );
+        let log_root = Url::parse("memory:///table/_delta_log/").unwrap();
+        let list = |path: &Url| -> Vec<String> {
+            client
+                .list_from_recursive(path)
+                .unwrap()
+                .map_ok(|file| file.location.path().to_string())
+                .try_collect()
+                .unwrap()
+        };
+        let expected = [
+            "/table/_delta_log/00000000000000000000.json",
+            "/table/_delta_log/00000000000000000001.json",
+            "/table/_delta_log/_sidecars/a.parquet",
+            "/table/_delta_log/_sidecars/b.parquet",
+        ];
+        assert_eq!(list(&log_root), expected);
+        assert_eq!(
+            list(&log_root.join("00000000000000000001").unwrap()),
+            expected[1..]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_read_files_with_range_coalescing() {
+        let store = Arc::new(object_store::memory::InMemory::new());
+        let data = Bytes::from("0123456789abcdefghij");
+        for name in ["a", "b"] {
+            store.put(&Path::from(name), data.clone()).await.unwrap();
+        }
+        let url = |name: &str| Url::parse(&format!("memory:///{name}")).unwrap();
+        let files = vec![
+            (url("a"), Some(10..12)),
+            (url("b"), Some(0..2)),
+            (url("a"), Some(0..3)),
+            // overlapping
+            (url("a"), Some(1..5)),
+            (url("b"), None),
+            // too far from the others
+            (url("a"), Some(18..20)),
+        ];
+
+        let reads = coalesce_reads(
+            files.iter().map(|file| (file.clone(), None)).collect(),
+            Some(RangeCoalescing {
+                gap: 6,
+                max_size: 16,
+            }),
+        );
+        let planned: Vec<_> = reads
+            .iter()
+            .map(|read| {
+                let slices = read.slices.iter().map(|(index, _)| *index).collect_vec();
+                (read.url.path(), read.range.clone(), slices)
+            })
+            .collect();
+        assert_eq!(
+            planned,
+            [
+                ("/a", Some(0..12), vec![2, 3, 0]),
+                ("/b", Some(0..2), vec![1]),
+                ("/b", None, vec![4]),
+                ("/a", Some(18..20), vec![5]),
+            ]
+        );
+
+        let client = ObjectStoreFileSystemClient::new(
+            store,
+            Path::from(""),
+            Arc::new(TokioBackgroundExecutor::new()),
+        )
+        .with_range_coalescing(6, 16);
+        let read: Vec<Bytes> = client.read_files(files).unwrap().try_collect().unwrap();
+        assert_eq!(
+            read,
+            ["ab", "01", "012", "1234", "0123456789abcdefghij", "ij"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_read_files_if_unchanged() {
+        let store = Arc::new(object_store::memory::InMemory::new());
+        store
+            .put(&Path::from("a"), Bytes::from("old"))
+            .await
+            .unwrap();
+        let client = ObjectStoreFileSystemClient::new(
+            store.clone(),
+            Path::from(""),
+            Arc::new(TokioBackgroundExecutor::new()),
+        );
+        let url = Url::parse("memory:///a").unwrap();
+        let list = || {
+            let dir = Url::parse("memory:///").unwrap();
+            client.list_from(&dir).unwrap().next().unwrap().unwrap()
+        };
+        let listed = list();
+        assert!(listed.e_tag.is_some());
+        store
+            .put(&Path::from("a"), Bytes::from("new"))
+            .await
+            .unwrap();
+
+        let read = |e_tag: Option<String>, range: Option<Range<usize>>| {
+            let files = vec![((url.clone(), range), e_tag)];
+            client
+                .read_files_if_unchanged(files)
+                .unwrap()
+                .next()
+                .unwrap()
+        };
+        for range in [None, Some(0..2)] {
+            let result = read(listed.e_tag.clone(), range.clone());
+            assert!(matches!(result, Err(Error::StaleRead(_))), "{result:?}");
+        }
+        // without an e-tag the new content is read, as by `read_files`
+        assert_eq!(read(None, None).unwrap(), "new");
+        assert_eq!(read(list().e_tag, Some(0..2)).unwrap(), "ne");
+    }
+
+    #[tokio::test]
+    async fn test_scheme_handler() {
+        let store = Arc::new(object_store::memory::InMemory::new());
+        store
+            .put(&Path::from("a"), Bytes::from("memory-data"))
+            .await
+            .unwrap();
+        let myfs_url = |name: &str| Url::parse(&format!("myfs:///table/{name}")).unwrap();
+        let myfs = InMemoryEngine::new();
+        myfs.put(myfs_url("a"), "myfs-data");
+        myfs.put(myfs_url("b"), "more-myfs-data");
+        let client = ObjectStoreFileSystemClient::new(
+            store,
+            Path::from(""),
+            Arc::new(TokioBackgroundExecutor::new()),
+        )
+        .with_scheme_handler("myfs", myfs.get_file_system_client());
+
+        let listed: Vec<_> = client
+            .list_from(&myfs_url("b"))
+            .unwrap()
+            .map_ok(|file| (file.location.path().to_string(), file.size))
+            .try_collect()
+            .unwrap();
+        assert_eq!(listed, [("/table/b".to_string(), 14)]);
+
+        // the results are in the order of the slices, whichever client reads them
+        let memory_url = Url::parse("memory:///a").unwrap();
+        let files = vec![
+            (myfs_url("a"), Some(0..4)),
+            (memory_url.clone(), Some(0..6)),
+            (memory_url, None),
+            (myfs_url("b"), Some(5..9)),
+            (myfs_url("a"), None),
+        ];
+        let read: Vec<Bytes> = client.read_files(files).unwrap().try_collect().unwrap();
+        assert_eq!(read, ["myfs", "memory", "memory-data", "myfs", "myfs-data"]);
+    }
 }
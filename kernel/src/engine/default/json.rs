@@ -5,22 +5,23 @@ use std::ops::Range;
 use std::sync::Arc;
 use std::task::{ready, Poll};
 
-use arrow_array::{new_null_array, Array, RecordBatch, StringArray, StructArray};
+use arrow_array::RecordBatch;
 use arrow_json::ReaderBuilder;
 use arrow_schema::SchemaRef as ArrowSchemaRef;
-use arrow_select::concat::concat_batches;
 use bytes::{Buf, Bytes};
+use futures::stream::BoxStream;
 use futures::{StreamExt, TryStreamExt};
-use itertools::Itertools;
 use object_store::path::Path;
-use object_store::{DynObjectStore, GetResultPayload};
+use object_store::{DynObjectStore, GetOptions, GetResultPayload};
 
 use super::executor::TaskExecutor;
 use super::file_stream::{FileOpenFuture, FileOpener, FileStream};
-use crate::engine::arrow_data::ArrowEngineData;
+use super::filesystem::{read_with_handler, SchemeHandlers};
+use crate::engine::arrow_utils::{parse_json, parse_json_stream};
 use crate::schema::SchemaRef;
 use crate::{
-    DeltaResult, EngineData, Error, Expression, FileDataReadResultIterator, FileMeta, JsonHandler,
+    DeltaResult, EngineData, Error, Expression, FileDataReadResultIterator, FileMeta,
+    FileSystemClient, JsonHandler,
 };
 
 #[derive(Debug)]
@@ -33,6 +34,8 @@ pub struct DefaultJsonHandler<E: TaskExecutor> {
     readahead: usize,
     /// The number of rows to read per batch
     batch_size: usize,
+    /// The clients to read the files with some URL schemes with, instead of the object store
+    scheme_handlers: SchemeHandlers,
 }
 
 impl<E: TaskExecutor> DefaultJsonHandler<E> {
@@ -42,6 +45,7 @@ impl<E: TaskExecutor> DefaultJsonHandler<E> {
             task_executor,
             readahead: 10,
             batch_size: 1024,
+            scheme_handlers: SchemeHandlers::default(),
         }
     }
 
@@ -60,26 +64,16 @@ impl<E: TaskExecutor> DefaultJsonHandler<E> {
         self.batch_size = batch_size;
         self
     }
-}
 
-fn hack_parse(
-    stats_schema: &ArrowSchemaRef,
-    json_string: Option<&str>,
-) -> DeltaResult<RecordBatch> {
-    match json_string {
-        Some(s) => Ok(ReaderBuilder::new(stats_schema.clone())
-            .build(BufReader::new(s.as_bytes()))?
-            .next()
-            .transpose()?
-            .ok_or(Error::missing_data("Expected data"))?),
-        None => Ok(RecordBatch::try_new(
-            stats_schema.clone(),
-            stats_schema
-                .fields
-                .iter()
-                .map(|field| new_null_array(field.data_type(), 1))
-                .collect(),
-        )?),
+    /// Read the files whose URLs have the scheme `scheme` with `handler`, instead of the object
+    /// store. See [`super::filesystem::ObjectStoreFileSystemClient::with_scheme_handler`].
+    pub fn with_scheme_handler(
+        mut self,
+        scheme: impl Into<String>,
+        handler: Arc<dyn FileSystemClient>,
+    ) -> Self {
+        self.scheme_handlers.0.insert(scheme.into(), handler);
+        self
     }
 }
 
@@ -89,30 +83,7 @@ impl<E: TaskExecutor> JsonHandler for DefaultJsonHandler<E> {
         json_strings: Box<dyn EngineData>,
         output_schema: SchemaRef,
     ) -> DeltaResult<Box<dyn EngineData>> {
-        let json_strings: RecordBatch = ArrowEngineData::try_from_engine_data(json_strings)?.into();
-        // TODO(nick): this is pretty terrible
-        let struct_array: StructArray = json_strings.into();
-        let json_strings = struct_array
-            .column(0)
-            .as_any()
-            .downcast_ref::<StringArray>()
-            .ok_or_else(|| {
-                Error::generic("Expected json_strings to be a StringArray, found something else")
-            })?;
-        let output_schema: ArrowSchemaRef = Arc::new(output_schema.as_ref().try_into()?);
-        if json_strings.is_empty() {
-            return Ok(Box::new(ArrowEngineData::new(RecordBatch::new_empty(
-                output_schema,
-            ))));
-        }
-        let output: Vec<_> = json_strings
-            .iter()
-            .map(|json_string| hack_parse(&output_schema, json_string))
-            .try_collect()?;
-        Ok(Box::new(ArrowEngineData::new(concat_batches(
-            &output_schema,
-            output.iter(),
-        )?)))
+        parse_json(json_strings, output_schema)
     }
 
     fn read_json_files(
@@ -126,7 +97,8 @@ impl<E: TaskExecutor> JsonHandler for DefaultJsonHandler<E> {
         }
 
         let schema: ArrowSchemaRef = Arc::new(physical_schema.as_ref().try_into()?);
-        let file_opener = JsonOpener::new(self.batch_size, schema.clone(), self.store.clone());
+        let file_opener = JsonOpener::new(self.batch_size, schema.clone(), self.store.clone())
+            .with_scheme_handlers(self.scheme_handlers.clone());
         FileStream::new_async_read_iterator(
             self.task_executor.clone(),
             schema,
@@ -135,6 +107,16 @@ impl<E: TaskExecutor> JsonHandler for DefaultJsonHandler<E> {
             self.readahead,
         )
     }
+
+    fn parse_json_stream<'a>(
+        &'a self,
+        json: BoxStream<'a, DeltaResult<Bytes>>,
+        output_schema: SchemaRef,
+        batch_size: usize,
+    ) -> DeltaResult<BoxStream<'a, DeltaResult<RecordBatch>>> {
+        let schema: ArrowSchemaRef = Arc::new(output_schema.as_ref().try_into()?);
+        parse_json_stream(json, schema, batch_size)
+    }
 }
 
 /// A [`FileOpener`] that opens a JSON file and yields a [`FileOpenFuture`]
@@ -143,6 +125,7 @@ pub struct JsonOpener {
     batch_size: usize,
     projected_schema: ArrowSchemaRef,
     object_store: Arc<DynObjectStore>,
+    scheme_handlers: SchemeHandlers,
 }
 
 impl JsonOpener {
@@ -158,8 +141,15 @@ impl JsonOpener {
             projected_schema,
             // file_compression_type,
             object_store,
+            scheme_handlers: SchemeHandlers::default(),
         }
     }
+
+    /// Read the files with the schemes of `scheme_handlers` with their handlers
+    pub(super) fn with_scheme_handlers(mut self, scheme_handlers: SchemeHandlers) -> Self {
+        self.scheme_handlers = scheme_handlers;
+        self
+    }
 }
 
 impl FileOpener for JsonOpener {
@@ -168,9 +158,24 @@ impl FileOpener for JsonOpener {
         let schema = self.projected_schema.clone();
         let batch_size = self.batch_size;
 
+        if let Some(handler) = self.scheme_handlers.get(&file_meta.location).cloned() {
+            return Ok(Box::pin(async move {
+                let data = read_with_handler(handler.as_ref(), &file_meta.location)?;
+                let reader = ReaderBuilder::new(schema)
+                    .with_batch_size(batch_size)
+                    .build(data.reader())?;
+                Ok(futures::stream::iter(reader).map_err(Error::from).boxed())
+            }));
+        }
         Ok(Box::pin(async move {
             let path = Path::from_url_path(file_meta.location.path())?;
-            match store.get(&path).await?.payload {
+            // a file that was overwritten since it was listed fails with an `Error::StaleRead`,
+            // instead of (possibly) failing to parse
+            let options = GetOptions {
+                if_match: file_meta.e_tag.clone(),
+                ..Default::default()
+            };
+            match store.get_opts(&path, options).await?.payload {
                 GetResultPayload::File(file, _) => {
                     let reader = ReaderBuilder::new(schema)
                         .with_batch_size(batch_size)
@@ -220,11 +225,14 @@ impl FileOpener for JsonOpener {
 mod tests {
     use std::path::PathBuf;
 
+    use arrow_array::{RecordBatch, StringArray};
     use arrow_schema::{DataType, Field, Schema as ArrowSchema};
+    use futures::stream;
     use itertools::Itertools;
     use object_store::{local::LocalFileSystem, ObjectStore};
 
     use super::*;
+    use crate::engine::arrow_data::ArrowEngineData;
     use crate::{
         actions::get_log_schema, engine::default::executor::tokio::TokioBackgroundExecutor,
     };
@@ -272,6 +280,8 @@ mod tests {
             location: url.clone(),
             last_modified: meta.last_modified.timestamp_millis(),
             size: meta.size,
+            e_tag: None,
+            version: None,
         }];
 
         let handler = DefaultJsonHandler::new(store, Arc::new(TokioBackgroundExecutor::new()));
@@ -294,4 +304,58 @@ mod tests {
         assert_eq!(data.len(), 1);
         assert_eq!(data[0].num_rows(), 4);
     }
+
+    #[test]
+    fn test_parse_json_stream() {
+        let store = Arc::new(LocalFileSystem::new());
+        let handler = DefaultJsonHandler::new(store, Arc::new(TokioBackgroundExecutor::new()));
+        let schema = Arc::new(crate::schema::StructType::new(vec![
+            crate::schema::StructField::new("a", crate::schema::DataType::LONG, true),
+        ]));
+        let values = |batch: DeltaResult<RecordBatch>| {
+            let batch = batch?;
+            let values = batch.column(0).as_any();
+            let values = values.downcast_ref::<arrow_array::Int64Array>().unwrap();
+            Ok(values.values().to_vec())
+        };
+        let json = "{\"a\": 1}\n{\"a\": 2}\n{\"a\": 3}\n\n{\"a\": 4}\n{\"a\": 5}";
+        let parse = |chunk_size: usize, json: &'static str| {
+            let chunks = json
+                .as_bytes()
+                .chunks(chunk_size)
+                .map(|chunk| Ok(Bytes::copy_from_slice(chunk)));
+            let batches = handler
+                .parse_json_stream(stream::iter(chunks).boxed(), schema.clone(), 2)
+                .unwrap();
+            futures::executor::block_on_stream(batches)
+                .map(values)
+                .collect::<DeltaResult<Vec<_>>>()
+        };
+
+        // records straddle the chunks, and the last one has no trailing newline
+        for chunk_size in [1, 3, 7, json.len()] {
+            let batches = parse(chunk_size, json).unwrap();
+            assert_eq!(batches, [vec![1, 2], vec![3, 4], vec![5]]);
+        }
+        // a truncated last record is an error, not dropped
+        assert!(parse(4, "{\"a\": 1}\n{\"a\": ").is_err());
+
+        // a batch is yielded as soon as its records have arrived, before the rest of the input
+        let (sender, receiver) = futures::channel::mpsc::unbounded();
+        let mut batches = futures::executor::block_on_stream(
+            handler
+                .parse_json_stream(receiver.boxed(), schema.clone(), 2)
+                .unwrap(),
+        );
+        sender
+            .unbounded_send(Ok(Bytes::from_static(b"{\"a\": 1}\n{\"a\": 2}\n{\"a\"")))
+            .unwrap();
+        assert_eq!(values(batches.next().unwrap()).unwrap(), [1, 2]);
+        sender
+            .unbounded_send(Ok(Bytes::from_static(b": 3}\n")))
+            .unwrap();
+        drop(sender);
+        assert_eq!(values(batches.next().unwrap()).unwrap(), [3]);
+        assert!(batches.next().is_none());
+    }
 }
@@ -6,16 +6,20 @@
 //! a separate thread pool, provided by the [`TaskExecutor`] trait. Read more in
 //! the [executor] module.
 
+use std::collections::HashMap;
+use std::path::PathBuf;
 use std::sync::Arc;
 
-use object_store::{parse_url_opts, path::Path, DynObjectStore};
+use object_store::{path::Path, DynObjectStore};
 use url::Url;
 
 use self::executor::TaskExecutor;
-use self::filesystem::ObjectStoreFileSystemClient;
+use self::filesystem::{ObjectStoreFileSystemClient, SchemeHandlers};
 use self::json::DefaultJsonHandler;
 use self::parquet::DefaultParquetHandler;
+use self::storage::ObjectStoreRegistry;
 use super::arrow_expression::ArrowExpressionHandler;
+use super::caching::CachingFileSystemClient;
 use crate::{
     DeltaResult, Engine, ExpressionHandler, FileSystemClient, JsonHandler, ParquetHandler,
 };
@@ -25,11 +29,14 @@ pub mod file_stream;
 pub mod filesystem;
 pub mod json;
 pub mod parquet;
+pub mod storage;
 
 #[derive(Debug)]
 pub struct DefaultEngine<E: TaskExecutor> {
-    store: Arc<DynObjectStore>,
+    stores: Arc<ObjectStoreRegistry>,
     file_system: Arc<ObjectStoreFileSystemClient<E>>,
+    /// Caches the reads of `file_system`, if the engine was built with a file cache
+    file_cache: Option<Arc<CachingFileSystemClient>>,
     json: Arc<DefaultJsonHandler<E>>,
     parquet: Arc<DefaultParquetHandler<E>>,
     expression: Arc<ArrowExpressionHandler>,
@@ -47,43 +54,189 @@ impl<E: TaskExecutor> DefaultEngine<E> {
         K: AsRef<str>,
         V: Into<String>,
     {
-        let (store, prefix) = parse_url_opts(path, options)?;
-        let store = Arc::new(store);
-        Ok(Self {
-            file_system: Arc::new(ObjectStoreFileSystemClient::new(
-                store.clone(),
-                prefix,
-                task_executor.clone(),
-            )),
-            json: Arc::new(DefaultJsonHandler::new(
-                store.clone(),
-                task_executor.clone(),
-            )),
-            parquet: Arc::new(DefaultParquetHandler::new(store.clone(), task_executor)),
-            store,
-            expression: Arc::new(ArrowExpressionHandler {}),
-        })
+        let (stores, prefix) = ObjectStoreRegistry::try_new_for_table(path, options)?;
+        Ok(Self::new_with_registry(stores, prefix, task_executor))
     }
 
     pub fn new(store: Arc<DynObjectStore>, prefix: Path, task_executor: Arc<E>) -> Self {
+        Self::new_with_registry(ObjectStoreRegistry::new(store), prefix, task_executor)
+    }
+
+    /// Create a new [`DefaultEngine`] that accesses each file in its store in `stores`, e.g. the
+    /// data files of a table that references them by absolute URL in another bucket. The table
+    /// is at `prefix` in the default store of `stores`.
+    pub fn new_with_registry(
+        stores: ObjectStoreRegistry,
+        prefix: Path,
+        task_executor: Arc<E>,
+    ) -> Self {
+        let stores = Arc::new(stores);
         Self {
-            file_system: Arc::new(ObjectStoreFileSystemClient::new(
-                store.clone(),
+            file_system: Arc::new(ObjectStoreFileSystemClient::new_with_registry(
+                stores.clone(),
                 prefix,
                 task_executor.clone(),
             )),
             json: Arc::new(DefaultJsonHandler::new(
-                store.clone(),
+                stores.default_store().clone(),
                 task_executor.clone(),
             )),
-            parquet: Arc::new(DefaultParquetHandler::new(store.clone(), task_executor)),
-            store,
+            parquet: Arc::new(DefaultParquetHandler::new_with_registry(
+                stores.clone(),
+                task_executor,
+            )),
+            stores,
             expression: Arc::new(ArrowExpressionHandler {}),
+            file_cache: None,
+        }
+    }
+
+    /// Create a [`DefaultEngineBuilder`] for an engine that accesses the table at `path`, to
+    /// configure the engine's object store before building it.
+    pub fn builder(path: &Url, task_executor: Arc<E>) -> DefaultEngineBuilder<E> {
+        DefaultEngineBuilder {
+            path: path.clone(),
+            task_executor,
+            object_store_options: HashMap::new(),
+            object_stores: Vec::new(),
+            range_coalescing: None,
+            scheme_handlers: SchemeHandlers::default(),
+            file_cache: None,
         }
     }
 
-    pub fn get_object_store_for_url(&self, _url: &Url) -> Option<Arc<DynObjectStore>> {
-        Some(self.store.clone())
+    /// Get the store the engine accesses the file at `url` with, or `None` if it has no store for
+    /// it (see [`ObjectStoreRegistry`]).
+    pub fn get_object_store_for_url(&self, url: &Url) -> Option<Arc<DynObjectStore>> {
+        self.stores.get_store(url).ok()
+    }
+}
+
+/// Builder for a [`DefaultEngine`], created with [`DefaultEngine::builder`].
+#[derive(Debug)]
+pub struct DefaultEngineBuilder<E: TaskExecutor> {
+    path: Url,
+    task_executor: Arc<E>,
+    object_store_options: HashMap<String, String>,
+    object_stores: Vec<(Url, Arc<DynObjectStore>)>,
+    range_coalescing: Option<(usize, usize)>,
+    scheme_handlers: SchemeHandlers,
+    file_cache: Option<(PathBuf, usize)>,
+}
+
+impl<E: TaskExecutor> DefaultEngineBuilder<E> {
+    /// Set an option of the [`object_store`] backend for the table's URL scheme, e.g.
+    /// `("endpoint", "http://minio:9000")` and `("allow_http", "true")` to use an S3-compatible
+    /// store, or `("proxy_url", ...)` to go through a proxy. The options each backend supports
+    /// are the config keys of its builder, e.g. `object_store::aws::AmazonS3ConfigKey` for S3, or
+    /// `object_store::ClientConfigKey` for the options common to all HTTP-based backends (the
+    /// cloud backends require the `cloud` feature). Options the backend doesn't know are ignored.
+    /// Setting an option again replaces its value.
+    pub fn with_object_store_option(
+        mut self,
+        key: impl Into<String>,
+        value: impl Into<String>,
+    ) -> Self {
+        self.object_store_options.insert(key.into(), value.into());
+        self
+    }
+
+    /// Set several options of the [`object_store`] backend. See
+    /// [`Self::with_object_store_option`].
+    pub fn with_object_store_options<K, V>(
+        mut self,
+        options: impl IntoIterator<Item = (K, V)>,
+    ) -> Self
+    where
+        K: Into<String>,
+        V: Into<String>,
+    {
+        self.object_store_options
+            .extend(options.into_iter().map(|(k, v)| (k.into(), v.into())));
+        self
+    }
+
+    /// Access the files with the scheme and authority (e.g. the bucket) of `url` with `store`.
+    /// Files of the table in other stores than the table's, i.e. files referenced by absolute
+    /// URL, can only be accessed with a store registered for them with this.
+    pub fn with_object_store(mut self, url: &Url, store: Arc<DynObjectStore>) -> Self {
+        self.object_stores.push((url.clone(), store));
+        self
+    }
+
+    /// Read byte ranges of the same file that are at most `coalesce_gap` bytes apart in a single
+    /// request of at most `max_coalesced_size` bytes. See
+    /// [`ObjectStoreFileSystemClient::with_range_coalescing`].
+    pub fn with_range_coalescing(mut self, coalesce_gap: usize, max_coalesced_size: usize) -> Self {
+        self.range_coalescing = Some((coalesce_gap, max_coalesced_size));
+        self
+    }
+
+    /// Access the files with the URL scheme `scheme` with `handler`, e.g. to read a table in a
+    /// bespoke storage system behind a custom scheme. The engine's file system client, JSON
+    /// handler and parquet handler all read such files with `handler`. See
+    /// [`ObjectStoreFileSystemClient::with_scheme_handler`].
+    pub fn with_scheme_handler(
+        mut self,
+        scheme: impl Into<String>,
+        handler: Arc<dyn FileSystemClient>,
+    ) -> Self {
+        self.scheme_handlers.0.insert(scheme.into(), handler);
+        self
+    }
+
+    /// Cache the reads of the engine's [`FileSystemClient`] in `cache_dir`, using up to
+    /// `max_size` bytes of disk space. See [`CachingFileSystemClient`].
+    pub fn with_file_cache(mut self, cache_dir: impl Into<PathBuf>, max_size: usize) -> Self {
+        self.file_cache = Some((cache_dir.into(), max_size));
+        self
+    }
+
+    /// Build the [`DefaultEngine`]. This fails if an option has an invalid value, or if the cache
+    /// directory can't be created.
+    pub fn build(self) -> DeltaResult<DefaultEngine<E>> {
+        let (stores, prefix) =
+            ObjectStoreRegistry::try_new_for_table(&self.path, self.object_store_options)?;
+        let stores = self
+            .object_stores
+            .into_iter()
+            .fold(stores, |stores, (url, store)| {
+                stores.with_store(&url, store)
+            });
+        let mut engine =
+            DefaultEngine::new_with_registry(stores, prefix.clone(), self.task_executor.clone());
+        if self.range_coalescing.is_some() || !self.scheme_handlers.0.is_empty() {
+            let mut file_system = ObjectStoreFileSystemClient::new_with_registry(
+                engine.stores.clone(),
+                prefix,
+                self.task_executor.clone(),
+            );
+            if let Some((coalesce_gap, max_coalesced_size)) = self.range_coalescing {
+                file_system = file_system.with_range_coalescing(coalesce_gap, max_coalesced_size);
+            }
+            let mut json = DefaultJsonHandler::new(
+                engine.stores.default_store().clone(),
+                self.task_executor.clone(),
+            );
+            let mut parquet = DefaultParquetHandler::new_with_registry(
+                engine.stores.clone(),
+                self.task_executor.clone(),
+            );
+            for (scheme, handler) in self.scheme_handlers.0 {
+                file_system = file_system.with_scheme_handler(scheme.clone(), handler.clone());
+                json = json.with_scheme_handler(scheme.clone(), handler.clone());
+                parquet = parquet.with_scheme_handler(scheme, handler);
+            }
+            engine.file_system = Arc::new(file_system);
+            engine.json = Arc::new(json);
+            engine.parquet = Arc::new(parquet);
+        }
+        if let Some((cache_dir, max_size)) = self.file_cache {
+            let file_system = engine.file_system.clone();
+            let file_cache = CachingFileSystemClient::try_new(file_system, cache_dir, max_size)?;
+            engine.file_cache = Some(Arc::new(file_cache));
+        }
+        Ok(engine)
     }
 }
 
@@ -93,7 +246,10 @@ impl<E: TaskExecutor> Engine for DefaultEngine<E> {
     }
 
     fn get_file_system_client(&self) -> Arc<dyn FileSystemClient> {
-        self.file_system.clone()
+        match &self.file_cache {
+            Some(file_cache) => file_cache.clone(),
+            None => self.file_system.clone(),
+        }
     }
 
     fn get_json_handler(&self) -> Arc<dyn JsonHandler> {
@@ -104,3 +260,97 @@ impl<E: TaskExecutor> Engine for DefaultEngine<E> {
         self.parquet.clone()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::executor::tokio::TokioBackgroundExecutor;
+    use super::*;
+
+    #[cfg(feature = "cloud")]
+    #[test]
+    fn test_builder_object_store_options() {
+        let url = Url::parse("s3://bucket/table/").unwrap();
+        let executor = Arc::new(TokioBackgroundExecutor::new());
+        DefaultEngine::builder(&url, executor.clone())
+            .with_object_store_option("endpoint", "http://minio:9000")
+            .with_object_store_option("allow_http", "true")
+            .with_object_store_options([("region", "us-east-1"), ("proxy_url", "http://proxy")])
+            .build()
+            .unwrap();
+
+        // the options are passed on to the object store, which validates them
+        let result = DefaultEngine::builder(&url, executor)
+            .with_object_store_option("allow_http", "not a bool")
+            .build();
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_builder_file_cache() {
+        let table_dir = tempfile::tempdir().unwrap();
+        let cache_dir = tempfile::tempdir().unwrap();
+        std::fs::write(table_dir.path().join("a"), "0123456789").unwrap();
+        let url = Url::from_directory_path(table_dir.path()).unwrap();
+        let engine = DefaultEngine::builder(&url, Arc::new(TokioBackgroundExecutor::new()))
+            .with_file_cache(cache_dir.path(), 1024)
+            .build()
+            .unwrap();
+
+        let file = (url.join("a").unwrap(), Some(2..5));
+        let data: Vec<_> = engine
+            .get_file_system_client()
+            .read_files(vec![file])
+            .unwrap()
+            .collect::<DeltaResult<_>>()
+            .unwrap();
+        assert_eq!(data, [bytes::Bytes::from("234")]);
+        // the read was cached in the cache directory
+        let cached: Vec<_> = std::fs::read_dir(cache_dir.path()).unwrap().collect();
+        assert_eq!(cached.len(), 1);
+    }
+
+    #[test]
+    fn test_builder_scheme_handler() {
+        use crate::engine::in_memory::InMemoryEngine;
+        use crate::scan::ScanBuilder;
+        use crate::Table;
+
+        let path = std::fs::canonicalize("./tests/data/table-with-dv-small/").unwrap();
+        let url = Url::from_directory_path(&path).unwrap();
+        let in_memory = InMemoryEngine::new();
+        in_memory.put_local_dir(&url, path).unwrap();
+        // the handler takes precedence over the built-in `file` scheme
+        let engine = DefaultEngine::builder(&url, Arc::new(TokioBackgroundExecutor::new()))
+            .with_scheme_handler("file", in_memory.get_file_system_client())
+            .build()
+            .unwrap();
+
+        let snapshot = Table::new(url).snapshot(&engine, None).unwrap();
+        let scan = ScanBuilder::new(snapshot).build().unwrap();
+        let results = scan.execute(&engine).unwrap();
+        let rows: usize = results
+            .into_iter()
+            .map(|result| result.mask.unwrap().into_iter().filter(|row| *row).count())
+            .sum();
+        assert_eq!(rows, 8);
+
+        // the log, the data file and the deletion vector were all read with the handler
+        let mut reads: Vec<_> = in_memory
+            .take_reads()
+            .iter()
+            .map(|location| location.path().rsplit('/').next().unwrap().to_string())
+            .collect();
+        reads.sort();
+        reads.dedup();
+        assert_eq!(
+            reads,
+            [
+                "00000000000000000000.json",
+                "00000000000000000001.json",
+                "_last_checkpoint",
+                "deletion_vector_61d16c75-6994-46b7-a15b-8b538852e50e.bin",
+                "part-00000-fae5310a-a37d-4e51-827b-c3d5516560ca-c000.snappy.parquet",
+            ]
+        );
+    }
+}
@@ -10,36 +10,124 @@ use parquet::arrow::arrow_reader::{
     ArrowReaderMetadata, ArrowReaderOptions, ParquetRecordBatchReaderBuilder,
 };
 use parquet::arrow::async_reader::{ParquetObjectReader, ParquetRecordBatchStreamBuilder};
+use parquet::file::metadata::ParquetMetaData;
+use url::Url;
 
 use super::file_stream::{FileOpenFuture, FileOpener, FileStream};
-use crate::engine::arrow_utils::{generate_mask, get_requested_indices, reorder_record_batch};
+use super::filesystem::{read_with_handler, SchemeHandlers};
+use super::storage::ObjectStoreRegistry;
+use crate::engine::arrow_data::ArrowEngineData;
+use crate::engine::arrow_expression::ArrowExpressionHandler;
+use crate::engine::arrow_utils::{
+    ensure_compatible_types, ensure_row_groups_exist, ensure_supported_codecs, generate_mask,
+    get_requested_indices, reorder_record_batch, row_group_layout, stats_from_footers,
+    stats_from_row_groups,
+};
 use crate::engine::default::executor::TaskExecutor;
+use crate::scan::data_skipping::DataSkippingFilter;
 use crate::schema::SchemaRef;
-use crate::{DeltaResult, Error, Expression, FileDataReadResultIterator, FileMeta, ParquetHandler};
+use crate::{
+    DeltaResult, EngineData, Error, Expression, FileDataReadResultIterator, FileMeta,
+    FileSystemClient, ParquetHandler, RowGroupMeta,
+};
 
+/// The default [`ParquetHandler`], reading files from an object store (or presigned URLs) with the
+/// async parquet reader. It supports files compressed with any codec of the parquet format except
+/// `LZO`, i.e. `SNAPPY`, `GZIP`, `BROTLI`, `LZ4`, `LZ4_RAW` and `ZSTD`. Reading a file with an
+/// unsupported codec fails with [`Error::UnsupportedCompressionCodec`].
+///
+/// The predicate hint of [`ParquetHandler::read_parquet_files`] is used to skip the row groups of
+/// each file whose statistics prove that none of their rows can satisfy it, so only the rows of
+/// the remaining row groups are read.
+///
+/// Files with a URL scheme that has a handler (see [`Self::with_scheme_handler`]) are read in
+/// full with it, like presigned URLs are fetched in full.
 #[derive(Debug)]
 pub struct DefaultParquetHandler<E: TaskExecutor> {
-    store: Arc<DynObjectStore>,
+    stores: Arc<ObjectStoreRegistry>,
     task_executor: Arc<E>,
     readahead: usize,
+    trim_column_names: bool,
+    validate_schema: bool,
+    scheme_handlers: SchemeHandlers,
 }
 
 impl<E: TaskExecutor> DefaultParquetHandler<E> {
     pub fn new(store: Arc<DynObjectStore>, task_executor: Arc<E>) -> Self {
+        Self::new_with_registry(Arc::new(ObjectStoreRegistry::new(store)), task_executor)
+    }
+
+    /// Create a handler that reads each file from its store in `stores`
+    pub fn new_with_registry(stores: Arc<ObjectStoreRegistry>, task_executor: Arc<E>) -> Self {
         Self {
-            store,
+            stores,
             task_executor,
             readahead: 10,
+            trim_column_names: false,
+            validate_schema: false,
+            scheme_handlers: SchemeHandlers::default(),
         }
     }
 
-    /// Max number of batches to read ahead while executing [Self::read_parquet_files()].
+    /// Max number of batches to read ahead while executing [Self::read_parquet_files()]. This is
+    /// also the max number of footers fetched concurrently by [Self::read_footer_stats()].
     ///
     /// Defaults to 10.
     pub fn with_readahead(mut self, readahead: usize) -> Self {
         self.readahead = readahead;
         self
     }
+
+    /// Match the columns of the files read by [Self::read_parquet_files()] to the requested
+    /// columns ignoring leading and trailing whitespace in their names, if they don't match
+    /// exactly. This allows reading files from writers that mangle (physical) column names.
+    ///
+    /// Off by default, in which case reading a file whose column names only differ from the
+    /// requested ones by whitespace or case fails with an [`Error::SchemaMismatch`].
+    pub fn with_trimmed_column_names(mut self) -> Self {
+        self.trim_column_names = true;
+        self
+    }
+
+    /// Check that the types of the columns of each file read by [Self::read_parquet_files()]
+    /// are compatible with the requested physical schema before reading it, failing with an
+    /// [`Error::SchemaMismatch`] naming the file and column if not. This catches files whose
+    /// schema drifted from the table metadata, which would otherwise read wrong data. Columns of
+    /// the file that aren't requested are ignored, and requested nullable columns that are
+    /// missing from the file read as null (with or without this).
+    ///
+    /// Off by default. The check uses the footer that is read anyway, but walks the whole
+    /// requested schema for every file.
+    pub fn with_schema_validation(mut self) -> Self {
+        self.validate_schema = true;
+        self
+    }
+
+    /// Read the files whose URLs have the scheme `scheme` with `handler`, instead of an object
+    /// store. See [`super::filesystem::ObjectStoreFileSystemClient::with_scheme_handler`].
+    pub fn with_scheme_handler(
+        mut self,
+        scheme: impl Into<String>,
+        handler: Arc<dyn FileSystemClient>,
+    ) -> Self {
+        self.scheme_handlers.0.insert(scheme.into(), handler);
+        self
+    }
+
+    /// Read the footer of the parquet file at `location`, with its scheme handler if it has one
+    fn read_footer(&self, location: &Url) -> DeltaResult<Arc<ParquetMetaData>> {
+        match self.scheme_handlers.get(location) {
+            Some(handler) => {
+                let data = read_with_handler(handler.as_ref(), location)?;
+                let metadata = ArrowReaderMetadata::load(&data, Default::default())
+                    .map_err(|err| Error::invalid_parquet_file(location, err))?;
+                Ok(metadata.metadata().clone())
+            }
+            None => self
+                .task_executor
+                .block_on(read_footer(self.stores.clone(), location.clone()))?,
+        }
+    }
 }
 
 impl<E: TaskExecutor> ParquetHandler for DefaultParquetHandler<E> {
@@ -47,28 +135,42 @@ impl<E: TaskExecutor> ParquetHandler for DefaultParquetHandler<E> {
         &self,
         files: &[FileMeta],
         physical_schema: SchemaRef,
-        _predicate: Option<Expression>,
+        predicate: Option<Expression>,
     ) -> DeltaResult<FileDataReadResultIterator> {
         if files.is_empty() {
             return Ok(Box::new(std::iter::empty()));
         }
 
-        // get the first FileMeta to decide how to fetch the file.
-        // NB: This means that every file in `FileMeta` _must_ have the same scheme or things will break
-        // s3://    -> aws   (ParquetOpener)
-        // nothing  -> local (ParquetOpener)
+        // row groups can only be skipped by the stats of columns that are read, so a predicate
+        // that references any other column (e.g. a partition column) isn't used
+        let predicate = predicate.filter(|predicate| {
+            predicate.references().iter().all(|name| {
+                name.top_level()
+                    .is_some_and(|name| physical_schema.field(name).is_some())
+            })
+        });
+        let row_group_filter = DataSkippingFilter::new_for_stats(
+            &ArrowExpressionHandler {},
+            &physical_schema,
+            &predicate,
+        )?
+        .map(Arc::new);
+
+        // each file is opened according to the scheme of its URL, so a scan can read files from
+        // different stores:
         // https:// -> assume presigned URL (and fetch without object_store)
-        //   -> reqwest to get data
-        //   -> parse to parquet
-        // SAFETY: we did is_empty check above, this is ok.
-        let file_opener: Box<dyn FileOpener> = match files[0].location.scheme() {
-            "http" | "https" => Box::new(PresignedUrlOpener::new(1024, physical_schema.clone())),
-            _ => Box::new(ParquetOpener::new(
+        // anything else -> the object store of the file's scheme and authority (e.g. bucket)
+        let file_opener: Box<dyn FileOpener> = Box::new(
+            ParquetOpener::new(
                 1024,
                 physical_schema.clone(),
-                self.store.clone(),
-            )),
-        };
+                self.stores.clone(),
+                self.trim_column_names,
+                self.validate_schema,
+                row_group_filter,
+            )
+            .with_scheme_handlers(self.scheme_handlers.clone()),
+        );
         FileStream::new_async_read_iterator(
             self.task_executor.clone(),
             Arc::new(physical_schema.as_ref().try_into()?),
@@ -77,6 +179,90 @@ impl<E: TaskExecutor> ParquetHandler for DefaultParquetHandler<E> {
             self.readahead,
         )
     }
+
+    fn read_footer_stats(
+        &self,
+        files: &[FileMeta],
+        stats_schema: SchemaRef,
+    ) -> DeltaResult<Option<Box<dyn EngineData>>> {
+        // presigned urls would need a request per file just to get the footer, and scheme
+        // handlers a read of the whole file
+        if files.iter().any(|file| {
+            matches!(file.location.scheme(), "http" | "https")
+                || self.scheme_handlers.get(&file.location).is_some()
+        }) {
+            return Ok(None);
+        }
+        let stores = self.stores.clone();
+        let locations: Vec<_> = files.iter().map(|file| file.location.clone()).collect();
+        let readahead = self.readahead;
+        let footers = self.task_executor.block_on(async move {
+            futures::stream::iter(locations)
+                .map(|location| read_footer(stores.clone(), location))
+                .buffered(readahead)
+                .collect::<Vec<DeltaResult<_>>>()
+                .await
+        })?;
+        let footers: Vec<_> = footers.into_iter().collect::<DeltaResult<_>>()?;
+        let stats = stats_from_footers(&footers, &stats_schema)?;
+        Ok(Some(Box::new(ArrowEngineData::new(stats))))
+    }
+
+    /// Reads the footer of the file from its object store (or scheme handler). Files with
+    /// presigned (`http(s)`) URLs aren't supported.
+    fn parquet_row_group_layout(&self, file: &FileMeta) -> DeltaResult<Vec<RowGroupMeta>> {
+        let has_handler = self.scheme_handlers.get(&file.location).is_some();
+        if matches!(file.location.scheme(), "http" | "https") && !has_handler {
+            return Err(Error::generic(format!(
+                "Reading the row group layout of a presigned URL is not supported: {}",
+                file.location
+            )));
+        }
+        let footer = self.read_footer(&file.location)?;
+        Ok(row_group_layout(&footer))
+    }
+
+    fn read_parquet_row_groups(
+        &self,
+        file: &FileMeta,
+        row_group_indices: &[usize],
+        physical_schema: SchemaRef,
+    ) -> DeltaResult<FileDataReadResultIterator> {
+        let file_opener: Box<dyn FileOpener> = Box::new(
+            ParquetOpener::new(
+                1024,
+                physical_schema.clone(),
+                self.stores.clone(),
+                self.trim_column_names,
+                self.validate_schema,
+                None,
+            )
+            .with_scheme_handlers(self.scheme_handlers.clone())
+            .with_row_groups(row_group_indices.to_vec()),
+        );
+        FileStream::new_async_read_iterator(
+            self.task_executor.clone(),
+            Arc::new(physical_schema.as_ref().try_into()?),
+            file_opener,
+            std::slice::from_ref(file),
+            self.readahead,
+        )
+    }
+}
+
+/// Read the footer of the parquet file at `location` from its store in `stores`
+async fn read_footer(
+    stores: Arc<ObjectStoreRegistry>,
+    location: Url,
+) -> DeltaResult<Arc<ParquetMetaData>> {
+    let store = stores.get_store(&location)?;
+    let path = Path::from_url_path(location.path())?;
+    let meta = store.head(&path).await?;
+    let mut reader = ParquetObjectReader::new(store, meta);
+    let metadata = ArrowReaderMetadata::load_async(&mut reader, Default::default())
+        .await
+        .map_err(|err| Error::invalid_parquet_file(&location, err))?;
+    Ok(metadata.metadata().clone())
 }
 
 /// Implements [`FileOpener`] for a parquet file
@@ -85,53 +271,141 @@ struct ParquetOpener {
     batch_size: usize,
     limit: Option<usize>,
     table_schema: SchemaRef,
-    store: Arc<DynObjectStore>,
+    stores: Arc<ObjectStoreRegistry>,
+    trim_column_names: bool,
+    /// Whether to check the types of the file's columns, see
+    /// [`DefaultParquetHandler::with_schema_validation`]
+    validate_schema: bool,
+    /// Skips the row groups that can't match the predicate of the read, if any
+    row_group_filter: Option<Arc<DataSkippingFilter>>,
+    /// The only row groups to read, if not all
+    row_groups: Option<Vec<usize>>,
+    /// The opener of the files that are fetched in full: those with `http(s)` URLs, which are
+    /// assumed to be presigned, and those with a scheme handler
+    whole_file_opener: WholeFileOpener,
 }
 
 impl ParquetOpener {
     pub(crate) fn new(
         batch_size: usize,
         table_schema: SchemaRef,
-        store: Arc<DynObjectStore>,
+        stores: Arc<ObjectStoreRegistry>,
+        trim_column_names: bool,
+        validate_schema: bool,
+        row_group_filter: Option<Arc<DataSkippingFilter>>,
     ) -> Self {
         Self {
             batch_size,
+            whole_file_opener: WholeFileOpener::new(
+                batch_size,
+                table_schema.clone(),
+                trim_column_names,
+                validate_schema,
+                row_group_filter.clone(),
+            ),
             table_schema,
             limit: None,
-            store,
+            stores,
+            trim_column_names,
+            validate_schema,
+            row_group_filter,
+            row_groups: None,
         }
     }
+
+    /// Only read the row groups with the given indices, in that order
+    pub(crate) fn with_row_groups(mut self, row_groups: Vec<usize>) -> Self {
+        self.whole_file_opener.row_groups = Some(row_groups.clone());
+        self.row_groups = Some(row_groups);
+        self
+    }
+
+    /// Read the files with the schemes of `scheme_handlers` with their handlers
+    pub(crate) fn with_scheme_handlers(mut self, scheme_handlers: SchemeHandlers) -> Self {
+        self.whole_file_opener.scheme_handlers = scheme_handlers;
+        self
+    }
+}
+
+/// The row groups of the file with the footer `footer` to read, i.e. those that `filter` can't rule
+/// out. An empty selection reads no rows at all.
+fn select_row_groups(
+    filter: &DataSkippingFilter,
+    footer: &ParquetMetaData,
+) -> DeltaResult<Vec<usize>> {
+    let stats = stats_from_row_groups(footer, filter.stats_schema())?;
+    let mut selection = Vec::with_capacity(footer.num_row_groups());
+    filter.apply_to_stats(&ArrowEngineData::new(stats), &mut selection)?;
+    Ok(selection
+        .into_iter()
+        .enumerate()
+        .filter_map(|(index, selected)| selected.then_some(index))
+        .collect())
 }
 
 impl FileOpener for ParquetOpener {
-    fn open(&self, file_meta: FileMeta, _range: Option<Range<i64>>) -> DeltaResult<FileOpenFuture> {
+    fn open(&self, file_meta: FileMeta, range: Option<Range<i64>>) -> DeltaResult<FileOpenFuture> {
+        let location = &file_meta.location;
+        if matches!(location.scheme(), "http" | "https")
+            || self
+                .whole_file_opener
+                .scheme_handlers
+                .get(location)
+                .is_some()
+        {
+            return self.whole_file_opener.open(file_meta, range);
+        }
         let path = Path::from_url_path(file_meta.location.path())?;
-        let store = self.store.clone();
+        let store = self.stores.get_store(&file_meta.location)?;
 
         let batch_size = self.batch_size;
         // let projection = self.projection.clone();
         let table_schema = self.table_schema.clone();
         let limit = self.limit;
+        let trim_names = self.trim_column_names;
+        let validate_schema = self.validate_schema;
+        let row_group_filter = self.row_group_filter.clone();
+        let row_groups = self.row_groups.clone();
 
         Ok(Box::pin(async move {
             // TODO avoid IO by converting passed file meta to ObjectMeta
             let meta = store.head(&path).await?;
             let mut reader = ParquetObjectReader::new(store, meta);
-            let metadata = ArrowReaderMetadata::load_async(&mut reader, Default::default()).await?;
+            // NOTE: the file is read as parquet regardless of its extension, since the log is the
+            // source of truth for which files are data files
+            let metadata = ArrowReaderMetadata::load_async(&mut reader, Default::default())
+                .await
+                .map_err(|err| Error::invalid_parquet_file(&file_meta.location, err))?;
+            ensure_supported_codecs(metadata.metadata(), &file_meta.location)?;
             let parquet_schema = metadata.schema();
-            let (indicies, requested_ordering) =
-                get_requested_indices(&table_schema, parquet_schema)?;
+            let indicies = get_requested_indices(
+                &table_schema,
+                parquet_schema,
+                &file_meta.location,
+                trim_names,
+            )?;
+            if validate_schema {
+                ensure_compatible_types(
+                    &table_schema,
+                    parquet_schema,
+                    &file_meta.location,
+                    trim_names,
+                )?;
+            }
             let options = ArrowReaderOptions::new(); //.with_page_index(enable_page_index);
             let mut builder =
                 ParquetRecordBatchStreamBuilder::new_with_options(reader, options).await?;
-            if let Some(mask) = generate_mask(
-                &table_schema,
-                parquet_schema,
-                builder.parquet_schema(),
-                &indicies,
-            ) {
+            if let Some(mask) = generate_mask(parquet_schema, builder.parquet_schema(), &indicies) {
                 builder = builder.with_projection(mask)
             }
+            if let Some(filter) = row_group_filter {
+                let row_groups = select_row_groups(&filter, builder.metadata())?;
+                builder = builder.with_row_groups(row_groups);
+            }
+            if let Some(row_groups) = row_groups {
+                ensure_row_groups_exist(builder.metadata(), &row_groups, &file_meta.location)?;
+                builder = builder.with_row_groups(row_groups);
+            }
 
             if let Some(limit) = limit {
                 builder = builder.with_limit(limit)
@@ -142,58 +416,92 @@ impl FileOpener for ParquetOpener {
             let stream = stream.map(move |rbr| {
                 // re-order each batch if needed
                 rbr.map_err(Error::Parquet)
-                    .and_then(|rb| reorder_record_batch(rb, &requested_ordering))
+                    .and_then(|rb| reorder_record_batch(rb, &table_schema, trim_names))
             });
             Ok(stream.boxed())
         }))
     }
 }
 
-/// Implements [`FileOpener`] for a opening a parquet file from a presigned URL
-struct PresignedUrlOpener {
+/// Implements [`FileOpener`] for opening a parquet file that is fetched in full: with its scheme
+/// handler if it has one, and otherwise from its (presigned) URL
+struct WholeFileOpener {
     batch_size: usize,
     limit: Option<usize>,
     table_schema: SchemaRef,
     client: reqwest::Client,
+    trim_column_names: bool,
+    validate_schema: bool,
+    row_group_filter: Option<Arc<DataSkippingFilter>>,
+    row_groups: Option<Vec<usize>>,
+    scheme_handlers: SchemeHandlers,
 }
 
-impl PresignedUrlOpener {
-    pub(crate) fn new(batch_size: usize, schema: SchemaRef) -> Self {
+impl WholeFileOpener {
+    pub(crate) fn new(
+        batch_size: usize,
+        schema: SchemaRef,
+        trim_column_names: bool,
+        validate_schema: bool,
+        row_group_filter: Option<Arc<DataSkippingFilter>>,
+    ) -> Self {
         Self {
             batch_size,
             table_schema: schema,
             limit: None,
             client: reqwest::Client::new(),
+            trim_column_names,
+            validate_schema,
+            row_group_filter,
+            row_groups: None,
+            scheme_handlers: SchemeHandlers::default(),
         }
     }
 }
 
-impl FileOpener for PresignedUrlOpener {
+impl FileOpener for WholeFileOpener {
     fn open(&self, file_meta: FileMeta, _range: Option<Range<i64>>) -> DeltaResult<FileOpenFuture> {
         let batch_size = self.batch_size;
         let table_schema = self.table_schema.clone();
         let limit = self.limit;
         let client = self.client.clone(); // uses Arc internally according to reqwest docs
+        let trim_names = self.trim_column_names;
+        let validate_schema = self.validate_schema;
+        let row_group_filter = self.row_group_filter.clone();
+        let row_groups = self.row_groups.clone();
+        let handler = self.scheme_handlers.get(&file_meta.location).cloned();
 
         Ok(Box::pin(async move {
-            // fetch the file from the interweb
-            let reader = client.get(file_meta.location).send().await?.bytes().await?;
-            let metadata = ArrowReaderMetadata::load(&reader, Default::default())?;
+            let location = file_meta.location;
+            let reader = match handler {
+                Some(handler) => read_with_handler(handler.as_ref(), &location)?,
+                // fetch the file from the interweb
+                None => client.get(location.clone()).send().await?.bytes().await?,
+            };
+            let metadata = ArrowReaderMetadata::load(&reader, Default::default())
+                .map_err(|err| Error::invalid_parquet_file(&location, err))?;
+            ensure_supported_codecs(metadata.metadata(), &location)?;
             let parquet_schema = metadata.schema();
-            let (indicies, requested_ordering) =
-                get_requested_indices(&table_schema, parquet_schema)?;
+            let indicies =
+                get_requested_indices(&table_schema, parquet_schema, &location, trim_names)?;
+            if validate_schema {
+                ensure_compatible_types(&table_schema, parquet_schema, &location, trim_names)?;
+            }
 
             let options = ArrowReaderOptions::new();
             let mut builder =
                 ParquetRecordBatchReaderBuilder::try_new_with_options(reader, options)?;
-            if let Some(mask) = generate_mask(
-                &table_schema,
-                parquet_schema,
-                builder.parquet_schema(),
-                &indicies,
-            ) {
+            if let Some(mask) = generate_mask(parquet_schema, builder.parquet_schema(), &indicies) {
                 builder = builder.with_projection(mask)
             }
+            if let Some(filter) = row_group_filter {
+                let row_groups = select_row_groups(&filter, builder.metadata())?;
+                builder = builder.with_row_groups(row_groups);
+            }
+            if let Some(row_groups) = row_groups {
+                ensure_row_groups_exist(builder.metadata(), &row_groups, &location)?;
+                builder = builder.with_row_groups(row_groups);
+            }
 
             if let Some(limit) = limit {
                 builder = builder.with_limit(limit)
@@ -205,7 +513,7 @@ impl FileOpener for PresignedUrlOpener {
             let stream = stream.map(move |rbr| {
                 // re-order each batch if needed
                 rbr.map_err(Error::Arrow)
-                    .and_then(|rb| reorder_record_batch(rb, &requested_ordering))
+                    .and_then(|rb| reorder_record_batch(rb, &table_schema, trim_names))
             });
             Ok(stream.boxed())
         }))
@@ -214,8 +522,10 @@ impl FileOpener for PresignedUrlOpener {
 
 #[cfg(test)]
 mod tests {
+    use std::collections::HashMap;
     use std::path::PathBuf;
 
+    use arrow_array::cast::AsArray;
     use arrow_array::RecordBatch;
     use object_store::{local::LocalFileSystem, ObjectStore};
 
@@ -255,8 +565,10 @@ mod tests {
 
         let files = &[FileMeta {
             location: url.clone(),
-            last_modified: meta.last_modified.timestamp(),
+            last_modified: meta.last_modified.timestamp_millis(),
             size: meta.size,
+            e_tag: None,
+            version: None,
         }];
 
         let handler = DefaultParquetHandler::new(store, Arc::new(TokioBackgroundExecutor::new()));
@@ -270,4 +582,405 @@ mod tests {
         assert_eq!(data.len(), 1);
         assert_eq!(data[0].num_rows(), 10);
     }
+
+    #[tokio::test]
+    async fn test_read_parquet_files_from_multiple_stores() {
+        let path = std::fs::canonicalize(PathBuf::from(
+            "./tests/data/table-with-dv-small/part-00000-fae5310a-a37d-4e51-827b-c3d5516560ca-c000.snappy.parquet"
+        )).unwrap();
+        let data = std::fs::read(&path).unwrap();
+        let local_file = FileMeta {
+            location: url::Url::from_file_path(path).unwrap(),
+            last_modified: 0,
+            size: data.len(),
+            e_tag: None,
+            version: None,
+        };
+
+        // a table in memory, with one file in its own store and one referenced by a file:// URL
+        let table_root = url::Url::parse("memory:///table/").unwrap();
+        let (stores, prefix) =
+            ObjectStoreRegistry::try_new_for_table(&table_root, HashMap::<String, String>::new())
+                .unwrap();
+        let file_root = url::Url::parse("file:///").unwrap();
+        let stores = stores.with_store(&file_root, Arc::new(LocalFileSystem::new()));
+        stores
+            .default_store()
+            .put(&prefix.child("part-00000.parquet"), data.clone().into())
+            .await
+            .unwrap();
+        let memory_file = FileMeta {
+            location: table_root.join("part-00000.parquet").unwrap(),
+            last_modified: 0,
+            size: data.len(),
+            e_tag: None,
+            version: None,
+        };
+
+        let reader = ParquetObjectReader::new(
+            Arc::new(LocalFileSystem::new()),
+            LocalFileSystem::new()
+                .head(&Path::from_url_path(local_file.location.path()).unwrap())
+                .await
+                .unwrap(),
+        );
+        let physical_schema = ParquetRecordBatchStreamBuilder::new(reader)
+            .await
+            .unwrap()
+            .schema()
+            .clone();
+        let handler = DefaultParquetHandler::new_with_registry(
+            Arc::new(stores),
+            Arc::new(TokioBackgroundExecutor::new()),
+        );
+        let data: Vec<RecordBatch> = handler
+            .read_parquet_files(
+                &[memory_file, local_file],
+                Arc::new(physical_schema.try_into().unwrap()),
+                None,
+            )
+            .unwrap()
+            .map(into_record_batch)
+            .try_collect()
+            .unwrap();
+        assert_eq!(data.iter().map(|batch| batch.num_rows()).sum::<usize>(), 20);
+    }
+
+    #[tokio::test]
+    async fn test_read_parquet_files_skips_row_groups() {
+        use arrow_array::Int64Array;
+        use object_store::memory::InMemory;
+        use parquet::file::properties::WriterProperties;
+
+        use crate::test_utils::parquet_bytes;
+
+        // a file with the values 0..30 in three row groups of 10 rows
+        let batch = RecordBatch::try_from_iter(vec![(
+            "value",
+            Arc::new(Int64Array::from_iter_values(0..30)) as _,
+        )])
+        .unwrap();
+        let props = WriterProperties::builder()
+            .set_max_row_group_size(10)
+            .build();
+        let data = parquet_bytes(&batch, Some(props));
+
+        let store = Arc::new(InMemory::new());
+        let size = data.len();
+        store
+            .put(&Path::from("part-00000.parquet"), data.into())
+            .await
+            .unwrap();
+        let files = &[FileMeta {
+            location: url::Url::parse("memory:///part-00000.parquet").unwrap(),
+            last_modified: 0,
+            size,
+            e_tag: None,
+            version: None,
+        }];
+        let physical_schema: SchemaRef = Arc::new(batch.schema().try_into().unwrap());
+        let handler = DefaultParquetHandler::new(store, Arc::new(TokioBackgroundExecutor::new()));
+        let read = |predicate: Option<Expression>| -> Vec<i64> {
+            let batches: Vec<RecordBatch> = handler
+                .read_parquet_files(files, physical_schema.clone(), predicate)
+                .unwrap()
+                .map(into_record_batch)
+                .try_collect()
+                .unwrap();
+            batches
+                .iter()
+                .flat_map(|batch| {
+                    batch
+                        .column(0)
+                        .as_primitive::<arrow_array::types::Int64Type>()
+                        .values()
+                        .to_vec()
+                })
+                .collect()
+        };
+
+        assert_eq!(read(None), (0..30).collect_vec());
+        // only the row groups whose stats allow a match are read, in full
+        let predicate = Expression::column("value").gt(Expression::literal(25i64));
+        assert_eq!(read(Some(predicate)), (20..30).collect_vec());
+        let predicate = Expression::and(
+            Expression::column("value").gt_eq(Expression::literal(5i64)),
+            Expression::column("value").lt(Expression::literal(12i64)),
+        );
+        assert_eq!(read(Some(predicate)), (0..20).collect_vec());
+        // no row group can match
+        let predicate = Expression::column("value").gt(Expression::literal(100i64));
+        assert_eq!(read(Some(predicate)), Vec::<i64>::new());
+        // a predicate on a column that isn't read doesn't skip anything
+        let predicate = Expression::column("other").gt(Expression::literal(100i64));
+        assert_eq!(read(Some(predicate)), (0..30).collect_vec());
+    }
+
+    #[tokio::test]
+    async fn test_read_parquet_row_groups() {
+        use arrow_array::Int64Array;
+        use object_store::memory::InMemory;
+        use parquet::file::properties::WriterProperties;
+
+        use crate::test_utils::parquet_bytes;
+
+        // a file with the values 0..25 in row groups of 10, 10 and 5 rows
+        let batch = RecordBatch::try_from_iter(vec![(
+            "value",
+            Arc::new(Int64Array::from_iter_values(0..25)) as _,
+        )])
+        .unwrap();
+        let props = WriterProperties::builder()
+            .set_max_row_group_size(10)
+            .build();
+        let data = parquet_bytes(&batch, Some(props));
+
+        let store = Arc::new(InMemory::new());
+        let size = data.len();
+        store
+            .put(&Path::from("part-00000.parquet"), data.into())
+            .await
+            .unwrap();
+        let file = FileMeta {
+            location: url::Url::parse("memory:///part-00000.parquet").unwrap(),
+            last_modified: 0,
+            size,
+            e_tag: None,
+            version: None,
+        };
+        let handler = DefaultParquetHandler::new(store, Arc::new(TokioBackgroundExecutor::new()));
+
+        let layout = handler.parquet_row_group_layout(&file).unwrap();
+        let num_rows: Vec<_> = layout.iter().map(|row_group| row_group.num_rows).collect();
+        assert_eq!(num_rows, [10, 10, 5]);
+        // the row groups are in file order and don't overlap, after the magic bytes at the start
+        // of the file
+        assert_eq!(layout[0].byte_range.start, 4);
+        for (row_group, next) in layout.iter().tuple_windows() {
+            assert!(!row_group.byte_range.is_empty());
+            assert!(row_group.byte_range.end <= next.byte_range.start);
+        }
+        assert!(layout[2].byte_range.end < size);
+
+        let physical_schema: SchemaRef = Arc::new(batch.schema().try_into().unwrap());
+        let read = |row_groups: &[usize]| -> DeltaResult<Vec<i64>> {
+            let batches: Vec<RecordBatch> = handler
+                .read_parquet_row_groups(&file, row_groups, physical_schema.clone())?
+                .map(into_record_batch)
+                .try_collect()?;
+            Ok(batches
+                .iter()
+                .flat_map(|batch| {
+                    batch
+                        .column(0)
+                        .as_primitive::<arrow_array::types::Int64Type>()
+                        .values()
+                        .to_vec()
+                })
+                .collect())
+        };
+        assert_eq!(read(&[1]).unwrap(), (10..20).collect_vec());
+        assert_eq!(read(&[2, 0]).unwrap(), (20..25).chain(0..10).collect_vec());
+        assert_eq!(read(&[]).unwrap(), Vec::<i64>::new());
+        assert!(read(&[3]).is_err());
+    }
+
+    #[test]
+    fn test_read_footer_stats() {
+        let store = Arc::new(LocalFileSystem::new());
+        let path = std::fs::canonicalize(PathBuf::from(
+            "./tests/data/table-without-dv-small/part-00000-517f5d32-9c95-48e8-82b4-0229cc194867-c000.snappy.parquet"
+        )).unwrap();
+        let file = FileMeta {
+            location: url::Url::from_file_path(path).unwrap(),
+            last_modified: 0,
+            size: 548,
+            e_tag: None,
+            version: None,
+        };
+        let value = || {
+            crate::schema::StructType::new(vec![crate::schema::StructField::new(
+                "value",
+                crate::schema::DataType::LONG,
+                true,
+            )])
+        };
+        let stats_schema = Arc::new(crate::schema::StructType::new(vec![
+            crate::schema::StructField::new("numRecords", crate::schema::DataType::LONG, true),
+            crate::schema::StructField::new("minValues", value(), true),
+            crate::schema::StructField::new("maxValues", value(), true),
+        ]));
+
+        let handler = DefaultParquetHandler::new(store, Arc::new(TokioBackgroundExecutor::new()));
+        let stats = handler
+            .read_footer_stats(&[file.clone(), file], stats_schema)
+            .unwrap()
+            .unwrap();
+        let stats = into_record_batch(Ok(stats)).unwrap();
+        assert_eq!(stats.num_rows(), 2);
+
+        let int64s = |array: &dyn arrow_array::Array| {
+            array
+                .as_primitive::<arrow_array::types::Int64Type>()
+                .values()
+                .to_vec()
+        };
+        let values = |name: &str| int64s(stats.column_by_name(name).unwrap().as_struct().column(0));
+        assert_eq!(int64s(stats.column(0)), [10, 10]);
+        assert_eq!(values("minValues"), [0, 0]);
+        assert_eq!(values("maxValues"), [9, 9]);
+    }
+
+    #[tokio::test]
+    async fn test_read_invalid_parquet_file() {
+        let store = Arc::new(LocalFileSystem::new());
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("part-00000.parquet");
+        std::fs::write(&path, "this is not parquet data").unwrap();
+        let url = url::Url::from_file_path(path).unwrap();
+        let files = &[FileMeta {
+            location: url.clone(),
+            last_modified: 0,
+            size: 24,
+            e_tag: None,
+            version: None,
+        }];
+        let physical_schema = Arc::new(crate::schema::StructType::new(vec![]));
+
+        let handler = DefaultParquetHandler::new(store, Arc::new(TokioBackgroundExecutor::new()));
+        let res: DeltaResult<Vec<RecordBatch>> = handler
+            .read_parquet_files(files, physical_schema, None)
+            .unwrap()
+            .map(into_record_batch)
+            .try_collect();
+        assert!(
+            matches!(res, Err(Error::InvalidParquetFile { ref location, .. }) if *location == url.to_string()),
+            "Expected an invalid parquet file error, got {res:?}"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_read_mismatched_column_names() {
+        let store = Arc::new(LocalFileSystem::new());
+        let path = std::fs::canonicalize(PathBuf::from(
+            "./tests/data/table-without-dv-small/part-00000-517f5d32-9c95-48e8-82b4-0229cc194867-c000.snappy.parquet",
+        ))
+        .unwrap();
+        let url = url::Url::from_file_path(path).unwrap();
+        let files = &[FileMeta {
+            location: url.clone(),
+            last_modified: 0,
+            size: 548,
+            e_tag: None,
+            version: None,
+        }];
+        let schema_with_name = |name: &str| {
+            Arc::new(crate::schema::StructType::new(vec![
+                crate::schema::StructField::new(name, crate::schema::DataType::LONG, true),
+            ]))
+        };
+        let read = |handler: &DefaultParquetHandler<TokioBackgroundExecutor>, name: &str| {
+            handler
+                .read_parquet_files(files, schema_with_name(name), None)
+                .unwrap()
+                .map(into_record_batch)
+                .try_collect::<_, Vec<_>, _>()
+        };
+
+        // the file's column is named `value`
+        let handler =
+            DefaultParquetHandler::new(store.clone(), Arc::new(TokioBackgroundExecutor::new()));
+        for name in ["Value", " value"] {
+            let res = read(&handler, name);
+            assert!(
+                matches!(res, Err(Error::SchemaMismatch { ref column, ref actual, .. })
+                    if column == name && actual == "columns \"value\""),
+                "Expected a schema mismatch error, got {res:?}"
+            );
+        }
+        // an unrelated name is a column that's missing from the file, and reads as null
+        let batches = read(&handler, "other").unwrap();
+        assert_eq!(batches[0].column(0).null_count(), 10);
+        // unless it isn't nullable
+        let schema = Arc::new(crate::schema::StructType::new(vec![
+            crate::schema::StructField::new("other", crate::schema::DataType::LONG, false),
+        ]));
+        let res = handler
+            .read_parquet_files(files, schema, None)
+            .unwrap()
+            .map(into_record_batch)
+            .try_collect::<_, Vec<_>, _>();
+        assert!(
+            matches!(res, Err(Error::SchemaMismatch { ref column, .. }) if column == "other"),
+            "Expected a schema mismatch error, got {res:?}"
+        );
+
+        let handler = DefaultParquetHandler::new(store, Arc::new(TokioBackgroundExecutor::new()))
+            .with_trimmed_column_names();
+        let batches = read(&handler, " value").unwrap();
+        assert_eq!(batches[0].schema().field(0).name(), " value");
+        assert_eq!(batches[0].column(0).null_count(), 0);
+        assert!(read(&handler, "Value").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_read_with_schema_validation() {
+        use crate::schema::{DataType, StructField, StructType};
+
+        let store = Arc::new(LocalFileSystem::new());
+        let path = std::fs::canonicalize(PathBuf::from(
+            "./tests/data/table-without-dv-small/part-00000-517f5d32-9c95-48e8-82b4-0229cc194867-c000.snappy.parquet",
+        ))
+        .unwrap();
+        let url = url::Url::from_file_path(path).unwrap();
+        let files = &[FileMeta {
+            location: url.clone(),
+            last_modified: 0,
+            size: 548,
+            e_tag: None,
+            version: None,
+        }];
+        let read = |handler: &DefaultParquetHandler<TokioBackgroundExecutor>,
+                    fields: Vec<StructField>| {
+            handler
+                .read_parquet_files(files, Arc::new(StructType::new(fields)), None)
+                .unwrap()
+                .map(into_record_batch)
+                .try_collect::<_, Vec<_>, _>()
+        };
+        let handler =
+            DefaultParquetHandler::new(store.clone(), Arc::new(TokioBackgroundExecutor::new()));
+        let validating =
+            DefaultParquetHandler::new(store, Arc::new(TokioBackgroundExecutor::new()))
+                .with_schema_validation();
+
+        // the file's `value` column is a long
+        let batches = read(
+            &validating,
+            vec![
+                StructField::new("value", DataType::LONG, true),
+                StructField::new("missing", DataType::STRING, true),
+            ],
+        )
+        .unwrap();
+        assert_eq!(batches[0].column(0).null_count(), 0);
+        assert_eq!(batches[0].column(1).null_count(), 10);
+
+        for data_type in [DataType::STRING, DataType::INTEGER] {
+            let fields = vec![StructField::new("value", data_type.clone(), true)];
+            let res = read(&validating, fields.clone());
+            assert!(
+                matches!(res, Err(Error::SchemaMismatch { ref file, ref column, ref expected, ref actual })
+                    if *file == url.to_string() && column == "value"
+                        && *expected == data_type.to_string() && actual == "Int64"),
+                "Expected a schema mismatch error, got {res:?}"
+            );
+            // without validation the file isn't checked
+            assert!(read(&handler, fields).is_ok());
+        }
+
+        // a missing column that isn't nullable fails either way
+        let fields = vec![StructField::new("missing", DataType::STRING, false)];
+        assert!(read(&validating, fields).is_err());
+    }
 }
@@ -0,0 +1,141 @@
+//! Resolution of the [`object_store`] backend to access each file of a table with.
+//!
+//! The files of a table are usually in the same store as its log, but a table can also reference
+//! files by absolute URL in other stores, e.g. a shallow clone referencing the data files of its
+//! source table in another bucket. The handlers of the [`DefaultEngine`] look up the store of
+//! every file they access by its URL in an [`ObjectStoreRegistry`].
+//!
+//! [`DefaultEngine`]: super::DefaultEngine
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use object_store::path::Path;
+use object_store::{parse_url_opts, DynObjectStore};
+use url::Url;
+
+use crate::{DeltaResult, Error};
+
+/// The stores to access files with, by the scheme and authority (e.g. the bucket) of their URLs.
+///
+/// A URL without a registered store is accessed with the default store, if it has the scheme and
+/// authority of the table (or the registry was created from a store for any URL, with
+/// [`ObjectStoreRegistry::new`]). Any other URL can't be accessed: stores are never created on
+/// the fly, since that would send the table's credentials to whatever store a file URL in the log
+/// points to.
+#[derive(Debug)]
+pub struct ObjectStoreRegistry {
+    default_store: Arc<DynObjectStore>,
+    /// The scheme and authority of the URLs of the default store, or `None` if it is used for all
+    /// URLs without a registered store
+    default_key: Option<String>,
+    stores: HashMap<String, Arc<DynObjectStore>>,
+}
+
+/// The key of the store of `url` in the registry, i.e. its scheme and authority
+fn store_key(url: &Url) -> String {
+    format!("{}://{}", url.scheme(), url.authority())
+}
+
+impl ObjectStoreRegistry {
+    /// Create a registry that accesses every URL without a registered store with `store`
+    pub fn new(store: Arc<DynObjectStore>) -> Self {
+        Self {
+            default_store: store,
+            default_key: None,
+            stores: HashMap::new(),
+        }
+    }
+
+    /// Create a registry for the table at `table_root`, whose store is created from its URL and
+    /// `options`, returning the registry and the path of the table in its store.
+    pub fn try_new_for_table<I, K, V>(table_root: &Url, options: I) -> DeltaResult<(Self, Path)>
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: AsRef<str>,
+        V: Into<String>,
+    {
+        let options: HashMap<String, String> = options
+            .into_iter()
+            .map(|(key, value)| (key.as_ref().to_string(), value.into()))
+            .collect();
+        let (store, prefix) = parse_url_opts(table_root, &options)?;
+        let registry = Self {
+            default_store: Arc::new(store),
+            default_key: Some(store_key(table_root)),
+            stores: HashMap::new(),
+        };
+        Ok((registry, prefix))
+    }
+
+    /// Access the URLs with the scheme and authority of `url` with `store`, instead of the default
+    /// store.
+    pub fn with_store(mut self, url: &Url, store: Arc<DynObjectStore>) -> Self {
+        self.stores.insert(store_key(url), store);
+        self
+    }
+
+    /// The store of the table, as passed to [`Self::new`] or created for its URL
+    pub fn default_store(&self) -> &Arc<DynObjectStore> {
+        &self.default_store
+    }
+
+    /// Get the store to access the file at `url` with. The path of the file in the store is the
+    /// path of `url`. Fails if no store is registered for the scheme and authority of `url`, and
+    /// it isn't that of the default store.
+    pub fn get_store(&self, url: &Url) -> DeltaResult<Arc<DynObjectStore>> {
+        let key = store_key(url);
+        if let Some(store) = self.stores.get(&key) {
+            return Ok(store.clone());
+        }
+        match self.default_key {
+            Some(ref default_key) if *default_key != key => Err(Error::generic(format!(
+                "No object store is registered for {key}, which {url} is in"
+            ))),
+            _ => Ok(self.default_store.clone()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use object_store::memory::InMemory;
+
+    use super::*;
+
+    #[test]
+    fn test_object_store_registry() {
+        let table_root = Url::parse("memory:///table/").unwrap();
+        let (registry, prefix) =
+            ObjectStoreRegistry::try_new_for_table(&table_root, [("a", "b")]).unwrap();
+        assert_eq!(prefix.as_ref(), "table");
+        let default_store = registry.default_store().clone();
+        let same = |a: &Arc<DynObjectStore>, b: &Arc<DynObjectStore>| Arc::ptr_eq(a, b);
+
+        let data_url = table_root.join("part-00000.parquet").unwrap();
+        assert!(same(
+            &registry.get_store(&data_url).unwrap(),
+            &default_store
+        ));
+
+        // a file in another store can only be accessed with a registered store
+        let bucket_url = Url::parse("s3://other-bucket/").unwrap();
+        let bucket_file = bucket_url.join("table/part-00000.parquet").unwrap();
+        let file_url = Url::parse("file:///data/part-00000.parquet").unwrap();
+        assert!(registry.get_store(&bucket_file).is_err());
+        assert!(registry.get_store(&file_url).is_err());
+        let bucket_store: Arc<DynObjectStore> = Arc::new(InMemory::new());
+        let registry = registry.with_store(&bucket_url, bucket_store.clone());
+        assert!(same(
+            &registry.get_store(&bucket_file).unwrap(),
+            &bucket_store
+        ));
+        assert!(registry.get_store(&file_url).is_err());
+
+        // a registry created from a store uses it for everything else
+        let registry = ObjectStoreRegistry::new(default_store.clone());
+        assert!(same(
+            &registry.get_store(&file_url).unwrap(),
+            &default_store
+        ));
+    }
+}
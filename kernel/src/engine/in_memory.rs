@@ -0,0 +1,373 @@
+//! An [`Engine`] whose files are all kept in memory, for tests of kernel logic that shouldn't
+//! need a real file system. This is enabled by the `test-utils` feature flag.
+//!
+//! Files are added with [`InMemoryEngine::put`], e.g. the commits of a fake `_delta_log`, and are
+//! parsed like the [`super::sync`] engine parses local files (see [`super::sync::client_handlers`]).
+//! The engine records the reads and listings of its files, and can inject failures into them, so
+//! that tests of clients and scans that need to observe their IO can share it.
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use bytes::Bytes;
+use itertools::Itertools;
+use url::Url;
+
+use super::arrow_expression::ArrowExpressionHandler;
+use super::sync::client_handlers::{ClientJsonHandler, ClientParquetHandler};
+use crate::{
+    DeltaResult, Engine, Error, ExpressionHandler, FileMeta, FileSlice, FileSystemClient,
+    JsonHandler, ParquetHandler,
+};
+
+/// The message of the [`Error::Generic`] of the failures injected with
+/// [`InMemoryEngine::fail_reads`] and [`InMemoryEngine::fail_listings`]
+pub const INJECTED_FAILURE: &str = "503 Service Unavailable";
+
+/// A file of an [`InMemoryEngine`]
+struct File {
+    data: Bytes,
+    last_modified: i64,
+}
+
+/// The files of an [`InMemoryEngine`], and what was done with them
+#[derive(Default)]
+struct State {
+    files: HashMap<Url, File>,
+    /// The number of files put so far, which is the `last_modified` of the next one
+    puts: i64,
+    /// The location of each slice read, in order
+    reads: Vec<Url>,
+    /// The location of each listing, in order
+    listings: Vec<Url>,
+    /// The number of reads of each file that fail before one succeeds
+    read_failures: HashMap<Url, usize>,
+    /// The number of listings that fail right after listing each file
+    list_failures: HashMap<Url, usize>,
+    /// How long each listing takes
+    list_delay: Duration,
+}
+
+type SharedState = Arc<Mutex<State>>;
+
+/// Take one of the failures of `location`, if it has any left
+fn take_failure(failures: &mut HashMap<Url, usize>, location: &Url) -> bool {
+    match failures.get_mut(location) {
+        Some(count) if *count > 0 => {
+            *count -= 1;
+            true
+        }
+        _ => false,
+    }
+}
+
+/// An [`Engine`] that serves files from memory instead of a file system. It has no notion of
+/// directories: listing a directory lists the files whose locations start with its location, and
+/// reading a file that wasn't [put](Self::put) fails with an [`Error::FileNotFound`].
+///
+/// It also records what its [`FileSystemClient`] does, for tests to check which files were read
+/// or listed (see [`Self::take_reads`] and [`Self::take_listings`]), and can make reads and
+/// listings fail like an unavailable object store (see [`Self::fail_reads`]) or take as long as a
+/// slow one (see [`Self::delay_listings`]).
+///
+/// Cloning the engine shares its files.
+#[derive(Clone, Default)]
+pub struct InMemoryEngine {
+    state: SharedState,
+}
+
+impl InMemoryEngine {
+    /// Create an engine without any files
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn state(&self) -> std::sync::MutexGuard<'_, State> {
+        self.state.lock().unwrap()
+    }
+
+    /// Store `data` as the content of the file at `location`, replacing any previous content.
+    /// Each file put is modified after the previous one: its `last_modified` is the number of
+    /// files put before it.
+    pub fn put(&self, location: Url, data: impl Into<Bytes>) {
+        let mut state = self.state();
+        let last_modified = state.puts;
+        state.puts += 1;
+        let data = data.into();
+        state.files.insert(
+            location,
+            File {
+                data,
+                last_modified,
+            },
+        );
+    }
+
+    /// Put every file under the local directory `path`, at its path relative to `path` under
+    /// `root` (a directory location, ending with a `/`)
+    pub fn put_local_dir(&self, root: &Url, path: impl AsRef<Path>) -> DeltaResult<()> {
+        for entry in std::fs::read_dir(path)? {
+            let entry = entry?;
+            let name = entry.file_name().to_string_lossy().into_owned();
+            if entry.file_type()?.is_dir() {
+                self.put_local_dir(&root.join(&format!("{name}/"))?, entry.path())?;
+            } else {
+                self.put(root.join(&name)?, std::fs::read(entry.path())?);
+            }
+        }
+        Ok(())
+    }
+
+    /// Remove the file at `location`, if there is one
+    pub fn remove(&self, location: &Url) {
+        self.state().files.remove(location);
+    }
+
+    /// The location of each slice read since the last call, in order. This includes the reads
+    /// of files that don't exist, and the failed reads.
+    pub fn take_reads(&self) -> Vec<Url> {
+        std::mem::take(&mut self.state().reads)
+    }
+
+    /// The location of each listing since the last call, in order
+    pub fn take_listings(&self) -> Vec<Url> {
+        std::mem::take(&mut self.state().listings)
+    }
+
+    /// Make the next `count` reads of the file at `location` fail with an [`Error::Generic`] of
+    /// [`INJECTED_FAILURE`]
+    pub fn fail_reads(&self, location: Url, count: usize) {
+        self.state().read_failures.insert(location, count);
+    }
+
+    /// Make the next `count` listings that list the file at `location` fail right after it, with
+    /// an [`Error::Generic`] of [`INJECTED_FAILURE`]
+    pub fn fail_listings(&self, location: Url, count: usize) {
+        self.state().list_failures.insert(location, count);
+    }
+
+    /// Make each listing take at least `delay`, e.g. for concurrent listings to overlap
+    pub fn delay_listings(&self, delay: Duration) {
+        self.state().list_delay = delay;
+    }
+}
+
+impl Engine for InMemoryEngine {
+    fn get_expression_handler(&self) -> Arc<dyn ExpressionHandler> {
+        Arc::new(ArrowExpressionHandler {})
+    }
+
+    fn get_file_system_client(&self) -> Arc<dyn FileSystemClient> {
+        Arc::new(InMemoryFileSystemClient(self.state.clone()))
+    }
+
+    fn get_json_handler(&self) -> Arc<dyn JsonHandler> {
+        Arc::new(ClientJsonHandler(self.get_file_system_client()))
+    }
+
+    fn get_parquet_handler(&self) -> Arc<dyn ParquetHandler> {
+        Arc::new(ClientParquetHandler(self.get_file_system_client()))
+    }
+}
+
+struct InMemoryFileSystemClient(SharedState);
+
+impl InMemoryFileSystemClient {
+    /// List the files in the directory of `path` (e.g. `a/` for `a/b`) from `path` on, in order of
+    /// their locations. Without `recursive`, only the files directly in the directory are listed.
+    fn list(
+        &self,
+        path: &Url,
+        recursive: bool,
+    ) -> DeltaResult<Box<dyn Iterator<Item = DeltaResult<FileMeta>>>> {
+        let mut state = self.0.lock().unwrap();
+        state.listings.push(path.clone());
+        let path = path.as_str();
+        let dir = &path[..path.rfind('/').map_or(0, |index| index + 1)];
+        let files = state
+            .files
+            .iter()
+            .filter(|(location, _)| {
+                let location = location.as_str();
+                location >= path
+                    && location
+                        .strip_prefix(dir)
+                        .is_some_and(|name| recursive || !name.contains('/'))
+            })
+            .map(|(location, file)| FileMeta {
+                location: location.clone(),
+                last_modified: file.last_modified,
+                size: file.data.len(),
+                e_tag: None,
+                version: None,
+            })
+            .sorted_by(|a, b| a.location.as_str().cmp(b.location.as_str()))
+            .collect_vec();
+        let mut listing = vec![];
+        for file in files {
+            let failed = take_failure(&mut state.list_failures, &file.location);
+            listing.push(Ok(file));
+            if failed {
+                listing.push(Err(Error::generic(INJECTED_FAILURE)));
+                break;
+            }
+        }
+        let delay = state.list_delay;
+        drop(state);
+        std::thread::sleep(delay);
+        Ok(Box::new(listing.into_iter()))
+    }
+}
+
+impl FileSystemClient for InMemoryFileSystemClient {
+    fn list_from(
+        &self,
+        path: &Url,
+    ) -> DeltaResult<Box<dyn Iterator<Item = DeltaResult<FileMeta>>>> {
+        self.list(path, false)
+    }
+
+    fn list_from_recursive(
+        &self,
+        path: &Url,
+    ) -> DeltaResult<Box<dyn Iterator<Item = DeltaResult<FileMeta>>>> {
+        self.list(path, true)
+    }
+
+    fn read_files(
+        &self,
+        files: Vec<FileSlice>,
+    ) -> DeltaResult<Box<dyn Iterator<Item = DeltaResult<Bytes>>>> {
+        let mut state = self.0.lock().unwrap();
+        let reads = files
+            .into_iter()
+            .map(|(location, range)| {
+                state.reads.push(location.clone());
+                if take_failure(&mut state.read_failures, &location) {
+                    return Err(Error::generic(INJECTED_FAILURE));
+                }
+                let data = match state.files.get(&location) {
+                    Some(file) => file.data.clone(),
+                    None => return Err(Error::file_not_found(&location)),
+                };
+                match range {
+                    Some(range) if range.end > data.len() || range.start > range.end => {
+                        Err(Error::generic(format!(
+                            "Range {range:?} is out of bounds of {location}, which has {} bytes",
+                            data.len()
+                        )))
+                    }
+                    Some(range) => Ok(data.slice(range)),
+                    None => Ok(data),
+                }
+            })
+            .collect_vec();
+        Ok(Box::new(reads.into_iter()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use arrow_array::cast::AsArray;
+    use arrow_array::types::Int64Type;
+    use arrow_array::{Int64Array, RecordBatch};
+    use arrow_schema::{DataType, Field, Schema as ArrowSchema};
+
+    use super::*;
+    use crate::engine::arrow_data::ArrowEngineData;
+    use crate::scan::ScanBuilder;
+    use crate::test_utils::{add_action, metadata_action, parquet_bytes, protocol_action};
+    use crate::Table;
+
+    fn parquet_file(values: Vec<i64>) -> Vec<u8> {
+        let schema = Arc::new(ArrowSchema::new(vec![Field::new(
+            "value",
+            DataType::Int64,
+            true,
+        )]));
+        let batch = RecordBatch::try_new(schema, vec![Arc::new(Int64Array::from(values))]).unwrap();
+        parquet_bytes(&batch, None)
+    }
+
+    #[test]
+    fn test_in_memory_file_system_client() {
+        let engine = InMemoryEngine::new();
+        let url = |path: &str| Url::parse(&format!("memory:///{path}")).unwrap();
+        for path in ["t/b", "t/a", "t/c/d", "t2/e"] {
+            engine.put(url(path), path.to_string());
+        }
+        let client = engine.get_file_system_client();
+        let list = |path: &str, recursive: bool| -> Vec<_> {
+            let listing = match recursive {
+                true => client.list_from_recursive(&url(path)),
+                false => client.list_from(&url(path)),
+            };
+            listing
+                .unwrap()
+                .map_ok(|file| file.location.path().to_string())
+                .try_collect()
+                .unwrap()
+        };
+        assert_eq!(list("t/", false), ["/t/a", "/t/b"]);
+        assert_eq!(list("t/b", false), ["/t/b"]);
+        assert_eq!(list("t/", true), ["/t/a", "/t/b", "/t/c/d"]);
+
+        let reads: Vec<_> = client
+            .read_files(vec![(url("t/c/d"), Some(2..5)), (url("t/a"), None)])
+            .unwrap()
+            .try_collect()
+            .unwrap();
+        assert_eq!(reads, ["c/d", "t/a"]);
+        let mut missing = client.read_files(vec![(url("t/x"), None)]).unwrap();
+        assert!(matches!(missing.next(), Some(Err(Error::FileNotFound(_)))));
+    }
+
+    #[test]
+    fn test_in_memory_table() {
+        let engine = InMemoryEngine::new();
+        let table_root = Url::parse("memory:///table/").unwrap();
+        let file = parquet_file(vec![1, 2, 3]);
+        let schema = serde_json::json!({
+            "type": "struct",
+            "fields": [{"name": "value", "type": "long", "nullable": true, "metadata": {}}]
+        });
+        let commit = [
+            protocol_action(1, 2),
+            metadata_action(&schema, &[], &[]),
+            add_action("part-0.parquet", file.len()),
+        ];
+        let log_root = table_root.join("_delta_log/").unwrap();
+        engine.put(
+            log_root.join("00000000000000000000.json").unwrap(),
+            commit.iter().join("\n"),
+        );
+        engine.put(table_root.join("part-0.parquet").unwrap(), file);
+        let file = parquet_file(vec![4]);
+        engine.put(
+            log_root.join("00000000000000000001.json").unwrap(),
+            add_action("part-1.parquet", file.len()).to_string(),
+        );
+        engine.put(table_root.join("part-1.parquet").unwrap(), file);
+
+        let snapshot = Table::new(table_root).snapshot(&engine, None).unwrap();
+        assert_eq!(snapshot.version(), 1);
+        let scan = ScanBuilder::new(snapshot).build().unwrap();
+        let values: Vec<_> = scan
+            .execute(&engine)
+            .unwrap()
+            .into_iter()
+            .flat_map(|result| {
+                let data = result.raw_data.unwrap();
+                let batch: RecordBatch =
+                    ArrowEngineData::try_from_engine_data(data).unwrap().into();
+                let values = batch.column(0).as_primitive::<Int64Type>();
+                values.values().to_vec()
+            })
+            .sorted()
+            .collect();
+        assert_eq!(values, [1, 2, 3, 4]);
+    }
+}
@@ -17,8 +17,21 @@ pub(crate) mod arrow_get_data;
 #[cfg(any(feature = "default-engine", feature = "sync-engine"))]
 pub(crate) mod arrow_utils;
 
+pub mod caching;
+
+#[cfg(feature = "datafusion")]
+pub mod datafusion;
+
 #[cfg(feature = "default-engine")]
 pub mod default;
 
+#[cfg(feature = "test-utils")]
+pub mod in_memory;
+
+pub mod retry;
+
 #[cfg(feature = "sync-engine")]
 pub mod sync;
+
+#[cfg(feature = "tar-bundle")]
+pub mod tar_bundle;
@@ -0,0 +1,341 @@
+//! A [`FileSystemClient`] decorator that retries the failed requests of another client with
+//! exponential backoff, so that transient errors (like 503s from cloud storage) don't fail a whole
+//! snapshot load or scan.
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::Arc;
+use std::time::Duration;
+
+use bytes::Bytes;
+use url::Url;
+
+use crate::{DeltaResult, Error, FileMeta, FileSlice, FileSystemClient};
+
+/// How [`RetryFileSystemClient`] backs off between the attempts of a request. The delay before
+/// retry `n` (starting at 0) is `initial_delay * multiplier^n`, capped at `max_delay`, of which
+/// up to the fraction `jitter` is randomly taken off.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Backoff {
+    /// The maximum number of attempts of a request, including the first one
+    pub max_attempts: usize,
+    /// The delay before the first retry
+    pub initial_delay: Duration,
+    /// The factor the delay grows by with every retry
+    pub multiplier: f64,
+    /// The maximum delay before a retry
+    pub max_delay: Duration,
+    /// The fraction (between 0 and 1) of each delay that is random, so that clients that fail at
+    /// the same time don't all retry at the same time
+    pub jitter: f64,
+}
+
+impl Default for Backoff {
+    /// Up to 5 attempts, with delays starting at 100ms and doubling up to 10s, half of which is
+    /// random
+    fn default() -> Self {
+        Self {
+            max_attempts: 5,
+            initial_delay: Duration::from_millis(100),
+            multiplier: 2.0,
+            max_delay: Duration::from_secs(10),
+            jitter: 0.5,
+        }
+    }
+}
+
+impl Backoff {
+    /// The delay before retry number `retry` (starting at 0)
+    fn delay(&self, retry: usize) -> Duration {
+        let exponent = i32::try_from(retry).unwrap_or(i32::MAX);
+        let delay = self.initial_delay.as_secs_f64() * self.multiplier.powi(exponent);
+        let delay = delay.min(self.max_delay.as_secs_f64());
+        let jitter = self.jitter.clamp(0.0, 1.0) * random_fraction();
+        Duration::try_from_secs_f64(delay * (1.0 - jitter)).unwrap_or(self.max_delay)
+    }
+}
+
+/// A random number between 0 and 1. This doesn't need to be a good random number, just one that
+/// differs between clients, and `RandomState` is seeded randomly for every one.
+fn random_fraction() -> f64 {
+    RandomState::new().build_hasher().finish() as f64 / u64::MAX as f64
+}
+
+type RetryPredicate = Arc<dyn Fn(&Error) -> bool + Send + Sync>;
+
+/// When and how to retry a failed request
+#[derive(Clone)]
+struct RetryPolicy {
+    should_retry: RetryPredicate,
+    backoff: Backoff,
+}
+
+impl RetryPolicy {
+    /// Whether to retry a request that failed with `err` after its attempt number `attempt`
+    /// (starting at 0), sleeping for the backoff if so
+    fn wait_to_retry(&self, err: &Error, attempt: usize) -> bool {
+        let retry = attempt + 1 < self.backoff.max_attempts && (self.should_retry)(err);
+        if retry {
+            std::thread::sleep(self.backoff.delay(attempt));
+        }
+        retry
+    }
+
+    /// Retry `request`, whose first attempt had `result`, until it succeeds, fails with an error
+    /// that isn't retried, or runs out of attempts
+    fn retry<T>(
+        &self,
+        mut result: DeltaResult<T>,
+        mut request: impl FnMut() -> DeltaResult<T>,
+    ) -> DeltaResult<T> {
+        let mut attempt = 0;
+        while let Err(ref err) = result {
+            if !self.wait_to_retry(err, attempt) {
+                break;
+            }
+            attempt += 1;
+            result = request();
+        }
+        result
+    }
+
+    fn run<T>(&self, mut request: impl FnMut() -> DeltaResult<T>) -> DeltaResult<T> {
+        self.retry(request(), request)
+    }
+}
+
+/// A [`FileSystemClient`] that retries the requests of an inner client that fail with an error
+/// that a predicate selects, e.g. because it's transient. Every request is retried on its own:
+///
+/// - a listing that fails midway is listed again, and the files that were already listed are
+///   skipped
+/// - of the slices of a [`FileSystemClient::read_files`] call, only the ones that failed are read
+///   again, each on its own
+///
+/// Retries block the calling thread for their backoff.
+pub struct RetryFileSystemClient {
+    inner: Arc<dyn FileSystemClient>,
+    policy: RetryPolicy,
+}
+
+impl RetryFileSystemClient {
+    /// Create a client retrying the requests of `inner` that fail with an error for which
+    /// `should_retry` returns true, backing off between the attempts of a request according to
+    /// `backoff`.
+    pub fn new(
+        inner: Arc<dyn FileSystemClient>,
+        should_retry: impl Fn(&Error) -> bool + Send + Sync + 'static,
+        backoff: Backoff,
+    ) -> Self {
+        let policy = RetryPolicy {
+            should_retry: Arc::new(should_retry),
+            backoff,
+        };
+        Self { inner, policy }
+    }
+
+    fn list(
+        &self,
+        path: &Url,
+        recursive: bool,
+    ) -> DeltaResult<Box<dyn Iterator<Item = DeltaResult<FileMeta>>>> {
+        let files = self
+            .policy
+            .run(|| list(self.inner.as_ref(), path, recursive))?;
+        Ok(Box::new(RetryListing {
+            inner: self.inner.clone(),
+            policy: self.policy.clone(),
+            path: path.clone(),
+            recursive,
+            files,
+            last_listed: None,
+            failures: 0,
+        }))
+    }
+}
+
+fn list(
+    client: &dyn FileSystemClient,
+    path: &Url,
+    recursive: bool,
+) -> DeltaResult<Box<dyn Iterator<Item = DeltaResult<FileMeta>>>> {
+    match recursive {
+        true => client.list_from_recursive(path),
+        false => client.list_from(path),
+    }
+}
+
+/// A listing that is listed again from the start when it fails, skipping the files up to the last
+/// one it returned. Listings are sorted, so these are the files returned before the failure.
+struct RetryListing {
+    inner: Arc<dyn FileSystemClient>,
+    policy: RetryPolicy,
+    path: Url,
+    recursive: bool,
+    files: Box<dyn Iterator<Item = DeltaResult<FileMeta>>>,
+    last_listed: Option<String>,
+    /// The number of failures since the last file was returned
+    failures: usize,
+}
+
+impl Iterator for RetryListing {
+    type Item = DeltaResult<FileMeta>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            match self.files.next()? {
+                Ok(file) => {
+                    let location = file.location.as_str();
+                    if self
+                        .last_listed
+                        .as_deref()
+                        .is_some_and(|last| location <= last)
+                    {
+                        continue;
+                    }
+                    self.last_listed = Some(location.to_string());
+                    self.failures = 0;
+                    return Some(Ok(file));
+                }
+                Err(err) if self.policy.wait_to_retry(&err, self.failures) => {
+                    self.failures += 1;
+                    self.files = list(self.inner.as_ref(), &self.path, self.recursive)
+                        .unwrap_or_else(|err| Box::new(std::iter::once(Err(err))));
+                }
+                Err(err) => return Some(Err(err)),
+            }
+        }
+    }
+}
+
+/// Read a single file slice (with its e-tag) with `client`
+fn read_one(
+    client: &dyn FileSystemClient,
+    file: (FileSlice, Option<String>),
+) -> DeltaResult<Bytes> {
+    let location = file.0 .0.clone();
+    client
+        .read_files_if_unchanged(vec![file])?
+        .next()
+        .unwrap_or_else(|| Err(Error::file_not_found(location)))
+}
+
+impl FileSystemClient for RetryFileSystemClient {
+    fn list_from(
+        &self,
+        path: &Url,
+    ) -> DeltaResult<Box<dyn Iterator<Item = DeltaResult<FileMeta>>>> {
+        self.list(path, false)
+    }
+
+    fn list_from_recursive(
+        &self,
+        path: &Url,
+    ) -> DeltaResult<Box<dyn Iterator<Item = DeltaResult<FileMeta>>>> {
+        self.list(path, true)
+    }
+
+    fn read_files(
+        &self,
+        files: Vec<FileSlice>,
+    ) -> DeltaResult<Box<dyn Iterator<Item = DeltaResult<Bytes>>>> {
+        self.read_files_if_unchanged(files.into_iter().map(|file| (file, None)).collect())
+    }
+
+    fn read_files_if_unchanged(
+        &self,
+        files: Vec<(FileSlice, Option<String>)>,
+    ) -> DeltaResult<Box<dyn Iterator<Item = DeltaResult<Bytes>>>> {
+        let mut reads = self
+            .policy
+            .run(|| self.inner.read_files_if_unchanged(files.clone()))?;
+        let inner = self.inner.clone();
+        let policy = self.policy.clone();
+        let reads = files.into_iter().map(move |file| {
+            let read = || read_one(inner.as_ref(), file.clone());
+            let result = reads.next().unwrap_or_else(read);
+            policy.retry(result, read)
+        });
+        Ok(Box::new(reads))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use itertools::Itertools;
+
+    use super::*;
+    use crate::engine::in_memory::{InMemoryEngine, INJECTED_FAILURE};
+    use crate::Engine;
+
+    fn client(engine: &InMemoryEngine, max_attempts: usize) -> RetryFileSystemClient {
+        let backoff = Backoff {
+            max_attempts,
+            initial_delay: Duration::ZERO,
+            ..Default::default()
+        };
+        let is_unavailable =
+            |err: &Error| matches!(err, Error::Generic(msg) if msg == INJECTED_FAILURE);
+        RetryFileSystemClient::new(engine.get_file_system_client(), is_unavailable, backoff)
+    }
+
+    #[test]
+    fn test_backoff_delay() {
+        let backoff = Backoff {
+            jitter: 0.0,
+            ..Default::default()
+        };
+        let delays = [0, 1, 2, 10].map(|retry| backoff.delay(retry));
+        assert_eq!(delays, [100, 200, 400, 10_000].map(Duration::from_millis));
+        let jittered = Backoff::default().delay(1);
+        assert!(jittered >= Duration::from_millis(100) && jittered <= Duration::from_millis(200));
+    }
+
+    #[test]
+    fn test_retry_read_files() {
+        let engine = InMemoryEngine::new();
+        let urls =
+            ["memory:///a", "memory:///b", "memory:///c"].map(|name| Url::parse(name).unwrap());
+        for url in &urls {
+            engine.put(url.clone(), url.to_string());
+        }
+        engine.fail_reads(urls[1].clone(), 2);
+        engine.fail_reads(urls[2].clone(), 5);
+        let files = urls.iter().map(|url| (url.clone(), None)).collect_vec();
+        let reads: Vec<_> = client(&engine, 3).read_files(files).unwrap().collect();
+
+        assert_eq!(reads[0].as_ref().unwrap(), "memory:///a");
+        assert_eq!(reads[1].as_ref().unwrap(), "memory:///b");
+        // c failed all 3 attempts
+        assert!(matches!(reads[2], Err(Error::Generic(_))));
+        // only the failed slices were read again
+        let counts = engine.take_reads().into_iter().counts();
+        assert_eq!(urls.map(|url| counts[&url]), [1, 3, 3]);
+    }
+
+    #[test]
+    fn test_retry_listing() {
+        let engine = InMemoryEngine::new();
+        let names = ["memory:///a", "memory:///b", "memory:///c"];
+        let urls = names.map(|name| Url::parse(name).unwrap());
+        for url in &urls {
+            engine.put(url.clone(), "");
+        }
+        engine.fail_listings(urls[0].clone(), 1);
+        engine.fail_listings(urls[1].clone(), 2);
+        let from = Url::parse("memory:///").unwrap();
+        let listed: Vec<_> = client(&engine, 3)
+            .list_from(&from)
+            .unwrap()
+            .map_ok(|file| file.location.to_string())
+            .try_collect()
+            .unwrap();
+        // every file is listed once, even though the listing was restarted three times
+        assert_eq!(listed, names);
+        assert_eq!(engine.take_listings().len(), 4);
+
+        engine.fail_listings(urls[2].clone(), 3);
+        let listing: Vec<_> = client(&engine, 3).list_from(&from).unwrap().collect();
+        assert_eq!(listing.len(), 4);
+        assert!(matches!(listing[3], Err(Error::Generic(_))));
+    }
+}
@@ -0,0 +1,187 @@
+//! A [`JsonHandler`] and a [`ParquetHandler`] that read files with a [`FileSystemClient`] and parse
+//! them like the [`super::SyncEngine`] parses local files, for engines whose files aren't on a file
+//! system (like the files of an [`crate::engine::in_memory::InMemoryEngine`]).
+use std::sync::Arc;
+use std::task::Poll;
+
+use arrow_schema::SchemaRef as ArrowSchemaRef;
+use bytes::Bytes;
+use itertools::Itertools;
+use url::Url;
+
+use super::json::SyncJsonHandler;
+use super::parquet::{read_parquet_metadata, try_create_reader};
+use crate::engine::arrow_data::ArrowEngineData;
+use crate::engine::arrow_utils::{
+    reorder_record_batch, row_group_layout, stats_from_footers, JsonChunkDecoder,
+};
+use crate::schema::SchemaRef;
+use crate::{
+    DeltaResult, EngineData, Error, Expression, FileDataReadResultIterator, FileMeta,
+    FileSystemClient, JsonHandler, ParquetHandler, RowGroupMeta,
+};
+
+/// The number of rows of the batches JSON files are parsed into
+const JSON_BATCH_SIZE: usize = 1024;
+
+/// Read the whole file at `location` with `client`
+fn read(client: &dyn FileSystemClient, location: &Url) -> DeltaResult<Bytes> {
+    client
+        .read_files(vec![(location.clone(), None)])?
+        .next()
+        .unwrap_or_else(|| Err(Error::file_not_found(location)))
+}
+
+/// An iterator of the batches parsed from newline-delimited JSON that is read in chunks
+struct JsonStreamParser {
+    chunks: Box<dyn Iterator<Item = DeltaResult<Bytes>> + Send>,
+    decoder: JsonChunkDecoder,
+}
+
+impl JsonStreamParser {
+    fn try_new(
+        chunks: Box<dyn Iterator<Item = DeltaResult<Bytes>> + Send>,
+        schema: ArrowSchemaRef,
+        batch_size: usize,
+    ) -> DeltaResult<Self> {
+        Ok(Self {
+            chunks,
+            decoder: JsonChunkDecoder::try_new(schema, batch_size)?,
+        })
+    }
+}
+
+impl Iterator for JsonStreamParser {
+    type Item = DeltaResult<Box<dyn EngineData>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // the chunks of an iterator are always ready
+        let Poll::Ready(batch) = self
+            .decoder
+            .poll_next_batch(|| Poll::Ready(self.chunks.next()))
+        else {
+            unreachable!("JSON decoding was pending on an iterator");
+        };
+        Some(batch?.map(|batch| Box::new(ArrowEngineData::new(batch)) as _))
+    }
+}
+
+/// Parses JSON like the [`SyncJsonHandler`], from the files read with a [`FileSystemClient`]
+pub(crate) struct ClientJsonHandler(pub(crate) Arc<dyn FileSystemClient>);
+
+impl JsonHandler for ClientJsonHandler {
+    fn parse_json(
+        &self,
+        json_strings: Box<dyn EngineData>,
+        output_schema: SchemaRef,
+    ) -> DeltaResult<Box<dyn EngineData>> {
+        SyncJsonHandler.parse_json(json_strings, output_schema)
+    }
+
+    fn read_json_files(
+        &self,
+        files: &[FileMeta],
+        physical_schema: SchemaRef,
+        _predicate: Option<Expression>,
+    ) -> DeltaResult<FileDataReadResultIterator> {
+        let schema: ArrowSchemaRef = Arc::new(physical_schema.as_ref().try_into()?);
+        let batches: Vec<_> = files
+            .iter()
+            .map(|file| {
+                let data = read(self.0.as_ref(), &file.location)?;
+                let json = Box::new(std::iter::once(Ok(data)));
+                JsonStreamParser::try_new(json, schema.clone(), JSON_BATCH_SIZE)
+            })
+            .flatten_ok()
+            .map(|batch| batch?)
+            .collect();
+        Ok(Box::new(batches.into_iter()))
+    }
+
+    #[cfg(feature = "default-engine")]
+    fn parse_json_stream<'a>(
+        &'a self,
+        json: futures::stream::BoxStream<'a, DeltaResult<Bytes>>,
+        output_schema: SchemaRef,
+        batch_size: usize,
+    ) -> DeltaResult<futures::stream::BoxStream<'a, DeltaResult<arrow_array::RecordBatch>>> {
+        SyncJsonHandler.parse_json_stream(json, output_schema, batch_size)
+    }
+}
+
+/// Reads parquet files like the sync engine's parquet handler, from the files read with a
+/// [`FileSystemClient`]. Each file is read in full.
+pub(crate) struct ClientParquetHandler(pub(crate) Arc<dyn FileSystemClient>);
+
+impl ClientParquetHandler {
+    /// Read the columns of `schema` of the row groups `row_groups` (or all of them) of the file
+    /// at `location`
+    fn read(
+        &self,
+        location: &Url,
+        schema: &SchemaRef,
+        row_groups: Option<Vec<usize>>,
+    ) -> DeltaResult<Vec<DeltaResult<Box<dyn EngineData>>>> {
+        let data = read(self.0.as_ref(), location)?;
+        let metadata = read_parquet_metadata(&data, location)?;
+        let reader = try_create_reader(data, metadata, schema, location, row_groups)?;
+        Ok(reader
+            .map(|batch| {
+                let batch = reorder_record_batch(batch?, schema, false)?;
+                Ok(Box::new(ArrowEngineData::new(batch)) as _)
+            })
+            .collect())
+    }
+}
+
+impl ParquetHandler for ClientParquetHandler {
+    fn read_parquet_files(
+        &self,
+        files: &[FileMeta],
+        physical_schema: SchemaRef,
+        _predicate: Option<Expression>,
+    ) -> DeltaResult<FileDataReadResultIterator> {
+        let batches: Vec<_> = files
+            .iter()
+            .map(|file| self.read(&file.location, &physical_schema, None))
+            .flatten_ok()
+            .map(|batch| batch?)
+            .collect();
+        Ok(Box::new(batches.into_iter()))
+    }
+
+    fn read_footer_stats(
+        &self,
+        files: &[FileMeta],
+        stats_schema: SchemaRef,
+    ) -> DeltaResult<Option<Box<dyn EngineData>>> {
+        let footers: Vec<_> = files
+            .iter()
+            .map(|file| {
+                let data = read(self.0.as_ref(), &file.location)?;
+                Ok(read_parquet_metadata(&data, &file.location)?
+                    .metadata()
+                    .clone())
+            })
+            .collect::<DeltaResult<_>>()?;
+        let stats = stats_from_footers(&footers, &stats_schema)?;
+        Ok(Some(Box::new(ArrowEngineData::new(stats))))
+    }
+
+    fn parquet_row_group_layout(&self, file: &FileMeta) -> DeltaResult<Vec<RowGroupMeta>> {
+        let data = read(self.0.as_ref(), &file.location)?;
+        let metadata = read_parquet_metadata(&data, &file.location)?;
+        Ok(row_group_layout(metadata.metadata()))
+    }
+
+    fn read_parquet_row_groups(
+        &self,
+        file: &FileMeta,
+        row_group_indices: &[usize],
+        physical_schema: SchemaRef,
+    ) -> DeltaResult<FileDataReadResultIterator> {
+        let row_groups = Some(row_group_indices.to_vec());
+        let batches = self.read(&file.location, &physical_schema, row_groups)?;
+        Ok(Box::new(batches.into_iter()))
+    }
+}
@@ -8,6 +8,29 @@ use crate::{DeltaResult, Error, FileMeta, FileSlice, FileSystemClient};
 
 pub(crate) struct SyncFilesystemClient;
 
+/// Get the [`FileMeta`] of the local file at `path`
+fn file_meta(path: &std::path::Path) -> DeltaResult<FileMeta> {
+    let metadata = path.metadata()?;
+    let last_modified = metadata
+        .modified()
+        .map(
+            |modified| match modified.duration_since(SystemTime::UNIX_EPOCH) {
+                Ok(d) => d.as_millis() as i64,
+                Err(_) => 0,
+            },
+        )
+        .unwrap_or(0);
+    Url::from_file_path(path)
+        .map(|location| FileMeta {
+            location,
+            last_modified,
+            size: metadata.len() as usize,
+            e_tag: None,
+            version: None,
+        })
+        .map_err(|_| Error::Generic(format!("Invalid path: {:?}", path)))
+}
+
 impl FileSystemClient for SyncFilesystemClient {
     /// List the paths in the same directory that are lexicographically greater or equal to
     /// (UTF-8 sorting) the given `path`. The result is sorted by the file name.
@@ -49,32 +72,58 @@ impl FileSystemClient for SyncFilesystemClient {
             let it = all_ents
                 .into_iter()
                 .sorted_by_key(|ent| ent.path())
-                .map(|ent| {
-                    ent.metadata().map_err(Error::IOError).and_then(|metadata| {
-                        let last_modified: u64 = metadata
-                            .modified()
-                            .map(
-                                |modified| match modified.duration_since(SystemTime::UNIX_EPOCH) {
-                                    Ok(d) => d.as_secs(),
-                                    Err(_) => 0,
-                                },
-                            )
-                            .unwrap_or(0);
-                        Url::from_file_path(ent.path())
-                            .map(|location| FileMeta {
-                                location,
-                                last_modified: last_modified as i64,
-                                size: metadata.len() as usize,
-                            })
-                            .map_err(|_| Error::Generic(format!("Invalid path: {:?}", ent.path())))
-                    })
-                });
+                .map(|ent| file_meta(&ent.path()));
             Ok(Box::new(it))
         } else {
             Err(Error::generic("Can only read local filesystem"))
         }
     }
 
+    fn list_from_recursive(
+        &self,
+        url_path: &Url,
+    ) -> DeltaResult<Box<dyn Iterator<Item = DeltaResult<FileMeta>>>> {
+        if url_path.scheme() != "file" {
+            return Err(Error::generic("Can only read local filesystem"));
+        }
+        let invalid_path = || Error::Generic(format!("Invalid path for list_from: {:?}", url_path));
+        let path = url_path.to_file_path().map_err(|_| invalid_path())?;
+        // like list_from, but the min_file_name is compared to the path relative to the dir
+        let (dir, min_path) = if path.is_dir() {
+            (path, None)
+        } else {
+            let parent = path.parent().ok_or_else(invalid_path)?.to_path_buf();
+            let file_name = path.file_name().ok_or_else(invalid_path)?;
+            (parent, Some(file_name.to_string_lossy().into_owned()))
+        };
+
+        let mut files = Vec::new();
+        let mut dirs = vec![dir.clone()];
+        while let Some(current) = dirs.pop() {
+            for ent in std::fs::read_dir(current)? {
+                let ent = ent?;
+                if ent.file_type()?.is_dir() {
+                    dirs.push(ent.path());
+                    continue;
+                }
+                let path = ent.path();
+                let relative = path
+                    .strip_prefix(&dir)
+                    .map_err(|_| invalid_path())?
+                    .to_string_lossy()
+                    .replace(std::path::MAIN_SEPARATOR, "/");
+                if min_path
+                    .as_ref()
+                    .is_none_or(|min_path| relative >= *min_path)
+                {
+                    files.push((relative, path));
+                }
+            }
+        }
+        let it = files.into_iter().sorted().map(|(_, path)| file_meta(&path));
+        Ok(Box::new(it))
+    }
+
     /// Read data specified by the start and end offset from the file.
     fn read_files(
         &self,
@@ -150,6 +199,45 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_list_from_recursive() -> Result<(), Box<dyn std::error::Error>> {
+        let client = SyncFilesystemClient;
+        let tmp_dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(tmp_dir.path().join("_sidecars/nested"))?;
+        for name in [
+            get_json_filename(0),
+            get_json_filename(1),
+            "_sidecars/a.parquet".to_string(),
+            "_sidecars/nested/b.parquet".to_string(),
+        ] {
+            std::fs::write(tmp_dir.path().join(name), "null")?;
+        }
+        let list = |url: &Url| -> Vec<String> {
+            client
+                .list_from_recursive(url)
+                .unwrap()
+                .map(|file| {
+                    let path = file.unwrap().location.to_file_path().unwrap();
+                    let relative = path.strip_prefix(tmp_dir.path()).unwrap();
+                    relative.to_str().unwrap().replace('\\', "/")
+                })
+                .collect()
+        };
+
+        let url = Url::from_directory_path(tmp_dir.path()).unwrap();
+        let expected = [
+            get_json_filename(0),
+            get_json_filename(1),
+            "_sidecars/a.parquet".to_string(),
+            "_sidecars/nested/b.parquet".to_string(),
+        ];
+        // directories are not listed
+        assert_eq!(list(&url), expected);
+        let url = Url::from_file_path(tmp_dir.path().join(format!("{:020}", 1))).unwrap();
+        assert_eq!(list(&url), expected[1..]);
+        Ok(())
+    }
+
     #[test]
     fn test_read_files() -> Result<(), Box<dyn std::error::Error>> {
         let client = SyncFilesystemClient;
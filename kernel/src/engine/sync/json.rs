@@ -1,22 +1,15 @@
-use std::{
-    fs::File,
-    io::{BufReader, Cursor},
-    sync::Arc,
-};
+use std::{fs::File, io::BufReader, sync::Arc};
 
 use crate::{
-    schema::SchemaRef, utils::require, DeltaResult, EngineData, Error, Expression,
-    FileDataReadResultIterator, FileMeta, JsonHandler,
+    schema::SchemaRef, DeltaResult, EngineData, Error, Expression, FileDataReadResultIterator,
+    FileMeta, JsonHandler,
 };
-use arrow_array::{cast::AsArray, RecordBatch};
-use arrow_json::ReaderBuilder;
-use arrow_schema::{Schema as ArrowSchema, SchemaRef as ArrowSchemaRef};
-use arrow_select::concat::concat_batches;
-use itertools::Itertools;
+use arrow_schema::Schema as ArrowSchema;
 use tracing::debug;
 use url::Url;
 
 use crate::engine::arrow_data::ArrowEngineData;
+use crate::engine::arrow_utils::parse_json;
 
 pub(crate) struct SyncJsonHandler;
 
@@ -62,37 +55,17 @@ impl JsonHandler for SyncJsonHandler {
         json_strings: Box<dyn EngineData>,
         output_schema: SchemaRef,
     ) -> DeltaResult<Box<dyn EngineData>> {
-        // TODO: This is taken from the default engine as it's the same. We should share an
-        // implementation at some point
-        let json_strings: RecordBatch = ArrowEngineData::try_from_engine_data(json_strings)?.into();
-        require!(
-            json_strings.num_columns() == 1,
-            Error::missing_column("Expected single column")
-        );
-        let json_strings =
-            json_strings
-                .column(0)
-                .as_string_opt::<i32>()
-                .ok_or(Error::unexpected_column_type(
-                    "Expected column to be String",
-                ))?;
-
-        let data: Vec<_> = json_strings
-            .into_iter()
-            .filter_map(|d| {
-                d.map(|dd| {
-                    let mut data = dd.as_bytes().to_vec();
-                    data.extend("\n".as_bytes());
-                    data
-                })
-            })
-            .flatten()
-            .collect();
+        parse_json(json_strings, output_schema)
+    }
 
-        let schema: ArrowSchemaRef = Arc::new(output_schema.as_ref().try_into()?);
-        let batches: Vec<_> = ReaderBuilder::new(schema.clone())
-            .build(Cursor::new(data))?
-            .try_collect()?;
-        Ok(Box::new(ArrowEngineData::new(concat_batches(&schema, &batches)?)) as _)
+    #[cfg(feature = "default-engine")]
+    fn parse_json_stream<'a>(
+        &'a self,
+        json: futures::stream::BoxStream<'a, DeltaResult<bytes::Bytes>>,
+        output_schema: SchemaRef,
+        batch_size: usize,
+    ) -> DeltaResult<futures::stream::BoxStream<'a, DeltaResult<arrow_array::RecordBatch>>> {
+        let schema: arrow_schema::SchemaRef = Arc::new(output_schema.as_ref().try_into()?);
+        crate::engine::arrow_utils::parse_json_stream(json, schema, batch_size)
     }
 }
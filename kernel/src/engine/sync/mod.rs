@@ -5,9 +5,11 @@ use crate::{Engine, ExpressionHandler, FileSystemClient, JsonHandler, ParquetHan
 
 use std::sync::Arc;
 
+#[cfg(any(feature = "test-utils", feature = "tar-bundle"))]
+pub(crate) mod client_handlers;
 mod fs_client;
 pub(crate) mod json;
-mod parquet;
+pub(crate) mod parquet;
 
 /// This is a simple implemention of [`Engine`]. It only supports reading data from the local
 /// filesystem, and internally represents data using `Arrow`.
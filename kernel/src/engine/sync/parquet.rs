@@ -1,37 +1,94 @@
 use std::fs::File;
 
-use parquet::arrow::arrow_reader::{ArrowReaderMetadata, ParquetRecordBatchReaderBuilder};
+use parquet::arrow::arrow_reader::{
+    ArrowReaderMetadata, ParquetRecordBatchReader, ParquetRecordBatchReaderBuilder,
+};
+use parquet::file::reader::ChunkReader;
 use tracing::debug;
 use url::Url;
 
 use crate::engine::arrow_data::ArrowEngineData;
-use crate::engine::arrow_utils::{generate_mask, get_requested_indices, reorder_record_batch};
+use crate::engine::arrow_utils::{
+    ensure_row_groups_exist, ensure_supported_codecs, generate_mask, get_requested_indices,
+    reorder_record_batch, row_group_layout, stats_from_footers,
+};
 use crate::schema::SchemaRef;
-use crate::{DeltaResult, Error, Expression, FileDataReadResultIterator, FileMeta, ParquetHandler};
+use crate::{
+    DeltaResult, EngineData, Error, Expression, FileDataReadResultIterator, FileMeta,
+    ParquetHandler, RowGroupMeta,
+};
 
+/// A [`ParquetHandler`] that reads local files synchronously. It supports files compressed with
+/// any codec of the parquet format except `LZO`, i.e. `SNAPPY`, `GZIP`, `BROTLI`, `LZ4`, `LZ4_RAW`
+/// and `ZSTD`. Reading a file with an unsupported codec fails with
+/// [`Error::UnsupportedCompressionCodec`].
 pub(crate) struct SyncParquetHandler;
 
-fn try_create_from_parquet(schema: SchemaRef, location: Url) -> DeltaResult<ArrowEngineData> {
+/// Open the local parquet file at `location` and read its footer
+fn open_parquet(location: &Url) -> DeltaResult<(File, ArrowReaderMetadata)> {
     let file = File::open(
         location
             .to_file_path()
             .map_err(|_| Error::generic("can only read local files"))?,
     )?;
-    let metadata = ArrowReaderMetadata::load(&file, Default::default())?;
-    let parquet_schema = metadata.schema();
-    let mut builder = ParquetRecordBatchReaderBuilder::try_new(file)?;
-    let (indicies, requested_ordering) = get_requested_indices(&schema, parquet_schema)?;
-    if let Some(mask) = generate_mask(&schema, parquet_schema, builder.parquet_schema(), &indicies)
-    {
+    let metadata = read_parquet_metadata(&file, location)?;
+    Ok((file, metadata))
+}
+
+/// Read the footer of the parquet file `reader`, which is at `location`
+pub(crate) fn read_parquet_metadata(
+    reader: &impl ChunkReader,
+    location: &Url,
+) -> DeltaResult<ArrowReaderMetadata> {
+    ArrowReaderMetadata::load(reader, Default::default())
+        .map_err(|err| Error::invalid_parquet_file(location, err))
+}
+
+/// Create a reader of the columns of `schema` of the local parquet file at `location`, reading
+/// only the `row_groups` if given
+fn try_create_parquet_reader(
+    schema: &SchemaRef,
+    location: &Url,
+    row_groups: Option<Vec<usize>>,
+) -> DeltaResult<ParquetRecordBatchReader> {
+    let (file, metadata) = open_parquet(location)?;
+    try_create_reader(file, metadata, schema, location, row_groups)
+}
+
+/// Create a reader of the columns of `schema` of the parquet file `reader` at `location`, whose
+/// footer is `metadata`, reading only the `row_groups` if given
+pub(crate) fn try_create_reader<R: ChunkReader + 'static>(
+    reader: R,
+    metadata: ArrowReaderMetadata,
+    schema: &SchemaRef,
+    location: &Url,
+    row_groups: Option<Vec<usize>>,
+) -> DeltaResult<ParquetRecordBatchReader> {
+    ensure_supported_codecs(metadata.metadata(), location)?;
+    if let Some(ref row_groups) = row_groups {
+        ensure_row_groups_exist(metadata.metadata(), row_groups, location)?;
+    }
+    let parquet_schema = metadata.schema().clone();
+    let mut builder = ParquetRecordBatchReaderBuilder::new_with_metadata(reader, metadata);
+    let indicies = get_requested_indices(schema, &parquet_schema, location, false)?;
+    if let Some(mask) = generate_mask(&parquet_schema, builder.parquet_schema(), &indicies) {
         builder = builder.with_projection(mask);
     }
-    let mut reader = builder.build()?;
+    if let Some(row_groups) = row_groups {
+        builder = builder.with_row_groups(row_groups);
+    }
+    Ok(builder.build()?)
+}
+
+// NOTE: the file is read as parquet regardless of its extension, since the log is the source of
+// truth for which files are data files
+fn try_create_from_parquet(schema: SchemaRef, location: Url) -> DeltaResult<ArrowEngineData> {
+    let mut reader = try_create_parquet_reader(&schema, &location, None)?;
     let data = reader
         .next()
         .ok_or_else(|| Error::generic("No data found reading parquet file"))?;
     Ok(ArrowEngineData::new(reorder_record_batch(
-        data?,
-        &requested_ordering,
+        data?, &schema, false,
     )?))
 }
 
@@ -51,4 +108,335 @@ impl ParquetHandler for SyncParquetHandler {
             try_create_from_parquet(schema.clone(), location).map(|d| Box::new(d) as _)
         })))
     }
+
+    fn read_footer_stats(
+        &self,
+        files: &[FileMeta],
+        stats_schema: SchemaRef,
+    ) -> DeltaResult<Option<Box<dyn EngineData>>> {
+        debug!("Reading parquet footer stats: {files:#?}");
+        let footers: Vec<_> = files
+            .iter()
+            .map(|file| Ok(open_parquet(&file.location)?.1.metadata().clone()))
+            .collect::<DeltaResult<_>>()?;
+        let stats = stats_from_footers(&footers, &stats_schema)?;
+        Ok(Some(Box::new(ArrowEngineData::new(stats))))
+    }
+
+    fn parquet_row_group_layout(&self, file: &FileMeta) -> DeltaResult<Vec<RowGroupMeta>> {
+        let (_, metadata) = open_parquet(&file.location)?;
+        Ok(row_group_layout(metadata.metadata()))
+    }
+
+    fn read_parquet_row_groups(
+        &self,
+        file: &FileMeta,
+        row_group_indices: &[usize],
+        physical_schema: SchemaRef,
+    ) -> DeltaResult<FileDataReadResultIterator> {
+        debug!("Reading row groups {row_group_indices:?} of parquet file {file:#?}");
+        let reader = try_create_parquet_reader(
+            &physical_schema,
+            &file.location,
+            Some(row_group_indices.to_vec()),
+        )?;
+        Ok(Box::new(reader.map(move |batch| {
+            let batch = reorder_record_batch(batch?, &physical_schema, false)?;
+            Ok(Box::new(ArrowEngineData::new(batch)) as _)
+        })))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::schema::{DataType, StructField, StructType};
+
+    fn read_value_column(location: Url) -> DeltaResult<ArrowEngineData> {
+        let schema = Arc::new(StructType::new(vec![StructField::new(
+            "value",
+            DataType::INTEGER,
+            true,
+        )]));
+        try_create_from_parquet(schema, location)
+    }
+
+    #[test]
+    fn test_read_parquet_with_unexpected_extension() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("part-00000.parquet.gz");
+        std::fs::copy(
+            PathBuf::from("./tests/data/table-with-dv-small/part-00000-fae5310a-a37d-4e51-827b-c3d5516560ca-c000.snappy.parquet"),
+            &path,
+        )
+        .unwrap();
+        let data = read_value_column(Url::from_file_path(path).unwrap()).unwrap();
+        assert_eq!(data.record_batch().num_rows(), 10);
+    }
+
+    #[test]
+    fn test_read_parquet_row_groups() {
+        use arrow_array::cast::AsArray;
+        use arrow_array::types::Int32Type;
+        use arrow_array::{Int32Array, RecordBatch};
+        use parquet::file::properties::WriterProperties;
+
+        use crate::test_utils::parquet_bytes;
+
+        // the values 0..25 in row groups of 10, 10 and 5 rows
+        let batch = RecordBatch::try_from_iter(vec![(
+            "value",
+            Arc::new(Int32Array::from_iter_values(0..25)) as _,
+        )])
+        .unwrap();
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("part-00000.parquet");
+        let props = WriterProperties::builder()
+            .set_max_row_group_size(10)
+            .build();
+        std::fs::write(&path, parquet_bytes(&batch, Some(props))).unwrap();
+        let file = FileMeta {
+            location: Url::from_file_path(&path).unwrap(),
+            last_modified: 0,
+            size: std::fs::metadata(&path).unwrap().len() as usize,
+            e_tag: None,
+            version: None,
+        };
+
+        let layout = SyncParquetHandler.parquet_row_group_layout(&file).unwrap();
+        let num_rows: Vec<_> = layout.iter().map(|row_group| row_group.num_rows).collect();
+        assert_eq!(num_rows, [10, 10, 5]);
+
+        let schema = Arc::new(StructType::new(vec![StructField::new(
+            "value",
+            DataType::INTEGER,
+            true,
+        )]));
+        let values: Vec<i32> = SyncParquetHandler
+            .read_parquet_row_groups(&file, &[2, 1], schema.clone())
+            .unwrap()
+            .flat_map(|data| {
+                let data = ArrowEngineData::try_from_engine_data(data.unwrap()).unwrap();
+                let values = data.record_batch().column(0).as_primitive::<Int32Type>();
+                values.values().to_vec()
+            })
+            .collect();
+        assert_eq!(values, (20..25).chain(10..20).collect::<Vec<_>>());
+        assert!(SyncParquetHandler
+            .read_parquet_row_groups(&file, &[3], schema)
+            .is_err());
+    }
+
+    #[test]
+    fn test_read_missing_columns() {
+        use arrow_array::cast::AsArray;
+        use arrow_array::{ArrayRef, RecordBatch, StructArray};
+        use arrow_schema::{DataType as ArrowDataType, Field as ArrowField};
+
+        let location = Url::from_file_path(
+            std::fs::canonicalize(PathBuf::from("./tests/data/table-with-dv-small/part-00000-fae5310a-a37d-4e51-827b-c3d5516560ca-c000.snappy.parquet"))
+                .unwrap(),
+        )
+        .unwrap();
+        let schema = Arc::new(StructType::new(vec![
+            StructField::new("missing", DataType::STRING, true),
+            StructField::new("value", DataType::INTEGER, true),
+        ]));
+        let data = try_create_from_parquet(schema, location.clone()).unwrap();
+        let batch = data.record_batch();
+        assert_eq!(batch.num_rows(), 10);
+        assert_eq!(batch.schema().field(0).name(), "missing");
+        assert_eq!(batch.column(0).null_count(), 10);
+        assert_eq!(batch.schema().field(1).name(), "value");
+        assert_eq!(batch.column(1).null_count(), 0);
+
+        // a missing column can't be filled with nulls if it isn't nullable
+        let schema = Arc::new(StructType::new(vec![StructField::new(
+            "missing",
+            DataType::STRING,
+            false,
+        )]));
+        assert!(try_create_from_parquet(schema.clone(), location.clone()).is_err());
+        let batch = batch.project(&[1]).unwrap();
+        assert!(reorder_record_batch(batch.clone(), &schema, false).is_err());
+
+        // and neither can a missing field of a struct column
+        let values = batch.column(0).clone();
+        let fields = vec![ArrowField::new("value", ArrowDataType::Int32, true)];
+        let column = StructArray::try_new(fields.into(), vec![values], None).unwrap();
+        let batch = RecordBatch::try_from_iter([("s", Arc::new(column) as ArrayRef)]).unwrap();
+        let struct_schema = |nullable| {
+            Arc::new(StructType::new(vec![StructField::new(
+                "s",
+                StructType::new(vec![
+                    StructField::new("value", DataType::INTEGER, true),
+                    StructField::new("missing", DataType::STRING, nullable),
+                ]),
+                true,
+            )]))
+        };
+        let filled = reorder_record_batch(batch.clone(), &struct_schema(true), false).unwrap();
+        assert_eq!(filled.column(0).as_struct().column(1).null_count(), 10);
+        assert!(reorder_record_batch(batch.clone(), &struct_schema(false), false).is_err());
+
+        // which is checked up front, like the columns
+        let parquet_schema = batch.schema();
+        let indices =
+            get_requested_indices(&struct_schema(true), &parquet_schema, &location, false);
+        assert_eq!(indices.unwrap(), [0]);
+        let res = get_requested_indices(&struct_schema(false), &parquet_schema, &location, false);
+        assert!(
+            matches!(res, Err(Error::SchemaMismatch { ref column, ref actual, .. })
+                if column == "s.missing" && actual == "fields \"value\""),
+            "Expected a schema mismatch error, got {res:?}"
+        );
+    }
+
+    #[test]
+    fn test_read_invalid_parquet() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("part-00000.snappy.parquet");
+        std::fs::write(&path, "this is not parquet data").unwrap();
+        let location = Url::from_file_path(path).unwrap();
+        match read_value_column(location.clone()) {
+            Err(Error::InvalidParquetFile { location: loc, .. }) => {
+                assert_eq!(loc, location.to_string())
+            }
+            Err(err) => panic!("Expected an invalid parquet file error, got {err}"),
+            Ok(_) => panic!("Expected reading invalid parquet to fail"),
+        }
+    }
+
+    #[test]
+    fn test_read_compression_codecs() {
+        use arrow_array::{Int32Array, RecordBatch};
+        use parquet::basic::{Compression, ZstdLevel};
+        use parquet::file::properties::WriterProperties;
+
+        use crate::test_utils::parquet_bytes;
+
+        let tmp = tempfile::tempdir().unwrap();
+        let batch =
+            RecordBatch::try_from_iter([("value", Arc::new(Int32Array::from(vec![1, 2, 3])) as _)])
+                .unwrap();
+        for codec in [
+            Compression::LZ4_RAW,
+            Compression::ZSTD(ZstdLevel::default()),
+            Compression::GZIP(Default::default()),
+            Compression::BROTLI(Default::default()),
+        ] {
+            let path = tmp.path().join(format!("{codec}.parquet"));
+            let props = WriterProperties::builder().set_compression(codec).build();
+            std::fs::write(&path, parquet_bytes(&batch, Some(props))).unwrap();
+
+            let data = read_value_column(Url::from_file_path(path).unwrap()).unwrap();
+            assert_eq!(data.record_batch(), &batch, "reading {codec}");
+        }
+    }
+
+    #[test]
+    fn test_read_timestamps_as_micros() {
+        use arrow_array::cast::AsArray;
+        use arrow_array::types::TimestampMicrosecondType;
+        use arrow_array::{
+            ArrayRef, RecordBatch, StructArray, TimestampMillisecondArray, TimestampNanosecondArray,
+        };
+        use arrow_schema::{DataType as ArrowDataType, Field, TimeUnit};
+
+        use crate::test_utils::parquet_bytes;
+
+        let tmp = tempfile::tempdir().unwrap();
+        let nanos: ArrayRef = Arc::new(
+            TimestampNanosecondArray::from(vec![1_000_000_000, 2_001_000_000]).with_timezone("UTC"),
+        );
+        let millis: ArrayRef =
+            Arc::new(TimestampMillisecondArray::from(vec![1_000, 2_001]).with_timezone("UTC"));
+        let nested: ArrayRef = Arc::new(StructArray::from(vec![(
+            Arc::new(Field::new("ts", nanos.data_type().clone(), true)),
+            nanos.clone(),
+        )]));
+        let batch =
+            RecordBatch::try_from_iter([("nanos", nanos), ("millis", millis), ("nested", nested)])
+                .unwrap();
+        let path = tmp.path().join("timestamps.parquet");
+        std::fs::write(&path, parquet_bytes(&batch, None)).unwrap();
+
+        let schema = Arc::new(StructType::new(vec![
+            StructField::new("nanos", DataType::TIMESTAMP, true),
+            StructField::new("millis", DataType::TIMESTAMP, true),
+            StructField::new(
+                "nested",
+                StructType::new(vec![StructField::new("ts", DataType::TIMESTAMP, true)]),
+                true,
+            ),
+        ]));
+        let data = try_create_from_parquet(schema, Url::from_file_path(path).unwrap()).unwrap();
+        let batch = data.record_batch();
+        let micros = ArrowDataType::Timestamp(TimeUnit::Microsecond, Some("UTC".into()));
+        let nested = batch.column(2).as_struct().column(0);
+        for column in [batch.column(0), batch.column(1), nested] {
+            assert_eq!(column.data_type(), &micros);
+            let values = column.as_primitive::<TimestampMicrosecondType>().values();
+            assert_eq!(values.to_vec(), vec![1_000_000, 2_001_000]);
+        }
+    }
+
+    #[test]
+    fn test_unsupported_compression_codec() {
+        use parquet::basic::{Compression, Type};
+        use parquet::file::metadata::{
+            ColumnChunkMetaData, FileMetaData, ParquetMetaData, RowGroupMetaData,
+        };
+        use parquet::schema::types::{SchemaDescriptor, Type as SchemaType};
+
+        let field = SchemaType::primitive_type_builder("value", Type::INT32)
+            .build()
+            .unwrap();
+        let schema = SchemaType::group_type_builder("schema")
+            .with_fields(vec![Arc::new(field)])
+            .build()
+            .unwrap();
+        let schema = Arc::new(SchemaDescriptor::new(Arc::new(schema)));
+        let footer = |codec| {
+            let column = ColumnChunkMetaData::builder(schema.column(0))
+                .set_compression(codec)
+                .build()
+                .unwrap();
+            let row_group = RowGroupMetaData::builder(schema.clone())
+                .set_column_metadata(vec![column])
+                .build()
+                .unwrap();
+            let file_metadata = FileMetaData::new(1, 0, None, None, schema.clone(), None);
+            ParquetMetaData::new(file_metadata, vec![row_group])
+        };
+
+        let location = Url::parse("file:///data/part-00000.parquet").unwrap();
+        // the codecs the kernel enables in the parquet crate, at any level
+        let supported = [
+            Compression::UNCOMPRESSED,
+            Compression::SNAPPY,
+            Compression::GZIP(Default::default()),
+            Compression::BROTLI(parquet::basic::BrotliLevel::try_new(1).unwrap()),
+            Compression::LZ4,
+            Compression::ZSTD(Default::default()),
+            Compression::LZ4_RAW,
+        ];
+        for codec in supported {
+            let result = ensure_supported_codecs(&footer(codec), &location);
+            assert!(result.is_ok(), "{codec}: {result:?}");
+        }
+        match ensure_supported_codecs(&footer(Compression::LZO), &location) {
+            Err(Error::UnsupportedCompressionCodec {
+                location: loc,
+                codec,
+            }) => {
+                assert_eq!(loc, location.to_string());
+                assert_eq!(codec, "LZO");
+            }
+            result => panic!("Expected an unsupported codec error, got {result:?}"),
+        }
+    }
 }
@@ -0,0 +1,387 @@
+//! A [`FileSystemClient`] serving a whole (small) table from a tarball that is fetched in a single
+//! request and kept in memory, and an [`Engine`] reading the table from it. This is enabled by the
+//! `tar-bundle` feature flag.
+//!
+//! This is meant for environments where requests are expensive compared to bandwidth, like
+//! serverless functions reading small tables. Larger tables should be read from their object store
+//! directly, so the size of a bundle is bounded.
+use std::collections::BTreeMap;
+use std::io::Read;
+use std::path::Component;
+use std::sync::Arc;
+
+use bytes::Bytes;
+use flate2::read::GzDecoder;
+use url::Url;
+
+use super::arrow_expression::ArrowExpressionHandler;
+use super::sync::client_handlers::{ClientJsonHandler, ClientParquetHandler};
+use crate::{
+    DeltaResult, Engine, Error, ExpressionHandler, FileMeta, FileSlice, FileSystemClient,
+    JsonHandler, ParquetHandler,
+};
+
+/// The magic bytes at the start of a gzip stream
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+/// A file of a bundle
+struct BundleFile {
+    data: Bytes,
+    /// The modification time of the file in milliseconds since the epoch
+    last_modified: i64,
+}
+
+/// A read-only [`FileSystemClient`] serving the files of a tarball (optionally gzip compressed),
+/// which is fetched once when the client is created. Every file of the tarball is served at its
+/// path in the tarball relative to a table root, i.e. an entry `_delta_log/00000000000000000000.json`
+/// is served at `<table_root>/_delta_log/00000000000000000000.json`.
+///
+/// Entries outside of the table root (like `../other/file`, or absolute paths) are rejected.
+///
+/// To read the table with the client, use a [`TarBundleEngine`], whose handlers read their files
+/// from the bundle too.
+pub struct TarBundleFileSystemClient {
+    /// The files of the bundle by the (string) url they are served at. This is ordered, so that
+    /// `list_from` can return files in lexicographic order.
+    files: BTreeMap<String, BundleFile>,
+}
+
+impl TarBundleFileSystemClient {
+    /// Fetch the tarball at `bundle` with `fetcher` (e.g. the [`FileSystemClient`] of the default
+    /// engine, which can read `https` urls), and serve its files under `table_root`.
+    ///
+    /// Fails if the bundle, or the total size of its (decompressed) contents, is larger than
+    /// `max_size` bytes, so that a misconfigured bundle url can't use unbounded memory.
+    pub fn try_new(
+        fetcher: &dyn FileSystemClient,
+        bundle: &Url,
+        table_root: &Url,
+        max_size: usize,
+    ) -> DeltaResult<Self> {
+        let data = fetcher
+            .read_files(vec![(bundle.clone(), None)])?
+            .next()
+            .unwrap_or_else(|| Err(Error::file_not_found(bundle)))?;
+        Self::try_from_bytes(data, table_root, max_size)
+    }
+
+    /// Serve the files of the already fetched tarball `data` under `table_root`. See
+    /// [`Self::try_new`].
+    pub fn try_from_bytes(data: Bytes, table_root: &Url, max_size: usize) -> DeltaResult<Self> {
+        let too_large = || {
+            Error::generic(format!(
+                "Bundle for table {table_root} is larger than the maximum of {max_size} bytes"
+            ))
+        };
+        if data.len() > max_size {
+            return Err(too_large());
+        }
+        let reader: Box<dyn Read> = match data.starts_with(&GZIP_MAGIC) {
+            true => Box::new(GzDecoder::new(data.as_ref())),
+            false => Box::new(data.as_ref()),
+        };
+
+        let mut table_root = table_root.clone();
+        if !table_root.path().ends_with('/') {
+            table_root.set_path(&format!("{}/", table_root.path()));
+        }
+        let mut files = BTreeMap::new();
+        let mut size = 0;
+        let mut archive = tar::Archive::new(reader);
+        for entry in archive.entries()? {
+            let mut entry = entry?;
+            // count every entry, so that the whole archive is bounded, not just its files
+            size += entry.size() as usize;
+            if size > max_size {
+                return Err(too_large());
+            }
+            if !entry.header().entry_type().is_file() {
+                continue;
+            }
+            let path = entry.path()?;
+            let invalid_path = || Error::generic(format!("Invalid path in bundle: {path:?}"));
+            // a path must be relative to the table root and stay inside of it
+            let escapes_root = path.components().any(|component| {
+                matches!(
+                    component,
+                    Component::ParentDir | Component::RootDir | Component::Prefix(_)
+                )
+            });
+            if escapes_root {
+                return Err(invalid_path());
+            }
+            let relative_path = path.to_str().ok_or_else(invalid_path)?;
+            let location = table_root.join(relative_path.trim_start_matches("./"))?;
+            if !location.as_str().starts_with(table_root.as_str()) {
+                return Err(invalid_path());
+            }
+            let last_modified = entry.header().mtime()? as i64 * 1000;
+            let mut data = Vec::with_capacity(entry.size() as usize);
+            entry.read_to_end(&mut data)?;
+            let file = BundleFile {
+                data: data.into(),
+                last_modified,
+            };
+            files.insert(location.to_string(), file);
+        }
+        Ok(Self { files })
+    }
+}
+
+impl TarBundleFileSystemClient {
+    /// List the files from `path`, only those directly in its directory unless `recursive`
+    fn list(&self, path: &Url, recursive: bool) -> DeltaResult<Vec<DeltaResult<FileMeta>>> {
+        let path = path.as_str();
+        // like a file system, a path is either a directory, listed in full, or a file name in a
+        // directory, listed from that name on
+        let is_dir = path.ends_with('/')
+            || self
+                .files
+                .range(format!("{path}/")..)
+                .next()
+                .is_some_and(|(location, _)| location.starts_with(&format!("{path}/")));
+        let (dir, from) = match is_dir {
+            true => {
+                let dir = format!("{}/", path.trim_end_matches('/'));
+                (dir.clone(), dir)
+            }
+            false => match path.rfind('/') {
+                Some(index) => (path[..=index].to_string(), path.to_string()),
+                None => {
+                    return Err(Error::generic(format!(
+                        "Invalid path for list_from: {path}"
+                    )))
+                }
+            },
+        };
+        let files: Vec<_> = self
+            .files
+            .range(from..)
+            .take_while(|(location, _)| location.starts_with(&dir))
+            // only the files directly in the directory, unless listing recursively
+            .filter(|(location, _)| recursive || !location[dir.len()..].contains('/'))
+            .map(|(location, file)| {
+                Ok(FileMeta {
+                    location: Url::parse(location)?,
+                    last_modified: file.last_modified,
+                    size: file.data.len(),
+                    e_tag: None,
+                    version: None,
+                })
+            })
+            .collect();
+        Ok(files)
+    }
+}
+
+impl FileSystemClient for TarBundleFileSystemClient {
+    fn list_from(
+        &self,
+        path: &Url,
+    ) -> DeltaResult<Box<dyn Iterator<Item = DeltaResult<FileMeta>>>> {
+        Ok(Box::new(self.list(path, false)?.into_iter()))
+    }
+
+    /// The bundle only has entries for files, so no directories are listed.
+    fn list_from_recursive(
+        &self,
+        path: &Url,
+    ) -> DeltaResult<Box<dyn Iterator<Item = DeltaResult<FileMeta>>>> {
+        Ok(Box::new(self.list(path, true)?.into_iter()))
+    }
+
+    fn read_files(
+        &self,
+        files: Vec<FileSlice>,
+    ) -> DeltaResult<Box<dyn Iterator<Item = DeltaResult<Bytes>>>> {
+        let reads: Vec<_> = files
+            .into_iter()
+            .map(|(location, range)| {
+                let file = self
+                    .files
+                    .get(location.as_str())
+                    .ok_or_else(|| Error::file_not_found(&location))?;
+                match range {
+                    Some(range) if range.end > file.data.len() => Err(Error::generic(format!(
+                        "Range {range:?} is out of bounds for {location} of size {}",
+                        file.data.len()
+                    ))),
+                    Some(range) => Ok(file.data.slice(range)),
+                    None => Ok(file.data.clone()),
+                }
+            })
+            .collect();
+        Ok(Box::new(reads.into_iter()))
+    }
+}
+
+/// An [`Engine`] reading a table from a [`TarBundleFileSystemClient`]: the log and data files are
+/// all read from the bundle, and parsed like the [`crate::engine::sync::SyncEngine`] parses local
+/// files.
+pub struct TarBundleEngine {
+    client: Arc<TarBundleFileSystemClient>,
+}
+
+impl TarBundleEngine {
+    /// Create an engine reading the files of the bundle served by `client`
+    pub fn new(client: TarBundleFileSystemClient) -> Self {
+        Self {
+            client: Arc::new(client),
+        }
+    }
+}
+
+impl Engine for TarBundleEngine {
+    fn get_expression_handler(&self) -> Arc<dyn ExpressionHandler> {
+        Arc::new(ArrowExpressionHandler {})
+    }
+
+    fn get_file_system_client(&self) -> Arc<dyn FileSystemClient> {
+        self.client.clone()
+    }
+
+    fn get_json_handler(&self) -> Arc<dyn JsonHandler> {
+        Arc::new(ClientJsonHandler(self.client.clone()))
+    }
+
+    fn get_parquet_handler(&self) -> Arc<dyn ParquetHandler> {
+        Arc::new(ClientParquetHandler(self.client.clone()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use flate2::write::GzEncoder;
+    use itertools::Itertools;
+
+    use super::*;
+
+    /// Create a gzipped tarball of `files`, all modified at 1s since the epoch
+    fn bundle(files: &[(&str, &str)]) -> Bytes {
+        let mut builder = tar::Builder::new(GzEncoder::new(vec![], Default::default()));
+        for (path, data) in files {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(data.len() as u64);
+            header.set_mtime(1);
+            header.set_mode(0o644);
+            builder
+                .append_data(&mut header, path, data.as_bytes())
+                .unwrap();
+        }
+        let mut encoder = builder.into_inner().unwrap();
+        encoder.flush().unwrap();
+        encoder.finish().unwrap().into()
+    }
+
+    fn list(client: &TarBundleFileSystemClient, path: &Url) -> Vec<String> {
+        client
+            .list_from(path)
+            .unwrap()
+            .map(|file| file.unwrap().location.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_tar_bundle_file_system_client() {
+        let data = bundle(&[
+            ("_delta_log/00000000000000000000.json", "zero"),
+            ("_delta_log/00000000000000000001.json", "one"),
+            ("./part-00000.parquet", "data"),
+        ]);
+        let root = Url::parse("https://example.com/table").unwrap();
+        let client =
+            TarBundleFileSystemClient::try_from_bytes(data.clone(), &root, 10_000).unwrap();
+
+        let log_root = root.join("table/_delta_log/").unwrap();
+        let listed = list(
+            &client,
+            &log_root.join("00000000000000000001.json").unwrap(),
+        );
+        assert_eq!(
+            listed,
+            ["https://example.com/table/_delta_log/00000000000000000001.json"]
+        );
+        assert_eq!(list(&client, &log_root).len(), 2);
+        // the directory itself, without a trailing slash
+        let listed = list(&client, &Url::parse("https://example.com/table").unwrap());
+        assert_eq!(listed, ["https://example.com/table/part-00000.parquet"]);
+        let listed = client
+            .list_from_recursive(&root.join("table/").unwrap())
+            .unwrap();
+        assert_eq!(listed.count(), 3);
+        let file = client
+            .list_from(&log_root)
+            .unwrap()
+            .next()
+            .unwrap()
+            .unwrap();
+        assert_eq!((file.size, file.last_modified), (4, 1000));
+
+        let files = vec![
+            (log_root.join("00000000000000000000.json").unwrap(), None),
+            (root.join("table/part-00000.parquet").unwrap(), Some(1..3)),
+        ];
+        let reads: Vec<_> = client.read_files(files).unwrap().try_collect().unwrap();
+        assert_eq!(reads, [Bytes::from("zero"), Bytes::from("at")]);
+        let missing = vec![(root.join("table/missing").unwrap(), None)];
+        let mut reads = client.read_files(missing).unwrap();
+        assert!(matches!(reads.next(), Some(Err(Error::FileNotFound(_)))));
+
+        // the contents of the bundle are bounded as well as its compressed size
+        assert!(TarBundleFileSystemClient::try_from_bytes(data.clone(), &root, 10).is_err());
+        let large = bundle(&[("large", &"x".repeat(1000))]);
+        assert!(large.len() < 200);
+        assert!(TarBundleFileSystemClient::try_from_bytes(large, &root, 200).is_err());
+    }
+
+    #[test]
+    fn test_tar_bundle_rejects_paths_outside_the_root() {
+        let root = Url::parse("https://example.com/table/").unwrap();
+        for path in [
+            "../other/00000000000000000000.json",
+            "_delta_log/../../x",
+            "/etc/x",
+        ] {
+            // the builder refuses to write such paths, so set the name of the entry directly
+            let mut header = tar::Header::new_old();
+            header.as_old_mut().name[..path.len()].copy_from_slice(path.as_bytes());
+            header.set_size(4);
+            header.set_mode(0o644);
+            header.set_cksum();
+            let mut builder = tar::Builder::new(vec![]);
+            builder.append(&header, "data".as_bytes()).unwrap();
+            let data = builder.into_inner().unwrap().into();
+            let result = TarBundleFileSystemClient::try_from_bytes(data, &root, 10_000);
+            assert!(result.is_err(), "{path} was accepted");
+        }
+    }
+
+    #[test]
+    fn test_tar_bundle_engine() {
+        let mut builder = tar::Builder::new(vec![]);
+        builder
+            .append_dir_all(".", "./tests/data/table-with-dv-small")
+            .unwrap();
+        let data = builder.into_inner().unwrap().into();
+        let root = Url::parse("https://example.com/table/").unwrap();
+        let client = TarBundleFileSystemClient::try_from_bytes(data, &root, 100_000).unwrap();
+        let engine = TarBundleEngine::new(client);
+
+        // the log, the data file and its deletion vector are all read from the bundle
+        let snapshot = crate::Table::new(root).snapshot(&engine, None).unwrap();
+        assert_eq!(snapshot.version(), 1);
+        let scan = crate::scan::ScanBuilder::new(snapshot).build().unwrap();
+        let rows: usize = scan
+            .execute(&engine)
+            .unwrap()
+            .into_iter()
+            .map(|result| match result.mask {
+                Some(mask) => mask.into_iter().filter(|selected| *selected).count(),
+                None => result.raw_data.unwrap().length(),
+            })
+            .sum();
+        assert_eq!(rows, 8);
+    }
+}
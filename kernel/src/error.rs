@@ -6,7 +6,9 @@ use std::{
     str::Utf8Error,
 };
 
+use crate::actions::Protocol;
 use crate::schema::DataType;
+use crate::Version;
 
 /// A [`std::result::Result`] that has the kernel [`Error`] as the error variant
 pub type DeltaResult<T, E = Error> = std::result::Result<T, E>;
@@ -57,6 +59,44 @@ pub enum Error {
     #[error("Arrow error: {0}")]
     Parquet(#[from] parquet::errors::ParquetError),
 
+    /// A data file could not be read as parquet, for example because its contents aren't actually
+    /// parquet
+    #[cfg(feature = "parquet")]
+    #[error("Could not read {location} as parquet: {source}")]
+    InvalidParquetFile {
+        location: String,
+        source: parquet::errors::ParquetError,
+    },
+
+    /// A data file is compressed with a codec the parquet reader doesn't support
+    #[error("Could not read {location}: unsupported parquet compression codec {codec}")]
+    UnsupportedCompressionCodec { location: String, codec: String },
+
+    /// The table's protocol requires a reader version or reader features that the kernel doesn't
+    /// implement, see [`crate::actions::Protocol::ensure_read_supported`]. The feature lists are
+    /// all the features the protocol names, not only the unsupported ones.
+    #[error(
+        "Unsupported table protocol: reader version {min_reader_version} with features \
+         {required_reader_features:?}, writer version {min_writer_version} with features \
+         {required_writer_features:?}"
+    )]
+    UnsupportedProtocol {
+        min_reader_version: i32,
+        min_writer_version: i32,
+        required_reader_features: Vec<String>,
+        required_writer_features: Vec<String>,
+    },
+
+    /// The schema of a data file doesn't match the schema it is read with, e.g. because a column
+    /// doesn't have the (physical) name recorded in the table metadata
+    #[error("Schema mismatch in {file} for column {column}: expected {expected}, found {actual}")]
+    SchemaMismatch {
+        file: String,
+        column: String,
+        expected: String,
+        actual: String,
+    },
+
     /// An error interacting with the object_store crate
     // We don't use [#from] object_store::Error here as our From impl transforms
     // object_store::Error::NotFound into Self::FileNotFound
@@ -77,6 +117,11 @@ pub enum Error {
     #[error("File not found: {0}")]
     FileNotFound(String),
 
+    /// A file was overwritten between listing and reading it, see
+    /// [`crate::FileSystemClient::read_files_if_unchanged`]
+    #[error("File {0} changed since it was listed")]
+    StaleRead(String),
+
     /// A column was requested, but not found
     #[error("{0}")]
     MissingColumn(String),
@@ -147,6 +192,53 @@ pub enum Error {
     /// Incosistent data passed to struct scalar
     #[error("Invalid struct data: {0}")]
     InvalidStructData(String),
+
+    /// The partition values of a file don't match the partition directories of its path
+    #[error("Invalid partition path: {0}")]
+    InvalidPartitionPath(String),
+
+    /// A file is added more than once without being removed in between (see
+    /// [`crate::scan::log_replay::DuplicateAddPolicy::Strict`])
+    #[error("File {0} is added more than once without being removed in between")]
+    DuplicateAdd(String),
+
+    /// A scan prunes less of the table than it is required to (see
+    /// [`crate::scan::ScanBuilder::require_pruning`])
+    #[error("Scan prunes {pruned_fraction} of the table, less than the required {min_fraction}")]
+    InsufficientPruning {
+        pruned_fraction: f64,
+        min_fraction: f64,
+    },
+
+    /// Validating a scan (see [`crate::scan::Scan::validate`]) found one or more problems
+    #[error("Scan validation failed:\n{}", .0.join("\n"))]
+    ScanValidation(Vec<String>),
+
+    /// Time travel to a timestamp before the earliest commit a snapshot can be created for (see
+    /// [`crate::Table::snapshot_at_timestamp`])
+    #[error(
+        "Timestamp {timestamp} is before the earliest available commit, version {earliest_version} \
+         at timestamp {earliest_timestamp}"
+    )]
+    TimestampBeforeEarliestCommit {
+        timestamp: i64,
+        earliest_version: Version,
+        earliest_timestamp: i64,
+    },
+
+    /// A checkpoint can't be read, e.g. because a sidecar file has a negative size
+    #[error("Invalid checkpoint: {0}")]
+    InvalidCheckpoint(String),
+
+    /// The log can't be resolved, e.g. because the sidecar files of a checkpoint are referenced
+    /// more than once (which would read them in a loop), or there are too many of them
+    #[error("Invalid commit: {0}")]
+    InvalidCommit(String),
+
+    /// The change data feed of a range of commits was requested, but it isn't enabled for all of
+    /// them (see [`crate::table_changes::TableChanges`])
+    #[error("Change data feed is not enabled at version {version}")]
+    ChangeDataFeedNotEnabled { version: Version },
 }
 
 // Convenience constructors for Error types that take a String argument
@@ -162,6 +254,9 @@ impl Error {
     pub fn file_not_found(path: impl ToString) -> Self {
         Self::FileNotFound(path.to_string())
     }
+    pub fn stale_read(path: impl ToString) -> Self {
+        Self::StaleRead(path.to_string())
+    }
     pub fn missing_column(name: impl ToString) -> Self {
         Self::MissingColumn(name.to_string())
     }
@@ -192,6 +287,78 @@ impl Error {
     pub fn invalid_struct_data(msg: impl ToString) -> Self {
         Self::InvalidStructData(msg.to_string())
     }
+    pub fn invalid_partition_path(msg: impl ToString) -> Self {
+        Self::InvalidPartitionPath(msg.to_string())
+    }
+    pub fn invalid_checkpoint(msg: impl ToString) -> Self {
+        Self::InvalidCheckpoint(msg.to_string())
+    }
+    pub fn invalid_commit(msg: impl ToString) -> Self {
+        Self::InvalidCommit(msg.to_string())
+    }
+    pub fn duplicate_add(path: impl ToString) -> Self {
+        Self::DuplicateAdd(path.to_string())
+    }
+    pub fn insufficient_pruning(pruned_fraction: f64, min_fraction: f64) -> Self {
+        Self::InsufficientPruning {
+            pruned_fraction,
+            min_fraction,
+        }
+    }
+    pub fn timestamp_before_earliest_commit(
+        timestamp: i64,
+        earliest_version: Version,
+        earliest_timestamp: i64,
+    ) -> Self {
+        Self::TimestampBeforeEarliestCommit {
+            timestamp,
+            earliest_version,
+            earliest_timestamp,
+        }
+    }
+    pub fn change_data_feed_not_enabled(version: Version) -> Self {
+        Self::ChangeDataFeedNotEnabled { version }
+    }
+    #[cfg(feature = "parquet")]
+    pub fn invalid_parquet_file(
+        location: impl ToString,
+        source: parquet::errors::ParquetError,
+    ) -> Self {
+        Self::InvalidParquetFile {
+            location: location.to_string(),
+            source,
+        }
+    }
+
+    pub fn unsupported_compression_codec(location: impl ToString, codec: impl ToString) -> Self {
+        Self::UnsupportedCompressionCodec {
+            location: location.to_string(),
+            codec: codec.to_string(),
+        }
+    }
+
+    pub fn unsupported_protocol(protocol: &Protocol) -> Self {
+        Self::UnsupportedProtocol {
+            min_reader_version: protocol.min_reader_version,
+            min_writer_version: protocol.min_writer_version,
+            required_reader_features: protocol.reader_features.clone().unwrap_or_default(),
+            required_writer_features: protocol.writer_features.clone().unwrap_or_default(),
+        }
+    }
+
+    pub fn schema_mismatch(
+        file: impl ToString,
+        column: impl ToString,
+        expected: impl ToString,
+        actual: impl ToString,
+    ) -> Self {
+        Self::SchemaMismatch {
+            file: file.to_string(),
+            column: column.to_string(),
+            expected: expected.to_string(),
+            actual: actual.to_string(),
+        }
+    }
 
     // Capture a backtrace when the error is constructed.
     #[must_use]
@@ -236,6 +403,8 @@ impl From<object_store::Error> for Error {
     fn from(value: object_store::Error) -> Self {
         match value {
             object_store::Error::NotFound { path, .. } => Self::file_not_found(path),
+            // we only make requests conditional on the e-tag of the object
+            object_store::Error::Precondition { path, .. } => Self::stale_read(path),
             err => Self::ObjectStore(err),
         }
     }
@@ -0,0 +1,101 @@
+//! The names of the (possibly nested) columns that expressions reference, see [`ColumnName`].
+
+use std::fmt::{Display, Formatter};
+
+/// The name of a (possibly nested) column: the path of field names that leads to it from the top
+/// level of a schema, e.g. `["address", "zip"]` for the field `zip` of the struct column
+/// `address`. The names can contain any character, including `.`.
+///
+/// It is displayed with its names separated by `.`, quoting each name that isn't a plain
+/// identifier in backticks (with the backticks in it doubled), like [`super::parse_expression`]
+/// parses column references.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ColumnName {
+    path: Vec<String>,
+}
+
+impl ColumnName {
+    /// Create the name of the column at `path`
+    pub fn new(path: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        Self {
+            path: path.into_iter().map(Into::into).collect(),
+        }
+    }
+
+    /// Create the name of the column at the path `name` with the names separated by `.`, e.g.
+    /// `a.b` for the field `b` of the struct column `a`. Use [`Self::new`] for names that contain
+    /// a `.`.
+    pub fn from_dotted(name: &str) -> Self {
+        Self::new(name.split('.'))
+    }
+
+    /// The path of field names leading to the column
+    pub fn path(&self) -> &[String] {
+        &self.path
+    }
+
+    /// The name of the column if it is a top-level one, i.e. not nested in a struct
+    pub fn top_level(&self) -> Option<&str> {
+        match self.path.as_slice() {
+            [name] => Some(name),
+            _ => None,
+        }
+    }
+
+    /// The name of this column nested in the struct column `parent`
+    pub fn nested_in(&self, parent: impl Into<String>) -> Self {
+        let path = std::iter::once(parent.into()).chain(self.path.iter().cloned());
+        Self::new(path)
+    }
+}
+
+/// Whether `name` can be written without backticks, i.e. is an identifier that isn't a keyword
+fn is_plain_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    let is_identifier = chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_');
+    let is_keyword = ["TRUE", "FALSE", "NULL"]
+        .iter()
+        .any(|keyword| name.eq_ignore_ascii_case(keyword));
+    is_identifier && !is_keyword
+}
+
+impl Display for ColumnName {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for (index, name) in self.path.iter().enumerate() {
+            if index > 0 {
+                write!(f, ".")?;
+            }
+            match is_plain_identifier(name) {
+                true => write!(f, "{name}")?,
+                false => write!(f, "`{}`", name.replace('`', "``"))?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::expressions::{parse_expression, Expression};
+
+    #[test]
+    fn test_column_name_display() {
+        let cases = [
+            (ColumnName::from_dotted("a"), "a"),
+            (ColumnName::from_dotted("address.zip"), "address.zip"),
+            (ColumnName::new(["my col", "x`y"]), "`my col`.`x``y`"),
+            (ColumnName::new(["a.b", "c"]), "`a.b`.c"),
+            (ColumnName::new(["null"]), "`null`"),
+        ];
+        for (name, expected) in cases {
+            assert_eq!(name.to_string(), expected);
+            // which parses back to the same name
+            let parsed = parse_expression(expected).unwrap();
+            assert_eq!(parsed, Expression::Column(name));
+        }
+    }
+}
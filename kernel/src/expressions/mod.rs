@@ -5,9 +5,15 @@ use std::fmt::{Display, Formatter};
 
 use itertools::Itertools;
 
+pub use self::column_name::ColumnName;
+pub use self::parse::parse_expression;
 pub use self::scalars::{Scalar, StructData};
+pub use self::simplify::simplify;
 
+mod column_name;
+mod parse;
 mod scalars;
+mod simplify;
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 /// A binary operator.
@@ -34,6 +40,10 @@ pub enum BinaryOperator {
     NotEqual,
     /// Distinct
     Distinct,
+    /// Null if the left-hand side equals the right-hand side, else the left-hand side
+    NullIf,
+    /// The left-hand side, or the right-hand side if the left-hand side is null
+    IfNull,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -59,6 +69,9 @@ impl Display for BinaryOperator {
             // so ideally this would not be used as we use Display for rendering expressions
             // in our code we take care of this, bot thers might now ...
             Self::Distinct => write!(f, "DISTINCT"),
+            // like DISTINCT, these are rendered as functions by `Expression`
+            Self::NullIf => write!(f, "NULLIF"),
+            Self::IfNull => write!(f, "IFNULL"),
         }
     }
 }
@@ -72,17 +85,83 @@ pub enum UnaryOperator {
     IsNull,
 }
 
+/// An interval of time that can be added to a date or timestamp with [`Expression::add_interval`].
+///
+/// Months are added as calendar months, keeping the day of the month where possible and clamping
+/// it to the last day of shorter months (e.g. January 31st plus one month is the last day of
+/// February). Days are added as calendar days, and microseconds as exact durations. Negative values
+/// subtract the interval. Only intervals of whole days (i.e. without microseconds) can be added to
+/// dates.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Interval {
+    /// The number of months of the interval
+    pub months: i32,
+    /// The number of days of the interval
+    pub days: i32,
+    /// The number of microseconds of the interval
+    pub microseconds: i64,
+}
+
+impl Interval {
+    /// Create an interval of `months`, `days` and `microseconds`
+    pub fn new(months: i32, days: i32, microseconds: i64) -> Self {
+        Self {
+            months,
+            days,
+            microseconds,
+        }
+    }
+
+    /// Create an interval of `months` months
+    pub fn months(months: i32) -> Self {
+        Self::new(months, 0, 0)
+    }
+
+    /// Create an interval of `days` days
+    pub fn days(days: i32) -> Self {
+        Self::new(0, days, 0)
+    }
+
+    /// Create an interval of `microseconds` microseconds
+    pub fn microseconds(microseconds: i64) -> Self {
+        Self::new(0, 0, microseconds)
+    }
+}
+
+impl Display for Interval {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let parts = [
+            (self.months as i64, "MONTHS"),
+            (self.days as i64, "DAYS"),
+            (self.microseconds, "MICROSECONDS"),
+        ];
+        let parts: Vec<_> = parts
+            .into_iter()
+            .filter(|(value, _)| *value != 0)
+            .map(|(value, unit)| format!("{value} {unit}"))
+            .collect();
+        match parts.is_empty() {
+            true => write!(f, "INTERVAL 0 DAYS"),
+            false => write!(f, "INTERVAL {}", parts.join(" ")),
+        }
+    }
+}
+
 /// A SQL expression.
 ///
 /// These expressions do not track or validate data types, other than the type
 /// of literals. It is up to the expression evaluator to validate the
-/// expression against a schema and add appropriate casts as required.
+/// expression against a schema and add appropriate casts as required. In
+/// particular, the operand of a binary operation with a literal is coerced to
+/// the type of the literal, so e.g. comparing a string column to a timestamp
+/// literal compares timestamps.
 #[derive(Debug, Clone, PartialEq)]
 pub enum Expression {
-    /// A literal value.
+    /// A literal value, with the (explicit) data type of its [`Scalar`].
     Literal(Scalar),
-    /// A column reference by name.
-    Column(String),
+    /// A column reference by name. A nested column is referenced by the path of names leading to
+    /// it, see [`Expression::nested_column`].
+    Column(ColumnName),
     /// A struct computed from a Vec of expressions
     Struct(Vec<Expression>),
     /// A binary operation.
@@ -107,7 +186,50 @@ pub enum Expression {
         /// The expressions.
         exprs: Vec<Expression>,
     },
-    // TODO: support more expressions, such as IS IN, LIKE, etc.
+    /// The value of a key in a map, e.g. `properties['env']`. The result is null if the map is
+    /// null or doesn't contain the key.
+    MapLookup {
+        /// The expression of the map to look the key up in.
+        map_expr: Box<Expression>,
+        /// The key to look up.
+        key: Box<Expression>,
+    },
+    /// A date or timestamp plus an [`Interval`], e.g. `date_col + INTERVAL 7 DAYS`. The result has
+    /// the type of the date or timestamp.
+    AddInterval {
+        /// The expression of the date or timestamp.
+        expr: Box<Expression>,
+        /// The interval to add.
+        interval: Interval,
+    },
+    /// The largest of the values of the expressions in each row, ignoring nulls, e.g.
+    /// `GREATEST(a, b, c)`. The result is null only if all the values are null. The values are
+    /// compared as their common supertype (e.g. the greatest of an integer and a long is a long).
+    Greatest(Vec<Expression>),
+    /// The smallest of the values of the expressions in each row, ignoring nulls. See
+    /// [`Expression::Greatest`].
+    Least(Vec<Expression>),
+    /// Whether the value of an expression is in a list of values, e.g. `part IN ('a', 'b')`, with
+    /// the SQL semantics for nulls: the result is null if the value is null, or if it isn't in the
+    /// list and the list has a null.
+    In {
+        /// The expression whose value is looked up.
+        target: Box<Expression>,
+        /// The values to look it up in, which must be coercible to the type of the target.
+        list: Vec<Scalar>,
+    },
+    /// Whether the value of a string expression matches a regular expression, e.g.
+    /// `path RLIKE '^/prod/.*\\.log$'`. The pattern matches anywhere in the value unless it is
+    /// anchored, and the result is null if the value is null.
+    RLike {
+        /// The expression of the string to match.
+        expr: Box<Expression>,
+        /// The regular expression to match it with, in the syntax of the [`regex`] crate.
+        ///
+        /// [`regex`]: https://docs.rs/regex
+        pattern: String,
+    },
+    // TODO: support more expressions, such as LIKE, etc.
 }
 
 impl<T: Into<Scalar>> From<T> for Expression {
@@ -131,6 +253,11 @@ impl Display for Expression {
                 left,
                 right,
             } => write!(f, "DISTINCT({}, {})", left, right),
+            Self::BinaryOperation {
+                op: op @ (BinaryOperator::NullIf | BinaryOperator::IfNull),
+                left,
+                right,
+            } => write!(f, "{}({}, {})", op, left, right),
             Self::BinaryOperation { op, left, right } => write!(f, "{} {} {}", left, op, right),
             Self::UnaryOperation { op, expr } => match op {
                 UnaryOperator::Not => write!(f, "NOT {}", expr),
@@ -152,27 +279,44 @@ impl Display for Expression {
                     )
                 }
             },
+            Self::MapLookup { map_expr, key } => write!(f, "{}[{}]", map_expr, key),
+            Self::AddInterval { expr, interval } => write!(f, "{} + {}", expr, interval),
+            Self::Greatest(exprs) => write!(f, "GREATEST({})", exprs.iter().join(", ")),
+            Self::Least(exprs) => write!(f, "LEAST({})", exprs.iter().join(", ")),
+            Self::In { target, list } => write!(f, "{} IN ({})", target, list.iter().join(", ")),
+            Self::RLike { expr, pattern } => write!(f, "{} RLIKE '{}'", expr, pattern),
         }
     }
 }
 
 impl Expression {
     /// Returns a set of columns referenced by this expression.
-    pub fn references(&self) -> HashSet<&str> {
+    pub fn references(&self) -> HashSet<&ColumnName> {
         let mut set = HashSet::new();
 
         for expr in self.walk() {
             if let Self::Column(name) = expr {
-                set.insert(name.as_str());
+                set.insert(name);
             }
         }
 
         set
     }
 
-    /// Create an new expression for a column reference
+    /// Create an new expression for a column reference. A nested column is referenced by its path
+    /// with the names separated by `.`, e.g. `a.b` for the field `b` of the struct column `a`, see
+    /// [`ColumnName::from_dotted`].
     pub fn column(name: impl ToString) -> Self {
-        Self::Column(name.to_string())
+        Self::Column(ColumnName::from_dotted(&name.to_string()))
+    }
+
+    /// Create a new expression for a reference to a nested column by the `path` of names leading
+    /// to it, e.g. `["address", "zip"]` for the field `zip` of the struct column `address`. Unlike
+    /// with [`Expression::column`], the names can contain `.`.
+    pub fn nested_column(path: impl IntoIterator<Item = impl ToString>) -> Self {
+        Self::Column(ColumnName::new(
+            path.into_iter().map(|name| name.to_string()),
+        ))
     }
 
     /// Create a new expression for a literal value
@@ -212,6 +356,48 @@ impl Expression {
         Self::VariadicOperation { op, exprs }
     }
 
+    /// Creates a new expression `map_expr[key]`
+    pub fn map_lookup(map_expr: impl Into<Expression>, key: impl Into<Expression>) -> Self {
+        Self::MapLookup {
+            map_expr: Box::new(map_expr.into()),
+            key: Box::new(key.into()),
+        }
+    }
+
+    /// Creates a new expression `self + interval`, for a date or timestamp `self`
+    pub fn add_interval(self, interval: Interval) -> Self {
+        Self::AddInterval {
+            expr: Box::new(self),
+            interval,
+        }
+    }
+
+    /// Creates a new expression GREATEST(exprs...)
+    pub fn greatest(exprs: impl IntoIterator<Item = Self>) -> Self {
+        Self::Greatest(exprs.into_iter().collect())
+    }
+
+    /// Creates a new expression LEAST(exprs...)
+    pub fn least(exprs: impl IntoIterator<Item = Self>) -> Self {
+        Self::Least(exprs.into_iter().collect())
+    }
+
+    /// Creates a new expression `self IN (list...)`
+    pub fn is_in(self, list: impl IntoIterator<Item = impl Into<Scalar>>) -> Self {
+        Self::In {
+            target: Box::new(self),
+            list: list.into_iter().map(Into::into).collect(),
+        }
+    }
+
+    /// Creates a new expression `self RLIKE 'pattern'`
+    pub fn rlike(self, pattern: impl ToString) -> Self {
+        Self::RLike {
+            expr: Box::new(self),
+            pattern: pattern.to_string(),
+        }
+    }
+
     /// Creates a new expression AND(exprs...)
     pub fn and_from(exprs: impl IntoIterator<Item = Self>) -> Self {
         Self::variadic(VariadicOperator::And, exprs)
@@ -282,7 +468,17 @@ impl Expression {
         Self::binary(BinaryOperator::Distinct, self, other)
     }
 
-    fn walk(&self) -> impl Iterator<Item = &Self> + '_ {
+    /// Create a new expression `NULLIF(self, other)`
+    pub fn null_if(self, other: Self) -> Self {
+        Self::binary(BinaryOperator::NullIf, self, other)
+    }
+
+    /// Create a new expression `IFNULL(self, default)`
+    pub fn if_null(self, default: Self) -> Self {
+        Self::binary(BinaryOperator::IfNull, self, default)
+    }
+
+    pub(crate) fn walk(&self) -> impl Iterator<Item = &Self> + '_ {
         let mut stack = vec![self];
         std::iter::from_fn(move || {
             let expr = stack.pop()?;
@@ -302,6 +498,22 @@ impl Expression {
                 Self::VariadicOperation { exprs, .. } => {
                     stack.extend(exprs.iter());
                 }
+                Self::MapLookup { map_expr, key } => {
+                    stack.push(map_expr);
+                    stack.push(key);
+                }
+                Self::AddInterval { expr, .. } => {
+                    stack.push(expr);
+                }
+                Self::Greatest(exprs) | Self::Least(exprs) => {
+                    stack.extend(exprs.iter());
+                }
+                Self::In { target, .. } => {
+                    stack.push(target);
+                }
+                Self::RLike { expr, .. } => {
+                    stack.push(expr);
+                }
             }
             Some(expr)
         })
@@ -350,7 +562,7 @@ impl std::ops::Div<Expression> for Expression {
 
 #[cfg(test)]
 mod tests {
-    use super::Expression as Expr;
+    use super::{Expression as Expr, Interval};
 
     #[test]
     fn test_expression_format() {
@@ -388,7 +600,39 @@ mod tests {
                     .or(col_ref.clone().lt(Expr::literal(10))),
                 "OR(Column(x) > 2, Column(x) < 10)",
             ),
-            (col_ref.eq(Expr::literal("foo")), "Column(x) = 'foo'"),
+            (
+                col_ref.clone().eq(Expr::literal("foo")),
+                "Column(x) = 'foo'",
+            ),
+            (
+                Expr::map_lookup(Expr::column("m"), "env").eq(Expr::literal("prod")),
+                "Column(m)['env'] = 'prod'",
+            ),
+            (
+                col_ref.clone().null_if(Expr::literal(0)),
+                "NULLIF(Column(x), 0)",
+            ),
+            (
+                col_ref.clone().if_null(Expr::literal(1)),
+                "IFNULL(Column(x), 1)",
+            ),
+            (
+                col_ref.clone().add_interval(Interval::days(7)),
+                "Column(x) + INTERVAL 7 DAYS",
+            ),
+            (
+                col_ref.clone().add_interval(Interval::new(-1, 0, 30)),
+                "Column(x) + INTERVAL -1 MONTHS 30 MICROSECONDS",
+            ),
+            (
+                Expr::greatest([col_ref.clone(), Expr::column("y"), Expr::literal(1)]),
+                "GREATEST(Column(x), Column(y), 1)",
+            ),
+            (
+                Expr::least([col_ref.clone(), Expr::column("y")]),
+                "LEAST(Column(x), Column(y))",
+            ),
+            (col_ref.is_in([1, 2]), "Column(x) IN (1, 2)"),
         ];
 
         for (expr, expected) in cases {
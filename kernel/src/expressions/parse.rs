@@ -0,0 +1,548 @@
+//! Parsing of SQL expressions into [`Expression`]s, see [`parse_expression`].
+
+use itertools::Itertools;
+
+use super::{Expression, Scalar};
+use crate::{DeltaResult, Error};
+
+/// Parse `sql`, a Spark SQL expression such as the generation expression of a generated column
+/// (e.g. `price * quantity`), into an [`Expression`].
+///
+/// Only the SQL that kernel expressions can represent is supported: column references (with
+/// nested columns as dotted paths, and names with other characters quoted in backticks),
+/// boolean, numeric and string literals, `+`, `-` and `*`, comparisons (including `<=>` and
+/// `IS [NOT] DISTINCT FROM`), `AND`, `OR`, `NOT`, `IS [NOT] NULL`, `[NOT] IN` with a list of
+/// literals, `[NOT] RLIKE`, map lookups (`col['key']`), and the functions `nullif`, `ifnull`,
+/// `nvl`, `coalesce`, `greatest` and `least`. Anything else, e.g. other functions or casts,
+/// fails to parse. So does `/`, which is double division in SQL, even of integers.
+///
+/// Integer literals are integers, or longs if they don't fit (or have the `L` suffix). Literals
+/// with a fractional part are decimals, and those with an exponent (or the `D` suffix) are
+/// doubles, like in Spark.
+pub fn parse_expression(sql: &str) -> DeltaResult<Expression> {
+    let mut parser = Parser {
+        tokens: tokenize(sql)?,
+        pos: 0,
+    };
+    let expr = parser.parse_or()?;
+    match parser.peek() {
+        None => Ok(expr),
+        Some(token) => Err(Error::generic(format!(
+            "Unexpected {token:?} in expression {sql:?}"
+        ))),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    /// An unquoted identifier or keyword, as written
+    Ident(String),
+    /// An identifier quoted in backticks, without them
+    Quoted(String),
+    /// A string literal, unescaped
+    String(String),
+    /// A numeric literal, as written (including any suffix)
+    Number(String),
+    Symbol(&'static str),
+}
+
+const SYMBOLS: &[&str] = &[
+    "<=>", "==", "!=", "<>", "<=", ">=", "=", "<", ">", "+", "-", "*", "/", "(", ")", ",", ".",
+    "[", "]",
+];
+
+fn tokenize(sql: &str) -> DeltaResult<Vec<Token>> {
+    let unterminated = || Error::generic(format!("Unterminated quote in expression {sql:?}"));
+    let mut tokens = vec![];
+    let mut chars = sql.char_indices().peekable();
+    while let Some(&(start, c)) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if c.is_ascii_alphabetic() || c == '_' {
+            let mut ident = String::new();
+            while let Some((_, c)) = chars.next_if(|(_, c)| c.is_ascii_alphanumeric() || *c == '_')
+            {
+                ident.push(c);
+            }
+            tokens.push(Token::Ident(ident));
+        } else if c.is_ascii_digit() {
+            let mut number = String::new();
+            while let Some((_, c)) = chars.next_if(|(_, c)| c.is_ascii_alphanumeric() || *c == '.')
+            {
+                number.push(c);
+                // the sign of an exponent
+                if c.eq_ignore_ascii_case(&'e') {
+                    if let Some((_, sign)) = chars.next_if(|(_, c)| *c == '+' || *c == '-') {
+                        number.push(sign);
+                    }
+                }
+            }
+            tokens.push(Token::Number(number));
+        } else if c == '`' {
+            chars.next();
+            let mut ident = String::new();
+            loop {
+                match chars.next().ok_or_else(unterminated)? {
+                    // a doubled backtick is an escaped one
+                    (_, '`') if chars.next_if(|(_, c)| *c == '`').is_some() => ident.push('`'),
+                    (_, '`') => break,
+                    (_, c) => ident.push(c),
+                }
+            }
+            tokens.push(Token::Quoted(ident));
+        } else if c == '\'' || c == '"' {
+            chars.next();
+            let mut string = String::new();
+            loop {
+                match chars.next().ok_or_else(unterminated)? {
+                    (_, '\\') => match chars.next().ok_or_else(unterminated)? {
+                        (_, 'n') => string.push('\n'),
+                        (_, 't') => string.push('\t'),
+                        (_, 'r') => string.push('\r'),
+                        (_, '0') => string.push('\0'),
+                        (_, c) => string.push(c),
+                    },
+                    (_, quote) if quote == c => break,
+                    (_, c) => string.push(c),
+                }
+            }
+            tokens.push(Token::String(string));
+        } else {
+            let symbol = SYMBOLS
+                .iter()
+                .find(|symbol| sql[start..].starts_with(*symbol))
+                .ok_or_else(|| {
+                    Error::generic(format!("Unexpected character {c:?} in expression {sql:?}"))
+                })?;
+            for _ in 0..symbol.len() {
+                chars.next();
+            }
+            tokens.push(Token::Symbol(symbol));
+        }
+    }
+    Ok(tokens)
+}
+
+/// A recursive descent parser, with a method for each level of operator precedence from the
+/// lowest (`OR`) to the highest (literals, columns, function calls and parentheses)
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> DeltaResult<Token> {
+        let token = self
+            .tokens
+            .get(self.pos)
+            .cloned()
+            .ok_or_else(|| Error::generic("Unexpected end of expression"))?;
+        self.pos += 1;
+        Ok(token)
+    }
+
+    /// Advance past the next token if it is the keyword `keyword`
+    fn next_if_keyword(&mut self, keyword: &str) -> bool {
+        match self.peek() {
+            Some(Token::Ident(ident)) if ident.eq_ignore_ascii_case(keyword) => {
+                self.pos += 1;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Advance past the next token if it is one of `symbols`, returning it
+    fn next_if_symbol(&mut self, symbols: &[&str]) -> Option<&'static str> {
+        match self.peek() {
+            Some(Token::Symbol(symbol)) if symbols.contains(symbol) => {
+                let symbol = *symbol;
+                self.pos += 1;
+                Some(symbol)
+            }
+            _ => None,
+        }
+    }
+
+    fn expect_keyword(&mut self, keyword: &str) -> DeltaResult<()> {
+        match self.next_if_keyword(keyword) {
+            true => Ok(()),
+            false => Err(self.unexpected(keyword)),
+        }
+    }
+
+    fn expect_symbol(&mut self, symbol: &str) -> DeltaResult<()> {
+        match self.next_if_symbol(&[symbol]) {
+            Some(_) => Ok(()),
+            None => Err(self.unexpected(symbol)),
+        }
+    }
+
+    fn unexpected(&self, expected: &str) -> Error {
+        match self.peek() {
+            Some(token) => Error::generic(format!("Expected {expected}, found {token:?}")),
+            None => Error::generic(format!("Expected {expected}, found the end of expression")),
+        }
+    }
+
+    fn parse_or(&mut self) -> DeltaResult<Expression> {
+        let mut exprs = vec![self.parse_and()?];
+        while self.next_if_keyword("OR") {
+            exprs.push(self.parse_and()?);
+        }
+        Ok(match exprs.len() {
+            1 => exprs.remove(0),
+            _ => Expression::or_from(exprs),
+        })
+    }
+
+    fn parse_and(&mut self) -> DeltaResult<Expression> {
+        let mut exprs = vec![self.parse_not()?];
+        while self.next_if_keyword("AND") {
+            exprs.push(self.parse_not()?);
+        }
+        Ok(match exprs.len() {
+            1 => exprs.remove(0),
+            _ => Expression::and_from(exprs),
+        })
+    }
+
+    fn parse_not(&mut self) -> DeltaResult<Expression> {
+        match self.next_if_keyword("NOT") {
+            true => Ok(!self.parse_not()?),
+            false => self.parse_predicate(),
+        }
+    }
+
+    /// A comparison, or one of the postfix predicates (`IS NULL`, `IN`, `RLIKE`)
+    fn parse_predicate(&mut self) -> DeltaResult<Expression> {
+        let left = self.parse_additive()?;
+        let comparisons = ["<=>", "==", "!=", "<>", "<=", ">=", "=", "<", ">"];
+        if let Some(op) = self.next_if_symbol(&comparisons) {
+            let right = self.parse_additive()?;
+            return Ok(match op {
+                "<=>" => !left.distinct(right),
+                "=" | "==" => left.eq(right),
+                "!=" | "<>" => left.ne(right),
+                "<" => left.lt(right),
+                "<=" => left.le(right),
+                ">" => left.gt(right),
+                _ => left.ge(right),
+            });
+        }
+        if self.next_if_keyword("IS") {
+            let negated = self.next_if_keyword("NOT");
+            let expr = if self.next_if_keyword("NULL") {
+                left.is_null()
+            } else {
+                self.expect_keyword("DISTINCT")?;
+                self.expect_keyword("FROM")?;
+                left.distinct(self.parse_additive()?)
+            };
+            return Ok(if negated { !expr } else { expr });
+        }
+        let negated = self.next_if_keyword("NOT");
+        let expr = if self.next_if_keyword("IN") {
+            self.expect_symbol("(")?;
+            let mut list = vec![self.parse_literal()?];
+            while self.next_if_symbol(&[","]).is_some() {
+                list.push(self.parse_literal()?);
+            }
+            self.expect_symbol(")")?;
+            left.is_in(list)
+        } else if self.next_if_keyword("RLIKE") || self.next_if_keyword("REGEXP") {
+            match self.next()? {
+                Token::String(pattern) => left.rlike(pattern),
+                token => {
+                    return Err(Error::generic(format!(
+                        "Expected a string pattern, found {token:?}"
+                    )))
+                }
+            }
+        } else if negated {
+            return Err(self.unexpected("IN or RLIKE"));
+        } else {
+            return Ok(left);
+        };
+        Ok(if negated { !expr } else { expr })
+    }
+
+    fn parse_additive(&mut self) -> DeltaResult<Expression> {
+        let mut expr = self.parse_multiplicative()?;
+        while let Some(op) = self.next_if_symbol(&["+", "-"]) {
+            let right = self.parse_multiplicative()?;
+            expr = match op {
+                "+" => expr + right,
+                _ => expr - right,
+            };
+        }
+        Ok(expr)
+    }
+
+    fn parse_multiplicative(&mut self) -> DeltaResult<Expression> {
+        let mut expr = self.parse_primary()?;
+        while let Some(op) = self.next_if_symbol(&["*", "/"]) {
+            // SQL `/` always divides as doubles, while kernel division of integers is integer
+            // division, and there are no casts to convert the operands
+            if op == "/" {
+                return Err(Error::generic(
+                    "Division is not supported, as it would be integer division",
+                ));
+            }
+            let right = self.parse_primary()?;
+            expr = expr * right;
+        }
+        Ok(expr)
+    }
+
+    /// A literal (which may be negative), for the values of an `IN` list
+    fn parse_literal(&mut self) -> DeltaResult<Scalar> {
+        match self.parse_primary()? {
+            Expression::Literal(value) => Ok(value),
+            expr => Err(Error::generic(format!("Expected a literal, found {expr}"))),
+        }
+    }
+
+    fn parse_primary(&mut self) -> DeltaResult<Expression> {
+        let expr = match self.next()? {
+            Token::Symbol("(") => {
+                let expr = self.parse_or()?;
+                self.expect_symbol(")")?;
+                expr
+            }
+            // only literals can be negated, as kernel expressions have no negation
+            Token::Symbol("-") => match self.next()? {
+                Token::Number(number) => Expression::Literal(parse_number(&number, true)?),
+                token => {
+                    return Err(Error::generic(format!(
+                        "Only numeric literals can be negated, found {token:?}"
+                    )))
+                }
+            },
+            Token::Number(number) => Expression::Literal(parse_number(&number, false)?),
+            Token::String(string) => Expression::literal(string),
+            Token::Ident(ident) if ident.eq_ignore_ascii_case("TRUE") => Expression::literal(true),
+            Token::Ident(ident) if ident.eq_ignore_ascii_case("FALSE") => {
+                Expression::literal(false)
+            }
+            Token::Ident(ident) if ident.eq_ignore_ascii_case("NULL") => {
+                return Err(Error::generic("Untyped null literals are not supported"));
+            }
+            Token::Ident(name) if self.next_if_symbol(&["("]).is_some() => {
+                self.parse_function(&name)?
+            }
+            Token::Ident(name) | Token::Quoted(name) => {
+                let mut path = vec![name];
+                while self.next_if_symbol(&["."]).is_some() {
+                    match self.next()? {
+                        Token::Ident(name) | Token::Quoted(name) => path.push(name),
+                        token => {
+                            return Err(Error::generic(format!(
+                                "Expected a field name, found {token:?}"
+                            )))
+                        }
+                    }
+                }
+                Expression::nested_column(path)
+            }
+            token => return Err(Error::generic(format!("Unexpected {token:?}"))),
+        };
+        // map lookups bind tighter than any operator
+        let mut expr = expr;
+        while self.next_if_symbol(&["["]).is_some() {
+            let key = self.parse_or()?;
+            self.expect_symbol("]")?;
+            expr = Expression::map_lookup(expr, key);
+        }
+        Ok(expr)
+    }
+
+    /// The call of the function `name`, whose opening parenthesis was already parsed
+    fn parse_function(&mut self, name: &str) -> DeltaResult<Expression> {
+        let mut args = vec![];
+        if self.next_if_symbol(&[")"]).is_none() {
+            args.push(self.parse_or()?);
+            while self.next_if_symbol(&[","]).is_some() {
+                args.push(self.parse_or()?);
+            }
+            self.expect_symbol(")")?;
+        }
+        let num_args = args.len();
+        let wrong_args =
+            || Error::generic(format!("Wrong number of arguments ({num_args}) for {name}"));
+        match name.to_ascii_lowercase().as_str() {
+            "nullif" | "ifnull" | "nvl" if args.len() != 2 => Err(wrong_args()),
+            "nullif" => {
+                let (left, right) = args.into_iter().collect_tuple().ok_or_else(wrong_args)?;
+                Ok(left.null_if(right))
+            }
+            "ifnull" | "nvl" | "coalesce" => {
+                // coalesce(a, b, c) is ifnull(a, ifnull(b, c))
+                let mut args = args.into_iter().rev();
+                let last = args.next().ok_or_else(wrong_args)?;
+                Ok(args.fold(last, |default, expr| expr.if_null(default)))
+            }
+            "greatest" if !args.is_empty() => Ok(Expression::greatest(args)),
+            "least" if !args.is_empty() => Ok(Expression::least(args)),
+            "greatest" | "least" => Err(wrong_args()),
+            _ => Err(Error::generic(format!("Unsupported function {name}"))),
+        }
+    }
+}
+
+/// Parse a numeric literal `number` (with any suffix), negated if `negative`
+fn parse_number(number: &str, negative: bool) -> DeltaResult<Scalar> {
+    let invalid = || Error::generic(format!("Invalid numeric literal {number}"));
+    let sign = if negative { "-" } else { "" };
+    let upper = number.to_ascii_uppercase();
+    if let Some(digits) = upper.strip_suffix('L') {
+        return format!("{sign}{digits}")
+            .parse()
+            .map(Scalar::Long)
+            .map_err(|_| invalid());
+    }
+    if let Some(digits) = upper.strip_suffix('D') {
+        return format!("{sign}{digits}")
+            .parse()
+            .map(Scalar::Double)
+            .map_err(|_| invalid());
+    }
+    if upper.contains('E') {
+        return format!("{sign}{upper}")
+            .parse()
+            .map(Scalar::Double)
+            .map_err(|_| invalid());
+    }
+    if let Some((integer, fraction)) = upper.split_once('.') {
+        let digits = format!("{integer}{fraction}");
+        if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(invalid());
+        }
+        let unscaled: i128 = format!("{sign}{digits}").parse().map_err(|_| invalid())?;
+        let scale = fraction.len();
+        let significant = digits.trim_start_matches('0').len();
+        let precision = significant.max(scale).max(1);
+        if precision > 38 {
+            return Err(invalid());
+        }
+        return Ok(Scalar::Decimal(unscaled, precision as u8, scale as u8));
+    }
+    let value: i64 = format!("{sign}{upper}").parse().map_err(|_| invalid())?;
+    Ok(match i32::try_from(value) {
+        Ok(value) => Scalar::Integer(value),
+        Err(_) => Scalar::Long(value),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::expressions::VariadicOperator;
+
+    fn column(name: &str) -> Expression {
+        Expression::column(name)
+    }
+
+    #[test]
+    fn test_parse_expression() {
+        let cases = [
+            ("price * quantity", column("price") * column("quantity")),
+            (
+                "a + b * 2 - 1",
+                column("a") + column("b") * Expression::literal(2) - Expression::literal(1),
+            ),
+            (
+                "(a + b) * 2",
+                (column("a") + column("b")) * Expression::literal(2),
+            ),
+            (
+                "`my col`.`x``y`",
+                Expression::nested_column(["my col", "x`y"]),
+            ),
+            ("address.zip", Expression::nested_column(["address", "zip"])),
+            (
+                "props['env']",
+                Expression::map_lookup(column("props"), "env"),
+            ),
+            (
+                "a = 1 AND NOT b OR c IS NOT NULL",
+                Expression::or_from([
+                    column("a").eq(Expression::literal(1)).and(!column("b")),
+                    !column("c").is_null(),
+                ]),
+            ),
+            ("a <=> b", !column("a").distinct(column("b"))),
+            ("a IS DISTINCT FROM b", column("a").distinct(column("b"))),
+            ("a <> 'it\\'s'", column("a").ne(Expression::literal("it's"))),
+            ("a NOT IN (1, -2)", !column("a").is_in([1, -2])),
+            ("s RLIKE \"^x\"", column("s").rlike("^x")),
+            (
+                "coalesce(a, b, 0)",
+                column("a").if_null(column("b").if_null(Expression::literal(0))),
+            ),
+            (
+                "NULLIF(a, '')",
+                column("a").null_if(Expression::literal("")),
+            ),
+            (
+                "greatest(a, b)",
+                Expression::greatest([column("a"), column("b")]),
+            ),
+            ("TRUE", Expression::literal(true)),
+        ];
+        for (sql, expected) in cases {
+            assert_eq!(parse_expression(sql).unwrap(), expected, "{sql}");
+        }
+        // AND binds tighter than OR, so the AND isn't flattened into it
+        let Expression::VariadicOperation { op, exprs } = parse_expression("a OR b AND c").unwrap()
+        else {
+            panic!("Expected a variadic operation");
+        };
+        assert_eq!(op, VariadicOperator::Or);
+        assert_eq!(exprs[1], column("b").and(column("c")));
+    }
+
+    #[test]
+    fn test_parse_numbers() {
+        let cases = [
+            ("1", Scalar::Integer(1)),
+            ("-2147483648", Scalar::Integer(i32::MIN)),
+            ("2147483648", Scalar::Long(2147483648)),
+            ("7L", Scalar::Long(7)),
+            ("1.50", Scalar::Decimal(150, 3, 2)),
+            ("-0.05", Scalar::Decimal(-5, 2, 2)),
+            ("1e3", Scalar::Double(1000.0)),
+            ("2.5D", Scalar::Double(2.5)),
+        ];
+        for (sql, expected) in cases {
+            assert_eq!(
+                parse_expression(sql).unwrap(),
+                Expression::Literal(expected),
+                "{sql}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_unsupported() {
+        for sql in [
+            "pmod(hash(id), 16)",
+            "CAST(ts AS DATE)",
+            "a +",
+            "a b",
+            "'unterminated",
+            "-a",
+            "a IN (b)",
+            "NULL",
+            "nullif(a)",
+            "a ? b",
+            "(a + b) / 2",
+        ] {
+            assert!(parse_expression(sql).is_err(), "{sql}");
+        }
+    }
+}
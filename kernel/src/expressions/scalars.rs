@@ -271,6 +271,7 @@ impl PrimitiveType {
         match self {
             String => Ok(Scalar::String(raw.to_string())),
             Binary => Ok(Scalar::Binary(raw.to_string().into_bytes())),
+            Void => Ok(Scalar::Null(self.data_type())),
             Byte => self.parse_str_as_scalar(raw, Scalar::Byte),
             Decimal(precision, scale) => self.parse_decimal(raw, *precision, *scale),
             Short => self.parse_str_as_scalar(raw, Scalar::Short),
@@ -299,10 +300,20 @@ impl PrimitiveType {
             // NOTE: Timestamp and TimestampNtz are parsed in the same way, as microsecond since unix epoch.
             // The difference arrises mostly in how they are to be handled on the engine side - i.e. timestampNTZ
             // is not adjusted to UTC, this is just so we can (de-)serialize it as a date sting.
+            // Both can be written with a space or a `T` between the date and the time, and a
+            // timestamp can be ISO 8601 with a timezone offset (e.g. `Z`), which it is adjusted by.
             // https://github.com/delta-io/delta/blob/master/PROTOCOL.md#partition-value-serialization
             Timestamp | TimestampNtz => {
-                let timestamp = NaiveDateTime::parse_from_str(raw, "%Y-%m-%d %H:%M:%S%.f")
-                    .map_err(|_| self.parse_error(raw))?;
+                let timestamp = ["%Y-%m-%d %H:%M:%S%.f", "%Y-%m-%dT%H:%M:%S%.f"]
+                    .iter()
+                    .find_map(|format| NaiveDateTime::parse_from_str(raw, format).ok())
+                    .or_else(|| match self {
+                        Timestamp => DateTime::parse_from_rfc3339(raw)
+                            .ok()
+                            .map(|timestamp| timestamp.naive_utc()),
+                        _ => None,
+                    })
+                    .ok_or_else(|| self.parse_error(raw))?;
                 let timestamp = Utc.from_utc_datetime(&timestamp);
                 let micros = timestamp
                     .signed_duration_since(*UNIX_EPOCH)
@@ -367,14 +378,26 @@ impl PrimitiveType {
         // most i128::MAX, and 0-i128::MAX doesn't underflow
         let scale = frac_digits - exp;
         let scale: u8 = scale.try_into().map_err(|_| self.parse_error(raw))?;
-        require!(scale == expected_scale, self.parse_error(raw));
-        Self::check_decimal(precision, scale)?;
+        // a value with fewer fractional digits than the type is scaled up to it, e.g. `1.5` is
+        // `1.50` in a decimal of scale 2, but digits can't be dropped
+        require!(scale <= expected_scale, self.parse_error(raw));
+        Self::check_decimal(precision, expected_scale)?;
 
         let int: i128 = match frac_part {
             None => int_part.parse()?,
             Some(frac_part) => format!("{}{}", int_part, frac_part).parse()?,
         };
-        Ok(Scalar::Decimal(int, precision, scale))
+        let int = 10i128
+            .checked_pow((expected_scale - scale).into())
+            .and_then(|factor| int.checked_mul(factor))
+            .ok_or_else(|| self.parse_error(raw))?;
+        // the value must also fit in the type's precision, e.g. `1000` has too many digits for a
+        // decimal(5,2), whose values are at most 999.99
+        require!(
+            int.unsigned_abs() < 10u128.pow(precision.into()),
+            self.parse_error(raw)
+        );
+        Ok(Scalar::Decimal(int, precision, expected_scale))
     }
 }
 
@@ -426,6 +449,12 @@ mod tests {
         assert_decimal("1234.5E-4", 12345, 5, 5)?;
         assert_decimal("-0", 0, 1, 0)?;
         assert_decimal("12.000000000000000000", 12000000000000000000, 38, 18)?;
+        // fewer fractional digits than the scale
+        assert_decimal("1.5", 150, 5, 2)?;
+        assert_decimal("-7", -7000, 5, 3)?;
+        // as many digits as the precision
+        assert_decimal("999.99", 99999, 5, 2)?;
+        assert_decimal("-999.99", -99999, 5, 2)?;
         Ok(())
     }
 
@@ -455,6 +484,11 @@ mod tests {
         expect_fail_parse("-+1.0", 1, 1);
         expect_fail_parse("++1.0", 1, 1);
         expect_fail_parse("1.0E1+", 1, 1);
+        // more digits than the precision, once scaled
+        expect_fail_parse("123456", 5, 2);
+        expect_fail_parse("1000", 5, 2);
+        expect_fail_parse("-1000.0", 5, 2);
+        expect_fail_parse("1E3", 5, 2);
         // overflow i8 for `scale`
         expect_fail_parse("0.999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999", 0, 0);
         // scale will be too small to fit in i8
@@ -0,0 +1,166 @@
+//! Constant folding of [`Expression`]s, see [`simplify`].
+
+use super::{Expression, Scalar, UnaryOperator, VariadicOperator};
+use crate::schema::DataType;
+
+/// Simplify `expr` without changing its result for any input, so that evaluating it does less
+/// work. This folds boolean literals into the `NOT`, `AND` and `OR` operations that use them,
+/// flattens nested `AND`s and `OR`s, removes double negations, and folds `IS NULL` of literals.
+///
+/// This follows SQL's three-valued logic, so null literals are only dropped where that is
+/// correct: `AND(null, false)` is `false`, but `AND(null, true)` and `AND(null, x)` keep the
+/// null. For the same reason comparisons of a column with itself (e.g. `x = x`) aren't folded,
+/// as they're null for rows where the column is null.
+pub fn simplify(expr: Expression) -> Expression {
+    use Expression::*;
+    match expr {
+        UnaryOperation {
+            op: UnaryOperator::Not,
+            expr,
+        } => match simplify(*expr) {
+            UnaryOperation {
+                op: UnaryOperator::Not,
+                expr,
+            } => *expr,
+            expr => match as_boolean(&expr) {
+                Some(value) => boolean(value.map(|value| !value)),
+                None => !expr,
+            },
+        },
+        UnaryOperation {
+            op: UnaryOperator::IsNull,
+            expr,
+        } => match simplify(*expr) {
+            Literal(value) => Expression::literal(value.is_null()),
+            expr => expr.is_null(),
+        },
+        VariadicOperation { op, exprs } => simplify_variadic(op, exprs),
+        BinaryOperation { op, left, right } => {
+            Expression::binary(op, simplify(*left), simplify(*right))
+        }
+        Struct(exprs) => Struct(exprs.into_iter().map(simplify).collect()),
+        MapLookup { map_expr, key } => Expression::map_lookup(simplify(*map_expr), simplify(*key)),
+        AddInterval { expr, interval } => AddInterval {
+            expr: Box::new(simplify(*expr)),
+            interval,
+        },
+        Greatest(exprs) => Greatest(exprs.into_iter().map(simplify).collect()),
+        Least(exprs) => Least(exprs.into_iter().map(simplify).collect()),
+        In { target, list } => In {
+            target: Box::new(simplify(*target)),
+            list,
+        },
+        RLike { expr, pattern } => simplify(*expr).rlike(pattern),
+        expr @ (Literal(_) | Column(_)) => expr,
+    }
+}
+
+/// Simplify an `AND` or `OR` of `exprs`. The operation's absorbing value (`false` for `AND` and
+/// `true` for `OR`) decides the result, its identity value is dropped, and all null literals are
+/// replaced by a single one.
+fn simplify_variadic(op: VariadicOperator, exprs: Vec<Expression>) -> Expression {
+    let absorbing = matches!(op, VariadicOperator::Or);
+    let mut operands = vec![];
+    let mut has_null = false;
+    let mut pending: Vec<_> = exprs.into_iter().rev().collect();
+    while let Some(expr) = pending.pop() {
+        match simplify(expr) {
+            // operands of a nested operation of the same kind are operands of this one
+            Expression::VariadicOperation {
+                op: inner_op,
+                exprs,
+            } if inner_op == op => {
+                pending.extend(exprs.into_iter().rev());
+            }
+            expr => match as_boolean(&expr) {
+                Some(Some(value)) if value == absorbing => return boolean(Some(absorbing)),
+                Some(Some(_)) => {}
+                Some(None) => has_null = true,
+                None => operands.push(expr),
+            },
+        }
+    }
+    if has_null {
+        operands.push(boolean(None));
+    }
+    match operands.len() {
+        0 => boolean(Some(!absorbing)),
+        1 => operands.pop().unwrap(),
+        _ => Expression::variadic(op, operands),
+    }
+}
+
+/// The value of `expr` if it is a boolean literal, where `Some(None)` is a null boolean
+fn as_boolean(expr: &Expression) -> Option<Option<bool>> {
+    match expr {
+        Expression::Literal(Scalar::Boolean(value)) => Some(Some(*value)),
+        Expression::Literal(Scalar::Null(data_type)) if *data_type == DataType::BOOLEAN => {
+            Some(None)
+        }
+        _ => None,
+    }
+}
+
+/// A boolean literal, which is null if `value` is `None`
+fn boolean(value: Option<bool>) -> Expression {
+    match value {
+        Some(value) => Expression::literal(value),
+        None => Expression::literal(Scalar::Null(DataType::BOOLEAN)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_simplify() {
+        let x = || Expression::column("x");
+        let y = || Expression::column("y").gt(Expression::literal(1));
+        let t = || Expression::literal(true);
+        let f = || Expression::literal(false);
+        let null = || Expression::literal(Scalar::Null(DataType::BOOLEAN));
+
+        let cases = [
+            (Expression::and(t(), x()), x()),
+            (Expression::and(f(), x()), f()),
+            (Expression::or(t(), x()), t()),
+            (Expression::or(f(), x()), x()),
+            (Expression::and_from([t(), t()]), t()),
+            (Expression::and_from([]), t()),
+            (Expression::or_from([]), f()),
+            // nulls are only absorbed by the absorbing value
+            (Expression::and(null(), f()), f()),
+            (Expression::and(null(), t()), null()),
+            (Expression::or(null(), t()), t()),
+            (Expression::or(null(), f()), null()),
+            (
+                Expression::and_from([null(), x(), null()]),
+                Expression::and(x(), null()),
+            ),
+            // nested operations of the same kind are flattened
+            (
+                Expression::and(x(), Expression::and(t(), y())),
+                Expression::and_from([x(), y()]),
+            ),
+            (Expression::or(x(), Expression::and(y(), f())), x()),
+            (!!x(), x()),
+            (!!!x(), !x()),
+            (!t(), f()),
+            (!null(), null()),
+            (!Expression::and(t(), f()), t()),
+            (null().is_null(), t()),
+            (Expression::literal(1).is_null(), f()),
+            // folding happens anywhere in the expression
+            (
+                Expression::struct_expr([Expression::and(t(), y())]),
+                Expression::struct_expr([y()]),
+            ),
+            // comparisons with a column are null for null values, so they stay
+            (x().eq(x()), x().eq(x())),
+        ];
+        for (expr, expected) in cases {
+            assert_eq!(simplify(expr.clone()), expected, "{expr}");
+        }
+    }
+}
@@ -55,6 +55,7 @@ use url::Url;
 use self::schema::{DataType, SchemaRef};
 
 pub mod actions;
+pub mod clock;
 pub mod column_mapping;
 pub mod engine_data;
 pub mod error;
@@ -64,9 +65,14 @@ pub mod scan;
 pub mod schema;
 pub mod snapshot;
 pub mod table;
+pub mod table_changes;
+pub mod tombstones;
 pub mod transaction;
 pub(crate) mod utils;
 
+#[cfg(test)]
+pub(crate) mod test_utils;
+
 pub use engine_data::{DataVisitor, EngineData};
 pub use error::{DeltaResult, Error};
 pub use expressions::Expression;
@@ -97,10 +103,28 @@ pub type FileDataReadResultIterator =
 pub struct FileMeta {
     /// The fully qualified path to the object
     pub location: Url,
-    /// The last modified time
+    /// The last modified time, in milliseconds since the Unix epoch
     pub last_modified: i64,
     /// The size in bytes of the object
     pub size: usize,
+    /// The [e-tag] of the object, if the file system has them. This identifies the content of the
+    /// object, and changes whenever it is overwritten, see
+    /// [`FileSystemClient::read_files_if_unchanged`].
+    ///
+    /// [e-tag]: https://datatracker.ietf.org/doc/html/rfc9110#name-etag
+    pub e_tag: Option<String>,
+    /// The version of the object, if the file system versions objects (e.g. the version id of an
+    /// object in a versioned bucket)
+    pub version: Option<String>,
+}
+
+/// The layout of a row group of a parquet file, see [`ParquetHandler::parquet_row_group_layout`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RowGroupMeta {
+    /// The number of rows in the row group
+    pub num_rows: usize,
+    /// The range of bytes of the file that holds the column chunks of the row group
+    pub byte_range: Range<usize>,
 }
 
 /// Trait for implementing an Expression evaluator.
@@ -114,6 +138,30 @@ pub trait ExpressionEvaluator: Send + Sync {
     /// Contains one value for each row of the input.
     /// The data type of the output is same as the type output of the expression this evaluator is using.
     fn evaluate(&self, batch: &dyn EngineData) -> DeltaResult<Box<dyn EngineData>>;
+
+    /// Evaluate a boolean expression on a given EngineData, replacing the contents of `out` with
+    /// one value for each row of the input: `true` if the expression is true for the row, and
+    /// `false` if it is false or null.
+    ///
+    /// Since `out` is reused, evaluating the expression on many batches with the same buffer
+    /// avoids allocating a new one for each batch. The default implementation calls
+    /// [`ExpressionEvaluator::evaluate`] and copies its result into `out`, but connectors can
+    /// override it to skip materializing the intermediate [`EngineData`].
+    fn evaluate_into(&self, batch: &dyn EngineData, out: &mut Vec<bool>) -> DeltaResult<()> {
+        let result = self.evaluate(batch)?;
+        out.clear();
+        let mut visitor = actions::visitors::SelectionVectorVisitor {
+            selection_vector: std::mem::take(out),
+        };
+        let schema = schema::StructType::new(vec![schema::StructField::new(
+            "output",
+            DataType::BOOLEAN,
+            true,
+        )]);
+        let extracted = result.extract(Arc::new(schema), &mut visitor);
+        *out = visitor.selection_vector;
+        extracted
+    }
 }
 
 /// Provides expression evaluation capability to Delta Kernel.
@@ -130,6 +178,9 @@ pub trait ExpressionHandler: Send + Sync {
     /// - `expression`: Expression to evaluate.
     /// - `output_type`: Expected result data type.
     ///
+    /// Fails if the expression is invalid for the schema, e.g. if it references columns the
+    /// schema doesn't have.
+    ///
     /// [`Schema`]: crate::schema::StructType
     /// [`DataType`]: crate::schema::DataType
     fn get_evaluator(
@@ -137,7 +188,7 @@ pub trait ExpressionHandler: Send + Sync {
         schema: SchemaRef,
         expression: Expression,
         output_type: DataType,
-    ) -> Arc<dyn ExpressionEvaluator>;
+    ) -> DeltaResult<Arc<dyn ExpressionEvaluator>>;
 }
 
 /// Provides file system related functionalities to Delta Kernel.
@@ -151,11 +202,44 @@ pub trait FileSystemClient: Send + Sync {
     fn list_from(&self, path: &Url)
         -> DeltaResult<Box<dyn Iterator<Item = DeltaResult<FileMeta>>>>;
 
+    /// List the files under the directory of `path`, including those in its subdirectories (at
+    /// any depth), whose paths are lexicographically greater or equal to (UTF-8 sorting) the
+    /// given `path`. Like with [`Self::list_from`], `path` is either a directory, which is listed
+    /// in full, or a file name in the directory to list from. The result is sorted by the full
+    /// path of each file, so the files of a subdirectory are listed where its name sorts, e.g.
+    /// `a.json`, `a/b.json`, `a0.json`.
+    ///
+    /// Only files are listed: directories are not, and neither are the zero-size placeholder
+    /// objects some tools create for directories in object stores.
+    ///
+    /// The default implementation fails, for clients that can't list recursively.
+    fn list_from_recursive(
+        &self,
+        path: &Url,
+    ) -> DeltaResult<Box<dyn Iterator<Item = DeltaResult<FileMeta>>>> {
+        Err(Error::generic(format!(
+            "Recursive listing is not supported by this file system client: {path}"
+        )))
+    }
+
     /// Read data specified by the start and end offset from the file.
     fn read_files(
         &self,
         files: Vec<FileSlice>,
     ) -> DeltaResult<Box<dyn Iterator<Item = DeltaResult<Bytes>>>>;
+
+    /// Like [`Self::read_files`], but each slice comes with the e-tag of its file when it was
+    /// listed (see [`FileMeta::e_tag`]), if any. A slice of a file that no longer has that e-tag,
+    /// i.e. that was overwritten since, fails with an [`Error::StaleRead`] instead of returning
+    /// the new content. Slices without an e-tag are read as by [`Self::read_files`].
+    ///
+    /// The default implementation ignores the e-tags, for clients of file systems without them.
+    fn read_files_if_unchanged(
+        &self,
+        files: Vec<(FileSlice, Option<String>)>,
+    ) -> DeltaResult<Box<dyn Iterator<Item = DeltaResult<Bytes>>>> {
+        self.read_files(files.into_iter().map(|(file, _)| file).collect())
+    }
 }
 
 /// Provides JSON handling functionality to Delta Kernel.
@@ -186,6 +270,27 @@ pub trait JsonHandler: Send + Sync {
         physical_schema: SchemaRef,
         predicate: Option<Expression>,
     ) -> DeltaResult<FileDataReadResultIterator>;
+
+    /// Parse newline-delimited JSON as it is read, e.g. the chunks of a large commit file as they
+    /// are fetched, without materializing it all first. Returns the fields requested by
+    /// `output_schema` in batches of at most `batch_size` rows, each yielded as soon as its rows
+    /// have arrived. A record may straddle any number of chunks, and the last record doesn't need
+    /// a trailing newline, but a truncated last record is an error.
+    ///
+    /// The default implementation fails, for handlers that can't parse incrementally. This is only
+    /// available with the `default-engine` feature, which provides the streams and arrow batches.
+    #[cfg(feature = "default-engine")]
+    fn parse_json_stream<'a>(
+        &'a self,
+        json: futures::stream::BoxStream<'a, DeltaResult<Bytes>>,
+        output_schema: SchemaRef,
+        batch_size: usize,
+    ) -> DeltaResult<futures::stream::BoxStream<'a, DeltaResult<arrow_array::RecordBatch>>> {
+        let _ = (json, output_schema, batch_size);
+        Err(Error::generic(
+            "Streaming JSON parsing is not supported by this JSON handler",
+        ))
+    }
 }
 
 /// Provides Parquet file related functionalities to Delta Kernel.
@@ -201,13 +306,69 @@ pub trait ParquetHandler: Send + Sync {
     ///
     /// - `files` - File metadata for files to be read.
     /// - `physical_schema` - Select list and order of columns to read from the Parquet file.
-    /// - `predicate` - Optional push-down predicate hint (engine is free to ignore it).
+    /// - `predicate` - Optional push-down predicate hint (engine is free to ignore it). An engine
+    ///   that uses it may leave out rows that can't satisfy it, e.g. whole row groups whose
+    ///   statistics rule them out, but _must_ still return every row that could.
     fn read_parquet_files(
         &self,
         files: &[FileMeta],
         physical_schema: SchemaRef,
         predicate: Option<Expression>,
     ) -> DeltaResult<FileDataReadResultIterator>;
+
+    /// Read the statistics of each of the given Parquet files from its footer, without reading
+    /// any data. This is used to skip files that were added to the table without statistics in
+    /// the log. The returned data _must_ have exactly one row per file in `files`, in the same
+    /// order, and follow the Delta [per-file statistics] format given by `stats_schema`. Any
+    /// statistic that can't be determined from the footer should be left null.
+    ///
+    /// Returns `Ok(None)` if the handler doesn't support reading footer statistics, which is the
+    /// default.
+    ///
+    /// # Parameters
+    ///
+    /// - `files` - File metadata for files to read statistics for.
+    /// - `stats_schema` - Schema of the statistics to return.
+    ///
+    /// [per-file statistics]: https://github.com/delta-io/delta/blob/master/PROTOCOL.md#Per-file-Statistics
+    fn read_footer_stats(
+        &self,
+        _files: &[FileMeta],
+        _stats_schema: SchemaRef,
+    ) -> DeltaResult<Option<Box<dyn EngineData>>> {
+        Ok(None)
+    }
+
+    /// Get the layout of the row groups of the Parquet file `file` from its footer: the number
+    /// of rows and the range of bytes of each row group, in the order they are in the file. An
+    /// engine can use this to split a large file into parts of whole row groups, and read the
+    /// parts in parallel with [`Self::read_parquet_row_groups`].
+    ///
+    /// The default implementation fails, for handlers that can't read row groups separately.
+    fn parquet_row_group_layout(&self, file: &FileMeta) -> DeltaResult<Vec<RowGroupMeta>> {
+        Err(Error::generic(format!(
+            "Reading the row group layout is not supported by this parquet handler: {}",
+            file.location
+        )))
+    }
+
+    /// Read only some row groups of the Parquet file `file`, given by their indices in
+    /// [`Self::parquet_row_group_layout`], like [`Self::read_parquet_files`] reads whole files.
+    /// The row groups are read in the order of `row_group_indices`. Fails if an index is out of
+    /// range.
+    ///
+    /// The default implementation fails, for handlers that can't read row groups separately.
+    fn read_parquet_row_groups(
+        &self,
+        file: &FileMeta,
+        _row_group_indices: &[usize],
+        _physical_schema: SchemaRef,
+    ) -> DeltaResult<FileDataReadResultIterator> {
+        Err(Error::generic(format!(
+            "Reading row groups is not supported by this parquet handler: {}",
+            file.location
+        )))
+    }
 }
 
 /// The `Engine` trait encapsulates all the functionality an engine or connector needs to provide
@@ -227,4 +388,15 @@ pub trait Engine: Send + Sync {
 
     /// Get the connector provided [`ParquetHandler`].
     fn get_parquet_handler(&self) -> Arc<dyn ParquetHandler>;
+
+    /// Get the [`Clock`] that timestamps commits without an in-commit timestamp. Defaults to a
+    /// [`FileModificationClock`], which uses the modification time of their files; test engines
+    /// can return a [`FixedClock`] to resolve timestamps deterministically.
+    ///
+    /// [`Clock`]: clock::Clock
+    /// [`FileModificationClock`]: clock::FileModificationClock
+    /// [`FixedClock`]: clock::FixedClock
+    fn get_clock(&self) -> Arc<dyn clock::Clock> {
+        Arc::new(clock::FileModificationClock)
+    }
 }
@@ -8,9 +8,6 @@ use crate::{DeltaResult, Version};
 /// terms, so we use `/`
 const DELIMITER: &str = "/";
 
-/// How many characters a version tag has
-const VERSION_LEN: usize = 20;
-
 /// How many characters a part specifier on a multipart checkpoint has
 const MULTIPART_PART_LEN: usize = 10;
 
@@ -23,6 +20,10 @@ pub(crate) struct LogPath<'a> {
     _compacted_to_version: Option<Version>,
     pub(crate) is_commit: bool,
     pub(crate) is_checkpoint: bool,
+    /// The part number and number of parts of a multi-part checkpoint, named
+    /// `<version>.checkpoint.<part>.<num parts>.parquet`. `None` for other files, including
+    /// single-file checkpoints.
+    pub(crate) checkpoint_part: Option<(Version, Version)>,
 }
 
 fn get_filename(path: &str) -> Option<&str> {
@@ -43,22 +44,33 @@ fn get_version_opt(version_str_opt: Option<&str>, expected_digits: usize) -> Opt
     })
 }
 
+/// Parse a version tag. The protocol pads versions with zeros to 20 digits, which is what the
+/// kernel writes, but some (older) writers use other widths, so any number of digits is accepted.
+fn parse_version(version_str: &str) -> Option<Version> {
+    if !version_str.is_empty() && version_str.bytes().all(|b| b.is_ascii_digit()) {
+        version_str.parse().ok()
+    } else {
+        None
+    }
+}
+
 pub(crate) fn version_from_location(location: &Url) -> Option<Version> {
     let path = location.path();
     get_filename(path)
         .and_then(|f| f.split_once('.'))
-        .and_then(|(name, _)| get_version_opt(Some(name), VERSION_LEN))
+        .and_then(|(name, _)| parse_version(name))
 }
 
 impl<'a> LogPath<'a> {
     pub(crate) fn new(url: &'a Url) -> Self {
         let filename = get_filename(url.path());
         let version_str = filename.and_then(|f| f.split_once('.'));
-        let version = version_str.and_then(|(name, _)| get_version_opt(Some(name), VERSION_LEN));
+        let version = version_str.and_then(|(name, _)| parse_version(name));
 
         let mut is_commit = false;
         let mut is_checkpoint = false;
         let mut compacted_to_version = None;
+        let mut checkpoint_part = None;
         if version.is_some() {
             // could be a checkpoint or commit file, let's check
             let (_, suffix) = version_str.unwrap(); // safe, version.is_some()
@@ -76,9 +88,23 @@ impl<'a> LogPath<'a> {
                         get_version_opt(split.next(), MULTIPART_PART_LEN),
                         split.next(),
                     );
-                    is_checkpoint = checkpoint_index.is_some()
-                        && checkpoint_max.is_some()
-                        && ext == Some("parquet");
+                    // the parts are numbered from 1
+                    if let (Some(part), Some(num_parts), Some("parquet")) =
+                        (checkpoint_index, checkpoint_max, ext)
+                    {
+                        if (1..=num_parts).contains(&part) {
+                            is_checkpoint = true;
+                            checkpoint_part = Some((part, num_parts));
+                        }
+                    }
+                }
+                if !is_checkpoint {
+                    // test if we're the top-level file of a V2 checkpoint,
+                    // [version].checkpoint.[uuid].(parquet|json)
+                    if let Some((id, ext)) = rest.split_once('.') {
+                        is_checkpoint =
+                            matches!(ext, "parquet" | "json") && uuid::Uuid::try_parse(id).is_ok();
+                    }
                 }
             }
 
@@ -86,8 +112,7 @@ impl<'a> LogPath<'a> {
                 // check if we're a compacted commit
                 if let Some((maybe_compacted_version, suffix)) = suffix.split_once('.') {
                     if suffix == "json" {
-                        compacted_to_version =
-                            get_version_opt(Some(maybe_compacted_version), VERSION_LEN);
+                        compacted_to_version = parse_version(maybe_compacted_version);
                         is_commit = compacted_to_version.is_some()
                     }
                 }
@@ -100,6 +125,7 @@ impl<'a> LogPath<'a> {
             _compacted_to_version: compacted_to_version,
             is_commit,
             is_checkpoint,
+            checkpoint_part,
         }
     }
 
@@ -184,6 +210,7 @@ mod tests {
         let path = log_path.child(child).unwrap();
         let to_test = LogPath::new(&path);
         assert_eq!(to_test.is_checkpoint, is_checkpoint);
+        assert_eq!(to_test.checkpoint_part.is_some(), is_checkpoint);
     }
 
     #[test]
@@ -197,6 +224,44 @@ mod tests {
         ] {
             test_child_is_multi(&log_path, good_path, true);
         }
+        let path = log_path
+            .child("_delta_log/00000000000000000021.checkpoint.0000000003.0000000010.parquet")
+            .unwrap();
+        assert_eq!(LogPath::new(&path).checkpoint_part, Some((3, 10)));
+    }
+
+    #[test]
+    fn test_v2_checkpoint_parsing() {
+        let table_url = table_url();
+        let log_path = LogPath::new(&table_url);
+
+        for (path, is_checkpoint) in [
+            (
+                "_delta_log/00000000000000000002.checkpoint.80a083e8-7026-4e79-81be-64bd76c43a11.parquet",
+                true,
+            ),
+            (
+                "_delta_log/00000000000000000002.checkpoint.80a083e8-7026-4e79-81be-64bd76c43a11.json",
+                true,
+            ),
+            // not a uuid
+            (
+                "_delta_log/00000000000000000002.checkpoint.80a083e8-7026-4e79.parquet",
+                false,
+            ),
+            // neither parquet nor json
+            (
+                "_delta_log/00000000000000000002.checkpoint.80a083e8-7026-4e79-81be-64bd76c43a11.crc",
+                false,
+            ),
+        ] {
+            let path = log_path.child(path).unwrap();
+            let log_path = LogPath::new(&path);
+            assert_eq!(log_path.is_checkpoint, is_checkpoint);
+            assert!(!log_path.is_commit);
+            assert_eq!(log_path.checkpoint_part, None);
+            assert_eq!(log_path.version, Some(2));
+        }
 
         for bad_path in [
             // `o` value is not 10 digits
@@ -211,11 +276,49 @@ mod tests {
             "_delta_log/00000000000000000001.checkpoinx.00000001.0000000002.parquet",
             // not .parquet
             "_delta_log/00000000000000000001.checkpoint.00000001.0000000002.json",
+            // parts are numbered from 1 to the number of parts
+            "_delta_log/00000000000000000001.checkpoint.0000000000.0000000002.parquet",
+            "_delta_log/00000000000000000001.checkpoint.0000000003.0000000002.parquet",
         ] {
             test_child_is_multi(&log_path, bad_path, false);
         }
     }
 
+    #[test]
+    fn test_version_padding() {
+        let table_url = table_url();
+        for (name, version) in [
+            ("00000000000000000012.json", 12),
+            ("0000000012.json", 12),
+            ("12.json", 12),
+            ("0.json", 0),
+        ] {
+            let url = LogPath::new(&table_url)
+                .child(format!("_delta_log/{name}"))
+                .unwrap();
+            let log_path = LogPath::new(&url);
+            assert!(log_path.is_commit, "{name}");
+            assert_eq!(log_path.version, Some(version), "{name}");
+            assert_eq!(version_from_location(&url), Some(version), "{name}");
+        }
+
+        let url = LogPath::new(&table_url)
+            .child("_delta_log/0000000003.checkpoint.parquet")
+            .unwrap();
+        let log_path = LogPath::new(&url);
+        assert!(log_path.is_checkpoint);
+        assert_eq!(log_path.version, Some(3));
+
+        for name in [".json", "1a.json", "-1.json", "+1.json", "_last_checkpoint"] {
+            let url = LogPath::new(&table_url)
+                .child(format!("_delta_log/{name}"))
+                .unwrap();
+            let log_path = LogPath::new(&url);
+            assert!(!log_path.is_commit, "{name}");
+            assert_eq!(log_path.version, None, "{name}");
+        }
+    }
+
     #[test]
     fn test_compaction_files() {
         let table_url = table_url();
@@ -4,11 +4,12 @@ use std::sync::Arc;
 
 use tracing::debug;
 
-use crate::actions::visitors::SelectionVectorVisitor;
-use crate::error::DeltaResult;
-use crate::expressions::{BinaryOperator, Expression as Expr, UnaryOperator, VariadicOperator};
+use crate::error::{DeltaResult, Error};
+use crate::expressions::{
+    simplify, BinaryOperator, ColumnName, Expression as Expr, UnaryOperator, VariadicOperator,
+};
 use crate::schema::{DataType, PrimitiveType, SchemaRef, StructField, StructType};
-use crate::{Engine, EngineData, ExpressionEvaluator, JsonHandler};
+use crate::{Engine, EngineData, ExpressionEvaluator, ExpressionHandler, JsonHandler};
 
 /// Returns `<op2>` (if any) such that `B <op2> A` is equivalent to `A <op> B`.
 fn commute(op: &BinaryOperator) -> Option<BinaryOperator> {
@@ -26,10 +27,10 @@ fn commute(op: &BinaryOperator) -> Option<BinaryOperator> {
 /// Get the expression that checks if a col could be null, assuming tight_bounds = true. In this
 /// case a column can contain null if any value > 0 is in the nullCount. This is further complicated
 /// by the default for tightBounds being true, so we have to check if it's EITHER `null` OR `true`
-fn get_tight_null_expr(null_col: String) -> Expr {
+fn get_tight_null_expr(null_col: ColumnName) -> Expr {
     Expr::and(
         Expr::distinct(Expr::column("tightBounds"), Expr::literal(false)),
-        Expr::gt(Expr::column(null_col), Expr::literal(0i64)),
+        Expr::gt(Expr::Column(null_col), Expr::literal(0i64)),
     )
 }
 
@@ -37,15 +38,31 @@ fn get_tight_null_expr(null_col: String) -> Expr {
 /// case, we can only check if the WHOLE column is null, but checking if the number of records is
 /// equal to the null count, since all other values of nullCount must be ignored (except 0, which
 /// doesn't help us)
-fn get_wide_null_expr(null_col: String) -> Expr {
+fn get_wide_null_expr(null_col: ColumnName) -> Expr {
     Expr::and(
         Expr::eq(Expr::column("tightBounds"), Expr::literal(false)),
-        Expr::eq(Expr::column("numRecords"), Expr::column(null_col)),
+        Expr::eq(Expr::column("numRecords"), Expr::Column(null_col)),
     )
 }
 
+/// Whether `col` is a (possibly nested) boolean column of `schema`
+fn is_boolean_column(schema: &StructType, col: &ColumnName) -> bool {
+    let Some((name, parents)) = col.path().split_last() else {
+        return false;
+    };
+    let mut schema = schema;
+    for parent in parents {
+        match schema.field(parent).map(StructField::data_type) {
+            Some(DataType::Struct(inner)) => schema = inner,
+            _ => return false,
+        }
+    }
+    schema.field(name).map(StructField::data_type) == Some(&DataType::BOOLEAN)
+}
+
 /// Rewrites a predicate to a predicate that can be used to skip files based on their stats.
-/// Returns `None` if the predicate is not eligible for data skipping.
+/// Returns `None` if the predicate is not eligible for data skipping. The columns of the predicate
+/// are columns of `table_schema`.
 ///
 /// We normalize each binary operation to a comparison between a column and a literal value
 /// and rewite that in terms of the min/max values of the column.
@@ -60,7 +77,7 @@ fn get_wide_null_expr(null_col: String) -> Expr {
 ///   operands that are not eligible for data skipping.
 /// - `OR` is rewritten only if all operands are eligible for data skipping. Otherwise,
 ///   the whole OR expression is dropped.
-fn as_data_skipping_predicate(expr: &Expr) -> Option<Expr> {
+fn as_data_skipping_predicate(expr: &Expr, table_schema: &StructType) -> Option<Expr> {
     use BinaryOperator::*;
     use Expr::*;
 
@@ -79,26 +96,46 @@ fn as_data_skipping_predicate(expr: &Expr) -> Option<Expr> {
                         Expr::le(Column(col.clone()), Literal(val.clone())),
                         Expr::le(Literal(val.clone()), Column(col.clone())),
                     ];
-                    return as_data_skipping_predicate(&Expr::and_from(exprs));
+                    return as_data_skipping_predicate(&Expr::and_from(exprs), table_schema);
                 }
                 NotEqual => {
                     let exprs = [
-                        Expr::gt(Column(format!("minValues.{}", col)), Literal(val.clone())),
-                        Expr::lt(Column(format!("maxValues.{}", col)), Literal(val.clone())),
+                        Expr::gt(Column(col.nested_in("minValues")), Literal(val.clone())),
+                        Expr::lt(Column(col.nested_in("maxValues")), Literal(val.clone())),
                     ];
                     return Some(Expr::or_from(exprs));
                 }
                 _ => return None, // unsupported operation
             };
-            let col = format!("{}.{}", stats_col, col);
+            let col = col.nested_in(stats_col);
             Some(Expr::binary(op, Column(col), Literal(val.clone())))
         }
+        // a bare boolean column selects the rows where it is true, and its negation the rows
+        // where it is false. Other bare columns aren't predicates, so they can't skip anything.
+        Column(col) if is_boolean_column(table_schema, col) => {
+            let expr = Expr::eq(expr.clone(), Expr::literal(true));
+            as_data_skipping_predicate(&expr, table_schema)
+        }
+        UnaryOperation {
+            op: UnaryOperator::Not,
+            expr,
+        } if matches!(expr.as_ref(), Column(col) if is_boolean_column(table_schema, col)) => {
+            let expr = Expr::eq(expr.as_ref().clone(), Expr::literal(false));
+            as_data_skipping_predicate(&expr, table_schema)
+        }
+        // the negation of the skipping predicate of `IN` would skip files that have rows equal to
+        // one of the values, even if they also have other rows. This is also the case for an `IN`
+        // nested deeper in the negated expression, e.g. `NOT (a IN (1, 2) AND b > 5)`
+        UnaryOperation {
+            op: UnaryOperator::Not,
+            expr,
+        } if expr.walk().any(|expr| matches!(expr, In { .. })) => None,
         UnaryOperation {
             op: UnaryOperator::Not,
             expr,
         } => {
             // get the expr as a skipping predicate, then invert it
-            as_data_skipping_predicate(expr).map(Expr::not)
+            as_data_skipping_predicate(expr, table_schema).map(Expr::not)
         }
         UnaryOperation {
             op: UnaryOperator::IsNull,
@@ -107,7 +144,7 @@ fn as_data_skipping_predicate(expr: &Expr) -> Option<Expr> {
             // to check if a column could have a null, we need two different checks, to see if
             // the bounds are tight and then to actually do the check
             if let Column(col) = expr.as_ref() {
-                let null_col = format!("nullCount.{col}");
+                let null_col = col.nested_in("nullCount");
                 Some(Expr::or(
                     get_tight_null_expr(null_col.clone()),
                     get_wide_null_expr(null_col),
@@ -117,8 +154,17 @@ fn as_data_skipping_predicate(expr: &Expr) -> Option<Expr> {
                 None
             }
         }
+        In { target, list } => {
+            // a file can only have matching rows if it can have rows equal to one of the values
+            let exprs = list
+                .iter()
+                .map(|value| Expr::eq(target.as_ref().clone(), Literal(value.clone())));
+            as_data_skipping_predicate(&Expr::or_from(exprs), table_schema)
+        }
         VariadicOperation { op, exprs } => {
-            let exprs = exprs.iter().map(as_data_skipping_predicate);
+            let exprs = exprs
+                .iter()
+                .map(|expr| as_data_skipping_predicate(expr, table_schema));
             match op {
                 VariadicOperator::And => Some(Expr::and_from(exprs.flatten())),
                 VariadicOperator::Or => Some(Expr::or_from(exprs.collect::<Option<Vec<_>>>()?)),
@@ -132,13 +178,14 @@ pub(crate) struct DataSkippingFilter {
     stats_schema: SchemaRef,
     select_stats_evaluator: Arc<dyn ExpressionEvaluator>,
     skipping_evaluator: Arc<dyn ExpressionEvaluator>,
-    filter_evaluator: Arc<dyn ExpressionEvaluator>,
-    json_handler: Arc<dyn JsonHandler>,
+    /// Parses the stats of add actions in [`Self::apply`], `None` if the filter is only applied
+    /// to already parsed stats
+    json_handler: Option<Arc<dyn JsonHandler>>,
 }
 
 impl DataSkippingFilter {
     /// Creates a new data skipping filter. Returns None if there is no predicate, or the predicate
-    /// is ineligible for data skipping.
+    /// is ineligible for data skipping, and fails if the engine can't evaluate it.
     ///
     /// NOTE: None is equivalent to a trivial filter that always returns TRUE (= keeps all files),
     /// but using an Option lets the engine easily avoid the overhead of applying trivial filters.
@@ -146,22 +193,53 @@ impl DataSkippingFilter {
         engine: &dyn Engine,
         table_schema: &SchemaRef,
         predicate: &Option<Expr>,
-    ) -> Option<Self> {
+    ) -> DeltaResult<Option<Self>> {
+        Self::new_with_handlers(
+            engine.get_expression_handler().as_ref(),
+            Some(engine.get_json_handler()),
+            table_schema,
+            predicate,
+        )
+    }
+
+    /// Creates a data skipping filter that can only be applied to already parsed stats with
+    /// [`Self::apply_to_stats`], e.g. the stats of the row groups of a parquet file.
+    #[cfg(feature = "default-engine")]
+    pub(crate) fn new_for_stats(
+        expression_handler: &dyn ExpressionHandler,
+        table_schema: &SchemaRef,
+        predicate: &Option<Expr>,
+    ) -> DeltaResult<Option<Self>> {
+        Self::new_with_handlers(expression_handler, None, table_schema, predicate)
+    }
+
+    fn new_with_handlers(
+        expression_handler: &dyn ExpressionHandler,
+        json_handler: Option<Arc<dyn JsonHandler>>,
+        table_schema: &SchemaRef,
+        predicate: &Option<Expr>,
+    ) -> DeltaResult<Option<Self>> {
         lazy_static::lazy_static!(
-            static ref PREDICATE_SCHEMA: DataType = StructType::new(vec![
-                StructField::new("predicate", DataType::BOOLEAN, true),
-            ]).into();
+            // the part of the log schema the stats are selected from
+            static ref ADD_STATS_SCHEMA: SchemaRef = Arc::new(StructType::new(vec![
+                StructField::new("add", StructType::new(vec![
+                    StructField::new("stats", DataType::STRING, true),
+                ]), true),
+            ]));
             static ref STATS_EXPR: Expr = Expr::column("add.stats");
-            static ref FILTER_EXPR: Expr = Expr::column("predicate").distinct(Expr::literal(false));
         );
 
         let predicate = match predicate {
             Some(predicate) => predicate,
-            None => return None,
+            None => return Ok(None),
         };
 
         debug!("Creating a data skipping filter for {}", &predicate);
-        let field_names: HashSet<_> = predicate.references();
+        let field_names: HashSet<_> = predicate
+            .references()
+            .into_iter()
+            .filter_map(ColumnName::top_level)
+            .collect();
 
         // Build the stats read schema by extracting the column names referenced by the predicate,
         // extracting the corresponding field from the table schema, and inserting that field.
@@ -172,7 +250,7 @@ impl DataSkippingFilter {
             .collect();
         if data_fields.is_empty() {
             // The predicate didn't reference any eligible stats columns, so skip it.
-            return None;
+            return Ok(None);
         }
 
         let stats_schema = Arc::new(StructType::new(vec![
@@ -207,33 +285,32 @@ impl DataSkippingFilter {
         //    null means we could not determine whether the file is safe to skip, because its stats
         //    were missing/null.
         //
-        // 3. The selection evaluator does DISTINCT(col(predicate), 'false') to produce true (= keep) when
-        //    the predicate is true/null and false (= skip) when the predicate is false.
-        let select_stats_evaluator = engine.get_expression_handler().get_evaluator(
-            stats_schema.clone(),
+        // 3. The skipping evaluator wraps the predicate in DISTINCT(predicate, 'false') to produce
+        //    true (= keep) when the predicate is true/null and false (= skip) when it is false,
+        //    which it evaluates straight into the selection vector.
+        let Some(skipping_predicate) = as_data_skipping_predicate(predicate, table_schema) else {
+            return Ok(None);
+        };
+
+        let select_stats_evaluator = expression_handler.get_evaluator(
+            ADD_STATS_SCHEMA.clone(),
             STATS_EXPR.clone(),
             DataType::STRING,
-        );
+        )?;
 
-        let skipping_evaluator = engine.get_expression_handler().get_evaluator(
+        let skipping_evaluator = expression_handler.get_evaluator(
             stats_schema.clone(),
-            Expr::struct_expr([as_data_skipping_predicate(predicate)?]),
-            PREDICATE_SCHEMA.clone(),
-        );
-
-        let filter_evaluator = engine.get_expression_handler().get_evaluator(
-            stats_schema.clone(),
-            FILTER_EXPR.clone(),
+            // the skipping predicate is evaluated for every file, so fold it first
+            simplify(skipping_predicate).distinct(Expr::literal(false)),
             DataType::BOOLEAN,
-        );
+        )?;
 
-        Some(Self {
+        Ok(Some(Self {
             stats_schema,
             select_stats_evaluator,
             skipping_evaluator,
-            filter_evaluator,
-            json_handler: engine.get_json_handler(),
-        })
+            json_handler,
+        }))
     }
 
     /// Apply the DataSkippingFilter to an EngineData batch of actions. Returns a selection vector
@@ -241,23 +318,31 @@ impl DataSkippingFilter {
     pub(crate) fn apply(&self, actions: &dyn EngineData) -> DeltaResult<Vec<bool>> {
         // retrieve and parse stats from actions data
         let stats = self.select_stats_evaluator.evaluate(actions)?;
-        let parsed_stats = self
-            .json_handler
-            .parse_json(stats, self.stats_schema.clone())?;
-
-        // evaluate the predicate on the parsed stats, then convert to selection vector
-        let skipping_predicate = self.skipping_evaluator.evaluate(&*parsed_stats)?;
-        let selection_vector = self
-            .filter_evaluator
-            .evaluate(skipping_predicate.as_ref())?;
-
-        // visit the engine's selection vector to produce a Vec<bool>
-        let mut visitor = SelectionVectorVisitor::default();
-        let schema = StructType::new(vec![StructField::new("output", DataType::BOOLEAN, false)]);
-        selection_vector
-            .as_ref()
-            .extract(Arc::new(schema), &mut visitor)?;
-        Ok(visitor.selection_vector)
+        let json_handler = self.json_handler.as_ref().ok_or_else(|| {
+            Error::generic("Data skipping filter has no JSON handler to parse stats with")
+        })?;
+        let parsed_stats = json_handler.parse_json(stats, self.stats_schema.clone())?;
+        let mut selection_vector = Vec::with_capacity(parsed_stats.length());
+        self.apply_to_stats(parsed_stats.as_ref(), &mut selection_vector)?;
+        Ok(selection_vector)
+    }
+
+    /// The schema of the parsed stats that [`Self::apply_to_stats`] expects.
+    pub(crate) fn stats_schema(&self) -> &SchemaRef {
+        &self.stats_schema
+    }
+
+    /// Apply the DataSkippingFilter to already parsed stats, with one row per file. Replaces the
+    /// contents of `selection_vector` with the selection vector of the files that passed data
+    /// skipping, so callers that apply the filter repeatedly can reuse one buffer.
+    pub(crate) fn apply_to_stats(
+        &self,
+        parsed_stats: &dyn EngineData,
+        selection_vector: &mut Vec<bool>,
+    ) -> DeltaResult<()> {
+        // evaluate the predicate on the parsed stats straight into the selection vector
+        self.skipping_evaluator
+            .evaluate_into(parsed_stats, selection_vector)
 
         // TODO(zach): add some debug info about data skipping that occurred
         // let before_count = actions.length();
@@ -272,6 +357,44 @@ impl DataSkippingFilter {
 mod tests {
     use super::*;
 
+    /// Rewrite `expr` for a table whose columns `a` and `b` are booleans, and `n` a long
+    fn rewrite(expr: &Expr) -> Option<Expr> {
+        let table_schema = StructType::new(vec![
+            StructField::new("a", DataType::BOOLEAN, true),
+            StructField::new("b", DataType::BOOLEAN, true),
+            StructField::new("n", DataType::LONG, true),
+        ]);
+        as_data_skipping_predicate(expr, &table_schema)
+    }
+
+    /// Which of the files with the JSON `stats` (or none) a data skipping filter with `predicate`
+    /// keeps, on a table with `table_schema`
+    fn selection(table_schema: &SchemaRef, stats: &[Option<&str>], predicate: Expr) -> Vec<bool> {
+        use arrow_array::{RecordBatch, StringArray};
+
+        use crate::engine::arrow_data::ArrowEngineData;
+        use crate::engine::sync::SyncEngine;
+
+        let engine = SyncEngine::new();
+        let filter = DataSkippingFilter::new(&engine, table_schema, &Some(predicate))
+            .unwrap()
+            .unwrap();
+        let stats = StringArray::from(stats.to_vec());
+        let batch = RecordBatch::try_from_iter([("stats", Arc::new(stats) as _)]).unwrap();
+        let parsed_stats = engine
+            .get_json_handler()
+            .parse_json(
+                Box::new(ArrowEngineData::new(batch)),
+                filter.stats_schema().clone(),
+            )
+            .unwrap();
+        let mut selection_vector = vec![];
+        filter
+            .apply_to_stats(parsed_stats.as_ref(), &mut selection_vector)
+            .unwrap();
+        selection_vector
+    }
+
     #[test]
     fn test_rewrite_basic_comparison() {
         let column = Expr::column("a");
@@ -343,8 +466,155 @@ mod tests {
         ];
 
         for (input, expected) in cases {
-            let rewritten = as_data_skipping_predicate(&input).unwrap();
+            let rewritten = rewrite(&input).unwrap();
             assert_eq!(rewritten, expected)
         }
     }
+
+    #[test]
+    fn test_rewrite_in_list() {
+        let bounds = |value: i32| {
+            Expr::and_from([
+                Expr::le(Expr::column("minValues.a"), Expr::literal(value)),
+                Expr::ge(Expr::column("maxValues.a"), Expr::literal(value)),
+            ])
+        };
+        let rewritten = rewrite(&Expr::column("a").is_in([1, 2])).unwrap();
+        assert_eq!(rewritten, Expr::or_from([bounds(1), bounds(2)]));
+        // nothing is in an empty list
+        let rewritten = rewrite(&Expr::column("a").is_in([0; 0])).unwrap();
+        assert_eq!(rewritten, Expr::or_from([]));
+        // files with a value in the list can still have other values
+        let not_in = Expr::not(Expr::column("a").is_in([1, 2]));
+        assert_eq!(rewrite(&not_in), None);
+        // also when the `IN` is nested in the negated expression
+        let n_gt_5 = || Expr::column("n").gt(Expr::literal(5i64));
+        for nested in [
+            Expr::not(Expr::and(Expr::column("a").is_in([1, 2]), n_gt_5())),
+            Expr::not(Expr::or(Expr::column("a").is_in([1, 2]), n_gt_5())),
+            Expr::not(Expr::not(Expr::not(Expr::column("a").is_in([1, 2])))),
+        ] {
+            assert_eq!(rewrite(&nested), None, "{nested}");
+            // which drops it from a conjunction, but keeps the other operands
+            let rewritten = rewrite(&Expr::and(n_gt_5(), nested)).unwrap();
+            let expected =
+                Expr::and_from([Expr::gt(Expr::column("maxValues.n"), Expr::literal(5i64))]);
+            assert_eq!(rewritten, expected);
+        }
+    }
+
+    #[test]
+    fn test_rewrite_boolean_column() {
+        let column = Expr::column("a");
+        let min_col = Expr::column("minValues.a");
+        let max_col = Expr::column("maxValues.a");
+        let bounds = |value: bool| {
+            Expr::and_from([
+                Expr::le(min_col.clone(), Expr::literal(value)),
+                Expr::ge(max_col.clone(), Expr::literal(value)),
+            ])
+        };
+
+        let rewritten = rewrite(&column).unwrap();
+        assert_eq!(rewritten, bounds(true));
+        let rewritten = rewrite(&!column.clone()).unwrap();
+        assert_eq!(rewritten, bounds(false));
+        let rewritten = rewrite(&column.clone().and(Expr::column("b"))).unwrap();
+        assert_eq!(
+            rewritten,
+            Expr::and_from([
+                bounds(true),
+                Expr::and_from([
+                    Expr::le(Expr::column("minValues.b"), Expr::literal(true)),
+                    Expr::ge(Expr::column("maxValues.b"), Expr::literal(true)),
+                ]),
+            ])
+        );
+
+        // a bare column that isn't a boolean can't skip anything
+        assert_eq!(rewrite(&Expr::column("n")), None);
+        assert_eq!(rewrite(&!Expr::column("n")), None);
+        let rewritten = rewrite(&column.and(Expr::column("n"))).unwrap();
+        assert_eq!(rewritten, Expr::and_from([bounds(true)]));
+    }
+
+    #[test]
+    fn test_decimal_stats_with_different_scale() {
+        use crate::expressions::Scalar;
+
+        let table_schema = Arc::new(StructType::new(vec![StructField::new(
+            "d",
+            DataType::decimal(38, 10).unwrap(),
+            true,
+        )]));
+        // the stats have the scale of the column, unlike the literal, and are just past it, so
+        // rescaling them to the literal's scale would round them to its value
+        let stats = [
+            Some(
+                r#"{"numRecords":1,"minValues":{"d":1.4999999999},"maxValues":{"d":1.5000000001}}"#,
+            ),
+            Some(r#"{"numRecords":1,"minValues":{"d":1.0},"maxValues":{"d":1.5}}"#),
+            Some(r#"{"numRecords":1,"minValues":{"d":1.5},"maxValues":{"d":2.0}}"#),
+        ];
+        let literal = Expr::literal(Scalar::Decimal(150, 5, 2));
+
+        let predicate = Expr::column("d").gt(literal.clone());
+        assert_eq!(
+            selection(&table_schema, &stats, predicate),
+            vec![true, false, true]
+        );
+        let predicate = Expr::column("d").lt(literal.clone());
+        assert_eq!(
+            selection(&table_schema, &stats, predicate),
+            vec![true, true, false]
+        );
+        let predicate = Expr::column("d").eq(literal);
+        assert_eq!(
+            selection(&table_schema, &stats, predicate),
+            vec![true, true, true]
+        );
+    }
+
+    /// The predicate shapes that can skip files, evaluated against the stats of a file with
+    /// values in [1, 5], one with values in [10, 20] and a null, and one without stats, which must
+    /// never be skipped.
+    #[test]
+    fn test_supported_predicate_shapes() {
+        let table_schema = Arc::new(StructType::new(vec![StructField::new(
+            "x",
+            DataType::INTEGER,
+            true,
+        )]));
+        let stats = [
+            Some(r#"{"numRecords":3,"nullCount":{"x":0},"minValues":{"x":1},"maxValues":{"x":5}}"#),
+            Some(
+                r#"{"numRecords":3,"nullCount":{"x":1},"minValues":{"x":10},"maxValues":{"x":20}}"#,
+            ),
+            None,
+        ];
+
+        let x = || Expr::column("x");
+        let cases = [
+            (x().eq(Expr::literal(7)), [false, false, true]),
+            (x().eq(Expr::literal(1)), [true, false, true]),
+            (x().lt(Expr::literal(5)), [true, false, true]),
+            (x().lt_eq(Expr::literal(5)), [true, false, true]),
+            (x().gt(Expr::literal(10)), [false, true, true]),
+            (x().gt_eq(Expr::literal(10)), [false, true, true]),
+            (Expr::literal(10).lt(x()), [false, true, true]),
+            (
+                x().gt(Expr::literal(3)).and(x().lt(Expr::literal(8))),
+                [true, false, true],
+            ),
+            (
+                x().lt(Expr::literal(0)).or(x().gt(Expr::literal(15))),
+                [false, true, true],
+            ),
+            (x().is_null(), [false, true, true]),
+        ];
+        for (predicate, expected) in cases {
+            let selection = selection(&table_schema, &stats, predicate.clone());
+            assert_eq!(selection, expected, "{predicate}");
+        }
+    }
 }
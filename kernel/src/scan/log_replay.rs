@@ -1,26 +1,174 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 
 use either::Either;
+use itertools::Itertools;
 use lazy_static::lazy_static;
+use percent_encoding::percent_decode_str;
 use tracing::debug;
+use url::Url;
 
 use super::data_skipping::DataSkippingFilter;
 use super::ScanData;
-use crate::actions::{get_log_schema, ADD_NAME, REMOVE_NAME};
+use crate::actions::{get_log_schema_for, ActionType};
 use crate::actions::{visitors::AddVisitor, visitors::RemoveVisitor, Add, Remove};
+use crate::column_mapping::ColumnMappingMode;
 use crate::engine_data::{GetData, TypedGetData};
 use crate::expressions::Expression;
 use crate::schema::{DataType, MapType, SchemaRef, StructField, StructType};
-use crate::{DataVisitor, DeltaResult, Engine, EngineData, ExpressionHandler};
+use crate::utils::require;
+use crate::{
+    DataVisitor, DeltaResult, Engine, EngineData, Error, ExpressionEvaluator, ExpressionHandler,
+    FileMeta, ParquetHandler,
+};
 
-struct LogReplayScanner {
+/// Tracks the (data file path, deletion vector unique id) pairs of the file actions seen so far
+/// during log replay. Log replay goes from the newest commit to the oldest and then the checkpoint,
+/// so this is how it filters out files that a newer commit removed (or re-added with a different
+/// deletion vector). The set grows with the number of files in the table, and is the largest
+/// memory cost of replaying the log of a table with very many files, so engines can provide their
+/// own (e.g. one that spills to disk) with [`crate::scan::ScanBuilder::with_file_deduplicator`].
+pub trait FileDeduplicator: Send {
+    /// Whether the file action for `path` and `dv_unique_id` was recorded with [`Self::insert`]
+    fn contains(&self, path: &str, dv_unique_id: Option<&str>) -> bool;
+
+    /// Record the file action for `path` and `dv_unique_id`
+    fn insert(&mut self, path: &str, dv_unique_id: Option<&str>);
+}
+
+/// The default [`FileDeduplicator`], which keeps every pair in memory. Most files have no deletion
+/// vector, so their paths are kept on their own, without the overhead of an (empty) id.
+#[derive(Debug, Default)]
+pub struct DefaultFileDeduplicator {
+    without_dv: HashSet<Box<str>>,
+    /// The deletion vector unique ids seen for each path that had any
+    with_dv: HashMap<Box<str>, Vec<Box<str>>>,
+}
+
+impl FileDeduplicator for DefaultFileDeduplicator {
+    fn contains(&self, path: &str, dv_unique_id: Option<&str>) -> bool {
+        match dv_unique_id {
+            None => self.without_dv.contains(path),
+            Some(dv_unique_id) => self
+                .with_dv
+                .get(path)
+                .is_some_and(|ids| ids.iter().any(|id| id.as_ref() == dv_unique_id)),
+        }
+    }
+
+    fn insert(&mut self, path: &str, dv_unique_id: Option<&str>) {
+        match dv_unique_id {
+            None => {
+                self.without_dv.insert(path.into());
+            }
+            Some(dv_unique_id) => {
+                let ids = self.with_dv.entry(path.into()).or_default();
+                if !ids.iter().any(|id| id.as_ref() == dv_unique_id) {
+                    ids.push(dv_unique_id.into());
+                }
+            }
+        }
+    }
+}
+
+/// How log replay handles a file that an older commit (or the checkpoint) adds again, without a
+/// commit in between removing it. A correct writer never does this, but a malformed log can.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum DuplicateAddPolicy {
+    /// Keep the newest Add of the file and ignore the older ones, which is the usual Delta
+    /// semantics
+    #[default]
+    LastWins,
+    /// Fail with an [`Error::DuplicateAdd`], to detect corrupt logs. This tracks the Adds of
+    /// every (path, deletion vector) pair in the log, in addition to the [`FileDeduplicator`] of
+    /// the replay. Files that data skipping filters out are checked as well, so whether a scan
+    /// fails doesn't depend on its predicate.
+    Strict,
+}
+
+pub(crate) struct LogReplayScanner {
     filter: Option<DataSkippingFilter>,
 
-    /// A set of (data file path, dv_unique_id) pairs that have been seen thus
-    /// far in the log. This is used to filter out files with Remove actions as
-    /// well as duplicate entries in the log.
-    seen: HashSet<(String, Option<String>)>,
+    /// If set, data skipping for files without stats in the log uses stats read from the parquet
+    /// footers of those files instead.
+    footer_stats: Option<FooterStatsFallback>,
+
+    /// If set, check that the partition values of every Add match the partition directories of
+    /// its path.
+    validate_partition_paths: bool,
+
+    /// The (data file path, dv_unique_id) pairs that have been seen thus far in the log. This is
+    /// used to filter out files with Remove actions as well as duplicate entries in the log.
+    seen: Box<dyn FileDeduplicator>,
+
+    /// The (data file path, dv_unique_id) pairs added by the commits replayed so far that no older
+    /// commit has removed (yet), if duplicate Adds are an error (see
+    /// [`DuplicateAddPolicy::Strict`]).
+    unremoved_adds: Option<HashSet<(String, Option<String>)>>,
+}
+
+struct FooterStatsFallback {
+    parquet_handler: Arc<dyn ParquetHandler>,
+    table_root: Url,
+    /// If the table has column mapping, the stats schema of the data skipping filter with the
+    /// physical names of the columns, which the footers are read with, and an evaluator that
+    /// renames the stats read back to the logical names the filter expects
+    physical_stats: Option<(SchemaRef, Arc<dyn ExpressionEvaluator>)>,
+}
+
+/// The stats schema (see [`DataSkippingFilter::stats_schema`]) with the physical names of the
+/// columns in `column_mapping_mode`, and the expression that evaluates stats of that schema to
+/// `stats_schema`, i.e. renames the columns back to their logical names. The physical names are
+/// those of the fields of `minValues`, which are the fields of the table schema.
+fn physical_stats_schema(
+    stats_schema: &StructType,
+    column_mapping_mode: ColumnMappingMode,
+) -> DeltaResult<(StructType, Expression)> {
+    let Some(DataType::Struct(data_fields)) =
+        stats_schema.field("minValues").map(|f| f.data_type())
+    else {
+        return Err(Error::generic("Stats schema has no minValues"));
+    };
+    let physical_names: HashMap<_, _> = data_fields
+        .fields()
+        .map(|field| Ok((field.name(), field.physical_name(column_mapping_mode)?)))
+        .try_collect::<_, _, Error>()?;
+    let physical_name = |field: &StructField| -> DeltaResult<String> {
+        physical_names
+            .get(field.name())
+            .map(|name| name.to_string())
+            .ok_or_else(|| Error::missing_column(field.name()))
+    };
+    let mut physical_fields = vec![];
+    let mut rename_exprs = vec![];
+    for stats_field in stats_schema.fields() {
+        match stats_field.data_type() {
+            DataType::Struct(inner) => {
+                let (fields, exprs): (Vec<_>, Vec<_>) = inner
+                    .fields()
+                    .map(|field| -> DeltaResult<_> {
+                        let name = physical_name(field)?;
+                        let expr = Expression::nested_column([stats_field.name(), &name]);
+                        Ok((field.with_name(name), expr))
+                    })
+                    .process_results(|iter| iter.unzip())?;
+                physical_fields.push(StructField::new(
+                    stats_field.name(),
+                    StructType::new(fields),
+                    stats_field.is_nullable(),
+                ));
+                rename_exprs.push(Expression::Struct(exprs));
+            }
+            _ => {
+                physical_fields.push(stats_field.clone());
+                rename_exprs.push(Expression::column(stats_field.name()));
+            }
+        }
+    }
+    Ok((
+        StructType::new(physical_fields),
+        Expression::Struct(rename_exprs),
+    ))
 }
 
 #[derive(Default)]
@@ -50,10 +198,10 @@ impl DataVisitor for AddRemoveVisitor {
             // Add will have a path at index 0 if it is valid
             if let Some(path) = getters[0].get_opt(i, "add.path")? {
                 // Keep the file unless the selection vector is present and is false for this row
-                if !self
+                if self
                     .selection_vector
                     .as_ref()
-                    .is_some_and(|selection| !selection[i])
+                    .is_none_or(|selection| selection[i])
                 {
                     self.adds.push((
                         AddVisitor::visit_add(i, path, &getters[..ADD_FIELD_COUNT])?,
@@ -110,11 +258,133 @@ lazy_static! {
 
 impl LogReplayScanner {
     /// Create a new [`LogReplayScanner`] instance
-    fn new(engine: &dyn Engine, table_schema: &SchemaRef, predicate: &Option<Expression>) -> Self {
-        Self {
-            filter: DataSkippingFilter::new(engine, table_schema, predicate),
-            seen: Default::default(),
+    pub(crate) fn new(
+        engine: &dyn Engine,
+        table_schema: &SchemaRef,
+        predicate: &Option<Expression>,
+    ) -> DeltaResult<Self> {
+        Ok(Self {
+            filter: DataSkippingFilter::new(engine, table_schema, predicate)?,
+            footer_stats: None,
+            validate_partition_paths: false,
+            seen: Box::<DefaultFileDeduplicator>::default(),
+            unremoved_adds: None,
+        })
+    }
+
+    /// Handle files that are added more than once without being removed in between according to
+    /// `policy`
+    pub(crate) fn with_duplicate_add_policy(mut self, policy: DuplicateAddPolicy) -> Self {
+        self.unremoved_adds = match policy {
+            DuplicateAddPolicy::LastWins => None,
+            DuplicateAddPolicy::Strict => Some(HashSet::new()),
+        };
+        self
+    }
+
+    /// Use `deduplicator` to track the file actions seen during replay
+    pub(crate) fn with_deduplicator(mut self, deduplicator: Box<dyn FileDeduplicator>) -> Self {
+        self.seen = deduplicator;
+        self
+    }
+
+    /// Use the `engine`'s [`ParquetHandler`] to read stats from the footers of files (relative to
+    /// `table_root`) that have no stats in the log, so they can still be skipped. The columns of
+    /// the files have their physical names in `column_mapping_mode`.
+    pub(crate) fn with_footer_stats_fallback(
+        mut self,
+        engine: &dyn Engine,
+        table_root: &Url,
+        column_mapping_mode: ColumnMappingMode,
+    ) -> DeltaResult<Self> {
+        let physical_stats = match (&self.filter, column_mapping_mode) {
+            (Some(filter), ColumnMappingMode::Id | ColumnMappingMode::Name) => {
+                let logical_schema = filter.stats_schema();
+                let (physical_schema, rename_expr) =
+                    physical_stats_schema(logical_schema, column_mapping_mode)?;
+                let physical_schema = Arc::new(physical_schema);
+                let evaluator = engine.get_expression_handler().get_evaluator(
+                    physical_schema.clone(),
+                    rename_expr,
+                    logical_schema.as_ref().clone().into(),
+                )?;
+                Some((physical_schema, evaluator))
+            }
+            _ => None,
+        };
+        self.footer_stats = Some(FooterStatsFallback {
+            parquet_handler: engine.get_parquet_handler(),
+            table_root: table_root.clone(),
+            physical_stats,
+        });
+        Ok(self)
+    }
+
+    /// Fail the scan if the partition values of an Add don't match the Hive-style partition
+    /// directories (e.g. `day=2024-01-01/`) of its path. See [`validate_partition_path`].
+    pub(crate) fn with_partition_path_validation(mut self) -> Self {
+        self.validate_partition_paths = true;
+        self
+    }
+
+    /// Apply data skipping to the `adds` that have no stats in the log, using stats read from
+    /// their parquet footers instead. Returns whether each of the `adds` should be kept. Adds with
+    /// stats in the log were already handled by the data skipping filter and are always kept.
+    fn apply_footer_stats(&self, adds: &[(Add, usize)]) -> DeltaResult<Vec<bool>> {
+        let mut keep = vec![true; adds.len()];
+        let (Some(filter), Some(footer_stats)) = (&self.filter, &self.footer_stats) else {
+            return Ok(keep);
+        };
+        let missing_stats: Vec<_> = adds
+            .iter()
+            .positions(|(add, _)| add.stats.is_none())
+            .collect();
+        if missing_stats.is_empty() {
+            return Ok(keep);
+        }
+        let files: Vec<_> = missing_stats
+            .iter()
+            .map(|&i| {
+                let add = &adds[i].0;
+                Ok(FileMeta {
+                    location: footer_stats.table_root.join(&add.path)?,
+                    last_modified: add.modification_time,
+                    size: add.size as usize,
+                    e_tag: None,
+                    version: None,
+                })
+            })
+            .try_collect::<_, _, Error>()?;
+        debug!("Reading footer stats for {} files", files.len());
+        let stats_schema = match footer_stats.physical_stats {
+            Some((ref physical_schema, _)) => physical_schema.clone(),
+            None => filter.stats_schema().clone(),
+        };
+        let Some(stats) = footer_stats
+            .parquet_handler
+            .read_footer_stats(&files, stats_schema)?
+        else {
+            // the engine can't read footer stats, so we can't skip these files
+            return Ok(keep);
+        };
+        let stats = match footer_stats.physical_stats {
+            Some((_, ref rename_evaluator)) => rename_evaluator.evaluate(stats.as_ref())?,
+            None => stats,
+        };
+        let mut selection_vector = Vec::with_capacity(files.len());
+        filter.apply_to_stats(stats.as_ref(), &mut selection_vector)?;
+        require!(
+            selection_vector.len() == files.len(),
+            Error::generic(format!(
+                "Expected footer stats for {} files, but got {}",
+                files.len(),
+                selection_vector.len()
+            ))
+        );
+        for (i, selected) in missing_stats.into_iter().zip(selection_vector) {
+            keep[i] = selected;
         }
+        Ok(keep)
     }
 
     /// Extract Add actions from a single batch. This will filter out rows that
@@ -134,27 +404,47 @@ impl LogReplayScanner {
             .transpose()?;
 
         let adds = self.setup_batch_process(selection_vector, actions, is_log_batch)?;
+        // only the footers of the files that are still active need to be read
+        let adds: Vec<_> = adds
+            .into_iter()
+            .filter(|(add, _)| self.check_and_record_seen(add, is_log_batch))
+            .collect();
+        let keep = self.apply_footer_stats(&adds)?;
 
-        adds.into_iter()
-            .filter_map(|(add, _)| {
-                // Note: each (add.path + add.dv_unique_id()) pair has a
-                // unique Add + Remove pair in the log. For example:
-                // https://github.com/delta-io/delta/blob/master/spark/src/test/resources/delta/table-with-dv-large/_delta_log/00000000000000000001.json
-                if !self.seen.contains(&(add.path.clone(), add.dv_unique_id())) {
-                    debug!("Found file: {}, is log {}", &add.path, is_log_batch);
-                    if is_log_batch {
-                        // Remember file actions from this batch so we can ignore duplicates
-                        // as we process batches from older commit and/or checkpoint files. We
-                        // don't need to track checkpoint batches because they are already the
-                        // oldest actions and can never replace anything.
-                        self.seen.insert((add.path.clone(), add.dv_unique_id()));
-                    }
-                    Some(Ok(add))
-                } else {
-                    None
-                }
-            })
-            .collect()
+        Ok(adds
+            .into_iter()
+            .zip(keep)
+            .filter_map(|((add, _), keep)| keep.then_some(add))
+            .collect())
+    }
+
+    /// Whether `add` hasn't been seen yet, i.e. isn't removed or replaced by a newer action, in
+    /// which case it's recorded as seen (if it's from a commit). Files that are then skipped by
+    /// their footer stats are still recorded, so that older actions of them aren't returned.
+    fn check_and_record_seen(&mut self, add: &Add, is_log_batch: bool) -> bool {
+        // Note: each (add.path + add.dv_unique_id()) pair has a
+        // unique Add + Remove pair in the log. For example:
+        // https://github.com/delta-io/delta/blob/master/spark/src/test/resources/delta/table-with-dv-large/_delta_log/00000000000000000001.json
+        let dv_unique_id = add.dv_unique_id();
+        if self.seen.contains(&add.path, dv_unique_id.as_deref()) {
+            debug!(
+                "Filtering out Add due to it being removed {}, is log {is_log_batch}",
+                add.path
+            );
+            return false;
+        }
+        debug!(
+            "Found file: ({}, {:?}), is log {is_log_batch}",
+            add.path, dv_unique_id,
+        );
+        if is_log_batch {
+            // Remember file actions from this batch so we can ignore duplicates
+            // as we process batches from older commit and/or checkpoint files. We
+            // don't need to track checkpoint batches because they are already the
+            // oldest actions and can never replace anything.
+            self.seen.insert(&add.path, dv_unique_id.as_deref());
+        }
+        true
     }
 
     fn get_add_transform_expr(&self) -> Expression {
@@ -189,39 +479,30 @@ impl LogReplayScanner {
         };
 
         let adds = self.setup_batch_process(filter_vector, actions, is_log_batch)?;
-
-        for (add, index) in adds.into_iter() {
-            // Note: each (add.path + add.dv_unique_id()) pair has a
-            // unique Add + Remove pair in the log. For example:
-            // https://github.com/delta-io/delta/blob/master/spark/src/test/resources/delta/table-with-dv-large/_delta_log/00000000000000000001.json
-            if !self.seen.contains(&(add.path.clone(), add.dv_unique_id())) {
-                debug!(
-                    "Including file in scan: ({}, {:?}), is log {is_log_batch}",
-                    add.path,
-                    add.dv_unique_id(),
-                );
-                if is_log_batch {
-                    // Remember file actions from this batch so we can ignore duplicates
-                    // as we process batches from older commit and/or checkpoint files. We
-                    // don't need to track checkpoint batches because they are already the
-                    // oldest actions and can never replace anything.
-                    self.seen.insert((add.path.clone(), add.dv_unique_id()));
-                }
-                selection_vector[index] = true;
+        let mut active_adds = vec![];
+        for (add, index) in adds {
+            if self.check_and_record_seen(&add, is_log_batch) {
+                active_adds.push((add, index));
             } else {
-                debug!(
-                    "Filtering out Add due to it being removed {}, is log {is_log_batch}",
-                    add.path
-                );
+                // the data skipping filter may have selected it
+                selection_vector[index] = false;
+            }
+        }
+        // only the footers of the files that are still active need to be read
+        let keep = self.apply_footer_stats(&active_adds)?;
+        for ((add, index), keep) in active_adds.into_iter().zip(keep) {
+            if !keep {
+                debug!("Filtering out Add due to its footer stats {}", add.path);
             }
+            selection_vector[index] = keep;
         }
 
         let result = expression_handler
             .get_evaluator(
-                get_log_schema().project(&[ADD_NAME])?,
+                get_log_schema_for(&[ActionType::Add])?,
                 self.get_add_transform_expr(),
                 SCAN_ROW_DATATYPE.clone(),
-            )
+            )?
             .evaluate(actions)?;
         Ok((result, selection_vector))
     }
@@ -234,27 +515,156 @@ impl LogReplayScanner {
         is_log_batch: bool,
     ) -> DeltaResult<Vec<(Add, usize)>> {
         let schema_to_use = if is_log_batch {
-            // NB: We _must_ pass these in the order `ADD_NAME, REMOVE_NAME` as the visitor assumes
-            // the Add action comes first. The [`project`] method honors this order, so this works
-            // as long as we keep this order here.
-            get_log_schema().project(&[ADD_NAME, REMOVE_NAME])?
+            // NB: We _must_ pass these in the order `Add, Remove` as the visitor assumes the Add
+            // action comes first. [`get_log_schema_for`] honors this order, so this works as long
+            // as we keep this order here.
+            get_log_schema_for(&[ActionType::Add, ActionType::Remove])?
         } else {
             // All checkpoint actions are already reconciled and Remove actions in checkpoint files
             // only serve as tombstones for vacuum jobs. So no need to load them here.
-            get_log_schema().project(&[ADD_NAME])?
+            get_log_schema_for(&[ActionType::Add])?
+        };
+        // duplicate Adds are also detected in the files data skipping filters out, so whether a
+        // scan fails doesn't depend on its predicate
+        let (visitor_selection, skipping_selection) = match self.unremoved_adds {
+            Some(_) => (None, selection_vector),
+            None => (selection_vector, None),
         };
-        let mut visitor = AddRemoveVisitor::new(selection_vector, is_log_batch);
+        let mut visitor = AddRemoveVisitor::new(visitor_selection, is_log_batch);
         actions.extract(schema_to_use, &mut visitor)?;
 
+        if let Some(ref mut unremoved_adds) = self.unremoved_adds {
+            check_duplicate_adds(unremoved_adds, &visitor.adds, &visitor.removes)?;
+        }
+        if let Some(selection) = skipping_selection {
+            visitor.adds.retain(|&(_, index)| selection[index]);
+        }
         for remove in visitor.removes.into_iter() {
             let dv_id = remove.dv_unique_id();
-            self.seen.insert((remove.path, dv_id));
+            self.seen.insert(&remove.path, dv_id.as_deref());
+        }
+        if self.validate_partition_paths {
+            for (add, _) in &visitor.adds {
+                validate_partition_path(add)?;
+            }
         }
 
         Ok(visitor.adds)
     }
 }
 
+/// Fail if any of the `adds` of a batch was added by a newer batch (or earlier in the batch) and not
+/// removed since, then track the `adds` and stop tracking the files the batch `removes`, which
+/// older batches may add again.
+fn check_duplicate_adds(
+    unremoved_adds: &mut HashSet<(String, Option<String>)>,
+    adds: &[(Add, usize)],
+    removes: &[Remove],
+) -> DeltaResult<()> {
+    let mut batch_adds = HashSet::new();
+    for (add, _) in adds {
+        let key = (add.path.clone(), add.dv_unique_id());
+        if unremoved_adds.contains(&key) || !batch_adds.insert(key) {
+            return Err(Error::duplicate_add(&add.path));
+        }
+    }
+    for remove in removes {
+        unremoved_adds.remove(&(remove.path.clone(), remove.dv_unique_id()));
+    }
+    unremoved_adds.extend(batch_adds);
+    Ok(())
+}
+
+/// The directory name Hive-style writers use for null partition values
+const HIVE_DEFAULT_PARTITION: &str = "__HIVE_DEFAULT_PARTITION__";
+
+/// Check that every partition value of `add` matches the `name=value` directory of that partition
+/// column in its path. Both the path and the names and values in its directories are escaped, so
+/// they are unescaped before comparing. A null (or empty) value must be in the Hive default
+/// partition directory.
+fn validate_partition_path(add: &Add) -> DeltaResult<()> {
+    let path = percent_decode_str(&add.path).decode_utf8_lossy();
+    // the last segment is the file name, only the directories before it encode partition values
+    let dirs: HashMap<_, _> = path
+        .rsplit('/')
+        .skip(1)
+        .filter_map(|segment| segment.split_once('='))
+        .map(|(name, value)| {
+            let name = percent_decode_str(name).decode_utf8_lossy();
+            (name, percent_decode_str(value).decode_utf8_lossy())
+        })
+        .collect();
+    for (name, value) in &add.partition_values {
+        let expected = if value.is_empty() {
+            HIVE_DEFAULT_PARTITION
+        } else {
+            value
+        };
+        match dirs.get(name.as_str()) {
+            Some(dir_value) if dir_value == expected => {}
+            Some(dir_value) => {
+                return Err(Error::invalid_partition_path(format!(
+                    "partition value {name}={value} of {} doesn't match its directory {name}={dir_value}",
+                    add.path
+                )))
+            }
+            None => {
+                return Err(Error::invalid_partition_path(format!(
+                    "{} has no directory for partition value {name}={value}",
+                    add.path
+                )))
+            }
+        }
+    }
+    Ok(())
+}
+
+impl LogReplayScanner {
+    /// Turn this scanner into an iterator of `Adds`, given an iterator of (engine_data, bool)
+    /// tuples. See [`log_replay_iter`].
+    pub(crate) fn into_add_iter(
+        mut self,
+        action_iter: impl Iterator<Item = DeltaResult<(Box<dyn EngineData>, bool)>> + Send,
+    ) -> impl Iterator<Item = DeltaResult<Add>> {
+        action_iter.flat_map(move |actions| match actions {
+            Ok((batch, is_log_batch)) => match self.process_batch(batch.as_ref(), is_log_batch) {
+                Ok(adds) => Either::Left(adds.into_iter().map(Ok)),
+                Err(err) => Either::Right(std::iter::once(Err(err))),
+            },
+            Err(err) => Either::Right(std::iter::once(Err(err))),
+        })
+    }
+
+    /// Turn this scanner into an iterator of `(engine_data, selection_vec)`, given an iterator of
+    /// (engine_data, bool) tuples. See [`scan_action_iter`].
+    pub(crate) fn into_scan_data_iter(
+        mut self,
+        engine: &dyn Engine,
+        action_iter: impl Iterator<Item = DeltaResult<(Box<dyn EngineData>, bool)>>,
+    ) -> impl Iterator<Item = DeltaResult<ScanData>> {
+        let expression_handler = engine.get_expression_handler();
+        action_iter
+            .map(move |action_res| {
+                action_res.and_then(|(batch, is_log_batch)| {
+                    self.process_scan_batch(
+                        expression_handler.as_ref(),
+                        batch.as_ref(),
+                        is_log_batch,
+                    )
+                })
+            })
+            .filter(|action_res| {
+                match action_res {
+                    Ok((_, sel_vec)) => {
+                        // don't bother returning it if everything is filtered out
+                        sel_vec.contains(&true)
+                    }
+                    Err(_) => true, // just pass through errors
+                }
+            })
+    }
+}
+
 /// Given an iterator of (engine_data, bool) tuples and a predicate, returns an iterator of `Adds`.
 /// The boolean flag indicates whether the record batch is a log or checkpoint batch.
 pub fn log_replay_iter(
@@ -262,18 +672,8 @@ pub fn log_replay_iter(
     action_iter: impl Iterator<Item = DeltaResult<(Box<dyn EngineData>, bool)>> + Send,
     table_schema: &SchemaRef,
     predicate: &Option<Expression>,
-) -> impl Iterator<Item = DeltaResult<Add>> {
-    let mut log_scanner = LogReplayScanner::new(engine, table_schema, predicate);
-
-    action_iter.flat_map(move |actions| match actions {
-        Ok((batch, is_log_batch)) => {
-            match log_scanner.process_batch(batch.as_ref(), is_log_batch) {
-                Ok(adds) => Either::Left(adds.into_iter().map(Ok)),
-                Err(err) => Either::Right(std::iter::once(Err(err))),
-            }
-        }
-        Err(err) => Either::Right(std::iter::once(Err(err))),
-    })
+) -> DeltaResult<impl Iterator<Item = DeltaResult<Add>>> {
+    Ok(LogReplayScanner::new(engine, table_schema, predicate)?.into_add_iter(action_iter))
 }
 
 /// Given an iterator of (engine_data, bool) tuples and a predicate, returns an iterator of
@@ -285,38 +685,31 @@ pub fn scan_action_iter(
     action_iter: impl Iterator<Item = DeltaResult<(Box<dyn EngineData>, bool)>>,
     table_schema: &SchemaRef,
     predicate: &Option<Expression>,
-) -> impl Iterator<Item = DeltaResult<ScanData>> {
-    let mut log_scanner = LogReplayScanner::new(engine, table_schema, predicate);
-    let expression_handler = engine.get_expression_handler();
-    action_iter
-        .map(move |action_res| {
-            action_res.and_then(|(batch, is_log_batch)| {
-                log_scanner.process_scan_batch(
-                    expression_handler.as_ref(),
-                    batch.as_ref(),
-                    is_log_batch,
-                )
-            })
-        })
-        .filter(|action_res| {
-            match action_res {
-                Ok((_, sel_vec)) => {
-                    // don't bother returning it if everything is filtered out
-                    sel_vec.contains(&true)
-                }
-                Err(_) => true, // just pass through errors
-            }
-        })
+) -> DeltaResult<impl Iterator<Item = DeltaResult<ScanData>>> {
+    let scanner = LogReplayScanner::new(engine, table_schema, predicate)?;
+    Ok(scanner.into_scan_data_iter(engine, action_iter))
 }
 
 #[cfg(test)]
 mod tests {
     use std::collections::HashMap;
 
+    use std::sync::Arc;
+
+    use super::{
+        validate_partition_path, DefaultFileDeduplicator, DuplicateAddPolicy, FileDeduplicator,
+        LogReplayScanner,
+    };
+    use crate::actions::Add;
+    use crate::engine::sync::SyncEngine;
     use crate::scan::{
         state::DvInfo,
-        test_utils::{add_batch_simple, add_batch_with_remove, run_with_validate_callback},
+        test_utils::{
+            actions_batch, add_batch_simple, add_batch_with_remove, run_with_validate_callback,
+        },
     };
+    use crate::schema::{DataType, StructField, StructType};
+    use crate::{DeltaResult, Error, Expression};
 
     // dv-info is more complex to validate, we validate that works in the test for visit_scan_files
     // in state.rs
@@ -355,4 +748,145 @@ mod tests {
             validate_simple,
         );
     }
+
+    #[test]
+    fn test_default_file_deduplicator() {
+        let mut seen = DefaultFileDeduplicator::default();
+        seen.insert("a.parquet", None);
+        seen.insert("b.parquet", Some("dv1"));
+        seen.insert("b.parquet", Some("dv2"));
+        assert!(seen.contains("a.parquet", None));
+        assert!(!seen.contains("a.parquet", Some("dv1")));
+        assert!(!seen.contains("b.parquet", None));
+        assert!(seen.contains("b.parquet", Some("dv1")));
+        assert!(seen.contains("b.parquet", Some("dv2")));
+        assert!(!seen.contains("b.parquet", Some("dv3")));
+        assert!(!seen.contains("c.parquet", None));
+    }
+
+    const ADD_A: &str = r#"{"add":{"path":"a.parquet","partitionValues":{},"size":635,"modificationTime":0,"dataChange":true,"stats":"{\"numRecords\":1,\"minValues\":{\"value\":1},\"maxValues\":{\"value\":1},\"nullCount\":{\"value\":0}}"}}"#;
+    const REMOVE_A: &str =
+        r#"{"remove":{"path":"a.parquet","deletionTimestamp":0,"dataChange":true}}"#;
+    const ADD_B: &str = r#"{"add":{"path":"b.parquet","partitionValues":{},"size":635,"modificationTime":0,"dataChange":true}}"#;
+
+    /// Replay the commits (newest first) of `batches` with `policy`, returning the selection
+    /// vector of each batch
+    fn replay_with_policy(
+        batches: &[&[&str]],
+        policy: DuplicateAddPolicy,
+        predicate: Option<Expression>,
+    ) -> DeltaResult<Vec<Vec<bool>>> {
+        let engine = SyncEngine::new();
+        let table_schema = Arc::new(StructType::new(vec![StructField::new(
+            "value",
+            DataType::INTEGER,
+            true,
+        )]));
+        let batches: Vec<_> = batches
+            .iter()
+            .map(|batch| Ok((actions_batch(batch) as _, true)))
+            .collect();
+        LogReplayScanner::new(&engine, &table_schema, &predicate)
+            .unwrap()
+            .with_duplicate_add_policy(policy)
+            .into_scan_data_iter(&engine, batches.into_iter())
+            .map(|res| res.map(|(_, selection_vector)| selection_vector))
+            .collect()
+    }
+
+    #[test]
+    fn test_duplicate_add_policy() {
+        // a.parquet is added by two commits without being removed in between
+        let duplicate: &[&[&str]] = &[&[ADD_A, ADD_B], &[ADD_A]];
+        let selected = replay_with_policy(duplicate, DuplicateAddPolicy::LastWins, None).unwrap();
+        // the older Add is ignored, which filters out the whole older batch
+        assert_eq!(selected, [[true, true]]);
+        let res = replay_with_policy(duplicate, DuplicateAddPolicy::Strict, None);
+        assert!(matches!(res, Err(Error::DuplicateAdd(path)) if path == "a.parquet"));
+
+        // also if data skipping filters out the file, so it isn't read either way
+        let predicate = Expression::column("value").gt(Expression::literal(5));
+        let selected = replay_with_policy(
+            duplicate,
+            DuplicateAddPolicy::LastWins,
+            Some(predicate.clone()),
+        )
+        .unwrap();
+        assert_eq!(selected, [[false, true]]);
+        let res = replay_with_policy(duplicate, DuplicateAddPolicy::Strict, Some(predicate));
+        assert!(matches!(res, Err(Error::DuplicateAdd(path)) if path == "a.parquet"));
+
+        // also within a single commit
+        let duplicate: &[&[&str]] = &[&[ADD_A, ADD_A]];
+        let res = replay_with_policy(duplicate, DuplicateAddPolicy::Strict, None);
+        assert!(matches!(res, Err(Error::DuplicateAdd(_))));
+
+        // a file that is removed and then added again is not a duplicate
+        let readded: &[&[&str]] = &[&[ADD_A], &[REMOVE_A], &[ADD_A, ADD_B]];
+        for policy in [DuplicateAddPolicy::LastWins, DuplicateAddPolicy::Strict] {
+            let selected = replay_with_policy(readded, policy, None).unwrap();
+            assert_eq!(selected, [vec![true], vec![false, true]]);
+        }
+    }
+
+    fn add_with_partitions(path: &str, partition_values: &[(&str, &str)]) -> Add {
+        Add {
+            path: path.to_string(),
+            partition_values: partition_values
+                .iter()
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect(),
+            size: 0,
+            modification_time: 0,
+            data_change: true,
+            stats: None,
+            tags: None,
+            deletion_vector: None,
+            base_row_id: None,
+            default_row_commit_version: None,
+            clustering_provider: None,
+        }
+    }
+
+    #[test]
+    fn test_validate_partition_path() {
+        let valid = [
+            add_with_partitions("day=2024-01-01/part-0.parquet", &[("day", "2024-01-01")]),
+            add_with_partitions("a.parquet", &[]),
+            add_with_partitions(
+                "day=2024-01-01/hour=3/part-0.parquet",
+                &[("hour", "3"), ("day", "2024-01-01")],
+            ),
+            // the path is escaped, and so is the value in the directory name
+            add_with_partitions(
+                "ts=2024-01-01%2000%253A00/part-0.parquet",
+                &[("ts", "2024-01-01 00:00")],
+            ),
+            add_with_partitions(
+                "day=__HIVE_DEFAULT_PARTITION__/part-0.parquet",
+                &[("day", "")],
+            ),
+            add_with_partitions(
+                "s3://bucket/table/day=2024-01-01/part-0.parquet",
+                &[("day", "2024-01-01")],
+            ),
+        ];
+        for add in &valid {
+            validate_partition_path(add).unwrap();
+        }
+
+        let invalid = [
+            add_with_partitions("day=2024-01-02/part-0.parquet", &[("day", "2024-01-01")]),
+            add_with_partitions("part-0.parquet", &[("day", "2024-01-01")]),
+            add_with_partitions("2024-01-01/part-0.parquet", &[("day", "2024-01-01")]),
+            // the file name doesn't encode partition values
+            add_with_partitions("day=2024-01-01", &[("day", "2024-01-01")]),
+        ];
+        for add in &invalid {
+            assert!(matches!(
+                validate_partition_path(add),
+                Err(Error::InvalidPartitionPath(_))
+            ));
+        }
+    }
 }
@@ -1,60 +1,194 @@
 //! Functionality to create and execute scans (reads) over data stored in a delta table
 
-use std::sync::Arc;
+use std::collections::HashMap;
+use std::ops::Deref;
+use std::sync::{Arc, Mutex, MutexGuard};
 
-use itertools::Itertools;
+#[cfg(feature = "default-engine")]
+use futures::stream::{self, BoxStream, StreamExt};
+use indexmap::IndexMap;
+use itertools::{Either, Itertools};
+use roaring::RoaringTreemap;
+use serde::{Deserialize, Serialize};
 use tracing::debug;
 use url::Url;
 
-use self::log_replay::{log_replay_iter, scan_action_iter};
+use self::log_replay::{DuplicateAddPolicy, FileDeduplicator, LogReplayScanner};
+use self::partition_pruning::{normalize_partition_predicate, PartitionPruningFilter};
 use self::state::GlobalScanState;
 use crate::actions::deletion_vector::{treemap_to_bools, DeletionVectorDescriptor};
-use crate::actions::{get_log_schema, Add, ADD_NAME, REMOVE_NAME};
-use crate::column_mapping::ColumnMappingMode;
-use crate::expressions::{Expression, Scalar};
-use crate::schema::{DataType, Schema, SchemaRef, StructField, StructType};
+use crate::actions::{get_log_schema_for, ActionType, Add};
+use crate::column_mapping::{matches_parquet_field_ids, with_parquet_field_id, ColumnMappingMode};
+use crate::expressions::{ColumnName, Expression, Scalar};
+use crate::schema::{
+    ArrayType, DataType, MapType, PrimitiveType, Schema, SchemaRef, StructField, StructType,
+};
 use crate::snapshot::Snapshot;
-use crate::{DeltaResult, Engine, EngineData, Error, FileMeta};
+use crate::utils::{list_file, require};
+use crate::{
+    DeltaResult, Engine, EngineData, Error, ExpressionHandler, FileMeta, FileSystemClient,
+};
 
-mod data_skipping;
+pub(crate) mod data_skipping;
 pub mod log_replay;
+mod partition_pruning;
 pub mod state;
 
+pub use self::partition_pruning::PARTITION_STRUCT_COLUMN;
+
 /// Builder to scan a snapshot of a table.
 pub struct ScanBuilder {
     snapshot: Arc<Snapshot>,
     schema: Option<SchemaRef>,
+    columns: Option<Vec<String>>,
+    output_schema: Option<SchemaRef>,
     predicate: Option<Expression>,
+    field_metadata_mode: FieldMetadataMode,
+    footer_stats_fallback: bool,
+    partition_path_validation: bool,
+    schema_override: bool,
+    read_ahead: usize,
+    file_deduplicator: Option<FileDeduplicatorFactory>,
+    duplicate_add_policy: DuplicateAddPolicy,
+    residual_applied: bool,
+    min_pruned_fraction: Option<f64>,
+    partition_columns: bool,
 }
 
+/// Creates the [`FileDeduplicator`] for each log replay of a scan
+type FileDeduplicatorFactory = Arc<dyn Fn() -> Box<dyn FileDeduplicator> + Send + Sync>;
+
 impl std::fmt::Debug for ScanBuilder {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> Result<(), std::fmt::Error> {
         f.debug_struct("ScanBuilder")
             .field("schema", &self.schema)
+            .field("schema_override", &self.schema_override)
+            .field("columns", &self.columns)
+            .field("output_schema", &self.output_schema)
             .field("predicate", &self.predicate)
+            .field("field_metadata_mode", &self.field_metadata_mode)
+            .field("footer_stats_fallback", &self.footer_stats_fallback)
+            .field("partition_path_validation", &self.partition_path_validation)
+            .field("read_ahead", &self.read_ahead)
+            .field("file_deduplicator", &self.file_deduplicator.is_some())
+            .field("duplicate_add_policy", &self.duplicate_add_policy)
+            .field("residual_applied", &self.residual_applied)
+            .field("min_pruned_fraction", &self.min_pruned_fraction)
+            .field("partition_columns", &self.partition_columns)
             .finish()
     }
 }
 
+/// Controls which metadata the fields of the data returned by a scan carry. See
+/// [`ScanBuilder::with_field_metadata`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FieldMetadataMode {
+    /// Fields (including nested ones) carry the metadata of the corresponding field in the scan's
+    /// logical schema. This gives every batch of a scan the same metadata, regardless of which
+    /// file it was read from.
+    #[default]
+    FromLogicalSchema,
+    /// Fields read from a data file keep whatever metadata the file has, and no metadata is added.
+    /// Note that this means batches read from different files may have different metadata, and
+    /// that fields the kernel needs to compute (e.g. partition columns) have no metadata.
+    Preserve,
+    /// Fields (including nested ones) carry no metadata at all
+    Strip,
+}
+
 impl ScanBuilder {
     /// Create a new [`ScanBuilder`] instance.
     pub fn new(snapshot: impl Into<Arc<Snapshot>>) -> Self {
         Self {
             snapshot: snapshot.into(),
             schema: None,
+            columns: None,
+            output_schema: None,
             predicate: None,
+            field_metadata_mode: FieldMetadataMode::default(),
+            footer_stats_fallback: false,
+            partition_path_validation: false,
+            schema_override: false,
+            read_ahead: 0,
+            file_deduplicator: None,
+            duplicate_add_policy: DuplicateAddPolicy::default(),
+            residual_applied: false,
+            min_pruned_fraction: None,
+            partition_columns: true,
         }
     }
 
     /// Provide [`Schema`] for columns to select from the [`Snapshot`].
     ///
     /// A table with columns `[a, b, c]` could have a scan which reads only the first
-    /// two columns by using the schema `[a, b]`.
+    /// two columns by using the schema `[a, b]`. Every (possibly nested) field of the schema must
+    /// be a field of the table schema, or [`ScanBuilder::build`] fails with an
+    /// [`Error::MissingColumn`]. A struct field may have only some of the fields of the table's
+    /// struct, in which case only those are read.
+    ///
+    /// This is the read schema of the scan, which may include columns that are only needed to
+    /// filter the data and should not be part of the output (see
+    /// [`ScanBuilder::with_output_schema`]). This replaces any columns set with
+    /// [`ScanBuilder::with_columns`], and vice versa.
     ///
     /// [`Schema`]: crate::schema::Schema
     /// [`Snapshot`]: crate::snapshot::Snapshot
     pub fn with_schema(mut self, schema: SchemaRef) -> Self {
         self.schema = Some(schema);
+        self.schema_override = false;
+        self.columns = None;
+        self
+    }
+
+    /// Select the columns of the table to scan by name, where nested columns are named by their
+    /// path, e.g. `a.b` for the field `b` of the struct column `a`. This reads only the named
+    /// columns (and, with [`ScanBuilder::with_residual_applied`], the columns the predicate
+    /// references), and the data returned by [`Scan::execute`] (and [`Scan::execute_iter`]) has
+    /// just the named top-level columns, in the order they are first named in. A struct column
+    /// named only through some of its fields keeps just those fields.
+    ///
+    /// [`ScanBuilder::build`] fails with an [`Error::MissingColumn`] if a column isn't in the
+    /// table schema. This replaces any schema set with [`ScanBuilder::with_schema`] (or
+    /// [`ScanBuilder::with_schema_override`]), and vice versa.
+    pub fn with_columns(mut self, columns: impl IntoIterator<Item = impl ToString>) -> Self {
+        self.columns = Some(
+            columns
+                .into_iter()
+                .map(|column| column.to_string())
+                .collect(),
+        );
+        self.schema = None;
+        self.schema_override = false;
+        self
+    }
+
+    /// Provide the [`Schema`] of the data returned by [`Scan::execute`] (and
+    /// [`Scan::execute_iter`]), if it should only have some of the columns that are read. Every
+    /// field of `schema` must be a field of the read schema (see [`ScanBuilder::with_schema`])
+    /// with the same type, and the other columns of the read schema are dropped from the results,
+    /// after they are read. The masks of the results are unaffected.
+    ///
+    /// Defaults to the read schema.
+    ///
+    /// [`Schema`]: crate::schema::Schema
+    pub fn with_output_schema(mut self, schema: SchemaRef) -> Self {
+        self.output_schema = Some(schema);
+        self
+    }
+
+    /// Read the table using `schema` as-is, instead of a schema derived from the table metadata.
+    /// This is an escape hatch for data recovery, e.g. for tables with broken or missing schema
+    /// metadata, and **bypasses normal schema validation**: the fields of `schema` are not checked
+    /// against the table schema, and are looked up in the data files by name, ignoring any column
+    /// mapping. Columns of `schema` that are missing from a file are filled with nulls, so they
+    /// must be nullable. Columns named in the table's partition columns are still filled in from
+    /// the partition values.
+    ///
+    /// This replaces any schema set with [`ScanBuilder::with_schema`], and vice versa.
+    pub fn with_schema_override(mut self, schema: SchemaRef) -> Self {
+        self.schema = Some(schema);
+        self.schema_override = true;
+        self.columns = None;
         self
     }
 
@@ -71,6 +205,10 @@ impl ScanBuilder {
     ///
     /// Can be used to filter the rows in a scan. For example, using the predicate
     /// `x < 4` to return a subset of the rows in the scan which satisfy the filter.
+    ///
+    /// Partition columns can also be referenced as fields of the [`PARTITION_STRUCT_COLUMN`]
+    /// struct, e.g. `__partition__.day`, or all at once by comparing `__partition__` to a struct
+    /// literal, which is equivalent to comparing each of its fields.
     pub fn with_predicate(mut self, predicate: Expression) -> Self {
         self.predicate = Some(predicate);
         self
@@ -85,6 +223,110 @@ impl ScanBuilder {
         }
     }
 
+    /// Set which metadata the fields of the returned data should carry. Defaults to
+    /// [`FieldMetadataMode::FromLogicalSchema`].
+    pub fn with_field_metadata(mut self, field_metadata_mode: FieldMetadataMode) -> Self {
+        self.field_metadata_mode = field_metadata_mode;
+        self
+    }
+
+    /// Skip files that have no stats in the log using the stats in their parquet footers instead,
+    /// as read by [`crate::ParquetHandler::read_footer_stats`]. Footers are only read for files
+    /// without stats, and only if the predicate can be used for data skipping. This can save
+    /// reading many files for tables written by tools that don't collect stats, but costs an extra
+    /// request per such file while planning the scan.
+    ///
+    /// Off by default.
+    pub fn with_footer_stats_fallback(mut self) -> Self {
+        self.footer_stats_fallback = true;
+        self
+    }
+
+    /// Check that the partition values of every file of the scan match the Hive-style partition
+    /// directories of its path (e.g. that a file with `partitionValues` `{"day": "2024-01-01"}`
+    /// is in a `day=2024-01-01/` directory), and fail the scan with an
+    /// [`Error::InvalidPartitionPath`] if they don't. A mismatch indicates a corrupt or misencoded
+    /// commit.
+    ///
+    /// Off by default, since not all writers encode partition values in paths (e.g. tables with
+    /// column mapping usually don't).
+    pub fn with_partition_path_validation(mut self) -> Self {
+        self.partition_path_validation = true;
+        self
+    }
+
+    /// Open up to `read_ahead` files of the scan beyond the one currently being consumed when
+    /// executing it lazily (see [`Scan::execute_iter`]), so that an engine whose
+    /// [`crate::ParquetHandler`] reads in the background (like the default engine's) can decode
+    /// the next files while the caller processes the current one. Dropping the iterator drops the
+    /// opened files, which stops their reads.
+    ///
+    /// This bounds the number of files open at once, not the memory they use: each opened file
+    /// holds whatever the parquet handler buffers for it, which depends on the handler (e.g. the
+    /// sync engine reads whole files) and on the size of the file. To stay within a memory budget,
+    /// pick the depth from the sizes of the table's files.
+    ///
+    /// Defaults to 0, i.e. a file is only opened once the previous one has been fully consumed.
+    pub fn with_read_ahead(mut self, read_ahead: usize) -> Self {
+        self.read_ahead = read_ahead;
+        self
+    }
+
+    /// Track the file actions seen while replaying the log with the [`FileDeduplicator`]s created
+    /// (one per replay) by `factory`, instead of a [`log_replay::DefaultFileDeduplicator`], which keeps them
+    /// all in memory. This lets engines scanning tables with very many files bound the memory of
+    /// log replay, e.g. by spilling to disk.
+    pub fn with_file_deduplicator(
+        mut self,
+        factory: impl Fn() -> Box<dyn FileDeduplicator> + Send + Sync + 'static,
+    ) -> Self {
+        self.file_deduplicator = Some(Arc::new(factory));
+        self
+    }
+
+    /// Handle files that the log adds more than once without removing them in between according
+    /// to `policy`. Defaults to [`DuplicateAddPolicy::LastWins`]; use
+    /// [`DuplicateAddPolicy::Strict`] to fail the scan on such (corrupt) logs instead.
+    pub fn with_duplicate_add_policy(mut self, policy: DuplicateAddPolicy) -> Self {
+        self.duplicate_add_policy = policy;
+        self
+    }
+
+    /// Fail the scan with an [`Error::InsufficientPruning`] instead of running it if planning
+    /// prunes less than `min_fraction` (between 0 and 1) of the bytes of the table's files (see
+    /// [`Scan::pruning_stats`]), e.g. to reject accidental full-table scans. This is checked by
+    /// [`Scan::scan_data`] and [`Scan::execute`] (and [`Scan::execute_iter`]) before they
+    /// return anything, at the cost of an additional replay of the log without the predicate.
+    pub fn require_pruning(mut self, min_fraction: f64) -> Self {
+        self.min_pruned_fraction = Some(min_fraction);
+        self
+    }
+
+    /// Apply the predicate of the scan to the rows it reads, so that the [`ScanResult::mask`] of
+    /// each batch returned by [`Scan::execute`] (and [`Scan::execute_iter`]) is false for the
+    /// rows the predicate doesn't select (i.e. evaluates to false or null for), in addition to
+    /// rows removed by deletion vectors. Then [`Scan::residual_predicate`] is `None`, and the
+    /// caller doesn't need to filter the rows itself.
+    ///
+    /// Every column the predicate references must be in the read schema of the scan (see
+    /// [`ScanBuilder::with_schema`]), or [`ScanBuilder::build`] fails.
+    pub fn with_residual_applied(mut self) -> Self {
+        self.residual_applied = true;
+        self
+    }
+
+    /// Whether the data returned by [`Scan::execute`] (and [`Scan::execute_iter`]) includes the
+    /// partition columns of the read schema. Their values aren't stored in the data files, so the
+    /// kernel fills them in from the partition values of each file, with the types of the table
+    /// schema, in their place in the read schema. Without them, the data only has the columns read
+    /// from the files, and [`Scan::output_schema`] leaves them out as well.
+    ///
+    /// Defaults to true.
+    pub fn with_partition_columns(mut self, partition_columns: bool) -> Self {
+        self.partition_columns = partition_columns;
+        self
+    }
+
     /// Build the [`Scan`].
     ///
     /// This does not scan the table at this point, but does do some work to ensure that the
@@ -92,30 +334,143 @@ impl ScanBuilder {
     /// [`Scan`] type itself can be used to fetch the files and associated metadata required to
     /// perform actual data reads.
     pub fn build(self) -> DeltaResult<Scan> {
-        // if no schema is provided, use snapshot's entire schema (e.g. SELECT *)
-        let logical_schema = self
-            .schema
-            .unwrap_or_else(|| self.snapshot.schema().clone().into());
+        let predicate = self
+            .predicate
+            .map(|predicate| {
+                normalize_partition_predicate(
+                    predicate,
+                    &self.snapshot.metadata().partition_columns,
+                )
+            })
+            .transpose()?;
+        let table_schema = self.snapshot.schema();
+        let mut output_schema = self.output_schema;
+        let logical_schema = match (self.schema, self.columns) {
+            (Some(schema), _) => schema,
+            (None, Some(columns)) => {
+                let mut read_columns = columns.clone();
+                if let (true, Some(predicate)) = (self.residual_applied, &predicate) {
+                    read_columns.extend(
+                        predicate
+                            .references()
+                            .into_iter()
+                            .filter(|column| column_exists(table_schema, column))
+                            .sorted()
+                            .map(|column| column.path().join(".")),
+                    );
+                }
+                let read_columns = read_columns
+                    .iter()
+                    .map(|column| (column.as_str(), column.as_str()));
+                let read_schema = Arc::new(project_columns(table_schema, read_columns)?);
+                if output_schema.is_none() {
+                    let names: Vec<_> = columns
+                        .iter()
+                        .map(|column| column.split('.').next().unwrap_or(column))
+                        .unique()
+                        .collect();
+                    output_schema = Some(read_schema.project(&names)?);
+                }
+                read_schema
+            }
+            // if no schema is provided, use snapshot's entire schema (e.g. SELECT *)
+            (None, None) => table_schema.clone().into(),
+        };
+        // an overridden schema names the physical columns directly
+        let column_mapping_mode = if self.schema_override {
+            ColumnMappingMode::None
+        } else {
+            self.snapshot.column_mapping_mode
+        };
         let (all_fields, read_fields, have_partition_cols) = get_state_info(
             logical_schema.as_ref(),
             &self.snapshot.metadata().partition_columns,
-            self.snapshot.column_mapping_mode,
+            column_mapping_mode,
         )?;
+        let read_fields = match matches_parquet_field_ids(
+            column_mapping_mode,
+            &self.snapshot.metadata().configuration,
+        ) {
+            true => read_fields.into_iter().map(with_parquet_field_id).collect(),
+            false => read_fields,
+        };
         let physical_schema = Arc::new(StructType::new(read_fields));
+        if let (true, Some(predicate)) = (self.residual_applied, &predicate) {
+            for column in predicate.references() {
+                require!(
+                    column_exists(&logical_schema, column),
+                    Error::missing_column(format!(
+                        "Predicate column {column} is not in the read schema, so the predicate \
+                         can't be applied"
+                    ))
+                );
+            }
+        }
+        if let Some(ref output_schema) = output_schema {
+            for field in output_schema.fields() {
+                let read_field = logical_schema.field(field.name()).ok_or_else(|| {
+                    Error::missing_column(format!(
+                        "Output column {} is not in the read schema",
+                        field.name()
+                    ))
+                })?;
+                require!(
+                    read_field.data_type() == field.data_type(),
+                    Error::unexpected_column_type(format!(
+                        "Output column {} has type {}, but type {} in the read schema",
+                        field.name(),
+                        field.data_type(),
+                        read_field.data_type()
+                    ))
+                );
+            }
+        }
+        let output_schema = match self.partition_columns {
+            true => output_schema,
+            false => {
+                let partition_columns = &self.snapshot.metadata().partition_columns;
+                let output_schema = output_schema.as_ref().unwrap_or(&logical_schema);
+                let fields = output_schema
+                    .fields()
+                    .filter(|field| !partition_columns.contains(field.name()))
+                    .cloned()
+                    .collect();
+                Some(Arc::new(StructType::new(fields)))
+            }
+        };
+        let output_field_metadata = OutputFieldMetadata::try_new(
+            self.field_metadata_mode,
+            output_schema.as_ref().unwrap_or(&logical_schema),
+            &logical_schema,
+            &all_fields,
+        )?;
         Ok(Scan {
             snapshot: self.snapshot,
             logical_schema,
             physical_schema,
-            predicate: self.predicate,
+            output_schema,
+            predicate,
             all_fields,
             have_partition_cols,
+            field_metadata_mode: self.field_metadata_mode,
+            output_field_metadata: Arc::new(output_field_metadata),
+            footer_stats_fallback: self.footer_stats_fallback,
+            partition_path_validation: self.partition_path_validation,
+            column_mapping_mode,
+            schema_override: self.schema_override,
+            read_ahead: self.read_ahead,
+            file_deduplicator: self.file_deduplicator,
+            duplicate_add_policy: self.duplicate_add_policy,
+            residual_applied: self.residual_applied,
+            min_pruned_fraction: self.min_pruned_fraction,
         })
     }
 }
 
 /// A vector of this type is returned from calling [`Scan::execute`]. Each [`ScanResult`] contains
 /// the raw [`EngineData`] as read by the engines [`crate::ParquetHandler`], and a boolean
-/// mask. Rows can be dropped from a scan due to deletion vectors, so we communicate back both
+/// mask. Rows can be dropped from a scan due to deletion vectors (or the predicate, see
+/// [`ScanBuilder::with_residual_applied`]), so we communicate back both
 /// EngineData and information regarding whether a row should be included or not See the docs below
 /// for [`ScanResult::mask`] for details on the mask.
 pub struct ScanResult {
@@ -135,21 +490,68 @@ pub struct ScanResult {
 pub enum ColumnType {
     // A column, selected from the data, as is
     Selected(String),
-    // A partition column that needs to be added back in
+    // A partition (or void) column that needs to be added back in
     Partition(usize),
 }
 
 pub type ScanData = (Box<dyn EngineData>, Vec<bool>);
 
+/// How many of the files of a table a scan reads after data skipping and partition pruning,
+/// returned by [`Scan::pruning_stats`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct PruningStats {
+    /// The number of files of the table
+    pub total_files: usize,
+    /// The total size in bytes of the files of the table
+    pub total_bytes: u64,
+    /// The number of files the scan reads
+    pub selected_files: usize,
+    /// The total size in bytes of the files the scan reads
+    pub selected_bytes: u64,
+}
+
+impl PruningStats {
+    /// The fraction (between 0 and 1) of the files of the table the scan doesn't read. This is 1
+    /// for an empty table, since there is nothing to read.
+    pub fn pruned_files_fraction(&self) -> f64 {
+        pruned_fraction(self.selected_files as f64, self.total_files as f64)
+    }
+
+    /// The fraction (between 0 and 1) of the bytes of the files of the table the scan doesn't
+    /// read. This is 1 for an empty table, since there is nothing to read.
+    pub fn pruned_bytes_fraction(&self) -> f64 {
+        pruned_fraction(self.selected_bytes as f64, self.total_bytes as f64)
+    }
+}
+
+fn pruned_fraction(selected: f64, total: f64) -> f64 {
+    match total {
+        0.0 => 1.0,
+        total => 1.0 - selected / total,
+    }
+}
+
 /// The result of building a scan over a table. This can be used to get the actual data from
 /// scanning the table.
 pub struct Scan {
     snapshot: Arc<Snapshot>,
     logical_schema: SchemaRef,
     physical_schema: SchemaRef,
+    output_schema: Option<SchemaRef>,
     predicate: Option<Expression>,
     all_fields: Vec<ColumnType>,
     have_partition_cols: bool,
+    field_metadata_mode: FieldMetadataMode,
+    output_field_metadata: Arc<OutputFieldMetadata>,
+    footer_stats_fallback: bool,
+    partition_path_validation: bool,
+    column_mapping_mode: ColumnMappingMode,
+    schema_override: bool,
+    read_ahead: usize,
+    file_deduplicator: Option<FileDeduplicatorFactory>,
+    duplicate_add_policy: DuplicateAddPolicy,
+    residual_applied: bool,
+    min_pruned_fraction: Option<f64>,
 }
 
 impl std::fmt::Debug for Scan {
@@ -169,19 +571,76 @@ impl Scan {
         &self.logical_schema
     }
 
+    /// Get a shared reference to the [`Schema`] of the data returned by [`Scan::execute`], which
+    /// is the [`Scan::schema`] unless an output schema was set with
+    /// [`ScanBuilder::with_output_schema`].
+    ///
+    /// [`Schema`]: crate::schema::Schema
+    pub fn output_schema(&self) -> &SchemaRef {
+        self.output_schema.as_ref().unwrap_or(&self.logical_schema)
+    }
+
     /// Get the predicate [`Expression`] of the scan.
     pub fn predicate(&self) -> &Option<Expression> {
         &self.predicate
     }
 
+    /// Get the part of the predicate of the scan that the caller must still apply to the rows
+    /// returned by [`Scan::execute`], if any. The kernel only uses the predicate to skip whole
+    /// files (based on their statistics and partition values), so the returned rows may include
+    /// rows the predicate doesn't select, and this is the whole predicate, unless the kernel
+    /// applies it to the rows as well (see [`ScanBuilder::with_residual_applied`]), in which case
+    /// this is `None`.
+    pub fn residual_predicate(&self) -> Option<&Expression> {
+        match self.residual_applied {
+            true => None,
+            false => self.predicate.as_ref(),
+        }
+    }
+
+    /// Get the filter used to prune files based on generated (bucket) partition columns, if the
+    /// predicate allows any pruning.
+    fn partition_pruning_filter(&self) -> Option<PartitionPruningFilter> {
+        PartitionPruningFilter::new(
+            self.snapshot.schema(),
+            &self.snapshot.metadata().partition_columns,
+            self.snapshot.column_mapping_mode,
+            &self.predicate,
+        )
+    }
+
+    /// Get the [`LogReplayScanner`] to use for this scan, skipping files with `predicate`
+    fn log_replay_scanner(
+        &self,
+        engine: &dyn Engine,
+        predicate: &Option<Expression>,
+    ) -> DeltaResult<LogReplayScanner> {
+        let mut scanner = LogReplayScanner::new(engine, &self.logical_schema, predicate)?;
+        if self.footer_stats_fallback {
+            scanner = scanner.with_footer_stats_fallback(
+                engine,
+                &self.snapshot.table_root,
+                self.snapshot.column_mapping_mode,
+            )?;
+        }
+        if self.partition_path_validation {
+            scanner = scanner.with_partition_path_validation();
+        }
+        if let Some(ref factory) = self.file_deduplicator {
+            scanner = scanner.with_deduplicator(factory());
+        }
+        Ok(scanner.with_duplicate_add_policy(self.duplicate_add_policy))
+    }
+
     /// Get an iterator of Add actions that should be included in scan for a query. This handles
-    /// log-replay, reconciling Add and Remove actions, and applying data skipping (if possible)
+    /// log-replay, reconciling Add and Remove actions, and applying data skipping and partition
+    /// pruning (if possible)
     pub(crate) fn files(
         &self,
         engine: &dyn Engine,
     ) -> DeltaResult<impl Iterator<Item = DeltaResult<Add>> + Send> {
-        let commit_read_schema = get_log_schema().project(&[ADD_NAME, REMOVE_NAME])?;
-        let checkpoint_read_schema = get_log_schema().project(&[ADD_NAME])?;
+        let commit_read_schema = get_log_schema_for(&[ActionType::Add, ActionType::Remove])?;
+        let checkpoint_read_schema = get_log_schema_for(&[ActionType::Add])?;
 
         let log_iter = self.snapshot.log_segment.replay(
             engine,
@@ -190,28 +649,31 @@ impl Scan {
             self.predicate.clone(),
         )?;
 
-        Ok(log_replay_iter(
-            engine,
-            log_iter,
-            &self.logical_schema,
-            &self.predicate,
-        ))
+        let pruning_filter = self.partition_pruning_filter();
+        Ok(self
+            .log_replay_scanner(engine, &self.predicate)?
+            .into_add_iter(log_iter)
+            .filter(move |add| match (add, &pruning_filter) {
+                (Ok(add), Some(filter)) => filter.keep(&add.partition_values),
+                _ => true,
+            }))
     }
 
     /// Get an iterator of [`EngineData`]s that should be included in scan for a query. This handles
-    /// log-replay, reconciling Add and Remove actions, and applying data skipping (if
-    /// possible). Each item in the returned iterator is a tuple of:
+    /// log-replay, reconciling Add and Remove actions, and applying data skipping and partition
+    /// pruning (if possible). Each item in the returned iterator is a tuple of:
     /// - `Box<dyn EngineData>`: Data in engine format, where each row represents a file to be
-    /// scanned. The schema for each row can be obtained by calling [`scan_row_schema`].
+    ///   scanned. The schema for each row can be obtained by calling [`scan_row_schema`].
     /// - `Vec<bool>`: A selection vector. If a row is at index `i` and this vector is `false` at
-    /// index `i`, then that row should *not* be processed (i.e. it is filtered out). If the vector
-    /// is `true` at index `i` the row *should* be processed.
+    ///   index `i`, then that row should *not* be processed (i.e. it is filtered out). If the vector
+    ///   is `true` at index `i` the row *should* be processed.
     pub fn scan_data(
         &self,
         engine: &dyn Engine,
     ) -> DeltaResult<impl Iterator<Item = DeltaResult<ScanData>>> {
-        let commit_read_schema = get_log_schema().project(&[ADD_NAME, REMOVE_NAME])?;
-        let checkpoint_read_schema = get_log_schema().project(&[ADD_NAME])?;
+        self.check_pruning(engine)?;
+        let commit_read_schema = get_log_schema_for(&[ActionType::Add, ActionType::Remove])?;
+        let checkpoint_read_schema = get_log_schema_for(&[ActionType::Add])?;
 
         let log_iter = self.snapshot.log_segment.replay(
             engine,
@@ -220,12 +682,232 @@ impl Scan {
             self.predicate.clone(),
         )?;
 
-        Ok(scan_action_iter(
+        let pruning_filter = self.partition_pruning_filter();
+        Ok(self
+            .log_replay_scanner(engine, &self.predicate)?
+            .into_scan_data_iter(engine, log_iter)
+            .filter_map(move |scan_data| {
+                let Some(ref filter) = pruning_filter else {
+                    return Some(scan_data);
+                };
+                let result = scan_data.and_then(|(data, mut selection_vector)| {
+                    filter.apply(data.as_ref(), &mut selection_vector)?;
+                    Ok((data, selection_vector))
+                });
+                match result {
+                    // don't bother returning it if everything was pruned
+                    Ok((_, ref selection_vector)) if !selection_vector.contains(&true) => None,
+                    result => Some(result),
+                }
+            }))
+    }
+
+    /// Get the number and total size of the files of the table and of those this scan reads after
+    /// data skipping and partition pruning. This only replays the log (twice: with and without
+    /// the predicate), no data is read.
+    pub fn pruning_stats(&self, engine: &dyn Engine) -> DeltaResult<PruningStats> {
+        let mut stats = PruningStats::default();
+        for add in self.files(engine)? {
+            stats.selected_files += 1;
+            stats.selected_bytes += add?.size as u64;
+        }
+        let log_iter = self.snapshot.log_segment.replay(
             engine,
-            log_iter,
-            &self.logical_schema,
-            &self.predicate,
-        ))
+            get_log_schema_for(&[ActionType::Add, ActionType::Remove])?,
+            get_log_schema_for(&[ActionType::Add])?,
+            None,
+        )?;
+        for add in self
+            .log_replay_scanner(engine, &None)?
+            .into_add_iter(log_iter)
+        {
+            stats.total_files += 1;
+            stats.total_bytes += add?.size as u64;
+        }
+        Ok(stats)
+    }
+
+    /// Fail if the scan requires more pruning than it does, see [`ScanBuilder::require_pruning`]
+    fn check_pruning(&self, engine: &dyn Engine) -> DeltaResult<()> {
+        let Some(min_fraction) = self.min_pruned_fraction else {
+            return Ok(());
+        };
+        let pruned_fraction = self.pruning_stats(engine)?.pruned_bytes_fraction();
+        match pruned_fraction < min_fraction {
+            true => Err(Error::insufficient_pruning(pruned_fraction, min_fraction)),
+            false => Ok(()),
+        }
+    }
+
+    /// Perform a "dry-run" of this scan, checking that the plan can be executed without actually
+    /// reading any data. This resolves the scan schema and predicate against the table schema, and
+    /// then uses the `engine`'s [`crate::FileSystemClient`] to check that every file (and deletion
+    /// vector file) that would be read exists and has the size recorded in the log.
+    ///
+    /// All problems found are collected and returned together as an [`Error::ScanValidation`],
+    /// rather than stopping at the first one.
+    pub fn validate(&self, engine: &dyn Engine) -> DeltaResult<()> {
+        let mut problems = self.validate_schema();
+        let table_root = &self.snapshot.table_root;
+
+        // collect the (location, expected size) of each file the scan would read
+        let mut files: Vec<(Url, Option<usize>)> = vec![];
+        for add in self.files(engine)? {
+            let add = match add {
+                Ok(add) => add,
+                Err(err) => {
+                    problems.push(format!("Failed to replay log: {err}"));
+                    continue;
+                }
+            };
+            match table_root.join(&add.path) {
+                Ok(location) => files.push((location, Some(add.size as usize))),
+                Err(err) => problems.push(format!("Invalid path {}: {err}", add.path)),
+            }
+            if let Some(ref dv) = add.deletion_vector {
+                match dv.absolute_path(table_root) {
+                    Ok(Some(dv_location)) => files.push((dv_location, None)),
+                    Ok(None) => {} // inline dv, nothing to check
+                    Err(err) => {
+                        problems.push(format!("Invalid deletion vector for {}: {err}", add.path))
+                    }
+                }
+            }
+        }
+
+        // look up each file on its own, so that only the files around it are listed rather than
+        // whole directories of the table
+        let fs_client = engine.get_file_system_client();
+        for (location, expected_size) in files {
+            let problem = match (list_file(fs_client.as_ref(), &location), expected_size) {
+                (Err(err), _) => Some(format!("Could not check {location}: {err}")),
+                (Ok(None), _) => Some(format!("File {location} does not exist")),
+                (Ok(Some(file)), Some(size)) if file.size != size => Some(format!(
+                    "File {location} has size {}, but the log records size {size}",
+                    file.size
+                )),
+                _ => None,
+            };
+            problems.extend(problem);
+        }
+
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(Error::ScanValidation(problems))
+        }
+    }
+
+    /// Check that the logical schema and predicate of this scan resolve against the table schema,
+    /// returning a description of each problem found. With a schema override, only the predicate is
+    /// checked, against the overridden schema.
+    fn validate_schema(&self) -> Vec<String> {
+        let table_schema = if self.schema_override {
+            self.logical_schema.as_ref()
+        } else {
+            self.snapshot.schema()
+        };
+        let mut problems = vec![];
+        check_fields(table_schema, &self.logical_schema, "", &mut problems);
+        if let Some(ref predicate) = self.predicate {
+            for column in predicate.references() {
+                if !column_exists(table_schema, column) {
+                    problems.push(format!(
+                        "Predicate column {column} does not exist in the table schema"
+                    ));
+                }
+            }
+        }
+        problems
+    }
+
+    /// Get the live (i.e. not deleted) row indexes of each file in this scan, without reading any
+    /// data. For files with a deletion vector this is every row of the file that isn't in the
+    /// deletion vector, and for files without one it is every row of the file. Engines can use this
+    /// to read only the rows they need, e.g. when implementing row-level operations.
+    ///
+    /// The number of rows in each file is taken from its `numRecords` statistic. For files without
+    /// one it is read from the Parquet footer of the file instead, with
+    /// [`crate::ParquetHandler::parquet_row_group_layout`].
+    pub fn live_rows(&self, engine: &dyn Engine) -> DeltaResult<Vec<(FileMeta, RoaringTreemap)>> {
+        let fs_client = engine.get_file_system_client();
+        let parquet_handler = engine.get_parquet_handler();
+        let table_root = &self.snapshot.table_root;
+        self.files(engine)?
+            .map(|add| {
+                let add = add?;
+                let meta = FileMeta {
+                    last_modified: add.modification_time,
+                    size: add.size as usize,
+                    location: table_root.join(&add.path)?,
+                    e_tag: None,
+                    version: None,
+                };
+                let num_records = match add.num_records() {
+                    Some(num_records) => num_records,
+                    None => parquet_handler
+                        .parquet_row_group_layout(&meta)?
+                        .iter()
+                        .map(|row_group| row_group.num_rows as u64)
+                        .sum(),
+                };
+                let mut live_rows = RoaringTreemap::new();
+                live_rows.insert_range(0..num_records);
+                if let Some(ref dv) = add.deletion_vector {
+                    live_rows -= dv.read(fs_client.clone(), table_root)?;
+                }
+                Ok((meta, live_rows))
+            })
+            .try_collect()
+    }
+
+    /// Summarize the partitions of the files in this scan without reading any data. The returned
+    /// batch has one row per file, with the partition columns of the scan schema (parsed from the
+    /// file's `partitionValues`) followed by a `numRecords` column with the number of live rows of
+    /// the file, i.e. the `numRecords` statistic less the rows removed by its deletion vector. This
+    /// is null for files without a `numRecords` statistic.
+    #[cfg(any(feature = "default-engine", feature = "sync-engine"))]
+    pub fn partition_summary(&self, engine: &dyn Engine) -> DeltaResult<arrow_array::RecordBatch> {
+        use arrow_array::{ArrayRef, Int64Array, RecordBatch};
+        use arrow_schema::{DataType as ArrowDataType, Field, Schema as ArrowSchema};
+
+        let partition_columns = &self.snapshot.metadata().partition_columns;
+        let partition_fields: Vec<_> = self
+            .logical_schema
+            .fields()
+            .filter(|field| partition_columns.contains(field.name()))
+            .collect();
+        let mut partition_values: Vec<Vec<ArrayRef>> = vec![vec![]; partition_fields.len()];
+        let mut num_records = vec![];
+        for add in self.files(engine)? {
+            let add = add?;
+            for (field, values) in partition_fields.iter().zip(partition_values.iter_mut()) {
+                let raw = add.partition_values.get(field.name());
+                values.push(partition_value(raw, field.data_type())?.to_array(1)?);
+            }
+            let cardinality = add.deletion_vector.as_ref().map_or(0, |dv| dv.cardinality);
+            num_records.push(add.num_records().map(|n| n as i64 - cardinality));
+        }
+
+        let mut fields = Vec::with_capacity(partition_fields.len() + 1);
+        let mut columns = Vec::with_capacity(partition_fields.len() + 1);
+        for (field, values) in partition_fields.into_iter().zip(partition_values) {
+            let field = Field::try_from(field)?;
+            columns.push(match values.is_empty() {
+                true => arrow_array::new_empty_array(field.data_type()),
+                false => {
+                    let values: Vec<_> = values.iter().map(AsRef::as_ref).collect();
+                    arrow_select::concat::concat(&values)?
+                }
+            });
+            fields.push(field);
+        }
+        fields.push(Field::new("numRecords", ArrowDataType::Int64, true));
+        columns.push(Arc::new(Int64Array::from(num_records)));
+        Ok(RecordBatch::try_new(
+            Arc::new(ArrowSchema::new(fields)),
+            columns,
+        )?)
     }
 
     /// Get global state that is valid for the entire scan. This is somewhat expensive so should
@@ -236,7 +918,8 @@ impl Scan {
             partition_columns: self.snapshot.metadata().partition_columns.clone(),
             logical_schema: self.logical_schema.clone(),
             read_schema: self.physical_schema.clone(),
-            column_mapping_mode: self.snapshot.column_mapping_mode,
+            column_mapping_mode: self.column_mapping_mode,
+            field_metadata_mode: self.field_metadata_mode,
         }
     }
 
@@ -246,102 +929,460 @@ impl Scan {
     /// present. See the documentation for [`ScanResult`] for more details. Generally
     /// connectors/engines will want to use [`Scan::scan_data`] so they can have more control over
     /// the execution of the scan.
-    // This calls [`Scan::files`] to get a set of `Add` actions for the scan, and then uses the
-    // `engine`'s [`crate::ParquetHandler`] to read the actual table data.
     pub fn execute(&self, engine: &dyn Engine) -> DeltaResult<Vec<ScanResult>> {
-        debug!(
-            "Executing scan with logical schema {:#?} and physical schema {:#?}",
-            self.logical_schema, self.physical_schema
-        );
-        let output_schema = DataType::from(self.schema().clone());
-        let parquet_handler = engine.get_parquet_handler();
+        self.execute_iter(engine)?.try_collect()
+    }
 
-        let mut results: Vec<ScanResult> = vec![];
-        let files = self.files(engine)?;
-        for add_result in files {
-            let add = add_result?;
-            let meta = FileMeta {
-                last_modified: add.modification_time,
-                size: add.size as usize,
-                location: self.snapshot.table_root.join(&add.path)?,
-            };
+    /// Perform an "all in one" scan like [`Scan::execute`], but lazily: the files of the scan are
+    /// only read as the returned iterator is advanced, one at a time (plus any read-ahead set with
+    /// [`ScanBuilder::with_read_ahead`]), so only the data of the files being read is held in
+    /// memory.
+    pub fn execute_iter<'a>(
+        &'a self,
+        engine: &'a dyn Engine,
+    ) -> DeltaResult<impl Iterator<Item = DeltaResult<ScanResult>> + Send + 'a> {
+        execute_lazily(self, engine)
+    }
 
-            let read_results =
-                parquet_handler.read_parquet_files(&[meta], self.physical_schema.clone(), None)?;
+    /// Perform an "all in one" scan like [`Scan::execute_iter`], but return the results as a
+    /// [`BoxStream`] for engines that consume data asynchronously. The handlers of the engine are
+    /// blocking, so the log replay and the reads run with [`tokio::task::spawn_blocking`], and
+    /// polling the stream never blocks. The stream must therefore be polled within a tokio
+    /// runtime. Files are opened with the same read-ahead as [`Scan::execute_iter`], and their
+    /// results are yielded in order. Nothing is read until the stream is first polled, and errors
+    /// (including those of the log replay) are yielded by the stream.
+    #[cfg(feature = "default-engine")]
+    pub fn execute_stream(
+        self: Arc<Self>,
+        engine: Arc<dyn Engine>,
+    ) -> BoxStream<'static, DeltaResult<ScanResult>> {
+        blocking_stream(move || execute_lazily(self, engine))
+    }
 
-            let read_expression = if self.have_partition_cols
-                || self.snapshot.column_mapping_mode != ColumnMappingMode::None
-            {
-                // Loop over all fields and create the correct expressions for them
-                let all_fields = self
-                    .all_fields
-                    .iter()
-                    .map(|field| match field {
-                        ColumnType::Partition(field_idx) => {
-                            let field = self.logical_schema.fields.get_index(*field_idx).ok_or_else(|| {
-                                Error::generic("logical schema did not contain expected field, can't execute scan")
-                            })?.1;
-                            let value_expression = parse_partition_value(
-                                add.partition_values.get(field.name()),
-                                field.data_type(),
-                            )?;
-                            Ok::<Expression, Error>(Expression::Literal(value_expression))
-                        }
-                        ColumnType::Selected(field_name) => Ok(Expression::column(field_name)),
-                    })
-                    .try_collect()?;
-                Some(Expression::Struct(all_fields))
-            } else {
-                None
-            };
+    /// Perform an "all in one" scan like [`Scan::execute_iter`], but return the data with the
+    /// mask of each result already applied, i.e. only the rows that weren't deleted (or filtered
+    /// out by the predicate, see [`ScanBuilder::with_residual_applied`]). Each batch has an
+    /// additional non-nullable `LONG` column named [`ROW_INDEX_COLUMN_NAME`] after the columns of
+    /// the scan's output schema, with the index of each row in its (physical) data file, counting
+    /// the rows the mask removed. These are the same indexes deletion vectors (and
+    /// [`Scan::live_rows`]) use.
+    ///
+    /// Every row group of the files is read, even if the predicate rules it out, so the indexes
+    /// line up with the files.
+    #[cfg(any(feature = "default-engine", feature = "sync-engine"))]
+    pub fn execute_with_row_indexes<'a>(
+        &'a self,
+        engine: &'a dyn Engine,
+    ) -> DeltaResult<impl Iterator<Item = DeltaResult<arrow_array::RecordBatch>> + Send + 'a> {
+        self.check_pruning(engine)?;
+        let dv_cache = DeletionVectorCache::default();
+        let files = self.files(engine)?.map(move |add_result| {
+            add_result.and_then(|add| self.read_file(engine, add, &dv_cache, false))
+        });
+        Ok(self
+            .read_ahead(files)
+            .flat_map(|file_results| match file_results {
+                Ok(scan_results) => {
+                    // the index in the file of the first row of the next result
+                    let mut offset = 0;
+                    Either::Left(
+                        scan_results.map(move |result| with_row_indexes(result, &mut offset)),
+                    )
+                }
+                Err(err) => Either::Right(std::iter::once(Err(err))),
+            }))
+    }
+
+    /// Advance `files` (an iterator that opens each file as it is advanced) up to the configured
+    /// read-ahead depth beyond the file currently being consumed.
+    fn read_ahead<I: Iterator>(&self, files: I) -> ReadAhead<I> {
+        ReadAhead {
+            files,
+            opened: std::collections::VecDeque::with_capacity(self.read_ahead + 1),
+            depth: self.read_ahead,
+        }
+    }
+
+    /// The type of the data of the results of this scan
+    #[cfg(feature = "datafusion")]
+    pub(crate) fn output_type(&self) -> DataType {
+        self.output_field_metadata.output_type.clone()
+    }
+
+    /// Read the data of the file added by `add`, returning one [`ScanResult`] per batch read. Its
+    /// deletion vector (if any) is looked up in `dv_cache` before it is read.
+    pub(crate) fn execute_file(
+        &self,
+        engine: &dyn Engine,
+        add: Add,
+        dv_cache: &DeletionVectorCache,
+    ) -> DeltaResult<impl Iterator<Item = ScanResult> + Send + 'static> {
+        self.read_file(engine, add, dv_cache, true)
+    }
+
+    /// Read the data of the file added by `add` like [`Self::execute_file`]. Unless
+    /// `skip_row_groups` is set, every row of the file is read (in order), so the position of a
+    /// row in the results is its index in the file.
+    fn read_file(
+        &self,
+        engine: &dyn Engine,
+        add: Add,
+        dv_cache: &DeletionVectorCache,
+        skip_row_groups: bool,
+    ) -> DeltaResult<impl Iterator<Item = ScanResult> + Send + 'static> {
+        let meta = FileMeta {
+            last_modified: add.modification_time,
+            size: add.size as usize,
+            location: self.snapshot.table_root.join(&add.path)?,
+            e_tag: None,
+            version: None,
+        };
+
+        // the predicate lets the parquet handler skip row groups that can't match it, unless the
+        // rows of the file have to line up with its row indexes (and deletion vector), or the
+        // predicate's (logical) column names aren't those of the file
+        let read_predicate = self.predicate.clone().filter(|_| {
+            skip_row_groups
+                && add.deletion_vector.is_none()
+                && self.column_mapping_mode == ColumnMappingMode::None
+        });
+        let read_results = engine.get_parquet_handler().read_parquet_files(
+            &[meta],
+            self.physical_schema.clone(),
+            read_predicate,
+        )?;
+
+        let read_evaluator = if needs_transform(self.have_partition_cols, self.column_mapping_mode)
+        {
+            let read_expression = transform_expression(
+                &self.logical_schema,
+                &self.all_fields,
+                &add.partition_values,
+            )?;
             debug!("Final expression for read: {read_expression:?}");
+            Some(engine.get_expression_handler().get_evaluator(
+                self.physical_schema.clone(),
+                read_expression,
+                self.logical_schema.clone().into(),
+            )?)
+        } else {
+            None
+        };
+
+        // evaluate the predicate on the logical data, before dropping columns that are only read
+        let residual_evaluator = self
+            .predicate
+            .as_ref()
+            .filter(|_| self.residual_applied)
+            .map(|predicate| {
+                engine.get_expression_handler().get_evaluator(
+                    self.logical_schema.clone(),
+                    predicate.clone(),
+                    DataType::BOOLEAN,
+                )
+            })
+            .transpose()?;
+
+        // drop the columns that are only read, not output
+        let output_evaluator = self.output_schema.as_ref().map(|output_schema| {
+            let output_expression = Expression::Struct(
+                output_schema
+                    .fields()
+                    .map(|field| Expression::column(field.name()))
+                    .collect(),
+            );
+            engine.get_expression_handler().get_evaluator(
+                self.logical_schema.clone(),
+                output_expression,
+                output_schema.clone().into(),
+            )
+        });
+        let output_evaluator = output_evaluator.transpose()?;
+        let output_field_metadata = self.output_field_metadata.clone();
+        let expression_handler = engine.get_expression_handler();
+
+        // the deletion vector is only read along with the first batch of the file, so that a file
+        // that is opened (e.g. read ahead) but never consumed doesn't fetch it
+        let mut deletion_vector = add.deletion_vector.map(|dv_descriptor| {
+            let fs_client = engine.get_file_system_client();
+            (dv_descriptor, fs_client, self.snapshot.table_root.clone())
+        });
+        let dv_cache = dv_cache.clone();
+        let mut dv_bools: Option<Arc<Vec<bool>>> = None;
+        let mut dv_error = None;
+        // the index in the file of the first row of the next result
+        let mut offset = 0;
 
-            let dv_treemap = add
-                .deletion_vector
+        Ok(read_results.map(move |read_result| {
+            if let Some((dv_descriptor, fs_client, table_root)) = deletion_vector.take() {
+                match dv_cache.get_or_read(&dv_descriptor, fs_client, &table_root) {
+                    Ok(bools) => dv_bools = Some(bools),
+                    Err(err) => dv_error = Some(err.to_string()),
+                }
+            }
+            // the rows of every batch of a file without its deletion vector are unknown
+            let read_result = match dv_error {
+                Some(ref err) => Err(Error::deletion_vector(err)),
+                None => read_result,
+            };
+            let len = if let Ok(ref res) = read_result {
+                res.length()
+            } else {
+                0
+            };
+            let file_metadata = read_result
                 .as_ref()
-                .map(|dv_descriptor| {
-                    let fs_client = engine.get_file_system_client();
-                    dv_descriptor.read(fs_client, &self.snapshot.table_root)
-                })
-                .transpose()?;
+                .ok()
+                .and_then(|data| output_field_metadata.file_metadata(data.as_ref()));
 
-            let mut dv_mask = dv_treemap.map(treemap_to_bools);
+            let read_result = match read_evaluator {
+                Some(ref read_evaluator) => {
+                    read_result.and_then(|data| read_evaluator.evaluate(data.as_ref()))
+                }
+                None => {
+                    // if we don't have partition columns, the result is just what we read
+                    read_result
+                }
+            };
+            let mut selected = vec![];
+            let read_result = match residual_evaluator {
+                Some(ref residual_evaluator) => read_result.and_then(|data| {
+                    residual_evaluator.evaluate_into(data.as_ref(), &mut selected)?;
+                    Ok(data)
+                }),
+                None => read_result,
+            };
+            let read_result = match output_evaluator {
+                Some(ref output_evaluator) => {
+                    read_result.and_then(|data| output_evaluator.evaluate(data.as_ref()))
+                }
+                None => read_result,
+            };
+            let read_result = read_result.and_then(|data| {
+                output_field_metadata.apply(expression_handler.as_ref(), file_metadata, data)
+            });
 
-            for read_result in read_results {
-                let len = if let Ok(ref res) = read_result {
-                    res.length()
-                } else {
-                    0
-                };
+            // the part of the deletion vector's selection vector that covers this result. It may
+            // end before the result does
+            let dv_mask = dv_bools.as_ref().map(|bools| {
+                let start = offset.min(bools.len());
+                let end = (offset + len).min(bools.len());
+                bools[start..end].to_vec()
+            });
+            offset += len;
 
-                let read_result = match read_expression {
-                    Some(ref read_expression) => engine
-                        .get_expression_handler()
-                        .get_evaluator(
-                            self.physical_schema.clone(),
-                            read_expression.clone(),
-                            output_schema.clone(),
-                        )
-                        .evaluate(read_result?.as_ref()),
-                    None => {
-                        // if we don't have partition columns, the result is just what we read
-                        read_result
-                    }
-                };
+            let mask = match residual_evaluator {
+                Some(_) => {
+                    // rows past the end of the deletion vector mask are valid
+                    let mut mask = dv_mask.unwrap_or_default();
+                    mask.resize(selected.len(), true);
+                    mask.iter_mut()
+                        .zip(selected)
+                        .for_each(|(row, sel)| *row &= sel);
+                    Some(mask)
+                }
+                None => dv_mask,
+            };
+            ScanResult {
+                raw_data: read_result,
+                mask,
+            }
+        }))
+    }
+}
 
-                // need to split the dv_mask. what's left in dv_mask covers this result, and rest
-                // will cover the following results
-                let rest = dv_mask.as_mut().map(|mask| mask.split_off(len));
+/// The name of the column with the index of each row in its data file, added to the data returned
+/// by [`Scan::execute_with_row_indexes`]
+pub const ROW_INDEX_COLUMN_NAME: &str = "_row_index";
 
-                let scan_result = ScanResult {
-                    raw_data: read_result,
-                    mask: dv_mask,
-                };
-                dv_mask = rest;
-                results.push(scan_result);
+/// Apply the mask of `result` to its data, and add a [`ROW_INDEX_COLUMN_NAME`] column with the
+/// index of each remaining row in its file, where the first row of `result` has the index
+/// `offset`. Advances `offset` past the rows of `result`.
+#[cfg(any(feature = "default-engine", feature = "sync-engine"))]
+fn with_row_indexes(result: ScanResult, offset: &mut i64) -> DeltaResult<arrow_array::RecordBatch> {
+    use arrow_array::{Array, Int64Array, RecordBatch};
+    use arrow_schema::{DataType as ArrowDataType, Field, Schema as ArrowSchema};
+    use arrow_select::filter::{filter, filter_record_batch};
+
+    use crate::engine::arrow_data::ArrowEngineData;
+
+    let batch: RecordBatch = ArrowEngineData::try_from_engine_data(result.raw_data?)?.into();
+    require!(
+        batch
+            .schema()
+            .column_with_name(ROW_INDEX_COLUMN_NAME)
+            .is_none(),
+        Error::generic(format!(
+            "Can't add row indexes to data that already has a {ROW_INDEX_COLUMN_NAME} column"
+        ))
+    );
+    let num_rows = batch.num_rows() as i64;
+    let row_indexes = Int64Array::from_iter_values(*offset..*offset + num_rows);
+    *offset += num_rows;
+    let (batch, row_indexes) = match result.mask {
+        Some(mut mask) => {
+            // rows past the end of the mask are valid
+            mask.resize(batch.num_rows(), true);
+            let mask = mask.into();
+            (
+                filter_record_batch(&batch, &mask)?,
+                filter(&row_indexes, &mask)?,
+            )
+        }
+        None => (batch, Arc::new(row_indexes) as _),
+    };
+
+    let schema = batch.schema();
+    let mut fields: Vec<_> = schema.fields().iter().cloned().collect();
+    fields.push(Arc::new(Field::new(
+        ROW_INDEX_COLUMN_NAME,
+        ArrowDataType::Int64,
+        false,
+    )));
+    let mut columns = batch.columns().to_vec();
+    columns.push(row_indexes);
+    debug_assert!(columns
+        .iter()
+        .all(|column| column.len() == columns[0].len()));
+    Ok(RecordBatch::try_new(
+        Arc::new(ArrowSchema::new_with_metadata(
+            fields,
+            schema.metadata().clone(),
+        )),
+        columns,
+    )?)
+}
+
+/// The number of deletion vectors a [`DeletionVectorCache`] keeps
+const DELETION_VECTOR_CACHE_CAPACITY: usize = 16;
+
+type SelectionVectors = IndexMap<String, Arc<Vec<bool>>>;
+
+/// The deletion vectors read by a scan, as selection vectors by their unique id, so a deletion
+/// vector that several files share (e.g. files in the same deletion vector file with the same
+/// offset, after a restore) is usually only read and decoded once per scan. Only the
+/// [`DELETION_VECTOR_CACHE_CAPACITY`] most recently used deletion vectors are kept, and the files
+/// being read share them rather than copying them.
+#[derive(Clone, Default)]
+pub(crate) struct DeletionVectorCache {
+    /// The cached selection vectors, from least to most recently used
+    selection_vectors: Arc<Mutex<SelectionVectors>>,
+}
+
+impl DeletionVectorCache {
+    /// Get the selection vector of `dv_descriptor`, reading it if it isn't cached (anymore)
+    fn get_or_read(
+        &self,
+        dv_descriptor: &DeletionVectorDescriptor,
+        fs_client: Arc<dyn FileSystemClient>,
+        table_root: &Url,
+    ) -> DeltaResult<Arc<Vec<bool>>> {
+        let unique_id = dv_descriptor.unique_id();
+        {
+            let mut selection_vectors = self.lock_selection_vectors()?;
+            if let Some(index) = selection_vectors.get_index_of(&unique_id) {
+                let last = selection_vectors.len() - 1;
+                selection_vectors.move_index(index, last);
+                return Ok(selection_vectors[last].clone());
+            }
+        }
+        // don't hold the lock while reading, files with other deletion vectors may be opened
+        // (read ahead) concurrently
+        let bools = Arc::new(treemap_to_bools(dv_descriptor.read(fs_client, table_root)?));
+        let mut selection_vectors = self.lock_selection_vectors()?;
+        if let Some(bools) = selection_vectors.get(&unique_id) {
+            return Ok(bools.clone());
+        }
+        while selection_vectors.len() >= DELETION_VECTOR_CACHE_CAPACITY {
+            selection_vectors.shift_remove_index(0);
+        }
+        selection_vectors.insert(unique_id, bools.clone());
+        Ok(bools)
+    }
+
+    fn lock_selection_vectors(&self) -> DeltaResult<MutexGuard<'_, SelectionVectors>> {
+        self.selection_vectors
+            .lock()
+            .map_err(|_| Error::generic("Deletion vector cache lock poisoned"))
+    }
+}
+
+/// Read the data of `scan` with `engine` lazily, opening the files of the scan with its read-ahead
+/// (see [`Scan::execute_iter`]). The scan and the engine are either borrowed or shared, so that the
+/// results can outlive the caller, e.g. to be read on another thread.
+pub(crate) fn execute_lazily<'a>(
+    scan: impl Deref<Target = Scan> + Clone + Send + 'a,
+    engine: impl Deref<Target = dyn Engine + 'a> + Send + 'a,
+) -> DeltaResult<impl Iterator<Item = DeltaResult<ScanResult>> + Send + 'a> {
+    debug!(
+        "Executing scan with logical schema {:#?} and physical schema {:#?}",
+        scan.logical_schema, scan.physical_schema
+    );
+    scan.check_pruning(&*engine)?;
+    let file_scan = scan.clone();
+    let dv_cache = DeletionVectorCache::default();
+    let files = scan.files(&*engine)?.map(move |add_result| {
+        add_result.and_then(|add| file_scan.execute_file(&*engine, add, &dv_cache))
+    });
+    Ok(scan
+        .read_ahead(files)
+        .flat_map(|file_results| match file_results {
+            Ok(scan_results) => Either::Left(scan_results.map(Ok)),
+            Err(err) => Either::Right(std::iter::once(Err(err))),
+        }))
+}
+
+/// Stream the items of the (blocking) iterator that `items` creates. Creating the iterator and
+/// advancing it runs with [`tokio::task::spawn_blocking`], one item at a time, so that polling
+/// the stream never blocks. The stream ends after the first error creating the iterator.
+#[cfg(feature = "default-engine")]
+fn blocking_stream<T, I>(
+    items: impl FnOnce() -> DeltaResult<I> + Send + 'static,
+) -> BoxStream<'static, DeltaResult<T>>
+where
+    T: Send + 'static,
+    I: Iterator<Item = DeltaResult<T>> + Send + 'static,
+{
+    stream::unfold(Some(Either::Left(items)), |state| async move {
+        let state = state?;
+        let read = tokio::task::spawn_blocking(move || {
+            let mut items = match state {
+                Either::Left(items) => items()?,
+                Either::Right(items) => items,
+            };
+            let item = items.next();
+            Ok((items, item))
+        });
+        match read.await {
+            Ok(Ok((items, Some(item)))) => Some((item, Some(Either::Right(items)))),
+            Ok(Ok((_, None))) => None,
+            Ok(Err(err)) => Some((Err(err), None)),
+            Err(err) => Some((Err(Error::join_failure(err)), None)),
+        }
+    })
+    .boxed()
+}
+
+/// An iterator that keeps up to `depth` items of its inner iterator pulled ahead of the item it
+/// returns. This bounds the number of files open at once, but not their memory (see
+/// [`ScanBuilder::with_read_ahead`]).
+pub(crate) struct ReadAhead<I: Iterator> {
+    files: I,
+    opened: std::collections::VecDeque<I::Item>,
+    depth: usize,
+}
+
+impl<I: Iterator> Iterator for ReadAhead<I> {
+    type Item = I::Item;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.opened.len() <= self.depth {
+            match self.files.next() {
+                Some(file) => self.opened.push_back(file),
+                None => break,
             }
         }
-        Ok(results)
+        self.opened.pop_front()
     }
 }
 
@@ -369,26 +1410,144 @@ pub fn scan_row_schema() -> Schema {
     log_replay::SCAN_ROW_SCHEMA.as_ref().clone()
 }
 
-fn parse_partition_value(raw: Option<&String>, data_type: &DataType) -> DeltaResult<Scalar> {
+/// The value of a partition column that is null, as written by Delta writers that serialize
+/// null partition values as a string instead of leaving them out of `partitionValues`
+const NULL_PARTITION_VALUE: &str = "null";
+
+/// Parse `raw`, the serialized value of a partition column of type `data_type` in the
+/// `partitionValues` of an `add` action, following the Delta [partition value serialization]
+/// rules:
+///
+/// - the string `null` is a null value, like a missing entry in `partitionValues`,
+/// - the empty string is the empty value of string and binary columns (other types have no empty
+///   value, so for them it is null),
+/// - booleans are `true` or `false`, dates are `yyyy-MM-dd`, and timestamps are
+///   `yyyy-MM-dd HH:mm:ss[.SSSSSS]` or ISO 8601 (e.g. `2024-01-01T12:00:00.000000Z`), with any
+///   digits past microseconds ignored,
+/// - decimals keep the scale of the column, e.g. `-1.5` is `-1.50` in a `decimal(5, 2)` column.
+///
+/// [partition value serialization]: https://github.com/delta-io/delta/blob/master/PROTOCOL.md#partition-value-serialization
+pub fn parse_partition_value(raw: &str, data_type: &DataType) -> DeltaResult<Scalar> {
+    match data_type {
+        DataType::Primitive(_) if raw == NULL_PARTITION_VALUE => {
+            Ok(Scalar::Null(data_type.clone()))
+        }
+        DataType::Primitive(PrimitiveType::String) => Ok(Scalar::String(raw.to_string())),
+        DataType::Primitive(PrimitiveType::Binary) => Ok(Scalar::Binary(raw.as_bytes().to_vec())),
+        DataType::Primitive(primitive) => primitive.parse_scalar(raw),
+        _ => Err(Error::generic(format!(
+            "Unexpected partition column type: {data_type:?}"
+        ))),
+    }
+}
+
+/// Get the value of the partition column of type `data_type` whose serialized value in the
+/// `partitionValues` of an `add` action is `raw`, which is `None` for a null value
+fn partition_value(raw: Option<&String>, data_type: &DataType) -> DeltaResult<Scalar> {
     match raw {
-        Some(v) => match data_type {
-            DataType::Primitive(primitive) => primitive.parse_scalar(v),
-            _ => Err(Error::generic(format!(
-                "Unexpected partition column type: {data_type:?}"
-            ))),
-        },
-        _ => Ok(Scalar::Null(data_type.clone())),
+        Some(raw) => parse_partition_value(raw, data_type),
+        None => Ok(Scalar::Null(data_type.clone())),
     }
 }
 
-/// Get the state needed to process a scan. In particular this returns a triple of
-/// (all_fields_in_query, fields_to_read_from_parquet, have_partition_cols) where:
-/// - all_fields_in_query - all fields in the query as [`ColumnType`] enums
-/// - fields_to_read_from_parquet - Which fields should be read from the raw parquet files. This takes
-///   into account column mapping
-/// - have_partition_cols - boolean indicating if we have partition columns in this query
-fn get_state_info(
-    logical_schema: &Schema,
+/// Check that a (possibly nested) column exists in `schema`
+fn column_exists(schema: &StructType, column: &ColumnName) -> bool {
+    let mut current = schema;
+    let mut path = column.path().iter().peekable();
+    while let Some(name) = path.next() {
+        match (current.field(name).map(StructField::data_type), path.peek()) {
+            (Some(_), None) => return true,
+            (Some(DataType::Struct(inner)), Some(_)) => current = inner,
+            _ => return false,
+        }
+    }
+    false
+}
+
+/// Project `schema` to the (possibly nested, e.g. `a.b`) columns of `columns`, which are pairs of
+/// the full name of a column and the part of it still to resolve in `schema`. Fields are in the
+/// order they are first named in, and a struct field named only through some of its fields keeps
+/// just those fields.
+fn project_columns<'a>(
+    schema: &StructType,
+    columns: impl IntoIterator<Item = (&'a str, &'a str)>,
+) -> DeltaResult<StructType> {
+    // for each field: the first column naming it, whether it is named itself, and the columns
+    // naming its fields
+    let mut projected = IndexMap::new();
+    for (column, path) in columns {
+        let (name, rest) = match path.split_once('.') {
+            Some((name, rest)) => (name, Some(rest)),
+            None => (path, None),
+        };
+        let (_, whole, nested): &mut (_, _, Vec<_>) =
+            projected.entry(name).or_insert((column, false, vec![]));
+        match rest {
+            Some(rest) => nested.push((column, rest)),
+            None => *whole = true,
+        }
+    }
+    let missing =
+        |column: &str| Error::missing_column(format!("Column {column} is not in the table schema"));
+    let fields = projected
+        .into_iter()
+        .map(|(name, (column, whole, nested))| {
+            let field = schema.field(name).ok_or_else(|| missing(column))?;
+            // nested columns are resolved even if the whole field is named, to validate them
+            let inner = match (field.data_type(), nested.first()) {
+                (_, None) => return Ok(field.clone()),
+                (DataType::Struct(inner), Some(_)) => project_columns(inner, nested)?,
+                (_, Some((column, _))) => return Err(missing(column)),
+            };
+            Ok(match whole {
+                true => field.clone(),
+                false => StructField {
+                    data_type: inner.into(),
+                    ..field.clone()
+                },
+            })
+        })
+        .try_collect()?;
+    Ok(StructType::new(fields))
+}
+
+/// Add a problem to `problems` for every (possibly nested) field of `requested`, which is nested
+/// at `prefix`, that isn't a field of `schema` with the same type. A struct field only needs to
+/// have some of the fields of the table's struct.
+fn check_fields(
+    schema: &StructType,
+    requested: &StructType,
+    prefix: &str,
+    problems: &mut Vec<String>,
+) {
+    for field in requested.fields() {
+        let column = format!("{prefix}{}", field.name());
+        match schema.field(field.name()) {
+            None => problems.push(format!(
+                "Column {column} does not exist in the table schema"
+            )),
+            Some(table_field) => match (table_field.data_type(), field.data_type()) {
+                (DataType::Struct(inner), DataType::Struct(requested)) => {
+                    check_fields(inner, requested, &format!("{column}."), problems)
+                }
+                (table_type, data_type) if table_type == data_type => {}
+                (table_type, data_type) => problems.push(format!(
+                    "Column {column} has type {data_type} in the scan schema, but {table_type} in \
+                     the table schema"
+                )),
+            },
+        }
+    }
+}
+
+/// Get the state needed to process a scan. In particular this returns a triple of
+/// (all_fields_in_query, fields_to_read_from_parquet, have_partition_cols) where:
+/// - all_fields_in_query - all fields in the query as [`ColumnType`] enums
+/// - fields_to_read_from_parquet - Which fields should be read from the raw parquet files. This takes
+///   into account column mapping
+/// - have_partition_cols - boolean indicating if we have partition columns in this query
+pub(crate) fn get_state_info(
+    logical_schema: &Schema,
     partition_columns: &[String],
     column_mapping_mode: ColumnMappingMode,
 ) -> DeltaResult<(Vec<ColumnType>, Vec<StructField>, bool)> {
@@ -401,7 +1560,11 @@ fn get_state_info(
         .fields()
         .enumerate()
         .map(|(index, logical_field)| -> DeltaResult<_> {
-            if partition_columns.contains(logical_field.name()) {
+            // void columns have no physical representation, so (like a partition column without
+            // a value) they are materialized as a null literal instead of being read
+            if partition_columns.contains(logical_field.name())
+                || logical_field.data_type() == &DataType::VOID
+            {
                 // Store the index into the schema for this field. When we turn it into an
                 // expression in the inner loop, we will index into the schema and get the name and
                 // data type, which we need to properly materialize the column.
@@ -434,7 +1597,7 @@ pub fn transform_to_logical(
     engine: &dyn Engine,
     data: Box<dyn EngineData>,
     global_state: &GlobalScanState,
-    partition_values: &std::collections::HashMap<String, String>,
+    partition_values: &HashMap<String, String>,
 ) -> DeltaResult<Box<dyn EngineData>> {
     let (all_fields, _read_fields, have_partition_cols) = get_state_info(
         &global_state.logical_schema,
@@ -442,40 +1605,254 @@ pub fn transform_to_logical(
         global_state.column_mapping_mode,
     )?;
     let read_schema = global_state.read_schema.clone();
-    if have_partition_cols || global_state.column_mapping_mode != ColumnMappingMode::None {
+    let output_field_metadata = OutputFieldMetadata::try_new(
+        global_state.field_metadata_mode,
+        &global_state.logical_schema,
+        &global_state.logical_schema,
+        &all_fields,
+    )?;
+    let file_metadata = output_field_metadata.file_metadata(data.as_ref());
+    let expression_handler = engine.get_expression_handler();
+    let data = if needs_transform(have_partition_cols, global_state.column_mapping_mode) {
         // need to add back partition cols and/or fix-up mapped columns
-        let all_fields = all_fields
-            .iter()
-            .map(|field| match field {
-                ColumnType::Partition(field_idx) => {
-                    let field = global_state
-                        .logical_schema
-                        .fields
-                        .get_index(*field_idx)
-                        .ok_or_else(|| {
-                            Error::generic("logical schema did not contain expected field, can't transform data")
-                        })?.1;
-                    let value_expression = parse_partition_value(
-                        partition_values.get(field.name()),
-                        field.data_type(),
-                    )?;
-                    Ok::<Expression, Error>(Expression::Literal(value_expression))
-                }
-                ColumnType::Selected(field_name) => Ok(Expression::column(field_name)),
-            })
-            .try_collect()?;
-        let read_expression = Expression::Struct(all_fields);
-        let result = engine
-            .get_expression_handler()
+        let read_expression =
+            transform_expression(&global_state.logical_schema, &all_fields, partition_values)?;
+        expression_handler
             .get_evaluator(
                 read_schema,
-                read_expression.clone(),
+                read_expression,
                 global_state.logical_schema.clone().into(),
-            )
-            .evaluate(data.as_ref())?;
-        Ok(result)
+            )?
+            .evaluate(data.as_ref())?
     } else {
-        Ok(data)
+        data
+    };
+    output_field_metadata.apply(expression_handler.as_ref(), file_metadata, data)
+}
+
+/// Check if data read from a file needs to be transformed to produce the logical output of a scan
+fn needs_transform(have_partition_cols: bool, column_mapping_mode: ColumnMappingMode) -> bool {
+    have_partition_cols || column_mapping_mode != ColumnMappingMode::None
+}
+
+/// Get the expression that transforms data read from a file with the given `partition_values` into
+/// the logical output of a scan
+pub(crate) fn transform_expression(
+    logical_schema: &Schema,
+    all_fields: &[ColumnType],
+    partition_values: &HashMap<String, String>,
+) -> DeltaResult<Expression> {
+    let all_fields = all_fields
+        .iter()
+        .map(|field| match field {
+            ColumnType::Partition(field_idx) => {
+                let field = logical_schema
+                    .fields
+                    .get_index(*field_idx)
+                    .ok_or_else(|| {
+                        Error::generic(
+                            "logical schema did not contain expected field, can't transform data",
+                        )
+                    })?
+                    .1;
+                let value_expression =
+                    partition_value(partition_values.get(field.name()), field.data_type())?;
+                Ok::<Expression, Error>(Expression::Literal(value_expression))
+            }
+            ColumnType::Selected(field_name) => Ok(Expression::column(field_name)),
+        })
+        .try_collect()?;
+    Ok(Expression::Struct(all_fields))
+}
+
+/// Get the type of the logical output of a scan, with field metadata set based on
+/// `field_metadata_mode`
+fn output_type(logical_schema: &Schema, field_metadata_mode: FieldMetadataMode) -> DataType {
+    match field_metadata_mode {
+        FieldMetadataMode::Strip => strip_metadata(&logical_schema.clone().into()),
+        FieldMetadataMode::FromLogicalSchema | FieldMetadataMode::Preserve => {
+            logical_schema.clone().into()
+        }
+    }
+}
+
+/// `data_type` without the metadata of any of its (possibly nested) fields
+fn strip_metadata(data_type: &DataType) -> DataType {
+    match data_type {
+        DataType::Struct(fields) => StructType::new(
+            fields
+                .fields()
+                .map(|field| {
+                    StructField::new(
+                        field.name(),
+                        strip_metadata(field.data_type()),
+                        field.is_nullable(),
+                    )
+                })
+                .collect(),
+        )
+        .into(),
+        DataType::Array(array) => {
+            ArrayType::new(strip_metadata(array.element_type()), array.contains_null()).into()
+        }
+        DataType::Map(map) => MapType::new(
+            strip_metadata(map.key_type()),
+            strip_metadata(map.value_type()),
+            map.value_contains_null(),
+        )
+        .into(),
+        DataType::Primitive(_) => data_type.clone(),
+    }
+}
+
+/// Sets the field metadata of the data returned by a scan as configured by its
+/// [`FieldMetadataMode`], once the data read from a file has been transformed to the output schema
+/// of the scan. The data is only evaluated again (by an [`crate::ExpressionEvaluator`] of the engine) if
+/// it isn't arrow data, whose fields are set directly instead.
+struct OutputFieldMetadata {
+    mode: FieldMetadataMode,
+    /// The output type of the scan, with the metadata of `mode`
+    output_type: DataType,
+    /// The names in the data files of the columns of the output, or `None` for the columns that
+    /// aren't read from them (e.g. partition columns)
+    #[cfg_attr(
+        not(any(feature = "default-engine", feature = "sync-engine")),
+        allow(dead_code)
+    )]
+    physical_names: Vec<Option<String>>,
+    /// The arrow schema of `output_type`
+    #[cfg(any(feature = "default-engine", feature = "sync-engine"))]
+    arrow_schema: Option<arrow_schema::Schema>,
+}
+
+impl OutputFieldMetadata {
+    /// Set the metadata of data with the schema `output_schema`, whose columns are read from
+    /// the columns of the logical schema given by `all_fields` (see [`get_state_info`])
+    fn try_new(
+        mode: FieldMetadataMode,
+        output_schema: &Schema,
+        logical_schema: &Schema,
+        all_fields: &[ColumnType],
+    ) -> DeltaResult<Self> {
+        let physical_names = output_schema
+            .fields()
+            .map(|field| {
+                let index = logical_schema.fields.get_index_of(field.name())?;
+                match all_fields.get(index)? {
+                    ColumnType::Selected(physical_name) => Some(physical_name.clone()),
+                    ColumnType::Partition(_) => None,
+                }
+            })
+            .collect();
+        let output_type = output_type(output_schema, mode);
+        #[cfg(any(feature = "default-engine", feature = "sync-engine"))]
+        let arrow_schema = match (&output_type, mode) {
+            (DataType::Struct(schema), FieldMetadataMode::FromLogicalSchema)
+            | (DataType::Struct(schema), FieldMetadataMode::Strip) => {
+                Some(arrow_schema::Schema::try_from(schema.as_ref())?)
+            }
+            _ => None,
+        };
+        Ok(Self {
+            mode,
+            output_type,
+            physical_names,
+            #[cfg(any(feature = "default-engine", feature = "sync-engine"))]
+            arrow_schema,
+        })
+    }
+
+    /// The metadata of the columns of the output in `data`, as read from a data file, if they
+    /// are needed (i.e. to preserve them) and known
+    fn file_metadata(&self, data: &dyn EngineData) -> Option<Vec<HashMap<String, String>>> {
+        if self.mode != FieldMetadataMode::Preserve {
+            return None;
+        }
+        #[cfg(any(feature = "default-engine", feature = "sync-engine"))]
+        {
+            use crate::engine::arrow_data::ArrowEngineData;
+
+            let batch = data.as_any().downcast_ref::<ArrowEngineData>()?;
+            let schema = batch.record_batch().schema();
+            let metadata = self.physical_names.iter().map(|physical_name| {
+                physical_name
+                    .as_ref()
+                    .and_then(|name| schema.field_with_name(name).ok())
+                    .map(|field| field.metadata().clone())
+                    .unwrap_or_default()
+            });
+            Some(metadata.collect())
+        }
+        #[cfg(not(any(feature = "default-engine", feature = "sync-engine")))]
+        {
+            let _ = data;
+            None
+        }
+    }
+
+    /// Set the metadata of the fields of `data`, the output of a scan, given the
+    /// [`Self::file_metadata`] of the data it was transformed from
+    fn apply(
+        &self,
+        expression_handler: &dyn ExpressionHandler,
+        file_metadata: Option<Vec<HashMap<String, String>>>,
+        data: Box<dyn EngineData>,
+    ) -> DeltaResult<Box<dyn EngineData>> {
+        #[cfg(any(feature = "default-engine", feature = "sync-engine"))]
+        {
+            use crate::engine::arrow_data::ArrowEngineData;
+            use crate::engine::arrow_utils::with_field_metadata;
+
+            if let Some(arrow_data) = data.as_any().downcast_ref::<ArrowEngineData>() {
+                let batch = arrow_data.record_batch();
+                let batch = match (&self.arrow_schema, file_metadata) {
+                    (Some(arrow_schema), _) => with_field_metadata(batch, arrow_schema)?,
+                    (None, Some(file_metadata)) => {
+                        let schema = batch.schema();
+                        let fields: Vec<_> = schema
+                            .fields()
+                            .iter()
+                            .zip(file_metadata)
+                            .map(|(field, metadata)| field.as_ref().clone().with_metadata(metadata))
+                            .collect();
+                        let schema = arrow_schema::Schema::new_with_metadata(
+                            fields,
+                            schema.metadata().clone(),
+                        );
+                        arrow_array::RecordBatch::try_new(
+                            Arc::new(schema),
+                            batch.columns().to_vec(),
+                        )?
+                    }
+                    (None, None) => return Ok(data),
+                };
+                return Ok(Box::new(ArrowEngineData::new(batch)));
+            }
+        }
+        match self.mode {
+            FieldMetadataMode::Preserve => {
+                let _ = file_metadata;
+                Ok(data)
+            }
+            FieldMetadataMode::FromLogicalSchema | FieldMetadataMode::Strip => {
+                let DataType::Struct(output_schema) = &self.output_type else {
+                    return Ok(data);
+                };
+                let expression = Expression::Struct(
+                    output_schema
+                        .fields()
+                        .map(|field| Expression::column(field.name()))
+                        .collect(),
+                );
+                expression_handler
+                    .get_evaluator(
+                        Arc::new(output_schema.as_ref().clone()),
+                        expression,
+                        self.output_type.clone(),
+                    )?
+                    .evaluate(data.as_ref())
+            }
+        }
     }
 }
 
@@ -524,6 +1901,17 @@ pub(crate) mod test_utils {
         ArrowEngineData::try_from_engine_data(parsed).unwrap()
     }
 
+    /// A batch of the actions serialized as the JSON lines `json_strings`
+    pub(crate) fn actions_batch(json_strings: &[&str]) -> Box<ArrowEngineData> {
+        let handler = SyncJsonHandler {};
+        let json_strings: StringArray = json_strings.to_vec().into();
+        let output_schema = Arc::new(get_log_schema().clone());
+        let parsed = handler
+            .parse_json(string_array_to_engine_data(json_strings), output_schema)
+            .unwrap();
+        ArrowEngineData::try_from_engine_data(parsed).unwrap()
+    }
+
     // add batch with a removed file
     pub(crate) fn add_batch_with_remove() -> Box<ArrowEngineData> {
         let handler = SyncJsonHandler {};
@@ -566,7 +1954,8 @@ pub(crate) mod test_utils {
             batch.into_iter().map(|batch| Ok((batch as _, true))),
             &table_schema,
             &None,
-        );
+        )
+        .unwrap();
         let mut batch_count = 0;
         for res in iter {
             let (batch, sel) = res.unwrap();
@@ -588,9 +1977,20 @@ pub(crate) mod test_utils {
 mod tests {
     use std::path::PathBuf;
 
+    use arrow_array::cast::AsArray;
+    use arrow_array::types::{Int32Type, Int64Type};
+    use arrow_array::{Array, RecordBatch, StructArray};
+    use arrow_schema::{Field, Schema as ArrowSchema};
+    use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+    use parquet::arrow::ArrowWriter;
+
     use super::*;
+    use crate::engine::arrow_data::ArrowEngineData;
     use crate::engine::sync::SyncEngine;
-    use crate::schema::PrimitiveType;
+    use crate::schema::MetadataValue;
+    use crate::test_utils::{
+        add_action, metadata_action, parquet_bytes, protocol_action, TestTable,
+    };
     use crate::Table;
 
     #[test]
@@ -630,6 +2030,201 @@ mod tests {
         assert_eq!(num_rows, 10)
     }
 
+    #[test]
+    fn test_scan_field_metadata() {
+        let path =
+            std::fs::canonicalize(PathBuf::from("./tests/data/table-without-dv-small/")).unwrap();
+        let url = url::Url::from_directory_path(path).unwrap();
+        let engine = SyncEngine::new();
+        let snapshot = Arc::new(Table::new(url).snapshot(&engine, None).unwrap());
+        let field = StructField::new("value", DataType::LONG, true)
+            .with_metadata([("custom", MetadataValue::String("marker".to_string()))]);
+        let expected_metadata = arrow_schema::Field::try_from(&field)
+            .unwrap()
+            .metadata()
+            .clone();
+        let schema = Arc::new(StructType::new(vec![field]));
+
+        let read_metadata = |mode| {
+            let scan = ScanBuilder::new(snapshot.clone())
+                .with_schema(schema.clone())
+                .with_field_metadata(mode)
+                .build()
+                .unwrap();
+            let results = scan.execute(&engine).unwrap();
+            assert_eq!(results.len(), 1);
+            let data = results.into_iter().next().unwrap().raw_data.unwrap();
+            let batch = ArrowEngineData::try_from_engine_data(data).unwrap();
+            batch.record_batch().schema().field(0).metadata().clone()
+        };
+
+        assert_eq!(
+            read_metadata(FieldMetadataMode::FromLogicalSchema),
+            expected_metadata
+        );
+        assert!(read_metadata(FieldMetadataMode::Strip).is_empty());
+        assert!(!read_metadata(FieldMetadataMode::Preserve).contains_key("custom"));
+    }
+
+    #[test]
+    fn test_scan_field_metadata_with_partitions_and_column_mapping() {
+        use arrow_array::{ArrayRef, Int64Array, RecordBatch, StructArray};
+        use arrow_schema::{DataType as ArrowDataType, Field as ArrowField};
+
+        // the data file has the physical column `col-s`, a struct with the field `x`, and both
+        // have metadata of their own
+        let file_metadata = |value: &str| HashMap::from([("file".to_string(), value.to_string())]);
+        let x = ArrowField::new("x", ArrowDataType::Int64, true).with_metadata(file_metadata("x"));
+        let s: ArrayRef = Arc::new(StructArray::from(vec![(
+            Arc::new(x),
+            Arc::new(Int64Array::from(vec![1, 2])) as ArrayRef,
+        )]));
+        let s_field =
+            ArrowField::new("col-s", s.data_type().clone(), true).with_metadata(file_metadata("s"));
+        let batch =
+            RecordBatch::try_new(Arc::new(arrow_schema::Schema::new(vec![s_field])), vec![s])
+                .unwrap();
+        let tmp = TestTable::new();
+        let data = parquet_bytes(&batch, None);
+        std::fs::write(tmp.path().join("a.parquet"), &data).unwrap();
+        let size = data.len();
+
+        let schema = serde_json::json!({
+            "type": "struct",
+            "fields": [{
+                "name": "p",
+                "type": "string",
+                "nullable": true,
+                "metadata": {
+                    "custom": "partition",
+                    "delta.columnMapping.id": 1,
+                    "delta.columnMapping.physicalName": "col-p"
+                }
+            }, {
+                "name": "s",
+                "type": {
+                    "type": "struct",
+                    "fields": [{
+                        "name": "x",
+                        "type": "long",
+                        "nullable": true,
+                        "metadata": {
+                            "custom": "nested",
+                            "delta.columnMapping.id": 3,
+                            "delta.columnMapping.physicalName": "x"
+                        }
+                    }]
+                },
+                "nullable": true,
+                "metadata": {
+                    "custom": "struct",
+                    "delta.columnMapping.id": 2,
+                    "delta.columnMapping.physicalName": "col-s"
+                }
+            }]
+        });
+        let add = serde_json::json!({"add": {
+            "path": "a.parquet",
+            "partitionValues": {"p": "a"},
+            "size": size,
+            "modificationTime": 1000,
+            "dataChange": true
+        }});
+        tmp.commit(
+            0,
+            [
+                protocol_action(2, 5),
+                metadata_action(&schema, &["p"], &[("delta.columnMapping.mode", "name")]),
+                add,
+            ],
+        );
+        let engine = SyncEngine::new();
+        let snapshot = Arc::new(Table::new(tmp.location()).snapshot(&engine, None).unwrap());
+
+        let read = |mode| {
+            let scan = ScanBuilder::new(snapshot.clone())
+                .with_field_metadata(mode)
+                .build()
+                .unwrap();
+            let mut results = scan.execute(&engine).unwrap();
+            assert_eq!(results.len(), 1);
+            let data = results.pop().unwrap().raw_data.unwrap();
+            let batch = ArrowEngineData::try_from_engine_data(data).unwrap();
+            let batch = batch.record_batch().clone();
+            assert_eq!(batch.num_rows(), 2);
+            batch
+        };
+        // the metadata of the fields of `s` and of its field `x`
+        let metadata = |schema: &arrow_schema::Schema| {
+            let s = schema.field_with_name("s").unwrap();
+            let ArrowDataType::Struct(fields) = s.data_type() else {
+                panic!("Expected a struct, got {}", s.data_type());
+            };
+            (
+                schema.field_with_name("p").unwrap().metadata().clone(),
+                s.metadata().clone(),
+                fields[0].metadata().clone(),
+            )
+        };
+
+        let logical_schema = arrow_schema::Schema::try_from(snapshot.schema()).unwrap();
+        let batch = read(FieldMetadataMode::FromLogicalSchema);
+        let (p, s, x) = metadata(&batch.schema());
+        assert_eq!((p.clone(), s.clone(), x.clone()), metadata(&logical_schema));
+        assert_eq!(p["custom"], "\"partition\"");
+        assert_eq!(s["custom"], "\"struct\"");
+        assert_eq!(x["custom"], "\"nested\"");
+
+        let batch = read(FieldMetadataMode::Strip);
+        let (p, s, x) = metadata(&batch.schema());
+        assert!(p.is_empty() && s.is_empty() && x.is_empty());
+
+        let batch = read(FieldMetadataMode::Preserve);
+        let (p, s, x) = metadata(&batch.schema());
+        assert!(p.is_empty());
+        assert_eq!(s, file_metadata("s"));
+        assert_eq!(x, file_metadata("x"));
+    }
+
+    #[test]
+    fn test_scan_schema_override() {
+        let path =
+            std::fs::canonicalize(PathBuf::from("./tests/data/table-without-dv-small/")).unwrap();
+        let url = url::Url::from_directory_path(path).unwrap();
+        let engine = SyncEngine::new();
+        let snapshot = Arc::new(Table::new(url).snapshot(&engine, None).unwrap());
+        // `missing` is in neither the table schema nor the data file
+        let schema = Arc::new(StructType::new(vec![
+            StructField::new("missing", DataType::STRING, true),
+            StructField::new("value", DataType::LONG, true),
+        ]));
+
+        let scan = ScanBuilder::new(snapshot.clone())
+            .with_schema(schema.clone())
+            .build()
+            .unwrap();
+        assert!(scan.validate(&engine).is_err());
+
+        let scan = ScanBuilder::new(snapshot)
+            .with_schema_override(schema.clone())
+            .build()
+            .unwrap();
+        assert_eq!(scan.schema(), &schema);
+        scan.validate(&engine).unwrap();
+        let results = scan.execute(&engine).unwrap();
+        assert_eq!(results.len(), 1);
+        let data = results.into_iter().next().unwrap().raw_data.unwrap();
+        let batch: RecordBatch = ArrowEngineData::try_from_engine_data(data).unwrap().into();
+        assert_eq!(batch.num_rows(), 10);
+        assert_eq!(batch.column(0).null_count(), 10);
+        let values: Vec<_> = batch
+            .column(1)
+            .as_primitive::<Int64Type>()
+            .values()
+            .to_vec();
+        assert_eq!(values, (0..10).collect::<Vec<_>>());
+    }
+
     #[test]
     fn test_get_partition_value() {
         let cases = [
@@ -662,16 +2257,1252 @@ mod tests {
                 PrimitiveType::Timestamp,
                 Scalar::Timestamp(123456),
             ),
+            (
+                "1970-01-01T01:00:00.000001Z",
+                PrimitiveType::Timestamp,
+                Scalar::Timestamp(3_600_000_001),
+            ),
+            (
+                "1970-01-01T01:00:00+01:00",
+                PrimitiveType::Timestamp,
+                Scalar::Timestamp(0),
+            ),
+            (
+                "1970-01-01 01:00:00",
+                PrimitiveType::TimestampNtz,
+                Scalar::TimestampNtz(3_600_000_000),
+            ),
+            (
+                "1970-01-01T00:00:00.5",
+                PrimitiveType::TimestampNtz,
+                Scalar::TimestampNtz(500_000),
+            ),
+            // booleans are only `true` and `false`, in any case
+            ("false", PrimitiveType::Boolean, Scalar::Boolean(false)),
+            ("TRUE", PrimitiveType::Boolean, Scalar::Boolean(true)),
+            // decimals keep the scale of the column, also when negative
+            (
+                "-12.34",
+                PrimitiveType::Decimal(5, 2),
+                Scalar::Decimal(-1234, 5, 2),
+            ),
+            (
+                "-0.5",
+                PrimitiveType::Decimal(5, 2),
+                Scalar::Decimal(-50, 5, 2),
+            ),
+            (
+                "-3",
+                PrimitiveType::Decimal(5, 2),
+                Scalar::Decimal(-300, 5, 2),
+            ),
+            // the empty string is a value of string and binary columns, but null for others
+            ("", PrimitiveType::String, Scalar::String("".to_string())),
+            ("", PrimitiveType::Binary, Scalar::Binary(vec![])),
+            ("", PrimitiveType::Integer, Scalar::Null(DataType::INTEGER)),
+            // `null` is null for any type
+            (
+                "null",
+                PrimitiveType::String,
+                Scalar::Null(DataType::STRING),
+            ),
+            (
+                "null",
+                PrimitiveType::Boolean,
+                Scalar::Null(DataType::BOOLEAN),
+            ),
+            ("null", PrimitiveType::Date, Scalar::Null(DataType::DATE)),
         ];
 
         for (raw, data_type, expected) in &cases {
-            let value = parse_partition_value(
-                Some(&raw.to_string()),
-                &DataType::Primitive(data_type.clone()),
+            let value =
+                parse_partition_value(raw, &DataType::Primitive(data_type.clone())).unwrap();
+            assert_eq!(value, *expected, "{raw} as {data_type}");
+        }
+
+        for (raw, data_type) in [
+            ("yes", PrimitiveType::Boolean),
+            ("1", PrimitiveType::Boolean),
+            ("1.234", PrimitiveType::Decimal(5, 2)),
+            ("2024-1-1x", PrimitiveType::Date),
+            ("1970-01-01T00:00:00Z", PrimitiveType::TimestampNtz),
+        ] {
+            let result = parse_partition_value(raw, &DataType::Primitive(data_type.clone()));
+            assert!(result.is_err(), "{raw} as {data_type}");
+        }
+        assert_eq!(
+            partition_value(None, &DataType::LONG).unwrap(),
+            Scalar::Null(DataType::LONG)
+        );
+    }
+
+    #[test]
+    fn test_validate_scan() {
+        for table in ["table-without-dv-small", "table-with-dv-small"] {
+            let path =
+                std::fs::canonicalize(PathBuf::from(format!("./tests/data/{table}/"))).unwrap();
+            let url = url::Url::from_directory_path(path).unwrap();
+            let engine = SyncEngine::new();
+
+            let snapshot = Table::new(url).snapshot(&engine, None).unwrap();
+            let scan = ScanBuilder::new(snapshot).build().unwrap();
+            scan.validate(&engine).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_validate_scan_reports_all_problems() {
+        let tmp = tempfile::tempdir().unwrap();
+        let source = PathBuf::from("./tests/data/table-without-dv-small/");
+        std::fs::create_dir(tmp.path().join("_delta_log")).unwrap();
+        std::fs::copy(
+            source.join("_delta_log/00000000000000000000.json"),
+            tmp.path().join("_delta_log/00000000000000000000.json"),
+        )
+        .unwrap();
+        // note: we don't copy the data file, so the scan should report it as missing
+        let url = url::Url::from_directory_path(tmp.path()).unwrap();
+        let engine = SyncEngine::new();
+
+        let snapshot = Table::new(url).snapshot(&engine, None).unwrap();
+        let schema = Arc::new(StructType::new(vec![StructField::new(
+            "missing",
+            DataType::INTEGER,
+            true,
+        )]));
+        let scan = ScanBuilder::new(snapshot)
+            .with_schema(schema)
+            .with_predicate(Expression::column("also_missing").is_null())
+            .build()
+            .unwrap();
+        match scan.validate(&engine) {
+            Err(Error::ScanValidation(problems)) => {
+                assert_eq!(problems.len(), 3, "unexpected problems: {problems:?}");
+                assert!(problems[0].contains("missing does not exist"));
+                assert!(problems[1].contains("also_missing does not exist"));
+                assert!(problems[2].contains(
+                    "part-00000-517f5d32-9c95-48e8-82b4-0229cc194867-c000.snappy.parquet does not exist"
+                ));
+            }
+            res => panic!("Expected validation to fail, got {res:?}"),
+        }
+    }
+
+    #[test]
+    fn test_scan_with_columns() {
+        use arrow_array::{Int64Array, StringArray};
+
+        let struct_fields = vec![
+            Field::new("x", arrow_schema::DataType::Int64, true),
+            Field::new("y", arrow_schema::DataType::Utf8, true),
+        ];
+        let parquet_schema = Arc::new(ArrowSchema::new(vec![
+            Field::new("id", arrow_schema::DataType::Int64, true),
+            Field::new(
+                "s",
+                arrow_schema::DataType::Struct(struct_fields.clone().into()),
+                true,
+            ),
+            Field::new("other", arrow_schema::DataType::Int64, true),
+        ]));
+        let struct_array = StructArray::new(
+            struct_fields.into(),
+            vec![
+                Arc::new(Int64Array::from(vec![1, 2, 3])),
+                Arc::new(StringArray::from(vec!["a", "b", "c"])),
+            ],
+            None,
+        );
+        let batch = RecordBatch::try_new(
+            parquet_schema.clone(),
+            vec![
+                Arc::new(Int64Array::from(vec![0, 1, 2])),
+                Arc::new(struct_array),
+                Arc::new(Int64Array::from(vec![5, 10, 15])),
+            ],
+        )
+        .unwrap();
+        let tmp = TestTable::new();
+        let data = parquet_bytes(&batch, None);
+        std::fs::write(tmp.path().join("data.parquet"), &data).unwrap();
+        let size = data.len();
+        let schema = StructType::new(vec![
+            StructField::new("id", DataType::LONG, true),
+            StructField::new(
+                "s",
+                StructType::new(vec![
+                    StructField::new("x", DataType::LONG, true),
+                    StructField::new("y", DataType::STRING, true),
+                ]),
+                true,
+            ),
+            StructField::new("other", DataType::LONG, true),
+        ]);
+        tmp.commit(
+            0,
+            [
+                protocol_action(1, 2),
+                metadata_action(&schema, &[], &[]),
+                add_action("data.parquet", size),
+            ],
+        );
+        let engine = SyncEngine::new();
+        let snapshot = Arc::new(Table::new(tmp.location()).snapshot(&engine, None).unwrap());
+
+        let scan = ScanBuilder::new(snapshot.clone())
+            .with_columns(["s.y", "id"])
+            .with_predicate(Expression::column("other").gt(Expression::literal(7i64)))
+            .with_residual_applied()
+            .build()
+            .unwrap();
+        // the predicate column is read, but not returned
+        let read_names: Vec<_> = scan.physical_schema.fields().map(|f| f.name()).collect();
+        assert_eq!(read_names, ["s", "id", "other"]);
+        let results = scan.execute(&engine).unwrap();
+        assert_eq!(results.len(), 1);
+        let result = results.into_iter().next().unwrap();
+        assert_eq!(result.mask, Some(vec![false, true, true]));
+        let data = result.raw_data.unwrap();
+        let batch: RecordBatch = ArrowEngineData::try_from_engine_data(data).unwrap().into();
+        let names: Vec<_> = batch
+            .schema()
+            .fields()
+            .iter()
+            .map(|f| f.name().clone())
+            .collect();
+        assert_eq!(names, ["s", "id"]);
+        let s = batch.column(0).as_struct();
+        assert_eq!(s.column_names(), ["y"]);
+        assert_eq!(
+            s.column(0).as_string::<i32>(),
+            &StringArray::from(vec!["a", "b", "c"])
+        );
+        assert_eq!(
+            batch.column(1).as_primitive::<Int64Type>(),
+            &Int64Array::from(vec![0, 1, 2])
+        );
+
+        for (column, missing) in [("s.z", "s.z"), ("id.x", "id.x"), ("nope.x", "nope.x")] {
+            let result = ScanBuilder::new(snapshot.clone())
+                .with_columns(["id", column])
+                .build();
+            match result {
+                Err(Error::MissingColumn(message)) => assert!(message.contains(missing)),
+                res => panic!("Expected a missing column error, got {:?}", res.map(|_| ())),
+            }
+        }
+    }
+
+    #[test]
+    fn test_scan_matches_parquet_field_ids() {
+        use arrow_array::Int64Array;
+        use parquet::arrow::PARQUET_FIELD_ID_META_KEY;
+
+        let field = |name: &str, id: i32| {
+            serde_json::json!({
+                "name": name,
+                "type": "long",
+                "nullable": true,
+                "metadata": {
+                    "delta.columnMapping.id": id,
+                    "delta.columnMapping.physicalName": format!("col-{name}")
+                }
+            })
+        };
+        let schema = serde_json::json!({
+            "type": "struct",
+            "fields": [field("a", 1), field("b", 2)]
+        });
+        // the parquet file has the right field ids, but not the physical name of `b`
+        let parquet_field = |name: &str, id: &str| {
+            Field::new(name, arrow_schema::DataType::Int64, true)
+                .with_metadata([(PARQUET_FIELD_ID_META_KEY.to_string(), id.to_string())].into())
+        };
+        let parquet_schema = Arc::new(ArrowSchema::new(vec![
+            parquet_field("renamed-b", "2"),
+            parquet_field("col-a", "1"),
+        ]));
+        let batch = RecordBatch::try_new(
+            parquet_schema.clone(),
+            vec![
+                Arc::new(Int64Array::from(vec![10, 20])),
+                Arc::new(Int64Array::from(vec![1, 2])),
+            ],
+        )
+        .unwrap();
+
+        let read = |configuration: &[(&str, &str)]| {
+            let tmp = TestTable::new();
+            let data = parquet_bytes(&batch, None);
+            std::fs::write(tmp.path().join("data.parquet"), &data).unwrap();
+            let size = data.len();
+            tmp.commit(
+                0,
+                [
+                    protocol_action(2, 5),
+                    metadata_action(&schema, &[], configuration),
+                    add_action("data.parquet", size),
+                ],
+            );
+
+            let engine = SyncEngine::new();
+            let snapshot = Table::new(tmp.location()).snapshot(&engine, None).unwrap();
+            let scan = ScanBuilder::new(snapshot).build().unwrap();
+            let results = scan.execute(&engine).unwrap();
+            let data = results.into_iter().next().unwrap().raw_data.unwrap();
+            let batch: RecordBatch = ArrowEngineData::try_from_engine_data(data).unwrap().into();
+            ["a", "b"].map(|name| {
+                let column = batch.column_by_name(name).unwrap();
+                column.as_primitive::<Int64Type>().clone()
+            })
+        };
+
+        let by_id = [Int64Array::from(vec![1, 2]), Int64Array::from(vec![10, 20])];
+        let id_mode = [("delta.columnMapping.mode", "id")];
+        assert_eq!(read(&id_mode), by_id);
+        let iceberg_compat = [
+            ("delta.columnMapping.mode", "name"),
+            ("delta.enableIcebergCompatV2", "true"),
+        ];
+        assert_eq!(read(&iceberg_compat), by_id);
+        // otherwise columns are matched by physical name, so `b` isn't found
+        let name_mode = [("delta.columnMapping.mode", "name")];
+        let by_name = [
+            Int64Array::from(vec![1, 2]),
+            Int64Array::from(vec![None, None]),
+        ];
+        assert_eq!(read(&name_mode), by_name);
+    }
+
+    #[test]
+    fn test_column_exists() {
+        let schema = StructType::new(vec![
+            StructField::new("a", DataType::INTEGER, true),
+            StructField::new(
+                "b",
+                StructType::new(vec![StructField::new("c", DataType::STRING, true)]),
+                true,
+            ),
+        ]);
+        assert!(column_exists(&schema, &ColumnName::from_dotted("a")));
+        assert!(column_exists(&schema, &ColumnName::from_dotted("b")));
+        assert!(column_exists(&schema, &ColumnName::from_dotted("b.c")));
+        assert!(!column_exists(&schema, &ColumnName::from_dotted("a.c")));
+        assert!(!column_exists(&schema, &ColumnName::from_dotted("b.d")));
+        assert!(!column_exists(&schema, &ColumnName::from_dotted("d")));
+    }
+
+    #[test]
+    fn test_scan_prunes_buckets() {
+        let schema = serde_json::json!({
+            "type": "struct",
+            "fields": [
+                {"name": "id", "type": "integer", "nullable": true, "metadata": {}},
+                {"name": "bucket", "type": "integer", "nullable": true, "metadata": {
+                    "delta.generationExpression": "pmod(hash(id), 4)"
+                }}
+            ]
+        });
+        let add = |path: &str, bucket: &str| {
+            let mut add = add_action(path, 100);
+            add["add"]["partitionValues"] = serde_json::json!({"bucket": bucket});
+            add
+        };
+        let tmp = TestTable::new();
+        tmp.commit(
+            0,
+            [
+                protocol_action(1, 4),
+                metadata_action(&schema, &["bucket"], &[]),
+                add("bucket=0/a.parquet", "0"),
+                add("bucket=3/b.parquet", "3"),
+                add("bucket=3/c.parquet", "3"),
+            ],
+        );
+        let engine = SyncEngine::new();
+        let snapshot = Arc::new(Table::new(tmp.location()).snapshot(&engine, None).unwrap());
+
+        // hash(1) = -559580957, and pmod(-559580957, 4) = 3
+        let scan = ScanBuilder::new(snapshot.clone())
+            .with_predicate(Expression::column("id").eq(Expression::literal(1)))
+            .build()
+            .unwrap();
+        let files: Vec<Add> = scan.files(&engine).unwrap().try_collect().unwrap();
+        let mut paths: Vec<_> = files.into_iter().map(|add| add.path).collect();
+        paths.sort();
+        assert_eq!(paths, ["bucket=3/b.parquet", "bucket=3/c.parquet"]);
+
+        let mut paths = vec![];
+        for res in scan.scan_data(&engine).unwrap() {
+            let (data, selection_vector) = res.unwrap();
+            paths = state::visit_scan_files(
+                data.as_ref(),
+                &selection_vector,
+                paths,
+                |paths: &mut Vec<String>, path, _, _, _| paths.push(path.to_string()),
             )
             .unwrap();
-            assert_eq!(value, *expected);
         }
+        paths.sort();
+        assert_eq!(paths, ["bucket=3/b.parquet", "bucket=3/c.parquet"]);
+
+        // a predicate we can't prune with keeps all the files
+        let scan = ScanBuilder::new(snapshot)
+            .with_predicate(Expression::column("id").gt(Expression::literal(1)))
+            .build()
+            .unwrap();
+        assert_eq!(scan.files(&engine).unwrap().count(), 3);
+    }
+
+    #[test]
+    fn test_scan_require_pruning() {
+        let path = std::fs::canonicalize(PathBuf::from("./tests/data/basic_partitioned/")).unwrap();
+        let url = url::Url::from_directory_path(path).unwrap();
+        let engine = SyncEngine::new();
+        let snapshot = Arc::new(Table::new(url).snapshot(&engine, None).unwrap());
+        let predicate = Expression::column("number").gt(Expression::literal(3i64));
+
+        // the paths and sizes of the files a scan reads, sorted by path
+        let files = |scan: &Scan| {
+            let mut files: Vec<_> = scan
+                .files(&engine)
+                .unwrap()
+                .map_ok(|add| (add.path, add.size as u64))
+                .try_collect()
+                .unwrap();
+            files.sort();
+            files
+        };
+        let all_files = files(&ScanBuilder::new(snapshot.clone()).build().unwrap());
+        let scan = ScanBuilder::new(snapshot.clone())
+            .with_predicate(predicate.clone())
+            .build()
+            .unwrap();
+        let selected_files = files(&scan);
+        let selected_paths: Vec<_> = selected_files.iter().map(|(path, _)| path).collect();
+        assert_eq!(
+            selected_paths,
+            [
+                "letter=__HIVE_DEFAULT_PARTITION__/part-00000-8eb7f29a-e6a1-436e-a638-bbf0a7953f09.c000.snappy.parquet",
+                "letter=a/part-00000-0dbe0cc5-e3bf-4fb0-b36a-b5fdd67fe843.c000.snappy.parquet",
+                "letter=e/part-00000-847cf2d1-1247-4aa0-89ef-2f90c68ea51e.c000.snappy.parquet",
+            ]
+        );
+        let pruned_paths: Vec<_> = all_files
+            .iter()
+            .filter(|file| !selected_files.contains(file))
+            .map(|(path, _)| path)
+            .collect();
+        assert_eq!(
+            pruned_paths,
+            [
+                "letter=a/part-00000-a08d296a-d2c5-4a99-bea9-afcea42ba2e9.c000.snappy.parquet",
+                "letter=b/part-00000-41954fb0-ef91-47e5-bd41-b75169c41c17.c000.snappy.parquet",
+                "letter=c/part-00000-27a17b8f-be68-485c-9c49-70c742be30c0.c000.snappy.parquet",
+            ]
+        );
+        let stats = scan.pruning_stats(&engine).unwrap();
+        assert_eq!(
+            stats,
+            PruningStats {
+                total_files: all_files.len(),
+                total_bytes: all_files.iter().map(|(_, size)| size).sum(),
+                selected_files: selected_files.len(),
+                selected_bytes: selected_files.iter().map(|(_, size)| size).sum(),
+            }
+        );
+        assert_eq!(stats.pruned_files_fraction(), 0.5);
+
+        let scan = ScanBuilder::new(snapshot.clone())
+            .with_predicate(predicate.clone())
+            .require_pruning(0.5)
+            .build()
+            .unwrap();
+        assert_eq!(scan.execute(&engine).unwrap().len(), 3);
+        let scan = ScanBuilder::new(snapshot.clone())
+            .with_predicate(predicate)
+            .require_pruning(0.6)
+            .build()
+            .unwrap();
+        assert!(matches!(
+            scan.execute(&engine),
+            Err(Error::InsufficientPruning { pruned_fraction, min_fraction })
+                if pruned_fraction == stats.pruned_bytes_fraction() && min_fraction == 0.6
+        ));
+        // a full table scan prunes nothing
+        let scan = ScanBuilder::new(snapshot)
+            .require_pruning(0.1)
+            .build()
+            .unwrap();
+        assert!(scan.scan_data(&engine).is_err());
+    }
+
+    #[test]
+    fn test_scan_partition_path_validation() {
+        let path = std::fs::canonicalize(PathBuf::from("./tests/data/basic_partitioned/")).unwrap();
+        let url = url::Url::from_directory_path(path).unwrap();
+        let engine = SyncEngine::new();
+        let snapshot = Arc::new(Table::new(url).snapshot(&engine, None).unwrap());
+        let scan = ScanBuilder::new(snapshot)
+            .with_partition_path_validation()
+            .build()
+            .unwrap();
+        let files: Vec<_> = scan.files(&engine).unwrap().try_collect().unwrap();
+        assert_eq!(files.len(), 6);
+    }
+
+    #[test]
+    fn test_scan_footer_stats_fallback() {
+        let tmp = TestTable::new();
+        // a copy of a file with values 0..9, added without any stats
+        std::fs::copy(
+            "./tests/data/table-without-dv-small/part-00000-517f5d32-9c95-48e8-82b4-0229cc194867-c000.snappy.parquet",
+            tmp.path().join("a.parquet"),
+        )
+        .unwrap();
+        let schema = StructType::new(vec![StructField::new("value", DataType::LONG, true)]);
+        tmp.commit(
+            0,
+            [
+                protocol_action(1, 2),
+                metadata_action(&schema, &[], &[]),
+                add_action("a.parquet", 548),
+            ],
+        );
+        let engine = SyncEngine::new();
+        let snapshot = Arc::new(Table::new(tmp.location()).snapshot(&engine, None).unwrap());
+
+        let count_scan_files = |scan: &Scan| {
+            let mut count = 0;
+            for res in scan.scan_data(&engine).unwrap() {
+                let (data, selection_vector) = res.unwrap();
+                count = state::visit_scan_files(
+                    data.as_ref(),
+                    &selection_vector,
+                    count,
+                    |count: &mut usize, _, _, _, _| *count += 1,
+                )
+                .unwrap();
+            }
+            count
+        };
+
+        // without stats in the log, the file can't be skipped
+        let predicate = Expression::column("value").gt(Expression::literal(20i64));
+        let scan = ScanBuilder::new(snapshot.clone())
+            .with_predicate(predicate.clone())
+            .build()
+            .unwrap();
+        assert_eq!(scan.files(&engine).unwrap().count(), 1);
+
+        // but the footer says all values are at most 9
+        let scan = ScanBuilder::new(snapshot.clone())
+            .with_predicate(predicate)
+            .with_footer_stats_fallback()
+            .build()
+            .unwrap();
+        assert_eq!(scan.files(&engine).unwrap().count(), 0);
+        assert_eq!(count_scan_files(&scan), 0);
+
+        let scan = ScanBuilder::new(snapshot)
+            .with_predicate(Expression::column("value").lt(Expression::literal(5i64)))
+            .with_footer_stats_fallback()
+            .build()
+            .unwrap();
+        assert_eq!(scan.files(&engine).unwrap().count(), 1);
+        assert_eq!(count_scan_files(&scan), 1);
+
+        // a newer add of the file that is skipped by its footer stats still replaces older adds,
+        // even if their (stale) stats in the log would keep them
+        let mut stale_add = add_action("a.parquet", 548);
+        stale_add["add"]["stats"] =
+            r#"{"numRecords":10,"minValues":{"value":0},"maxValues":{"value":30}}"#.into();
+        tmp.commit(
+            0,
+            [
+                protocol_action(1, 2),
+                metadata_action(&schema, &[], &[]),
+                stale_add,
+            ],
+        );
+        tmp.commit(1, [add_action("a.parquet", 548)]);
+        let snapshot = Arc::new(Table::new(tmp.location()).snapshot(&engine, None).unwrap());
+        let scan = ScanBuilder::new(snapshot)
+            .with_predicate(Expression::column("value").gt(Expression::literal(20i64)))
+            .with_footer_stats_fallback()
+            .build()
+            .unwrap();
+        assert_eq!(scan.files(&engine).unwrap().count(), 0);
+        assert_eq!(count_scan_files(&scan), 0);
+    }
+
+    #[test]
+    fn test_scan_footer_stats_fallback_with_column_mapping() {
+        let tmp = TestTable::new();
+        // a copy of a file with values 0..9 in its column `value`, added without any stats
+        std::fs::copy(
+            "./tests/data/table-without-dv-small/part-00000-517f5d32-9c95-48e8-82b4-0229cc194867-c000.snappy.parquet",
+            tmp.path().join("a.parquet"),
+        )
+        .unwrap();
+        // the logical name of the column is `v`
+        let schema = serde_json::json!({
+            "type": "struct",
+            "fields": [{
+                "name": "v",
+                "type": "long",
+                "nullable": true,
+                "metadata": {
+                    "delta.columnMapping.id": 1,
+                    "delta.columnMapping.physicalName": "value"
+                }
+            }]
+        });
+        tmp.commit(
+            0,
+            [
+                protocol_action(2, 5),
+                metadata_action(&schema, &[], &[("delta.columnMapping.mode", "name")]),
+                add_action("a.parquet", 548),
+            ],
+        );
+        let engine = SyncEngine::new();
+        let snapshot = Arc::new(Table::new(tmp.location()).snapshot(&engine, None).unwrap());
+        let files = |predicate: Expression| {
+            let scan = ScanBuilder::new(snapshot.clone())
+                .with_predicate(predicate)
+                .with_footer_stats_fallback()
+                .build()
+                .unwrap();
+            scan.files(&engine).unwrap().count()
+        };
+        // the footer stats of the physical column are used for the logical one
+        assert_eq!(
+            files(Expression::column("v").gt(Expression::literal(20i64))),
+            0
+        );
+        assert_eq!(
+            files(Expression::column("v").lt(Expression::literal(5i64))),
+            1
+        );
+    }
+
+    #[test]
+    fn test_scan_with_checkpoint_without_stats() {
+        let source = "./tests/data/with_checkpoint_no_last_checkpoint/";
+        let tmp = tempfile::tempdir().unwrap();
+        std::fs::create_dir(tmp.path().join("_delta_log")).unwrap();
+
+        // rewrite the checkpoint with an `add` column that has no `stats` field
+        let checkpoint = "_delta_log/00000000000000000002.checkpoint.parquet";
+        let file = std::fs::File::open(PathBuf::from(source).join(checkpoint)).unwrap();
+        let batches: Vec<RecordBatch> = ParquetRecordBatchReaderBuilder::try_new(file)
+            .unwrap()
+            .build()
+            .unwrap()
+            .try_collect()
+            .unwrap();
+        let batches: Vec<_> = batches
+            .into_iter()
+            .map(|batch| {
+                let schema = batch.schema();
+                let mut columns = batch.columns().to_vec();
+                let mut fields: Vec<_> = schema.fields().iter().cloned().collect();
+                let add_index = schema.index_of("add").unwrap();
+                let (add_fields, add_columns, nulls) =
+                    columns[add_index].as_struct().clone().into_parts();
+                let (add_fields, add_columns): (Vec<_>, Vec<_>) = add_fields
+                    .iter()
+                    .cloned()
+                    .zip(add_columns)
+                    .filter(|(field, _)| field.name() != "stats")
+                    .unzip();
+                let add = StructArray::new(add_fields.into(), add_columns, nulls);
+                fields[add_index] = Arc::new(Field::new("add", add.data_type().clone(), true));
+                columns[add_index] = Arc::new(add);
+                RecordBatch::try_new(Arc::new(ArrowSchema::new(fields)), columns).unwrap()
+            })
+            .collect();
+        let file = std::fs::File::create(tmp.path().join(checkpoint)).unwrap();
+        let mut writer = ArrowWriter::try_new(file, batches[0].schema(), None).unwrap();
+        for batch in batches {
+            writer.write(&batch).unwrap();
+        }
+        writer.close().unwrap();
+
+        let url = url::Url::from_directory_path(tmp.path()).unwrap();
+        let engine = SyncEngine::new();
+        let snapshot = Table::new(url).snapshot(&engine, Some(2)).unwrap();
+        // the only file has values of `int` up to 667, but without stats it can't be skipped
+        let scan = ScanBuilder::new(snapshot)
+            .with_predicate(Expression::column("int").gt(Expression::literal(1000i64)))
+            .build()
+            .unwrap();
+        let files: Vec<_> = scan.files(&engine).unwrap().try_collect().unwrap();
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].stats, None);
+    }
+
+    #[test]
+    fn test_scan_execute_with_row_indexes() {
+        let path =
+            std::fs::canonicalize(PathBuf::from("./tests/data/table-with-dv-small/")).unwrap();
+        let url = url::Url::from_directory_path(path).unwrap();
+        let engine = SyncEngine::new();
+        let snapshot = Arc::new(Table::new(url).snapshot(&engine, None).unwrap());
+        let scan = ScanBuilder::new(snapshot).build().unwrap();
+
+        // the rows the deletion vector keeps, and their indexes in the file
+        let mut expected_values = vec![];
+        let mut expected_indexes = vec![];
+        for result in scan.execute(&engine).unwrap() {
+            let batch: RecordBatch =
+                ArrowEngineData::try_from_engine_data(result.raw_data.unwrap())
+                    .unwrap()
+                    .into();
+            let mask = result.mask.unwrap();
+            let values = batch.column(0).as_primitive::<Int32Type>();
+            for (index, value) in values.iter().enumerate() {
+                if mask.get(index).copied().unwrap_or(true) {
+                    expected_values.push(value.unwrap());
+                    expected_indexes.push(index as i64);
+                }
+            }
+        }
+        assert_eq!(expected_indexes.len(), 8);
+
+        let batches: Vec<RecordBatch> = scan
+            .execute_with_row_indexes(&engine)
+            .unwrap()
+            .try_collect()
+            .unwrap();
+        let schema = batches[0].schema();
+        assert_eq!(schema.fields().len(), 2);
+        assert_eq!(schema.field(1).name(), ROW_INDEX_COLUMN_NAME);
+        assert!(!schema.field(1).is_nullable());
+        let mut values: Vec<i32> = vec![];
+        let mut indexes: Vec<i64> = vec![];
+        for batch in batches {
+            values.extend(batch.column(0).as_primitive::<Int32Type>().values().iter());
+            indexes.extend(batch.column(1).as_primitive::<Int64Type>().values().iter());
+        }
+        assert_eq!(values, expected_values);
+        assert_eq!(indexes, expected_indexes);
+    }
+
+    #[test]
+    fn test_scan_with_partition_columns() {
+        let path = std::fs::canonicalize(PathBuf::from("./tests/data/basic_partitioned/")).unwrap();
+        let url = url::Url::from_directory_path(path).unwrap();
+        let engine = SyncEngine::new();
+        let snapshot = Arc::new(Table::new(url).snapshot(&engine, None).unwrap());
+        let column_names = |scan: &Scan| -> Vec<Vec<String>> {
+            scan.execute(&engine)
+                .unwrap()
+                .into_iter()
+                .map(|result| {
+                    let batch: RecordBatch =
+                        ArrowEngineData::try_from_engine_data(result.raw_data.unwrap())
+                            .unwrap()
+                            .into();
+                    batch
+                        .schema()
+                        .fields()
+                        .iter()
+                        .map(|field| field.name().clone())
+                        .collect()
+                })
+                .collect()
+        };
+        let table_columns: Vec<String> = snapshot
+            .schema()
+            .fields()
+            .map(|field| field.name().clone())
+            .collect();
+        assert_eq!(table_columns[0], "letter");
+
+        // the partition column is filled in, in its place in the schema
+        let scan = ScanBuilder::new(snapshot.clone()).build().unwrap();
+        assert_eq!(scan.output_schema().as_ref(), snapshot.schema());
+        let batches = column_names(&scan);
+        assert!(!batches.is_empty());
+        assert!(batches.iter().all(|columns| *columns == table_columns));
+
+        let scan = ScanBuilder::new(snapshot.clone())
+            .with_partition_columns(false)
+            .build()
+            .unwrap();
+        assert!(scan.output_schema().field("letter").is_none());
+        let batches = column_names(&scan);
+        assert!(!batches.is_empty());
+        assert!(batches
+            .iter()
+            .all(|columns| *columns == table_columns[1..].to_vec()));
+    }
+
+    #[test]
+    fn test_scan_output_schema() {
+        let path = std::fs::canonicalize(PathBuf::from("./tests/data/basic_partitioned/")).unwrap();
+        let url = url::Url::from_directory_path(path).unwrap();
+        let engine = SyncEngine::new();
+        let snapshot = Arc::new(Table::new(url).snapshot(&engine, None).unwrap());
+        let read_schema = Arc::new(snapshot.schema().clone());
+        let output_schema = Arc::new(StructType::new(vec![StructField::new(
+            "number",
+            DataType::LONG,
+            true,
+        )]));
+
+        let scan = ScanBuilder::new(snapshot.clone())
+            .with_schema(read_schema.clone())
+            .with_output_schema(output_schema.clone())
+            .build()
+            .unwrap();
+        assert_eq!(scan.schema(), &read_schema);
+        assert_eq!(scan.output_schema(), &output_schema);
+        let results = scan.execute(&engine).unwrap();
+        assert!(!results.is_empty());
+        for result in results {
+            let batch: RecordBatch =
+                ArrowEngineData::try_from_engine_data(result.raw_data.unwrap())
+                    .unwrap()
+                    .into();
+            assert_eq!(batch.num_columns(), 1);
+            assert_eq!(batch.schema().field(0).name(), "number");
+        }
+
+        // the output columns must be read
+        let output_schema = Arc::new(StructType::new(vec![StructField::new(
+            "other",
+            DataType::LONG,
+            true,
+        )]));
+        let result = ScanBuilder::new(snapshot.clone())
+            .with_output_schema(output_schema)
+            .build();
+        assert!(matches!(result, Err(Error::MissingColumn(_))));
+        let output_schema = Arc::new(StructType::new(vec![StructField::new(
+            "number",
+            DataType::STRING,
+            true,
+        )]));
+        let result = ScanBuilder::new(snapshot)
+            .with_output_schema(output_schema)
+            .build();
+        assert!(matches!(result, Err(Error::UnexpectedColumnType(_))));
+    }
+
+    #[test]
+    fn test_scan_void_column() {
+        let path =
+            std::fs::canonicalize(PathBuf::from("./tests/data/table-with-void-column/")).unwrap();
+        let url = url::Url::from_directory_path(path).unwrap();
+        let engine = SyncEngine::new();
+        let snapshot = Arc::new(Table::new(url).snapshot(&engine, None).unwrap());
+        assert_eq!(
+            snapshot.schema().field("nothing").unwrap().data_type(),
+            &DataType::VOID
+        );
+
+        // the void column isn't in the data file, so it must not be read from it
+        let scan = ScanBuilder::new(snapshot.clone()).build().unwrap();
+        assert_eq!(scan.physical_schema.fields().count(), 1);
+        let results = scan.execute(&engine).unwrap();
+        assert_eq!(results.len(), 1);
+        let data = results.into_iter().next().unwrap().raw_data.unwrap();
+        let batch: RecordBatch = ArrowEngineData::try_from_engine_data(data).unwrap().into();
+        let column = batch.column_by_name("nothing").unwrap();
+        assert_eq!(column.data_type(), &arrow_schema::DataType::Null);
+        assert_eq!(column.len(), 10);
+
+        // predicates on the void column evaluate trivially
+        let evaluate = |predicate: Expression| {
+            let evaluator = engine
+                .get_expression_handler()
+                .get_evaluator(scan.schema().clone(), predicate, DataType::BOOLEAN)
+                .unwrap();
+            let result = evaluator
+                .evaluate(&ArrowEngineData::new(batch.clone()))
+                .unwrap();
+            let result: RecordBatch = ArrowEngineData::try_from_engine_data(result)
+                .unwrap()
+                .into();
+            result.column(0).as_boolean().clone()
+        };
+        let is_null = evaluate(Expression::column("nothing").is_null());
+        assert_eq!(is_null.true_count(), 10);
+        let eq = evaluate(Expression::column("nothing").eq(Expression::literal(1)));
+        assert_eq!(eq.null_count(), 10);
+
+        // and can't be used to skip files
+        let scan = ScanBuilder::new(snapshot)
+            .with_predicate(Expression::column("nothing").eq(Expression::literal(1)))
+            .build()
+            .unwrap();
+        assert_eq!(scan.files(&engine).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn test_scan_partition_summary() {
+        let path = std::fs::canonicalize(PathBuf::from("./tests/data/basic_partitioned/")).unwrap();
+        let url = url::Url::from_directory_path(path).unwrap();
+        let engine = SyncEngine::new();
+        let snapshot = Table::new(url).snapshot(&engine, None).unwrap();
+        let scan = ScanBuilder::new(snapshot).build().unwrap();
+
+        let summary = scan.partition_summary(&engine).unwrap();
+        let names: Vec<_> = summary
+            .schema()
+            .fields()
+            .iter()
+            .map(|field| field.name().clone())
+            .collect();
+        assert_eq!(names, ["letter", "numRecords"]);
+        let letters = summary.column(0).as_string::<i32>();
+        let num_records = summary.column(1).as_primitive::<Int64Type>();
+        let mut rows: Vec<_> = (0..summary.num_rows())
+            .map(|i| {
+                let letter = letters.is_valid(i).then(|| letters.value(i));
+                (letter, num_records.value(i))
+            })
+            .collect();
+        rows.sort();
+        assert_eq!(
+            rows,
+            [
+                (None, 1),
+                (Some("a"), 1),
+                (Some("a"), 1),
+                (Some("b"), 1),
+                (Some("c"), 1),
+                (Some("e"), 1),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_scan_with_file_deduplicator() {
+        use std::sync::Mutex;
+
+        use crate::scan::log_replay::DefaultFileDeduplicator;
+
+        type Inserted = Arc<Mutex<Vec<(String, Option<String>)>>>;
+        /// Records every action inserted into a default deduplicator
+        struct RecordingDeduplicator {
+            inner: DefaultFileDeduplicator,
+            inserted: Inserted,
+        }
+        impl FileDeduplicator for RecordingDeduplicator {
+            fn contains(&self, path: &str, dv_unique_id: Option<&str>) -> bool {
+                self.inner.contains(path, dv_unique_id)
+            }
+            fn insert(&mut self, path: &str, dv_unique_id: Option<&str>) {
+                let action = (path.to_string(), dv_unique_id.map(ToString::to_string));
+                self.inserted.lock().unwrap().push(action);
+                self.inner.insert(path, dv_unique_id)
+            }
+        }
+
+        let path =
+            std::fs::canonicalize(PathBuf::from("./tests/data/table-with-dv-small/")).unwrap();
+        let url = url::Url::from_directory_path(path).unwrap();
+        let engine = SyncEngine::new();
+        let snapshot = Table::new(url).snapshot(&engine, None).unwrap();
+        let inserted = Inserted::default();
+        let factory_inserted = inserted.clone();
+        let scan = ScanBuilder::new(snapshot)
+            .with_file_deduplicator(move || {
+                Box::new(RecordingDeduplicator {
+                    inner: DefaultFileDeduplicator::default(),
+                    inserted: factory_inserted.clone(),
+                })
+            })
+            .build()
+            .unwrap();
+        let files: Vec<_> = scan.files(&engine).unwrap().try_collect().unwrap();
+        assert_eq!(files.len(), 1);
+        assert!(files[0].deletion_vector.is_some());
+
+        // version 1 removed the file without a deletion vector and re-added it with one
+        let inserted = inserted.lock().unwrap();
+        let path = &files[0].path;
+        assert!(inserted.contains(&(path.clone(), None)));
+        assert!(inserted.contains(&(path.clone(), files[0].dv_unique_id())));
+    }
+
+    #[test]
+    fn test_scan_with_residual_applied() {
+        let path =
+            std::fs::canonicalize(PathBuf::from("./tests/data/table-with-dv-small/")).unwrap();
+        let url = url::Url::from_directory_path(path).unwrap();
+        let engine = SyncEngine::new();
+        let snapshot = Arc::new(Table::new(url).snapshot(&engine, None).unwrap());
+        let predicate = Expression::column("value").gt(Expression::literal(4));
+
+        let scan = ScanBuilder::new(snapshot.clone())
+            .with_predicate(predicate.clone())
+            .build()
+            .unwrap();
+        assert_eq!(scan.residual_predicate(), Some(&predicate));
+
+        let scan = ScanBuilder::new(snapshot.clone())
+            .with_predicate(predicate)
+            .with_residual_applied()
+            .build()
+            .unwrap();
+        assert_eq!(scan.residual_predicate(), None);
+        let mut values = vec![];
+        for result in scan.execute(&engine).unwrap() {
+            let batch: RecordBatch =
+                ArrowEngineData::try_from_engine_data(result.raw_data.unwrap())
+                    .unwrap()
+                    .into();
+            let mask = result.mask.unwrap();
+            let column = batch
+                .column(0)
+                .as_primitive::<arrow_array::types::Int32Type>();
+            values.extend(
+                column
+                    .values()
+                    .iter()
+                    .zip(mask)
+                    .filter_map(|(v, m)| m.then_some(*v)),
+            );
+        }
+        // the deletion vector removes the row with value 9
+        assert_eq!(values, [5, 6, 7, 8]);
+
+        // the predicate can only be applied to columns that are read
+        let schema = Arc::new(StructType::new(vec![]));
+        let result = ScanBuilder::new(snapshot)
+            .with_schema(schema)
+            .with_predicate(Expression::column("value").gt(Expression::literal(4)))
+            .with_residual_applied()
+            .build();
+        assert!(matches!(result, Err(Error::MissingColumn(_))));
+    }
+
+    #[test]
+    fn test_scan_reads_deletion_vector_lazily() {
+        use crate::engine::in_memory::InMemoryEngine;
+
+        let path =
+            std::fs::canonicalize(PathBuf::from("./tests/data/table-with-dv-small/")).unwrap();
+        let url = url::Url::from_directory_path(&path).unwrap();
+        let engine = InMemoryEngine::new();
+        engine.put_local_dir(&url, path).unwrap();
+        // the number of deletion vectors read since the last call
+        let dv_reads = || {
+            let reads = engine.take_reads();
+            reads
+                .iter()
+                .filter(|location| location.path().ends_with(".bin"))
+                .count()
+        };
+        let snapshot = Arc::new(Table::new(url).snapshot(&engine, None).unwrap());
+        let scan = ScanBuilder::new(snapshot).build().unwrap();
+        let add = scan.files(&engine).unwrap().next().unwrap().unwrap();
+        assert!(add.deletion_vector.is_some());
+
+        dv_reads();
+        let dv_cache = DeletionVectorCache::default();
+        let mut results = scan.execute_file(&engine, add.clone(), &dv_cache).unwrap();
+        assert_eq!(dv_reads(), 0);
+        let result = results.next().unwrap();
+        assert_eq!(dv_reads(), 1);
+        let mask = result.mask.unwrap();
+        assert_eq!(mask.iter().filter(|valid| !**valid).count(), 2);
+
+        // another file with the same deletion vector reuses it
+        let mut results = scan.execute_file(&engine, add.clone(), &dv_cache).unwrap();
+        let result = results.next().unwrap();
+        assert_eq!(dv_reads(), 0);
+        assert_eq!(result.mask.unwrap(), mask);
+
+        // once the cache is full, reading another deletion vector evicts the least recently used
+        let dv_cache = DeletionVectorCache::default();
+        for i in 0..DELETION_VECTOR_CACHE_CAPACITY {
+            let mut selection_vectors = dv_cache.selection_vectors.lock().unwrap();
+            selection_vectors.insert(format!("other{i}"), Default::default());
+        }
+        let mut results = scan.execute_file(&engine, add, &dv_cache).unwrap();
+        let result = results.next().unwrap();
+        assert_eq!(dv_reads(), 1);
+        assert_eq!(result.mask.unwrap(), mask);
+        let selection_vectors = dv_cache.selection_vectors.lock().unwrap();
+        assert_eq!(selection_vectors.len(), DELETION_VECTOR_CACHE_CAPACITY);
+        assert!(!selection_vectors.contains_key("other0"));
+        assert!(selection_vectors.contains_key("other1"));
+    }
+
+    #[test]
+    fn test_scan_timestamp_ntz() {
+        use arrow_array::cast::AsArray;
+        use arrow_array::types::TimestampMicrosecondType;
+        use arrow_array::{
+            ArrayRef, RecordBatch, TimestampMicrosecondArray, TimestampNanosecondArray,
+        };
+        use arrow_schema::{DataType as ArrowDataType, TimeUnit};
+
+        use crate::engine::arrow_data::ArrowEngineData;
+
+        // write a data file like the ones of `timestamp_ntz` columns, which are stored without a
+        // timezone (`isAdjustedToUTC = false`), in microseconds or (from some writers) nanoseconds
+        let write = |path: &std::path::Path, batch: RecordBatch| {
+            let data = parquet_bytes(&batch, None);
+            std::fs::write(path, &data).unwrap();
+            data.len()
+        };
+        let tmp = TestTable::new();
+        let micros: ArrayRef =
+            Arc::new(TimestampMicrosecondArray::from(vec![1_000_000, 2_000_000]));
+        let nanos: ArrayRef = Arc::new(TimestampNanosecondArray::from(vec![
+            1_000_000_000,
+            2_000_000_000,
+        ]));
+        let batch = RecordBatch::try_from_iter([("ntz", micros), ("ntz_nanos", nanos)]).unwrap();
+        let size = write(&tmp.path().join("a.parquet"), batch);
+        let schema = StructType::new(vec![
+            StructField::new("ntz", DataType::TIMESTAMP_NTZ, true),
+            StructField::new("ntz_nanos", DataType::TIMESTAMP_NTZ, true),
+        ]);
+        let protocol = serde_json::json!({"protocol": {
+            "minReaderVersion": 3,
+            "minWriterVersion": 7,
+            "readerFeatures": ["timestampNtz"],
+            "writerFeatures": ["timestampNtz"]
+        }});
+        tmp.commit(
+            0,
+            [
+                protocol,
+                metadata_action(&schema, &[], &[]),
+                add_action("a.parquet", size),
+            ],
+        );
+        let engine = SyncEngine::new();
+        let snapshot = Arc::new(Table::new(tmp.location()).snapshot(&engine, None).unwrap());
+
+        let scan = ScanBuilder::new(snapshot.clone()).build().unwrap();
+        let mut results = scan.execute(&engine).unwrap();
+        assert_eq!(results.len(), 1);
+        let raw_data = results.pop().unwrap().raw_data.unwrap();
+        let data = ArrowEngineData::try_from_engine_data(raw_data).unwrap();
+        let batch = data.record_batch();
+        let ntz = ArrowDataType::Timestamp(TimeUnit::Microsecond, None);
+        for column in batch.columns() {
+            assert_eq!(column.data_type(), &ntz);
+            let values = column.as_primitive::<TimestampMicrosecondType>().values();
+            assert_eq!(values.to_vec(), vec![1_000_000, 2_000_000]);
+        }
+
+        // data with a timezone isn't a valid timestamp_ntz column
+        let utc: ArrayRef = Arc::new(
+            TimestampMicrosecondArray::from(vec![1_000_000, 2_000_000]).with_timezone("UTC"),
+        );
+        let batch = RecordBatch::try_from_iter([("ntz", utc.clone()), ("ntz_nanos", utc)]).unwrap();
+        let size = write(&tmp.path().join("b.parquet"), batch);
+        tmp.commit(1, [add_action("b.parquet", size)]);
+        let snapshot = Arc::new(Table::new(tmp.location()).snapshot(&engine, None).unwrap());
+        let scan = ScanBuilder::new(snapshot).build().unwrap();
+        let results = scan.execute(&engine).unwrap();
+        let err = results
+            .into_iter()
+            .find_map(|result| result.raw_data.err())
+            .unwrap();
+        assert!(err.to_string().contains("timestamp_ntz"), "{err}");
+    }
+
+    #[test]
+    fn test_scan_read_ahead() {
+        use crate::engine::in_memory::InMemoryEngine;
+
+        let path = std::fs::canonicalize(PathBuf::from("./tests/data/basic_partitioned/")).unwrap();
+        let url = url::Url::from_directory_path(&path).unwrap();
+        let engine = InMemoryEngine::new();
+        engine.put_local_dir(&url, path).unwrap();
+        // the number of data files opened since the last call
+        let opened = || {
+            let reads = engine.take_reads();
+            reads
+                .iter()
+                .filter(|location| location.path().ends_with(".parquet"))
+                .count()
+        };
+        let snapshot = Arc::new(Table::new(url).snapshot(&engine, None).unwrap());
+        let num_files = ScanBuilder::new(snapshot.clone())
+            .build()
+            .unwrap()
+            .files(&engine)
+            .unwrap()
+            .count();
+        assert!(num_files > 3);
+
+        // every way of executing a scan lazily opens `read_ahead` files beyond the one being
+        // consumed, and still reads every file exactly once
+        for read_ahead in [0, 2] {
+            let scan = ScanBuilder::new(snapshot.clone())
+                .with_read_ahead(read_ahead)
+                .build()
+                .unwrap();
+            opened();
+            let mut results = scan.execute_iter(&engine).unwrap();
+            results.next().unwrap().unwrap();
+            assert_eq!(opened(), read_ahead + 1);
+            assert_eq!(1 + results.count(), num_files);
+            assert_eq!(read_ahead + 1 + opened(), num_files);
+
+            let mut batches = scan.execute_with_row_indexes(&engine).unwrap();
+            batches.next().unwrap().unwrap();
+            assert_eq!(opened(), read_ahead + 1);
+            assert_eq!(1 + batches.count(), num_files);
+            assert_eq!(read_ahead + 1 + opened(), num_files);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_scan_execute_stream() {
+        use crate::engine::in_memory::InMemoryEngine;
+
+        let path = std::fs::canonicalize(PathBuf::from("./tests/data/basic_partitioned/")).unwrap();
+        let url = url::Url::from_directory_path(&path).unwrap();
+        let engine = InMemoryEngine::new();
+        engine.put_local_dir(&url, path).unwrap();
+        let opened = || {
+            let reads = engine.take_reads();
+            reads
+                .iter()
+                .filter(|location| location.path().ends_with(".parquet"))
+                .count()
+        };
+        let snapshot = Arc::new(Table::new(url).snapshot(&engine, None).unwrap());
+        let scan = Arc::new(
+            ScanBuilder::new(snapshot)
+                .with_read_ahead(1)
+                .build()
+                .unwrap(),
+        );
+        let expected = scan.execute(&engine).unwrap();
+        opened();
+
+        // nothing is read until the stream is polled, and then with the read-ahead of the scan
+        let mut stream = scan.execute_stream(Arc::new(engine.clone()));
+        assert_eq!(opened(), 0);
+        let first = stream.next().await.unwrap().unwrap();
+        assert_eq!(opened(), 2);
+        let results: Vec<_> = stream.collect().await;
+        assert_eq!(1 + results.len(), expected.len());
+        assert_eq!(2 + opened(), expected.len());
+
+        // the results are those of the files in order
+        let rows = |result: ScanResult| {
+            let data = ArrowEngineData::try_from_engine_data(result.raw_data.unwrap()).unwrap();
+            data.record_batch().clone()
+        };
+        let results = std::iter::once(first)
+            .chain(results.into_iter().map(Result::unwrap))
+            .map(rows);
+        assert!(results.eq(expected.into_iter().map(rows)));
     }
 
     #[test_log::test]
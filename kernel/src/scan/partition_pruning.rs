@@ -0,0 +1,540 @@
+//! Pruning of files based on partition columns that are generated from data columns.
+//!
+//! Some tables bucket their data with a partition column generated as `pmod(hash(col), n)`. For
+//! such tables an equality predicate on `col` tells us exactly which bucket can contain matching
+//! rows, so files in every other bucket can be skipped.
+//!
+//! Predicates can refer to partition columns directly, or as the fields of the synthetic
+//! [`PARTITION_STRUCT_COLUMN`] struct (see [`normalize_partition_predicate`]).
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use tracing::debug;
+
+use crate::column_mapping::ColumnMappingMode;
+use crate::engine_data::{GetData, TypedGetData};
+use crate::expressions::{BinaryOperator, Expression, Scalar, StructData, VariadicOperator};
+use crate::schema::{DataType, MapType, PrimitiveType, StructField, StructType};
+use crate::{DataVisitor, DeltaResult, EngineData, Error};
+
+/// The name of a synthetic struct column whose fields are the partition columns of a table, which
+/// predicates can use to refer to partition values, e.g. `__partition__.day = '2024-01-01'`, or
+/// `__partition__ = {day: '2024-01-01', hour: 12}` to match all of them at once.
+pub const PARTITION_STRUCT_COLUMN: &str = "__partition__";
+
+/// Rewrite the references to the [`PARTITION_STRUCT_COLUMN`] struct in `predicate` into references
+/// to the partition columns themselves, so that the rest of the scan only has to deal with the
+/// latter. A field `__partition__.col` becomes the column `col`, and `__partition__` compared for
+/// equality with a struct literal becomes the conjunction of the equalities of its fields. Fails
+/// if a field isn't a partition column, or the struct is used in any other way.
+pub(crate) fn normalize_partition_predicate(
+    predicate: Expression,
+    partition_columns: &[String],
+) -> DeltaResult<Expression> {
+    let partition_column = |name: &str| {
+        partition_columns
+            .iter()
+            .find(|column| column.as_str() == name)
+            .map(|column| Expression::nested_column([column]))
+            .ok_or_else(|| {
+                Error::missing_column(format!(
+                    "{PARTITION_STRUCT_COLUMN}.{name} is not a partition column"
+                ))
+            })
+    };
+    let normalize = |expr: Expression| normalize_partition_predicate(expr, partition_columns);
+    let normalize_all = |exprs: Vec<Expression>| -> DeltaResult<Vec<_>> {
+        exprs.into_iter().map(normalize).collect()
+    };
+    let normalize_boxed = |expr: Box<Expression>| normalize(*expr).map(Box::new);
+
+    let expr = match predicate {
+        Expression::Column(name) if name.top_level() == Some(PARTITION_STRUCT_COLUMN) => {
+            return Err(Error::generic(format!(
+                "{PARTITION_STRUCT_COLUMN} can only be compared for equality with a struct literal"
+            )));
+        }
+        Expression::Column(name) => match name.path() {
+            [struct_name, field] if struct_name == PARTITION_STRUCT_COLUMN => {
+                partition_column(field)?
+            }
+            [struct_name, ..] if struct_name == PARTITION_STRUCT_COLUMN => {
+                return Err(Error::missing_column(format!(
+                    "{name} is not a partition column"
+                )));
+            }
+            _ => Expression::Column(name),
+        },
+        Expression::Literal(value) => Expression::Literal(value),
+        Expression::Struct(exprs) => Expression::Struct(normalize_all(exprs)?),
+        Expression::BinaryOperation { op, left, right } => {
+            if let (BinaryOperator::Equal, Some(values)) =
+                (&op, partition_struct_literal(&left, &right))
+            {
+                let equalities =
+                    values
+                        .fields()
+                        .iter()
+                        .zip(values.values())
+                        .map(|(field, value)| {
+                            Ok(partition_column(field.name())?
+                                .eq(Expression::literal(value.clone())))
+                        });
+                return Ok(Expression::and_from(
+                    equalities.collect::<DeltaResult<Vec<_>>>()?,
+                ));
+            }
+            Expression::BinaryOperation {
+                op,
+                left: normalize_boxed(left)?,
+                right: normalize_boxed(right)?,
+            }
+        }
+        Expression::UnaryOperation { op, expr } => Expression::UnaryOperation {
+            op,
+            expr: normalize_boxed(expr)?,
+        },
+        Expression::VariadicOperation { op, exprs } => Expression::VariadicOperation {
+            op,
+            exprs: normalize_all(exprs)?,
+        },
+        Expression::MapLookup { map_expr, key } => Expression::MapLookup {
+            map_expr: normalize_boxed(map_expr)?,
+            key: normalize_boxed(key)?,
+        },
+        Expression::AddInterval { expr, interval } => Expression::AddInterval {
+            expr: normalize_boxed(expr)?,
+            interval,
+        },
+        Expression::Greatest(exprs) => Expression::Greatest(normalize_all(exprs)?),
+        Expression::Least(exprs) => Expression::Least(normalize_all(exprs)?),
+        Expression::In { target, list } => Expression::In {
+            target: normalize_boxed(target)?,
+            list,
+        },
+        Expression::RLike { expr, pattern } => Expression::RLike {
+            expr: normalize_boxed(expr)?,
+            pattern,
+        },
+    };
+    Ok(expr)
+}
+
+/// The struct literal that the [`PARTITION_STRUCT_COLUMN`] is compared to by a binary operation
+/// with operands `left` and `right`, if any
+fn partition_struct_literal<'a>(
+    left: &'a Expression,
+    right: &'a Expression,
+) -> Option<&'a StructData> {
+    match (left, right) {
+        (Expression::Column(col), Expression::Literal(Scalar::Struct(values)))
+        | (Expression::Literal(Scalar::Struct(values)), Expression::Column(col))
+            if col.top_level() == Some(PARTITION_STRUCT_COLUMN) =>
+        {
+            Some(values)
+        }
+        _ => None,
+    }
+}
+
+/// The seed used by Spark's `hash` function
+const SPARK_HASH_SEED: i32 = 42;
+
+/// A partition column whose value is computed from a data column as `pmod(hash(col), n)`
+#[derive(Debug, PartialEq)]
+struct BucketExpression {
+    source_column: String,
+    num_buckets: i32,
+}
+
+/// Parse a generation expression of the form `pmod(hash(col), n)`. Returns `None` for any other
+/// expression, since we can't know how to prune on it.
+fn parse_bucket_expression(expr: &str) -> Option<BucketExpression> {
+    fn strip_call<'a>(expr: &'a str, function: &str) -> Option<&'a str> {
+        let expr = expr.trim();
+        if !expr.get(..function.len())?.eq_ignore_ascii_case(function) {
+            return None;
+        }
+        expr[function.len()..]
+            .trim_start()
+            .strip_prefix('(')?
+            .strip_suffix(')')
+    }
+
+    let (hash_call, num_buckets) = strip_call(expr, "pmod")?.rsplit_once(',')?;
+    let num_buckets: i32 = num_buckets.trim().parse().ok().filter(|n| *n > 0)?;
+    let column = strip_call(hash_call, "hash")?.trim();
+    let column = match column.strip_prefix('`') {
+        Some(quoted) => quoted.strip_suffix('`')?,
+        None => column,
+    };
+    if column.is_empty() || column.contains([',', '(', ')', '`']) {
+        return None;
+    }
+    Some(BucketExpression {
+        source_column: column.to_string(),
+        num_buckets,
+    })
+}
+
+/// Collect the `column = literal` equalities that must hold for `predicate` to be true. Only the
+/// top-level conjuncts of the predicate are considered.
+fn collect_equalities<'a>(predicate: &'a Expression, equalities: &mut Vec<(&'a str, &'a Scalar)>) {
+    match predicate {
+        Expression::BinaryOperation {
+            op: BinaryOperator::Equal,
+            left,
+            right,
+        } => match (left.as_ref(), right.as_ref()) {
+            (Expression::Column(col), Expression::Literal(value))
+            | (Expression::Literal(value), Expression::Column(col)) => {
+                // only top-level columns can be the source of a bucket
+                if let Some(col) = col.top_level() {
+                    equalities.push((col, value))
+                }
+            }
+            _ => {}
+        },
+        Expression::VariadicOperation {
+            op: VariadicOperator::And,
+            exprs,
+        } => exprs
+            .iter()
+            .for_each(|expr| collect_equalities(expr, equalities)),
+        _ => {}
+    }
+}
+
+// Spark's murmur3 implementation (see `org.apache.spark.unsafe.hash.Murmur3_x86_32`)
+fn mix_k1(k1: i32) -> i32 {
+    (k1.wrapping_mul(0xcc9e2d51_u32 as i32))
+        .rotate_left(15)
+        .wrapping_mul(0x1b873593)
+}
+
+fn mix_h1(h1: i32, k1: i32) -> i32 {
+    (h1 ^ k1)
+        .rotate_left(13)
+        .wrapping_mul(5)
+        .wrapping_add(0xe6546b64_u32 as i32)
+}
+
+fn fmix(h1: i32, length: i32) -> i32 {
+    let mut h1 = (h1 ^ length) as u32;
+    h1 ^= h1 >> 16;
+    h1 = h1.wrapping_mul(0x85ebca6b);
+    h1 ^= h1 >> 13;
+    h1 = h1.wrapping_mul(0xc2b2ae35);
+    h1 ^= h1 >> 16;
+    h1 as i32
+}
+
+fn hash_int(input: i32, seed: i32) -> i32 {
+    fmix(mix_h1(seed, mix_k1(input)), 4)
+}
+
+fn hash_long(input: i64, seed: i32) -> i32 {
+    let h1 = mix_h1(seed, mix_k1(input as i32));
+    let h1 = mix_h1(h1, mix_k1((input >> 32) as i32));
+    fmix(h1, 8)
+}
+
+fn hash_bytes(input: &[u8], seed: i32) -> i32 {
+    let mut chunks = input.chunks_exact(4);
+    let mut h1 = seed;
+    for chunk in chunks.by_ref() {
+        let word = i32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]);
+        h1 = mix_h1(h1, mix_k1(word));
+    }
+    // Spark mixes each trailing byte in on its own (as a signed value)
+    for byte in chunks.remainder() {
+        h1 = mix_h1(h1, mix_k1(*byte as i8 as i32));
+    }
+    fmix(h1, input.len() as i32)
+}
+
+/// Compute Spark's `hash(value)` for a value of a column with type `data_type`. Returns `None` if
+/// the value can't be (safely) hashed as that type.
+fn spark_hash(value: &Scalar, data_type: &DataType) -> Option<i32> {
+    use PrimitiveType::*;
+    let DataType::Primitive(data_type) = data_type else {
+        return None;
+    };
+    let hash = match (data_type, value) {
+        (Byte | Short | Integer, Scalar::Byte(v)) => hash_int(*v as i32, SPARK_HASH_SEED),
+        (Short | Integer, Scalar::Short(v)) => hash_int(*v as i32, SPARK_HASH_SEED),
+        (Integer, Scalar::Integer(v)) => hash_int(*v, SPARK_HASH_SEED),
+        (Long, Scalar::Byte(v)) => hash_long(*v as i64, SPARK_HASH_SEED),
+        (Long, Scalar::Short(v)) => hash_long(*v as i64, SPARK_HASH_SEED),
+        (Long, Scalar::Integer(v)) => hash_long(*v as i64, SPARK_HASH_SEED),
+        (Long, Scalar::Long(v)) => hash_long(*v, SPARK_HASH_SEED),
+        (Boolean, Scalar::Boolean(v)) => hash_int(*v as i32, SPARK_HASH_SEED),
+        (Date, Scalar::Date(v)) => hash_int(*v, SPARK_HASH_SEED),
+        (Timestamp, Scalar::Timestamp(v)) => hash_long(*v, SPARK_HASH_SEED),
+        (TimestampNtz, Scalar::TimestampNtz(v)) => hash_long(*v, SPARK_HASH_SEED),
+        (String, Scalar::String(v)) => hash_bytes(v.as_bytes(), SPARK_HASH_SEED),
+        (Binary, Scalar::Binary(v)) => hash_bytes(v, SPARK_HASH_SEED),
+        _ => return None,
+    };
+    Some(hash)
+}
+
+/// Prunes files whose bucket partition value can't match the equality predicates of a scan.
+#[derive(Debug)]
+pub(crate) struct PartitionPruningFilter {
+    /// (physical partition column name, required bucket) pairs. A file is kept only if it can be
+    /// in the required bucket for every entry.
+    required_buckets: Vec<(String, i32)>,
+}
+
+impl PartitionPruningFilter {
+    /// Creates a new partition pruning filter. Returns `None` if there is no predicate, or the
+    /// predicate doesn't allow pruning any bucket partition column.
+    pub(crate) fn new(
+        table_schema: &StructType,
+        partition_columns: &[String],
+        column_mapping_mode: ColumnMappingMode,
+        predicate: &Option<Expression>,
+    ) -> Option<Self> {
+        let mut equalities = vec![];
+        collect_equalities(predicate.as_ref()?, &mut equalities);
+
+        let mut required_buckets = vec![];
+        for partition_column in partition_columns {
+            let Some(field) = table_schema.field(partition_column) else {
+                continue;
+            };
+            let Some(bucket) = field
+                .generation_expression()
+                .and_then(parse_bucket_expression)
+            else {
+                continue;
+            };
+            let Some(source_field) = table_schema.field(&bucket.source_column) else {
+                continue;
+            };
+            let Ok(physical_name) = field.physical_name(column_mapping_mode) else {
+                continue;
+            };
+            for (_, value) in equalities
+                .iter()
+                .filter(|(col, _)| *col == bucket.source_column)
+            {
+                if let Some(hash) = spark_hash(value, source_field.data_type()) {
+                    let required_bucket = hash.rem_euclid(bucket.num_buckets);
+                    debug!("Pruning {partition_column} to bucket {required_bucket}");
+                    required_buckets.push((physical_name.to_string(), required_bucket));
+                }
+            }
+        }
+
+        (!required_buckets.is_empty()).then_some(Self { required_buckets })
+    }
+
+    /// Check if a file with the given partition values can contain rows matching the predicate.
+    /// Files with missing or unparseable bucket values are always kept.
+    pub(crate) fn keep(&self, partition_values: &HashMap<String, String>) -> bool {
+        self.required_buckets
+            .iter()
+            .all(|(column, required_bucket)| {
+                match partition_values
+                    .get(column)
+                    .map(|value| value.parse::<i32>())
+                {
+                    Some(Ok(bucket)) => bucket == *required_bucket,
+                    _ => true,
+                }
+            })
+    }
+
+    /// Apply the filter to a batch of scan files (with the schema of [`scan_row_schema`]) by
+    /// deselecting any file that can be pruned.
+    ///
+    /// [`scan_row_schema`]: crate::scan::scan_row_schema
+    pub(crate) fn apply(
+        &self,
+        scan_files: &dyn EngineData,
+        selection_vector: &mut [bool],
+    ) -> DeltaResult<()> {
+        lazy_static::lazy_static!(
+            static ref PARTITION_VALUES_SCHEMA: Arc<StructType> =
+                Arc::new(StructType::new(vec![StructField::new(
+                    "fileConstantValues",
+                    StructType::new(vec![StructField::new(
+                        "partitionValues",
+                        MapType::new(DataType::STRING, DataType::STRING, false),
+                        true,
+                    )]),
+                    true,
+                )]));
+        );
+        let mut visitor = PruningVisitor {
+            filter: self,
+            selection_vector,
+        };
+        scan_files.extract(PARTITION_VALUES_SCHEMA.clone(), &mut visitor)
+    }
+}
+
+struct PruningVisitor<'a> {
+    filter: &'a PartitionPruningFilter,
+    selection_vector: &'a mut [bool],
+}
+
+impl DataVisitor for PruningVisitor<'_> {
+    fn visit<'a>(&mut self, row_count: usize, getters: &[&'a dyn GetData<'a>]) -> DeltaResult<()> {
+        for row_index in 0..row_count {
+            if !self.selection_vector[row_index] {
+                continue;
+            }
+            let partition_values: Option<HashMap<_, _>> =
+                getters[0].get_opt(row_index, "scanFile.fileConstantValues.partitionValues")?;
+            if let Some(partition_values) = partition_values {
+                self.selection_vector[row_index] = self.filter.keep(&partition_values);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::{ColumnMetadataKey, MetadataValue};
+
+    #[test]
+    fn test_parse_bucket_expression() {
+        let expected = Some(BucketExpression {
+            source_column: "id".to_string(),
+            num_buckets: 16,
+        });
+        assert_eq!(parse_bucket_expression("pmod(hash(id), 16)"), expected);
+        assert_eq!(
+            parse_bucket_expression(" PMOD ( HASH(`id`),16 ) "),
+            expected
+        );
+        assert_eq!(parse_bucket_expression("pmod(hash(id), 0)"), None);
+        assert_eq!(parse_bucket_expression("pmod(hash(id, name), 16)"), None);
+        assert_eq!(parse_bucket_expression("pmod(xxhash64(id), 16)"), None);
+        assert_eq!(parse_bucket_expression("hash(id) % 16"), None);
+        assert_eq!(parse_bucket_expression("YEAR(ts)"), None);
+    }
+
+    #[test]
+    fn test_spark_hash() {
+        // expected values computed with spark's `hash` function
+        let cases = [
+            (Scalar::Integer(1), DataType::INTEGER, -559580957),
+            (Scalar::Integer(0), DataType::INTEGER, 933211791),
+            (Scalar::Long(1), DataType::LONG, -1712319331),
+            (Scalar::Integer(1), DataType::LONG, -1712319331),
+            (Scalar::String("".to_string()), DataType::STRING, 142593372),
+            (
+                Scalar::String("Spark".to_string()),
+                DataType::STRING,
+                228093765,
+            ),
+        ];
+        for (value, data_type, expected) in cases {
+            assert_eq!(spark_hash(&value, &data_type), Some(expected), "{value}");
+        }
+        assert_eq!(spark_hash(&Scalar::Long(1), &DataType::INTEGER), None);
+        assert_eq!(spark_hash(&Scalar::Double(1.0), &DataType::DOUBLE), None);
+    }
+
+    #[test]
+    fn test_partition_pruning_filter() {
+        let schema = StructType::new(vec![
+            StructField::new("id", DataType::INTEGER, true),
+            StructField::new("bucket", DataType::INTEGER, true).with_metadata([(
+                ColumnMetadataKey::GenerationExpression.as_ref(),
+                MetadataValue::String("pmod(hash(id), 4)".to_string()),
+            )]),
+        ]);
+        let partition_columns = ["bucket".to_string()];
+        let new_filter = |predicate| {
+            PartitionPruningFilter::new(
+                &schema,
+                &partition_columns,
+                ColumnMappingMode::None,
+                &Some(predicate),
+            )
+        };
+        let bucket = |value: &str| HashMap::from([("bucket".to_string(), value.to_string())]);
+
+        // hash(1) = -559580957, and pmod(-559580957, 4) = 3
+        let filter = new_filter(Expression::column("id").eq(Expression::literal(1))).unwrap();
+        assert!(filter.keep(&bucket("3")));
+        assert!(!filter.keep(&bucket("0")));
+        assert!(filter.keep(&bucket("not a number")));
+        assert!(filter.keep(&HashMap::new()));
+
+        let filter = new_filter(Expression::and(
+            Expression::literal(1).eq(Expression::column("id")),
+            Expression::column("other").gt(Expression::literal(3)),
+        ))
+        .unwrap();
+        assert!(filter.keep(&bucket("3")));
+        assert!(!filter.keep(&bucket("2")));
+
+        // predicates we can't prune with
+        assert!(new_filter(Expression::column("id").gt(Expression::literal(1))).is_none());
+        assert!(new_filter(Expression::or(
+            Expression::column("id").eq(Expression::literal(1)),
+            Expression::column("id").eq(Expression::literal(2)),
+        ))
+        .is_none());
+        assert!(new_filter(Expression::column("id").eq(Expression::literal(1.0))).is_none());
+    }
+
+    #[test]
+    fn test_normalize_partition_predicate() {
+        let partition_columns = ["day".to_string(), "hour".to_string()];
+        let normalize = |predicate| normalize_partition_predicate(predicate, &partition_columns);
+        let day = || Expression::literal("2024-01-01");
+
+        let predicate = Expression::column("__partition__.day").eq(day());
+        assert_eq!(
+            normalize(predicate).unwrap(),
+            Expression::column("day").eq(day())
+        );
+
+        // references outside of the partition struct are kept
+        let predicate = !Expression::and(
+            Expression::column("__partition__.hour").gt(Expression::literal(3)),
+            Expression::column("value").gt(Expression::literal(3)),
+        );
+        let expected = !Expression::and(
+            Expression::column("hour").gt(Expression::literal(3)),
+            Expression::column("value").gt(Expression::literal(3)),
+        );
+        assert_eq!(normalize(predicate).unwrap(), expected);
+
+        let values = Scalar::Struct(
+            StructData::try_new(
+                vec![
+                    StructField::new("day", DataType::STRING, true),
+                    StructField::new("hour", DataType::INTEGER, true),
+                ],
+                vec![Scalar::from("2024-01-01"), Scalar::from(12)],
+            )
+            .unwrap(),
+        );
+        let expected = Expression::and_from([
+            Expression::column("day").eq(day()),
+            Expression::column("hour").eq(Expression::literal(12)),
+        ]);
+        let predicate =
+            Expression::column(PARTITION_STRUCT_COLUMN).eq(Expression::literal(values.clone()));
+        assert_eq!(normalize(predicate).unwrap(), expected);
+        let predicate = Expression::literal(values).eq(Expression::column(PARTITION_STRUCT_COLUMN));
+        assert_eq!(normalize(predicate).unwrap(), expected);
+
+        // the struct only has the partition columns, and can only be compared to a struct literal
+        let predicate = Expression::column("__partition__.value").eq(Expression::literal(1));
+        assert!(matches!(normalize(predicate), Err(Error::MissingColumn(_))));
+        let predicate = Expression::column(PARTITION_STRUCT_COLUMN).is_null();
+        assert!(normalize(predicate).is_err());
+    }
+}
@@ -6,6 +6,7 @@ use crate::{
     actions::{
         deletion_vector::{treemap_to_bools, DeletionVectorDescriptor},
         visitors::visit_deletion_vector_at,
+        Add, Remove,
     },
     column_mapping::ColumnMappingMode,
     engine_data::{GetData, TypedGetData},
@@ -15,6 +16,7 @@ use crate::{
 use serde::{Deserialize, Serialize};
 
 use super::log_replay::SCAN_ROW_SCHEMA;
+use super::FieldMetadataMode;
 
 /// State that doesn't change beween scans
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -24,6 +26,8 @@ pub struct GlobalScanState {
     pub logical_schema: SchemaRef,
     pub read_schema: SchemaRef,
     pub column_mapping_mode: ColumnMappingMode,
+    #[serde(default)]
+    pub field_metadata_mode: FieldMetadataMode,
 }
 
 /// this struct can be used by an engine to materialize a selection vector
@@ -33,6 +37,13 @@ pub struct DvInfo {
 }
 
 impl DvInfo {
+    /// Whether the file has a deletion vector. This doesn't read the deletion vector, so it can
+    /// be used to plan a scan without fetching the deletion vectors of files that end up not being
+    /// read.
+    pub fn has_vector(&self) -> bool {
+        self.deletion_vector.is_some()
+    }
+
     pub fn get_selection_vector(
         &self,
         engine: &dyn Engine,
@@ -50,6 +61,47 @@ impl DvInfo {
     }
 }
 
+/// A data file of a table, with what an engine needs to read it: the arguments that
+/// [`visit_scan_files`] passes to its callback, as one value. See e.g.
+/// [`crate::snapshot::Snapshot::added_files_since`].
+#[derive(Debug)]
+pub struct ScanFile {
+    /// The path of the file, relative to the table root unless it is an absolute URL
+    pub path: String,
+    /// The size of the file in bytes. This is only `None` for removed files that don't record it
+    pub size: Option<i64>,
+    /// The deletion vector of the file, which allows getting its selection vector
+    pub dv_info: DvInfo,
+    /// The partition values of the file
+    pub partition_values: HashMap<String, String>,
+}
+
+impl From<Add> for ScanFile {
+    fn from(add: Add) -> Self {
+        Self {
+            path: add.path,
+            size: Some(add.size),
+            dv_info: DvInfo {
+                deletion_vector: add.deletion_vector,
+            },
+            partition_values: add.partition_values,
+        }
+    }
+}
+
+impl From<Remove> for ScanFile {
+    fn from(remove: Remove) -> Self {
+        Self {
+            path: remove.path,
+            size: remove.size,
+            dv_info: DvInfo {
+                deletion_vector: remove.deletion_vector,
+            },
+            partition_values: remove.partition_values.unwrap_or_default(),
+        }
+    }
+}
+
 /// Request that the kernel call a callback on each valid file that needs to be read for the
 /// scan.
 ///
@@ -157,7 +209,7 @@ mod tests {
         assert_eq!(size, 635);
         assert_eq!(part_vals.get("date"), Some(&"2017-12-10".to_string()));
         assert_eq!(part_vals.get("non-existent"), None);
-        assert!(dv_info.deletion_vector.is_some());
+        assert!(dv_info.has_vector());
         let dv = dv_info.deletion_vector.unwrap();
         assert_eq!(dv.unique_id(), "uvBn[lx{q8@P<9BNH/isA@1");
         assert_eq!(context.id, 2);
@@ -21,6 +21,10 @@ pub enum MetadataValue {
     Number(i32),
     String(String),
     Boolean(bool),
+    /// Any other JSON value, e.g. a number that doesn't fit in an `i32` (like the high water mark
+    /// of an identity column) or a nested object. These are kept as is, so that serializing a
+    /// schema gives back the metadata it was parsed from.
+    Other(serde_json::Value),
 }
 
 impl From<String> for MetadataValue {
@@ -57,6 +61,9 @@ pub enum ColumnMetadataKey {
     IdentityHighWaterMark,
     IdentityAllowExplicitInsert,
     Invariants,
+    /// The field id of the column in parquet files, which the parquet handler matches columns by
+    /// (before falling back to their names) when it is set on a field of the read schema
+    ParquetFieldId,
 }
 
 impl AsRef<str> for ColumnMetadataKey {
@@ -70,6 +77,7 @@ impl AsRef<str> for ColumnMetadataKey {
             Self::IdentityStart => "delta.identity.start",
             Self::IdentityStep => "delta.identity.step",
             Self::Invariants => "delta.invariants",
+            Self::ParquetFieldId => "parquet.field.id",
         }
     }
 }
@@ -113,6 +121,15 @@ impl StructField {
         self.metadata.get(key.as_ref())
     }
 
+    /// Get the generation expression of this field, if it is a generated column. This is the SQL
+    /// expression (e.g. `pmod(hash(id), 16)`) the column's values are derived from.
+    pub fn generation_expression(&self) -> Option<&str> {
+        match self.get_config_value(&ColumnMetadataKey::GenerationExpression) {
+            Some(MetadataValue::String(expr)) => Some(expr),
+            _ => None,
+        }
+    }
+
     /// Get the physical name for this field as it should be read from parquet, based on the
     /// specified column mapping mode.
     pub fn physical_name(&self, mapping_mode: ColumnMappingMode) -> DeltaResult<&str> {
@@ -124,9 +141,10 @@ impl StructField {
             (ColumnMappingMode::Name, invalid) => Err(Error::generic(format!(
                 "Missing or invalid {physical_name_key}: {invalid:?}"
             ))),
-            (ColumnMappingMode::Id, _) => {
-                Err(Error::generic("Don't support id column mapping yet"))
-            }
+            // files are matched by field id, but still have physical names when they were written
+            // by writers that set them
+            (ColumnMappingMode::Id, Some(MetadataValue::String(name))) => Ok(name),
+            (ColumnMappingMode::Id, _) => Ok(self.name.as_str()),
         }
     }
 
@@ -358,6 +376,8 @@ pub enum PrimitiveType {
     Timestamp,
     #[serde(rename = "timestamp_ntz")]
     TimestampNtz,
+    /// A column that is always null, with no physical representation in data files
+    Void,
     #[serde(
         serialize_with = "serialize_decimal",
         deserialize_with = "deserialize_decimal",
@@ -416,6 +436,7 @@ impl Display for PrimitiveType {
             PrimitiveType::Date => write!(f, "date"),
             PrimitiveType::Timestamp => write!(f, "timestamp"),
             PrimitiveType::TimestampNtz => write!(f, "timestamp_ntz"),
+            PrimitiveType::Void => write!(f, "void"),
             PrimitiveType::Decimal(precision, scale) => {
                 write!(f, "decimal({},{})", precision, scale)
             }
@@ -476,6 +497,7 @@ impl DataType {
     pub const DATE: Self = DataType::Primitive(PrimitiveType::Date);
     pub const TIMESTAMP: Self = DataType::Primitive(PrimitiveType::Timestamp);
     pub const TIMESTAMP_NTZ: Self = DataType::Primitive(PrimitiveType::TimestampNtz);
+    pub const VOID: Self = DataType::Primitive(PrimitiveType::Void);
 
     pub fn decimal(precision: u8, scale: u8) -> DeltaResult<Self> {
         PrimitiveType::check_decimal(precision, scale)?;
@@ -536,6 +558,18 @@ mod tests {
             DataType::Primitive(PrimitiveType::Integer)
         ));
 
+        let data = r#"
+        {
+            "name": "v",
+            "type": "void",
+            "nullable": true,
+            "metadata": {}
+        }
+        "#;
+        let field: StructField = serde_json::from_str(data).unwrap();
+        assert_eq!(field.data_type, DataType::VOID);
+        assert_eq!(field.data_type.to_string(), "void");
+
         let data = r#"
         {
             "name": "c",
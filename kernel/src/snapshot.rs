@@ -2,28 +2,84 @@
 //! has schema etc.)
 //!
 
-use std::cmp::Ordering;
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::sync::{Arc, Mutex, OnceLock};
 
+use either::Either;
+use indexmap::IndexMap;
 use itertools::Itertools;
 use serde::{Deserialize, Serialize};
-use tracing::debug;
+use tracing::{debug, warn};
 use url::Url;
 
-use crate::actions::{get_log_schema, Metadata, Protocol, METADATA_NAME, PROTOCOL_NAME};
-use crate::column_mapping::{ColumnMappingMode, COLUMN_MAPPING_MODE_KEY};
+use crate::actions::visitors::{AddVisitor, RemoveVisitor, SidecarVisitor};
+use crate::actions::{
+    get_log_schema_for, ActionType, Add, CommitInfo, Metadata, Protocol, Remove, ADD_NAME,
+    REMOVE_NAME,
+};
+use crate::column_mapping::{ColumnMapping, ColumnMappingMode, COLUMN_MAPPING_MODE_KEY};
+use crate::expressions::parse_expression;
 use crate::path::{version_from_location, LogPath};
-use crate::schema::{Schema, SchemaRef};
+use crate::scan::log_replay::LogReplayScanner;
+use crate::scan::state::ScanFile;
+use crate::schema::{
+    ArrayType, ColumnMetadataKey, DataType, MapType, Schema, SchemaRef, StructField, StructType,
+};
+use crate::tombstones::active_files_and_tombstones;
 use crate::utils::require;
-use crate::{DeltaResult, Engine, Error, FileMeta, FileSystemClient, Version};
+use crate::{
+    DeltaResult, Engine, Error, FileDataReadResultIterator, FileMeta, FileSystemClient,
+    JsonHandler, ParquetHandler, Version,
+};
 use crate::{EngineData, Expression};
 
 const LAST_CHECKPOINT_FILE_NAME: &str = "_last_checkpoint";
+const IN_COMMIT_TIMESTAMP_FEATURE: &str = "inCommitTimestamp";
+const ENABLE_IN_COMMIT_TIMESTAMPS_KEY: &str = "delta.enableInCommitTimestamps";
+const IN_COMMIT_TIMESTAMP_ENABLEMENT_VERSION_KEY: &str = "delta.inCommitTimestampEnablementVersion";
 
-#[derive(Debug)]
+/// `struct_type` without the column mapping metadata of its (possibly nested) fields
+fn without_column_mapping_metadata(struct_type: &StructType) -> StructType {
+    fn strip(data_type: &DataType) -> DataType {
+        match data_type {
+            DataType::Struct(inner) => without_column_mapping_metadata(inner).into(),
+            DataType::Array(array) => {
+                ArrayType::new(strip(array.element_type()), array.contains_null()).into()
+            }
+            DataType::Map(map) => MapType::new(
+                strip(map.key_type()),
+                strip(map.value_type()),
+                map.value_contains_null(),
+            )
+            .into(),
+            primitive => primitive.clone(),
+        }
+    }
+    let keys = [
+        ColumnMetadataKey::ColumnMappingId,
+        ColumnMetadataKey::ColumnMappingPhysicalName,
+        ColumnMetadataKey::ParquetFieldId,
+    ];
+    let fields = struct_type.fields().map(|field| {
+        let mut metadata = field.metadata().clone();
+        for key in &keys {
+            metadata.remove(key.as_ref());
+        }
+        StructField {
+            data_type: strip(field.data_type()),
+            metadata,
+            ..field.clone()
+        }
+    });
+    StructType::new(fields.collect())
+}
+
+#[derive(Debug, Clone)]
 #[cfg_attr(feature = "developer-visibility", visibility::make(pub))]
 #[cfg_attr(not(feature = "developer-visibility"), visibility::make(pub(crate)))]
 struct LogSegment {
-    log_root: Url,
+    pub(crate) log_root: Url,
     /// Reverse order sorted commit files in the log segment
     pub(crate) commit_files: Vec<FileMeta>,
     /// checkpoint files in the log segment.
@@ -33,7 +89,8 @@ struct LogSegment {
 impl LogSegment {
     /// Read a stream of log data from this log segment.
     ///
-    /// The log files will be read from most recent to oldest.
+    /// The log files will be read from most recent to oldest. The sidecar files of a V2
+    /// checkpoint are read after its top-level file, if `checkpoint_read_schema` has file actions.
     /// The boolean flags indicates whether the data was read from
     /// a commit file (true) or a checkpoint file (false).
     ///
@@ -57,10 +114,25 @@ impl LogSegment {
             .map_ok(|batch| (batch, true));
 
         let parquet_client = engine.get_parquet_handler();
+        let log_root = self.log_root.clone();
+        let checkpoint_files = self.checkpoint_files.clone();
+        // the checkpoint is only opened once the commits have been consumed, so that callers that
+        // stop early (e.g. once they found the protocol and metadata) don't read it at all
         // TODO change predicate to: predicate AND add.path not null
-        let checkpoint_stream = parquet_client
-            .read_parquet_files(&self.checkpoint_files, checkpoint_read_schema, predicate)?
-            .map_ok(|batch| (batch, false));
+        let checkpoint_stream = std::iter::once_with(move || {
+            read_checkpoint(
+                json_client.as_ref(),
+                parquet_client.as_ref(),
+                &log_root,
+                &checkpoint_files,
+                checkpoint_read_schema,
+                predicate,
+            )
+        })
+        .flat_map(|batches| match batches {
+            Ok(batches) => Either::Left(batches.map_ok(|batch| (batch, false))),
+            Err(err) => Either::Right(std::iter::once(Err(err))),
+        });
 
         let batches = commit_stream.chain(checkpoint_stream);
 
@@ -68,7 +140,7 @@ impl LogSegment {
     }
 
     fn read_metadata(&self, engine: &dyn Engine) -> DeltaResult<Option<(Metadata, Protocol)>> {
-        let schema = get_log_schema().project(&[PROTOCOL_NAME, METADATA_NAME])?;
+        let schema = get_log_schema_for(&[ActionType::Protocol, ActionType::Metadata])?;
         // read the same protocol and metadata schema for both commits and checkpoints
         // TODO add metadata.table_id is not null and protocol.something_required is not null
         let data_batches = self.replay(engine, schema.clone(), schema, None)?;
@@ -96,11 +168,135 @@ impl LogSegment {
     }
 }
 
+/// The maximum number of sidecar files a checkpoint can have. Writers split the file actions of a
+/// checkpoint into sidecars of many actions each, so even the checkpoints of tables with hundreds of
+/// millions of files have far fewer sidecars. A checkpoint with more is treated as corrupt, rather
+/// than opening that many files.
+const MAX_CHECKPOINT_SIDECARS: usize = 10_000;
+
+/// Read the `checkpoint_files` with `read_schema`. If the schema has file actions and the
+/// checkpoint is a V2 checkpoint with sidecar files, the sidecar files are read after the
+/// checkpoint files themselves. The top-level file of a V2 checkpoint can be a JSON file, other
+/// checkpoint files are parquet files.
+fn read_checkpoint(
+    json_client: &dyn JsonHandler,
+    parquet_client: &dyn ParquetHandler,
+    log_root: &Url,
+    checkpoint_files: &[FileMeta],
+    read_schema: SchemaRef,
+    predicate: Option<Expression>,
+) -> DeltaResult<FileDataReadResultIterator> {
+    let batches = read_checkpoint_files(
+        json_client,
+        parquet_client,
+        checkpoint_files,
+        read_schema.clone(),
+        predicate.clone(),
+    )?;
+    // sidecars only have file actions
+    if read_schema.field(ADD_NAME).is_none() && read_schema.field(REMOVE_NAME).is_none() {
+        return Ok(batches);
+    }
+    let sidecar_files =
+        checkpoint_sidecar_files(json_client, parquet_client, log_root, checkpoint_files)?;
+    if sidecar_files.is_empty() {
+        return Ok(batches);
+    }
+    let sidecar_batches =
+        parquet_client.read_parquet_files(&sidecar_files, read_schema, predicate)?;
+    Ok(Box::new(batches.chain(sidecar_batches)))
+}
+
+/// Read `checkpoint_files` with the handler for their format
+fn read_checkpoint_files(
+    json_client: &dyn JsonHandler,
+    parquet_client: &dyn ParquetHandler,
+    checkpoint_files: &[FileMeta],
+    read_schema: SchemaRef,
+    predicate: Option<Expression>,
+) -> DeltaResult<FileDataReadResultIterator> {
+    let is_json = |file: &FileMeta| LogPath::new(&file.location).extension() == Some("json");
+    if checkpoint_files.iter().any(is_json) {
+        json_client.read_json_files(checkpoint_files, read_schema, predicate)
+    } else {
+        parquet_client.read_parquet_files(checkpoint_files, read_schema, predicate)
+    }
+}
+
+/// Get the sidecar files the `checkpoint_files` reference with `sidecar` actions, in the order
+/// they are referenced. Sidecar files hold file actions only, so they can't reference further
+/// sidecars. A corrupt checkpoint that references a file more than once (or references one of
+/// its own files) would be read repeatedly, so this fails with an [`Error::InvalidCommit`]
+/// instead, as it does for a checkpoint with more than [`MAX_CHECKPOINT_SIDECARS`] sidecars.
+fn checkpoint_sidecar_files(
+    json_client: &dyn JsonHandler,
+    parquet_client: &dyn ParquetHandler,
+    log_root: &Url,
+    checkpoint_files: &[FileMeta],
+) -> DeltaResult<Vec<FileMeta>> {
+    let schema = get_log_schema_for(&[ActionType::Sidecar])?;
+    let sidecar_root = log_root.join("_sidecars/")?;
+    let mut referenced: HashSet<Url> = checkpoint_files
+        .iter()
+        .map(|file| file.location.clone())
+        .collect();
+    let mut sidecar_files = Vec::new();
+    for batch in read_checkpoint_files(
+        json_client,
+        parquet_client,
+        checkpoint_files,
+        schema.clone(),
+        None,
+    )? {
+        let mut visitor = SidecarVisitor::default();
+        batch?.extract(schema.clone(), &mut visitor)?;
+        for sidecar in visitor.sidecars {
+            let location = sidecar_root.join(&sidecar.path)?;
+            require!(
+                referenced.insert(location.clone()),
+                Error::invalid_commit(format!(
+                    "{location} is referenced more than once as a sidecar of the checkpoint"
+                ))
+            );
+            require!(
+                sidecar_files.len() < MAX_CHECKPOINT_SIDECARS,
+                Error::invalid_commit(format!(
+                    "The checkpoint has more than {MAX_CHECKPOINT_SIDECARS} sidecars"
+                ))
+            );
+            sidecar_files.push(FileMeta {
+                location,
+                last_modified: sidecar.modification_time,
+                size: sidecar.size_in_bytes.try_into().map_err(|_| {
+                    Error::invalid_checkpoint(format!(
+                        "Sidecar {} has a negative size",
+                        sidecar.path
+                    ))
+                })?,
+                e_tag: None,
+                version: None,
+            });
+        }
+    }
+    Ok(sidecar_files)
+}
+
+/// The generation expression of a generated column, see [`Snapshot::generated_columns`]
+#[derive(Debug, Clone, PartialEq)]
+pub enum GenerationExpression {
+    /// The expression parsed into a kernel [`Expression`]
+    Parsed(Expression),
+    /// The SQL of an expression that kernel expressions can't represent (e.g. because it calls
+    /// a function like `hash`), as it is in the table's schema
+    Unparsed(String),
+}
+
 // TODO expose methods for accessing the files of a table (with file pruning).
 /// In-memory representation of a specific snapshot of a Delta table. While a `DeltaTable` exists
 /// throughout time, `Snapshot`s represent a view of a table at a specific point in time; they
 /// have a defined schema (which may change over time for any given table), specific version, and
 /// frozen log segment.
+#[derive(Clone)]
 pub struct Snapshot {
     pub(crate) table_root: Url,
     pub(crate) log_segment: LogSegment,
@@ -109,6 +305,7 @@ pub struct Snapshot {
     protocol: Protocol,
     schema: Schema,
     pub(crate) column_mapping_mode: ColumnMappingMode,
+    column_mapping: Option<ColumnMapping>,
 }
 
 impl Drop for Snapshot {
@@ -140,16 +337,69 @@ impl Snapshot {
         engine: &dyn Engine,
         version: Option<Version>,
     ) -> DeltaResult<Self> {
+        let (log_segment, version_eff) = Self::list_log_segment(&table_root, engine, version)?;
+        Self::try_new_from_log_segment(table_root, log_segment, version_eff, engine)
+    }
+
+    /// Create a new [`Snapshot`] for the given version like [`Snapshot::try_new`], reusing the
+    /// log segment of the table at that version from `cache` if it was loaded before, in which
+    /// case the log isn't listed or read at all. Otherwise the snapshot is constructed as usual,
+    /// and its log segment added to `cache`. Without a `version`, the log is still listed to find
+    /// the latest version, but only read if that version isn't cached. Concurrent calls for the
+    /// same version that isn't cached yet share a single listing and read of the log (see
+    /// [`LogSegmentCache`]).
+    pub fn try_new_with_log_cache(
+        table_root: Url,
+        engine: &dyn Engine,
+        version: Option<Version>,
+        cache: &LogSegmentCache,
+    ) -> DeltaResult<Self> {
+        let load = |log_segment, version| {
+            let snapshot =
+                Self::try_new_from_log_segment(table_root.clone(), log_segment, version, engine)?;
+            Ok(CachedLogSegment {
+                log_segment: snapshot.log_segment.clone(),
+                metadata: snapshot.metadata.clone(),
+                protocol: snapshot.protocol.clone(),
+            })
+        };
+        let (cached, version) = match version {
+            Some(version) => {
+                let cached = cache.get_or_load(&table_root, version, || {
+                    let (log_segment, version) =
+                        Self::list_log_segment(&table_root, engine, Some(version))?;
+                    load(log_segment, version)
+                })?;
+                (cached, version)
+            }
+            None => {
+                let (log_segment, version) = Self::list_log_segment(&table_root, engine, None)?;
+                let cached =
+                    cache.get_or_load(&table_root, version, || load(log_segment, version))?;
+                (cached, version)
+            }
+        };
+        cached.to_snapshot(table_root, version)
+    }
+
+    /// List the log segment of the table at `table_root` up to `version` (or the latest version),
+    /// returning it with its version
+    fn list_log_segment(
+        table_root: &Url,
+        engine: &dyn Engine,
+        version: Option<Version>,
+    ) -> DeltaResult<(LogSegment, Version)> {
         let fs_client = engine.get_file_system_client();
-        let log_url = LogPath::new(&table_root).child("_delta_log/").unwrap();
+        let log_url = LogPath::new(table_root).child("_delta_log/").unwrap();
 
-        // List relevant files from log
+        // List relevant files from log, starting at the last checkpoint unless it's newer than the
+        // requested version
         let (mut commit_files, checkpoint_files) =
-            match (read_last_checkpoint(fs_client.as_ref(), &log_url)?, version) {
-                (Some(cp), Some(version)) if cp.version >= version => {
-                    list_log_files_with_checkpoint(&cp, fs_client.as_ref(), &log_url)?
+            match read_last_checkpoint(fs_client.as_ref(), &log_url)? {
+                Some(cp) if cp.version <= version.unwrap_or(Version::MAX) => {
+                    list_log_files_with_checkpoint(&cp, fs_client.as_ref(), &log_url, version)?
                 }
-                _ => list_log_files(fs_client.as_ref(), &log_url)?,
+                _ => list_log_files(fs_client.as_ref(), &log_url, version)?,
             };
 
         // remove all files above requested version
@@ -182,8 +432,7 @@ impl Snapshot {
             commit_files,
             checkpoint_files,
         };
-
-        Self::try_new_from_log_segment(table_root, log_segment, version_eff, engine)
+        Ok((log_segment, version_eff))
     }
 
     /// Create a new [`Snapshot`] instance.
@@ -196,11 +445,25 @@ impl Snapshot {
         let (metadata, protocol) = log_segment
             .read_metadata(engine)?
             .ok_or(Error::MissingMetadata)?;
+        Self::try_new_from_parts(location, log_segment, version, metadata, protocol)
+    }
+
+    /// Create a new [`Snapshot`] from the already read `metadata` and `protocol` of its version
+    fn try_new_from_parts(
+        location: Url,
+        log_segment: LogSegment,
+        version: Version,
+        metadata: Metadata,
+        protocol: Protocol,
+    ) -> DeltaResult<Self> {
+        protocol.ensure_read_supported()?;
         let schema = metadata.schema()?;
+        protocol.ensure_schema_supported(&schema)?;
         let column_mapping_mode = match metadata.configuration.get(COLUMN_MAPPING_MODE_KEY) {
             Some(mode) if protocol.min_reader_version >= 2 => mode.as_str().try_into(),
             _ => Ok(ColumnMappingMode::None),
         }?;
+        let column_mapping = ColumnMapping::try_new(&schema, column_mapping_mode)?;
         Ok(Self {
             table_root: location,
             log_segment,
@@ -209,9 +472,40 @@ impl Snapshot {
             protocol,
             schema,
             column_mapping_mode,
+            column_mapping,
         })
     }
 
+    /// Read only the [`Metadata`] and [`Protocol`] of the latest version of the table at
+    /// `table_root`, e.g. to describe a table without constructing a full [`Snapshot`] of it.
+    ///
+    /// If the table has a `_last_checkpoint` file, only the log files from that checkpoint on are
+    /// listed. The commits after the checkpoint are read newest first, and the checkpoint itself is
+    /// only read if they don't contain both actions. Only the protocol and metadata columns of
+    /// the checkpoint are read, so no file actions are parsed.
+    pub fn latest_metadata_and_protocol(
+        table_root: &Url,
+        engine: &dyn Engine,
+    ) -> DeltaResult<(Metadata, Protocol)> {
+        let fs_client = engine.get_file_system_client();
+        let log_root = LogPath::new(table_root).child("_delta_log/")?;
+        let (commit_files, checkpoint_files) =
+            match read_last_checkpoint(fs_client.as_ref(), &log_root)? {
+                Some(cp) => {
+                    list_log_files_with_checkpoint(&cp, fs_client.as_ref(), &log_root, None)?
+                }
+                None => list_log_files(fs_client.as_ref(), &log_root, None)?,
+            };
+        let log_segment = LogSegment {
+            log_root,
+            commit_files,
+            checkpoint_files,
+        };
+        log_segment
+            .read_metadata(engine)?
+            .ok_or(Error::MissingMetadata)
+    }
+
     /// Log segment this snapshot uses
     #[cfg_attr(feature = "developer-visibility", visibility::make(pub))]
     fn _log_segment(&self) -> &LogSegment {
@@ -224,15 +518,48 @@ impl Snapshot {
     }
 
     /// Table [`Schema`] at this `Snapshot`s version.
+    ///
+    /// This is the Delta schema of the table, as parsed from the `schemaString` of its metadata,
+    /// including the metadata of each field (e.g. `delta.columnMapping.*` keys and generation
+    /// expressions). Serializing it gives back the same schema JSON, unlike converting the schema
+    /// to arrow and back.
     pub fn schema(&self) -> &Schema {
         &self.schema
     }
 
+    /// The logical schema of the table at this `Snapshot`s version, i.e. its [`schema`] without
+    /// the column mapping metadata of its fields (their `delta.columnMapping.*` keys and parquet
+    /// field ids), which only matter for reading data files. Other field metadata, e.g. comments
+    /// and generation expressions, is kept. This is the schema to present to users, or to
+    /// validate projections against before building a scan. The schema of a scan should still be
+    /// a projection of [`schema`], since the scan needs the column mapping metadata to find the
+    /// columns in the data files.
+    ///
+    /// [`schema`]: Self::schema
+    pub fn logical_schema(&self) -> SchemaRef {
+        Arc::new(without_column_mapping_metadata(&self.schema))
+    }
+
+    /// The names of the partition columns of the table at this `Snapshot`s version, in order.
+    pub fn partition_columns(&self) -> &[String] {
+        &self.metadata.partition_columns
+    }
+
     /// Table [`Metadata`] at this `Snapshot`s version.
     pub fn metadata(&self) -> &Metadata {
         &self.metadata
     }
 
+    /// The user-facing name of the table at this `Snapshot`s version, if it has one.
+    pub fn name(&self) -> Option<&str> {
+        self.metadata.name.as_deref()
+    }
+
+    /// The user-facing description of the table at this `Snapshot`s version, if it has one.
+    pub fn description(&self) -> Option<&str> {
+        self.metadata.description.as_deref()
+    }
+
     /// Table [`Protocol`] at this `Snapshot`s version.
     pub fn protocol(&self) -> &Protocol {
         &self.protocol
@@ -244,153 +571,1021 @@ impl Snapshot {
     pub fn column_mapping_mode(&self) -> ColumnMappingMode {
         self.column_mapping_mode
     }
-}
 
-#[derive(Debug, Deserialize, Serialize)]
-#[serde(rename_all = "camelCase")]
-#[cfg_attr(feature = "developer-visibility", visibility::make(pub))]
-#[cfg_attr(not(feature = "developer-visibility"), visibility::make(pub(crate)))]
-struct CheckpointMetadata {
-    /// The version of the table when the last checkpoint was made.
-    #[allow(unreachable_pub)] // used by acceptance tests (TODO make an fn accessor?)
-    pub version: Version,
-    /// The number of actions that are stored in the checkpoint.
-    pub(crate) size: i32,
-    /// The number of fragments if the last checkpoint was written in multiple parts.
-    pub(crate) parts: Option<i32>,
-    /// The number of bytes of the checkpoint.
-    pub(crate) size_in_bytes: Option<i32>,
-    /// The number of AddFile actions in the checkpoint.
-    pub(crate) num_of_add_files: Option<i32>,
-    /// The schema of the checkpoint file.
-    pub(crate) checkpoint_schema: Option<Schema>,
-    /// The checksum of the last checkpoint JSON.
-    pub(crate) checksum: Option<String>,
-}
+    /// Get the [`ColumnMapping`] from the logical columns of the table to the physical columns of
+    /// its parquet files at this `Snapshot`s version. Returns `None` if the table doesn't use
+    /// column mapping, in which case physical columns have the same names as logical ones.
+    pub fn column_mapping(&self) -> Option<&ColumnMapping> {
+        self.column_mapping.as_ref()
+    }
 
-/// Try reading the `_last_checkpoint` file.
-///
-/// In case the file is not found, `None` is returned.
-fn read_last_checkpoint(
-    fs_client: &dyn FileSystemClient,
-    log_root: &Url,
-) -> DeltaResult<Option<CheckpointMetadata>> {
-    let file_path = LogPath::new(log_root).child(LAST_CHECKPOINT_FILE_NAME)?;
-    match fs_client
-        .read_files(vec![(file_path, None)])
-        .and_then(|mut data| data.next().expect("read_files should return one file"))
-    {
-        Ok(data) => Ok(Some(serde_json::from_slice(&data)?)),
-        Err(Error::FileNotFound(_)) => Ok(None),
-        Err(err) => Err(err),
+    /// The generation expressions of the generated columns of the table at this `Snapshot`s
+    /// version, by column name. These are the `delta.generationExpression`s of the fields of
+    /// [`Snapshot::schema`] (see [`StructField::generation_expression`]), parsed with
+    /// [`parse_expression`] where possible, and kept as SQL where not. Their column references
+    /// are to the logical names of the columns.
+    pub fn generated_columns(&self) -> HashMap<String, GenerationExpression> {
+        self.schema
+            .fields()
+            .filter_map(|field| {
+                let sql = field.generation_expression()?;
+                let expr = match parse_expression(sql) {
+                    Ok(expr) => GenerationExpression::Parsed(expr),
+                    Err(err) => {
+                        debug!(
+                            "Can't parse generation expression of {}: {err}",
+                            field.name()
+                        );
+                        GenerationExpression::Unparsed(sql.to_string())
+                    }
+                };
+                Some((field.name().clone(), expr))
+            })
+            .collect()
+    }
+
+    /// Get the history of the table up to this `Snapshot`s version, like `DESCRIBE HISTORY`: an
+    /// entry for each commit, newest first, with its version, timestamp (resolved like
+    /// [`Snapshot::timestamp`]) and `commitInfo` action. This stops after `limit` entries, if
+    /// given, or at the earliest commit still in the log (log cleanup removes old commits). A
+    /// commit without a `commitInfo` action has an entry with a default (empty) one.
+    ///
+    /// Each commit file in the history is read, in addition to listing the log.
+    pub fn history(
+        &self,
+        engine: &dyn Engine,
+        limit: Option<usize>,
+    ) -> DeltaResult<Vec<HistoryEntry>> {
+        let commit_files = list_commit_files(
+            engine.get_file_system_client().as_ref(),
+            &self.log_segment.log_root,
+            None,
+            self.version,
+        )?;
+        let in_commit_timestamps_from = self.in_commit_timestamps_enablement_version()?;
+        commit_files
+            .iter()
+            .rev()
+            .take(limit.unwrap_or(usize::MAX))
+            .map(|commit_file| {
+                let version =
+                    version_from_location(&commit_file.location).ok_or(Error::MissingVersion)?;
+                let commit_info = read_commit_info(engine, commit_file)?;
+                let timestamp = match in_commit_timestamps_from {
+                    Some(from) if version >= from => {
+                        in_commit_timestamp(version, commit_info.as_ref())?
+                    }
+                    _ => engine.get_clock().commit_timestamp(version, commit_file),
+                };
+                Ok(HistoryEntry {
+                    version,
+                    timestamp,
+                    commit_info: commit_info.unwrap_or_default(),
+                })
+            })
+            .collect()
+    }
+
+    /// The first version from which commits of the table record an [in-commit
+    /// timestamp](https://github.com/delta-io/delta/blob/master/PROTOCOL.md#in-commit-timestamps),
+    /// or `None` if in-commit timestamps are not enabled at this `Snapshot`s version.
+    pub(crate) fn in_commit_timestamps_enablement_version(&self) -> DeltaResult<Option<Version>> {
+        let has_feature = self
+            .protocol
+            .writer_features
+            .as_ref()
+            .is_some_and(|features| features.iter().any(|f| f == IN_COMMIT_TIMESTAMP_FEATURE));
+        let configuration = &self.metadata.configuration;
+        let enabled = configuration
+            .get(ENABLE_IN_COMMIT_TIMESTAMPS_KEY)
+            .is_some_and(|enabled| enabled.eq_ignore_ascii_case("true"));
+        if !(has_feature && enabled) {
+            return Ok(None);
+        }
+        // tables created with the feature enabled need not record the enablement version
+        match configuration.get(IN_COMMIT_TIMESTAMP_ENABLEMENT_VERSION_KEY) {
+            Some(version) => Ok(Some(version.parse()?)),
+            None => Ok(Some(0)),
+        }
+    }
+
+    /// The timestamp of the commit of this `Snapshot`s version, in milliseconds since the Unix
+    /// epoch. If the table has in-commit timestamps enabled this is the `inCommitTimestamp` of the
+    /// commit's `commitInfo` action, otherwise it is the modification time of the commit file (as
+    /// resolved by the [`Engine::get_clock`] of `engine`).
+    pub fn timestamp(&self, engine: &dyn Engine) -> DeltaResult<i64> {
+        let commit_file = match self.log_segment.commit_files.first() {
+            Some(commit_file) => commit_file.clone(),
+            // the snapshot is at a checkpoint version, but its commit file may still be in the log
+            None => find_commit_file(
+                engine.get_file_system_client().as_ref(),
+                &self.log_segment.log_root,
+                self.version,
+            )?,
+        };
+        read_commit_timestamp(
+            engine,
+            &commit_file,
+            self.in_commit_timestamps_enablement_version()?,
+        )
     }
 }
 
-/// List all log files after a given checkpoint.
-fn list_log_files_with_checkpoint(
-    cp: &CheckpointMetadata,
-    fs_client: &dyn FileSystemClient,
-    log_root: &Url,
-) -> DeltaResult<(Vec<FileMeta>, Vec<FileMeta>)> {
-    let version_prefix = format!("{:020}", cp.version);
-    let start_from = log_root.join(&version_prefix)?;
+impl Snapshot {
+    /// Get the data files that were added to and removed from the table since the `base`
+    /// snapshot of it, by replaying only the commits after `base`'s version up to this snapshot's
+    /// version. Unlike the change data feed this is at the granularity of files rather than rows,
+    /// and doesn't require the table to have the change data feed enabled.
+    ///
+    /// Returns the files that are active in this snapshot but weren't in `base`, from their latest
+    /// Add action, and the files that were active in `base` but no longer are, from their latest
+    /// Remove action. A file that was added and removed again between the two snapshots is in
+    /// neither group. A file whose deletion vector changed is removed with its old deletion vector
+    /// and added with its new one.
+    ///
+    /// This fails if `base` is of a newer version than this snapshot or belongs to a different
+    /// table, or if any of the commits in between have been removed by log cleanup.
+    pub fn added_files_since(
+        &self,
+        base: &Snapshot,
+        engine: &dyn Engine,
+    ) -> DeltaResult<(Vec<ScanFile>, Vec<ScanFile>)> {
+        require!(
+            base.table_root == self.table_root,
+            Error::generic(format!(
+                "Base snapshot is of table {}, not {}",
+                base.table_root, self.table_root
+            ))
+        );
+        require!(
+            base.version <= self.version,
+            Error::generic(format!(
+                "Base snapshot version {} is newer than snapshot version {}",
+                base.version, self.version
+            ))
+        );
+        if base.version == self.version {
+            return Ok((vec![], vec![]));
+        }
+        let mut commit_files = list_commit_files(
+            engine.get_file_system_client().as_ref(),
+            &self.log_segment.log_root,
+            Some(base.version + 1),
+            self.version,
+        )?;
+        // replay from the newest commit to the oldest
+        commit_files.reverse();
 
-    let files = fs_client
-        .list_from(&start_from)?
-        .collect::<Result<Vec<_>, Error>>()?
-        .into_iter()
-        // TODO this filters out .crc files etc which start with "." - how do we want to use these kind of files?
-        .filter(|f| version_from_location(&f.location).is_some())
-        .collect::<Vec<_>>();
+        let add_schema = get_log_schema_for(&[ActionType::Add])?;
+        let remove_schema = get_log_schema_for(&[ActionType::Remove])?;
+        let read_schema = get_log_schema_for(&[ActionType::Add, ActionType::Remove])?;
+        // for each (path, dv_unique_id) pair, the newest action for it and whether the oldest one
+        // was an add, i.e. whether it was absent from the base snapshot
+        let mut files: IndexMap<_, (Either<Add, Remove>, bool)> = IndexMap::new();
+        let batches =
+            engine
+                .get_json_handler()
+                .read_json_files(&commit_files, read_schema, None)?;
+        for batch in batches {
+            let batch = batch?;
+            let mut add_visitor = AddVisitor::default();
+            batch.extract(add_schema.clone(), &mut add_visitor)?;
+            let mut remove_visitor = RemoveVisitor::default();
+            batch.extract(remove_schema.clone(), &mut remove_visitor)?;
 
-    let mut commit_files = files
-        .iter()
-        .filter_map(|f| {
-            if LogPath::new(&f.location).is_commit {
-                Some(f.clone())
-            } else {
-                None
+            // a commit can't both add and remove the same file, so the order within a batch
+            // doesn't matter
+            for add in add_visitor.adds {
+                let key = (add.path.clone(), add.dv_unique_id());
+                files
+                    .entry(key)
+                    .and_modify(|(_, oldest_is_add)| *oldest_is_add = true)
+                    .or_insert((Either::Left(add), true));
             }
-        })
-        .collect_vec();
-    // NOTE this will sort in reverse order
-    commit_files.sort_unstable_by(|a, b| b.location.cmp(&a.location));
+            for remove in remove_visitor.removes {
+                let key = (remove.path.clone(), remove.dv_unique_id());
+                files
+                    .entry(key)
+                    .and_modify(|(_, oldest_is_add)| *oldest_is_add = false)
+                    .or_insert((Either::Right(remove), false));
+            }
+        }
 
-    let checkpoint_files = files
-        .iter()
-        .filter_map(|f| {
-            if LogPath::new(&f.location).is_checkpoint {
-                Some(f.clone())
-            } else {
-                None
+        let (mut added, mut removed) = (vec![], vec![]);
+        for (newest, oldest_is_add) in files.into_values() {
+            match newest {
+                Either::Left(add) if oldest_is_add => added.push(add.into()),
+                Either::Right(remove) if !oldest_is_add => removed.push(remove.into()),
+                // the file was active in both snapshots, or in neither of them
+                _ => {}
             }
-        })
-        .collect_vec();
+        }
+        Ok((added, removed))
+    }
 
-    // TODO raise a proper error
-    assert_eq!(checkpoint_files.len(), cp.parts.unwrap_or(1) as usize);
+    /// Get the locations of the deletion vector files that the active files of this snapshot
+    /// depend on, without duplicates and in sorted order. Deletion vectors stored inline in the
+    /// log contribute nothing. Vacuum tools can compare this to the `deletion_vector_*.bin` files
+    /// in the table's directories to find deletion vector files that are safe to delete.
+    pub fn referenced_deletion_vector_files(&self, engine: &dyn Engine) -> DeltaResult<Vec<Url>> {
+        let commit_read_schema = get_log_schema_for(&[ActionType::Add, ActionType::Remove])?;
+        let checkpoint_read_schema = get_log_schema_for(&[ActionType::Add])?;
+        let log_iter =
+            self.log_segment
+                .replay(engine, commit_read_schema, checkpoint_read_schema, None)?;
 
-    Ok((commit_files, checkpoint_files))
+        let mut dv_files = BTreeSet::new();
+        let table_schema = Arc::new(self.schema.clone());
+        for add in LogReplayScanner::new(engine, &table_schema, &None)?.into_add_iter(log_iter) {
+            if let Some(dv) = add?.deletion_vector {
+                dv_files.extend(dv.absolute_path(&self.table_root)?);
+            }
+        }
+        Ok(dv_files.into_iter().collect())
+    }
+
+    /// Get the clustering implementation of this snapshot: the `clusteringProvider` (e.g.
+    /// `liquid`) of the most recently added active file that records one, or `None` if no active
+    /// file was clustered, e.g. because the table isn't clustered, or was only written to since
+    /// clustering was enabled. Clustering-aware maintenance tools can use this to report the
+    /// clustering health of a table; it doesn't affect how the table is read.
+    pub fn clustering_provider(&self, engine: &dyn Engine) -> DeltaResult<Option<String>> {
+        let commit_read_schema = get_log_schema_for(&[ActionType::Add, ActionType::Remove])?;
+        let checkpoint_read_schema = get_log_schema_for(&[ActionType::Add])?;
+        let log_iter =
+            self.log_segment
+                .replay(engine, commit_read_schema, checkpoint_read_schema, None)?;
+
+        // the log is replayed from the newest commit to the oldest
+        let table_schema = Arc::new(self.schema.clone());
+        for add in LogReplayScanner::new(engine, &table_schema, &None)?.into_add_iter(log_iter) {
+            if let Some(clustering_provider) = add?.clustering_provider {
+                return Ok(Some(clustering_provider));
+            }
+        }
+        Ok(None)
+    }
+
+    /// List the files under the table root that a vacuum with a retention period of
+    /// `retention_millis` milliseconds would delete, without deleting anything. These are the data
+    /// and deletion vector files that were last modified before the retention period, and are
+    /// neither referenced by an active file of this snapshot nor by a tombstone that was removed
+    /// within the retention period. The retention period ends at the current time of the
+    /// [`Engine::get_clock`] of `engine`.
+    /// Hidden files and directories (whose names start with `_` or `.`, like `_delta_log`) are
+    /// never candidates, except for partition directories like `_col=value`.
+    ///
+    /// Files that aren't in the log at all, like those of a write that hasn't committed yet, are
+    /// only candidates once their modification time is outside the retention period. This
+    /// requires a [`FileSystemClient`] that supports [`FileSystemClient::list_from_recursive`].
+    pub fn vacuum_candidates(
+        &self,
+        engine: &dyn Engine,
+        retention_millis: i64,
+    ) -> DeltaResult<Vec<FileMeta>> {
+        let cutoff_timestamp = engine.get_clock().now().saturating_sub(retention_millis);
+
+        let (active_files, tombstones) = active_files_and_tombstones(self, engine)?;
+        let mut retained = HashSet::new();
+        for add in &active_files {
+            retained.insert(self.table_root.join(&add.path)?);
+            if let Some(dv) = &add.deletion_vector {
+                retained.extend(dv.absolute_path(&self.table_root)?);
+            }
+        }
+        // this includes tombstones of data files that are still active, e.g. because only their
+        // deletion vector changed, whose old deletion vector must be retained until it expires
+        for remove in &tombstones {
+            // like `TombstoneScanner::expired_tombstones`, tombstones without a deletion timestamp
+            // have expired
+            if remove.deletion_timestamp.unwrap_or(0) >= cutoff_timestamp {
+                retained.insert(self.table_root.join(&remove.path)?);
+                if let Some(dv) = &remove.deletion_vector {
+                    retained.extend(dv.absolute_path(&self.table_root)?);
+                }
+            }
+        }
+
+        let is_hidden = |location: &Url| {
+            let relative_path = location
+                .path()
+                .strip_prefix(self.table_root.path())
+                .unwrap_or(location.path());
+            relative_path
+                .split('/')
+                .any(|name| (name.starts_with('_') || name.starts_with('.')) && !name.contains('='))
+        };
+        let fs_client = engine.get_file_system_client();
+        let mut candidates = vec![];
+        for maybe_meta in fs_client.list_from_recursive(&self.table_root)? {
+            let meta = maybe_meta?;
+            if meta.last_modified < cutoff_timestamp
+                && !is_hidden(&meta.location)
+                && !retained.contains(&meta.location)
+            {
+                candidates.push(meta);
+            }
+        }
+        Ok(candidates)
+    }
 }
 
-/// List relevant log files.
+/// A commit in the history of a table, returned by [`Snapshot::history`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HistoryEntry {
+    /// The version of the commit
+    pub version: Version,
+    /// The timestamp of the commit, in milliseconds since the Unix epoch
+    pub timestamp: i64,
+    /// The `commitInfo` action of the commit, or a default (empty) one if it has none
+    pub commit_info: CommitInfo,
+}
+
+/// List the files of the log at `log_root` that have a version, from `start_version` up to
+/// `end_version` (if any), in order of version. The listing is lazy and stops at the first file
+/// after `end_version`, so callers that only need the first few files don't list the whole log.
+/// This is what all the listings of (parts of) the log that don't reconstruct a log segment use.
+pub(crate) fn list_log_files_from<'a>(
+    fs_client: &'a dyn FileSystemClient,
+    log_root: &Url,
+    start_version: Version,
+    end_version: Option<Version>,
+) -> DeltaResult<impl Iterator<Item = DeltaResult<FileMeta>> + 'a> {
+    let start_from = log_root.join(&format!("{:020}", start_version))?;
+    let end_version = end_version.unwrap_or(Version::MAX);
+    let files = fs_client
+        .list_from(&start_from)?
+        .filter_ok(|meta| version_from_location(&meta.location).is_some())
+        .take_while(move |meta| match meta {
+            Ok(meta) => version_from_location(&meta.location) <= Some(end_version),
+            Err(_) => true,
+        });
+    Ok(files)
+}
+
+/// List the commit files of the log at `log_root` from `start_version` up to `end_version`, in
+/// order of version. A `start_version` of `None` lists from the earliest commit that wasn't
+/// removed by log cleanup. This fails if the commits aren't contiguous, or if the commits of
+/// `start_version` or `end_version` are missing.
+pub(crate) fn list_commit_files(
+    fs_client: &dyn FileSystemClient,
+    log_root: &Url,
+    start_version: Option<Version>,
+    end_version: Version,
+) -> DeltaResult<Vec<FileMeta>> {
+    let missing = |version: Version| {
+        Error::MissingData(format!(
+            "Commit file for version {version} not found in the log"
+        ))
+    };
+    let mut commit_files: Vec<FileMeta> = vec![];
+    let mut next_version = start_version;
+    for maybe_meta in list_log_files_from(
+        fs_client,
+        log_root,
+        start_version.unwrap_or(0),
+        Some(end_version),
+    )? {
+        let meta = maybe_meta?;
+        let log_path = LogPath::new(&meta.location);
+        if let (true, Some(version)) = (log_path.is_commit, log_path.version) {
+            // the listing is sorted, so the versions are contiguous iff each is the next one
+            if let Some(expected_version) = next_version {
+                require!(version == expected_version, missing(expected_version));
+            }
+            next_version = Some(version + 1);
+            commit_files.push(meta);
+        }
+    }
+    match next_version {
+        Some(next_version) if next_version > end_version => Ok(commit_files),
+        Some(next_version) => Err(missing(next_version)),
+        None => Err(missing(end_version)),
+    }
+}
+
+/// Find the latest version of the table whose log is at `log_root`, i.e. the highest version that
+/// has a commit file, without listing the whole log.
 ///
-/// Relevant files are the max checkpoint found and all subsequent commits.
-fn list_log_files(
+/// The version in `_last_checkpoint` (if any) is only used as a hint of where to start: from the
+/// first commit at or after it, this probes ever further ahead until no commit is found, and then
+/// bisects between the last commit found and the first version known to have none. Each probe is
+/// a single [`FileSystemClient::list_from`] that stops at its first commit file, so this takes
+/// O(log n) probes for a table with n commits after the hint, however stale the hint is.
+pub(crate) fn latest_version(
     fs_client: &dyn FileSystemClient,
     log_root: &Url,
-) -> DeltaResult<(Vec<FileMeta>, Vec<FileMeta>)> {
-    let version_prefix = format!("{:020}", 0);
-    let start_from = log_root.join(&version_prefix)?;
+) -> DeltaResult<Version> {
+    // the version of the first commit file at or after `version`, if there is one
+    let first_commit_from = |version: Version| -> DeltaResult<Option<Version>> {
+        for maybe_meta in list_log_files_from(fs_client, log_root, version, None)? {
+            let meta = maybe_meta?;
+            let log_path = LogPath::new(&meta.location);
+            if log_path.is_commit {
+                return Ok(log_path.version);
+            }
+        }
+        Ok(None)
+    };
 
-    let mut max_checkpoint_version = -1_i64;
-    let mut commit_files = Vec::new();
-    let mut checkpoint_files = Vec::with_capacity(10);
+    let hint = read_last_checkpoint(fs_client, log_root)?.map_or(0, |cp| cp.version);
+    let first_commit = match first_commit_from(hint)? {
+        Some(version) => Some(version),
+        // the hint is past the end of the log, so ignore it
+        None if hint > 0 => first_commit_from(0)?,
+        None => None,
+    };
+    // invariant: there is a commit of version `low`, and none of version `high` or later
+    let mut low = first_commit.ok_or(Error::MissingVersion)?;
+    let mut step = 1;
+    let mut high = loop {
+        match first_commit_from(low + step)? {
+            Some(version) => {
+                low = version;
+                step *= 2;
+            }
+            None => break low + step,
+        }
+    };
+    while high - low > 1 {
+        let mid = low + (high - low) / 2;
+        match first_commit_from(mid)? {
+            Some(version) => low = version,
+            None => high = mid,
+        }
+    }
+    Ok(low)
+}
 
-    for maybe_meta in fs_client.list_from(&start_from)? {
+/// Find the earliest version of the table with the log at `log_root` that a [`Snapshot`] can be
+/// created for. This is version 0 if its commit is still in the log, and otherwise the version of
+/// the earliest checkpoint, since log cleanup removes commits (and checkpoints) up to some version.
+/// Commits without a checkpoint or complete history before them can't be reconstructed, and
+/// neither can the version of a multi-part checkpoint that is missing parts.
+pub(crate) fn earliest_version(
+    fs_client: &dyn FileSystemClient,
+    log_root: &Url,
+) -> DeltaResult<Version> {
+    // the log is listed in order, so the first complete checkpoint is the earliest one. These are
+    // the files of the earliest checkpoint version that hasn't been checked yet
+    let mut checkpoint_files: Vec<FileMeta> = vec![];
+    for maybe_meta in list_log_files_from(fs_client, log_root, 0, None)? {
         let meta = maybe_meta?;
         let log_path = LogPath::new(&meta.location);
-        if log_path.is_checkpoint {
-            let version = log_path.version.unwrap_or(0) as i64;
-            match version.cmp(&max_checkpoint_version) {
-                Ordering::Greater => {
-                    max_checkpoint_version = version;
-                    checkpoint_files.clear();
-                    checkpoint_files.push(meta);
+        match log_path.version {
+            Some(0) if log_path.is_commit => return Ok(0),
+            Some(version) if log_path.is_checkpoint => {
+                let previous_version = checkpoint_files
+                    .first()
+                    .and_then(|f| version_from_location(&f.location));
+                if previous_version.is_some_and(|previous| previous != version) {
+                    let files = std::mem::take(&mut checkpoint_files);
+                    if let Some((version, _)) = latest_complete_checkpoint(files) {
+                        return Ok(version);
+                    }
                 }
-                Ordering::Equal => {
-                    checkpoint_files.push(meta);
-                }
-                _ => {}
+                checkpoint_files.push(meta);
             }
-        } else if log_path.is_commit {
-            commit_files.push(meta);
+            _ => {}
         }
     }
-
-    commit_files.retain(|f| {
-        version_from_location(&f.location).unwrap_or(0) as i64 > max_checkpoint_version
-    });
-    // NOTE this will sort in reverse order
-    commit_files.sort_unstable_by(|a, b| b.location.cmp(&a.location));
-
-    Ok((commit_files, checkpoint_files))
+    latest_complete_checkpoint(checkpoint_files)
+        .map(|(version, _)| version)
+        .ok_or(Error::MissingVersion)
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+/// Find the commit file of `version` in the log at `log_root`.
+fn find_commit_file(
+    fs_client: &dyn FileSystemClient,
+    log_root: &Url,
+    version: Version,
+) -> DeltaResult<FileMeta> {
+    let mut commit_files = list_commit_files(fs_client, log_root, Some(version), version)?;
+    // the listing fails unless it found exactly the commit of `version`
+    Ok(commit_files.remove(0))
+}
 
-    use std::path::PathBuf;
-    use std::sync::Arc;
+/// Find the index of the first of `commit_files` whose commit timestamp is after
+/// `timestamp_millis`, or the number of commit files if there is none. `commit_files` must be the
+/// commit files of contiguous versions in order of version, and `in_commit_timestamps_from` the
+/// version in-commit timestamps were enabled at, if they are (see [`read_commit_timestamp`]).
+///
+/// In-commit timestamps increase with the version, so the commits that have them are binary
+/// searched. The modification times of commit files that older commits are timestamped with need
+/// not increase, e.g. when the files were copied, so those commits are scanned in order instead:
+/// like Delta writers do, a commit whose file timestamp isn't after the timestamp of the commit
+/// before it is treated as just after it.
+pub(crate) fn first_commit_after(
+    engine: &dyn Engine,
+    commit_files: &[FileMeta],
+    in_commit_timestamps_from: Option<Version>,
+    timestamp_millis: i64,
+) -> DeltaResult<usize> {
+    let timestamp = |index: usize| {
+        read_commit_timestamp(engine, &commit_files[index], in_commit_timestamps_from)
+    };
+    let first_in_commit_timestamp = match in_commit_timestamps_from {
+        Some(from) => {
+            commit_files.partition_point(|file| version_from_location(&file.location) < Some(from))
+        }
+        None => commit_files.len(),
+    };
 
-    use object_store::local::LocalFileSystem;
-    use object_store::path::Path;
+    let mut previous_timestamp = None;
+    for index in 0..first_in_commit_timestamp {
+        let timestamp = match previous_timestamp {
+            Some(previous) => timestamp(index)?.max(previous + 1),
+            None => timestamp(index)?,
+        };
+        if timestamp > timestamp_millis {
+            return Ok(index);
+        }
+        previous_timestamp = Some(timestamp);
+    }
+
+    let (mut low, mut high) = (first_in_commit_timestamp, commit_files.len());
+    while low < high {
+        let mid = low + (high - low) / 2;
+        if timestamp(mid)? <= timestamp_millis {
+            low = mid + 1;
+        } else {
+            high = mid;
+        }
+    }
+    Ok(low)
+}
+
+/// Resolve the timestamp of the commit in `commit_file`, in milliseconds since the Unix epoch.
+/// Commits at or after `in_commit_timestamps_from` (the version in-commit timestamps were enabled
+/// at, if they are) must record an authoritative `inCommitTimestamp` in their `commitInfo` action;
+/// older commits fall back to the modification time of their commit file, as resolved by the
+/// clock of `engine`.
+pub(crate) fn read_commit_timestamp(
+    engine: &dyn Engine,
+    commit_file: &FileMeta,
+    in_commit_timestamps_from: Option<Version>,
+) -> DeltaResult<i64> {
+    let version = version_from_location(&commit_file.location).ok_or(Error::MissingVersion)?;
+    if in_commit_timestamps_from.is_none_or(|from| version < from) {
+        return Ok(engine.get_clock().commit_timestamp(version, commit_file));
+    }
+    in_commit_timestamp(version, read_commit_info(engine, commit_file)?.as_ref())
+}
+
+/// Read the `commitInfo` action of the commit in `commit_file`, if it has one
+fn read_commit_info(
+    engine: &dyn Engine,
+    commit_file: &FileMeta,
+) -> DeltaResult<Option<CommitInfo>> {
+    let schema = get_log_schema_for(&[ActionType::CommitInfo])?;
+    let batches = engine.get_json_handler().read_json_files(
+        std::slice::from_ref(commit_file),
+        schema,
+        None,
+    )?;
+    for batch in batches {
+        if let Some(commit_info) = CommitInfo::try_new_from_data(batch?.as_ref())? {
+            return Ok(Some(commit_info));
+        }
+    }
+    Ok(None)
+}
+
+/// The in-commit timestamp of the commit of `version` with the `commitInfo` action `commit_info`,
+/// for a commit that must have one
+fn in_commit_timestamp(version: Version, commit_info: Option<&CommitInfo>) -> DeltaResult<i64> {
+    let commit_info = commit_info.ok_or_else(|| {
+        Error::MissingData(format!(
+            "Commit {version} has no commitInfo action, but in-commit timestamps are enabled"
+        ))
+    })?;
+    commit_info.in_commit_timestamp.ok_or_else(|| {
+        Error::MissingData(format!(
+            "Commit {version} has no inCommitTimestamp, but in-commit timestamps are enabled"
+        ))
+    })
+}
+
+/// The parsed log of a table at a version that a [`LogSegmentCache`] keeps: the files of its log
+/// segment, and its protocol and metadata
+#[derive(Debug)]
+struct CachedLogSegment {
+    log_segment: LogSegment,
+    metadata: Metadata,
+    protocol: Protocol,
+}
+
+impl CachedLogSegment {
+    fn to_snapshot(&self, table_root: Url, version: Version) -> DeltaResult<Snapshot> {
+        Snapshot::try_new_from_parts(
+            table_root,
+            self.log_segment.clone(),
+            version,
+            self.metadata.clone(),
+            self.protocol.clone(),
+        )
+    }
+}
+
+type LogSegmentEntries = IndexMap<(Url, Version), Arc<CachedLogSegment>>;
+
+/// A cache of the parsed logs of tables by table root and version, for applications that open
+/// many snapshots of the same versions of tables (e.g. interactive query engines). Snapshots
+/// created with [`Snapshot::try_new_with_log_cache`] (or [`crate::Table::snapshot_with_log_cache`])
+/// reuse the log segment, protocol and metadata of a version that is in the cache instead of
+/// listing and reading the log again.
+///
+/// The cache is passed in explicitly by the caller, and keeps any number of versions of each
+/// table, up to `capacity` versions in total. Once full, the
+/// least recently used version is evicted. Only the parsed log is kept, never an [`Engine`], so a
+/// cache can be shared by snapshots created with different engines.
+///
+/// The cache can be shared between threads. If several threads create a snapshot of the same
+/// version of a table that isn't cached yet, only one of them lists and reads the log, while the
+/// others wait for it to be cached. If that fails, the others try again.
+#[derive(Debug)]
+pub struct LogSegmentCache {
+    capacity: usize,
+    /// The cached versions, from least to most recently used
+    entries: Mutex<LogSegmentEntries>,
+    /// The versions that are being loaded, each with the loaded log once it is loaded, or `None`
+    /// if loading it failed
+    loading: Mutex<HashMap<(Url, Version), LoadingLogSegment>>,
+}
+
+type LoadingLogSegment = Arc<OnceLock<Option<Arc<CachedLogSegment>>>>;
+
+impl LogSegmentCache {
+    /// Create a new, empty [`LogSegmentCache`] that keeps up to `capacity` versions
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: Mutex::new(IndexMap::with_capacity(capacity)),
+            loading: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// The number of versions in the cache
+    pub fn len(&self) -> DeltaResult<usize> {
+        Ok(self.lock_entries()?.len())
+    }
+
+    /// Whether the cache is empty
+    pub fn is_empty(&self) -> DeltaResult<bool> {
+        Ok(self.len()? == 0)
+    }
+
+    /// Whether the cache has the log of the table at `table_root` at `version`. This doesn't
+    /// count as a use of it.
+    pub fn contains(&self, table_root: &Url, version: Version) -> DeltaResult<bool> {
+        let key = (table_root.clone(), version);
+        Ok(self.lock_entries()?.contains_key(&key))
+    }
+
+    /// Remove all cached versions
+    pub fn clear(&self) -> DeltaResult<()> {
+        self.lock_entries()?.clear();
+        Ok(())
+    }
+
+    /// Get the cached log of the table at `table_root` at `version`, marking it as the most
+    /// recently used
+    fn get(
+        &self,
+        table_root: &Url,
+        version: Version,
+    ) -> DeltaResult<Option<Arc<CachedLogSegment>>> {
+        let mut entries = self.lock_entries()?;
+        let key = (table_root.clone(), version);
+        let Some(index) = entries.get_index_of(&key) else {
+            return Ok(None);
+        };
+        let last = entries.len() - 1;
+        entries.move_index(index, last);
+        Ok(entries.get(&key).cloned())
+    }
+
+    /// Get the cached log of the table at `table_root` at `version` like [`Self::get`], or load it
+    /// with `load` and cache it. Concurrent calls for a version that isn't cached share a single
+    /// `load`: the first of them loads it, and the others wait for its result. If that load fails,
+    /// the others try again the same way, so that each call either gets the log or fails itself.
+    fn get_or_load(
+        &self,
+        table_root: &Url,
+        version: Version,
+        load: impl FnOnce() -> DeltaResult<CachedLogSegment>,
+    ) -> DeltaResult<Arc<CachedLogSegment>> {
+        if let Some(cached) = self.get(table_root, version)? {
+            debug!("Using cached log segment for {table_root} at version {version}");
+            return Ok(cached);
+        }
+        let key = (table_root.clone(), version);
+        let loading = self.lock_loading()?.entry(key.clone()).or_default().clone();
+        let mut load = Some(load);
+        let mut load_error = None;
+        let loaded = loading.get_or_init(|| {
+            let load = load.take()?;
+            // the log may have been cached since this missed the cache
+            if let Ok(Some(cached)) = self.get(table_root, version) {
+                return Some(cached);
+            }
+            match load() {
+                Ok(log) => {
+                    let log = Arc::new(log);
+                    self.insert(key.0.clone(), version, log.clone()).ok()?;
+                    Some(log)
+                }
+                Err(err) => {
+                    load_error = Some(err);
+                    None
+                }
+            }
+        });
+        let loaded = loaded.clone();
+        // later calls either find the log cached, or load it again after a failure
+        let mut loading_versions = self.lock_loading()?;
+        if loading_versions
+            .get(&key)
+            .is_some_and(|entry| Arc::ptr_eq(entry, &loading))
+        {
+            loading_versions.remove(&key);
+        }
+        drop(loading_versions);
+        match (loaded, load_error, load) {
+            (Some(cached), _, _) => Ok(cached),
+            (None, Some(err), _) => Err(err),
+            // another call failed to load the log, so try again
+            (None, None, Some(load)) => self.get_or_load(table_root, version, load),
+            (None, None, None) => Err(Error::generic("Failed to cache the log segment")),
+        }
+    }
+
+    /// Cache the log of the table at `table_root` at `version`, evicting the least recently used
+    /// versions if the cache is full
+    fn insert(
+        &self,
+        table_root: Url,
+        version: Version,
+        log: Arc<CachedLogSegment>,
+    ) -> DeltaResult<()> {
+        if self.capacity == 0 {
+            return Ok(());
+        }
+        let mut entries = self.lock_entries()?;
+        let key = (table_root, version);
+        entries.shift_remove(&key);
+        while entries.len() >= self.capacity {
+            entries.shift_remove_index(0);
+        }
+        entries.insert(key, log);
+        Ok(())
+    }
+
+    fn lock_entries(&self) -> DeltaResult<std::sync::MutexGuard<'_, LogSegmentEntries>> {
+        self.entries
+            .lock()
+            .map_err(|_| Error::generic("Log segment cache lock poisoned"))
+    }
+
+    fn lock_loading(
+        &self,
+    ) -> DeltaResult<std::sync::MutexGuard<'_, HashMap<(Url, Version), LoadingLogSegment>>> {
+        self.loading
+            .lock()
+            .map_err(|_| Error::generic("Log segment cache lock poisoned"))
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+#[cfg_attr(feature = "developer-visibility", visibility::make(pub))]
+#[cfg_attr(not(feature = "developer-visibility"), visibility::make(pub(crate)))]
+struct CheckpointMetadata {
+    /// The version of the table when the last checkpoint was made.
+    #[allow(unreachable_pub)] // used by acceptance tests (TODO make an fn accessor?)
+    pub version: Version,
+    /// The number of actions that are stored in the checkpoint.
+    pub(crate) size: i32,
+    /// The number of fragments if the last checkpoint was written in multiple parts.
+    pub(crate) parts: Option<i32>,
+    /// The number of bytes of the checkpoint.
+    pub(crate) size_in_bytes: Option<i32>,
+    /// The number of AddFile actions in the checkpoint.
+    pub(crate) num_of_add_files: Option<i32>,
+    /// The schema of the checkpoint file.
+    pub(crate) checkpoint_schema: Option<Schema>,
+    /// The checksum of the last checkpoint JSON.
+    pub(crate) checksum: Option<String>,
+}
+
+/// Try reading the `_last_checkpoint` file.
+///
+/// In case the file is not found or is corrupt, `None` is returned. It's only a hint, so the log
+/// can still be listed to find the last checkpoint.
+fn read_last_checkpoint(
+    fs_client: &dyn FileSystemClient,
+    log_root: &Url,
+) -> DeltaResult<Option<CheckpointMetadata>> {
+    let file_path = LogPath::new(log_root).child(LAST_CHECKPOINT_FILE_NAME)?;
+    match fs_client
+        .read_files(vec![(file_path, None)])
+        .and_then(|mut data| data.next().expect("read_files should return one file"))
+    {
+        Ok(data) => match serde_json::from_slice(&data) {
+            Ok(cp) => Ok(Some(cp)),
+            Err(err) => {
+                warn!("Ignoring corrupt {LAST_CHECKPOINT_FILE_NAME} file: {err}");
+                Ok(None)
+            }
+        },
+        Err(Error::FileNotFound(_)) => Ok(None),
+        Err(err) => Err(err),
+    }
+}
+
+/// List all log files after a given checkpoint. If the checkpoint is incomplete, this falls back
+/// to [`list_log_files`] up to `end_version`.
+fn list_log_files_with_checkpoint(
+    cp: &CheckpointMetadata,
+    fs_client: &dyn FileSystemClient,
+    log_root: &Url,
+    end_version: Option<Version>,
+) -> DeltaResult<(Vec<FileMeta>, Vec<FileMeta>)> {
+    let version_prefix = format!("{:020}", cp.version);
+    let start_from = log_root.join(&version_prefix)?;
+
+    let files = fs_client
+        .list_from(&start_from)?
+        .collect::<Result<Vec<_>, Error>>()?
+        .into_iter()
+        // TODO this filters out .crc files etc which start with "." - how do we want to use these kind of files?
+        .filter(|f| version_from_location(&f.location).is_some())
+        .collect::<Vec<_>>();
+
+    let mut commit_files = files
+        .iter()
+        .filter_map(|f| {
+            if LogPath::new(&f.location).is_commit {
+                Some(f.clone())
+            } else {
+                None
+            }
+        })
+        .collect_vec();
+    // NOTE this will sort in reverse order. Versions that aren't padded to the standard width
+    // don't sort by their names, and may not be ordered relative to `start_from` in the listing.
+    // The commit of the checkpoint's version is already part of the checkpoint.
+    commit_files.retain(|f| version_from_location(&f.location) > Some(cp.version));
+    commit_files.sort_unstable_by_key(|f| Reverse(version_from_location(&f.location)));
+
+    // the listing also has the files of any later checkpoints
+    let checkpoint_files = files
+        .iter()
+        .filter(|f| {
+            let log_path = LogPath::new(&f.location);
+            log_path.is_checkpoint && log_path.version == Some(cp.version)
+        })
+        .cloned()
+        .collect_vec();
+
+    match latest_complete_checkpoint(checkpoint_files) {
+        Some((_, checkpoint_files)) if cp.parts.unwrap_or(1) as usize == checkpoint_files.len() => {
+            Ok((commit_files, checkpoint_files))
+        }
+        _ => {
+            // parts of the checkpoint are missing, e.g. they were cleaned up or the checkpoint is
+            // still being written. Replay from the latest complete checkpoint instead
+            warn!(
+                "The checkpoint at version {} in _last_checkpoint is incomplete, listing the log",
+                cp.version
+            );
+            let (commit_files, checkpoint_files) =
+                list_log_files(fs_client, log_root, end_version)?;
+            // the commits between the previous checkpoint and this one may have been cleaned up
+            let checkpoint_version = checkpoint_files
+                .first()
+                .and_then(|f| version_from_location(&f.location));
+            ensure_contiguous_commits(&commit_files, checkpoint_version)?;
+            Ok((commit_files, checkpoint_files))
+        }
+    }
+}
+
+/// Check that `commit_files`, sorted in reverse order of version, are the commits of all the
+/// versions after `checkpoint_version`, or from version 0 on if there is no checkpoint
+fn ensure_contiguous_commits(
+    commit_files: &[FileMeta],
+    checkpoint_version: Option<Version>,
+) -> DeltaResult<()> {
+    let mut next_version = checkpoint_version.map_or(0, |version| version + 1);
+    for version in commit_files
+        .iter()
+        .rev()
+        .filter_map(|f| version_from_location(&f.location))
+    {
+        require!(
+            version == next_version,
+            Error::MissingData(format!(
+                "Commit file for version {next_version} not found in the log"
+            ))
+        );
+        next_version = version + 1;
+    }
+    Ok(())
+}
+
+/// Pick the latest complete checkpoint out of `checkpoint_files`, returning its version and its
+/// files. A checkpoint is either a single file, or a multi-part checkpoint that is only complete
+/// if all of its parts are there, in which case they are returned in order. Returns `None` if
+/// there is no complete checkpoint.
+fn latest_complete_checkpoint(checkpoint_files: Vec<FileMeta>) -> Option<(Version, Vec<FileMeta>)> {
+    let mut checkpoints: BTreeMap<Version, Vec<FileMeta>> = BTreeMap::new();
+    for file in checkpoint_files {
+        if let Some(version) = version_from_location(&file.location) {
+            checkpoints.entry(version).or_default().push(file);
+        }
+    }
+    checkpoints.into_iter().rev().find_map(|(version, files)| {
+        // the parts of each number of parts, in case the checkpoint was written more than once
+        let mut parts: BTreeMap<Version, BTreeMap<Version, FileMeta>> = BTreeMap::new();
+        for file in files {
+            match LogPath::new(&file.location).checkpoint_part {
+                Some((part, num_parts)) => {
+                    parts.entry(num_parts).or_default().insert(part, file);
+                }
+                // a single-file checkpoint is complete
+                None => return Some((version, vec![file])),
+            }
+        }
+        parts
+            .into_iter()
+            .find(|(num_parts, parts)| parts.len() as Version == *num_parts)
+            .map(|(_, parts)| (version, parts.into_values().collect()))
+    })
+}
+
+/// List relevant log files.
+///
+/// Relevant files are the latest complete checkpoint found and all subsequent commits, ignoring
+/// any files after `end_version`.
+fn list_log_files(
+    fs_client: &dyn FileSystemClient,
+    log_root: &Url,
+    end_version: Option<Version>,
+) -> DeltaResult<(Vec<FileMeta>, Vec<FileMeta>)> {
+    let version_prefix = format!("{:020}", 0);
+    let start_from = log_root.join(&version_prefix)?;
+
+    let mut commit_files = Vec::new();
+    let mut checkpoint_files = Vec::new();
+
+    for maybe_meta in fs_client.list_from(&start_from)? {
+        let meta = maybe_meta?;
+        let log_path = LogPath::new(&meta.location);
+        if log_path.version.unwrap_or(0) > end_version.unwrap_or(Version::MAX) {
+            continue;
+        }
+        if log_path.is_checkpoint {
+            checkpoint_files.push(meta);
+        } else if log_path.is_commit {
+            commit_files.push(meta);
+        }
+    }
+
+    let (checkpoint_version, checkpoint_files) = match latest_complete_checkpoint(checkpoint_files)
+    {
+        Some((version, files)) => (Some(version), files),
+        None => (None, Vec::new()),
+    };
+    commit_files.retain(|f| version_from_location(&f.location) > checkpoint_version);
+    // NOTE this will sort in reverse order, by version since versions that aren't padded to the
+    // standard width don't sort by their names
+    commit_files.sort_unstable_by_key(|f| Reverse(version_from_location(&f.location)));
+
+    Ok((commit_files, checkpoint_files))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::path::PathBuf;
+    use std::sync::Arc;
+
+    use arrow_schema::{DataType as ArrowDataType, Schema as ArrowSchema, TimeUnit};
+    use object_store::local::LocalFileSystem;
+    use object_store::path::Path;
 
     use crate::engine::default::executor::tokio::TokioBackgroundExecutor;
     use crate::engine::default::filesystem::ObjectStoreFileSystemClient;
+    use crate::engine::default::DefaultEngine;
+    use crate::engine::in_memory::InMemoryEngine;
     use crate::engine::sync::SyncEngine;
+    use crate::scan::ScanBuilder;
     use crate::schema::StructType;
+    use crate::test_utils::{
+        add_action, metadata_action, protocol_action, value_schema, TestTable,
+    };
 
     #[test]
     fn test_snapshot_read_metadata() {
@@ -434,6 +1629,105 @@ mod tests {
         let schema_string = r#"{"type":"struct","fields":[{"name":"value","type":"integer","nullable":true,"metadata":{}}]}"#;
         let expected: StructType = serde_json::from_str(schema_string).unwrap();
         assert_eq!(snapshot.schema(), &expected);
+        assert_eq!(snapshot.column_mapping(), None);
+        assert_eq!((snapshot.name(), snapshot.description()), (None, None));
+    }
+
+    #[test]
+    fn test_snapshot_name_and_description() {
+        let mut metadata = metadata_action(&StructType::new(vec![]), &[], &[]);
+        metadata["metaData"]["name"] = "sales".into();
+        metadata["metaData"]["description"] = "Daily sales".into();
+        let table = TestTable::new();
+        table.commit(0, [protocol_action(1, 2), metadata]);
+        let snapshot = Snapshot::try_new(table.location(), &SyncEngine::new(), None).unwrap();
+        assert_eq!(snapshot.name(), Some("sales"));
+        assert_eq!(snapshot.description(), Some("Daily sales"));
+    }
+
+    #[test]
+    fn test_snapshot_generated_columns() {
+        let generated = |name: &str, data_type: &str, expr: &str| {
+            serde_json::json!({
+                "name": name,
+                "type": data_type,
+                "nullable": true,
+                "metadata": {"delta.generationExpression": expr}
+            })
+        };
+        let schema = serde_json::json!({
+            "type": "struct",
+            "fields": [
+                {"name": "price", "type": "double", "nullable": true, "metadata": {}},
+                {"name": "quantity", "type": "integer", "nullable": true, "metadata": {}},
+                generated("total", "double", "price * quantity"),
+                generated("in_stock", "boolean", "coalesce(quantity, 0) > 0"),
+                generated("day", "date", "CAST(ts AS DATE)"),
+                generated("half", "double", "quantity / 2"),
+            ]
+        });
+        let table = TestTable::new();
+        table.commit(
+            0,
+            [protocol_action(1, 4), metadata_action(&schema, &[], &[])],
+        );
+        let snapshot = Snapshot::try_new(table.location(), &SyncEngine::new(), None).unwrap();
+
+        let quantity = || Expression::column("quantity");
+        let expected = HashMap::from([
+            (
+                "total".to_string(),
+                GenerationExpression::Parsed(Expression::column("price") * quantity()),
+            ),
+            (
+                "in_stock".to_string(),
+                GenerationExpression::Parsed(
+                    quantity()
+                        .if_null(Expression::literal(0))
+                        .gt(Expression::literal(0)),
+                ),
+            ),
+            (
+                "day".to_string(),
+                GenerationExpression::Unparsed("CAST(ts AS DATE)".to_string()),
+            ),
+            (
+                "half".to_string(),
+                GenerationExpression::Unparsed("quantity / 2".to_string()),
+            ),
+        ]);
+        assert_eq!(snapshot.generated_columns(), expected);
+    }
+
+    #[test]
+    fn test_snapshot_unsupported_protocol() {
+        let protocol = r#"{"protocol":{"minReaderVersion":3,"minWriterVersion":7,"readerFeatures":["deletionVectors","someFutureFeature"],"writerFeatures":["deletionVectors","someFutureFeature","appendOnly"]}}"#;
+        let table = TestTable::new();
+        table.commit(
+            0,
+            [
+                protocol.to_string(),
+                metadata_action(&StructType::new(vec![]), &[], &[]).to_string(),
+            ],
+        );
+        match Snapshot::try_new(table.location(), &SyncEngine::new(), None) {
+            Err(Error::UnsupportedProtocol {
+                min_reader_version: 3,
+                min_writer_version: 7,
+                required_reader_features,
+                required_writer_features,
+            }) => {
+                assert_eq!(
+                    required_reader_features,
+                    ["deletionVectors", "someFutureFeature"]
+                );
+                assert_eq!(
+                    required_writer_features,
+                    ["deletionVectors", "someFutureFeature", "appendOnly"]
+                );
+            }
+            result => panic!("Expected an unsupported protocol error, got {result:?}"),
+        }
     }
 
     #[test]
@@ -476,4 +1770,1113 @@ mod tests {
             Some(3)
         );
     }
+
+    /// The paths of the files a scan of `snapshot` reads, sorted
+    fn scan_file_paths(snapshot: &Snapshot, engine: &dyn Engine) -> Vec<String> {
+        let scan = ScanBuilder::new(snapshot.clone()).build().unwrap();
+        let mut paths: Vec<_> = scan
+            .files(engine)
+            .unwrap()
+            .map_ok(|add| add.path)
+            .try_collect()
+            .unwrap();
+        paths.sort();
+        paths
+    }
+
+    /// Copy the log of the table at `source` (including its subdirectories, like `_sidecars`) to a
+    /// temporary directory
+    fn copy_table_log(source: &str) -> tempfile::TempDir {
+        fn copy_dir(source: &std::path::Path, dest: &std::path::Path) {
+            std::fs::create_dir(dest).unwrap();
+            for entry in std::fs::read_dir(source).unwrap() {
+                let entry = entry.unwrap();
+                if entry.file_type().unwrap().is_dir() {
+                    copy_dir(&entry.path(), &dest.join(entry.file_name()));
+                } else {
+                    std::fs::copy(entry.path(), dest.join(entry.file_name())).unwrap();
+                }
+            }
+        }
+        let tmp = tempfile::tempdir().unwrap();
+        copy_dir(
+            &PathBuf::from(source).join("_delta_log"),
+            &tmp.path().join("_delta_log"),
+        );
+        tmp
+    }
+
+    #[test]
+    fn test_read_table_with_multi_part_checkpoint() {
+        let source = "./tests/data/with_checkpoint_no_last_checkpoint/";
+        let engine = SyncEngine::new();
+        let expected = Snapshot::try_new(
+            url::Url::from_directory_path(std::fs::canonicalize(source).unwrap()).unwrap(),
+            &engine,
+            None,
+        )
+        .unwrap();
+        let expected_files = scan_file_paths(&expected, &engine);
+        assert!(!expected_files.is_empty());
+
+        // the parts of the checkpoint at version 2 and the commits from version 2 on
+        let tmp = copy_table_log("./tests/data/multi-part-checkpoint/");
+        let log_dir = tmp.path().join("_delta_log");
+        let location = url::Url::from_directory_path(tmp.path()).unwrap();
+        let assert_snapshot = |checkpoint_parts: usize| {
+            let snapshot = Snapshot::try_new(location.clone(), &engine, None).unwrap();
+            assert_eq!(snapshot.version(), 3);
+            assert_eq!(snapshot.metadata(), expected.metadata());
+            assert_eq!(snapshot.protocol(), expected.protocol());
+            assert_eq!(scan_file_paths(&snapshot, &engine), expected_files);
+            let parts: Vec<_> = snapshot
+                .log_segment
+                .checkpoint_files
+                .iter()
+                .map(|file| LogPath::new(&file.location).checkpoint_part)
+                .collect();
+            let expected_parts: Vec<_> = (1..=checkpoint_parts as Version)
+                .map(|part| Some((part, checkpoint_parts as Version)))
+                .collect();
+            assert_eq!(parts, expected_parts);
+        };
+        assert_snapshot(2);
+
+        // the parts are also grouped when the log is listed to find the checkpoint
+        std::fs::remove_file(log_dir.join(LAST_CHECKPOINT_FILE_NAME)).unwrap();
+        assert_snapshot(2);
+
+        // with a part missing the commits are replayed instead of the partial checkpoint
+        for commit in ["00000000000000000000.json", "00000000000000000001.json"] {
+            let source_log = PathBuf::from(source).join("_delta_log");
+            std::fs::copy(source_log.join(commit), log_dir.join(commit)).unwrap();
+        }
+        std::fs::remove_file(
+            log_dir.join("00000000000000000002.checkpoint.0000000002.0000000002.parquet"),
+        )
+        .unwrap();
+        assert_snapshot(0);
+        std::fs::write(
+            log_dir.join(LAST_CHECKPOINT_FILE_NAME),
+            r#"{"version":2,"size":4,"parts":2}"#,
+        )
+        .unwrap();
+        assert_snapshot(0);
+
+        // without a complete checkpoint to fall back to, the replay needs every commit
+        std::fs::remove_file(log_dir.join("00000000000000000001.json")).unwrap();
+        assert!(matches!(
+            Snapshot::try_new(location.clone(), &engine, None),
+            Err(Error::MissingData(_))
+        ));
+    }
+
+    /// Write an empty file at `path`, last modified on 2000-01-01
+    fn write_old_file(path: &std::path::Path) {
+        let file = std::fs::File::create(path).unwrap();
+        let modified = std::time::UNIX_EPOCH + std::time::Duration::from_millis(946_684_800_000);
+        file.set_modified(modified).unwrap();
+    }
+
+    /// The number of milliseconds from `timestamp` until now
+    fn millis_since(timestamp: i64) -> i64 {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap();
+        now.as_millis() as i64 - timestamp
+    }
+
+    #[test]
+    fn test_vacuum_candidates() {
+        let tmp = copy_table_log("./tests/data/with_checkpoint_no_last_checkpoint/");
+        let active = "part-00000-70b1dcdf-0236-4f63-a072-124cdbafd8a0-c000.snappy.parquet";
+        // both removed in January 2023
+        let removed = [
+            "part-00000-ad1a4bb7-07e8-4f40-b50b-49910d209e0c-c000.snappy.parquet",
+            "part-00000-a190be9e-e3df-439e-b366-06a863f51e99-c000.snappy.parquet",
+        ];
+        let unreferenced = "part-00000-00000000-0000-0000-0000-000000000000-c000.snappy.parquet";
+        // e.g. written by a commit in progress
+        let pending = "part-00000-11111111-1111-1111-1111-111111111111-c000.snappy.parquet";
+        std::fs::create_dir_all(tmp.path().join("_hidden")).unwrap();
+        std::fs::create_dir_all(tmp.path().join("_part=1")).unwrap();
+        for file in [active, removed[0], removed[1], unreferenced, "_hidden/file"] {
+            write_old_file(&tmp.path().join(file));
+        }
+        write_old_file(&tmp.path().join("_part=1").join(unreferenced));
+        std::fs::write(tmp.path().join(pending), "").unwrap();
+
+        let location = url::Url::from_directory_path(tmp.path()).unwrap();
+        let engine = SyncEngine::new();
+        let snapshot = Snapshot::try_new(location.clone(), &engine, None).unwrap();
+        let candidates = |retention_millis| {
+            let mut paths: Vec<_> = snapshot
+                .vacuum_candidates(&engine, retention_millis)
+                .unwrap()
+                .into_iter()
+                .map(|meta| meta.location)
+                .collect();
+            paths.sort();
+            paths
+        };
+        let url = |path: &str| location.join(path).unwrap();
+
+        let day_millis = 24 * 60 * 60 * 1000;
+        let mut expected = vec![
+            url(unreferenced),
+            url(&format!("_part=1/{unreferenced}")),
+            url(removed[0]),
+            url(removed[1]),
+        ];
+        expected.sort();
+        assert_eq!(candidates(day_millis), expected);
+
+        // with a retention period back to 2010 the tombstones haven't expired yet, but the files
+        // modified in 2000 have
+        let mut expected = vec![url(unreferenced), url(&format!("_part=1/{unreferenced}"))];
+        expected.sort();
+        assert_eq!(candidates(millis_since(1_262_304_000_000)), expected);
+
+        // with a retention period back to before 2000 nothing has expired
+        assert_eq!(candidates(millis_since(0)), Vec::<Url>::new());
+        assert_eq!(candidates(i64::MAX), Vec::<Url>::new());
+    }
+
+    #[test]
+    fn test_vacuum_candidates_with_replaced_deletion_vector() {
+        let table = TestTable::new();
+        let dv = |name: &str| {
+            let path = Url::from_file_path(table.path().join(name)).unwrap();
+            serde_json::json!({
+                "storageType": "p",
+                "pathOrInlineDv": path.to_string(),
+                "offset": 1,
+                "sizeInBytes": 36,
+                "cardinality": 1
+            })
+        };
+        let mut add = add_action("part-0.parquet", 10);
+        add["add"]["deletionVector"] = dv("dv-0.bin");
+        table.commit(
+            0,
+            [
+                protocol_action(1, 2),
+                metadata_action(&value_schema(), &[], &[]),
+                add,
+            ],
+        );
+        // the deletion vector of the file is replaced, so the file is removed and added again
+        let deletion_timestamp = millis_since(0) - 60 * 60 * 1000;
+        let remove = serde_json::json!({"remove": {
+            "path": "part-0.parquet",
+            "deletionTimestamp": deletion_timestamp,
+            "dataChange": true,
+            "deletionVector": dv("dv-0.bin")
+        }});
+        let mut add = add_action("part-0.parquet", 10);
+        add["add"]["deletionVector"] = dv("dv-1.bin");
+        table.commit(1, [remove, add]);
+        for file in ["part-0.parquet", "dv-0.bin", "dv-1.bin"] {
+            write_old_file(&table.path().join(file));
+        }
+
+        let engine = SyncEngine::new();
+        let snapshot = Snapshot::try_new(table.location(), &engine, None).unwrap();
+        let candidates = |retention_millis| {
+            snapshot
+                .vacuum_candidates(&engine, retention_millis)
+                .unwrap()
+                .into_iter()
+                .map(|meta| meta.location)
+                .collect_vec()
+        };
+        // the old deletion vector is retained until its tombstone expires, although the data file
+        // is still active
+        assert_eq!(candidates(24 * 60 * 60 * 1000), Vec::<Url>::new());
+        assert_eq!(
+            candidates(60 * 1000),
+            [Url::from_file_path(table.path().join("dv-0.bin")).unwrap()]
+        );
+    }
+
+    #[test]
+    fn test_read_table_with_v2_checkpoint() {
+        // a JSON V2 checkpoint at version 2, whose file actions are in a parquet sidecar
+        let tmp = copy_table_log("./tests/data/v2-checkpoint-with-sidecars/");
+        let location = url::Url::from_directory_path(tmp.path()).unwrap();
+        let engine = SyncEngine::new();
+        let assert_snapshots = || {
+            for (version, expected_files) in [
+                (
+                    Some(2),
+                    ["part-00000-a190be9e-e3df-439e-b366-06a863f51e99-c000.snappy.parquet"],
+                ),
+                (
+                    None,
+                    ["part-00000-70b1dcdf-0236-4f63-a072-124cdbafd8a0-c000.snappy.parquet"],
+                ),
+            ] {
+                let snapshot = Snapshot::try_new(location.clone(), &engine, version).unwrap();
+                assert_eq!(snapshot.log_segment.checkpoint_files.len(), 1);
+                assert_eq!(
+                    snapshot.protocol().reader_features,
+                    Some(vec!["v2Checkpoint".to_string()])
+                );
+                assert_eq!(
+                    snapshot
+                        .metadata()
+                        .configuration
+                        .get("delta.checkpointPolicy"),
+                    Some(&"v2".to_string())
+                );
+                assert_eq!(scan_file_paths(&snapshot, &engine), expected_files);
+            }
+        };
+        assert_snapshots();
+        // the checkpoint is also found by listing the log
+        std::fs::remove_file(
+            tmp.path()
+                .join("_delta_log")
+                .join(LAST_CHECKPOINT_FILE_NAME),
+        )
+        .unwrap();
+        assert_snapshots();
+    }
+
+    #[test]
+    fn test_v2_checkpoint_with_repeated_sidecars() {
+        let checkpoint_name =
+            "00000000000000000002.checkpoint.80a083e8-7026-4e79-81be-64bd76c43a11.json";
+        for (sidecar_paths, expected_error) in [
+            // a sidecar that is the checkpoint itself
+            (
+                vec![format!("../{checkpoint_name}")],
+                "is referenced more than once",
+            ),
+            // the same sidecar twice
+            (
+                vec![
+                    "3a0d65cd-4056-49b8-937b-95f9e3ee90e5.parquet".to_string(),
+                    "3a0d65cd-4056-49b8-937b-95f9e3ee90e5.parquet".to_string(),
+                ],
+                "is referenced more than once",
+            ),
+            // more sidecars than a checkpoint can have, which aren't read at all
+            (
+                (0..=MAX_CHECKPOINT_SIDECARS)
+                    .map(|i| format!("{i}.parquet"))
+                    .collect(),
+                "The checkpoint has more than 10000 sidecars",
+            ),
+        ] {
+            let tmp = copy_table_log("./tests/data/v2-checkpoint-with-sidecars/");
+            let checkpoint_path = tmp.path().join("_delta_log").join(checkpoint_name);
+            let mut checkpoint: Vec<_> = std::fs::read_to_string(&checkpoint_path)
+                .unwrap()
+                .lines()
+                .filter(|line| !line.starts_with(r#"{"sidecar""#))
+                .map(String::from)
+                .collect();
+            checkpoint.extend(sidecar_paths.iter().map(|path| {
+                format!(
+                    r#"{{"sidecar":{{"path":"{path}","sizeInBytes":9070,"modificationTime":0}}}}"#
+                )
+            }));
+            std::fs::write(&checkpoint_path, checkpoint.join("\n")).unwrap();
+
+            let location = url::Url::from_directory_path(tmp.path()).unwrap();
+            // the sync engine only reads the first batch of rows of a JSON file, which doesn't
+            // have all the sidecars of the checkpoint with too many of them
+            let engine = DefaultEngine::try_new(
+                &location,
+                HashMap::<String, String>::new(),
+                Arc::new(TokioBackgroundExecutor::new()),
+            )
+            .unwrap();
+            // the protocol and metadata don't need the sidecars
+            let snapshot = Snapshot::try_new(location, &engine, Some(2)).unwrap();
+            let scan = ScanBuilder::new(snapshot).build().unwrap();
+            let result: DeltaResult<Vec<_>> = scan.files(&engine).unwrap().collect();
+            assert!(
+                matches!(&result, Err(Error::InvalidCommit(msg)) if msg.contains(expected_error)),
+                "{result:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_read_table_with_only_checkpoint() {
+        // log cleanup removed all the commits, leaving only the checkpoint and _last_checkpoint
+        let path =
+            std::fs::canonicalize(PathBuf::from("./tests/data/checkpoint-only-log/")).unwrap();
+        let location = url::Url::from_directory_path(path).unwrap();
+        let engine = SyncEngine::new();
+        let expected = Snapshot::try_new(
+            url::Url::from_directory_path(
+                std::fs::canonicalize(PathBuf::from(
+                    "./tests/data/with_checkpoint_no_last_checkpoint/",
+                ))
+                .unwrap(),
+            )
+            .unwrap(),
+            &engine,
+            Some(2),
+        )
+        .unwrap();
+
+        for version in [None, Some(2)] {
+            let snapshot = Snapshot::try_new(location.clone(), &engine, version).unwrap();
+            assert_eq!(snapshot.version(), 2);
+            assert!(snapshot.log_segment.commit_files.is_empty());
+            assert_eq!(snapshot.log_segment.checkpoint_files.len(), 1);
+            assert_eq!(snapshot.metadata(), expected.metadata());
+            assert_eq!(snapshot.protocol(), expected.protocol());
+            let expected_files = scan_file_paths(&expected, &engine);
+            assert!(!expected_files.is_empty());
+            assert_eq!(scan_file_paths(&snapshot, &engine), expected_files);
+        }
+        // the commits before the checkpoint were cleaned up, and there are none after it
+        for version in [1, 3] {
+            assert!(Snapshot::try_new(location.clone(), &engine, Some(version)).is_err());
+        }
+    }
+
+    /// The versions the log was listed from since the last call
+    fn take_listed_versions(engine: &InMemoryEngine) -> Vec<Version> {
+        engine
+            .take_listings()
+            .iter()
+            .map(|location| {
+                let name = location.path_segments().unwrap().next_back().unwrap();
+                name.parse().unwrap()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_list_log_from_last_checkpoint() {
+        let engine = InMemoryEngine::new();
+        let location = Url::parse("memory:///table/").unwrap();
+        let log_root = location.join("_delta_log/").unwrap();
+        let source = "./tests/data/with_checkpoint_no_last_checkpoint/_delta_log";
+        engine.put_local_dir(&log_root, source).unwrap();
+        let last_checkpoint = log_root.join(LAST_CHECKPOINT_FILE_NAME).unwrap();
+        let assert_snapshot = |checkpoint_version: Option<Version>| {
+            let snapshot = Snapshot::try_new(location.clone(), &engine, None).unwrap();
+            assert_eq!(snapshot.version(), 3);
+            let versions = |files: &[FileMeta]| {
+                files
+                    .iter()
+                    .map(|file| LogPath::new(&file.location).version.unwrap())
+                    .collect_vec()
+            };
+            assert_eq!(
+                versions(&snapshot.log_segment.checkpoint_files),
+                Vec::from_iter(checkpoint_version)
+            );
+            let first_commit = checkpoint_version.map_or(0, |version| version + 1);
+            assert_eq!(
+                versions(&snapshot.log_segment.commit_files),
+                (first_commit..=3).rev().collect_vec()
+            );
+        };
+
+        // without _last_checkpoint the whole log is listed
+        assert_snapshot(Some(2));
+        assert_eq!(take_listed_versions(&engine), [0]);
+
+        engine.put(last_checkpoint.clone(), r#"{"version":2,"size":8}"#);
+        assert_snapshot(Some(2));
+        assert_eq!(take_listed_versions(&engine), [2]);
+        // a snapshot of a version before the checkpoint can't use it
+        let snapshot = Snapshot::try_new(location.clone(), &engine, Some(1)).unwrap();
+        assert_eq!(snapshot.version(), 1);
+        assert_eq!(take_listed_versions(&engine), [0]);
+
+        engine.put(last_checkpoint.clone(), "{\"version\":");
+        assert_snapshot(Some(2));
+        assert_eq!(take_listed_versions(&engine), [0]);
+
+        // the checkpoint was cleaned up, but _last_checkpoint still points at it
+        engine.put(last_checkpoint.clone(), r#"{"version":2,"size":8}"#);
+        engine.remove(
+            &log_root
+                .join("00000000000000000002.checkpoint.parquet")
+                .unwrap(),
+        );
+        assert_snapshot(None);
+        assert_eq!(take_listed_versions(&engine), [2, 0]);
+    }
+    #[test]
+    fn test_latest_metadata_and_protocol() {
+        let source = "./tests/data/with_checkpoint_no_last_checkpoint/";
+        let tmp = tempfile::tempdir().unwrap();
+        let log_dir = tmp.path().join("_delta_log");
+        std::fs::create_dir(&log_dir).unwrap();
+        for entry in std::fs::read_dir(PathBuf::from(source).join("_delta_log")).unwrap() {
+            let entry = entry.unwrap();
+            std::fs::copy(entry.path(), log_dir.join(entry.file_name())).unwrap();
+        }
+        let location = url::Url::from_directory_path(tmp.path()).unwrap();
+        let engine = SyncEngine::new();
+        let snapshot = Snapshot::try_new(location.clone(), &engine, None).unwrap();
+
+        // without _last_checkpoint the log is listed to find the checkpoint
+        let (metadata, protocol) =
+            Snapshot::latest_metadata_and_protocol(&location, &engine).unwrap();
+        assert_eq!(&metadata, snapshot.metadata());
+        assert_eq!(&protocol, snapshot.protocol());
+
+        // with _last_checkpoint, the commits before the checkpoint aren't needed
+        std::fs::write(
+            log_dir.join(LAST_CHECKPOINT_FILE_NAME),
+            r#"{"version":2,"size":8}"#,
+        )
+        .unwrap();
+        std::fs::remove_file(log_dir.join("00000000000000000000.json")).unwrap();
+        std::fs::remove_file(log_dir.join("00000000000000000001.json")).unwrap();
+        let (metadata, protocol) =
+            Snapshot::latest_metadata_and_protocol(&location, &engine).unwrap();
+        assert_eq!(&metadata, snapshot.metadata());
+        assert_eq!(&protocol, snapshot.protocol());
+    }
+
+    #[test]
+    fn test_log_segment_cache() {
+        let metadata = |description: &str| {
+            let mut metadata = metadata_action(&StructType::new(vec![]), &[], &[]);
+            metadata["metaData"]["description"] = description.into();
+            metadata
+        };
+        let test_table = TestTable::new();
+        test_table
+            .commit(0, [protocol_action(1, 2), metadata("v0")])
+            .commit(1, [metadata("v1")])
+            .commit(2, [metadata("v2")]);
+        let log_dir = test_table.path().join("_delta_log");
+        let table = crate::Table::new(test_table.location());
+        let engine = SyncEngine::new();
+        let cache = LogSegmentCache::new(2);
+
+        let snapshot = table
+            .snapshot_with_log_cache(&engine, None, &cache)
+            .unwrap();
+        assert_eq!(snapshot.version(), 2);
+        for version in [0, 1] {
+            let snapshot = table
+                .snapshot_with_log_cache(&engine, Some(version), &cache)
+                .unwrap();
+            assert_eq!(snapshot.description(), Some(format!("v{version}").as_str()));
+        }
+        // the least recently used version 2 was evicted
+        assert_eq!(cache.len().unwrap(), 2);
+        assert!(!cache.contains(table.location(), 2).unwrap());
+
+        // cached versions are served without reading the log, which is now gone
+        std::fs::remove_dir_all(&log_dir).unwrap();
+        assert!(table.snapshot(&engine, Some(0)).is_err());
+        for version in [0, 1] {
+            let snapshot = table
+                .snapshot_with_log_cache(&engine, Some(version), &cache)
+                .unwrap();
+            assert_eq!(snapshot.version(), version);
+            assert_eq!(snapshot.description(), Some(format!("v{version}").as_str()));
+            assert_eq!(snapshot.log_segment.commit_files.len() as u64, version + 1);
+        }
+        assert!(table
+            .snapshot_with_log_cache(&engine, Some(2), &cache)
+            .is_err());
+
+        // a cache without capacity caches nothing
+        std::fs::create_dir(&log_dir).unwrap();
+        test_table.commit(0, [protocol_action(1, 2), metadata("v0")]);
+        let cache = LogSegmentCache::new(0);
+        table
+            .snapshot_with_log_cache(&engine, Some(0), &cache)
+            .unwrap();
+        assert!(cache.is_empty().unwrap());
+    }
+
+    #[test]
+    fn test_log_segment_cache_concurrent_misses() {
+        let test_table = TestTable::new();
+        test_table
+            .commit(
+                0,
+                [
+                    protocol_action(1, 2),
+                    metadata_action(&StructType::new(vec![]), &[], &[]),
+                ],
+            )
+            .commit(1, [add_action("a.parquet", 0)]);
+        let table = crate::Table::new(test_table.location());
+        let engine = InMemoryEngine::new();
+        engine
+            .put_local_dir(table.location(), test_table.path())
+            .unwrap();
+        table.snapshot(&engine, Some(1)).unwrap();
+        let listings = engine.take_listings();
+        engine.take_reads();
+
+        // every thread misses the cache, and all but one wait for it to load the log
+        let cache = LogSegmentCache::new(1);
+        engine.delay_listings(std::time::Duration::from_millis(100));
+        let threads = 8;
+        let barrier = std::sync::Barrier::new(threads);
+        std::thread::scope(|scope| {
+            for _ in 0..threads {
+                scope.spawn(|| {
+                    barrier.wait();
+                    let snapshot = table
+                        .snapshot_with_log_cache(&engine, Some(1), &cache)
+                        .unwrap();
+                    assert_eq!(snapshot.version(), 1);
+                });
+            }
+        });
+        assert_eq!(engine.take_listings(), listings);
+        assert!(cache.contains(table.location(), 1).unwrap());
+
+        // after a failed load, the waiting threads load the log themselves
+        let cache = LogSegmentCache::new(1);
+        let commit = table
+            .location()
+            .join("_delta_log/00000000000000000001.json")
+            .unwrap();
+        engine.take_reads();
+        engine.fail_reads(commit.clone(), 1);
+        let failures = std::sync::atomic::AtomicUsize::new(0);
+        std::thread::scope(|scope| {
+            for _ in 0..threads {
+                scope.spawn(|| {
+                    barrier.wait();
+                    if table
+                        .snapshot_with_log_cache(&engine, Some(1), &cache)
+                        .is_err()
+                    {
+                        failures.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+                    }
+                });
+            }
+        });
+        assert_eq!(failures.into_inner(), 1);
+        assert!(cache.contains(table.location(), 1).unwrap());
+    }
+
+    #[test]
+    fn test_snapshot_schema_roundtrip() {
+        let schema = serde_json::json!({
+            "type": "struct",
+            "fields": [
+                {
+                    "name": "id",
+                    "type": "long",
+                    "nullable": false,
+                    "metadata": {
+                        "delta.columnMapping.id": 1,
+                        "delta.columnMapping.physicalName": "col-5f422f40",
+                        "delta.identity.start": 1,
+                        "delta.identity.step": 1,
+                        "delta.identity.highWaterMark": 10000000000i64,
+                        "delta.identity.allowExplicitInsert": false
+                    }
+                },
+                {
+                    "name": "bucket",
+                    "type": "integer",
+                    "nullable": true,
+                    "metadata": {
+                        "delta.columnMapping.id": 2,
+                        "delta.columnMapping.physicalName": "col-8bd4ec35",
+                        "delta.generationExpression": "pmod(hash(id), 16)",
+                        "comment": "the bucket of the id",
+                        "custom": {"nested": ["a", "b"], "ratio": 0.5}
+                    }
+                }
+            ]
+        });
+        let metadata = metadata_action(&schema, &[], &[("delta.columnMapping.mode", "name")]);
+        let table = TestTable::new();
+        table.commit(0, [protocol_action(2, 5), metadata]);
+
+        let engine = SyncEngine::new();
+        let snapshot = Snapshot::try_new(table.location(), &engine, None).unwrap();
+        assert_eq!(serde_json::to_value(snapshot.schema()).unwrap(), schema);
+        let bucket = snapshot.schema().field("bucket").unwrap();
+        assert_eq!(bucket.generation_expression(), Some("pmod(hash(id), 16)"));
+        assert_eq!(
+            snapshot.generated_columns(),
+            HashMap::from([(
+                "bucket".to_string(),
+                GenerationExpression::Unparsed("pmod(hash(id), 16)".to_string())
+            )])
+        );
+        assert_eq!(
+            bucket.physical_name(ColumnMappingMode::Name).unwrap(),
+            "col-8bd4ec35"
+        );
+
+        // the logical schema only drops the column mapping metadata
+        let logical_schema = snapshot.logical_schema();
+        let bucket = logical_schema.field("bucket").unwrap();
+        let keys: BTreeSet<_> = bucket.metadata().keys().map(String::as_str).collect();
+        assert_eq!(
+            keys,
+            BTreeSet::from(["delta.generationExpression", "comment", "custom"])
+        );
+        assert!(logical_schema.field("id").unwrap().metadata().len() == 4);
+        assert!(snapshot.partition_columns().is_empty());
+    }
+
+    #[test]
+    fn test_snapshot_logical_schema() {
+        let field = |name: &str, id: i64, data_type: serde_json::Value| {
+            serde_json::json!({
+                "name": name,
+                "type": data_type,
+                "nullable": true,
+                "metadata": {
+                    "delta.columnMapping.id": id,
+                    "delta.columnMapping.physicalName": format!("col-{id}")
+                }
+            })
+        };
+        let nested = serde_json::json!({
+            "type": "struct",
+            "fields": [field("ts", 3, "timestamp".into())]
+        });
+        let schema = serde_json::json!({
+            "type": "struct",
+            "fields": [
+                field("amount", 1, "decimal(10,2)".into()),
+                field("nested", 2, nested),
+                field("part", 4, "string".into()),
+            ]
+        });
+        let metadata = metadata_action(&schema, &["part"], &[("delta.columnMapping.mode", "name")]);
+        let table = TestTable::new();
+        table.commit(0, [protocol_action(2, 5), metadata]);
+
+        let engine = SyncEngine::new();
+        let snapshot = Snapshot::try_new(table.location(), &engine, None).unwrap();
+        assert_eq!(snapshot.partition_columns(), ["part"]);
+
+        let schema = snapshot.logical_schema();
+        let arrow_schema = ArrowSchema::try_from(schema.as_ref()).unwrap();
+        for field in arrow_schema.fields() {
+            assert!(field.metadata().is_empty(), "{field:?}");
+        }
+        let nested_fields = match arrow_schema.field(1).data_type() {
+            ArrowDataType::Struct(fields) => fields.clone(),
+            data_type => panic!("Unexpected type {data_type}"),
+        };
+        assert!(nested_fields[0].metadata().is_empty());
+        assert_eq!(
+            nested_fields[0].data_type(),
+            &ArrowDataType::Timestamp(TimeUnit::Microsecond, Some("UTC".into()))
+        );
+        let names: Vec<_> = arrow_schema.fields().iter().map(|f| f.name()).collect();
+        assert_eq!(names, ["amount", "nested", "part"]);
+        assert_eq!(
+            arrow_schema.field(0).data_type(),
+            &ArrowDataType::Decimal128(10, 2)
+        );
+    }
+
+    #[test]
+    fn test_snapshot_timestamp() {
+        let tmp = copy_table_log("./tests/data/table-with-dv-small/");
+        // a modification time with a fractional second, to tell milliseconds from seconds
+        let modified_millis = 1_700_000_000_123;
+        let modified =
+            std::time::UNIX_EPOCH + std::time::Duration::from_millis(modified_millis as u64);
+        std::fs::File::options()
+            .write(true)
+            .open(tmp.path().join("_delta_log/00000000000000000001.json"))
+            .unwrap()
+            .set_modified(modified)
+            .unwrap();
+        let location = url::Url::from_directory_path(tmp.path()).unwrap();
+
+        // the modification time is read in milliseconds by both the sync and the default engine
+        let sync_engine = SyncEngine::new();
+        let default_engine = DefaultEngine::try_new(
+            &location,
+            HashMap::<String, String>::new(),
+            Arc::new(TokioBackgroundExecutor::new()),
+        )
+        .unwrap();
+        for engine in [&sync_engine as &dyn Engine, &default_engine] {
+            let snapshot = Snapshot::try_new(location.clone(), engine, None).unwrap();
+            assert_eq!(
+                snapshot.in_commit_timestamps_enablement_version().unwrap(),
+                None
+            );
+            assert_eq!(snapshot.timestamp(engine).unwrap(), modified_millis);
+        }
+    }
+
+    #[test]
+    fn test_snapshot_history() {
+        let table = TestTable::new();
+        table
+            .commit(
+                0,
+                [
+                    serde_json::json!({"commitInfo": {
+                        "timestamp": 1000,
+                        "operation": "CREATE TABLE",
+                        "operationParameters": {"isManaged": "false", "description": null}
+                    }}),
+                    protocol_action(1, 2),
+                    metadata_action(&value_schema(), &[], &[]),
+                ],
+            )
+            // a commit without commitInfo
+            .commit(1, [add_action("part-00000.parquet", 100)])
+            .commit(
+                2,
+                [serde_json::json!({"commitInfo": {
+                    "timestamp": 3000,
+                    "operation": "WRITE",
+                    "operationParameters": {"mode": "Append", "partitionBy": "[]"},
+                    "userMetadata": "nightly job"
+                }})],
+            );
+        let location = table.location();
+        let engine = SyncEngine::new();
+        let snapshot = Snapshot::try_new(location.clone(), &engine, None).unwrap();
+
+        let history = snapshot.history(&engine, None).unwrap();
+        let versions: Vec<_> = history.iter().map(|entry| entry.version).collect();
+        assert_eq!(versions, [2, 1, 0]);
+        assert_eq!(history[0].timestamp, snapshot.timestamp(&engine).unwrap());
+        assert_eq!(history[0].commit_info.operation.as_deref(), Some("WRITE"));
+        assert_eq!(
+            history[0].commit_info.operation_parameters,
+            Some(HashMap::from([
+                ("mode".to_string(), "Append".to_string()),
+                ("partitionBy".to_string(), "[]".to_string()),
+            ]))
+        );
+        assert_eq!(
+            history[0].commit_info.user_metadata.as_deref(),
+            Some("nightly job")
+        );
+        assert_eq!(history[1].commit_info, CommitInfo::default());
+        assert_eq!(
+            history[2].commit_info.operation_parameters,
+            Some(HashMap::from([(
+                "isManaged".to_string(),
+                "false".to_string()
+            )]))
+        );
+        assert_eq!(history[2].commit_info.user_metadata, None);
+
+        assert_eq!(snapshot.history(&engine, Some(2)).unwrap(), history[..2]);
+        // the history of an older snapshot ends at its version
+        let snapshot = Snapshot::try_new(location, &engine, Some(1)).unwrap();
+        assert_eq!(snapshot.history(&engine, None).unwrap(), history[1..]);
+    }
+
+    #[test]
+    fn test_first_commit_after() {
+        let commit_info = |timestamp: i64| serde_json::json!({"commitInfo": {"inCommitTimestamp": timestamp, "timestamp": 0}});
+        let table = TestTable::new();
+        table
+            .commit(
+                0,
+                [
+                    protocol_action(1, 2),
+                    metadata_action(&value_schema(), &[], &[]),
+                ],
+            )
+            .commit(1, [serde_json::json!({"commitInfo": {"timestamp": 0}})])
+            .commit(2, [serde_json::json!({"commitInfo": {"timestamp": 0}})])
+            .commit(3, [commit_info(4000)])
+            .commit(4, [commit_info(5000)]);
+        let engine = SyncEngine::new();
+        let log_root = table.location().join("_delta_log/").unwrap();
+        let mut commit_files =
+            list_commit_files(engine.get_file_system_client().as_ref(), &log_root, None, 4)
+                .unwrap();
+        // the file of version 2 is older than that of version 1, e.g. because it was copied
+        for (commit_file, last_modified) in commit_files.iter_mut().zip([1000, 3000, 2000]) {
+            commit_file.last_modified = last_modified;
+        }
+
+        // version 2 is treated as committed just after version 1
+        let cases = [
+            (999, 0),
+            (1000, 1),
+            (2000, 1),
+            (2999, 1),
+            (3000, 2),
+            (3001, 3),
+            (4000, 4),
+            (4999, 4),
+            (5000, 5),
+        ];
+        for (timestamp, expected) in cases {
+            let index = first_commit_after(&engine, &commit_files, Some(3), timestamp).unwrap();
+            assert_eq!(index, expected, "{timestamp}");
+        }
+    }
+
+    #[test]
+    fn test_snapshot_in_commit_timestamp() {
+        let table = TestTable::new();
+        let configuration = [
+            ("delta.enableInCommitTimestamps", "true"),
+            ("delta.inCommitTimestampEnablementVersion", "1"),
+            (
+                "delta.inCommitTimestampEnablementTimestamp",
+                "1700000000000",
+            ),
+        ];
+        table
+            .commit(
+                0,
+                [
+                    serde_json::json!({"commitInfo": {"timestamp": 1000, "operation": "CREATE TABLE"}}),
+                    protocol_action(1, 2),
+                    metadata_action(&value_schema(), &[], &[]),
+                ],
+            )
+            .commit(
+                1,
+                [
+                    serde_json::json!({"commitInfo": {
+                        "inCommitTimestamp": 1700000000000i64,
+                        "timestamp": 2000,
+                        "operation": "SET TBLPROPERTIES"
+                    }}),
+                    serde_json::json!({"protocol": {
+                        "minReaderVersion": 1,
+                        "minWriterVersion": 7,
+                        "writerFeatures": ["inCommitTimestamp"]
+                    }}),
+                    metadata_action(&value_schema(), &[], &configuration),
+                ],
+            );
+        let location = table.location();
+        let engine = SyncEngine::new();
+
+        // the commit before in-commit timestamps were enabled falls back to the file time
+        let snapshot = Snapshot::try_new(location.clone(), &engine, Some(0)).unwrap();
+        assert_eq!(
+            snapshot.in_commit_timestamps_enablement_version().unwrap(),
+            None
+        );
+        assert_eq!(
+            snapshot.timestamp(&engine).unwrap(),
+            snapshot.log_segment.commit_files[0].last_modified
+        );
+
+        let snapshot = Snapshot::try_new(location, &engine, None).unwrap();
+        assert_eq!(snapshot.version(), 1);
+        assert_eq!(
+            snapshot.in_commit_timestamps_enablement_version().unwrap(),
+            Some(1)
+        );
+        assert_eq!(snapshot.timestamp(&engine).unwrap(), 1700000000000);
+
+        // the commit is also resolved with respect to the enablement version
+        let first_commit = find_commit_file(
+            engine.get_file_system_client().as_ref(),
+            &snapshot.log_segment.log_root,
+            0,
+        )
+        .unwrap();
+        assert_eq!(
+            read_commit_timestamp(&engine, &first_commit, Some(1)).unwrap(),
+            first_commit.last_modified
+        );
+        assert!(read_commit_timestamp(&engine, &first_commit, Some(0)).is_err());
+    }
+
+    #[test]
+    fn test_added_files_since() {
+        let path =
+            std::fs::canonicalize(PathBuf::from("./tests/data/table-with-dv-small/")).unwrap();
+        let location = url::Url::from_directory_path(path).unwrap();
+        let engine = SyncEngine::new();
+        let base = Snapshot::try_new(location.clone(), &engine, Some(0)).unwrap();
+        let snapshot = Snapshot::try_new(location, &engine, None).unwrap();
+
+        // the only file got a deletion vector
+        let (added, removed) = snapshot.added_files_since(&base, &engine).unwrap();
+        let path = "part-00000-fae5310a-a37d-4e51-827b-c3d5516560ca-c000.snappy.parquet";
+        assert_eq!(added.len(), 1);
+        assert_eq!(added[0].path, path);
+        assert_eq!(added[0].size, Some(635));
+        assert!(added[0].dv_info.has_vector());
+        assert_eq!(removed.len(), 1);
+        assert_eq!(removed[0].path, path);
+        assert!(!removed[0].dv_info.has_vector());
+
+        let (added, removed) = snapshot.added_files_since(&snapshot, &engine).unwrap();
+        assert!(added.is_empty() && removed.is_empty());
+        assert!(base.added_files_since(&snapshot, &engine).is_err());
+    }
+
+    #[test]
+    fn test_added_files_since_reconciles_commits() {
+        let add = |path: &str| add_action(path, 1);
+        let remove = |path: &str| serde_json::json!({"remove": {"path": path, "deletionTimestamp": 1, "dataChange": true}});
+        let table = TestTable::new();
+        table
+            .commit(
+                0,
+                [
+                    protocol_action(1, 2),
+                    metadata_action(&value_schema(), &[], &[]),
+                    add("a"),
+                    add("b"),
+                ],
+            )
+            .commit(1, [add("c"), remove("a")])
+            .commit(2, [add("d"), remove("c")])
+            .commit(3, [remove("b")])
+            .commit(4, [add("b")]);
+        let log_dir = table.path().join("_delta_log");
+        let location = table.location();
+        let engine = SyncEngine::new();
+        let base = Snapshot::try_new(location.clone(), &engine, Some(0)).unwrap();
+        let snapshot = Snapshot::try_new(location, &engine, None).unwrap();
+
+        // c was added and removed again, and b was removed and added again
+        let (added, removed) = snapshot.added_files_since(&base, &engine).unwrap();
+        let added: Vec<_> = added.iter().map(|file| file.path.as_str()).collect();
+        let removed: Vec<_> = removed.iter().map(|file| file.path.as_str()).collect();
+        assert_eq!(added, ["d"]);
+        assert_eq!(removed, ["a"]);
+
+        // the commits in between must still be in the log
+        std::fs::remove_file(log_dir.join(format!("{:020}.json", 2))).unwrap();
+        assert!(matches!(
+            snapshot.added_files_since(&base, &engine),
+            Err(Error::MissingData(_))
+        ));
+    }
+
+    #[test]
+    fn test_referenced_deletion_vector_files() {
+        let path =
+            std::fs::canonicalize(PathBuf::from("./tests/data/table-with-dv-small/")).unwrap();
+        let url = url::Url::from_directory_path(path).unwrap();
+        let engine = SyncEngine::new();
+
+        let snapshot = Snapshot::try_new(url.clone(), &engine, None).unwrap();
+        let dv_files = snapshot.referenced_deletion_vector_files(&engine).unwrap();
+        let expected = url
+            .join("deletion_vector_61d16c75-6994-46b7-a15b-8b538852e50e.bin")
+            .unwrap();
+        assert_eq!(dv_files, [expected]);
+
+        // the file had no deletion vector before the second commit
+        let snapshot = Snapshot::try_new(url, &engine, Some(0)).unwrap();
+        assert_eq!(
+            snapshot.referenced_deletion_vector_files(&engine).unwrap(),
+            []
+        );
+    }
+
+    #[test]
+    fn test_clustering_provider() {
+        let path = std::fs::canonicalize(PathBuf::from("./tests/data/clustered-table/")).unwrap();
+        let url = url::Url::from_directory_path(path).unwrap();
+        let engine = SyncEngine::new();
+
+        // the file written at version 0 was clustered by the OPTIMIZE of version 1
+        let snapshot = Snapshot::try_new(url.clone(), &engine, Some(0)).unwrap();
+        assert_eq!(snapshot.clustering_provider(&engine).unwrap(), None);
+        let snapshot = Snapshot::try_new(url, &engine, None).unwrap();
+        assert_eq!(
+            snapshot.clustering_provider(&engine).unwrap().as_deref(),
+            Some("liquid")
+        );
+    }
+
+    #[test]
+    fn test_latest_version() {
+        let engine = InMemoryEngine::new();
+        let log_root = Url::parse("memory:///table/_delta_log/").unwrap();
+        let log_file = |name: String| log_root.join(&name).unwrap();
+        // the log was cleaned up before version 3, and has a checkpoint at version 10
+        for version in 3..=200 {
+            engine.put(log_file(format!("{version:020}.json")), "{}");
+            engine.put(log_file(format!(".{version:020}.json.crc")), "");
+        }
+        engine.put(log_file(format!("{:020}.checkpoint.parquet", 10)), "");
+        let client = engine.get_file_system_client();
+        // the log shouldn't be listed from every version
+        let listings = || engine.take_listings().len();
+
+        // without `_last_checkpoint`
+        assert_eq!(latest_version(client.as_ref(), &log_root).unwrap(), 200);
+        assert!(listings() < 20);
+
+        // with a stale `_last_checkpoint`
+        let last_checkpoint = log_file(LAST_CHECKPOINT_FILE_NAME.to_string());
+        engine.put(last_checkpoint.clone(), r#"{"version":10,"size":1}"#);
+        assert_eq!(latest_version(client.as_ref(), &log_root).unwrap(), 200);
+        assert!(listings() < 20);
+
+        // with a `_last_checkpoint` past the end of the log
+        engine.put(last_checkpoint, r#"{"version":300,"size":1}"#);
+        assert_eq!(latest_version(client.as_ref(), &log_root).unwrap(), 200);
+
+        engine.put(log_file(format!("{:020}.json", 201)), "{}");
+        let table = crate::Table::new(Url::parse("memory:///table/").unwrap());
+        assert_eq!(table.latest_version(&engine).unwrap(), 201);
+
+        let table = crate::Table::new(Url::parse("memory:///empty/").unwrap());
+        assert!(matches!(
+            table.latest_version(&engine),
+            Err(Error::MissingVersion)
+        ));
+    }
+
+    #[test]
+    fn test_earliest_version() {
+        let engine = SyncEngine::new();
+        let dir = tempfile::tempdir().unwrap();
+        let log_dir = dir.path().join("_delta_log");
+        std::fs::create_dir(&log_dir).unwrap();
+        let table = crate::Table::new(Url::from_directory_path(dir.path()).unwrap());
+        assert!(matches!(
+            table.earliest_version(&engine),
+            Err(Error::MissingVersion)
+        ));
+
+        // the log was cleaned up before version 3, so the commits before the checkpoint at version
+        // 10 can't be reconstructed
+        for version in 3..=20 {
+            std::fs::write(log_dir.join(format!("{version:020}.json")), "{}").unwrap();
+        }
+        assert!(matches!(
+            table.earliest_version(&engine),
+            Err(Error::MissingVersion)
+        ));
+        for version in [10, 15] {
+            let checkpoint = format!("{version:020}.checkpoint.parquet");
+            std::fs::write(log_dir.join(checkpoint), "").unwrap();
+        }
+        std::fs::write(log_dir.join(LAST_CHECKPOINT_FILE_NAME), "{}").unwrap();
+        assert_eq!(table.earliest_version(&engine).unwrap(), 10);
+
+        // a multi-part checkpoint only counts once all of its parts are there
+        let part = |part: u32| format!("{:020}.checkpoint.{part:010}.{:010}.parquet", 5, 2);
+        std::fs::write(log_dir.join(part(2)), "").unwrap();
+        assert_eq!(table.earliest_version(&engine).unwrap(), 10);
+        std::fs::write(log_dir.join(part(1)), "").unwrap();
+        assert_eq!(table.earliest_version(&engine).unwrap(), 5);
+
+        // with all commits, the whole history can be reconstructed
+        for version in 0..3 {
+            std::fs::write(log_dir.join(format!("{version:020}.json")), "{}").unwrap();
+        }
+        assert_eq!(table.earliest_version(&engine).unwrap(), 0);
+    }
 }
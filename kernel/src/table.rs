@@ -1,12 +1,20 @@
 //! In-memory representation of a Delta table, which acts as an immutable root entity for reading
 //! the different versions
 
+use std::ops::{Bound, RangeBounds};
 use std::path::PathBuf;
 
 use url::Url;
 
-use crate::snapshot::Snapshot;
-use crate::{DeltaResult, Engine, Error, Version};
+use crate::actions::{get_log_schema_for, ActionType, Metadata, Protocol};
+use crate::path::version_from_location;
+use crate::schema::StructType;
+use crate::snapshot::{
+    earliest_version, first_commit_after, latest_version, list_commit_files, read_commit_timestamp,
+    LogSegmentCache, Snapshot,
+};
+use crate::table_changes::TableChanges;
+use crate::{DeltaResult, Engine, EngineData, Error, Version};
 
 /// In-memory representation of a Delta table, which acts as an immutable root entity for reading
 /// the different versions (see [`Snapshot`]) of the table located in storage.
@@ -76,6 +84,246 @@ impl Table {
     pub fn snapshot(&self, engine: &dyn Engine, version: Option<Version>) -> DeltaResult<Snapshot> {
         Snapshot::try_new(self.location.clone(), engine, version)
     }
+
+    /// Create a [`Snapshot`] of the table corresponding to `version` like [`Table::snapshot`],
+    /// reusing the parsed log of that version from `cache` if it was loaded before. See
+    /// [`Snapshot::try_new_with_log_cache`].
+    pub fn snapshot_with_log_cache(
+        &self,
+        engine: &dyn Engine,
+        version: Option<Version>,
+        cache: &LogSegmentCache,
+    ) -> DeltaResult<Snapshot> {
+        Snapshot::try_new_with_log_cache(self.location.clone(), engine, version, cache)
+    }
+
+    /// Create a [`Snapshot`] of the table as of `timestamp_millis` (in milliseconds since the Unix
+    /// epoch), like `TIMESTAMP AS OF` time travel: the latest version whose commit timestamp is at
+    /// most `timestamp_millis`. Commit timestamps are resolved like [`Snapshot::timestamp`], i.e.
+    /// in-commit timestamps are used where the table has them, and the modification time of the
+    /// commit file (as resolved by the engine's [`crate::clock::Clock`]) otherwise. A commit whose
+    /// file is older than that of the commit before it is treated as committed just after it.
+    ///
+    /// Only versions from the [`Table::earliest_version`] on can be travelled to. Log cleanup
+    /// removes the commits before a checkpoint, so once it has run, the earliest version is that
+    /// of the earliest checkpoint rather than 0. The timestamp of a version is still that of its
+    /// commit, so if the commit of the earliest checkpoint's version was removed as well, the
+    /// earliest version with a commit after it is the earliest one that can be travelled to. A
+    /// timestamp before it fails with an [`Error::TimestampBeforeEarliestCommit`].
+    pub fn snapshot_at_timestamp(
+        &self,
+        engine: &dyn Engine,
+        timestamp_millis: i64,
+    ) -> DeltaResult<Snapshot> {
+        let latest = self.snapshot(engine, None)?;
+        let fs_client = engine.get_file_system_client();
+        let log_root = &latest.log_segment.log_root;
+        let earliest = earliest_version(fs_client.as_ref(), log_root)?;
+        let commit_files: Vec<_> =
+            list_commit_files(fs_client.as_ref(), log_root, None, latest.version())?
+                .into_iter()
+                .filter(|file| version_from_location(&file.location) >= Some(earliest))
+                .collect();
+        let in_commit_timestamps_from = latest.in_commit_timestamps_enablement_version()?;
+        let timestamp = |index: usize| {
+            read_commit_timestamp(engine, &commit_files[index], in_commit_timestamps_from)
+        };
+        let version = |index: usize| {
+            version_from_location(&commit_files[index].location).ok_or(Error::MissingVersion)
+        };
+
+        // the index of the first commit with a timestamp after the target
+        let low = first_commit_after(
+            engine,
+            &commit_files,
+            in_commit_timestamps_from,
+            timestamp_millis,
+        )?;
+        if low == 0 {
+            return match commit_files.is_empty() {
+                true => Err(Error::MissingVersion),
+                false => Err(Error::timestamp_before_earliest_commit(
+                    timestamp_millis,
+                    version(0)?,
+                    timestamp(0)?,
+                )),
+            };
+        }
+        match version(low - 1)? {
+            version if version == latest.version() => Ok(latest),
+            version => self.snapshot(engine, Some(version)),
+        }
+    }
+
+    /// Find the latest version of the table, without creating a [`Snapshot`] of it. This doesn't
+    /// list the whole log, but only a few files around the version in `_last_checkpoint`, and is
+    /// still correct when `_last_checkpoint` is stale.
+    pub fn latest_version(&self, engine: &dyn Engine) -> DeltaResult<Version> {
+        let log_root = self.location.join("_delta_log/")?;
+        latest_version(engine.get_file_system_client().as_ref(), &log_root)
+    }
+
+    /// Find the earliest version of the table that a [`Snapshot`] can be created for, taking log
+    /// cleanup into account: this is version 0 if the log still has all its commits, and otherwise
+    /// the version of its earliest checkpoint. This lists the log from its start.
+    pub fn earliest_version(&self, engine: &dyn Engine) -> DeltaResult<Version> {
+        let log_root = self.location.join("_delta_log/")?;
+        earliest_version(engine.get_file_system_client().as_ref(), &log_root)
+    }
+
+    /// Get the history of the schema of the table over the range of `versions`, as the versions
+    /// at which the schema changed (in order) and the schema from that version on. The first
+    /// entry is the schema at the start of the range, and every other entry a commit of the range
+    /// with a `metaData` action that changed the schema (commits that only change other metadata,
+    /// like the table properties, are skipped).
+    ///
+    /// The range defaults to start at the [`Table::earliest_version`] and end at the
+    /// [`Table::latest_version`]. Only the `metaData` actions of the commits in the range are
+    /// read, along with what is needed to find the schema at its start (e.g. a checkpoint). An
+    /// empty range has no history.
+    pub fn schema_history(
+        &self,
+        engine: &dyn Engine,
+        versions: impl RangeBounds<Version>,
+    ) -> DeltaResult<Vec<(Version, StructType)>> {
+        let Some((start_version, end_version)) = self.resolve_versions(engine, versions)? else {
+            return Ok(vec![]);
+        };
+
+        let mut history: Vec<(Version, StructType)> = vec![];
+        for (version, metadata) in self.metadata_history(engine, start_version, end_version)? {
+            let schema = metadata.schema()?;
+            if history.last().is_none_or(|(_, last)| *last != schema) {
+                history.push((version, schema));
+            }
+        }
+        Ok(history)
+    }
+
+    /// Get the metadata of the table at `start_version`, followed by the `metaData` actions of
+    /// the commits after it up to `end_version` (inclusive), with the versions they took effect
+    /// at. Only the `metaData` actions of the commits are read, along with what is needed to find
+    /// the metadata at the start (e.g. a checkpoint).
+    pub(crate) fn metadata_history(
+        &self,
+        engine: &dyn Engine,
+        start_version: Version,
+        end_version: Version,
+    ) -> DeltaResult<Vec<(Version, Metadata)>> {
+        self.action_history(
+            engine,
+            start_version,
+            end_version,
+            ActionType::Metadata,
+            |snapshot| snapshot.metadata().clone(),
+            Metadata::try_new_from_data,
+        )
+    }
+
+    /// Get the action of type `action_type` of the table at `start_version`, as `at_start` gets it
+    /// from the snapshot of that version, followed by the actions of that type of the commits
+    /// after it up to `end_version` (inclusive), with the versions they took effect at. Only the
+    /// actions of that type are read from the commits, with `try_new_from_data`, so this is only
+    /// for actions that a commit has at most one of, like `metaData` and `protocol`.
+    fn action_history<T>(
+        &self,
+        engine: &dyn Engine,
+        start_version: Version,
+        end_version: Version,
+        action_type: ActionType,
+        at_start: impl FnOnce(&Snapshot) -> T,
+        try_new_from_data: impl Fn(&dyn EngineData) -> DeltaResult<Option<T>>,
+    ) -> DeltaResult<Vec<(Version, T)>> {
+        let snapshot = self.snapshot(engine, Some(start_version))?;
+        let mut history = vec![(start_version, at_start(&snapshot))];
+        if start_version == end_version {
+            return Ok(history);
+        }
+        let commit_files = list_commit_files(
+            engine.get_file_system_client().as_ref(),
+            &snapshot.log_segment.log_root,
+            Some(start_version + 1),
+            end_version,
+        )?;
+        let read_schema = get_log_schema_for(&[action_type])?;
+        for commit_file in commit_files {
+            let version =
+                version_from_location(&commit_file.location).ok_or(Error::MissingVersion)?;
+            let batches = engine.get_json_handler().read_json_files(
+                std::slice::from_ref(&commit_file),
+                read_schema.clone(),
+                None,
+            )?;
+            for batch in batches {
+                if let Some(action) = try_new_from_data(batch?.as_ref())? {
+                    history.push((version, action));
+                    break;
+                }
+            }
+        }
+        Ok(history)
+    }
+
+    /// Get the changes made to the table by the commits of versions `start_version` to
+    /// `end_version` (inclusive), or to the latest version if `end_version` is `None`, i.e. its
+    /// change data feed. See [`TableChanges`].
+    pub fn table_changes(
+        &self,
+        engine: &dyn Engine,
+        start_version: Version,
+        end_version: Option<Version>,
+    ) -> DeltaResult<TableChanges> {
+        TableChanges::try_new(self.location.clone(), engine, start_version, end_version)
+    }
+
+    /// Get the history of the protocol of the table over the range of `versions`, as the versions
+    /// with a `protocol` action (in order) and that protocol. The first entry is the protocol at
+    /// the start of the range, and every other entry a commit of the range with a `protocol`
+    /// action, e.g. one that enabled a table feature like deletion vectors or column mapping.
+    ///
+    /// The range defaults to the whole table, like for [`Table::schema_history`]. Only the
+    /// `protocol` actions of the commits in the range are read, along with what is needed to find
+    /// the protocol at its start (e.g. a checkpoint). An empty range has no history.
+    pub fn protocol_history(
+        &self,
+        engine: &dyn Engine,
+        versions: impl RangeBounds<Version>,
+    ) -> DeltaResult<Vec<(Version, Protocol)>> {
+        let Some((start_version, end_version)) = self.resolve_versions(engine, versions)? else {
+            return Ok(vec![]);
+        };
+
+        self.action_history(
+            engine,
+            start_version,
+            end_version,
+            ActionType::Protocol,
+            |snapshot| snapshot.protocol().clone(),
+            Protocol::try_new_from_data,
+        )
+    }
+
+    /// Resolve `versions` to the first and last version of the range, defaulting to the
+    /// [`Table::earliest_version`] and the [`Table::latest_version`]. Returns `None` if the range
+    /// is empty.
+    fn resolve_versions(
+        &self,
+        engine: &dyn Engine,
+        versions: impl RangeBounds<Version>,
+    ) -> DeltaResult<Option<(Version, Version)>> {
+        let start_version = match versions.start_bound() {
+            Bound::Included(version) => *version,
+            Bound::Excluded(version) => version + 1,
+            Bound::Unbounded => self.earliest_version(engine)?,
+        };
+        let end_version = match versions.end_bound() {
+            Bound::Included(version) => *version,
+            Bound::Excluded(0) => return Ok(None),
+            Bound::Excluded(version) => version - 1,
+            Bound::Unbounded => self.latest_version(engine)?,
+        };
+        Ok((start_version <= end_version).then_some((start_version, end_version)))
+    }
 }
 
 #[derive(Debug)]
@@ -115,6 +363,8 @@ mod tests {
 
     use super::*;
     use crate::engine::sync::SyncEngine;
+    use crate::schema::{DataType, StructField};
+    use crate::test_utils::{metadata_action, protocol_action, TestTable};
 
     #[test]
     fn test_table() {
@@ -127,6 +377,156 @@ mod tests {
         assert_eq!(snapshot.version(), 1)
     }
 
+    #[test]
+    fn test_schema_history() {
+        let metadata = |columns: &[&str], configuration: &[(&str, &str)]| {
+            let fields = columns
+                .iter()
+                .map(|name| StructField::new(*name, DataType::LONG, true));
+            metadata_action(&StructType::new(fields.collect()), &[], configuration)
+        };
+        let test_table = TestTable::new();
+        test_table
+            .commit(0, [protocol_action(1, 2), metadata(&["id"], &[])])
+            .commit(
+                1,
+                [serde_json::json!({"commitInfo": {"operation": "WRITE"}})],
+            )
+            .commit(2, [metadata(&["id", "value"], &[])])
+            // only the table properties change
+            .commit(3, [metadata(&["id", "value"], &[("a", "b")])])
+            .commit(4, [metadata(&["value"], &[("a", "b")])]);
+
+        let engine = SyncEngine::new();
+        let table = Table::new(test_table.location());
+        let names = |history: Vec<(Version, StructType)>| -> Vec<(Version, Vec<String>)> {
+            history
+                .into_iter()
+                .map(|(version, schema)| {
+                    let names = schema.fields().map(|field| field.name().clone()).collect();
+                    (version, names)
+                })
+                .collect()
+        };
+        let history = table.schema_history(&engine, ..).unwrap();
+        assert_eq!(
+            names(history),
+            [
+                (0, vec!["id".to_string()]),
+                (2, vec!["id".to_string(), "value".to_string()]),
+                (4, vec!["value".to_string()]),
+            ]
+        );
+        // a range starts with the schema at its start, even if that didn't change at the start
+        let history = table.schema_history(&engine, 1..4).unwrap();
+        assert_eq!(
+            names(history),
+            [
+                (1, vec!["id".to_string()]),
+                (2, vec!["id".to_string(), "value".to_string()]),
+            ]
+        );
+        assert_eq!(table.schema_history(&engine, 3..3).unwrap(), []);
+        assert!(table.schema_history(&engine, 0..=5).is_err());
+    }
+
+    #[test]
+    fn test_protocol_history() {
+        let schema = StructType::new(vec![StructField::new("id", DataType::LONG, true)]);
+        let protocol = |features: &[&str]| {
+            serde_json::json!({"protocol": {
+                "minReaderVersion": 3,
+                "minWriterVersion": 7,
+                "readerFeatures": features,
+                "writerFeatures": features
+            }})
+        };
+        let write = serde_json::json!({"commitInfo": {"operation": "WRITE"}});
+        let test_table = TestTable::new();
+        test_table
+            .commit(
+                0,
+                [protocol_action(1, 2), metadata_action(&schema, &[], &[])],
+            )
+            .commit(1, [&write])
+            .commit(2, [protocol(&["deletionVectors"])])
+            .commit(3, [&write])
+            .commit(4, [protocol(&["deletionVectors", "columnMapping"])]);
+
+        let engine = SyncEngine::new();
+        let table = Table::new(test_table.location());
+        let features = |history: Vec<(Version, Protocol)>| -> Vec<(Version, i32, Vec<String>)> {
+            history
+                .into_iter()
+                .map(|(version, protocol)| {
+                    let features = protocol.reader_features.unwrap_or_default();
+                    (version, protocol.min_reader_version, features)
+                })
+                .collect()
+        };
+        let dv = || "deletionVectors".to_string();
+        let cm = || "columnMapping".to_string();
+        let history = table.protocol_history(&engine, ..).unwrap();
+        assert_eq!(
+            features(history),
+            [(0, 1, vec![]), (2, 3, vec![dv()]), (4, 3, vec![dv(), cm()])]
+        );
+        // a range starts with the protocol at its start, even if it has no protocol action
+        let history = table.protocol_history(&engine, 1..4).unwrap();
+        assert_eq!(features(history), [(1, 1, vec![]), (2, 3, vec![dv()])]);
+        let history = table.protocol_history(&engine, 3..=4).unwrap();
+        assert_eq!(
+            features(history),
+            [(3, 3, vec![dv()]), (4, 3, vec![dv(), cm()])]
+        );
+        assert_eq!(table.protocol_history(&engine, 3..3).unwrap(), []);
+        assert!(table.protocol_history(&engine, 0..=5).is_err());
+    }
+
+    #[test]
+    fn test_snapshot_at_timestamp() {
+        let schema = StructType::new(vec![StructField::new("id", DataType::LONG, true)]);
+        let commit_info = |timestamp: i64| serde_json::json!({"commitInfo": {"inCommitTimestamp": timestamp, "timestamp": 0}});
+        let test_table = TestTable::new();
+        test_table
+            .commit(
+                0,
+                [
+                    commit_info(1000),
+                    serde_json::json!({"protocol": {
+                        "minReaderVersion": 1,
+                        "minWriterVersion": 7,
+                        "writerFeatures": ["inCommitTimestamp"]
+                    }}),
+                    metadata_action(&schema, &[], &[("delta.enableInCommitTimestamps", "true")]),
+                ],
+            )
+            .commit(1, [commit_info(2000)])
+            .commit(2, [commit_info(3000)]);
+        let table = Table::new(test_table.location());
+        let engine = SyncEngine::new();
+
+        let version = |timestamp| {
+            table
+                .snapshot_at_timestamp(&engine, timestamp)
+                .map(|snapshot| snapshot.version())
+        };
+        assert_eq!(version(1000).unwrap(), 0);
+        assert_eq!(version(1999).unwrap(), 0);
+        assert_eq!(version(2000).unwrap(), 1);
+        assert_eq!(version(2500).unwrap(), 1);
+        assert_eq!(version(3000).unwrap(), 2);
+        assert_eq!(version(i64::MAX).unwrap(), 2);
+        assert!(matches!(
+            version(999),
+            Err(Error::TimestampBeforeEarliestCommit {
+                timestamp: 999,
+                earliest_version: 0,
+                earliest_timestamp: 1000,
+            })
+        ));
+    }
+
     #[test]
     fn test_path_parsing() {
         for x in [
@@ -0,0 +1,1155 @@
+//! Read the [change data feed] of a table, i.e. the rows that were inserted, deleted and updated
+//! by a range of its commits.
+//!
+//! [change data feed]: https://github.com/delta-io/delta/blob/master/PROTOCOL.md#add-cdc-file
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use itertools::Itertools;
+use tracing::debug;
+use url::Url;
+
+use crate::actions::deletion_vector::{treemap_to_bools, DeletionVectorDescriptor};
+use crate::actions::visitors::{AddVisitor, CdcVisitor, RemoveVisitor};
+use crate::actions::{get_log_schema_for, ActionType};
+use crate::column_mapping::ColumnMappingMode;
+use crate::expressions::{Expression, Scalar};
+use crate::path::version_from_location;
+use crate::scan::{transform_expression, ColumnType, ScanResult};
+use crate::schema::{DataType, SchemaRef, StructField, StructType};
+use crate::snapshot::{first_commit_after, list_commit_files, read_commit_timestamp, Snapshot};
+use crate::table::Table;
+use crate::utils::require;
+use crate::{DeltaResult, Engine, EngineData, Error, FileMeta, Version};
+
+/// The name of the column holding the type of change of each row: `insert`, `delete`,
+/// `update_preimage` or `update_postimage`
+pub const CHANGE_TYPE_COL_NAME: &str = "_change_type";
+/// The name of the column holding the version of the commit that made the change of each row
+pub const COMMIT_VERSION_COL_NAME: &str = "_commit_version";
+/// The name of the column holding the timestamp of the commit that made the change of each row
+pub const COMMIT_TIMESTAMP_COL_NAME: &str = "_commit_timestamp";
+
+const ENABLE_CHANGE_DATA_FEED_KEY: &str = "delta.enableChangeDataFeed";
+
+/// The changes made to a table by the commits in a range of versions. Use
+/// [`TableChanges::execute_stream`] to read them.
+pub struct TableChanges {
+    snapshot: Arc<Snapshot>,
+    start_version: Version,
+    /// The commit files of the range, in order of version
+    commit_files: Vec<FileMeta>,
+    /// The schema the table data is read with: the schema of the table at the end version of the
+    /// range, unless another one was set with [`TableChanges::with_schema`]
+    logical_schema: SchemaRef,
+    /// The schemas of the table over the range, with the version each took effect at
+    schema_history: Vec<(Version, StructType)>,
+    /// How to read the commits of the range, for each entry of the `schema_history`
+    read_plans: Vec<ReadPlan>,
+    all_fields: Vec<ColumnType>,
+}
+
+impl std::fmt::Debug for TableChanges {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TableChanges")
+            .field("table_root", &self.snapshot.table_root.as_str())
+            .field("start_version", &self.start_version)
+            .field("end_version", &self.snapshot.version())
+            .finish()
+    }
+}
+
+/// The rows of a file that are changes
+enum ChangeRows {
+    /// The rows that aren't deleted by the deletion vector, if the file has one, e.g. the rows of
+    /// an added file
+    Live(Option<DeletionVectorDescriptor>),
+    /// The rows that `deletion_vector` deletes but `other` doesn't, e.g. the rows that a commit
+    /// deleted by replacing the deletion vector `other` of a file with `deletion_vector`
+    DeletedOnlyBy {
+        deletion_vector: DeletionVectorDescriptor,
+        other: Option<DeletionVectorDescriptor>,
+    },
+}
+
+/// How to read the files of the commits that have one of the schemas the table had over the range,
+/// to get their changes with the schema the changes are read with
+struct ReadPlan {
+    /// The schema of the table data in data files
+    physical_schema: SchemaRef,
+    /// The schema of the table data in change data files, which also hold the change type
+    cdc_physical_schema: SchemaRef,
+    /// The indices of the fields of the logical schema that the table didn't have yet (or didn't
+    /// have anymore) in these commits, which are null in their changes
+    missing_fields: Vec<usize>,
+}
+
+/// The name a field of the table data is stored with, which identifies it across renames when
+/// column mapping is enabled. The fields of schemas from before column mapping was enabled don't
+/// have physical names, and are stored with their logical names.
+fn physical_key(field: &StructField, column_mapping_mode: ColumnMappingMode) -> &str {
+    field
+        .physical_name(column_mapping_mode)
+        .unwrap_or(field.name())
+}
+
+/// Whether the data of a column of type `commit_type` can be read as `target_type`: the types
+/// must be the same, except that a struct may have fields that the column doesn't have (which
+/// are read as null). Any other type change can't be reconciled, as the values in the files
+/// written before the change still have the old type.
+fn is_readable_as(
+    commit_type: &DataType,
+    target_type: &DataType,
+    column_mapping_mode: ColumnMappingMode,
+) -> bool {
+    match (commit_type, target_type) {
+        (DataType::Struct(commit_struct), DataType::Struct(target_struct)) => {
+            commit_struct.fields().all(|commit_field| {
+                let key = physical_key(commit_field, column_mapping_mode);
+                target_struct.fields().any(|target_field| {
+                    physical_key(target_field, column_mapping_mode) == key
+                        && is_readable_as(
+                            commit_field.data_type(),
+                            target_field.data_type(),
+                            column_mapping_mode,
+                        )
+                })
+            })
+        }
+        (DataType::Array(commit_array), DataType::Array(target_array)) => is_readable_as(
+            commit_array.element_type(),
+            target_array.element_type(),
+            column_mapping_mode,
+        ),
+        (DataType::Map(commit_map), DataType::Map(target_map)) => {
+            is_readable_as(
+                commit_map.key_type(),
+                target_map.key_type(),
+                column_mapping_mode,
+            ) && is_readable_as(
+                commit_map.value_type(),
+                target_map.value_type(),
+                column_mapping_mode,
+            )
+        }
+        _ => commit_type == target_type,
+    }
+}
+
+/// Resolve the columns of `logical_schema` to the columns of the table they are read from. Each
+/// column is the column of the table with its name in the latest of the schemas in
+/// `schema_history` that has one, and is read by the [physical key](physical_key) of that column,
+/// so that a column can be requested by any name it had within the range. Partition columns and
+/// void columns aren't read, but materialized. Fails with an [`Error::MissingColumn`] for a column
+/// that the table didn't have at any version of the range.
+fn resolve_columns(
+    snapshot: &Snapshot,
+    logical_schema: &StructType,
+    schema_history: &[(Version, StructType)],
+) -> DeltaResult<Vec<ColumnType>> {
+    let column_mapping_mode = snapshot.column_mapping_mode();
+    let partition_columns = &snapshot.metadata().partition_columns;
+    logical_schema
+        .fields()
+        .enumerate()
+        .map(|(index, field)| {
+            let table_field = schema_history
+                .iter()
+                .rev()
+                .find_map(|(_, commit_schema)| commit_schema.field(field.name()))
+                .ok_or_else(|| Error::missing_column(field.name()))?;
+            if partition_columns.contains(field.name()) || field.data_type() == &DataType::VOID {
+                Ok(ColumnType::Partition(index))
+            } else {
+                let physical_key = physical_key(table_field, column_mapping_mode);
+                Ok(ColumnType::Selected(physical_key.to_string()))
+            }
+        })
+        .try_collect()
+}
+
+/// Plan how to read the commits with each of the schemas in `schema_history` to get their changes
+/// with `logical_schema`. Fails if a column of `logical_schema` isn't a column of the table (see
+/// [`resolve_columns`]), or has a type in one of the schemas that can't be read as its type in
+/// `logical_schema`.
+fn plan_reads(
+    snapshot: &Snapshot,
+    logical_schema: &StructType,
+    schema_history: &[(Version, StructType)],
+) -> DeltaResult<(Vec<ColumnType>, Vec<ReadPlan>)> {
+    let column_mapping_mode = snapshot.column_mapping_mode();
+    let all_fields = resolve_columns(snapshot, logical_schema, schema_history)?;
+    let read_plans = schema_history
+        .iter()
+        .map(|(version, commit_schema)| {
+            let mut read_fields = vec![];
+            let mut missing_fields = vec![];
+            for (index, (field, column_type)) in
+                logical_schema.fields().zip(&all_fields).enumerate()
+            {
+                let ColumnType::Selected(physical_name) = column_type else {
+                    continue;
+                };
+                let commit_field = commit_schema.fields().find(|commit_field| {
+                    physical_key(commit_field, column_mapping_mode) == physical_name
+                });
+                let Some(commit_field) = commit_field else {
+                    missing_fields.push(index);
+                    continue;
+                };
+                require!(
+                    is_readable_as(
+                        commit_field.data_type(),
+                        field.data_type(),
+                        column_mapping_mode
+                    ),
+                    Error::schema_mismatch(
+                        snapshot
+                            .log_segment
+                            .log_root
+                            .join(&format!("{version:020}.json"))?,
+                        field.name(),
+                        field.data_type(),
+                        commit_field.data_type(),
+                    )
+                );
+                read_fields.push(field.with_name(physical_name));
+            }
+            let physical_schema = Arc::new(StructType::new(read_fields.clone()));
+            read_fields.push(StructField::new(
+                CHANGE_TYPE_COL_NAME,
+                DataType::STRING,
+                false,
+            ));
+            Ok(ReadPlan {
+                physical_schema,
+                cdc_physical_schema: Arc::new(StructType::new(read_fields)),
+                missing_fields,
+            })
+        })
+        .try_collect()?;
+    Ok((all_fields, read_plans))
+}
+
+/// A file of a commit to read changes from, and the type of the changes it holds
+struct ChangeFile {
+    path: String,
+    partition_values: HashMap<String, String>,
+    size: i64,
+    /// The rows of the file that are changes
+    rows: ChangeRows,
+    /// The type of change of every row of the file, or `None` for change data files, which hold
+    /// the type of each row in their `_change_type` column
+    change_type: Option<&'static str>,
+}
+
+impl TableChanges {
+    /// Get the changes made to the table at `table_root` by the commits of versions
+    /// `start_version` to `end_version` (inclusive), or to the latest version if `end_version`
+    /// is `None`. The changes are read with the schema of the table at the end version, see
+    /// [`TableChanges::with_schema`] for how changes of commits with other schemas are read.
+    ///
+    /// This fails with [`Error::ChangeDataFeedNotEnabled`] if the change data feed isn't enabled
+    /// (with the `delta.enableChangeDataFeed` table property) for every commit of the range, since
+    /// the changes of commits that update or delete rows can only be read from the change data
+    /// files the feature makes writers write. It also fails if any of the commits of the range have
+    /// been removed by log cleanup.
+    pub fn try_new(
+        table_root: Url,
+        engine: &dyn Engine,
+        start_version: Version,
+        end_version: Option<Version>,
+    ) -> DeltaResult<Self> {
+        let snapshot = Arc::new(Snapshot::try_new(table_root, engine, end_version)?);
+        Self::try_new_from_snapshot(snapshot, engine, start_version)
+    }
+
+    /// Get the changes made by the commits of versions `start_version` to the version of
+    /// `snapshot` (inclusive), see [`TableChanges::try_new`]
+    fn try_new_from_snapshot(
+        snapshot: Arc<Snapshot>,
+        engine: &dyn Engine,
+        start_version: Version,
+    ) -> DeltaResult<Self> {
+        require!(
+            start_version <= snapshot.version(),
+            Error::generic(format!(
+                "Start version {start_version} of the change feed is after its end version {}",
+                snapshot.version()
+            ))
+        );
+        let commit_files = list_commit_files(
+            engine.get_file_system_client().as_ref(),
+            &snapshot.log_segment.log_root,
+            Some(start_version),
+            snapshot.version(),
+        )?;
+
+        let metadata_history = Table::new(snapshot.table_root.clone()).metadata_history(
+            engine,
+            start_version,
+            snapshot.version(),
+        )?;
+        let mut schema_history: Vec<(Version, StructType)> = vec![];
+        for (version, metadata) in metadata_history {
+            let enabled = metadata
+                .configuration
+                .get(ENABLE_CHANGE_DATA_FEED_KEY)
+                .is_some_and(|enabled| enabled.eq_ignore_ascii_case("true"));
+            require!(enabled, Error::change_data_feed_not_enabled(version));
+            let schema = metadata.schema()?;
+            if schema_history
+                .last()
+                .is_none_or(|(_, last)| *last != schema)
+            {
+                schema_history.push((version, schema));
+            }
+        }
+        let logical_schema: SchemaRef = Arc::new(snapshot.schema().clone());
+        let (all_fields, read_plans) = plan_reads(&snapshot, &logical_schema, &schema_history)?;
+        Ok(Self {
+            snapshot,
+            start_version,
+            commit_files,
+            logical_schema,
+            schema_history,
+            read_plans,
+            all_fields,
+        })
+    }
+
+    /// Read the changes with `schema` instead of the schema of the table at the end version, e.g.
+    /// with the schema at the start version from [`Table::schema_history`]. Every column of
+    /// `schema` must be a column of the table at some version of the range.
+    ///
+    /// The schema of the table may change within the range, but the changes of every commit are
+    /// read with the same schema, so that all the batches of the stream have the same schema:
+    /// - a column that the table didn't have yet at a commit (or didn't have anymore) is null in
+    ///   the changes of that commit, and so are fields missing from struct columns
+    /// - columns the table had at a commit that aren't in the schema aren't read
+    /// - a column whose type changed within the range can't be read with one type for all
+    ///   commits, since files written before the change have values of the old type. This fails
+    ///   with an [`Error::SchemaMismatch`] for the first commit with another type, so the range
+    ///   must be read in parts instead.
+    ///
+    /// When column mapping is enabled, columns are matched across commits by their physical
+    /// names, so the changes of a renamed column are read for commits from before the rename,
+    /// whichever of its names within the range it is requested by. Otherwise they are matched by
+    /// name.
+    pub fn with_schema(self, schema: SchemaRef) -> DeltaResult<Self> {
+        let (all_fields, read_plans) = plan_reads(&self.snapshot, &schema, &self.schema_history)?;
+        Ok(Self {
+            logical_schema: schema,
+            read_plans,
+            all_fields,
+            ..self
+        })
+    }
+
+    /// Get the changes made to the table at `table_root` by the commits with timestamps (in
+    /// milliseconds since the Unix epoch) from `start_timestamp` to `end_timestamp` (inclusive), or
+    /// to the latest commit if `end_timestamp` is `None`. Commit timestamps are resolved like
+    /// [`Snapshot::timestamp`], i.e. in-commit timestamps are used where the table has them, and
+    /// the window is resolved to versions like [`Table::snapshot_at_timestamp`] resolves a
+    /// timestamp. The timestamp of the commit of each change is in its
+    /// [`COMMIT_TIMESTAMP_COL_NAME`] column.
+    ///
+    /// A window that starts before the earliest commit in the log is clamped to start at it, and
+    /// one that ends after the latest commit is clamped to end at it. This fails if the window
+    /// starts after the latest commit, ends before the earliest one, or contains no commits.
+    pub fn try_new_for_timestamps(
+        table_root: Url,
+        engine: &dyn Engine,
+        start_timestamp: i64,
+        end_timestamp: Option<i64>,
+    ) -> DeltaResult<Self> {
+        require!(
+            end_timestamp.is_none_or(|end| start_timestamp <= end),
+            Error::generic(format!(
+                "Start timestamp {start_timestamp} of the change feed is after its end timestamp {}",
+                end_timestamp.unwrap_or_default()
+            ))
+        );
+        let latest = Snapshot::try_new(table_root.clone(), engine, None)?;
+        let commit_files = list_commit_files(
+            engine.get_file_system_client().as_ref(),
+            &latest.log_segment.log_root,
+            None,
+            latest.version(),
+        )?;
+        let in_commit_timestamps_from = latest.in_commit_timestamps_enablement_version()?;
+        // the index of the first commit with a timestamp of at least `target`
+        let first_commit_from = |target: i64| {
+            first_commit_after(
+                engine,
+                &commit_files,
+                in_commit_timestamps_from,
+                target.saturating_sub(1),
+            )
+        };
+
+        let start = first_commit_from(start_timestamp)?;
+        require!(
+            start < commit_files.len(),
+            Error::generic(format!(
+                "Start timestamp {start_timestamp} of the change feed is after the latest commit"
+            ))
+        );
+        let end = match end_timestamp {
+            Some(end_timestamp) => {
+                let after_end = first_commit_from(end_timestamp.saturating_add(1))?;
+                require!(
+                    after_end > 0,
+                    Error::generic(format!(
+                        "End timestamp {end_timestamp} of the change feed is before the earliest commit"
+                    ))
+                );
+                after_end - 1
+            }
+            None => commit_files.len() - 1,
+        };
+        require!(
+            start <= end,
+            Error::generic(format!(
+                "No commits between timestamps {start_timestamp} and {}",
+                end_timestamp.unwrap_or_default()
+            ))
+        );
+        let version = |index: usize| {
+            version_from_location(&commit_files[index].location).ok_or(Error::MissingVersion)
+        };
+        let snapshot = match version(end)? {
+            end_version if end_version == latest.version() => latest,
+            end_version => Snapshot::try_new(table_root, engine, Some(end_version))?,
+        };
+        Self::try_new_from_snapshot(Arc::new(snapshot), engine, version(start)?)
+    }
+
+    /// The version of the first commit whose changes are read
+    pub fn start_version(&self) -> Version {
+        self.start_version
+    }
+
+    /// The version of the last commit whose changes are read
+    pub fn end_version(&self) -> Version {
+        self.snapshot.version()
+    }
+
+    /// The schema of the changes: the schema of the table at the end version (or the one set with
+    /// [`TableChanges::with_schema`]), followed by the [`CHANGE_TYPE_COL_NAME`], [`COMMIT_VERSION_COL_NAME`] and [`COMMIT_TIMESTAMP_COL_NAME`]
+    /// columns.
+    pub fn schema(&self) -> SchemaRef {
+        let cdf_fields = [
+            StructField::new(CHANGE_TYPE_COL_NAME, DataType::STRING, false),
+            StructField::new(COMMIT_VERSION_COL_NAME, DataType::LONG, false),
+            StructField::new(COMMIT_TIMESTAMP_COL_NAME, DataType::TIMESTAMP, false),
+        ];
+        let fields = self.logical_schema.fields().cloned().chain(cdf_fields);
+        Arc::new(StructType::new(fields.collect()))
+    }
+
+    /// Read the changes as a lazy stream of batches with the [`schema`](Self::schema) of the
+    /// changes. Commits are read one at a time in order of version, and the files of a commit are
+    /// read only as the stream reaches them, so the memory needed doesn't depend on the size of
+    /// the range.
+    ///
+    /// The changes of a commit are read from its change data (`cdc`) files if it has any.
+    /// Otherwise the rows of the files it added are inserts and the rows of the files it removed
+    /// are deletes, where only files with `dataChange` set are considered. A file that the commit
+    /// removed and added back with another deletion vector (e.g. to delete rows without rewriting
+    /// the file) only has the rows the new deletion vector deletes as deletes. As for a scan, rows
+    /// whose [`ScanResult::mask`] is false must be ignored.
+    pub fn execute_stream<'a>(
+        &'a self,
+        engine: &'a dyn Engine,
+    ) -> impl Iterator<Item = DeltaResult<ScanResult>> + 'a {
+        self.commit_files.iter().flat_map(move |commit_file| {
+            match self.read_commit_changes(engine, commit_file) {
+                Ok(results) => results,
+                Err(err) => Box::new(std::iter::once(Err(err))),
+            }
+        })
+    }
+
+    /// Read the actions of the commit in `commit_file`, and get the stream of its changes
+    fn read_commit_changes<'a>(
+        &'a self,
+        engine: &'a dyn Engine,
+        commit_file: &FileMeta,
+    ) -> DeltaResult<Box<dyn Iterator<Item = DeltaResult<ScanResult>> + 'a>> {
+        let version = crate::path::version_from_location(&commit_file.location)
+            .ok_or(Error::MissingVersion)?;
+        let timestamp = read_commit_timestamp(
+            engine,
+            commit_file,
+            self.snapshot.in_commit_timestamps_enablement_version()?,
+        )?;
+        let files = self.read_change_files(engine, commit_file)?;
+        debug!("Reading {} change files of commit {version}", files.len());
+        // the read plan of the last schema change at or before this commit
+        let schema_index = self
+            .schema_history
+            .partition_point(|(schema_version, _)| *schema_version <= version);
+        let read_plan = &self.read_plans[schema_index.saturating_sub(1)];
+
+        let cdf_values = [
+            Expression::literal(version as i64),
+            // commit timestamps are in milliseconds, but timestamps in microseconds
+            Expression::literal(Scalar::Timestamp(timestamp * 1000)),
+        ];
+        let output_type: DataType = self.schema().as_ref().clone().into();
+        let results = files.into_iter().flat_map(move |file| {
+            let results =
+                self.read_change_file(engine, &file, read_plan, &cdf_values, &output_type);
+            match results {
+                Ok(results) => results,
+                Err(err) => Box::new(std::iter::once(Err(err))),
+            }
+        });
+        Ok(Box::new(results))
+    }
+
+    /// Get the files to read the changes of the commit in `commit_file` from
+    fn read_change_files(
+        &self,
+        engine: &dyn Engine,
+        commit_file: &FileMeta,
+    ) -> DeltaResult<Vec<ChangeFile>> {
+        let read_schema =
+            get_log_schema_for(&[ActionType::Add, ActionType::Remove, ActionType::Cdc])?;
+        let batches = engine.get_json_handler().read_json_files(
+            std::slice::from_ref(commit_file),
+            read_schema,
+            None,
+        )?;
+        let mut add_visitor = AddVisitor::default();
+        let mut remove_visitor = RemoveVisitor::default();
+        let mut cdc_visitor = CdcVisitor::default();
+        for batch in batches {
+            let batch = batch?;
+            batch.extract(get_log_schema_for(&[ActionType::Add])?, &mut add_visitor)?;
+            batch.extract(
+                get_log_schema_for(&[ActionType::Remove])?,
+                &mut remove_visitor,
+            )?;
+            batch.extract(get_log_schema_for(&[ActionType::Cdc])?, &mut cdc_visitor)?;
+        }
+
+        // change data files take precedence over the data files of the commit
+        if !cdc_visitor.cdcs.is_empty() {
+            let files = cdc_visitor.cdcs.into_iter().map(|cdc| ChangeFile {
+                path: cdc.path,
+                partition_values: cdc.partition_values,
+                size: cdc.size,
+                rows: ChangeRows::Live(None),
+                change_type: None,
+            });
+            return Ok(files.collect());
+        }
+        let mut removes: Vec<_> = remove_visitor
+            .removes
+            .into_iter()
+            .filter(|remove| remove.data_change)
+            .collect();
+        let mut adds = vec![];
+        for add in add_visitor.adds.into_iter().filter(|add| add.data_change) {
+            let file = |rows, change_type| ChangeFile {
+                path: add.path.clone(),
+                partition_values: add.partition_values.clone(),
+                size: add.size,
+                rows,
+                change_type: Some(change_type),
+            };
+            let Some(index) = removes.iter().position(|remove| remove.path == add.path) else {
+                adds.push(file(
+                    ChangeRows::Live(add.deletion_vector.clone()),
+                    "insert",
+                ));
+                continue;
+            };
+            // the file is added back with a new deletion vector, so only the rows that the
+            // deletion vectors disagree on have changed
+            let remove = removes.remove(index);
+            if let Some(deletion_vector) = add.deletion_vector.clone() {
+                let rows = ChangeRows::DeletedOnlyBy {
+                    deletion_vector,
+                    other: remove.deletion_vector.clone(),
+                };
+                adds.push(file(rows, "delete"));
+            }
+            if let Some(deletion_vector) = remove.deletion_vector {
+                // rows that were deleted before, but are restored by the new deletion vector
+                let rows = ChangeRows::DeletedOnlyBy {
+                    deletion_vector,
+                    other: add.deletion_vector.clone(),
+                };
+                adds.push(file(rows, "insert"));
+            }
+        }
+        let removes = removes.into_iter().map(|remove| ChangeFile {
+            path: remove.path,
+            partition_values: remove.partition_values.unwrap_or_default(),
+            // the size of a removed file is optional, and is only used as a hint by engines
+            size: remove.size.unwrap_or_default(),
+            rows: ChangeRows::Live(remove.deletion_vector),
+            change_type: Some("delete"),
+        });
+        Ok(removes.chain(adds).collect())
+    }
+
+    /// Get the stream of the changes in `file`, read with the `read_plan` of its commit, with the
+    /// `cdf_values` of its commit (the version and timestamp) appended to each row
+    fn read_change_file<'a>(
+        &'a self,
+        engine: &'a dyn Engine,
+        file: &ChangeFile,
+        read_plan: &ReadPlan,
+        cdf_values: &[Expression],
+        output_type: &DataType,
+    ) -> DeltaResult<Box<dyn Iterator<Item = DeltaResult<ScanResult>> + 'a>> {
+        let table_root = &self.snapshot.table_root;
+        let meta = FileMeta {
+            location: table_root.join(&file.path)?,
+            last_modified: 0,
+            size: file.size as usize,
+            e_tag: None,
+            version: None,
+        };
+        let (read_schema, change_type) = match file.change_type {
+            Some(change_type) => (&read_plan.physical_schema, Expression::literal(change_type)),
+            None => (
+                &read_plan.cdc_physical_schema,
+                Expression::column(CHANGE_TYPE_COL_NAME),
+            ),
+        };
+        let Expression::Struct(mut fields) = transform_expression(
+            &self.logical_schema,
+            &self.all_fields,
+            &file.partition_values,
+        )?
+        else {
+            return Err(Error::generic("Expected a struct transform expression"));
+        };
+        for &index in &read_plan.missing_fields {
+            let (_, field) = self.logical_schema.fields.get_index(index).unwrap();
+            fields[index] = Expression::literal(Scalar::Null(field.data_type().clone()));
+        }
+        fields.push(change_type);
+        fields.extend(cdf_values.iter().cloned());
+        let evaluator = engine.get_expression_handler().get_evaluator(
+            read_schema.clone(),
+            Expression::Struct(fields),
+            output_type.clone(),
+        )?;
+
+        let fs_client = engine.get_file_system_client();
+        // whether the rows past the end of the mask are changes
+        let (mut dv_mask, rows_past_mask_selected) = match &file.rows {
+            ChangeRows::Live(deletion_vector) => {
+                let mask = deletion_vector
+                    .as_ref()
+                    .map(|dv| dv.read(fs_client, table_root))
+                    .transpose()?
+                    .map(treemap_to_bools);
+                (mask, true)
+            }
+            ChangeRows::DeletedOnlyBy {
+                deletion_vector,
+                other,
+            } => {
+                let mut deleted = deletion_vector.read(fs_client.clone(), table_root)?;
+                if let Some(other) = other {
+                    deleted -= other.read(fs_client, table_root)?;
+                }
+                // select the deleted rows, rather than the ones that aren't deleted
+                let mask = treemap_to_bools(deleted).into_iter().map(|row| !row);
+                (Some(mask.collect()), false)
+            }
+        };
+        let batches =
+            engine
+                .get_parquet_handler()
+                .read_parquet_files(&[meta], read_schema.clone(), None)?;
+        let results = batches.map(move |batch| {
+            let batch = batch?;
+            // split off the part of the mask that covers this batch. The mask may be shorter than
+            // the file, in which case its remaining rows are all selected
+            let rest = dv_mask
+                .as_mut()
+                .map(|mask| mask.split_off(batch.length().min(mask.len())));
+            let mut mask = std::mem::replace(&mut dv_mask, rest);
+            if let (Some(mask), false) = (mask.as_mut(), rows_past_mask_selected) {
+                mask.resize(batch.length(), false);
+            }
+            Ok(ScanResult {
+                raw_data: evaluator.evaluate(batch.as_ref() as &dyn EngineData),
+                mask,
+            })
+        });
+        Ok(Box::new(results))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use arrow_array::cast::AsArray;
+    use arrow_array::types::{Int32Type, Int64Type, TimestampMicrosecondType};
+    use arrow_array::Array;
+    use arrow_array::{ArrayRef, Int64Array, RecordBatch, StringArray};
+    use arrow_select::filter::filter_record_batch;
+    use itertools::Itertools;
+
+    use super::*;
+    use crate::engine::arrow_data::ArrowEngineData;
+    use crate::engine::sync::SyncEngine;
+    use crate::test_utils::{
+        add_action, metadata_action, protocol_action, write_parquet, TestTable,
+    };
+
+    /// Read all the changes, as (id, change type, version) tuples sorted within each commit
+    fn read_changes(
+        table_changes: &TableChanges,
+        engine: &dyn Engine,
+    ) -> Vec<(Option<i64>, String, i64)> {
+        let mut changes = vec![];
+        for result in table_changes.execute_stream(engine) {
+            let result = result.unwrap();
+            let batch: RecordBatch =
+                ArrowEngineData::try_from_engine_data(result.raw_data.unwrap())
+                    .unwrap()
+                    .into();
+            let batch = match result.mask {
+                Some(mask) => {
+                    let mut mask = mask;
+                    mask.resize(batch.num_rows(), true);
+                    filter_record_batch(&batch, &mask.into()).unwrap()
+                }
+                None => batch,
+            };
+            let ids: Vec<_> = match batch.column(0).as_primitive_opt::<Int64Type>() {
+                Some(ids) => ids.iter().collect(),
+                None => batch
+                    .column(0)
+                    .as_primitive::<Int32Type>()
+                    .iter()
+                    .map(|id| id.map(i64::from))
+                    .collect(),
+            };
+            let change_types = batch.column_by_name(CHANGE_TYPE_COL_NAME).unwrap();
+            let versions = batch.column_by_name(COMMIT_VERSION_COL_NAME).unwrap();
+            let timestamps = batch.column_by_name(COMMIT_TIMESTAMP_COL_NAME).unwrap();
+            assert_eq!(
+                timestamps
+                    .as_primitive::<TimestampMicrosecondType>()
+                    .null_count(),
+                0
+            );
+            for (row, id) in ids.into_iter().enumerate() {
+                changes.push((
+                    id,
+                    change_types.as_string::<i32>().value(row).to_string(),
+                    versions.as_primitive::<Int64Type>().value(row),
+                ));
+            }
+        }
+        changes.sort_by_key(|(id, change_type, version)| (*version, change_type.clone(), *id));
+        changes
+    }
+
+    #[test]
+    fn test_table_changes_from_cdc_and_data_files() {
+        let table = TestTable::new();
+        let root = table.path();
+        let ids = |ids: Vec<i64>| Arc::new(Int64Array::from(ids)) as ArrayRef;
+        let a_size = write_parquet(&root.join("a.parquet"), vec![("id", ids(vec![1, 2, 3]))]);
+        let b_size = write_parquet(&root.join("b.parquet"), vec![("id", ids(vec![4]))]);
+        let c_size = write_parquet(&root.join("c.parquet"), vec![("id", ids(vec![40, 4]))]);
+        let d_size = write_parquet(
+            &root.join("d.parquet"),
+            vec![
+                ("id", ids(vec![4, 40])),
+                (
+                    CHANGE_TYPE_COL_NAME,
+                    Arc::new(StringArray::from(vec![
+                        "update_preimage",
+                        "update_postimage",
+                    ])),
+                ),
+            ],
+        );
+
+        let schema = StructType::new(vec![
+            StructField::new("id", DataType::LONG, true),
+            StructField::new("part", DataType::STRING, true),
+        ]);
+        let file = |path: &str, size: usize, data_change: bool| {
+            serde_json::json!({
+                "path": path,
+                "partitionValues": {"part": "x"},
+                "size": size,
+                "modificationTime": 0,
+                "deletionTimestamp": 0,
+                "dataChange": data_change,
+            })
+        };
+        let cdf_enabled = [("delta.enableChangeDataFeed", "true")];
+        table
+            .commit(
+                0,
+                [
+                    protocol_action(1, 4),
+                    metadata_action(&schema, &["part"], &cdf_enabled),
+                    serde_json::json!({"add": file("a.parquet", a_size, true)}),
+                ],
+            )
+            .commit(
+                1,
+                [
+                    serde_json::json!({"remove": file("a.parquet", a_size, true)}),
+                    serde_json::json!({"add": file("b.parquet", b_size, true)}),
+                ],
+            )
+            // an update: the changes are read from the cdc file instead of the data files
+            .commit(
+                2,
+                [
+                    serde_json::json!({"remove": file("b.parquet", b_size, true)}),
+                    serde_json::json!({"add": file("c.parquet", c_size, true)}),
+                    serde_json::json!({"cdc": file("d.parquet", d_size, false)}),
+                ],
+            )
+            // compaction doesn't change the data
+            .commit(
+                3,
+                [
+                    serde_json::json!({"remove": file("c.parquet", c_size, false)}),
+                    serde_json::json!({"add": file("c.parquet", c_size, false)}),
+                ],
+            );
+
+        let url = table.location();
+        let engine = SyncEngine::new();
+        let table_changes = TableChanges::try_new(url.clone(), &engine, 0, None).unwrap();
+        assert_eq!(table_changes.end_version(), 3);
+        let schema = table_changes.schema();
+        let names: Vec<_> = schema.fields().map(|field| field.name().as_str()).collect();
+        assert_eq!(
+            names,
+            [
+                "id",
+                "part",
+                "_change_type",
+                "_commit_version",
+                "_commit_timestamp"
+            ]
+        );
+
+        let changes = read_changes(&table_changes, &engine);
+        let expected = [
+            (Some(1), "insert", 0),
+            (Some(2), "insert", 0),
+            (Some(3), "insert", 0),
+            (Some(1), "delete", 1),
+            (Some(2), "delete", 1),
+            (Some(3), "delete", 1),
+            (Some(4), "insert", 1),
+            (Some(40), "update_postimage", 2),
+            (Some(4), "update_preimage", 2),
+        ]
+        .map(|(id, change_type, version)| (id, change_type.to_string(), version));
+        assert_eq!(changes, expected);
+
+        let table_changes = TableChanges::try_new(url.clone(), &engine, 1, Some(1)).unwrap();
+        assert_eq!(read_changes(&table_changes, &engine), expected[3..7]);
+        assert!(TableChanges::try_new(url, &engine, 2, Some(1)).is_err());
+    }
+
+    #[test]
+    fn test_table_changes_with_schema_evolution() {
+        let test_table = TestTable::new();
+        let root = test_table.path();
+        let ids = |ids: Vec<i64>| Arc::new(Int64Array::from(ids)) as ArrayRef;
+        let strings = |values: Vec<&str>| Arc::new(StringArray::from(values)) as ArrayRef;
+        let a_size = write_parquet(
+            &root.join("a.parquet"),
+            vec![("id", ids(vec![1, 2])), ("old", strings(vec!["a", "b"]))],
+        );
+        let b_size = write_parquet(
+            &root.join("b.parquet"),
+            vec![("id", ids(vec![3])), ("new", strings(vec!["c"]))],
+        );
+
+        let metadata = |fields: &[(&str, DataType)]| {
+            let fields = fields
+                .iter()
+                .map(|(name, data_type)| StructField::new(*name, data_type.clone(), true));
+            let schema = StructType::new(fields.collect());
+            metadata_action(&schema, &[], &[("delta.enableChangeDataFeed", "true")])
+        };
+        test_table
+            .commit(
+                0,
+                [
+                    protocol_action(1, 4),
+                    metadata(&[("id", DataType::LONG), ("old", DataType::STRING)]),
+                    add_action("a.parquet", a_size),
+                ],
+            )
+            // drops the `old` column and adds the `new` one
+            .commit(
+                1,
+                [
+                    metadata(&[("id", DataType::LONG), ("new", DataType::STRING)]),
+                    add_action("b.parquet", b_size),
+                ],
+            )
+            // changes the type of the `id` column
+            .commit(
+                2,
+                [metadata(&[
+                    ("id", DataType::INTEGER),
+                    ("new", DataType::STRING),
+                ])],
+            );
+        let url = test_table.location();
+        let engine = SyncEngine::new();
+
+        // the values of the given column of every batch, which must all have the same schema
+        let read_column = |table_changes: &TableChanges, name: &str| {
+            let mut schema = None;
+            let mut values = vec![];
+            for result in table_changes.execute_stream(&engine) {
+                let batch: RecordBatch =
+                    ArrowEngineData::try_from_engine_data(result.unwrap().raw_data.unwrap())
+                        .unwrap()
+                        .into();
+                let types: Vec<_> = batch
+                    .schema()
+                    .fields()
+                    .iter()
+                    .map(|field| (field.name().clone(), field.data_type().clone()))
+                    .collect();
+                assert_eq!(types.len(), table_changes.schema().fields().count());
+                assert_eq!(&types, schema.get_or_insert(types.clone()));
+                let column = batch.column_by_name(name).unwrap().as_string::<i32>();
+                values.extend(column.iter().map(|value| value.map(str::to_string)));
+            }
+            values
+        };
+
+        // the files written before `new` was added have no values for it
+        let table_changes = TableChanges::try_new(url.clone(), &engine, 0, Some(1)).unwrap();
+        let names: Vec<_> = table_changes
+            .schema()
+            .fields()
+            .map(|field| field.name().clone())
+            .collect();
+        assert_eq!(names[..2], ["id", "new"]);
+        let expected = [None, None, Some("c".to_string())];
+        assert_eq!(read_column(&table_changes, "new"), expected);
+
+        // reading with the schema at the start version reads the dropped column instead
+        let table = Table::new(url.clone());
+        let (_, start_schema) = table.schema_history(&engine, 0..=0).unwrap().remove(0);
+        let table_changes = table_changes.with_schema(Arc::new(start_schema)).unwrap();
+        let expected = [Some("a".to_string()), Some("b".to_string()), None];
+        assert_eq!(read_column(&table_changes, "old"), expected);
+
+        // columns that the table never had can't be read
+        let unknown = StructType::new(vec![StructField::new("unknown", DataType::STRING, true)]);
+        let table_changes = TableChanges::try_new(url.clone(), &engine, 0, Some(1)).unwrap();
+        assert!(matches!(
+            table_changes.with_schema(Arc::new(unknown)),
+            Err(Error::MissingColumn(_))
+        ));
+
+        // the files written before the type change have values of the old type
+        assert!(matches!(
+            TableChanges::try_new(url.clone(), &engine, 0, None),
+            Err(Error::SchemaMismatch { column, .. }) if column == "id"
+        ));
+        assert!(TableChanges::try_new(url, &engine, 2, None).is_ok());
+    }
+
+    #[test]
+    fn test_table_changes_with_renamed_column() {
+        let test_table = TestTable::new();
+        let root = test_table.path();
+        let ids = |ids: Vec<i64>| Arc::new(Int64Array::from(ids)) as ArrayRef;
+        let strings = |values: Vec<&str>| Arc::new(StringArray::from(values)) as ArrayRef;
+        let a_size = write_parquet(
+            &root.join("a.parquet"),
+            vec![("col-1", ids(vec![1])), ("col-2", strings(vec!["a"]))],
+        );
+        let b_size = write_parquet(
+            &root.join("b.parquet"),
+            vec![("col-1", ids(vec![2])), ("col-2", strings(vec!["b"]))],
+        );
+
+        let field = |name: &str, id: i64, data_type: &str| {
+            serde_json::json!({
+                "name": name,
+                "type": data_type,
+                "nullable": true,
+                "metadata": {
+                    "delta.columnMapping.id": id,
+                    "delta.columnMapping.physicalName": format!("col-{id}")
+                }
+            })
+        };
+        let metadata = |name: &str| {
+            let schema = serde_json::json!({
+                "type": "struct",
+                "fields": [field("id", 1, "long"), field(name, 2, "string")]
+            });
+            let configuration = [
+                ("delta.enableChangeDataFeed", "true"),
+                ("delta.columnMapping.mode", "name"),
+            ];
+            metadata_action(&schema, &[], &configuration)
+        };
+        test_table
+            .commit(
+                0,
+                [
+                    protocol_action(2, 5),
+                    metadata("old"),
+                    add_action("a.parquet", a_size),
+                ],
+            )
+            // renames the `old` column to `new`
+            .commit(1, [metadata("new"), add_action("b.parquet", b_size)]);
+        let url = test_table.location();
+        let engine = SyncEngine::new();
+
+        let read_column = |table_changes: &TableChanges, name: &str| {
+            let mut values = vec![];
+            for result in table_changes.execute_stream(&engine) {
+                let batch: RecordBatch =
+                    ArrowEngineData::try_from_engine_data(result.unwrap().raw_data.unwrap())
+                        .unwrap()
+                        .into();
+                let column = batch.column_by_name(name).unwrap().as_string::<i32>();
+                values.extend(column.iter().map(|value| value.map(str::to_string)));
+            }
+            values
+        };
+        let expected = [Some("a".to_string()), Some("b".to_string())];
+
+        let table_changes = TableChanges::try_new(url.clone(), &engine, 0, None).unwrap();
+        assert_eq!(read_column(&table_changes, "new"), expected);
+
+        // the column is read by its physical name whichever of its names it is requested by, even
+        // without the column mapping metadata of the table
+        for name in ["old", "new"] {
+            let schema = StructType::new(vec![
+                StructField::new("id", DataType::LONG, true),
+                StructField::new(name, DataType::STRING, true),
+            ]);
+            let table_changes = TableChanges::try_new(url.clone(), &engine, 0, None)
+                .unwrap()
+                .with_schema(Arc::new(schema))
+                .unwrap();
+            assert_eq!(read_column(&table_changes, name), expected);
+        }
+    }
+
+    #[test]
+    fn test_table_changes_for_timestamps() {
+        let schema = StructType::new(vec![StructField::new("id", DataType::LONG, true)]);
+        let configuration = [
+            ("delta.enableChangeDataFeed", "true"),
+            ("delta.enableInCommitTimestamps", "true"),
+        ];
+        let commit_info = |timestamp: i64| serde_json::json!({"commitInfo": {"inCommitTimestamp": timestamp, "timestamp": 0}});
+        let table = TestTable::new();
+        table
+            .commit(
+                0,
+                [
+                    commit_info(1000),
+                    serde_json::json!({"protocol": {
+                        "minReaderVersion": 1,
+                        "minWriterVersion": 7,
+                        "writerFeatures": ["inCommitTimestamp"]
+                    }}),
+                    metadata_action(&schema, &[], &configuration),
+                ],
+            )
+            .commit(1, [commit_info(2000)])
+            .commit(2, [commit_info(3000)]);
+        let url = table.location();
+        let engine = SyncEngine::new();
+
+        let versions = |start_timestamp, end_timestamp| {
+            TableChanges::try_new_for_timestamps(
+                url.clone(),
+                &engine,
+                start_timestamp,
+                end_timestamp,
+            )
+            .map(|changes| (changes.start_version(), changes.end_version()))
+        };
+        assert_eq!(versions(1500, Some(2500)).unwrap(), (1, 1));
+        assert_eq!(versions(2000, Some(3000)).unwrap(), (1, 2));
+        assert_eq!(versions(1000, Some(1000)).unwrap(), (0, 0));
+        // windows reaching past the ends of the log are clamped
+        assert_eq!(versions(0, None).unwrap(), (0, 2));
+        assert_eq!(versions(0, Some(5000)).unwrap(), (0, 2));
+        // windows without commits fail
+        assert!(versions(3500, None).is_err());
+        assert!(versions(0, Some(500)).is_err());
+        assert!(versions(1200, Some(1800)).is_err());
+        assert!(versions(2000, Some(1000)).is_err());
+    }
+
+    #[test]
+    fn test_table_changes_with_deletion_vector() {
+        let path =
+            std::fs::canonicalize(PathBuf::from("./tests/data/table-with-dv-small/")).unwrap();
+        let engine = SyncEngine::new();
+        let table = Table::new(Url::from_directory_path(&path).unwrap());
+        assert!(matches!(
+            table.table_changes(&engine, 0, None),
+            Err(Error::ChangeDataFeedNotEnabled { version: 0 })
+        ));
+
+        // the same table with the change data feed enabled
+        let test_table = TestTable::new();
+        let root = test_table.path();
+        for entry in std::fs::read_dir(&path).unwrap() {
+            let entry = entry.unwrap();
+            if entry.file_type().unwrap().is_file() {
+                std::fs::copy(entry.path(), root.join(entry.file_name())).unwrap();
+            }
+        }
+        for version in 0..2 {
+            let commit = format!("_delta_log/{version:020}.json");
+            let actions = std::fs::read_to_string(path.join(&commit)).unwrap();
+            let actions = actions.replace(
+                r#""configuration":{"#,
+                r#""configuration":{"delta.enableChangeDataFeed":"true","#,
+            );
+            std::fs::write(root.join(&commit), actions).unwrap();
+        }
+        let table = Table::new(test_table.location());
+        let table_changes = table.table_changes(&engine, 0, None).unwrap();
+        let changes = read_changes(&table_changes, &engine);
+        let counts = changes
+            .iter()
+            .counts_by(|(_, change_type, version)| (change_type.clone(), *version));
+        // the second commit deletes rows by removing the file and adding it back with a deletion
+        // vector, so only the rows the deletion vector deletes have changed
+        assert_eq!(counts.len(), 2);
+        assert_eq!(counts[&("insert".to_string(), 0)], 10);
+        assert_eq!(counts[&("delete".to_string(), 1)], 2);
+        let deleted: Vec<_> = changes
+            .iter()
+            .filter(|(_, change_type, _)| change_type == "delete")
+            .map(|(id, _, _)| *id)
+            .collect();
+        assert_eq!(deleted, [Some(0), Some(9)]);
+    }
+}
@@ -0,0 +1,119 @@
+//! Fixtures shared by the unit tests of the crate
+use std::path::Path;
+
+use arrow_array::{ArrayRef, RecordBatch};
+use parquet::arrow::ArrowWriter;
+use parquet::file::properties::WriterProperties;
+use serde::Serialize;
+use tempfile::TempDir;
+use url::Url;
+
+use crate::schema::{DataType, StructField, StructType};
+use crate::Version;
+
+/// The schema of a table with a single nullable integer column `value`
+pub(crate) fn value_schema() -> StructType {
+    StructType::new(vec![StructField::new("value", DataType::INTEGER, true)])
+}
+
+/// A `protocol` action with the minimum reader and writer versions, and no features
+pub(crate) fn protocol_action(
+    min_reader_version: i32,
+    min_writer_version: i32,
+) -> serde_json::Value {
+    serde_json::json!({"protocol": {
+        "minReaderVersion": min_reader_version,
+        "minWriterVersion": min_writer_version
+    }})
+}
+
+/// A `metaData` action of a table with `schema` (a [`crate::schema::StructType`], or its JSON),
+/// partitioned by `partition_columns` and with the table properties `configuration`
+pub(crate) fn metadata_action(
+    schema: &impl Serialize,
+    partition_columns: &[&str],
+    configuration: &[(&str, &str)],
+) -> serde_json::Value {
+    let configuration: serde_json::Map<_, _> = configuration
+        .iter()
+        .map(|(key, value)| (key.to_string(), serde_json::Value::from(*value)))
+        .collect();
+    serde_json::json!({"metaData": {
+        "id": "testId",
+        "format": {"provider": "parquet", "options": {}},
+        "schemaString": serde_json::to_string(schema).unwrap(),
+        "partitionColumns": partition_columns,
+        "configuration": configuration,
+        "createdTime": 1000
+    }})
+}
+
+/// An `add` action of the data file at `path` (relative to the table root) with no partition
+/// values or statistics
+pub(crate) fn add_action(path: &str, size: usize) -> serde_json::Value {
+    serde_json::json!({"add": {
+        "path": path,
+        "partitionValues": {},
+        "size": size,
+        "modificationTime": 1000,
+        "dataChange": true
+    }})
+}
+
+/// The content of a parquet file with the rows of `batch`, written with `props`, e.g. to split the
+/// rows into row groups
+pub(crate) fn parquet_bytes(batch: &RecordBatch, props: Option<WriterProperties>) -> Vec<u8> {
+    let mut data = vec![];
+    let mut writer = ArrowWriter::try_new(&mut data, batch.schema(), props).unwrap();
+    writer.write(batch).unwrap();
+    writer.close().unwrap();
+    data
+}
+
+/// Write a parquet file with the `columns` at `path`, and return its size
+pub(crate) fn write_parquet(path: &Path, columns: Vec<(&str, ArrayRef)>) -> usize {
+    let batch = RecordBatch::try_from_iter(columns).unwrap();
+    let data = parquet_bytes(&batch, None);
+    std::fs::write(path, &data).unwrap();
+    data.len()
+}
+
+/// A table in a temporary directory, which is deleted when this is dropped. Its commits are
+/// written with [`Self::commit`].
+pub(crate) struct TestTable {
+    dir: TempDir,
+}
+
+impl TestTable {
+    /// Create an empty table, with an empty `_delta_log` directory
+    pub(crate) fn new() -> Self {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("_delta_log")).unwrap();
+        Self { dir }
+    }
+
+    /// The directory of the table
+    pub(crate) fn path(&self) -> &Path {
+        self.dir.path()
+    }
+
+    /// The location of the table root
+    pub(crate) fn location(&self) -> Url {
+        Url::from_directory_path(self.path()).unwrap()
+    }
+
+    /// Write the commit file of `version`, with one of `actions` per line
+    pub(crate) fn commit(
+        &self,
+        version: Version,
+        actions: impl IntoIterator<Item = impl ToString>,
+    ) -> &Self {
+        let commit: Vec<_> = actions
+            .into_iter()
+            .map(|action| action.to_string())
+            .collect();
+        let commit_path = self.path().join(format!("_delta_log/{version:020}.json"));
+        std::fs::write(commit_path, commit.join("\n")).unwrap();
+        self
+    }
+}
@@ -0,0 +1,149 @@
+//! Functionality to list the tombstones (`remove` actions) of a table, e.g. for vacuum tools that
+//! need to decide which removed data files are safe to physically delete.
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use crate::actions::visitors::{AddVisitor, RemoveVisitor};
+use crate::actions::{get_log_schema_for, ActionType, Add, Remove};
+use crate::snapshot::Snapshot;
+use crate::{DeltaResult, Engine};
+
+/// Lists the tombstones of a [`Snapshot`]: the `remove` actions of data files that are no longer
+/// part of the table, with their full details (size, partition values, deletion timestamp, ...).
+/// Tombstones are only kept in the log for the table's tombstone retention period, after which a
+/// data file may be physically deleted; [`Self::expired_tombstones`] lists those whose retention
+/// period has passed.
+pub struct TombstoneScanner {
+    snapshot: Arc<Snapshot>,
+}
+
+impl TombstoneScanner {
+    /// Create a new [`TombstoneScanner`] for the tombstones of `snapshot`
+    pub fn new(snapshot: Arc<Snapshot>) -> Self {
+        TombstoneScanner { snapshot }
+    }
+
+    /// Scan the Delta Log for the tombstones of the table at the snapshot's version. A tombstone is
+    /// the latest `remove` action for a data file (and deletion vector) that was not re-added
+    /// afterwards. Tombstones for data files that are still referenced by an active file of the
+    /// table (e.g. because only their deletion vector changed) are excluded, since those data
+    /// files must not be deleted.
+    ///
+    /// This does not affect which files are active in the table.
+    pub fn tombstones(&self, engine: &dyn Engine) -> DeltaResult<Vec<Remove>> {
+        let (active_files, mut tombstones) = active_files_and_tombstones(&self.snapshot, engine)?;
+        let active_paths: HashSet<_> = active_files.into_iter().map(|add| add.path).collect();
+        tombstones.retain(|remove| !active_paths.contains(&remove.path));
+        Ok(tombstones)
+    }
+
+    /// Scan the Delta Log for the tombstones of the table (see [`Self::tombstones`]) that were
+    /// removed before `cutoff_timestamp` (in milliseconds since the epoch), i.e. those whose
+    /// retention period has passed if `cutoff_timestamp` is the current time minus the retention
+    /// period. Tombstones without a deletion timestamp are always included.
+    pub fn expired_tombstones(
+        &self,
+        engine: &dyn Engine,
+        cutoff_timestamp: i64,
+    ) -> DeltaResult<Vec<Remove>> {
+        let mut tombstones = self.tombstones(engine)?;
+        tombstones.retain(|remove| remove.deletion_timestamp.unwrap_or(0) < cutoff_timestamp);
+        Ok(tombstones)
+    }
+}
+
+/// Replay the Delta Log of `snapshot` for its active files and the latest `remove` action of each
+/// data file and deletion vector that wasn't re-added afterwards. Unlike
+/// [`TombstoneScanner::tombstones`], this includes the removes of data files that are still
+/// active, since the deletion vectors they reference may still be within their retention period.
+pub(crate) fn active_files_and_tombstones(
+    snapshot: &Snapshot,
+    engine: &dyn Engine,
+) -> DeltaResult<(Vec<Add>, Vec<Remove>)> {
+    let add_schema = get_log_schema_for(&[ActionType::Add])?;
+    let remove_schema = get_log_schema_for(&[ActionType::Remove])?;
+    // checkpoints keep unexpired tombstones, so read removes from them as well
+    let read_schema = get_log_schema_for(&[ActionType::Add, ActionType::Remove])?;
+    let iter = snapshot
+        .log_segment
+        .replay(engine, read_schema.clone(), read_schema, None)?;
+
+    // (path, dv_unique_id) pairs that already have a newer action
+    let mut seen = HashSet::new();
+    let mut active_files = vec![];
+    let mut tombstones = vec![];
+    for maybe_data in iter {
+        let (actions, _) = maybe_data?;
+        let mut add_visitor = AddVisitor::default();
+        actions.extract(add_schema.clone(), &mut add_visitor)?;
+        let mut remove_visitor = RemoveVisitor::default();
+        actions.extract(remove_schema.clone(), &mut remove_visitor)?;
+
+        // a commit can't both add and remove the same file, so the order within a batch doesn't
+        // matter
+        for add in add_visitor.adds {
+            if seen.insert((add.path.clone(), add.dv_unique_id())) {
+                active_files.push(add);
+            }
+        }
+        for remove in remove_visitor.removes {
+            if seen.insert((remove.path.clone(), remove.dv_unique_id())) {
+                tombstones.push(remove);
+            }
+        }
+    }
+    Ok((active_files, tombstones))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::engine::sync::SyncEngine;
+    use crate::Table;
+
+    fn get_tombstone_scanner(path: &str) -> TombstoneScanner {
+        let path = std::fs::canonicalize(PathBuf::from(path)).unwrap();
+        let url = url::Url::from_directory_path(path).unwrap();
+        let engine = SyncEngine::new();
+        let snapshot = Table::new(url).snapshot(&engine, None).unwrap();
+        TombstoneScanner::new(snapshot.into())
+    }
+
+    #[test]
+    fn test_tombstones() {
+        let engine = SyncEngine::new();
+        let scanner = get_tombstone_scanner("./tests/data/with_checkpoint_no_last_checkpoint/");
+        let mut tombstones = scanner.tombstones(&engine).unwrap();
+        tombstones.sort_by_key(|remove| remove.deletion_timestamp);
+        let paths: Vec<_> = tombstones
+            .iter()
+            .map(|remove| remove.path.as_str())
+            .collect();
+        assert_eq!(
+            paths,
+            [
+                "part-00000-ad1a4bb7-07e8-4f40-b50b-49910d209e0c-c000.snappy.parquet",
+                "part-00000-a190be9e-e3df-439e-b366-06a863f51e99-c000.snappy.parquet",
+            ]
+        );
+        assert_eq!(tombstones[0].size, Some(965));
+        assert_eq!(tombstones[0].extended_file_metadata, Some(true));
+        assert_eq!(tombstones[0].partition_values, Some(Default::default()));
+
+        // only the first file was removed before the cutoff
+        let expired = scanner.expired_tombstones(&engine, 1674611461000).unwrap();
+        assert_eq!(expired, tombstones[..1]);
+        let expired = scanner.expired_tombstones(&engine, i64::MAX).unwrap();
+        assert_eq!(expired.len(), 2);
+    }
+
+    #[test]
+    fn test_tombstones_of_active_file() {
+        // the file was removed and re-added with a deletion vector, so it's still active
+        let engine = SyncEngine::new();
+        let scanner = get_tombstone_scanner("./tests/data/table-with-dv-small/");
+        assert_eq!(scanner.tombstones(&engine).unwrap(), []);
+    }
+}
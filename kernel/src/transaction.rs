@@ -1,7 +1,7 @@
 use std::sync::Arc;
 
 use crate::actions::visitors::TransactionVisitor;
-use crate::actions::{get_log_schema, TRANSACTION_NAME};
+use crate::actions::{get_log_schema_for, ActionType};
 use crate::snapshot::Snapshot;
 use crate::Engine;
 use crate::{actions::Transaction, DeltaResult};
@@ -22,7 +22,7 @@ impl TransactionScanner {
         engine: &dyn Engine,
         application_id: Option<&str>,
     ) -> DeltaResult<TransactionMap> {
-        let schema = get_log_schema().project(&[TRANSACTION_NAME])?;
+        let schema = get_log_schema_for(&[ActionType::Transaction])?;
 
         let mut visitor = TransactionVisitor::new(application_id.map(|s| s.to_owned()));
 
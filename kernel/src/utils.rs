@@ -1,5 +1,9 @@
 //! Various utility functions/macros used throughout the kernel
 
+use url::Url;
+
+use crate::{DeltaResult, FileMeta, FileSystemClient};
+
 /// convenient way to return an error if a condition isn't true
 macro_rules! require {
     ( $cond:expr, $err:expr ) => {
@@ -10,3 +14,28 @@ macro_rules! require {
 }
 
 pub(crate) use require;
+
+/// List the file at `location` on its own with `fs_client`, returning its [`FileMeta`] if it
+/// exists. This lists from just before the file, so that the file is listed whether or not the
+/// listings of the client include the path they start from, and stops at the first file listed
+/// after it, so only a few files of its directory are listed by clients that list lazily.
+pub(crate) fn list_file(
+    fs_client: &dyn FileSystemClient,
+    location: &Url,
+) -> DeltaResult<Option<FileMeta>> {
+    let mut start = location.clone();
+    let mut path = location.path().to_string();
+    path.pop();
+    start.set_path(&path);
+    for file in fs_client.list_from(&start)? {
+        let file = file?;
+        if file.location == *location {
+            return Ok(Some(file));
+        }
+        // listings are sorted, so the file doesn't exist once a later one is listed
+        if file.location.as_str() > location.as_str() {
+            return Ok(None);
+        }
+    }
+    Ok(None)
+}
@@ -5,6 +5,7 @@ use std::path::PathBuf;
 use delta_kernel::engine::sync::SyncEngine;
 use delta_kernel::scan::ScanBuilder;
 use delta_kernel::Table;
+use itertools::Itertools;
 
 use test_log::test;
 
@@ -24,7 +25,7 @@ fn dv_table() -> Result<(), Box<dyn std::error::Error>> {
         let data = res.raw_data?;
         let rows = data.length();
         for i in 0..rows {
-            if res.mask.as_ref().map_or(true, |mask| mask[i]) {
+            if res.mask.as_ref().is_none_or(|mask| mask[i]) {
                 total_rows += 1;
             }
         }
@@ -49,7 +50,7 @@ fn non_dv_table() -> Result<(), Box<dyn std::error::Error>> {
         let data = res.raw_data?;
         let rows = data.length();
         for i in 0..rows {
-            if res.mask.as_ref().map_or(true, |mask| mask[i]) {
+            if res.mask.as_ref().is_none_or(|mask| mask[i]) {
                 total_rows += 1;
             }
         }
@@ -57,3 +58,134 @@ fn non_dv_table() -> Result<(), Box<dyn std::error::Error>> {
     assert_eq!(total_rows, 10);
     Ok(())
 }
+
+#[test]
+fn dv_table_live_rows() -> Result<(), Box<dyn std::error::Error>> {
+    let path = std::fs::canonicalize(PathBuf::from("./tests/data/table-with-dv-small/"))?;
+    let url = url::Url::from_directory_path(path).unwrap();
+    let engine = SyncEngine::new();
+
+    let table = Table::new(url);
+    let snapshot = table.snapshot(&engine, None)?;
+    let scan = ScanBuilder::new(snapshot).build()?;
+
+    let live_rows = scan.live_rows(&engine)?;
+    assert_eq!(live_rows.len(), 1);
+    let (file, rows) = &live_rows[0];
+    assert!(file
+        .location
+        .path()
+        .ends_with("part-00000-fae5310a-a37d-4e51-827b-c3d5516560ca-c000.snappy.parquet"));
+    // the deletion vector removes the first and last rows
+    assert_eq!(rows.iter().collect::<Vec<_>>(), (1..9).collect::<Vec<_>>());
+    Ok(())
+}
+
+#[test]
+fn non_dv_table_live_rows() -> Result<(), Box<dyn std::error::Error>> {
+    let path = std::fs::canonicalize(PathBuf::from("./tests/data/table-without-dv-small/"))?;
+    let url = url::Url::from_directory_path(path).unwrap();
+    let engine = SyncEngine::new();
+
+    let table = Table::new(url);
+    let snapshot = table.snapshot(&engine, None)?;
+    let scan = ScanBuilder::new(snapshot).build()?;
+
+    let live_rows = scan.live_rows(&engine)?;
+    assert_eq!(live_rows.len(), 1);
+    assert_eq!(
+        live_rows[0].1.iter().collect::<Vec<_>>(),
+        (0..10).collect::<Vec<_>>()
+    );
+    Ok(())
+}
+
+#[test]
+fn live_rows_without_num_records() -> Result<(), Box<dyn std::error::Error>> {
+    // a copy of the non-dv table, whose add doesn't have any statistics
+    let source = PathBuf::from("./tests/data/table-without-dv-small/");
+    let tmp = tempfile::tempdir()?;
+    std::fs::create_dir(tmp.path().join("_delta_log"))?;
+    let parquet_file = "part-00000-517f5d32-9c95-48e8-82b4-0229cc194867-c000.snappy.parquet";
+    std::fs::copy(source.join(parquet_file), tmp.path().join(parquet_file))?;
+    let commit = std::fs::read_to_string(source.join("_delta_log/00000000000000000000.json"))?;
+    let commit = commit
+        .lines()
+        .map(|line| {
+            let mut action: serde_json::Value = serde_json::from_str(line).unwrap();
+            if let Some(add) = action.get_mut("add").and_then(|add| add.as_object_mut()) {
+                add.remove("stats");
+            }
+            action.to_string()
+        })
+        .join("\n");
+    std::fs::write(
+        tmp.path().join("_delta_log/00000000000000000000.json"),
+        commit,
+    )?;
+    let url = url::Url::from_directory_path(tmp.path()).unwrap();
+    let engine = SyncEngine::new();
+
+    let table = Table::new(url);
+    let snapshot = table.snapshot(&engine, None)?;
+    let scan = ScanBuilder::new(snapshot).build()?;
+
+    // the number of rows is read from the parquet footer instead
+    let live_rows = scan.live_rows(&engine)?;
+    assert_eq!(live_rows.len(), 1);
+    assert_eq!(
+        live_rows[0].1.iter().collect::<Vec<_>>(),
+        (0..10).collect::<Vec<_>>()
+    );
+    Ok(())
+}
+
+#[test]
+fn dv_table_with_dvs_disabled() -> Result<(), Box<dyn std::error::Error>> {
+    // a copy of the dv table, in which a later commit turns off deletion vectors
+    let source = PathBuf::from("./tests/data/table-with-dv-small/");
+    let tmp = tempfile::tempdir()?;
+    std::fs::create_dir(tmp.path().join("_delta_log"))?;
+    for file in [
+        "_delta_log/00000000000000000000.json",
+        "_delta_log/00000000000000000001.json",
+        "deletion_vector_61d16c75-6994-46b7-a15b-8b538852e50e.bin",
+        "part-00000-fae5310a-a37d-4e51-827b-c3d5516560ca-c000.snappy.parquet",
+    ] {
+        std::fs::copy(source.join(file), tmp.path().join(file))?;
+    }
+    let disable_dvs = [
+        r#"{"commitInfo":{"timestamp":1677811180000,"operation":"SET TBLPROPERTIES"}}"#,
+        r#"{"metaData":{"id":"testId","format":{"provider":"parquet","options":{}},"schemaString":"{\"type\":\"struct\",\"fields\":[{\"name\":\"value\",\"type\":\"integer\",\"nullable\":true,\"metadata\":{}}]}","partitionColumns":[],"configuration":{"delta.enableDeletionVectors":"false","delta.columnMapping.mode":"none"},"createdTime":1677811175819}}"#,
+    ];
+    std::fs::write(
+        tmp.path().join("_delta_log/00000000000000000002.json"),
+        disable_dvs.join("\n"),
+    )?;
+    let url = url::Url::from_directory_path(tmp.path()).unwrap();
+    let engine = SyncEngine::new();
+
+    let table = Table::new(url);
+    let snapshot = table.snapshot(&engine, None)?;
+    assert_eq!(snapshot.version(), 2);
+    assert_eq!(
+        snapshot.metadata().configuration["delta.enableDeletionVectors"],
+        "false"
+    );
+    let scan = ScanBuilder::new(snapshot).build()?;
+
+    // the existing deletion vector still applies, the property only affects new writes
+    let stream = scan.execute(&engine)?;
+    let mut total_rows = 0;
+    for res in stream {
+        let data = res.raw_data?;
+        let rows = data.length();
+        for i in 0..rows {
+            if res.mask.as_ref().is_none_or(|mask| mask[i]) {
+                total_rows += 1;
+            }
+        }
+    }
+    assert_eq!(total_rows, 8);
+    Ok(())
+}
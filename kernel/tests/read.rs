@@ -517,7 +517,7 @@ fn predicate_on_number() -> Result<(), Box<dyn std::error::Error>> {
     read_table_data(
         "./tests/data/basic_partitioned",
         Some(&["a_float", "number"]),
-        Some(Expression::Column("number".to_string()).lt(Expression::Literal(Scalar::Long(4)))),
+        Some(Expression::column("number").lt(Expression::Literal(Scalar::Long(4)))),
         expected,
     )?;
     Ok(())
@@ -537,8 +537,51 @@ fn predicate_on_number_with_not_null() -> Result<(), Box<dyn std::error::Error>>
         "./tests/data/basic_partitioned",
         Some(&["a_float", "number"]),
         Some(Expression::and(
-            Expression::Column("number".to_string()).is_null().not(),
-            Expression::Column("number".to_string()).lt(Expression::Literal(Scalar::Long(3))),
+            Expression::column("number").is_null().not(),
+            Expression::column("number").lt(Expression::Literal(Scalar::Long(3))),
+        )),
+        expected,
+    )?;
+    Ok(())
+}
+
+#[test]
+fn predicate_on_non_boolean_column() -> Result<(), Box<dyn std::error::Error>> {
+    // a bare column that isn't a boolean doesn't skip any file, and doesn't prevent the rest of
+    // the predicate from skipping files
+    let expected = vec![
+        "+---------+--------+",
+        "| a_float | number |",
+        "+---------+--------+",
+        "| 1.1     | 1      |",
+        "| 2.2     | 2      |",
+        "| 3.3     | 3      |",
+        "| 4.4     | 4      |",
+        "| 5.5     | 5      |",
+        "| 6.6     | 6      |",
+        "+---------+--------+",
+    ];
+    read_table_data(
+        "./tests/data/basic_partitioned",
+        Some(&["a_float", "number"]),
+        Some(Expression::column("number")),
+        expected,
+    )?;
+    let expected = vec![
+        "+---------+--------+",
+        "| a_float | number |",
+        "+---------+--------+",
+        "| 1.1     | 1      |",
+        "| 2.2     | 2      |",
+        "| 3.3     | 3      |",
+        "+---------+--------+",
+    ];
+    read_table_data(
+        "./tests/data/basic_partitioned",
+        Some(&["a_float", "number"]),
+        Some(Expression::and(
+            Expression::column("number"),
+            Expression::column("number").lt(Expression::Literal(Scalar::Long(4))),
         )),
         expected,
     )?;
@@ -551,7 +594,7 @@ fn predicate_null() -> Result<(), Box<dyn std::error::Error>> {
     read_table_data(
         "./tests/data/basic_partitioned",
         Some(&["a_float", "number"]),
-        Some(Expression::Column("number".to_string()).is_null()),
+        Some(Expression::column("number").is_null()),
         expected,
     )?;
     Ok(())